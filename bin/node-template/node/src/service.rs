@@ -12,6 +12,7 @@ pub use sc_executor::NativeExecutor;
 use sp_consensus_aura::sr25519::{AuthorityPair as AuraPair};
 use sc_finality_grandpa::{
 	FinalityProofProvider as GrandpaFinalityProofProvider, StorageAndProofProvider, SharedVoterState,
+	WarpSyncProofProvider as GrandpaWarpSyncProofProvider,
 };
 
 // Our native executor instance.
@@ -21,6 +22,9 @@ native_executor_instance!(
 	node_template_runtime::native_version,
 );
 
+/// Number of blocks after which GRANDPA generates a justification, at the latest.
+pub const GRANDPA_JUSTIFICATION_PERIOD: u32 = 512;
+
 /// Starts a `ServiceBuilder` for a full service.
 ///
 /// Use this macro if you don't actually need the full service, but just the builder in order to
@@ -39,6 +43,7 @@ macro_rules! new_full_start {
 			.with_select_chain(|_config, backend| {
 				Ok(sc_consensus::LongestChain::new(backend.clone()))
 			})?
+			.with_aux_revert(|client| Ok(sc_finality_grandpa::revert(client)?))?
 			.with_transaction_pool(|config, client, _fetcher, prometheus_registry| {
 				let pool_api = sc_transaction_pool::FullChainApi::new(client.clone());
 				Ok(sc_transaction_pool::BasicPool::new(config, std::sync::Arc::new(pool_api), prometheus_registry))
@@ -78,7 +83,9 @@ macro_rules! new_full_start {
 				import_setup = Some((grandpa_block_import, grandpa_link));
 
 				Ok(import_queue)
-			})?;
+			})?
+			// blocks are finalized at least once per justification period.
+			.with_expected_reorg_depth(crate::service::GRANDPA_JUSTIFICATION_PERIOD as u64)?;
 
 		(builder, import_setup, inherent_data_providers)
 	}}
@@ -103,6 +110,10 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 			let provider = client as Arc<dyn StorageAndProofProvider<_, _>>;
 			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, provider)) as _)
 		})?
+		.with_warp_sync_provider(|_client, backend| {
+			let authority_set = grandpa_link.shared_authority_set().clone();
+			Ok(Arc::new(GrandpaWarpSyncProofProvider::new(backend, authority_set)) as _)
+		})?
 		.build()?;
 
 	if role.is_authority() {
@@ -148,7 +159,7 @@ pub fn new_full(config: Configuration) -> Result<impl AbstractService, ServiceEr
 	let grandpa_config = sc_finality_grandpa::Config {
 		// FIXME #1578 make this available through chainspec
 		gossip_duration: Duration::from_millis(333),
-		justification_period: 512,
+		justification_period: GRANDPA_JUSTIFICATION_PERIOD,
 		name: Some(name),
 		observer_enabled: false,
 		keystore,
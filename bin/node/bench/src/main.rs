@@ -69,7 +69,7 @@ fn main() {
 	let opt = Opt::from_args();
 
 	if !opt.json {
-		sc_cli::init_logger("");
+		sc_cli::init_logger("", None).expect("Logging to the console only can't fail; qed");
 	}
 
 	let mut import_benchmarks = Vec::new();
@@ -37,6 +37,10 @@ pub enum Subcommand {
 	#[structopt(flatten)]
 	Base(sc_cli::Subcommand),
 
+	/// The key management subcommands, as provided by `subkey`.
+	#[structopt(name = "key", about = "Generate, inspect, sign with and insert keys.")]
+	Key(sc_cli::KeySubcommand),
+
 	/// The custom inspect subcommmand for decoding blocks and extrinsics.
 	#[structopt(
 		name = "inspect",
@@ -44,7 +48,15 @@ pub enum Subcommand {
 	)]
 	Inspect(node_inspect::cli::InspectCmd),
 
-	/// The custom benchmark subcommmand benchmarking runtime pallets.
-	#[structopt(name = "benchmark", about = "Benchmark runtime pallets.")]
+	/// The custom benchmark subcommmand benchmarking runtime pallets or the hardware.
+	#[structopt(name = "benchmark", about = "Benchmark runtime pallets or the hardware.")]
 	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
+
+	/// The custom try-runtime subcommmand testing runtime upgrades against a real state.
+	#[structopt(name = "try-runtime", about = "Test a runtime upgrade against the state of a block.")]
+	TryRuntime(try_runtime_cli::TryRuntimeCmd),
+
+	/// The custom check-runtime subcommmand checking the version of a runtime upgrade.
+	#[structopt(name = "check-runtime", about = "Check that a Wasm runtime can be upgraded to.")]
+	CheckRuntime(try_runtime_cli::CheckRuntimeCmd),
 }
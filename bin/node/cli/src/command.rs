@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{chain_spec, service, Cli, Subcommand};
+use frame_benchmarking_cli::HardwareBenchmarkCmd;
 use node_executor::Executor;
 use node_runtime::{Block, RuntimeApi};
 use sc_cli::{Result, SubstrateCli};
@@ -76,13 +77,24 @@ pub fn run() -> Result<()> {
 				node_runtime::VERSION
 			)
 		}
+		Some(Subcommand::Key(cmd)) => cmd.run(&cli),
 		Some(Subcommand::Inspect(cmd)) => {
 			let runner = cli.create_runner(cmd)?;
 
 			runner.sync_run(|config| cmd.run::<Block, RuntimeApi, Executor>(config))
 		}
-		Some(Subcommand::Benchmark(cmd)) => {
-			if cfg!(feature = "runtime-benchmarks") {
+		Some(Subcommand::Benchmark(cmd)) => match &cmd.hardware {
+			Some(HardwareBenchmarkCmd::Machine(cmd)) => {
+				let runner = cli.create_runner(cmd)?;
+
+				runner.sync_run(|config| cmd.run::<Executor>(config))
+			}
+			Some(HardwareBenchmarkCmd::Storage(cmd)) => {
+				let runner = cli.create_runner(cmd)?;
+
+				runner.sync_run(|config| cmd.run::<Block>(config))
+			}
+			None => if cfg!(feature = "runtime-benchmarks") {
 				let runner = cli.create_runner(cmd)?;
 
 				runner.sync_run(|config| cmd.run::<Block, Executor>(config))
@@ -92,6 +104,20 @@ pub fn run() -> Result<()> {
 				Ok(())
 			}
 		}
+		Some(Subcommand::TryRuntime(cmd)) => if cfg!(feature = "try-runtime") {
+			let runner = cli.create_runner(cmd)?;
+
+			runner.sync_run(|config| cmd.run::<Block, Executor>(config))
+		} else {
+			println!("Testing runtime upgrades wasn't enabled when building the node. \
+			You can enable it with `--features try-runtime`.");
+			Ok(())
+		}
+		Some(Subcommand::CheckRuntime(cmd)) => {
+			let runner = cli.create_runner(cmd)?;
+
+			runner.sync_run(|config| cmd.run::<Block, Executor>(config))
+		}
 		Some(Subcommand::Base(subcommand)) => {
 			let runner = cli.create_runner(subcommand)?;
 
@@ -35,6 +35,9 @@ use sc_service::{
 use sp_inherents::InherentDataProviders;
 use sc_consensus::LongestChain;
 
+/// Number of blocks after which GRANDPA generates a justification, at the latest.
+pub const GRANDPA_JUSTIFICATION_PERIOD: u32 = 512;
+
 /// Starts a `ServiceBuilder` for a full service.
 ///
 /// Use this macro if you don't actually need the full service, but just the builder in order to
@@ -53,6 +56,7 @@ macro_rules! new_full_start {
 			.with_select_chain(|_config, backend| {
 				Ok(sc_consensus::LongestChain::new(backend.clone()))
 			})?
+			.with_aux_revert(|client| Ok(grandpa::revert(client)?))?
 			.with_transaction_pool(|config, client, _fetcher, prometheus_registry| {
 				let pool_api = sc_transaction_pool::FullChainApi::new(client.clone());
 				Ok(sc_transaction_pool::BasicPool::new(
@@ -98,12 +102,20 @@ macro_rules! new_full_start {
 				import_setup = Some((block_import, grandpa_link, babe_link));
 				Ok(import_queue)
 			})?
+			// forks are resolved within an epoch, and finalized at least once per justification
+			// period.
+			.with_expected_reorg_depth(std::cmp::max(
+				import_setup.as_ref().map_or(0, |s| s.2.config().epoch_length),
+				crate::service::GRANDPA_JUSTIFICATION_PERIOD as u64,
+			))?
 			.with_rpc_extensions_builder(|builder| {
 				let grandpa_link = import_setup.as_ref().map(|s| &s.1)
 					.expect("GRANDPA LinkHalf is present for full services or set up failed; qed.");
 
 				let shared_authority_set = grandpa_link.shared_authority_set().clone();
 				let shared_voter_state = grandpa::SharedVoterState::empty();
+				let justification_stream = grandpa_link.justification_stream();
+				let subscriptions = sc_rpc::Subscriptions::new(Arc::new(builder.spawn_handle()));
 
 				rpc_setup = Some((shared_voter_state.clone()));
 
@@ -113,17 +125,25 @@ macro_rules! new_full_start {
 				let babe_config = babe_link.config().clone();
 				let shared_epoch_changes = babe_link.epoch_changes().clone();
 
+				let backend = builder.backend().clone();
 				let client = builder.client().clone();
 				let pool = builder.pool().clone();
+				let finality_provider = Arc::new(GrandpaFinalityProofProvider::new(
+					backend.clone(),
+					client.clone() as Arc<dyn StorageAndProofProvider<_, _>>,
+				));
 				let select_chain = builder.select_chain().cloned()
 					.expect("SelectChain is present for full services or set up failed; qed.");
 				let keystore = builder.keystore().clone();
+				let chain_spec = builder.chain_spec().cloned_box();
 
 				Ok(move |deny_unsafe| {
 					let deps = node_rpc::FullDeps {
+						backend: backend.clone(),
 						client: client.clone(),
 						pool: pool.clone(),
 						select_chain: select_chain.clone(),
+						chain_spec: chain_spec.cloned_box(),
 						deny_unsafe,
 						babe: node_rpc::BabeDeps {
 							babe_config: babe_config.clone(),
@@ -133,6 +153,9 @@ macro_rules! new_full_start {
 						grandpa: node_rpc::GrandpaDeps {
 							shared_voter_state: shared_voter_state.clone(),
 							shared_authority_set: shared_authority_set.clone(),
+							justification_stream: justification_stream.clone(),
+							subscriptions: subscriptions.clone(),
+							finality_provider: finality_provider.clone(),
 						},
 					};
 
@@ -151,7 +174,6 @@ macro_rules! new_full_start {
 macro_rules! new_full {
 	($config:expr, $with_startup_data: expr) => {{
 		use futures::prelude::*;
-		use sc_network::Event;
 		use sc_client_api::ExecutorProvider;
 
 		let (
@@ -218,35 +240,42 @@ macro_rules! new_full {
 
 		// Spawn authority discovery module.
 		if matches!(role, sc_service::config::Role::Authority{..} | sc_service::config::Role::Sentry {..}) {
-			let (sentries, authority_discovery_role) = match role {
-				sc_service::config::Role::Authority { ref sentry_nodes } => (
+			let (sentries, keystore) = match role {
+				sc_service::config::Role::Authority { ref sentry_nodes, .. } => (
 					sentry_nodes.clone(),
-					sc_authority_discovery::Role::Authority (
-						service.keystore(),
-					),
+					Some(service.keystore()),
 				),
 				sc_service::config::Role::Sentry {..} => (
 					vec![],
-					sc_authority_discovery::Role::Sentry,
+					None,
 				),
 				_ => unreachable!("Due to outer matches! constraint; qed.")
 			};
 
+			let client = service.client();
 			let network = service.network();
-			let dht_event_stream = network.event_stream("authority-discovery").filter_map(|e| async move { match e {
-				Event::Dht(e) => Some(e),
-				_ => None,
-			}}).boxed();
-			let authority_discovery = sc_authority_discovery::AuthorityDiscovery::new(
-				service.client(),
-				network,
-				sentries,
-				dht_event_stream,
-				authority_discovery_role,
-				service.prometheus_registry(),
+			let prometheus_registry = service.prometheus_registry();
+			// the module is built again if it panics, so that a transient failure doesn't take
+			// out the authority.
+			service.spawn_restartable_essential_task(
+				"authority-discovery",
+				sc_service::RestartPolicy::default(),
+				move || {
+					let authority_discovery_role = match keystore {
+						Some(ref keystore) => sc_authority_discovery::Role::Authority(keystore.clone()),
+						None => sc_authority_discovery::Role::Sentry,
+					};
+					let dht_event_stream = network.dht_event_stream("authority-discovery").boxed();
+					sc_authority_discovery::AuthorityDiscovery::new(
+						client.clone(),
+						network.clone(),
+						sentries.clone(),
+						dht_event_stream,
+						authority_discovery_role,
+						prometheus_registry.clone(),
+					)
+				},
 			);
-
-			service.spawn_task("authority-discovery", authority_discovery);
 		}
 
 		// if the node isn't actively participating in consensus then it doesn't
@@ -260,7 +289,7 @@ macro_rules! new_full {
 		let config = grandpa::Config {
 			// FIXME #1578 make this available through chainspec
 			gossip_duration: std::time::Duration::from_millis(333),
-			justification_period: 512,
+			justification_period: GRANDPA_JUSTIFICATION_PERIOD,
 			name: Some(name),
 			observer_enabled: false,
 			keystore,
@@ -41,23 +41,26 @@ pub struct InspectCmd {
 /// A possible inspect sub-commands.
 #[derive(Debug, StructOpt, Clone)]
 pub enum InspectSubCmd {
-	/// Decode block with native version of runtime and print out the details.
+	/// Decode block with native version of runtime and print out the details, along with the
+	/// calls decoded with the metadata of the runtime.
 	Block {
 		/// Address of the block to print out.
 		///
 		/// Can be either a block hash (no 0x prefix) or a number to retrieve existing block,
 		/// or a 0x-prefixed bytes hex string, representing SCALE encoding of
-		/// a block.
+		/// a block, or `-` to read any of them, or the raw SCALE encoding, from the standard input.
 		#[structopt(value_name = "HASH or NUMBER or BYTES")]
 		input: String,
 	},
-	/// Decode extrinsic with native version of runtime and print out the details.
+	/// Decode extrinsic with native version of runtime and print out the details, along with its
+	/// call decoded with the metadata of the runtime.
 	Extrinsic {
 		/// Address of an extrinsic to print out.
 		///
 		/// Can be either a block hash (no 0x prefix) or number and the index, in the form
 		/// of `{block}:{index}` or a 0x-prefixed bytes hex string,
-		/// representing SCALE encoding of an extrinsic.
+		/// representing SCALE encoding of an extrinsic, or `-` to read any of them, or the raw
+		/// SCALE encoding, from the standard input.
 		#[structopt(value_name = "BLOCK:INDEX or BYTES")]
 		input: String,
 	},
@@ -19,11 +19,17 @@
 //! Command ran by the CLI
 
 use crate::cli::{InspectCmd, InspectSubCmd};
-use crate::Inspector;
+use crate::metadata::{CallDecoder, ExtrinsicCall};
+use crate::{BlockAddress, BlockAddressFor, ExtrinsicAddress, Inspector, MetadataPrinter};
+use codec::Decode;
+use log::warn;
 use sc_cli::{CliConfiguration, ImportParams, Result, SharedParams};
+use sc_client_api::{CallExecutor, ExecutionStrategy, ExecutorProvider};
 use sc_service::{new_full_client, Configuration, NativeExecutionDispatch};
-use sp_runtime::traits::Block;
-use std::str::FromStr;
+use sp_blockchain::HeaderBackend;
+use sp_core::hexdisplay::HexDisplay;
+use sp_runtime::{generic::BlockId, traits::{Block, Hash, HashFor, NumberFor}};
+use std::{io::Read, str::FromStr};
 
 impl InspectCmd {
 	/// Run the inspect command, passing the inspector.
@@ -31,21 +37,30 @@ impl InspectCmd {
 	where
 		B: Block,
 		B::Hash: FromStr,
+		B::Extrinsic: ExtrinsicCall,
 		RA: Send + Sync + 'static,
 		EX: NativeExecutionDispatch + 'static,
 	{
 		let client = new_full_client::<B, RA, EX>(&config)?;
-		let inspect = Inspector::<B>::new(client);
 
 		match &self.command {
 			InspectSubCmd::Block { input } => {
-				let input = input.parse()?;
+				let input: BlockAddressFor<B> = read_input(input)?.parse()?;
+				let printer = metadata_printer(&client, block_id(&input));
+				let inspect = Inspector::<B, _>::with_printer(client, printer);
 				let res = inspect.block(input).map_err(|e| format!("{}", e))?;
 				println!("{}", res);
 				Ok(())
 			}
 			InspectSubCmd::Extrinsic { input } => {
-				let input = input.parse()?;
+				let input: ExtrinsicAddress<<HashFor<B> as Hash>::Output, NumberFor<B>> =
+					read_input(input)?.parse()?;
+				let at = match &input {
+					ExtrinsicAddress::Block(block, _) => block_id(block),
+					ExtrinsicAddress::Bytes(_) => None,
+				};
+				let printer = metadata_printer(&client, at);
+				let inspect = Inspector::<B, _>::with_printer(client, printer);
 				let res = inspect.extrinsic(input).map_err(|e| format!("{}", e))?;
 				println!("{}", res);
 				Ok(())
@@ -54,6 +69,55 @@ impl InspectCmd {
 	}
 }
 
+/// Returns the input, read from the standard input if it is `-`.
+///
+/// The standard input can either be in any of the formats of the arguments, or be the raw SCALE
+/// encoding of a block or an extrinsic.
+fn read_input(input: &str) -> Result<String> {
+	if input != "-" {
+		return Ok(input.into())
+	}
+
+	let mut bytes = Vec::new();
+	std::io::stdin().read_to_end(&mut bytes)?;
+	Ok(match String::from_utf8(bytes) {
+		Ok(input) => input.trim().into(),
+		Err(e) => format!("0x{}", HexDisplay::from(&e.as_bytes())),
+	})
+}
+
+/// Returns the block whose runtime decodes the calls, `None` for the best block.
+fn block_id<B: Block>(address: &BlockAddressFor<B>) -> Option<BlockId<B>> {
+	match address {
+		BlockAddress::Hash(hash) => Some(BlockId::Hash(*hash)),
+		BlockAddress::Number(number) => Some(BlockId::Number(*number)),
+		BlockAddress::Bytes(_) => None,
+	}
+}
+
+/// Returns a printer decoding the calls with the metadata of the runtime of the block `at`.
+///
+/// The calls aren't decoded if the metadata can't be loaded.
+fn metadata_printer<B, C>(client: &C, at: Option<BlockId<B>>) -> MetadataPrinter where
+	B: Block,
+	C: ExecutorProvider<B> + HeaderBackend<B>,
+{
+	let at = at.unwrap_or_else(|| BlockId::Hash(client.info().best_hash));
+	let decoder = client.executor()
+		.call(&at, "Metadata_metadata", &[], ExecutionStrategy::NativeElseWasm, None)
+		.map_err(|e| e.to_string())
+		.and_then(|metadata| Vec::<u8>::decode(&mut &metadata[..]).map_err(|e| e.to_string()))
+		.and_then(|metadata| CallDecoder::new(&metadata));
+
+	match decoder {
+		Ok(decoder) => MetadataPrinter::new(Some(decoder)),
+		Err(e) => {
+			warn!("Failed to load the metadata of the runtime, the calls won't be decoded: {}", e);
+			MetadataPrinter::new(None)
+		},
+	}
+}
+
 impl CliConfiguration for InspectCmd {
 	fn shared_params(&self) -> &SharedParams {
 		&self.shared_params
@@ -20,12 +20,14 @@
 //! about blocks and extrinsics.
 //!
 //! The blocks and extrinsics can either be retrieved from the database (on-chain),
-//! or a raw SCALE-encoding can be provided.
+//! or a raw SCALE-encoding can be provided. The calls of the extrinsics can be decoded
+//! with the metadata of the runtime.
 
 #![warn(missing_docs)]
 
 pub mod cli;
 pub mod command;
+pub mod metadata;
 
 use std::{
 	fmt,
@@ -79,6 +81,50 @@ impl<TBlock: Block> PrettyPrinter<TBlock> for DebugPrinter {
 	}
 }
 
+/// Printer decoding the calls of the extrinsics with the metadata of the runtime, in addition to
+/// the output of [`DebugPrinter`].
+///
+/// Without decoder, the output is the one of [`DebugPrinter`].
+pub struct MetadataPrinter {
+	decoder: Option<metadata::CallDecoder>,
+}
+
+impl MetadataPrinter {
+	/// Create a printer using the given call decoder.
+	pub fn new(decoder: Option<metadata::CallDecoder>) -> Self {
+		MetadataPrinter { decoder }
+	}
+}
+
+impl<TBlock: Block> PrettyPrinter<TBlock> for MetadataPrinter where
+	TBlock::Extrinsic: metadata::ExtrinsicCall,
+{
+	fn fmt_block(&self, fmt: &mut fmt::Formatter, block: &TBlock) -> fmt::Result {
+		writeln!(fmt, "Header:")?;
+		writeln!(fmt, "{:?}", block.header())?;
+		writeln!(fmt, "Block bytes: {:?}", HexDisplay::from(&block.encode()))?;
+		writeln!(fmt, "Extrinsics ({})", block.extrinsics().len())?;
+		for (idx, ex) in block.extrinsics().iter().enumerate() {
+			writeln!(fmt, "- {}:", idx)?;
+			<MetadataPrinter as PrettyPrinter<TBlock>>::fmt_extrinsic(self, fmt, ex)?;
+		}
+		Ok(())
+	}
+
+	fn fmt_extrinsic(&self, fmt: &mut fmt::Formatter, extrinsic: &TBlock::Extrinsic) -> fmt::Result {
+		use metadata::ExtrinsicCall;
+
+		<DebugPrinter as PrettyPrinter<TBlock>>::fmt_extrinsic(&DebugPrinter, fmt, extrinsic)?;
+		if let (Some(decoder), Some(call)) = (&self.decoder, extrinsic.encoded_call()) {
+			match decoder.decode(&call) {
+				Ok(call) => writeln!(fmt, " Call: {}", call)?,
+				Err(e) => writeln!(fmt, " Call could not be decoded with the metadata: {}", e)?,
+			}
+		}
+		Ok(())
+	}
+}
+
 /// Aggregated error for `Inspector` operations.
 #[derive(Debug, derive_more::From, derive_more::Display)]
 pub enum Error {
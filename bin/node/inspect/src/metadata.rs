@@ -0,0 +1,217 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Decoding of the calls of extrinsics with the metadata of the runtime.
+
+use std::fmt::Write;
+use codec::{Compact, Decode, Encode, Input};
+use frame_metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed, META_RESERVED};
+use sp_core::hexdisplay::HexDisplay;
+
+/// Access to the call of an extrinsic.
+pub trait ExtrinsicCall {
+	/// Returns the SCALE encoding of the call, if the extrinsic has one.
+	fn encoded_call(&self) -> Option<Vec<u8>>;
+}
+
+impl<Address, Call: Encode, Signature, Extra> ExtrinsicCall
+	for sp_runtime::generic::UncheckedExtrinsic<Address, Call, Signature, Extra> where
+	Extra: sp_runtime::traits::SignedExtension,
+{
+	fn encoded_call(&self) -> Option<Vec<u8>> {
+		Some(self.function.encode())
+	}
+}
+
+impl ExtrinsicCall for sp_runtime::OpaqueExtrinsic {
+	fn encoded_call(&self) -> Option<Vec<u8>> {
+		None
+	}
+}
+
+/// A function of the runtime, with the names and types of its arguments.
+struct Function {
+	name: String,
+	arguments: Vec<(String, String)>,
+}
+
+/// A module of the runtime, with its functions.
+struct Module {
+	name: String,
+	functions: Vec<Function>,
+}
+
+fn decoded<B, O>(value: DecodeDifferent<B, O>) -> Option<O> {
+	match value {
+		DecodeDifferent::Decoded(value) => Some(value),
+		DecodeDifferent::Encode(_) => None,
+	}
+}
+
+/// Decodes the calls of extrinsics with the metadata of a runtime.
+pub struct CallDecoder {
+	/// The modules with calls, indexed as in the calls.
+	modules: Vec<Module>,
+}
+
+impl CallDecoder {
+	/// Creates a decoder from the SCALE-encoded metadata of a runtime.
+	pub fn new(metadata: &[u8]) -> Result<Self, String> {
+		let metadata = RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+			.map_err(|e| format!("Invalid runtime metadata: {}", e))?;
+		if metadata.0 != META_RESERVED {
+			return Err("Invalid runtime metadata prefix".into())
+		}
+		let modules = match metadata.1 {
+			RuntimeMetadata::V11(metadata) => decoded(metadata.modules).unwrap_or_default(),
+			_ => return Err("Unsupported runtime metadata version".into()),
+		};
+
+		let modules = modules.into_iter()
+			.filter_map(|module| {
+				let functions = decoded(module.calls?)?;
+				Some(Module {
+					name: decoded(module.name)?,
+					functions: functions.into_iter()
+						.filter_map(|function| Some(Function {
+							name: decoded(function.name)?,
+							arguments: decoded(function.arguments)?.into_iter()
+								.filter_map(|argument| Some((decoded(argument.name)?, decoded(argument.ty)?)))
+								.collect(),
+						}))
+						.collect(),
+				})
+			})
+			.collect();
+		Ok(CallDecoder { modules })
+	}
+
+	/// Returns a human-readable representation of the SCALE-encoded `call`.
+	///
+	/// The arguments whose type is unknown, and the ones following them, are printed as raw bytes.
+	pub fn decode(&self, call: &[u8]) -> Result<String, String> {
+		let mut input = call;
+		let (module, function) = <(u8, u8)>::decode(&mut input).map_err(|e| format!("Invalid call: {}", e))?;
+		let module = self.modules.get(module as usize)
+			.ok_or_else(|| format!("Unknown module index {}", module))?;
+		let function = module.functions.get(function as usize)
+			.ok_or_else(|| format!("Unknown call index {} of module {}", function, module.name))?;
+
+		let mut output = format!("{}.{}(", module.name, function.name);
+		let mut arguments = function.arguments.iter().peekable();
+		while let Some((name, ty)) = arguments.next() {
+			match decode_argument(ty, &mut input) {
+				Some(value) => write!(output, "{}: {}", name, value),
+				None => {
+					let names = std::iter::once(name).chain(arguments.by_ref().map(|(name, _)| name))
+						.map(|name| name.as_str())
+						.collect::<Vec<_>>();
+					write!(output, "{}: 0x{}", names.join(" + "), HexDisplay::from(&input))
+				},
+			}.expect("Writing to a string is infallible; qed");
+			if arguments.peek().is_some() {
+				output.push_str(", ");
+			}
+		}
+		output.push(')');
+		Ok(output)
+	}
+}
+
+/// Decodes a value of the type named `ty`, `None` if the type is unknown or the value invalid.
+fn decode_argument(ty: &str, input: &mut &[u8]) -> Option<String> {
+	fn decode<T: Decode + ToString>(input: &mut &[u8]) -> Option<String> {
+		T::decode(input).ok().map(|value| value.to_string())
+	}
+
+	let ty = ty.trim();
+	if ty.starts_with("Compact<") {
+		return Compact::<u128>::decode(input).ok().map(|value| value.0.to_string())
+	}
+	match ty {
+		"bool" => decode::<bool>(input),
+		"u8" => decode::<u8>(input),
+		"u16" => decode::<u16>(input),
+		"u32" => decode::<u32>(input),
+		"u64" => decode::<u64>(input),
+		"u128" => decode::<u128>(input),
+		"Vec<u8>" | "Bytes" => Vec::<u8>::decode(input).ok().map(|bytes| format!("0x{}", HexDisplay::from(&bytes))),
+		"T::AccountId" | "AccountId" | "T::Hash" | "Hash" | "H256" => {
+			let mut bytes = [0u8; 32];
+			input.read(&mut bytes).ok().map(|_| format!("0x{}", HexDisplay::from(&bytes)))
+		},
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use frame_metadata::{
+		ExtrinsicMetadata, FunctionArgumentMetadata, FunctionMetadata, ModuleMetadata, RuntimeMetadataV11,
+	};
+
+	fn module(name: &str, calls: Option<Vec<FunctionMetadata>>) -> ModuleMetadata {
+		ModuleMetadata {
+			name: DecodeDifferent::Decoded(name.into()),
+			storage: None,
+			calls: calls.map(DecodeDifferent::Decoded),
+			event: None,
+			constants: DecodeDifferent::Decoded(vec![]),
+			errors: DecodeDifferent::Decoded(vec![]),
+		}
+	}
+
+	fn argument(name: &str, ty: &str) -> FunctionArgumentMetadata {
+		FunctionArgumentMetadata {
+			name: DecodeDifferent::Decoded(name.into()),
+			ty: DecodeDifferent::Decoded(ty.into()),
+		}
+	}
+
+	#[test]
+	fn calls_are_decoded_with_the_metadata() {
+		let transfer = FunctionMetadata {
+			name: DecodeDifferent::Decoded("transfer".into()),
+			arguments: DecodeDifferent::Decoded(vec![
+				argument("dest", "T::AccountId"),
+				argument("value", "Compact<T::Balance>"),
+				argument("memo", "Memo"),
+				argument("tip", "u8"),
+			]),
+			documentation: DecodeDifferent::Decoded(vec![]),
+		};
+		let metadata: RuntimeMetadataPrefixed = RuntimeMetadataV11 {
+			// the module without calls doesn't count in the indexes of the calls.
+			modules: DecodeDifferent::Decoded(vec![module("System", None), module("Balances", Some(vec![transfer]))]),
+			extrinsic: ExtrinsicMetadata { version: 4, signed_extensions: vec![] },
+		}.into();
+		let decoder = CallDecoder::new(&metadata.encode()).unwrap();
+
+		let mut call = vec![0u8, 0];
+		call.extend_from_slice(&[1; 32]);
+		Compact(1_000u128).encode_to(&mut call);
+		call.extend_from_slice(&[2, 3]);
+
+		assert_eq!(
+			decoder.decode(&call).unwrap(),
+			format!("Balances.transfer(dest: 0x{}, value: 1000, memo + tip: 0x0203)", "01".repeat(32)),
+		);
+		assert!(decoder.decode(&[1, 0]).is_err());
+	}
+}
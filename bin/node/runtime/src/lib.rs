@@ -29,7 +29,10 @@ use frame_support::{
 		Weight, IdentityFee,
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 	},
-	traits::{Currency, Imbalance, KeyOwnerProofSystem, OnUnbalanced, Randomness, LockIdentifier},
+	traits::{
+		Currency, GetCallMetadata, Imbalance, KeyOwnerProofSystem, OnUnbalanced, Randomness,
+		LockIdentifier,
+	},
 };
 use sp_core::{
 	crypto::KeyTypeId,
@@ -93,8 +96,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// and set impl_version to 0. If only runtime
 	// implementation changes and behavior does not, then leave spec_version as
 	// is and increment impl_version.
-	spec_version: 251,
-	impl_version: 1,
+	spec_version: 252,
+	impl_version: 0,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
 };
@@ -917,6 +920,22 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl frame_system_rpc_runtime_api::ExtrinsicDecodeApi<Block, UncheckedExtrinsic> for Runtime {
+		fn decode_extrinsic(
+			extrinsic: UncheckedExtrinsic,
+		) -> frame_system_rpc_runtime_api::ExtrinsicParts {
+			let metadata = extrinsic.function.get_call_metadata();
+			// calls are encoded as the index of their pallet, their index and their arguments.
+			let args = extrinsic.function.encode().split_off(2);
+			frame_system_rpc_runtime_api::ExtrinsicParts {
+				pallet: metadata.pallet_name.as_bytes().to_vec(),
+				call: metadata.function_name.as_bytes().to_vec(),
+				args,
+				signer: extrinsic.signature.map(|(address, _, _)| address.encode()),
+			}
+		}
+	}
+
 	impl pallet_contracts_rpc_runtime_api::ContractsApi<Block, AccountId, Balance, BlockNumber>
 		for Runtime
 	{
@@ -974,6 +993,13 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[cfg(feature = "try-runtime")]
+	impl frame_try_runtime::TryRuntime<Block> for Runtime {
+		fn on_runtime_upgrade() -> (Weight, Weight) {
+			(Executive::try_runtime_upgrade(), MaximumBlockWeight::get())
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn dispatch_benchmark(
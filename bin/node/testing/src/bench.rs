@@ -269,7 +269,14 @@ impl BenchDb {
 		let db_config = sc_client_db::DatabaseSettings {
 			state_cache_size: 16*1024*1024,
 			state_cache_child_ratio: Some((0, 100)),
+			trie_cache_size: 16*1024*1024,
 			pruning: PruningMode::ArchiveAll,
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: sc_client_db::DEFAULT_CANONICALIZATION_DELAY,
 			source: database_type.into_settings(dir.into()),
 		};
 
@@ -43,6 +43,12 @@ use parking_lot::RwLock;
 pub use sp_state_machine::Backend as StateBackend;
 use std::marker::PhantomData;
 
+/// Access to the data written by the runtime through offchain indexing.
+pub trait IndexedData: Send + Sync {
+	/// Returns the data indexed under `key`, if any.
+	fn indexed_data(&self, key: &[u8]) -> Option<Vec<u8>>;
+}
+
 /// Extracts the state backend type for the given backend.
 pub type StateBackendFor<B, Block> = <B as Backend<Block>>::State;
 
@@ -79,6 +85,9 @@ pub struct ClientImportOperation<Block: BlockT, B: Backend<Block>> {
 	pub notify_imported: Option<ImportSummary<Block>>,
 	/// A list of hashes of blocks that got finalized.
 	pub notify_finalized: Vec<Block::Hash>,
+	/// Hashes of the imported blocks enacting a runtime, whose metadata is stored once they are
+	/// committed.
+	pub runtime_upgrades: Vec<Block::Hash>,
 }
 
 /// Helper function to apply auxiliary data insertion into an operation.
@@ -162,6 +171,14 @@ pub trait BlockImportOperation<Block: BlockT> {
 	/// Inject storage data into the database replacing any existing data.
 	fn reset_storage(&mut self, storage: Storage) -> sp_blockchain::Result<Block::Hash>;
 
+	/// Inject the complete state of a block downloaded from the network, replacing any existing
+	/// data. Unlike `reset_storage`, the ancestors of the block don't need to be known.
+	///
+	/// The block data must be set with `NewBlockState::Final`.
+	fn set_imported_state(&mut self, storage: Storage) -> sp_blockchain::Result<Block::Hash> {
+		self.reset_storage(storage)
+	}
+
 	/// Set storage changes.
 	fn update_storage(
 		&mut self,
@@ -269,38 +286,83 @@ pub trait AuxStore {
 }
 
 /// An `Iterator` that iterates keys in a given block under a prefix.
-pub struct KeyIterator<'a, State, Block> {
+///
+/// The iterator owns the state it reads, so it can be moved to other threads and tasks, and it
+/// can be resumed later by starting a new iterator after the last key returned.
+pub struct KeyIterator<State, Block> {
 	state: State,
-	prefix: Option<&'a StorageKey>,
+	child_storage: Option<ChildInfo>,
+	prefix: Option<StorageKey>,
 	current_key: Vec<u8>,
+	// whether `current_key` itself should be returned, if it exists.
+	include_current: bool,
 	_phantom: PhantomData<Block>,
 }
 
-impl <'a, State, Block> KeyIterator<'a, State, Block> {
-	/// create a KeyIterator instance
-	pub fn new(state: State, prefix: Option<&'a StorageKey>, current_key: Vec<u8>) -> Self {
+impl <State, Block> KeyIterator<State, Block> {
+	/// Create a `KeyIterator` instance, iterating the keys after `start_key`, or all the keys
+	/// starting with `prefix` if there is no start key.
+	pub fn new(state: State, prefix: Option<StorageKey>, start_key: Option<StorageKey>) -> Self {
+		Self::with_child_storage(state, None, prefix, start_key)
+	}
+
+	/// Create a `KeyIterator` instance for a child storage.
+	pub fn new_child(
+		state: State,
+		child_info: ChildInfo,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+	) -> Self {
+		Self::with_child_storage(state, Some(child_info), prefix, start_key)
+	}
+
+	fn with_child_storage(
+		state: State,
+		child_storage: Option<ChildInfo>,
+		prefix: Option<StorageKey>,
+		start_key: Option<StorageKey>,
+	) -> Self {
+		let (current_key, include_current) = match (start_key, &prefix) {
+			(Some(start_key), _) => (start_key.0, false),
+			(None, Some(prefix)) => (prefix.0.clone(), true),
+			(None, None) => (Vec::new(), true),
+		};
 		Self {
 			state,
+			child_storage,
 			prefix,
 			current_key,
+			include_current,
 			_phantom: PhantomData,
 		}
 	}
 }
 
-impl<'a, State, Block> Iterator for KeyIterator<'a, State, Block> where
+impl<State, Block> Iterator for KeyIterator<State, Block> where
 	Block: BlockT,
 	State: StateBackend<HashFor<Block>>,
 {
 	type Item = StorageKey;
 
 	fn next(&mut self) -> Option<Self::Item> {
-		let next_key = self.state
-			.next_storage_key(&self.current_key)
+		if self.include_current {
+			self.include_current = false;
+			let exists = match &self.child_storage {
+				Some(child_info) => self.state.exists_child_storage(child_info, &self.current_key),
+				None => self.state.exists_storage(&self.current_key),
+			};
+			if exists.unwrap_or(false) {
+				return Some(StorageKey(self.current_key.clone()));
+			}
+		}
+		let next_key = match &self.child_storage {
+			Some(child_info) => self.state.next_child_storage_key(child_info, &self.current_key),
+			None => self.state.next_storage_key(&self.current_key),
+		}
 			.ok()
 			.flatten()?;
 		// this terminates the iterator the first time it fails.
-		if let Some(prefix) = self.prefix {
+		if let Some(prefix) = &self.prefix {
 			if !next_key.starts_with(&prefix.0[..]) {
 				return None;
 			}
@@ -310,6 +372,46 @@ impl<'a, State, Block> Iterator for KeyIterator<'a, State, Block> where
 	}
 }
 
+/// Number of keys and total size of the values of the storage entries sharing a key prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoragePrefixStats {
+	/// The prefix of the keys.
+	pub prefix: StorageKey,
+	/// Number of keys.
+	pub keys: u64,
+	/// Total size of the values, in bytes.
+	pub value_bytes: u64,
+}
+
+/// Returns the statistics of the storage entries of `state`, grouped by the first `prefix_len`
+/// bytes of their keys and sorted by decreasing size of the values.
+///
+/// The keys shorter than `prefix_len`, such as the well known keys, are grouped by themselves.
+/// The entries of the child tries aren't counted, only their roots are.
+pub fn storage_stats<Block, S>(state: &S, prefix_len: usize) -> Vec<StoragePrefixStats> where
+	Block: BlockT,
+	S: StateBackend<HashFor<Block>>,
+{
+	let mut stats: Vec<StoragePrefixStats> = Vec::new();
+	state.for_key_values_with_prefix(&[], |key, value| {
+		let prefix = &key[..key.len().min(prefix_len)];
+		// the keys are iterated in order, so the keys sharing a prefix are contiguous.
+		match stats.last_mut() {
+			Some(last) if last.prefix.0 == prefix => {
+				last.keys += 1;
+				last.value_bytes += value.len() as u64;
+			},
+			_ => stats.push(StoragePrefixStats {
+				prefix: StorageKey(prefix.to_vec()),
+				keys: 1,
+				value_bytes: value.len() as u64,
+			}),
+		}
+	});
+	stats.sort_by(|a, b| b.value_bytes.cmp(&a.value_bytes));
+	stats
+}
+
 /// Provides acess to storage primitives
 pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 	/// Given a `BlockId` and a key, return the value under the key in that block.
@@ -328,13 +430,24 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		key_prefix: &StorageKey
 	) -> sp_blockchain::Result<Vec<(StorageKey, StorageData)>>;
 
+	/// Given a `BlockId`, return the number of keys and the size of the values of the storage
+	/// entries grouped by the first `prefix_len` bytes of their keys, largest first.
+	fn storage_stats(
+		&self,
+		id: &BlockId<Block>,
+		prefix_len: usize,
+	) -> sp_blockchain::Result<Vec<StoragePrefixStats>>;
+
 	/// Given a `BlockId` and a key prefix, return a `KeyIterator` iterates matching storage keys in that block.
-	fn storage_keys_iter<'a>(
+	///
+	/// The keys are iterated after `start_key` if given, so a big map can be iterated page by
+	/// page, each page starting after the last key of the previous one.
+	fn storage_keys_iter(
 		&self,
 		id: &BlockId<Block>,
-		prefix: Option<&'a StorageKey>,
+		prefix: Option<&StorageKey>,
 		start_key: Option<&StorageKey>
-	) -> sp_blockchain::Result<KeyIterator<'a, B::State, Block>>;
+	) -> sp_blockchain::Result<KeyIterator<B::State, Block>>;
 
 	/// Given a `BlockId`, a key and a child storage key, return the value under the key in that block.
 	fn child_storage(
@@ -352,6 +465,16 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		key_prefix: &StorageKey
 	) -> sp_blockchain::Result<Vec<StorageKey>>;
 
+	/// Given a `BlockId`, a child storage key and a key prefix, return a `KeyIterator` that
+	/// iterates the matching child storage keys in that block.
+	fn child_storage_keys_iter(
+		&self,
+		id: &BlockId<Block>,
+		child_info: ChildInfo,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>
+	) -> sp_blockchain::Result<KeyIterator<B::State, Block>>;
+
 	/// Given a `BlockId`, a key and a child storage key, return the hash under the key in that block.
 	fn child_storage_hash(
 		&self,
@@ -381,6 +504,18 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		storage_key: Option<&PrefixedStorageKey>,
 		key: &StorageKey
 	) -> sp_blockchain::Result<Vec<(NumberFor<Block>, u32)>>;
+
+	/// Get the numbers and hashes of the blocks within [first; last] that changed the key of the
+	/// top storage, including the blocks of every fork, using the index of the storage changes.
+	///
+	/// Changes are returned in ascending order. Returns Ok(None) if the storage changes of some
+	/// blocks of the range are not indexed.
+	fn indexed_key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<Vec<(NumberFor<Block>, Block::Hash)>>>;
 }
 
 /// Client backend.
@@ -439,6 +574,12 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns a handle to offchain storage.
 	fn offchain_storage(&self) -> Option<Self::OffchainStorage>;
 
+	/// Returns a handle to the values written by the runtime through the offchain indexing API,
+	/// if the backend stores them.
+	fn offchain_index(&self) -> Option<Arc<dyn IndexedData>> {
+		None
+	}
+
 	/// Returns true if state for given block is available.
 	fn have_state_at(&self, hash: &Block::Hash, _number: NumberFor<Block>) -> bool {
 		self.state_at(BlockId::Hash(hash.clone())).is_ok()
@@ -458,6 +599,13 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 		revert_finalized: bool,
 	) -> sp_blockchain::Result<NumberFor<Block>>;
 
+	/// Removes the blocks of the forks that are not descendants of the last finalized block, and
+	/// whose leaves are below the block `below`, along with the leaves from the leaf set.
+	///
+	/// The states of the removed blocks are left to the pruning of the states. Returns the hashes
+	/// of the removed blocks.
+	fn prune_abandoned_forks(&self, below: NumberFor<Block>) -> sp_blockchain::Result<Vec<Block::Hash>>;
+
 	/// Insert auxiliary data into key-value store.
 	fn insert_aux<
 		'a,
@@ -530,3 +678,28 @@ pub fn changes_tries_state_at_block<'a, Block: BlockT>(
 		None => Ok(None),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_state_machine::InMemoryBackend;
+	use sp_test_primitives::Block;
+
+	#[test]
+	fn storage_stats_are_grouped_by_prefix() {
+		let state: InMemoryBackend<HashFor<Block>> = vec![
+			(b"aa1".to_vec(), vec![0; 10]),
+			(b"aa2".to_vec(), vec![0; 20]),
+			(b"ab1".to_vec(), vec![0; 40]),
+			(b":code".to_vec(), vec![0; 5]),
+		].into_iter().collect::<std::collections::BTreeMap<_, _>>().into();
+
+		let stats = storage_stats::<Block, _>(&state, 2);
+		let stats: Vec<_> = stats.into_iter().map(|s| (s.prefix.0, s.keys, s.value_bytes)).collect();
+		assert_eq!(stats, vec![
+			(b"ab".to_vec(), 1, 40),
+			(b"aa".to_vec(), 2, 30),
+			(b":c".to_vec(), 1, 5),
+		]);
+	}
+}
@@ -16,7 +16,7 @@
 
 //! A set of APIs supported by the client along with their primitives.
 
-use std::{fmt, collections::HashSet};
+use std::{fmt, collections::HashSet, sync::Arc};
 use sp_core::storage::StorageKey;
 use sp_runtime::{
 	traits::{Block as BlockT, NumberFor},
@@ -24,8 +24,10 @@ use sp_runtime::{
 	Justification,
 };
 use sp_consensus::BlockOrigin;
+use sp_state_machine::{StorageCollection, ChildStorageCollection};
 
 use crate::blockchain::Info;
+use crate::lossy_notifications::{LossyImportNotifications, LossyFinalityNotifications};
 use crate::notifications::StorageEventStream;
 use sp_utils::mpsc::TracingUnboundedReceiver;
 use sp_blockchain;
@@ -64,6 +66,18 @@ pub trait BlockchainEvents<Block: BlockT> {
 	/// finalized block.
 	fn finality_notification_stream(&self) -> FinalityNotifications<Block>;
 
+	/// Get a block import event stream queueing at most `capacity` notifications.
+	///
+	/// When the subscriber lags behind, the oldest notifications are replaced by a marker of the
+	/// range of the skipped blocks.
+	fn lossy_import_notification_stream(&self, capacity: usize) -> LossyImportNotifications<Block>;
+
+	/// Get a stream of finality notifications queueing at most `capacity` notifications.
+	///
+	/// When the subscriber lags behind, the oldest notifications are replaced by a marker of the
+	/// range of the skipped blocks.
+	fn lossy_finality_notification_stream(&self, capacity: usize) -> LossyFinalityNotifications<Block>;
+
 	/// Get storage changes event stream.
 	///
 	/// Passing `None` as `filter_keys` subscribes to all storage changes.
@@ -72,6 +86,12 @@ pub trait BlockchainEvents<Block: BlockT> {
 		filter_keys: Option<&[StorageKey]>,
 		child_filter_keys: Option<&[(StorageKey, Option<Vec<StorageKey>>)]>,
 	) -> sp_blockchain::Result<StorageEventStream<Block::Hash>>;
+
+	/// Get a stream of the changes to the storage keys starting with one of `prefixes`.
+	fn storage_changes_prefix_notification_stream(
+		&self,
+		prefixes: &[StorageKey],
+	) -> sp_blockchain::Result<StorageEventStream<Block::Hash>>;
 }
 
 /// Interface for fetching block data.
@@ -90,6 +110,36 @@ pub trait BlockBackend<Block: BlockT> {
 
 	/// Get block justification set by id.
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>>;
+
+	/// Get the hash of the canonical block including the extrinsic with the given hash, and the
+	/// index of the extrinsic in its body. Returns `None` if the extrinsics are not indexed.
+	fn extrinsic_location(&self, hash: &Block::Hash) -> sp_blockchain::Result<Option<(Block::Hash, u32)>>;
+}
+
+/// A leaf of the block tree, with its lowest common ancestor with the best chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafInfo<Block: BlockT> {
+	/// Hash of the leaf.
+	pub hash: Block::Hash,
+	/// Number of the leaf.
+	pub number: NumberFor<Block>,
+	/// Hash of the lowest common ancestor of the leaf and the best block.
+	pub ancestor_hash: Block::Hash,
+	/// Number of the lowest common ancestor of the leaf and the best block.
+	pub ancestor_number: NumberFor<Block>,
+	/// Whether the leaf is not a descendant of the last finalized block, and can't be finalized.
+	pub abandoned: bool,
+}
+
+/// Interface for enumerating and pruning the forks of the block tree.
+pub trait ForkBackend<Block: BlockT> {
+	/// Get the leaves of the block tree, best first.
+	fn leaves_info(&self) -> sp_blockchain::Result<Vec<LeafInfo<Block>>>;
+
+	/// Remove the abandoned forks whose leaves are below the block `below` from the database.
+	///
+	/// Returns the hashes of the removed blocks.
+	fn prune_abandoned_forks(&self, below: NumberFor<Block>) -> sp_blockchain::Result<Vec<Block::Hash>>;
 }
 
 /// Provide a list of potential uncle headers for a given block.
@@ -149,6 +199,32 @@ pub struct StateDbMemoryInfo {
 	pub pinned: MemorySize,
 }
 
+/// Status of the pruning of the states by the state db.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct StateDbPruningInfo {
+	/// Number of canonical blocks whose states are kept, `None` if no state is pruned.
+	pub window: Option<u64>,
+	/// Number of the oldest block whose state is available.
+	pub oldest_block: u64,
+	/// Number of the last canonicalized block, if any.
+	pub last_canonicalized: Option<u64>,
+	/// Number of non-canonical blocks.
+	pub non_canonical: u64,
+	/// Number of canonical blocks past the window whose states are still to be pruned.
+	pub pending_prunes: u64,
+}
+
+/// Statistics of the cache of trie nodes.
+#[derive(Default, Clone, Debug)]
+pub struct TrieCacheInfo {
+	/// Memory used by the cached nodes.
+	pub size: MemorySize,
+	/// Number of nodes read from the cache since the start of the client.
+	pub hits: u64,
+	/// Number of nodes missing from the cache since the start of the client.
+	pub misses: u64,
+}
+
 /// Memory statistics for client instance.
 #[derive(Default, Clone, Debug)]
 pub struct MemoryInfo {
@@ -187,6 +263,21 @@ pub struct IoInfo {
 	pub state_writes_nodes: u64,
 }
 
+/// I/O statistics of a column of the database, since the start of the client.
+#[derive(Default, Clone, Debug)]
+pub struct ColumnIoInfo {
+	/// Name of the column.
+	pub name: &'static str,
+	/// Number of values read.
+	pub reads: u64,
+	/// Bytes of the values read.
+	pub bytes_read: u64,
+	/// Number of values written or removed.
+	pub writes: u64,
+	/// Bytes of the keys and values written, and of the keys removed.
+	pub bytes_written: u64,
+}
+
 /// Usage statistics for running client instance.
 ///
 /// Returning backend determines the scope of these stats,
@@ -198,6 +289,12 @@ pub struct UsageInfo {
 	pub memory: MemoryInfo,
 	/// I/O statistics.
 	pub io: IoInfo,
+	/// Pruning status of the state db, if any.
+	pub pruning: Option<StateDbPruningInfo>,
+	/// Statistics of the cache of trie nodes, if any.
+	pub trie_cache: Option<TrieCacheInfo>,
+	/// I/O statistics of the columns of the database, empty if they are not collected.
+	pub database_columns: Vec<ColumnIoInfo>,
 }
 
 impl fmt::Display for UsageInfo {
@@ -236,6 +333,12 @@ pub struct BlockImportNotification<Block: BlockT> {
 	pub is_new_best: bool,
 	/// List of retracted blocks ordered by block number.
 	pub retracted: Vec<Block::Hash>,
+	/// Changes of the main and child storages made by the execution of the block, if it has been
+	/// executed.
+	///
+	/// The changes are shared by all the subscribers, and freed once all the notifications are
+	/// dropped.
+	pub storage_changes: Option<Arc<(StorageCollection, ChildStorageCollection)>>,
 }
 
 /// Summary of a finalized block.
@@ -711,6 +711,10 @@ impl<Block: BlockT> backend::Backend<Block> for Backend<Block> where Block::Hash
 		Ok(Zero::zero())
 	}
 
+	fn prune_abandoned_forks(&self, _below: NumberFor<Block>) -> sp_blockchain::Result<Vec<Block::Hash>> {
+		Ok(Vec::new())
+	}
+
 	fn get_import_lock(&self) -> &RwLock<()> {
 		&self.import_lock
 	}
@@ -192,6 +192,15 @@ impl<H, N> LeafSet<H, N> where
 		}
 	}
 
+	/// Remove the leaf with the given hash and number, returns `false` if there was no such leaf.
+	pub fn remove(&mut self, hash: H, number: N) -> bool {
+		let removed = self.remove_leaf(&Reverse(number), &hash);
+		if removed {
+			self.pending_removed.push(hash);
+		}
+		removed
+	}
+
 	/// returns an iterator over all hashes in the leaf set
 	/// ordered by their block number descending.
 	pub fn hashes(&self) -> Vec<H> {
@@ -25,8 +25,10 @@ pub mod execution_extensions;
 pub mod in_mem;
 pub mod light;
 pub mod leaves;
+pub mod lossy_notifications;
 pub mod notifications;
 pub mod proof_provider;
+pub mod runtime_metadata;
 
 pub use sp_blockchain as blockchain;
 pub use backend::*;
@@ -34,6 +36,7 @@ pub use notifications::*;
 pub use call_executor::*;
 pub use client::*;
 pub use light::*;
+pub use lossy_notifications::*;
 pub use notifications::*;
 pub use proof_provider::*;
 
@@ -0,0 +1,223 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Bounded streams of block notifications, for subscribers that may lag behind.
+//!
+//! At most `capacity` notifications are queued for a subscriber. When the queue is full, the
+//! oldest notification is dropped, and the subscriber receives a marker of the range of blocks
+//! whose notifications were dropped before the notifications still queued.
+
+use std::{collections::VecDeque, pin::Pin, sync::Arc, task::{Context, Poll, Waker}};
+use futures::Stream;
+use parking_lot::Mutex;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor};
+
+use crate::client::{BlockImportNotification, FinalityNotification};
+
+/// Notification about a block.
+pub trait BlockNotification<Block: BlockT> {
+	/// Returns the number of the block.
+	fn block_number(&self) -> NumberFor<Block>;
+}
+
+impl<Block: BlockT> BlockNotification<Block> for BlockImportNotification<Block> {
+	fn block_number(&self) -> NumberFor<Block> {
+		*self.header.number()
+	}
+}
+
+impl<Block: BlockT> BlockNotification<Block> for FinalityNotification<Block> {
+	fn block_number(&self) -> NumberFor<Block> {
+		*self.header.number()
+	}
+}
+
+/// Item of a lossy notification stream.
+#[derive(Clone, Debug)]
+pub enum LossyNotification<Block: BlockT, N> {
+	/// A notification.
+	Notification(N),
+	/// The notifications of blocks were dropped, as the subscriber lagged behind.
+	Skipped {
+		/// Lowest number of the blocks whose notification was dropped.
+		from: NumberFor<Block>,
+		/// Highest number of the blocks whose notification was dropped.
+		to: NumberFor<Block>,
+		/// Number of dropped notifications.
+		count: usize,
+	},
+}
+
+/// Stream of block import notifications dropping the oldest ones of lagging subscribers.
+pub type LossyImportNotifications<Block> = LossyReceiver<Block, BlockImportNotification<Block>>;
+
+/// Stream of finality notifications dropping the oldest ones of lagging subscribers.
+pub type LossyFinalityNotifications<Block> = LossyReceiver<Block, FinalityNotification<Block>>;
+
+struct Shared<Block: BlockT, N> {
+	queue: VecDeque<N>,
+	/// Range and number of the notifications dropped since the last marker was received.
+	skipped: Option<(NumberFor<Block>, NumberFor<Block>, usize)>,
+	waker: Option<Waker>,
+	sender_dropped: bool,
+	receiver_dropped: bool,
+}
+
+/// Creates a lossy channel queueing at most `capacity` notifications, at least one.
+pub fn lossy_channel<Block: BlockT, N>(capacity: usize) -> (LossySender<Block, N>, LossyReceiver<Block, N>) {
+	let shared = Arc::new(Mutex::new(Shared {
+		queue: VecDeque::new(),
+		skipped: None,
+		waker: None,
+		sender_dropped: false,
+		receiver_dropped: false,
+	}));
+	let sender = LossySender { shared: shared.clone(), capacity: capacity.max(1) };
+	(sender, LossyReceiver { shared })
+}
+
+/// Sending side of a lossy channel.
+pub struct LossySender<Block: BlockT, N> {
+	shared: Arc<Mutex<Shared<Block, N>>>,
+	capacity: usize,
+}
+
+impl<Block: BlockT, N: BlockNotification<Block>> LossySender<Block, N> {
+	/// Queues a notification, dropping the oldest one if the queue is full.
+	///
+	/// Returns the notification back if the receiver has been dropped.
+	pub fn send(&self, notification: N) -> Result<(), N> {
+		let mut shared = self.shared.lock();
+		if shared.receiver_dropped {
+			return Err(notification)
+		}
+		if shared.queue.len() >= self.capacity {
+			if let Some(dropped) = shared.queue.pop_front() {
+				let number = dropped.block_number();
+				shared.skipped = Some(match shared.skipped {
+					Some((from, to, count)) => (from.min(number), to.max(number), count + 1),
+					None => (number, number, 1),
+				});
+			}
+		}
+		shared.queue.push_back(notification);
+		if let Some(waker) = shared.waker.take() {
+			waker.wake();
+		}
+		Ok(())
+	}
+}
+
+impl<Block: BlockT, N> LossySender<Block, N> {
+	/// Returns whether the receiver has been dropped.
+	pub fn is_closed(&self) -> bool {
+		self.shared.lock().receiver_dropped
+	}
+}
+
+impl<Block: BlockT, N> Drop for LossySender<Block, N> {
+	fn drop(&mut self) {
+		let mut shared = self.shared.lock();
+		shared.sender_dropped = true;
+		if let Some(waker) = shared.waker.take() {
+			waker.wake();
+		}
+	}
+}
+
+/// Receiving side of a lossy channel, ending once the sender is dropped and the queued
+/// notifications are received.
+pub struct LossyReceiver<Block: BlockT, N> {
+	shared: Arc<Mutex<Shared<Block, N>>>,
+}
+
+impl<Block: BlockT, N> Stream for LossyReceiver<Block, N> {
+	type Item = LossyNotification<Block, N>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+		let mut shared = self.shared.lock();
+		// the dropped notifications are older than the queued ones.
+		if let Some((from, to, count)) = shared.skipped.take() {
+			return Poll::Ready(Some(LossyNotification::Skipped { from, to, count }))
+		}
+		if let Some(notification) = shared.queue.pop_front() {
+			return Poll::Ready(Some(LossyNotification::Notification(notification)))
+		}
+		if shared.sender_dropped {
+			return Poll::Ready(None)
+		}
+		shared.waker = Some(cx.waker().clone());
+		Poll::Pending
+	}
+}
+
+impl<Block: BlockT, N> Drop for LossyReceiver<Block, N> {
+	fn drop(&mut self) {
+		let mut shared = self.shared.lock();
+		shared.receiver_dropped = true;
+		shared.queue.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on_stream;
+	use sp_test_primitives::{Block, Header};
+
+	fn finalized(number: u64) -> FinalityNotification<Block> {
+		let header = Header::new(number, Default::default(), Default::default(), Default::default(), Default::default());
+		FinalityNotification { hash: header.hash(), header }
+	}
+
+	fn numbers(stream: LossyFinalityNotifications<Block>) -> Vec<(u64, u64, usize)> {
+		block_on_stream(stream)
+			.map(|item| match item {
+				LossyNotification::Notification(n) => (n.header.number, n.header.number, 0),
+				LossyNotification::Skipped { from, to, count } => (from, to, count),
+			})
+			.collect()
+	}
+
+	#[test]
+	fn lagging_subscriber_receives_a_skipped_range() {
+		let (sender, receiver) = lossy_channel(2);
+		for number in 1..=5 {
+			sender.send(finalized(number)).unwrap();
+		}
+		drop(sender);
+
+		assert_eq!(numbers(receiver), vec![(1, 3, 3), (4, 4, 0), (5, 5, 0)]);
+	}
+
+	#[test]
+	fn subscriber_keeping_up_receives_every_notification() {
+		let (sender, mut receiver) = lossy_channel::<Block, _>(1);
+		for number in 1..=3 {
+			sender.send(finalized(number)).unwrap();
+			match futures::executor::block_on(futures::StreamExt::next(&mut receiver)) {
+				Some(LossyNotification::Notification(n)) => assert_eq!(n.header.number, number),
+				other => panic!("Unexpected item {:?}", other),
+			}
+		}
+
+		drop(receiver);
+		assert!(sender.is_closed());
+		assert!(sender.send(finalized(4)).is_err());
+	}
+}
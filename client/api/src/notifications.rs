@@ -35,6 +35,8 @@ pub struct StorageChangeSet {
 	changes: Arc<Vec<(StorageKey, Option<StorageData>)>>,
 	child_changes: Arc<Vec<(StorageKey, Vec<(StorageKey, Option<StorageData>)>)>>,
 	filter: Option<HashSet<StorageKey>>,
+	/// Prefixes of the keys kept in addition to the ones of `filter`.
+	prefix_filter: Vec<StorageKey>,
 	child_filters: Option<HashMap<StorageKey, Option<HashSet<StorageKey>>>>,
 }
 
@@ -45,7 +47,8 @@ impl StorageChangeSet {
 		let top = self.changes
 			.iter()
 			.filter(move |&(key, _)| match self.filter {
-				Some(ref filter) => filter.contains(key),
+				Some(ref filter) => filter.contains(key) ||
+					self.prefix_filter.iter().any(|prefix| key.0.starts_with(&prefix.0)),
 				None => true,
 			})
 			.map(move |(k,v)| (None, k, v.as_ref()));
@@ -83,6 +86,8 @@ pub struct StorageNotifications<Block: BlockT> {
 	next_id: SubscriberId,
 	wildcard_listeners: FnvHashSet<SubscriberId>,
 	listeners: HashMap<StorageKey, FnvHashSet<SubscriberId>>,
+	/// Listeners of the keys starting with a prefix, indexed by prefix.
+	prefix_listeners: HashMap<Vec<u8>, FnvHashSet<SubscriberId>>,
 	child_listeners: HashMap<StorageKey, (
 		HashMap<StorageKey, FnvHashSet<SubscriberId>>,
 		FnvHashSet<SubscriberId>
@@ -91,6 +96,7 @@ pub struct StorageNotifications<Block: BlockT> {
 		TracingUnboundedSender<(Block::Hash, StorageChangeSet)>,
 		Option<HashSet<StorageKey>>,
 		Option<HashMap<StorageKey, Option<HashSet<StorageKey>>>>,
+		Vec<StorageKey>,
 	)>,
 }
 
@@ -101,6 +107,7 @@ impl<Block: BlockT> Default for StorageNotifications<Block> {
 			next_id: Default::default(),
 			wildcard_listeners: Default::default(),
 			listeners: Default::default(),
+			prefix_listeners: Default::default(),
 			child_listeners: Default::default(),
 			sinks: Default::default(),
 		}
@@ -127,6 +134,7 @@ impl<Block: BlockT> StorageNotifications<Block> {
 			next_id: Default::default(),
 			wildcard_listeners: Default::default(),
 			listeners: Default::default(),
+			prefix_listeners: Default::default(),
 			child_listeners: Default::default(),
 			sinks: Default::default(),
 		}
@@ -147,7 +155,9 @@ impl<Block: BlockT> StorageNotifications<Block> {
 		let has_wildcard = !self.wildcard_listeners.is_empty();
 
 		// early exit if no listeners
-		if !has_wildcard && self.listeners.is_empty() && self.child_listeners.is_empty() {
+		if !has_wildcard && self.listeners.is_empty() && self.prefix_listeners.is_empty()
+			&& self.child_listeners.is_empty()
+		{
 			return;
 		}
 
@@ -164,7 +174,19 @@ impl<Block: BlockT> StorageNotifications<Block> {
 				subscribers.extend(listeners.iter());
 			}
 
-			if has_wildcard || listeners.is_some() {
+			// Look the prefixes of the key up, rather than matching the key against every prefix
+			// listened to.
+			let mut has_prefix_listeners = false;
+			if !self.prefix_listeners.is_empty() {
+				for len in 0..=k.0.len() {
+					if let Some(listeners) = self.prefix_listeners.get(&k.0[..len]) {
+						subscribers.extend(listeners.iter());
+						has_prefix_listeners = true;
+					}
+				}
+			}
+
+			if has_wildcard || listeners.is_some() || has_prefix_listeners {
 				changes.push((k, v.map(StorageData)));
 			}
 		}
@@ -203,13 +225,14 @@ impl<Block: BlockT> StorageNotifications<Block> {
 
 		let to_remove = self.sinks
 			.iter()
-			.filter_map(|(subscriber, &(ref sink, ref filter, ref child_filters))| {
+			.filter_map(|(subscriber, &(ref sink, ref filter, ref child_filters, ref prefixes))| {
 				let should_remove = {
 					if subscribers.contains(subscriber) {
 						sink.unbounded_send((hash.clone(), StorageChangeSet {
 							changes: changes.clone(),
 							child_changes: child_changes.clone(),
 							filter: filter.clone(),
+							prefix_filter: prefixes.clone(),
 							child_filters: child_filters.clone(),
 						})).is_err()
 					} else {
@@ -259,13 +282,26 @@ impl<Block: BlockT> StorageNotifications<Block> {
 	}
 
 	fn remove_subscriber(&mut self, subscriber: SubscriberId) {
-		if let Some((_, filters, child_filters)) = self.sinks.remove(&subscriber) {
+		if let Some((_, filters, child_filters, prefixes)) = self.sinks.remove(&subscriber) {
 			Self::remove_subscriber_from(
 				&subscriber,
 				&filters,
 				&mut self.listeners,
 				&mut self.wildcard_listeners,
 			);
+			for prefix in prefixes {
+				let remove_prefix = match self.prefix_listeners.get_mut(&prefix.0) {
+					Some(set) => {
+						set.remove(&subscriber);
+						set.is_empty()
+					},
+					None => false,
+				};
+
+				if remove_prefix {
+					self.prefix_listeners.remove(&prefix.0);
+				}
+			}
 			if let Some(child_filters) = child_filters.as_ref() {
 				for (c_key, filters) in child_filters {
 
@@ -345,7 +381,31 @@ impl<Block: BlockT> StorageNotifications<Block> {
 
 		// insert sink
 		let (tx, rx) = tracing_unbounded("mpsc_storage_notification_items");
-		self.sinks.insert(current_id, (tx, keys, child_keys));
+		self.sinks.insert(current_id, (tx, keys, child_keys, Vec::new()));
+
+		if let Some(m) = self.metrics.as_ref() {
+			m.with_label_values(&[&"added"]).inc();
+		}
+
+		rx
+	}
+
+	/// Start listening for the storage keys starting with one of `prefixes`.
+	///
+	/// Every notification holds all the changes made by a block to these keys.
+	pub fn listen_prefixes(&mut self, prefixes: &[StorageKey]) -> StorageEventStream<Block::Hash> {
+		self.next_id += 1;
+		let current_id = self.next_id;
+
+		for prefix in prefixes {
+			self.prefix_listeners
+				.entry(prefix.0.clone())
+				.or_insert_with(Default::default)
+				.insert(current_id);
+		}
+
+		let (tx, rx) = tracing_unbounded("mpsc_storage_notification_items");
+		self.sinks.insert(current_id, (tx, Some(HashSet::new()), None, prefixes.to_vec()));
 
 		if let Some(m) = self.metrics.as_ref() {
 			m.with_label_values(&[&"added"]).inc();
@@ -378,6 +438,7 @@ mod tests {
 				changes: Arc::new(changes.0),
 				child_changes: Arc::new(changes.1),
 				filter: None,
+				prefix_filter: Vec::new(),
 				child_filters,
 			}
 		}
@@ -527,4 +588,51 @@ mod tests {
 		// then
 		assert_eq!(recv.next(), None);
 	}
+
+	#[test]
+	fn should_notify_prefix_listeners() {
+		// given
+		let mut notifications = StorageNotifications::<Block>::default();
+		let mut recv = futures::executor::block_on_stream(
+			notifications.listen_prefixes(&[StorageKey(vec![1]), StorageKey(vec![2, 2])])
+		);
+
+		// when
+		let changeset = vec![
+			(vec![1], Some(vec![1])),
+			(vec![1, 5], None),
+			(vec![2, 3], Some(vec![3])),
+			(vec![2, 2, 7], Some(vec![4])),
+		];
+		let c_changeset = empty::<(_, Empty<_>)>();
+		notifications.trigger(&Hash::from_low_u64_be(1), changeset.into_iter(), c_changeset);
+
+		// then
+		assert_eq!(recv.next().unwrap(), (Hash::from_low_u64_be(1), (vec![
+			(StorageKey(vec![1]), Some(StorageData(vec![1]))),
+			(StorageKey(vec![1, 5]), None),
+			(StorageKey(vec![2, 2, 7]), Some(StorageData(vec![4]))),
+		], vec![]).into()));
+	}
+
+	#[test]
+	fn should_cleanup_prefix_subscribers_if_dropped() {
+		// given
+		let mut notifications = StorageNotifications::<Block>::default();
+		{
+			let _recv = futures::executor::block_on_stream(
+				notifications.listen_prefixes(&[StorageKey(vec![1])])
+			);
+			assert_eq!(notifications.prefix_listeners.len(), 1);
+		}
+
+		// when
+		let changeset = vec![(vec![1, 2], None)];
+		let c_changeset = empty::<(_, Empty<_>)>();
+		notifications.trigger(&Hash::from_low_u64_be(1), changeset.into_iter(), c_changeset);
+
+		// then
+		assert_eq!(notifications.prefix_listeners.len(), 0);
+		assert_eq!(notifications.sinks.len(), 0);
+	}
 }
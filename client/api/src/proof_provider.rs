@@ -24,6 +24,18 @@ use sp_runtime::{
 use crate::{StorageProof, ChangesProof};
 use sp_storage::{ChildInfo, StorageKey, PrefixedStorageKey};
 
+/// Key-value pair of the state returned by [`ProofProvider::state_chunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateEntry {
+	/// Storage key of the default child trie the entry belongs to (without the
+	/// `:child_storage:default:` prefix), or `None` for entries of the top trie.
+	pub child: Option<Vec<u8>>,
+	/// Key of the entry.
+	pub key: Vec<u8>,
+	/// Value of the entry.
+	pub value: Vec<u8>,
+}
+
 /// Interface for providing block proving utilities.
 pub trait ProofProvider<Block: BlockT> {
 	/// Reads storage value at a given block + key, returning read proof.
@@ -42,6 +54,18 @@ pub trait ProofProvider<Block: BlockT> {
 		keys: &mut dyn Iterator<Item=&[u8]>,
 	) -> sp_blockchain::Result<StorageProof>;
 
+	/// Reads storage values of the top trie and of child tries at a given block, returning a
+	/// single read proof of all of them.
+	///
+	/// The trie nodes shared by the entries, such as the ones leading to the roots of the child
+	/// tries, are only included once.
+	fn read_proof_with_child_keys(
+		&self,
+		id: &BlockId<Block>,
+		keys: &mut dyn Iterator<Item=&[u8]>,
+		child_keys: &[(ChildInfo, Vec<Vec<u8>>)],
+	) -> sp_blockchain::Result<StorageProof>;
+
 	/// Execute a call to a contract on top of state in a block of given hash
 	/// AND returning execution proof.
 	///
@@ -52,6 +76,21 @@ pub trait ProofProvider<Block: BlockT> {
 		method: &str,
 		call_data: &[u8],
 	) -> sp_blockchain::Result<(Vec<u8>, StorageProof)>;
+
+	/// Executes the runtime `method` with `call_data` on the state of the given block, without
+	/// initializing a new block first, returning its result along with the proof of the storage
+	/// read during the execution.
+	///
+	/// The proof includes the runtime code of the block, so that it can be checked with
+	/// `sp_state_machine::execution_proof_check_with_proof_code` knowing only the state root.
+	/// No changes are made.
+	fn prove_execution(
+		&self,
+		id: &BlockId<Block>,
+		method: &str,
+		call_data: &[u8],
+	) -> sp_blockchain::Result<(Vec<u8>, StorageProof)>;
+
 	/// Reads given header and generates CHT-based header proof.
 	fn header_proof(&self, id: &BlockId<Block>) -> sp_blockchain::Result<(Block::Header, StorageProof)>;
 
@@ -70,4 +109,41 @@ pub trait ProofProvider<Block: BlockT> {
 		storage_key: Option<&PrefixedStorageKey>,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<ChangesProof<Block::Header>>;
+
+	/// Reads a chunk of the state at a given block, in key order, with child tries listed right
+	/// after the top trie key holding their root.
+	///
+	/// `start` is empty to read from the beginning of the state, contains the last top trie key
+	/// already read, or contains the prefixed storage key of a child trie followed by the last
+	/// key of that child trie already read. Entries are added until their total size reaches
+	/// `size_limit`. Returns the entries along with `true` if the end of the state was reached.
+	fn state_chunk(
+		&self,
+		id: &BlockId<Block>,
+		start: &[Vec<u8>],
+		size_limit: usize,
+	) -> sp_blockchain::Result<(Vec<StateEntry>, bool)>;
+
+	/// Reads a chunk of the state at a given block, like [`ProofProvider::state_chunk`], and
+	/// returns the proof of the trie nodes read instead of the entries themselves.
+	///
+	/// The entries can be recovered from the proof with [`ProofProvider::verify_range_proof`].
+	fn read_proof_collection(
+		&self,
+		id: &BlockId<Block>,
+		start: &[Vec<u8>],
+		size_limit: usize,
+	) -> sp_blockchain::Result<StorageProof>;
+
+	/// Recovers the chunk of the state starting at `start` from a proof generated by
+	/// [`ProofProvider::read_proof_collection`], checked against the state `root`.
+	///
+	/// The chunk ends where the proof stops covering the state. Returns the entries along with
+	/// `true` if the end of the state was reached.
+	fn verify_range_proof(
+		&self,
+		root: Block::Hash,
+		proof: StorageProof,
+		start: &[Vec<u8>],
+	) -> sp_blockchain::Result<(Vec<StateEntry>, bool)>;
 }
@@ -0,0 +1,71 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Metadata of the runtimes of the chain, stored in the auxiliary storage.
+//!
+//! The metadata of a block is built by its runtime, which may no longer be instantiable once
+//! the chain has been upgraded. The metadata of every runtime is therefore stored when the
+//! runtime is enacted, indexed by its `spec_version`, and the `spec_version` is indexed by the
+//! hash of the code of the runtime.
+
+use codec::{Decode, Encode};
+use sp_blockchain::Result as ClientResult;
+use crate::backend::AuxStore;
+
+const METADATA_PREFIX: &[u8] = b"runtime_metadata";
+const SPEC_VERSION_PREFIX: &[u8] = b"runtime_metadata_spec_version";
+
+fn metadata_key(spec_version: u32) -> Vec<u8> {
+	(METADATA_PREFIX, spec_version).encode()
+}
+
+fn spec_version_key<H: Encode>(code_hash: &H) -> Vec<u8> {
+	(SPEC_VERSION_PREFIX, code_hash).encode()
+}
+
+/// Returns the stored metadata of the runtime with the given `spec_version`.
+pub fn load_metadata<A: AuxStore>(store: &A, spec_version: u32) -> ClientResult<Option<Vec<u8>>> {
+	store.get_aux(&metadata_key(spec_version))
+}
+
+/// Returns the `spec_version` of the runtime whose code has the given hash, if its metadata is
+/// stored.
+pub fn load_spec_version<A: AuxStore, H: Encode>(store: &A, code_hash: &H) -> ClientResult<Option<u32>> {
+	match store.get_aux(&spec_version_key(code_hash))? {
+		Some(spec_version) => u32::decode(&mut &spec_version[..])
+			.map(Some)
+			.map_err(|e| sp_blockchain::Error::Backend(format!("Invalid runtime spec version: {}", e))),
+		None => Ok(None),
+	}
+}
+
+/// Stores the metadata of the runtime with the given `spec_version` and code hash.
+pub fn write_metadata<A: AuxStore, H: Encode>(
+	store: &A,
+	code_hash: &H,
+	spec_version: u32,
+	metadata: &[u8],
+) -> ClientResult<()> {
+	store.insert_aux(
+		&[
+			(&metadata_key(spec_version)[..], metadata),
+			(&spec_version_key(code_hash)[..], &spec_version.encode()[..]),
+		],
+		&[],
+	)
+}
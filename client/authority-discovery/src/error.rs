@@ -30,6 +30,15 @@ pub enum Error {
 	ReceivingDhtValueFoundEventWithNoRecords,
 	/// Failed to verify a dht payload with the given signature.
 	VerifyingDhtPayload,
+	/// Received a dht payload that isn't signed with the network key of its publisher.
+	MissingPeerSignature,
+	/// Failed to verify a dht payload with the signature made with the network key.
+	VerifyingPeerSignature,
+	/// Received a dht payload with addresses of another peer than the one whose network key signed
+	/// it.
+	AddressesOfAnotherPeer,
+	/// Failed to decode the network public key a dht payload is signed with.
+	DecodingPeerPublicKey(libp2p::identity::error::DecodingError),
 	/// Failed to hash the authority id to be used as a dht key.
 	HashingAuthorityId(libp2p::core::multiaddr::multihash::EncodeError),
 	/// Failed calling into the Substrate runtime.
@@ -52,6 +61,8 @@ pub enum Error {
 	MissingSignature(CryptoTypePublicPair),
 	/// Failed to sign using all public keys.
 	Signing,
+	/// Failed to sign using the network key.
+	SigningWithNetworkKey(libp2p::identity::error::SigningError),
 	/// Failed to register Prometheus metric.
 	Prometheus(prometheus_endpoint::PrometheusError),
 }
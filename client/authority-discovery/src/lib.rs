@@ -26,10 +26,11 @@
 //! 1. **Makes itself discoverable**
 //!
 //!    1. Retrieves its external addresses (including peer id) or the ones of its sentry nodes.
+//!       Which addresses are published can be restricted with [`Config::published_addresses`].
 //!
-//!    2. Signs the above.
+//!    2. Signs the above with its authority key, and with its network key.
 //!
-//!    3. Puts the signature and the addresses on the libp2p Kademlia DHT.
+//!    3. Puts the signatures and the addresses on the libp2p Kademlia DHT.
 //!
 //!
 //! 2. **Discovers other authorities**
@@ -38,7 +39,7 @@
 //!
 //!    2. Starts DHT queries for the ids of the authorities.
 //!
-//!    3. Validates the signatures of the retrieved key value pairs.
+//!    3. Validates both signatures of the retrieved key value pairs.
 //!
 //!    4. Adds the retrieved external addresses as priority nodes to the peerset.
 //!
@@ -46,6 +47,10 @@
 //! publish any addresses to the DHT but still discovers validators and
 //! sentry nodes of validators, i.e. only step 2 (Discovers other authorities)
 //! is executed.
+//!
+//! A private authority, only connected to its sentry nodes, never reaches the DHT directly: it
+//! publishes its signed records through its sentry nodes, which keep it out of their routing
+//! table, and the records only contain the addresses of the sentry nodes.
 
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
@@ -60,11 +65,11 @@ use futures_timer::Delay;
 
 use codec::Decode;
 use error::{Error, Result};
-use log::{debug, error, log_enabled};
+use log::{debug, error, log_enabled, warn};
 use prometheus_endpoint::{Counter, CounterVec, Gauge, Opts, U64, register};
 use prost::Message;
 use sc_client_api::blockchain::HeaderBackend;
-use sc_network::{Multiaddr, config::MultiaddrWithPeerId, DhtEvent, ExHashT, NetworkStateInfo};
+use sc_network::{Multiaddr, PeerId, config::MultiaddrWithPeerId, DhtEvent, ExHashT, NetworkStateInfo, Signature};
 use sp_authority_discovery::{AuthorityDiscoveryApi, AuthorityId, AuthoritySignature, AuthorityPair};
 use sp_core::crypto::{key_types, Pair};
 use sp_core::traits::BareCryptoStorePtr;
@@ -93,6 +98,45 @@ const LIBP2P_KADEMLIA_BOOTSTRAP_TIME: Duration = Duration::from_secs(30);
 /// discovery module.
 const AUTHORITIES_PRIORITY_GROUP_NAME: &'static str = "authorities";
 
+/// Configuration of an [`AuthorityDiscovery`].
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// Interval at which our addresses are published on the DHT.
+	///
+	/// Defaults to 12 hours.
+	pub publish_interval: Duration,
+	/// Time after which the records we publish expire from the DHT. Should be longer than
+	/// `publish_interval`, otherwise our addresses can't be found between the expiration and the
+	/// next publication.
+	///
+	/// Defaults to 36 hours.
+	pub record_ttl: Duration,
+	/// Addresses published on the DHT. Ignored when sentry nodes are specified, whose addresses
+	/// are published instead.
+	///
+	/// Defaults to [`PublishedAddresses::External`].
+	pub published_addresses: PublishedAddresses,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			publish_interval: Duration::from_secs(12 * 60 * 60),
+			record_ttl: Duration::from_secs(36 * 60 * 60),
+			published_addresses: PublishedAddresses::External,
+		}
+	}
+}
+
+/// Addresses an authority publishes on the DHT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishedAddresses {
+	/// All the external addresses of the node, as reported by the network.
+	External,
+	/// Only the given addresses, for example the ones passed with `--public-addr`.
+	Only(Vec<Multiaddr>),
+}
+
 /// Role an authority discovery module can run as.
 pub enum Role {
 	/// Actual authority as well as a reference to its key store.
@@ -128,6 +172,10 @@ where
 
 	/// Interval to be proactive, publishing own addresses.
 	publish_interval: Interval,
+	/// Time after which the records we publish expire from the DHT.
+	record_ttl: Duration,
+	/// Addresses published when not running with sentry nodes.
+	published_addresses: PublishedAddresses,
 	/// Interval on which to query for addresses of other authorities.
 	query_interval: Interval,
 
@@ -149,7 +197,7 @@ where
 		AuthorityDiscoveryApi<Block, Error = sp_blockchain::Error>,
 	Self: Future<Output = ()>,
 {
-	/// Return a new authority discovery.
+	/// Return a new authority discovery, with the default [`Config`].
 	///
 	/// Note: When specifying `sentry_nodes` this module will not advertise the public addresses of
 	/// the node itself but only the public addresses of its sentry nodes.
@@ -161,13 +209,45 @@ where
 		role: Role,
 		prometheus_registry: Option<prometheus_endpoint::Registry>,
 	) -> Self {
-		// Kademlia's default time-to-live for Dht records is 36h, republishing records every 24h.
+		Self::new_with_config(
+			Config::default(),
+			client,
+			network,
+			sentry_nodes,
+			dht_event_rx,
+			role,
+			prometheus_registry,
+		)
+	}
+
+	/// Return a new authority discovery, with the given [`Config`].
+	///
+	/// Note: When specifying `sentry_nodes` this module will not advertise the public addresses of
+	/// the node itself but only the public addresses of its sentry nodes.
+	pub fn new_with_config(
+		config: Config,
+		client: Arc<Client>,
+		network: Arc<Network>,
+		sentry_nodes: Vec<MultiaddrWithPeerId>,
+		dht_event_rx: Pin<Box<dyn Stream<Item = DhtEvent> + Send>>,
+		role: Role,
+		prometheus_registry: Option<prometheus_endpoint::Registry>,
+	) -> Self {
 		// Given that a node could restart at any point in time, one can not depend on the
-		// republishing process, thus publishing own external addresses should happen on an interval
-		// < 36h.
+		// republishing process of Kademlia, thus publishing own external addresses should happen
+		// on an interval shorter than the time-to-live of the records.
+		if config.publish_interval >= config.record_ttl {
+			warn!(
+				target: LOG_TARGET,
+				"Publish interval ({:?}) isn't shorter than the record time-to-live ({:?}), \
+				 our addresses will periodically be missing from the DHT.",
+				config.publish_interval,
+				config.record_ttl,
+			);
+		}
 		let publish_interval = interval_at(
 			Instant::now() + LIBP2P_KADEMLIA_BOOTSTRAP_TIME,
-			Duration::from_secs(12 * 60 * 60),
+			config.publish_interval,
 		);
 
 		// External addresses of other authorities can change at any given point in time. The
@@ -205,6 +285,8 @@ where
 			sentry_nodes,
 			dht_event_rx,
 			publish_interval,
+			record_ttl: config.record_ttl,
+			published_addresses: config.published_addresses,
 			query_interval,
 			addr_cache,
 			role,
@@ -231,13 +313,18 @@ where
 			Some(addrs) => addrs.clone().into_iter()
 				.map(|a| a.to_vec())
 				.collect(),
-			None => self.network.external_addresses()
-				.into_iter()
-				.map(|a| a.with(libp2p::core::multiaddr::Protocol::P2p(
-					self.network.local_peer_id().into(),
-				)))
-				.map(|a| a.to_vec())
-				.collect(),
+			None => {
+				let addresses = match &self.published_addresses {
+					PublishedAddresses::External => self.network.external_addresses(),
+					PublishedAddresses::Only(addresses) => addresses.clone(),
+				};
+				addresses.into_iter()
+					.map(|a| a.with(libp2p::core::multiaddr::Protocol::P2p(
+						self.network.local_peer_id().into(),
+					)))
+					.map(|a| a.to_vec())
+					.collect()
+			},
 		};
 
 		if let Some(metrics) = &self.metrics {
@@ -249,6 +336,13 @@ where
 			.encode(&mut serialized_addresses)
 			.map_err(Error::EncodingProto)?;
 
+		let peer_signature = self.network.sign_with_local_identity(&serialized_addresses)
+			.map_err(Error::SigningWithNetworkKey)?;
+		let peer_signature = schema::PeerSignature {
+			signature: peer_signature.bytes,
+			public_key: peer_signature.public_key.into_protobuf_encoding(),
+		};
+
 		let keys = AuthorityDiscovery::get_own_public_keys_within_authority_set(
 			&key_store,
 			&self.client,
@@ -272,6 +366,7 @@ where
 			schema::SignedAuthorityAddresses {
 				addresses: serialized_addresses.clone(),
 				signature,
+				peer_signature: Some(peer_signature.clone()),
 			}
 			.encode(&mut signed_addresses)
 				.map_err(Error::EncodingProto)?;
@@ -279,6 +374,7 @@ where
 			self.network.put_value(
 				hash_authority_id(key.1.as_ref()),
 				signed_addresses,
+				self.record_ttl,
 			);
 		}
 
@@ -412,7 +508,7 @@ where
 
 		let remote_addresses: Vec<Multiaddr> = values.into_iter()
 			.map(|(_k, v)| {
-				let schema::SignedAuthorityAddresses { signature, addresses } =
+				let schema::SignedAuthorityAddresses { signature, addresses, peer_signature } =
 					schema::SignedAuthorityAddresses::decode(v.as_slice())
 					.map_err(Error::DecodingProto)?;
 
@@ -423,7 +519,19 @@ where
 					return Err(Error::VerifyingDhtPayload);
 				}
 
-				let addresses = schema::AuthorityAddresses::decode(addresses.as_slice())
+				let peer_signature = peer_signature.ok_or(Error::MissingPeerSignature)?;
+				let peer_signature = Signature {
+					public_key: libp2p::identity::PublicKey::from_protobuf_encoding(
+						&peer_signature.public_key,
+					).map_err(Error::DecodingPeerPublicKey)?,
+					bytes: peer_signature.signature,
+				};
+				if !peer_signature.verify(&addresses) {
+					return Err(Error::VerifyingPeerSignature);
+				}
+				let peer_id = peer_signature.public_key.into_peer_id();
+
+				let addresses: Vec<Multiaddr> = schema::AuthorityAddresses::decode(addresses.as_slice())
 					.map(|a| a.addresses)
 					.map_err(Error::DecodingProto)?
 					.into_iter()
@@ -431,6 +539,16 @@ where
 					.collect::<std::result::Result<_, _>>()
 					.map_err(Error::ParsingMultiaddress)?;
 
+				// the network key only vouches for the addresses of the node it identifies.
+				let is_own_address = |address: &Multiaddr| address.iter().any(|protocol| match protocol {
+					libp2p::core::multiaddr::Protocol::P2p(hash) =>
+						PeerId::from_multihash(hash).map_or(false, |id| id == peer_id),
+					_ => false,
+				});
+				if !addresses.iter().all(is_own_address) {
+					return Err(Error::AddressesOfAnotherPeer);
+				}
+
 				Ok(addresses)
 			})
 			.collect::<Result<Vec<Vec<Multiaddr>>>>()?
@@ -559,11 +677,17 @@ pub trait NetworkProvider: NetworkStateInfo {
 		peers: HashSet<libp2p::Multiaddr>,
 	) -> std::result::Result<(), String>;
 
-	/// Start putting a value in the Dht.
-	fn put_value(&self, key: libp2p::kad::record::Key, value: Vec<u8>);
+	/// Start putting a value in the Dht, expiring after `ttl`.
+	fn put_value(&self, key: libp2p::kad::record::Key, value: Vec<u8>, ttl: Duration);
 
 	/// Start getting a value from the Dht.
 	fn get_value(&self, key: &libp2p::kad::record::Key);
+
+	/// Sign a message with the network key of the local node.
+	fn sign_with_local_identity(
+		&self,
+		message: &[u8],
+	) -> std::result::Result<Signature, libp2p::identity::error::SigningError>;
 }
 
 impl<B, H> NetworkProvider for sc_network::NetworkService<B, H>
@@ -578,11 +702,17 @@ where
 	) -> std::result::Result<(), String> {
 		self.set_priority_group(group_id, peers)
 	}
-	fn put_value(&self, key: libp2p::kad::record::Key, value: Vec<u8>) {
-		self.put_value(key, value)
+	fn put_value(&self, key: libp2p::kad::record::Key, value: Vec<u8>, ttl: Duration) {
+		self.put_dht_value_with_ttl(key, value, ttl)
 	}
 	fn get_value(&self, key: &libp2p::kad::record::Key) {
-		self.get_value(key)
+		self.get_dht_value(key)
+	}
+	fn sign_with_local_identity(
+		&self,
+		message: &[u8],
+	) -> std::result::Result<Signature, libp2p::identity::error::SigningError> {
+		self.sign_with_local_identity(message)
 	}
 }
 
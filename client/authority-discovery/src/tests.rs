@@ -165,18 +165,21 @@ sp_api::mock_impl_runtime_apis! {
 }
 
 struct TestNetwork {
+	identity: libp2p::identity::Keypair,
 	peer_id: PeerId,
 	// Whenever functions on `TestNetwork` are called, the function arguments are added to the
 	// vectors below.
-	pub put_value_call: Arc<Mutex<Vec<(kad::record::Key, Vec<u8>)>>>,
+	pub put_value_call: Arc<Mutex<Vec<(kad::record::Key, Vec<u8>, Duration)>>>,
 	pub get_value_call: Arc<Mutex<Vec<kad::record::Key>>>,
 	pub set_priority_group_call: Arc<Mutex<Vec<(String, HashSet<Multiaddr>)>>>,
 }
 
 impl Default for TestNetwork {
 	fn default() -> Self {
+		let identity = libp2p::identity::Keypair::generate_ed25519();
 		TestNetwork {
-			peer_id: PeerId::random(),
+			peer_id: identity.public().into_peer_id(),
+			identity,
 			put_value_call: Default::default(),
 			get_value_call: Default::default(),
 			set_priority_group_call: Default::default(),
@@ -196,12 +199,19 @@ impl NetworkProvider for TestNetwork {
 			.push((group_id, peers));
 		Ok(())
 	}
-	fn put_value(&self, key: kad::record::Key, value: Vec<u8>) {
-		self.put_value_call.lock().unwrap().push((key, value));
+	fn put_value(&self, key: kad::record::Key, value: Vec<u8>, ttl: Duration) {
+		self.put_value_call.lock().unwrap().push((key, value, ttl));
 	}
 	fn get_value(&self, key: &kad::record::Key) {
 		self.get_value_call.lock().unwrap().push(key.clone());
 	}
+	fn sign_with_local_identity(
+		&self,
+		message: &[u8],
+	) -> std::result::Result<Signature, libp2p::identity::error::SigningError> {
+		let bytes = self.identity.sign(message)?;
+		Ok(Signature { public_key: self.identity.public(), bytes })
+	}
 }
 
 impl NetworkStateInfo for TestNetwork {
@@ -310,7 +320,7 @@ fn publish_discover_cycle() {
 	assert_eq!(network.put_value_call.lock().unwrap().len(), 1);
 
 	let dht_event = {
-		let (key, value) = network.put_value_call.lock().unwrap().pop().unwrap();
+		let (key, value, _) = network.put_value_call.lock().unwrap().pop().unwrap();
 		sc_network::DhtEvent::ValueFound(vec![(key, value)])
 	};
 
@@ -358,6 +368,138 @@ fn publish_discover_cycle() {
 	let _ = block_on(poll_fn(f));
 }
 
+#[test]
+fn publish_with_config() {
+	let (_dht_event_tx, dht_event_rx) = channel(1000);
+	let network: Arc<TestNetwork> = Arc::new(Default::default());
+	let key_store = KeyStore::new();
+	let public = key_store
+		.write()
+		.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None)
+		.unwrap();
+	let test_api = Arc::new(TestApi {
+		authorities: vec![public.into()],
+	});
+
+	let public_addr: Multiaddr = "/ip4/198.51.100.19/tcp/30333".parse().unwrap();
+	let config = Config {
+		record_ttl: Duration::from_secs(60),
+		published_addresses: PublishedAddresses::Only(vec![public_addr.clone()]),
+		..Default::default()
+	};
+	let mut authority_discovery = AuthorityDiscovery::new_with_config(
+		config,
+		test_api,
+		network.clone(),
+		vec![],
+		dht_event_rx.boxed(),
+		Role::Authority(key_store),
+		None,
+	);
+
+	authority_discovery.publish_ext_addresses().unwrap();
+
+	let (_, value, ttl) = network.put_value_call.lock().unwrap().pop().unwrap();
+	assert_eq!(ttl, Duration::from_secs(60));
+
+	let signed = schema::SignedAuthorityAddresses::decode(value.as_slice()).unwrap();
+	let addresses = schema::AuthorityAddresses::decode(signed.addresses.as_slice()).unwrap().addresses;
+	let expected = public_addr.with(libp2p::core::multiaddr::Protocol::P2p(network.peer_id.clone().into()));
+	assert_eq!(addresses, vec![expected.to_vec()]);
+}
+
+#[test]
+fn records_without_valid_peer_signature_are_rejected() {
+	let (_dht_event_tx, dht_event_rx) = channel(1000);
+	let network: Arc<TestNetwork> = Arc::new(Default::default());
+	let key_store = KeyStore::new();
+	let public = key_store
+		.write()
+		.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None)
+		.unwrap();
+	let test_api = Arc::new(TestApi {
+		authorities: vec![public.into()],
+	});
+
+	let mut authority_discovery = AuthorityDiscovery::new(
+		test_api,
+		network.clone(),
+		vec![],
+		dht_event_rx.boxed(),
+		Role::Authority(key_store),
+		None,
+	);
+
+	authority_discovery.publish_ext_addresses().unwrap();
+	let (key, value, _) = network.put_value_call.lock().unwrap().pop().unwrap();
+	let signed = schema::SignedAuthorityAddresses::decode(value.as_slice()).unwrap();
+
+	let reencode = |signed: schema::SignedAuthorityAddresses| {
+		let mut value = Vec::new();
+		signed.encode(&mut value).unwrap();
+		vec![(key.clone(), value)]
+	};
+
+	let mut unsigned = signed.clone();
+	unsigned.peer_signature = None;
+	assert!(matches!(
+		authority_discovery.handle_dht_value_found_event(reencode(unsigned)),
+		Err(Error::MissingPeerSignature)
+	));
+
+	let mut forged = signed.clone();
+	forged.peer_signature.as_mut().unwrap().public_key =
+		libp2p::identity::Keypair::generate_ed25519().public().into_protobuf_encoding();
+	assert!(matches!(
+		authority_discovery.handle_dht_value_found_event(reencode(forged)),
+		Err(Error::VerifyingPeerSignature)
+	));
+
+	assert!(authority_discovery.handle_dht_value_found_event(reencode(signed)).is_ok());
+}
+
+#[test]
+fn records_with_addresses_of_another_peer_are_rejected() {
+	let (_dht_event_tx, dht_event_rx) = channel(1000);
+	let network: Arc<TestNetwork> = Arc::new(Default::default());
+	let key_store = KeyStore::new();
+	let public = key_store
+		.write()
+		.sr25519_generate_new(key_types::AUTHORITY_DISCOVERY, None)
+		.unwrap();
+	let test_api = Arc::new(TestApi {
+		authorities: vec![public.into()],
+	});
+
+	let mut authority_discovery = AuthorityDiscovery::new(
+		test_api,
+		network.clone(),
+		vec![],
+		dht_event_rx.boxed(),
+		Role::Authority(key_store),
+		None,
+	);
+
+	authority_discovery.publish_ext_addresses().unwrap();
+	let (key, value, _) = network.put_value_call.lock().unwrap().pop().unwrap();
+	let mut signed = schema::SignedAuthorityAddresses::decode(value.as_slice()).unwrap();
+
+	// the addresses of `network` are validly signed with the network key of another node.
+	let other = libp2p::identity::Keypair::generate_ed25519();
+	signed.peer_signature = Some(schema::PeerSignature {
+		signature: other.sign(&signed.addresses).unwrap(),
+		public_key: other.public().into_protobuf_encoding(),
+	});
+	let mut value = Vec::new();
+	signed.encode(&mut value).unwrap();
+
+	assert!(matches!(
+		authority_discovery.handle_dht_value_found_event(vec![(key, value)]),
+		Err(Error::AddressesOfAnotherPeer)
+	));
+	assert!(authority_discovery.addr_cache.get_subset().is_empty());
+}
+
 #[test]
 fn terminate_when_event_stream_terminates() {
 	let (dht_event_tx, dht_event_rx) = channel(1000);
@@ -21,22 +21,38 @@ use proc_macro_crate::crate_name;
 
 const CRATE_NAME: &str = "sc-chain-spec";
 const ATTRIBUTE_NAME: &str = "forks";
+const VALIDATE_ATTRIBUTE_NAME: &str = "validate";
 
 /// Implements `Extension's` `Group` accessor.
 ///
 /// The struct that derives this implementation will be usable within the `ChainSpec` file.
-/// The derive implements a by-type accessor method.
+/// The derive implements by-type accessor methods, and the validation with the function given
+/// in the `#[validate(function)]` attribute, if any.
 pub fn extension_derive(ast: &DeriveInput) -> proc_macro::TokenStream {
+	let validate = match ast.attrs.iter().find(|attr| attr.path.is_ident(VALIDATE_ATTRIBUTE_NAME)) {
+		Some(attr) => match attr.parse_args::<syn::Path>() {
+			Ok(function) => Some(function),
+			Err(e) => return e.to_compile_error().into(),
+		},
+		None => None,
+	};
+
 	derive(ast, |crate_name, name, generics: &syn::Generics, field_names, field_types, fields| {
 		let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
-		let forks = fields.named.iter().find_map(|f| {
-			if f.attrs.iter().any(|attr| attr.path.is_ident(ATTRIBUTE_NAME)) {
+		let forks_field = fields.named.iter()
+			.find(|f| f.attrs.iter().any(|attr| attr.path.is_ident(ATTRIBUTE_NAME)));
+		let forks = forks_field
+			.map(|f| {
 				let typ = &f.ty;
-				Some(quote! { #typ })
-			} else {
-				None
-			}
-		}).unwrap_or_else(|| quote! { #crate_name::NoExtension });
+				quote! { #typ }
+			})
+			.unwrap_or_else(|| quote! { #crate_name::NoExtension });
+		let validate_forks = forks_field.and_then(|f| f.ident.as_ref()).map(|field| quote! {
+			#crate_name::Extension::validate(&self.#field)?;
+		});
+		let validate_self = validate.as_ref().map(|function| quote! {
+			#function(self)?;
+		});
 
 		quote! {
 			impl #impl_generics #crate_name::Extension for #name #ty_generics #where_clause {
@@ -59,6 +75,21 @@ pub fn extension_derive(ast: &DeriveInput) -> proc_macro::TokenStream {
 						_ => self,
 					}
 				}
+
+				fn get_any_mut(&mut self, t: std::any::TypeId) -> &mut dyn std::any::Any {
+					use std::any::{Any, TypeId};
+
+					match t {
+						#( x if x == TypeId::of::<#field_types>() => &mut self.#field_names ),*,
+						_ => self,
+					}
+				}
+
+				fn validate(&self) -> std::result::Result<(), std::string::String> {
+					#validate_self
+					#validate_forks
+					std::result::Result::Ok(())
+				}
 			}
 		}
 	})
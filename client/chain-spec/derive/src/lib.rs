@@ -28,7 +28,7 @@ pub fn group_derive(input: TokenStream) -> TokenStream {
 	}
 }
 
-#[proc_macro_derive(ChainSpecExtension, attributes(forks))]
+#[proc_macro_derive(ChainSpecExtension, attributes(forks, validate))]
 pub fn extensions_derive(input: TokenStream) -> TokenStream {
 	match syn::parse(input) {
 		Ok(ast) => impls::extension_derive(&ast),
@@ -23,7 +23,7 @@ use serde::{Serialize, Deserialize};
 use sp_core::storage::{StorageKey, StorageData, ChildInfo, Storage, StorageChild};
 use sp_runtime::BuildStorage;
 use serde_json as json;
-use crate::{RuntimeGenesis, ChainType, extension::GetExtension, Properties};
+use crate::{RuntimeGenesis, ChainType, extension::{Extension, GetExtension}, Properties};
 use sc_network::config::MultiaddrWithPeerId;
 use sc_telemetry::TelemetryEndpoints;
 
@@ -141,7 +141,6 @@ enum Genesis<G> {
 /// A configuration of a client. Does not include runtime storage initialization.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
-#[serde(deny_unknown_fields)]
 struct ClientSpec<E> {
 	name: String,
 	id: String,
@@ -153,10 +152,44 @@ struct ClientSpec<E> {
 	properties: Option<Properties>,
 	#[serde(flatten)]
 	extensions: E,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	light_sync_state: Option<LightSyncState>,
 	// Never used, left only for backward compatibility.
 	consensus_engine: (),
 	#[serde(skip_serializing)]
 	genesis: serde::de::IgnoredAny,
+	/// Fields unknown to this node, e.g. the extensions of other nodes, kept when serializing.
+	#[serde(flatten)]
+	unknown_fields: json::Map<String, json::Value>,
+}
+
+impl<E: Extension> ClientSpec<E> {
+	/// Validates the extensions, and removes their fields from the unknown fields.
+	fn check_extensions(mut self) -> Result<Self, String> {
+		self.extensions.validate().map_err(|e| format!("Invalid chain spec extensions: {}", e))?;
+		// The flattened maps of the extensions, e.g. the base of `Forks`, don't take the fields
+		// they deserialize, which are then deserialized in the unknown fields too.
+		let extensions = json::to_value(&self.extensions)
+			.map_err(|e| format!("Error serializing the chain spec extensions: {}", e))?;
+		if let json::Value::Object(extensions) = extensions {
+			self.unknown_fields.retain(|field, _| !extensions.contains_key(field));
+		}
+		Ok(self)
+	}
+}
+
+/// State of the chain at a finalized block, from which light clients can start syncing instead
+/// of starting from genesis.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct LightSyncState {
+	/// SCALE encoded header of the finalized block.
+	pub finalized_block_header: StorageData,
+	/// SCALE encoded BABE epoch changes at that block.
+	pub babe_epoch_changes: StorageData,
+	/// SCALE encoded GRANDPA authority set at that block.
+	pub grandpa_authority_set: StorageData,
 }
 
 /// A type denoting empty extensions.
@@ -222,6 +255,21 @@ impl<G, E> ChainSpec<G, E> {
 		&self.client_spec.extensions
 	}
 
+	/// Returns a mutable reference to defined chain spec extensions.
+	pub fn extensions_mut(&mut self) -> &mut E {
+		&mut self.client_spec.extensions
+	}
+
+	/// State light clients can start syncing from, if any.
+	pub fn light_sync_state(&self) -> Option<&LightSyncState> {
+		self.client_spec.light_sync_state.as_ref()
+	}
+
+	/// Set the state light clients can start syncing from.
+	pub fn set_light_sync_state(&mut self, light_sync_state: LightSyncState) {
+		self.client_spec.light_sync_state = Some(light_sync_state);
+	}
+
 	/// Create hardcoded spec.
 	pub fn from_genesis<F: Fn() -> G + 'static + Send + Sync>(
 		name: &str,
@@ -243,8 +291,10 @@ impl<G, E> ChainSpec<G, E> {
 			protocol_id: protocol_id.map(str::to_owned),
 			properties,
 			extensions,
+			light_sync_state: None,
 			consensus_engine: (),
 			genesis: Default::default(),
+			unknown_fields: Default::default(),
 		};
 
 		ChainSpec {
@@ -259,12 +309,15 @@ impl<G, E> ChainSpec<G, E> {
 	}
 }
 
-impl<G, E: serde::de::DeserializeOwned> ChainSpec<G, E> {
+impl<G, E: Extension> ChainSpec<G, E> {
 	/// Parse json content into a `ChainSpec`
+	///
+	/// Fails if the extensions aren't valid. The unknown fields are kept.
 	pub fn from_json_bytes(json: impl Into<Cow<'static, [u8]>>) -> Result<Self, String> {
 		let json = json.into();
-		let client_spec = json::from_slice(json.as_ref())
-			.map_err(|e| format!("Error parsing spec file: {}", e))?;
+		let client_spec = json::from_slice::<ClientSpec<E>>(json.as_ref())
+			.map_err(|e| format!("Error parsing spec file: {}", e))?
+			.check_extensions()?;
 		Ok(ChainSpec {
 			client_spec,
 			genesis: GenesisSource::Binary(json),
@@ -272,11 +325,14 @@ impl<G, E: serde::de::DeserializeOwned> ChainSpec<G, E> {
 	}
 
 	/// Parse json file into a `ChainSpec`
+	///
+	/// Fails if the extensions aren't valid. The unknown fields are kept.
 	pub fn from_json_file(path: PathBuf) -> Result<Self, String> {
 		let file = File::open(&path)
 			.map_err(|e| format!("Error opening spec file: {}", e))?;
-		let client_spec = json::from_reader(file)
-			.map_err(|e| format!("Error parsing spec file: {}", e))?;
+		let client_spec = json::from_reader::<_, ClientSpec<E>>(file)
+			.map_err(|e| format!("Error parsing spec file: {}", e))?
+			.check_extensions()?;
 		Ok(ChainSpec {
 			client_spec,
 			genesis: GenesisSource::File(path),
@@ -284,6 +340,49 @@ impl<G, E: serde::de::DeserializeOwned> ChainSpec<G, E> {
 	}
 }
 
+impl<G: RuntimeGenesis, E> ChainSpec<G, E> {
+	/// Deep-merge the JSON `patch` into the runtime genesis config.
+	///
+	/// The patch follows the JSON merge patch semantics (RFC 7396): its objects are merged into
+	/// the objects of the config, its other values replace the ones of the config, and its `null`
+	/// values remove them. The genesis of raw specs can't be patched.
+	pub fn patch_genesis(&mut self, patch: json::Value) -> Result<(), String> {
+		let mut config = match self.genesis.resolve()? {
+			Genesis::Runtime(config) => json::to_value(config)
+				.map_err(|e| format!("Error serializing the genesis config: {}", e))?,
+			Genesis::Raw(_) => return Err("The genesis of a raw chain spec can't be patched".into()),
+		};
+		merge_patch(&mut config, patch);
+
+		let config: G = json::from_value(config)
+			.map_err(|e| format!("Invalid patched genesis config: {}", e))?;
+		let genesis = json::to_vec(&json::json!({ "genesis": Genesis::Runtime(config) }))
+			.map_err(|e| format!("Error serializing the patched genesis config: {}", e))?;
+		self.genesis = GenesisSource::Binary(genesis.into());
+		Ok(())
+	}
+}
+
+/// Applies the JSON merge `patch` to `value`.
+fn merge_patch(value: &mut json::Value, patch: json::Value) {
+	match patch {
+		json::Value::Object(patch) => {
+			if !value.is_object() {
+				*value = json::Value::Object(Default::default());
+			}
+			let object = value.as_object_mut().expect("`value` was just made an object; qed");
+			for (key, patch) in patch {
+				if patch.is_null() {
+					object.remove(&key);
+				} else {
+					merge_patch(object.entry(key).or_insert(json::Value::Null), patch);
+				}
+			}
+		},
+		patch => *value = patch,
+	}
+}
+
 impl<G: RuntimeGenesis, E: serde::Serialize + Clone + 'static> ChainSpec<G, E> {
 	/// Dump to json string.
 	pub fn as_json(&self, raw: bool) -> Result<String, String> {
@@ -325,7 +424,7 @@ impl<G: RuntimeGenesis, E: serde::Serialize + Clone + 'static> ChainSpec<G, E> {
 impl<G, E> crate::ChainSpec for ChainSpec<G, E>
 where
 	G: RuntimeGenesis + 'static,
-	E: GetExtension + serde::Serialize + Clone + Send + 'static,
+	E: GetExtension + serde::Serialize + Clone + Send + Sync + 'static,
 {
 	fn boot_nodes(&self) -> &[MultiaddrWithPeerId] {
 		ChainSpec::boot_nodes(self)
@@ -363,6 +462,10 @@ where
 		ChainSpec::extensions(self) as &dyn GetExtension
 	}
 
+	fn extensions_mut(&mut self) -> &mut dyn GetExtension {
+		ChainSpec::extensions_mut(self) as &mut dyn GetExtension
+	}
+
 	fn as_json(&self, raw: bool) -> Result<String, String> {
 		ChainSpec::as_json(self, raw)
 	}
@@ -378,11 +481,26 @@ where
 	fn set_storage(&mut self, storage: Storage) {
 		self.genesis = GenesisSource::Storage(storage);
 	}
+
+	fn light_sync_state(&self) -> Option<&LightSyncState> {
+		ChainSpec::light_sync_state(self)
+	}
+
+	fn set_light_sync_state(&mut self, light_sync_state: LightSyncState) {
+		ChainSpec::set_light_sync_state(self, light_sync_state)
+	}
+
+	fn patch_genesis(&mut self, patch: json::Value) -> Result<(), String> {
+		ChainSpec::patch_genesis(self, patch)
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use sc_chain_spec_derive::ChainSpecExtension;
+	// Make the proc macro work for tests.
+	use crate as sc_chain_spec;
 
 	#[derive(Debug, Serialize, Deserialize)]
 	struct Genesis(HashMap<String, String>);
@@ -414,12 +532,22 @@ mod tests {
 		assert_eq!(spec2.chain_type(), ChainType::Live)
 	}
 
-	#[derive(Debug, Serialize, Deserialize)]
+	#[derive(Debug, Clone, Serialize, Deserialize, ChainSpecExtension)]
 	#[serde(rename_all = "camelCase")]
+	#[validate(Extension1::check)]
 	struct Extension1 {
 		my_property: String,
 	}
 
+	impl Extension1 {
+		fn check(&self) -> Result<(), String> {
+			if self.my_property.is_empty() {
+				return Err("`myProperty` can't be empty".into())
+			}
+			Ok(())
+		}
+	}
+
 	type TestSpec2 = ChainSpec<Genesis, Extension1>;
 
 	#[test]
@@ -430,4 +558,92 @@ mod tests {
 
 		assert_eq!(spec.extensions().my_property, "Test Extension");
 	}
+
+	#[test]
+	fn should_validate_the_extensions() {
+		let json = String::from_utf8(include_bytes!("../res/chain_spec2.json").to_vec()).unwrap()
+			.replace("\"Test Extension\"", "\"\"");
+
+		let err = TestSpec2::from_json_bytes(Cow::Owned(json.into_bytes())).err().unwrap();
+		assert!(err.contains("`myProperty` can't be empty"));
+	}
+
+	#[test]
+	fn should_keep_the_unknown_fields() {
+		let spec = TestSpec::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec2.json").to_vec()
+		)).unwrap();
+		let json: json::Value = json::from_str(&spec.as_json(false).unwrap()).unwrap();
+		assert_eq!(json["myProperty"], "Test Extension");
+
+		// the fields of the extensions aren't unknown.
+		let spec = TestSpec2::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec2.json").to_vec()
+		)).unwrap();
+		assert!(spec.client_spec.unknown_fields.is_empty());
+	}
+
+	#[test]
+	fn should_change_the_extensions() {
+		let mut spec: Box<dyn crate::ChainSpec> = Box::new(TestSpec2::from_json_bytes(Cow::Owned(
+			include_bytes!("../res/chain_spec2.json").to_vec()
+		)).unwrap());
+
+		crate::get_extension_mut::<String>(spec.extensions_mut()).unwrap().push_str(" 2");
+
+		let json: json::Value = json::from_str(&spec.as_json(false).unwrap()).unwrap();
+		assert_eq!(json["myProperty"], "Test Extension 2");
+	}
+
+	#[test]
+	fn should_keep_the_light_sync_state() {
+		let mut spec = TestSpec::from_json_file(PathBuf::from("./res/chain_spec.json")).unwrap();
+		assert_eq!(spec.light_sync_state(), None);
+
+		let state = LightSyncState {
+			finalized_block_header: StorageData(vec![1]),
+			babe_epoch_changes: StorageData(vec![2]),
+			grandpa_authority_set: StorageData(vec![3]),
+		};
+		spec.set_light_sync_state(state.clone());
+		let json = spec.as_json(true).unwrap();
+
+		let spec = TestSpec::from_json_bytes(Cow::Owned(json.into_bytes())).unwrap();
+		assert_eq!(spec.light_sync_state(), Some(&state));
+	}
+
+	#[test]
+	fn should_patch_the_genesis_config() {
+		let mut spec = TestSpec::from_genesis(
+			"Test",
+			"test",
+			ChainType::Local,
+			|| Genesis(vec![
+				("alice".to_owned(), "1".to_owned()),
+				("bob".to_owned(), "2".to_owned()),
+			].into_iter().collect()),
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+		);
+		spec.patch_genesis(json::json!({ "bob": null, "charlie": "3" })).unwrap();
+
+		let storage = spec.build_storage().unwrap();
+		assert_eq!(storage.top.get(&b"alice"[..]), Some(&b"1".to_vec()));
+		assert_eq!(storage.top.get(&b"bob"[..]), None);
+		assert_eq!(storage.top.get(&b"charlie"[..]), Some(&b"3".to_vec()));
+
+		let mut raw = TestSpec::from_json_file(PathBuf::from("./res/chain_spec.json")).unwrap();
+		assert!(raw.patch_genesis(json::json!({})).is_err());
+	}
+
+	#[test]
+	fn merge_patch_merges_objects_recursively() {
+		let mut value = json::json!({ "a": { "b": 1, "c": [1, 2] }, "d": true });
+		merge_patch(&mut value, json::json!({ "a": { "b": null, "c": [3], "e": "f" }, "g": 1 }));
+
+		assert_eq!(value, json::json!({ "a": { "c": [3], "e": "f" }, "d": true, "g": 1 }));
+	}
 }
@@ -124,6 +124,17 @@ pub trait Extension: Serialize + DeserializeOwned + Clone {
 	fn get<T: 'static>(&self) -> Option<&T>;
 	/// Get an extension of specific type as refernce to `Any`
 	fn get_any(&self, t: TypeId) -> &dyn Any;
+	/// Get an extension of specific type as mutable reference to `Any`
+	fn get_any_mut(&mut self, t: TypeId) -> &mut dyn Any;
+
+	/// Check that the extensions are valid, which is done when loading a chain spec.
+	///
+	/// By default the extensions are always valid. The `ChainSpecExtension` macro implements it
+	/// with the function given in the `#[validate(function)]` attribute, which takes `&Self`
+	/// and returns a `Result<(), String>`, and validates the `#[forks]` field.
+	fn validate(&self) -> Result<(), String> {
+		Ok(())
+	}
 
 	/// Get forkable extensions of specific type.
 	fn forks<BlockNumber, T>(&self) -> Option<Forks<BlockNumber, T>> where
@@ -142,6 +153,7 @@ impl Extension for crate::NoExtension {
 
 	fn get<T: 'static>(&self) -> Option<&T> { None }
 	fn get_any(&self, _t: TypeId) -> &dyn Any { self }
+	fn get_any_mut(&mut self, _t: TypeId) -> &mut dyn Any { self }
 }
 
 pub trait IsForks {
@@ -243,6 +255,32 @@ impl<B, E> Extension for Forks<B, E> where
 		}
 	}
 
+	fn get_any_mut(&mut self, t: TypeId) -> &mut dyn Any {
+		match t {
+			x if x == TypeId::of::<E>() => &mut self.base,
+			_ => self.base.get_any_mut(t),
+		}
+	}
+
+	fn validate(&self) -> Result<(), String> {
+		self.base.validate()?;
+
+		let mut params = self.base.clone().to_fork();
+		for (block, fork) in &self.forks {
+			params.combine_with(fork.clone());
+			params.clone()
+				.to_base()
+				.expect("We start from the `base` object, so it's always fully initialized; qed")
+				.validate()
+				.map_err(|e| format!(
+					"{} (at the fork of block {})",
+					e,
+					serde_json::to_string(block).unwrap_or_default(),
+				))?;
+		}
+		Ok(())
+	}
+
 	fn forks<BlockNumber, T>(&self) -> Option<Forks<BlockNumber, T>> where
 		BlockNumber: Ord + Clone + 'static,
 		T: Group + 'static,
@@ -262,12 +300,18 @@ impl<B, E> Extension for Forks<B, E> where
 pub trait GetExtension {
 	/// Get an extension of specific type.
 	fn get_any(&self, t: TypeId) -> &dyn Any;
+	/// Get an extension of specific type, mutably.
+	fn get_any_mut(&mut self, t: TypeId) -> &mut dyn Any;
 }
 
 impl <E: Extension> GetExtension for E {
 	fn get_any(&self, t: TypeId) -> &dyn Any {
 		Extension::get_any(self, t)
 	}
+
+	fn get_any_mut(&mut self, t: TypeId) -> &mut dyn Any {
+		Extension::get_any_mut(self, t)
+	}
 }
 
 /// Helper function that queries an extension by type from `GetExtension`
@@ -276,6 +320,12 @@ pub fn get_extension<T: 'static>(e: &dyn GetExtension) -> Option<&T> {
 	Any::downcast_ref(GetExtension::get_any(e, TypeId::of::<T>()))
 }
 
+/// Helper function that queries a mutable extension by type from `GetExtension`
+/// trait object, e.g. to change it before writing a chain spec.
+pub fn get_extension_mut<T: 'static>(e: &mut dyn GetExtension) -> Option<&mut T> {
+	Any::downcast_mut(GetExtension::get_any_mut(e, TypeId::of::<T>()))
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
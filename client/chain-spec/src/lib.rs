@@ -106,12 +106,17 @@
 //!
 //! pub type MyChainSpec<G> = GenericChainSpec<G, Extension>;
 //! ```
+//!
+//! The extensions are validated when loading a chain spec, with the function given in the
+//! `#[validate(function)]` attribute of `ChainSpecExtension`, and can be changed with
+//! [`get_extension_mut`](./fn.get_extension_mut.html) before writing a chain spec. The fields
+//! unknown to the node, e.g. the extensions of other nodes, are kept when writing it.
 
 mod chain_spec;
 mod extension;
 
-pub use chain_spec::{ChainSpec as GenericChainSpec, NoExtension};
-pub use extension::{Group, Fork, Forks, Extension, GetExtension, get_extension};
+pub use chain_spec::{ChainSpec as GenericChainSpec, LightSyncState, NoExtension};
+pub use extension::{Group, Fork, Forks, Extension, GetExtension, get_extension, get_extension_mut};
 pub use sc_chain_spec_derive::{ChainSpecExtension, ChainSpecGroup};
 pub use sp_chain_spec::{Properties, ChainType};
 
@@ -126,7 +131,7 @@ pub trait RuntimeGenesis: Serialize + DeserializeOwned + BuildStorage {}
 impl<T: Serialize + DeserializeOwned + BuildStorage> RuntimeGenesis for T {}
 
 /// Common interface of a chain specification.
-pub trait ChainSpec: BuildStorage + Send {
+pub trait ChainSpec: BuildStorage + Send + Sync {
 	/// Spec name.
 	fn name(&self) -> &str;
 	/// Spec id.
@@ -145,6 +150,8 @@ pub trait ChainSpec: BuildStorage + Send {
 	fn properties(&self) -> Properties;
 	/// Returns a reference to defined chain spec extensions.
 	fn extensions(&self) -> &dyn GetExtension;
+	/// Returns the extensions mutably, e.g. to change them before writing the chain spec.
+	fn extensions_mut(&mut self) -> &mut dyn GetExtension;
 	/// Add a bootnode to the list.
 	fn add_boot_node(&mut self, addr: MultiaddrWithPeerId);
 	/// Return spec as JSON.
@@ -157,4 +164,10 @@ pub trait ChainSpec: BuildStorage + Send {
 	///
 	/// This will be used as storage at genesis.
 	fn set_storage(&mut self, storage: Storage);
+	/// State light clients can start syncing from, if any.
+	fn light_sync_state(&self) -> Option<&LightSyncState>;
+	/// Set the state light clients can start syncing from.
+	fn set_light_sync_state(&mut self, light_sync_state: LightSyncState);
+	/// Deep-merge the JSON `patch` into the runtime genesis config.
+	fn patch_genesis(&mut self, patch: serde_json::Value) -> Result<(), String>;
 }
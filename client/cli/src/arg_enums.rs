@@ -123,18 +123,30 @@ impl ExecutionStrategy {
 	}
 }
 
-arg_enum! {
-	/// Available RPC methods.
-	#[allow(missing_docs)]
-	#[derive(Debug, Copy, Clone, PartialEq)]
-	pub enum RpcMethods {
-		// Expose every RPC method only when RPC is listening on `localhost`,
-		// otherwise serve only safe RPC methods.
-		Auto,
-		// Allow only a safe subset of RPC methods.
-		Safe,
-		// Expose every RPC method (even potentially unsafe ones).
-		Unsafe,
+/// Available RPC methods.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RpcMethods {
+	/// Expose every RPC method only when RPC is listening on `localhost`,
+	/// otherwise serve only safe RPC methods.
+	Auto,
+	/// Allow only a safe subset of RPC methods.
+	Safe,
+	/// Expose every RPC method (even potentially unsafe ones).
+	Unsafe,
+	/// Expose the methods of the given groups.
+	Groups(sc_service::config::RpcMethodGroups),
+}
+
+impl std::str::FromStr for RpcMethods {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"auto" => Ok(RpcMethods::Auto),
+			"safe" => Ok(RpcMethods::Safe),
+			"unsafe" => Ok(RpcMethods::Unsafe),
+			groups => groups.parse().map(RpcMethods::Groups),
+		}
 	}
 }
 
@@ -144,6 +156,7 @@ impl Into<sc_service::config::RpcMethods> for RpcMethods {
 			RpcMethods::Auto => sc_service::config::RpcMethods::Auto,
 			RpcMethods::Safe => sc_service::config::RpcMethods::Safe,
 			RpcMethods::Unsafe => sc_service::config::RpcMethods::Unsafe,
+			RpcMethods::Groups(groups) => sc_service::config::RpcMethods::Groups(groups),
 		}
 	}
 }
@@ -162,6 +175,52 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// Syncing mode.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum SyncMode {
+		// Download and import every block since genesis.
+		Full,
+		// Download a proof of finality of the latest finalized block along with its state,
+		// then import the blocks that follow it. Only used if the database is empty.
+		Warp,
+		// Download the headers of every block, then the state of the latest finalized block,
+		// and import the blocks that follow it. Only used if the database is empty.
+		Fast,
+	}
+}
+
+impl Into<sc_network::config::SyncMode> for SyncMode {
+	fn into(self) -> sc_network::config::SyncMode {
+		match self {
+			SyncMode::Full => sc_network::config::SyncMode::Full,
+			SyncMode::Warp => sc_network::config::SyncMode::Warp,
+			SyncMode::Fast => sc_network::config::SyncMode::Fast,
+		}
+	}
+}
+
+arg_enum! {
+	/// Format of the output of the informant.
+	#[allow(missing_docs)]
+	#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+	pub enum OutputFormat {
+		// Human-readable status lines.
+		Text,
+		// One JSON object per line on the standard output.
+		Json,
+	}
+}
+
+impl Into<sc_informant::OutputFormat> for OutputFormat {
+	fn into(self) -> sc_informant::OutputFormat {
+		match self {
+			OutputFormat::Text => sc_informant::OutputFormat::Coloured,
+			OutputFormat::Json => sc_informant::OutputFormat::Json,
+		}
+	}
+}
 
 arg_enum! {
 	/// Whether off-chain workers are enabled.
@@ -174,6 +233,27 @@ arg_enum! {
 	}
 }
 
+arg_enum! {
+	/// The cryptography scheme of a key.
+	#[allow(missing_docs)]
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum CryptoScheme {
+		Ed25519,
+		Sr25519,
+		Ecdsa,
+	}
+}
+
+arg_enum! {
+	/// The output format of the key commands.
+	#[allow(missing_docs)]
+	#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+	pub enum OutputType {
+		Json,
+		Text,
+	}
+}
+
 /// Default value for the `--execution-syncing` parameter.
 pub const DEFAULT_EXECUTION_SYNCING: ExecutionStrategy = ExecutionStrategy::NativeElseWasm;
 /// Default value for the `--execution-import-block` parameter.
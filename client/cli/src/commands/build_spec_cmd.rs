@@ -24,7 +24,7 @@ use log::info;
 use sc_network::config::build_multiaddr;
 use sc_service::{config::MultiaddrWithPeerId, Configuration};
 use structopt::StructOpt;
-use std::io::Write;
+use std::{fs, io::Write, path::PathBuf};
 
 /// The `build-spec` command used to build a specification.
 #[derive(Debug, StructOpt, Clone)]
@@ -40,6 +40,14 @@ pub struct BuildSpecCmd {
 	#[structopt(long = "disable-default-bootnode")]
 	pub disable_default_bootnode: bool,
 
+	/// JSON file deep-merged into the genesis config before the specification is built.
+	///
+	/// Objects of the file are merged into the ones of the genesis config, other values replace
+	/// the ones of the genesis config and `null` values remove them. Can be passed several times,
+	/// the files are then applied in order.
+	#[structopt(long = "patch", value_name = "PATH", parse(from_os_str))]
+	pub patch: Vec<PathBuf>,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -66,6 +74,14 @@ impl BuildSpecCmd {
 			spec.add_boot_node(addr)
 		}
 
+		for path in &self.patch {
+			let file = fs::File::open(path)
+				.map_err(|e| format!("Error opening patch file {}: {}", path.display(), e))?;
+			let patch = serde_json::from_reader(file)
+				.map_err(|e| format!("Error parsing patch file {}: {}", path.display(), e))?;
+			spec.patch_genesis(patch)?;
+		}
+
 		let json = sc_service::chain_ops::build_spec(&*spec, raw_output)?;
 		if std::io::stdout().write_all(json.as_bytes()).is_err() {
 			let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
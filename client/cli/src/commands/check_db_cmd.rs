@@ -0,0 +1,115 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use log::info;
+use sc_client_db::{check_database, Backend};
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor, SaturatedConversion};
+use structopt::StructOpt;
+
+/// Number of blocks between two reports of the progress of the check.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// The `db check` command used to check the integrity of the database of a full node.
+///
+/// The canonical chain is walked from the best block down to the genesis block, checking the
+/// headers, bodies, justifications and states of the blocks, and then the leaves of the tree of
+/// blocks. With `--truncate`, the chain is reverted to the last block before the first
+/// inconsistent one, along with the auxiliary data of the consensus engines, so that the node can
+/// sync again from there.
+#[derive(Debug, StructOpt, Clone)]
+pub struct CheckDbCmd {
+	/// Revert the chain to the last consistent block, including finalized blocks.
+	///
+	/// The chain is only reverted as far as the pruning of the state allows.
+	#[structopt(long)]
+	pub truncate: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl CheckDbCmd {
+	/// Run the `db check` command
+	pub fn run<B, BC, BB>(&self, config: Configuration, builder: B) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT,
+	{
+		let backend = Backend::<BB>::new(sc_service::database_settings(&config))?;
+
+		let check = check_database(&backend, |number: NumberFor<BB>| {
+			let number = number.saturated_into::<u64>();
+			if number % PROGRESS_INTERVAL == 0 {
+				info!("Checking block #{}", number);
+			}
+		})?;
+		for inconsistency in &check.inconsistencies {
+			println!("{}", inconsistency);
+		}
+		if check.inconsistencies.is_empty() {
+			println!("No inconsistency found up to the best block #{}", check.best_number);
+			return Ok(())
+		}
+
+		let target = match check.last_consistent_block() {
+			Some(target) if self.truncate => target,
+			Some(target) => return Err(error::Error::Input(format!(
+				"{} inconsistencies found. Pass `--truncate` to revert the chain to block #{}",
+				check.inconsistencies.len(),
+				target,
+			))),
+			None => return Err(error::Error::Input(format!(
+				"{} inconsistencies found, which reverting the chain doesn't fix",
+				check.inconsistencies.len(),
+			))),
+		};
+		// the database is opened again by the builder, which reverts the auxiliary data of the
+		// consensus engines along with the blocks.
+		drop(backend);
+		builder(config)?.revert_chain(check.best_number - target, true)?;
+		Ok(())
+	}
+}
+
+impl CliConfiguration for CheckDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -0,0 +1,55 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{error, commands::{CheckDbCmd, MigrateDbCmd, StatsDbCmd}};
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{fmt::Debug, str::FromStr};
+use structopt::StructOpt;
+
+/// The `db` command used to manage the database of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub enum DbSubcommand {
+	/// Convert the database to another backend, without resyncing the chain.
+	Migrate(MigrateDbCmd),
+
+	/// Check the integrity of the database, and revert the chain to the last consistent block.
+	Check(CheckDbCmd),
+
+	/// Show the number of keys and the size of the values of the state, per key prefix.
+	Stats(StatsDbCmd),
+}
+
+impl DbSubcommand {
+	/// Run the `db` command
+	pub fn run<B, BC, BB>(&self, config: Configuration, builder: B) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: Debug,
+		<NumberFor<BB> as FromStr>::Err: Debug,
+	{
+		match self {
+			DbSubcommand::Migrate(cmd) => cmd.run(config),
+			DbSubcommand::Check(cmd) => cmd.run(config, builder),
+			DbSubcommand::Stats(cmd) => cmd.run::<BB>(config),
+		}
+	}
+}
@@ -21,7 +21,7 @@ use crate::params::{BlockNumber, DatabaseParams, PruningParams, SharedParams};
 use crate::CliConfiguration;
 use log::info;
 use sc_service::{
-	config::DatabaseConfig, Configuration, ServiceBuilderCommand,
+	chain_ops::BlocksIndex, config::DatabaseConfig, Configuration, ServiceBuilderCommand,
 };
 use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use std::fmt::Debug;
@@ -50,6 +50,9 @@ pub struct ExportBlocksCmd {
 	pub to: Option<BlockNumber>,
 
 	/// Use binary output rather than JSON.
+	///
+	/// When writing to a file, its index is written next to it, with the `.index` extension
+	/// appended. It allows `import-blocks --from` to start reading from the middle of the file.
 	#[structopt(long)]
 	pub binary: bool,
 
@@ -93,9 +96,13 @@ impl ExportBlocksCmd {
 			Some(filename) => Box::new(fs::File::create(filename)?),
 			None => Box::new(io::stdout()),
 		};
+		let index: Option<Box<dyn io::Write>> = match &self.output {
+			Some(filename) if binary => Some(Box::new(fs::File::create(BlocksIndex::path(filename))?)),
+			_ => None,
+		};
 
 		builder(config)?
-			.export_blocks(file, from.into(), to, binary)
+			.export_blocks(file, from.into(), to, binary, index)
 			.await
 			.map_err(Into::into)
 	}
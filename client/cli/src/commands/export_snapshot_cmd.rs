@@ -0,0 +1,119 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	CliConfiguration, error, params::{DatabaseParams, PruningParams, SharedParams, BlockNumberOrHash},
+};
+use codec::Encode;
+use log::info;
+use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::{fmt::Debug, fs, path::{Path, PathBuf}, str::FromStr};
+use structopt::StructOpt;
+
+/// Name of the file holding the manifest of a snapshot.
+pub(crate) const MANIFEST_FILE: &str = "manifest";
+
+/// Compression level of the chunks of a snapshot.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Returns the path of the chunk numbered `index` of the snapshot in the directory `dir`.
+pub(crate) fn chunk_path(dir: &Path, index: usize) -> PathBuf {
+	dir.join(format!("chunk-{:06}.zst", index))
+}
+
+/// The `export-snapshot` command used to export a snapshot of the state of a block, from which
+/// a fresh node can be started with `import-snapshot`.
+///
+/// The snapshot is a directory holding the zstd-compressed chunks of the state, and a manifest
+/// with the header of the block and the hashes of the chunks.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportSnapshotCmd {
+	/// Directory to write the snapshot to, created if missing.
+	#[structopt(value_name = "PATH", parse(from_os_str))]
+	pub output: PathBuf,
+
+	/// Block hash or number of the state to export.
+	///
+	/// Default is the best block.
+	#[structopt(long = "at", value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// Size of the entries of a chunk of the state, in bytes, before compression.
+	#[structopt(long = "chunk-size", value_name = "BYTES", default_value = "16777216")]
+	pub chunk_size: usize,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl ExportSnapshotCmd {
+	/// Run the `export-snapshot` command
+	pub fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		<NumberFor<BB> as FromStr>::Err: std::fmt::Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		let block_id = self.at.as_ref().map(|b| b.parse()).transpose()?;
+		fs::create_dir_all(&self.output)?;
+
+		info!("Exporting a snapshot of the state to {}...", self.output.display());
+		let manifest = builder(config)?.export_snapshot(block_id, self.chunk_size, |index, chunk| {
+			let compressed = zstd::encode_all(&chunk[..], COMPRESSION_LEVEL)?;
+			fs::write(chunk_path(&self.output, index), compressed)?;
+			Ok(())
+		})?;
+		// the manifest is written last, so that an interrupted export can't be imported.
+		fs::write(self.output.join(MANIFEST_FILE), manifest.encode())?;
+
+		println!("Block hash: {}", manifest.block_hash);
+		println!("State root: {}", manifest.state_root);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for ExportSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
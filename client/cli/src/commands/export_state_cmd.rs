@@ -17,12 +17,14 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-	CliConfiguration, error, params::{PruningParams, SharedParams, BlockNumberOrHash},
+	CliConfiguration, error, params::{DatabaseParams, PruningParams, SharedParams, BlockNumberOrHash},
 };
+use codec::Encode;
 use log::info;
 use sc_service::{Configuration, ServiceBuilderCommand};
+use sp_core::storage::Storage;
 use sp_runtime::traits::{Block as BlockT, NumberFor};
-use std::{fmt::Debug, str::FromStr, io::Write};
+use std::{collections::BTreeMap, fmt::Debug, fs, io::{self, Write}, path::PathBuf, str::FromStr};
 use structopt::StructOpt;
 
 /// The `export-state` command used to export the state of a given block into
@@ -33,6 +35,23 @@ pub struct ExportStateCmd {
 	#[structopt(value_name = "HASH or NUMBER")]
 	pub input: Option<BlockNumberOrHash>,
 
+	/// Block hash or number of the state to export.
+	///
+	/// Same as the positional argument. Default is the best block.
+	#[structopt(long = "at", value_name = "HASH or NUMBER", conflicts_with = "input")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// Output file name or stdout if unspecified.
+	#[structopt(long = "output", short = "o", value_name = "PATH", parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	/// Export a binary dump of the state rather than a JSON chain spec.
+	///
+	/// The dump is the SCALE encoding of the key/value pairs of the top trie followed by the
+	/// child tries, each as its storage key and its key/value pairs.
+	#[structopt(long)]
+	pub binary: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -40,6 +59,10 @@ pub struct ExportStateCmd {
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
 }
 
 impl ExportStateCmd {
@@ -59,19 +82,36 @@ impl ExportStateCmd {
 	{
 		info!("Exporting raw state...");
 		let mut input_spec = config.chain_spec.cloned_box();
-		let block_id = self.input.clone().map(|b| b.parse()).transpose()?;
+		let block_id = self.at.as_ref().or(self.input.as_ref()).map(|b| b.parse()).transpose()?;
 		let raw_state = builder(config)?.export_raw_state(block_id)?;
-		input_spec.set_storage(raw_state);
 
-		info!("Generating new chain spec...");
-		let json = sc_service::chain_ops::build_spec(&*input_spec, true)?;
-		if std::io::stdout().write_all(json.as_bytes()).is_err() {
-			let _ = std::io::stderr().write_all(b"Error writing to stdout\n");
+		let output = if self.binary {
+			encode_raw_state(&raw_state)
+		} else {
+			input_spec.set_storage(raw_state);
+
+			info!("Generating new chain spec...");
+			sc_service::chain_ops::build_spec(&*input_spec, true)?.into_bytes()
+		};
+
+		match &self.output {
+			Some(filename) => fs::File::create(filename)?.write_all(&output)?,
+			None => if io::stdout().write_all(&output).is_err() {
+				let _ = io::stderr().write_all(b"Error writing to stdout\n");
+			},
 		}
 		Ok(())
 	}
 }
 
+/// Returns the binary dump of a raw state.
+fn encode_raw_state(state: &Storage) -> Vec<u8> {
+	let children = state.children_default.iter()
+		.map(|(key, child)| (key, &child.data))
+		.collect::<BTreeMap<_, _>>();
+	(&state.top, children).encode()
+}
+
 impl CliConfiguration for ExportStateCmd {
 	fn shared_params(&self) -> &SharedParams {
 		&self.shared_params
@@ -80,4 +120,31 @@ impl CliConfiguration for ExportStateCmd {
 	fn pruning_params(&self) -> Option<&PruningParams> {
 		Some(&self.pruning_params)
 	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Decode;
+	use sp_core::storage::{ChildInfo, StorageChild};
+
+	#[test]
+	fn binary_dump_contains_top_and_child_tries() {
+		let mut state = Storage::default();
+		state.top.insert(b"key".to_vec(), b"value".to_vec());
+		let mut child = StorageChild { child_info: ChildInfo::new_default(b"child"), data: Default::default() };
+		child.data.insert(b"child_key".to_vec(), b"child_value".to_vec());
+		state.children_default.insert(b"child".to_vec(), child);
+
+		let dump = encode_raw_state(&state);
+		type Pairs = Vec<(Vec<u8>, Vec<u8>)>;
+		let (top, children) = <(Pairs, Vec<(Vec<u8>, Pairs)>)>::decode(&mut &dump[..]).unwrap();
+
+		assert_eq!(top, vec![(b"key".to_vec(), b"value".to_vec())]);
+		assert_eq!(children, vec![(b"child".to_vec(), vec![(b"child_key".to_vec(), b"child_value".to_vec())])]);
+	}
 }
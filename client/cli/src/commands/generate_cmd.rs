@@ -0,0 +1,64 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, params::{CryptoSchemeParams, KeyOutputParams, KeyPasswordParams},
+	commands::key_utils::print_from_uri,
+};
+use bip39::{Language, Mnemonic, MnemonicType};
+use structopt::StructOpt;
+
+/// The `key generate` command used to generate a random account.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateCmd {
+	/// The number of words in the phrase to generate. One of 12, 15, 18, 21 and 24.
+	#[structopt(long, short = "w", value_name = "WORDS", default_value = "12")]
+	pub words: usize,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub crypto_scheme_params: CryptoSchemeParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub key_output_params: KeyOutputParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub key_password_params: KeyPasswordParams,
+}
+
+impl GenerateCmd {
+	/// Run the `key generate` command
+	pub fn run(&self) -> error::Result<()> {
+		let words = MnemonicType::for_word_count(self.words)
+			.map_err(|_| "Invalid number of words given for phrase: must be 12/15/18/21/24")?;
+		let mnemonic = Mnemonic::new(words, Language::English);
+		let password = self.key_password_params.read_password()?;
+
+		with_crypto_scheme!(
+			self.crypto_scheme_params.scheme,
+			print_from_uri(
+				mnemonic.phrase(),
+				password.as_deref(),
+				self.key_output_params.network,
+				self.key_output_params.output_type,
+			)
+		)
+	}
+}
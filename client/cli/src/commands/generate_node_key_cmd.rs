@@ -0,0 +1,78 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use sc_network::config::{identity::ed25519, NodeKeyConfig, PublicKey, Secret};
+use sp_core::hexdisplay::HexDisplay;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// The `key generate-node-key` command used to generate a libp2p node key.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateNodeKeyCmd {
+	/// File to save the secret key to, as read by `--node-key-file`.
+	///
+	/// By default the hex-encoded secret key is printed on stdout, as read by `--node-key`, and
+	/// the peer ID on stderr.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub file: Option<PathBuf>,
+}
+
+impl GenerateNodeKeyCmd {
+	/// Run the `key generate-node-key` command
+	pub fn run(&self) -> error::Result<()> {
+		match &self.file {
+			Some(file) => {
+				if file.exists() {
+					return Err(format!("The file {} already exists", file.display()).into())
+				}
+				// the missing file is created with the generated key, readable only by the user.
+				let keypair = NodeKeyConfig::Ed25519(Secret::File(file.clone())).into_keypair()?;
+				println!("{}", keypair.public().into_peer_id());
+			},
+			None => {
+				let keypair = ed25519::Keypair::generate();
+				println!("{}", HexDisplay::from(&keypair.secret().as_ref()));
+				eprintln!("{}", PublicKey::Ed25519(keypair.public()).into_peer_id());
+			},
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_network::config::identity::Keypair;
+
+	#[test]
+	fn generated_key_file_is_read_by_the_node() {
+		let dir = tempfile::tempdir().unwrap();
+		let file = dir.path().join("node-key");
+		let cmd = GenerateNodeKeyCmd { file: Some(file.clone()) };
+
+		cmd.run().unwrap();
+		let key = std::fs::read(&file).unwrap();
+		let keypair = NodeKeyConfig::Ed25519(Secret::File(file)).into_keypair().unwrap();
+		match keypair {
+			Keypair::Ed25519(keypair) => assert_eq!(keypair.secret().as_ref(), &key[..]),
+			_ => panic!("The node key is an ed25519 key"),
+		}
+		assert!(cmd.run().is_err());
+	}
+}
@@ -17,24 +17,43 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::error;
+use crate::params::BlockNumber;
 use crate::params::ImportParams;
 use crate::params::SharedParams;
 use crate::CliConfiguration;
-use sc_service::{Configuration, ServiceBuilderCommand};
-use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use codec::{Decode, Encode};
+use log::info;
+use sc_service::{chain_ops::BlocksIndex, Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion};
 use std::fmt::Debug;
 use std::fs;
-use std::io::{self, Read, Seek};
-use std::path::PathBuf;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 /// The `import-blocks` command used to import blocks.
 #[derive(Debug, StructOpt, Clone)]
 pub struct ImportBlocksCmd {
 	/// Input file or stdin if unspecified.
+	///
+	/// The blocks already in the chain are skipped, so an interrupted import can be resumed by
+	/// running the same command again.
 	#[structopt(parse(from_os_str))]
 	pub input: Option<PathBuf>,
 
+	/// Specify the number of the first block to import.
+	///
+	/// The blocks before it are skipped. When a binary input file has an index, the reading
+	/// starts from the last indexed block before it.
+	#[structopt(long = "from", value_name = "BLOCK")]
+	pub from: Option<BlockNumber>,
+
+	/// Specify the number of the last block to import.
+	///
+	/// Default is the last block of the input.
+	#[structopt(long = "to", value_name = "BLOCK")]
+	pub to: Option<BlockNumber>,
+
 	/// The default number of 64KB pages to ever allocate for Wasm execution.
 	///
 	/// Don't alter this unless you know what you're doing.
@@ -54,11 +73,6 @@ pub struct ImportBlocksCmd {
 	pub import_params: ImportParams,
 }
 
-/// Internal trait used to cast to a dynamic type that implements Read and Seek.
-trait ReadPlusSeek: Read + Seek {}
-
-impl<T: Read + Seek> ReadPlusSeek for T {}
-
 impl ImportBlocksCmd {
 	/// Run the import-blocks command
 	pub async fn run<B, BC, BB>(
@@ -73,22 +87,47 @@ impl ImportBlocksCmd {
 		<<<BB as BlockT>::Header as HeaderT>::Number as std::str::FromStr>::Err: std::fmt::Debug,
 		<BB as BlockT>::Hash: std::str::FromStr,
 	{
-		let file: Box<dyn ReadPlusSeek + Send> = match &self.input {
-			Some(filename) => Box::new(fs::File::open(filename)?),
-			None => {
-				let mut buffer = Vec::new();
-				io::stdin().read_to_end(&mut buffer)?;
-				Box::new(io::Cursor::new(buffer))
-			}
+		let from = self.from.as_ref().map(|f| f.parse::<NumberFor<BB>>()).transpose()?;
+		let to = self.to.as_ref().map(|t| t.parse::<NumberFor<BB>>()).transpose()?;
+
+		let file: Box<dyn Read + Send> = match (&self.input, from) {
+			(Some(filename), Some(from)) if self.binary => {
+				open_indexed(filename, from.saturated_into::<u64>())?
+			},
+			(Some(filename), _) => Box::new(fs::File::open(filename)?),
+			// The input is read as it comes, so that the blocks can be piped from another node.
+			(None, _) => Box::new(io::stdin()),
 		};
 
 		builder(config)?
-			.import_blocks(file, false, self.binary)
+			.import_blocks(file, false, self.binary, from, to)
 			.await
 			.map_err(Into::into)
 	}
 }
 
+/// Opens the binary export at `path`, positioned at the last indexed block up to `from` if the
+/// export has an index.
+fn open_indexed(path: &Path, from: u64) -> error::Result<Box<dyn Read + Send>> {
+	let mut file = fs::File::open(path)?;
+	let index_path = BlocksIndex::path(path);
+	if !index_path.exists() {
+		return Ok(Box::new(file))
+	}
+
+	let index = BlocksIndex::decode(&mut &fs::read(&index_path)?[..])
+		.map_err(|e| format!("Invalid index {}: {}", index_path.display(), e))?;
+	match index.seek(from) {
+		Some((offset, count)) => {
+			info!("Reading {} blocks from the offset {} given by the index", count, offset);
+			file.seek(SeekFrom::Start(offset))?;
+			// The number of blocks to read comes first in binary exports.
+			Ok(Box::new(io::Cursor::new(count.encode()).chain(file)))
+		},
+		None => Ok(Box::new(file)),
+	}
+}
+
 impl CliConfiguration for ImportBlocksCmd {
 	fn shared_params(&self) -> &SharedParams {
 		&self.shared_params
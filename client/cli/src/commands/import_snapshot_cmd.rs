@@ -0,0 +1,101 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{ImportParams, SharedParams};
+use crate::CliConfiguration;
+use super::export_snapshot_cmd::{chunk_path, MANIFEST_FILE};
+use codec::Decode;
+use log::info;
+use sc_service::{chain_ops::SnapshotManifest, Configuration, ServiceBuilderCommand};
+use sp_runtime::traits::Block as BlockT;
+use std::{fmt::Debug, fs, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+
+/// The `import-snapshot` command used to start a fresh node from a snapshot of the state written
+/// by `export-snapshot`.
+///
+/// The chunks are checked against the manifest, and the state against the state root of the
+/// header of the manifest. The block itself isn't verified by the consensus engine, so the
+/// snapshot must come from a trusted source, or its block hash be given with `--expected-hash`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ImportSnapshotCmd {
+	/// Directory of the snapshot.
+	#[structopt(value_name = "PATH", parse(from_os_str))]
+	pub input: PathBuf,
+
+	/// Hash of the block the snapshot must be of.
+	#[structopt(long = "expected-hash", value_name = "HASH")]
+	pub expected_hash: Option<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub import_params: ImportParams,
+}
+
+impl ImportSnapshotCmd {
+	/// Run the `import-snapshot` command
+	pub async fn run<B, BC, BB>(
+		&self,
+		config: Configuration,
+		builder: B,
+	) -> error::Result<()>
+	where
+		B: FnOnce(Configuration) -> Result<BC, sc_service::error::Error>,
+		BC: ServiceBuilderCommand<Block = BB> + Unpin,
+		BB: BlockT + Debug,
+		BB::Hash: FromStr,
+		<BB::Hash as FromStr>::Err: std::fmt::Debug,
+	{
+		let manifest_path = self.input.join(MANIFEST_FILE);
+		let manifest = SnapshotManifest::<BB::Hash, BB::Header>::decode(&mut &fs::read(&manifest_path)?[..])
+			.map_err(|e| format!("Invalid manifest {}: {}", manifest_path.display(), e))?;
+		if let Some(expected_hash) = &self.expected_hash {
+			let expected_hash = BB::Hash::from_str(expected_hash.trim_start_matches("0x"))
+				.map_err(|e| format!("Failed to parse the expected hash: {:?}", e))?;
+			if expected_hash != manifest.block_hash {
+				return Err(format!("The snapshot is of the block {}", manifest.block_hash).into())
+			}
+		}
+
+		info!("Importing the snapshot of the state of block {}...", manifest.block_hash);
+		let dir = self.input.clone();
+		let chunks = (0..manifest.chunks.len()).map(move |index| -> Result<Vec<u8>, sc_service::Error> {
+			let compressed = fs::read(chunk_path(&dir, index))?;
+			Ok(zstd::decode_all(&compressed[..])?)
+		});
+		builder(config)?
+			.import_snapshot(manifest, chunks)
+			.await
+			.map_err(Into::into)
+	}
+}
+
+impl CliConfiguration for ImportSnapshotCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn import_params(&self) -> Option<&ImportParams> {
+		Some(&self.import_params)
+	}
+}
@@ -0,0 +1,90 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, params::{CryptoSchemeParams, KeystoreParams, SharedParams},
+	commands::key_utils::{format_hex, read_uri}, config::default_base_path, SubstrateCli,
+};
+use sc_keystore::{KeyStorePtr, Store};
+use sc_service::config::KeystoreConfig;
+use sp_core::{crypto::KeyTypeId, Pair};
+use std::convert::TryFrom;
+use structopt::StructOpt;
+
+/// The `key insert` command used to insert a key into the keystore of the node.
+#[derive(Debug, StructOpt, Clone)]
+pub struct InsertKeyCmd {
+	/// The secret key URI.
+	///
+	/// If the value is a file, the file content is used as URI. If not given, you will be prompted
+	/// for the URI.
+	#[structopt(long, value_name = "SURI")]
+	pub suri: Option<String>,
+
+	/// Key type, examples: "gran", or "imon".
+	#[structopt(long = "key-type", value_name = "KEY_TYPE")]
+	pub key_type: String,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub crypto_scheme_params: CryptoSchemeParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl InsertKeyCmd {
+	/// Run the `key insert` command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> error::Result<()> {
+		let key_type = KeyTypeId::try_from(self.key_type.as_str())
+			.map_err(|_| "Cannot convert argument to keytype: argument should be 4-character string")?;
+		let suri = read_uri(self.suri.as_ref())?;
+
+		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
+		let chain_spec = cli.load_spec(&chain_id)?;
+		let config_dir = self.shared_params.base_path()
+			.unwrap_or_else(default_base_path::<C>)
+			.join("chains")
+			.join(chain_spec.id());
+		let keystore = match self.keystore_params.keystore_config(&config_dir)? {
+			KeystoreConfig::Path { path, password } => Store::open(path, password)
+				.map_err(|e| format!("Failed to open the keystore: {}", e))?,
+			KeystoreConfig::InMemory => return Err("The keystore of the node isn't on the disk".into()),
+		};
+
+		let public = with_crypto_scheme!(
+			self.crypto_scheme_params.scheme,
+			insert_key(&keystore, key_type, &suri)
+		)?;
+		println!("Inserted key {} of type `{}`", format_hex(&public), self.key_type);
+		Ok(())
+	}
+}
+
+/// Inserts the key `suri` into the `keystore`, and returns its public key.
+fn insert_key<P: Pair>(keystore: &KeyStorePtr, key_type: KeyTypeId, suri: &str) -> error::Result<Vec<u8>> {
+	keystore.read()
+		.insert_by_type::<P>(key_type, suri)
+		.map(|pair| pair.public().as_ref().to_vec())
+		.map_err(|e| format!("Failed to insert the key: {}", e).into())
+}
@@ -0,0 +1,65 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, params::{CryptoSchemeParams, KeyOutputParams, KeyPasswordParams},
+	commands::key_utils::{print_from_uri, read_uri},
+};
+use structopt::StructOpt;
+
+/// The `key inspect` command used to print the public key and the SS58 address of a key.
+#[derive(Debug, StructOpt, Clone)]
+pub struct InspectKeyCmd {
+	/// A key URI to be inspected. May be a secret seed, secret URI (with derivation paths and
+	/// password), SS58 or public URI.
+	///
+	/// If the value is a file, the file content is used as URI. If not given, you will be prompted
+	/// for the URI.
+	#[structopt(value_name = "URI")]
+	pub uri: Option<String>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub crypto_scheme_params: CryptoSchemeParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub key_output_params: KeyOutputParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub key_password_params: KeyPasswordParams,
+}
+
+impl InspectKeyCmd {
+	/// Run the `key inspect` command
+	pub fn run(&self) -> error::Result<()> {
+		let uri = read_uri(self.uri.as_ref())?;
+		let password = self.key_password_params.read_password()?;
+
+		with_crypto_scheme!(
+			self.crypto_scheme_params.scheme,
+			print_from_uri(
+				&uri,
+				password.as_deref(),
+				self.key_output_params.network,
+				self.key_output_params.output_type,
+			)
+		)
+	}
+}
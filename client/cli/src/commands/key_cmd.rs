@@ -0,0 +1,59 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, SubstrateCli,
+	commands::{GenerateCmd, GenerateNodeKeyCmd, InsertKeyCmd, InspectKeyCmd, SignCmd, VerifyCmd},
+};
+use structopt::StructOpt;
+
+/// The `key` command used to manage keys, as `subkey` does.
+#[derive(Debug, StructOpt, Clone)]
+pub enum KeySubcommand {
+	/// Generate a random account.
+	Generate(GenerateCmd),
+
+	/// Generate a random node libp2p key, save it to a file or print it, and print its peer ID.
+	GenerateNodeKey(GenerateNodeKeyCmd),
+
+	/// Print the public key and the SS58 address of a secret seed, a secret URI or a public URI.
+	Inspect(InspectKeyCmd),
+
+	/// Sign a message with a secret key.
+	Sign(SignCmd),
+
+	/// Verify the signature of a message with a public or secret key.
+	Verify(VerifyCmd),
+
+	/// Insert a key into the keystore of the node.
+	Insert(InsertKeyCmd),
+}
+
+impl KeySubcommand {
+	/// Run the `key` command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> error::Result<()> {
+		match self {
+			KeySubcommand::Generate(cmd) => cmd.run(),
+			KeySubcommand::GenerateNodeKey(cmd) => cmd.run(),
+			KeySubcommand::Inspect(cmd) => cmd.run(),
+			KeySubcommand::Sign(cmd) => cmd.run(),
+			KeySubcommand::Verify(cmd) => cmd.run(),
+			KeySubcommand::Insert(cmd) => cmd.run(cli),
+		}
+	}
+}
@@ -0,0 +1,158 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers shared by the key commands.
+
+use crate::{arg_enums::OutputType, error::{self, Error}};
+use serde_json::json;
+use sp_core::{
+	crypto::{Ss58AddressFormat, Ss58Codec},
+	hexdisplay::HexDisplay,
+	Pair,
+};
+use sp_runtime::{traits::IdentifyAccount, MultiSigner};
+use std::{fs, io::Read, path::Path};
+
+/// Calls the function `$method` generic over a `sp_core::Pair`, with the pair type of the
+/// cryptography scheme `$scheme`.
+macro_rules! with_crypto_scheme {
+	($scheme:expr, $method:ident($($params:expr),* $(,)?)) => {
+		match $scheme {
+			$crate::CryptoScheme::Ed25519 => $method::<sp_core::ed25519::Pair>($($params),*),
+			$crate::CryptoScheme::Sr25519 => $method::<sp_core::sr25519::Pair>($($params),*),
+			$crate::CryptoScheme::Ecdsa => $method::<sp_core::ecdsa::Pair>($($params),*),
+		}
+	};
+}
+
+/// Returns the key URI given on the command line, or prompts for it.
+///
+/// If the given URI is a file, its content is used as URI.
+pub(crate) fn read_uri(uri: Option<&String>) -> error::Result<String> {
+	Ok(match uri {
+		Some(uri) if Path::new(uri).is_file() => fs::read_to_string(uri)?.trim_end().into(),
+		Some(uri) => uri.clone(),
+		None => {
+			#[cfg(not(target_os = "unknown"))]
+			{
+				rpassword::read_password_from_tty(Some("URI: "))?
+			}
+			#[cfg(target_os = "unknown")]
+			return Err("No URI given".into())
+		},
+	})
+}
+
+/// Returns the message given on the command line, or read from stdin, decoding it from hex if
+/// requested.
+pub(crate) fn read_message(message: Option<&String>, hex: bool) -> error::Result<Vec<u8>> {
+	let message = match message {
+		Some(message) => message.as_bytes().to_vec(),
+		None => {
+			let mut message = Vec::new();
+			std::io::stdin().lock().read_to_end(&mut message)?;
+			message
+		},
+	};
+	if hex {
+		decode_hex(&message)
+	} else {
+		Ok(message)
+	}
+}
+
+/// Decodes the hex-encoded `data`, optionally prefixed with `0x`.
+pub(crate) fn decode_hex<T: AsRef<[u8]>>(data: T) -> error::Result<Vec<u8>> {
+	let data = data.as_ref();
+	let data = if data.starts_with(b"0x") { &data[2..] } else { data };
+	hex::decode(data).map_err(|e| format!("Invalid hex ({})", e).into())
+}
+
+/// Prints the public key, the account ID and the SS58 address of the key `uri`, which may be a
+/// phrase, a secret URI or a public URI.
+pub(crate) fn print_from_uri<P: Pair>(
+	uri: &str,
+	password: Option<&str>,
+	network: Option<Ss58AddressFormat>,
+	output: OutputType,
+) -> error::Result<()> where
+	P::Public: Into<MultiSigner>,
+{
+	// the kind of the URI, as printed and as named in JSON.
+	let (kind, secret_seed, public, version) = if let Ok((pair, seed)) = P::from_phrase(uri, password) {
+		(("Secret phrase", "secretPhrase"), Some(format_hex(seed.as_ref())), pair.public(), None)
+	} else if let Ok((pair, seed)) = P::from_string_with_seed(uri, password) {
+		let seed = seed.map(|seed| format_hex(seed.as_ref())).unwrap_or_else(|| "n/a".into());
+		(("Secret Key URI", "secretKeyUri"), Some(seed), pair.public(), None)
+	} else if let Ok((public, version)) = P::Public::from_string_with_version(uri) {
+		(("Public Key URI", "publicKeyUri"), None, public, Some(version))
+	} else {
+		return Err(Error::Input("Invalid phrase/URI given".into()))
+	};
+
+	let network = network.or(version).unwrap_or_default();
+	let public_key = format_hex(public.as_ref());
+	let account_id = Into::<MultiSigner>::into(public).into_account();
+	let ss58_address = account_id.to_ss58check_with_version(network);
+	let account_id = format_hex(account_id.as_ref());
+
+	match output {
+		OutputType::Json => {
+			let mut json = json!({
+				"networkId": String::from(network),
+				"publicKey": public_key,
+				"accountId": account_id,
+				"ss58Address": ss58_address,
+			});
+			if let Some(secret_seed) = secret_seed {
+				json["secretSeed"] = secret_seed.into();
+			}
+			json[kind.1] = uri.into();
+			println!("{}", serde_json::to_string_pretty(&json).expect("Json pretty print failed"));
+		},
+		OutputType::Text => {
+			println!("{} `{}` is account:", kind.0, uri);
+			println!("  Network ID/version: {}", String::from(network));
+			if let Some(secret_seed) = secret_seed {
+				println!("  Secret seed:        {}", secret_seed);
+			}
+			println!("  Public key (hex):   {}", public_key);
+			println!("  Account ID:         {}", account_id);
+			println!("  SS58 Address:       {}", ss58_address);
+		},
+	}
+	Ok(())
+}
+
+/// Formats `data` as `0x`-prefixed hex.
+pub(crate) fn format_hex(data: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&data))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hex_is_decoded_with_or_without_prefix() {
+		assert_eq!(decode_hex("0x0102").unwrap(), vec![1, 2]);
+		assert_eq!(decode_hex("0102").unwrap(), vec![1, 2]);
+		assert!(decode_hex("0x01g2").is_err());
+		assert_eq!(format_hex(&[1, 2]), "0x0102");
+	}
+}
@@ -0,0 +1,107 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::arg_enums::Database;
+use crate::error;
+use crate::params::{DatabaseParams, SharedParams};
+use crate::CliConfiguration;
+use log::info;
+use sc_service::{Configuration, config::{DatabaseConfig, MigrationProgress}};
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// The `db migrate` command used to convert the database of the node to another backend.
+///
+/// The database of the node is selected with `--database`, and is left untouched.
+#[derive(Debug, StructOpt, Clone)]
+pub struct MigrateDbCmd {
+	/// Select database backend to convert the database to.
+	#[structopt(
+		long,
+		value_name = "DB",
+		possible_values = &Database::variants(),
+		case_insensitive = true,
+	)]
+	pub to: Database,
+
+	/// Specify the path of the converted database.
+	///
+	/// Default is the path the node uses for the selected backend.
+	#[structopt(long = "to-path", value_name = "PATH", parse(from_os_str))]
+	pub to_path: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl MigrateDbCmd {
+	/// Run the migrate command
+	pub fn run(&self, config: Configuration) -> error::Result<()> {
+		let db_path = config.database.path()
+			.ok_or_else(|| error::Error::Input("Cannot migrate custom database implementation".into()))?;
+		let cache_size = self.database_params.database_cache_size().unwrap_or(128);
+		let target = match &self.to_path {
+			Some(path) => match self.to {
+				Database::RocksDb => DatabaseConfig::RocksDb { path: path.clone(), cache_size },
+				Database::SubDb => DatabaseConfig::SubDb { path: path.clone() },
+				Database::ParityDb => DatabaseConfig::ParityDb { path: path.clone() },
+			},
+			None => {
+				let base_path = db_path.parent()
+					.ok_or_else(|| error::Error::Input(format!("Invalid database path {:?}", db_path)))?;
+				self.database_config(&base_path.to_path_buf(), cache_size, self.to)?
+			},
+		};
+		let target_path = target.path().expect("The target database isn't custom; qed");
+
+		if fs::read_dir(target_path).map(|mut dir| dir.next().is_some()).unwrap_or(false) {
+			return Err(error::Error::Input(format!("{:?} already exists and isn't empty", target_path)))
+		}
+
+		info!("Migrating {:?} to {:?}", db_path, target_path);
+		sc_service::config::migrate_database(&config.database, &target, |progress| match progress {
+			MigrationProgress::Copied { column, keys } =>
+				info!("Copied {} entries of the column {}", keys, column),
+			MigrationProgress::Verified { column, keys } =>
+				info!("Verified {} entries of the column {}", keys, column),
+		})?;
+		println!(
+			"{:?} migrated to {:?}. Pass `--database {:?}` to the node to use it.",
+			db_path,
+			target_path,
+			self.to,
+		);
+		Ok(())
+	}
+}
+
+impl CliConfiguration for MigrateDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -15,23 +15,51 @@
 
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
+#[macro_use]
+mod key_utils;
 mod build_spec_cmd;
 mod check_block_cmd;
+mod check_db_cmd;
+mod db_cmd;
 mod export_blocks_cmd;
+mod export_snapshot_cmd;
 mod export_state_cmd;
+mod generate_cmd;
+mod generate_node_key_cmd;
 mod import_blocks_cmd;
+mod import_snapshot_cmd;
+mod insert_key_cmd;
+mod inspect_key_cmd;
+mod key_cmd;
+mod migrate_db_cmd;
 mod purge_chain_cmd;
 mod revert_cmd;
 mod run_cmd;
+mod sign_cmd;
+mod stats_db_cmd;
+mod verify_cmd;
 
 pub use self::build_spec_cmd::BuildSpecCmd;
 pub use self::check_block_cmd::CheckBlockCmd;
+pub use self::check_db_cmd::CheckDbCmd;
+pub use self::db_cmd::DbSubcommand;
 pub use self::export_blocks_cmd::ExportBlocksCmd;
 pub use self::import_blocks_cmd::ImportBlocksCmd;
 pub use self::purge_chain_cmd::PurgeChainCmd;
 pub use self::revert_cmd::RevertCmd;
 pub use self::run_cmd::RunCmd;
 pub use self::export_state_cmd::ExportStateCmd;
+pub use self::export_snapshot_cmd::ExportSnapshotCmd;
+pub use self::import_snapshot_cmd::ImportSnapshotCmd;
+pub use self::generate_cmd::GenerateCmd;
+pub use self::generate_node_key_cmd::GenerateNodeKeyCmd;
+pub use self::insert_key_cmd::InsertKeyCmd;
+pub use self::inspect_key_cmd::InspectKeyCmd;
+pub use self::key_cmd::KeySubcommand;
+pub use self::migrate_db_cmd::MigrateDbCmd;
+pub use self::sign_cmd::SignCmd;
+pub use self::stats_db_cmd::StatsDbCmd;
+pub use self::verify_cmd::VerifyCmd;
 use std::fmt::Debug;
 use structopt::StructOpt;
 
@@ -62,6 +90,15 @@ pub enum Subcommand {
 
 	/// Export state as raw chain spec.
 	ExportState(ExportStateCmd),
+
+	/// Export a snapshot of the state, to start fresh nodes from.
+	ExportSnapshot(ExportSnapshotCmd),
+
+	/// Import a snapshot of the state into a fresh node.
+	ImportSnapshot(ImportSnapshotCmd),
+
+	/// Manage the database of the node.
+	Db(DbSubcommand),
 }
 
 // TODO: move to config.rs?
@@ -297,18 +334,74 @@ macro_rules! substrate_cli_subcommands {
 				}
 			}
 
+			fn rpc_ipc(&self) -> $crate::Result<::std::option::Option<::std::string::String>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_ipc()),*
+				}
+			}
+
+			fn rpc_grpc(&self) -> $crate::Result<::std::option::Option<::std::net::SocketAddr>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_grpc()),*
+				}
+			}
+
 			fn rpc_methods(&self) -> $crate::Result<sc_service::config::RpcMethods> {
 				match self {
 					$($enum::$variant(cmd) => cmd.rpc_methods()),*
 				}
 			}
 
+			fn rpc_interface_methods(&self) -> $crate::Result<sc_service::config::RpcInterfaceMethods> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_interface_methods()),*
+				}
+			}
+
 			fn rpc_ws_max_connections(&self) -> $crate::Result<::std::option::Option<usize>> {
 				match self {
 					$($enum::$variant(cmd) => cmd.rpc_ws_max_connections()),*
 				}
 			}
 
+			fn rpc_ws_max_subscriptions_per_connection(
+				&self
+			) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_ws_max_subscriptions_per_connection()),*
+				}
+			}
+
+			fn rpc_max_request_size(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_max_request_size()),*
+				}
+			}
+
+			fn rpc_max_response_size(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_max_response_size()),*
+				}
+			}
+
+			fn rpc_max_batch_size(&self) -> $crate::Result<::std::option::Option<usize>> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_max_batch_size()),*
+				}
+			}
+
+			fn rpc_rate_limit(&self) -> $crate::Result<::sc_service::config::RateLimitConfig> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_rate_limit()),*
+				}
+			}
+
+			fn rpc_method_filter(&self) -> $crate::Result<::sc_service::config::RpcMethodFilter> {
+				match self {
+					$($enum::$variant(cmd) => cmd.rpc_method_filter()),*
+				}
+			}
+
 			fn rpc_cors(&self, is_dev: bool)
 			-> $crate::Result<::std::option::Option<::std::vec::Vec<String>>> {
 				match self {
@@ -407,6 +500,11 @@ macro_rules! substrate_cli_subcommands {
 }
 
 substrate_cli_subcommands!(
-	Subcommand => BuildSpec, ExportBlocks, ImportBlocks, CheckBlock, Revert, PurgeChain, ExportState
+	Subcommand => BuildSpec, ExportBlocks, ImportBlocks, CheckBlock, Revert, PurgeChain, ExportState,
+	ExportSnapshot, ImportSnapshot, Db
+);
+
+substrate_cli_subcommands!(
+	DbSubcommand => Migrate, Check, Stats
 );
 
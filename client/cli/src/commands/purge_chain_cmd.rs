@@ -19,19 +19,27 @@
 use crate::error;
 use crate::params::{DatabaseParams, SharedParams};
 use crate::CliConfiguration;
-use sc_service::Configuration;
+use sc_network::config::Secret;
+use sc_service::{Configuration, config::{DatabaseData, NodeKeyConfig}};
 use std::fmt::Debug;
 use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 use structopt::StructOpt;
 
 /// The `purge-chain` command used to remove the whole chain.
+///
+/// The keystore and the network key are never removed.
 #[derive(Debug, StructOpt, Clone)]
 pub struct PurgeChainCmd {
 	/// Skip interactive prompt by answering yes automatically.
 	#[structopt(short = "y")]
 	pub yes: bool,
 
+	/// Keep the local storage of the offchain workers.
+	#[structopt(long = "keep-offchain")]
+	pub keep_offchain: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -42,6 +50,15 @@ pub struct PurgeChainCmd {
 }
 
 impl PurgeChainCmd {
+	/// Returns the data to remove, `None` if the whole database is removed.
+	fn data(&self) -> Option<Vec<DatabaseData>> {
+		if self.keep_offchain {
+			Some(vec![DatabaseData::State, DatabaseData::Blocks])
+		} else {
+			None
+		}
+	}
+
 	/// Run the purge command
 	pub fn run(&self, config: Configuration) -> error::Result<()> {
 		let db_path = config.database.path()
@@ -49,8 +66,32 @@ impl PurgeChainCmd {
 				error::Error::Input("Cannot purge custom database implementation".into())
 		)?;
 
+		let data = self.data();
+		if data.is_none() {
+			// the keys are usually outside of the database, unless explicitly configured otherwise.
+			let node_key_path = match &config.network.node_key {
+				NodeKeyConfig::Ed25519(Secret::File(path)) => Some(path.as_path()),
+				_ => None,
+			};
+			let keys = config.keystore.path().into_iter()
+				.chain(config.network.net_config_path.as_ref().map(|path| path.as_path()))
+				.chain(node_key_path)
+				.collect::<Vec<&Path>>();
+			if let Some(path) = keys.into_iter().find(|path| path.starts_with(db_path)) {
+				return Err(error::Error::Input(format!(
+					"Cannot purge {:?} containing the keys at {:?}. Remove the database manually, \
+					or pass `--keep-offchain` to remove its data.",
+					db_path,
+					path,
+				)))
+			}
+		}
+
 		if !self.yes {
-			print!("Are you sure to remove {:?}? [y/N]: ", &db_path);
+			match &data {
+				Some(data) => print!("Are you sure to remove the {:?} data of {:?}? [y/N]: ", data, &db_path),
+				None => print!("Are you sure to remove {:?}? [y/N]: ", &db_path),
+			}
 			io::stdout().flush().expect("failed to flush stdout");
 
 			let mut input = String::new();
@@ -66,6 +107,16 @@ impl PurgeChainCmd {
 			}
 		}
 
+		if let Some(data) = data {
+			if !db_path.exists() {
+				eprintln!("{:?} did not exist.", &db_path);
+				return Ok(())
+			}
+			sc_service::config::purge_database(&config.database, &data)?;
+			println!("{:?} data of {:?} removed.", data, &db_path);
+			return Ok(())
+		}
+
 		match fs::remove_dir_all(&db_path) {
 			Ok(_) => {
 				println!("{:?} removed.", &db_path);
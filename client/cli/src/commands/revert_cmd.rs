@@ -31,6 +31,14 @@ pub struct RevertCmd {
 	#[structopt(default_value = "256")]
 	pub num: BlockNumber,
 
+	/// Also revert the finalized blocks, along with the authority sets of the consensus engines.
+	///
+	/// Only meant for recovering from the finalization of a bad block, once all the operators of
+	/// the chain agreed to revert it: other nodes keep considering the reverted blocks final.
+	/// The states of the blocks to revert to must be available, e.g. with `--pruning archive`.
+	#[structopt(long = "force-finalized")]
+	pub force_finalized: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub shared_params: SharedParams,
@@ -51,7 +59,10 @@ impl RevertCmd {
 		<BB as BlockT>::Hash: std::str::FromStr,
 	{
 		let blocks = self.num.parse()?;
-		builder(config)?.revert_chain(blocks)?;
+		if self.force_finalized {
+			log::warn!("⚠️  `--force-finalized` reverts finalized blocks, which can't be undone.");
+		}
+		builder(config)?.revert_chain(blocks, self.force_finalized)?;
 
 		Ok(())
 	}
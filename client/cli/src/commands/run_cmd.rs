@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::arg_enums::RpcMethods;
+use crate::arg_enums::{OutputFormat, RpcMethods};
 use crate::error::{Error, Result};
 use crate::params::ImportParams;
 use crate::params::KeystoreParams;
@@ -27,11 +27,14 @@ use crate::params::OffchainWorkerParams;
 use crate::CliConfiguration;
 use regex::Regex;
 use sc_service::{
-	config::{MultiaddrWithPeerId, PrometheusConfig, TransactionPoolOptions},
+	config::{
+		HealthConfig, MultiaddrWithPeerId, PrometheusConfig, RateLimit, RateLimitConfig, RpcInterfaceMethods,
+		RpcMethodFilter, SystemdNotifyConfig, TransactionPoolOptions,
+	},
 	ChainSpec, Role,
 };
 use sc_telemetry::TelemetryEndpoints;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{fs, net::{IpAddr, Ipv4Addr, SocketAddr}, path::PathBuf, time::Duration};
 use structopt::StructOpt;
 
 /// The `run` command used to run a node.
@@ -92,16 +95,28 @@ pub struct RunCmd {
 	/// - `Safe`: Exposes only a safe subset of RPC methods, denying unsafe RPC methods.
 	/// - `Auto`: Acts as `Safe` if RPC is served externally, e.g. when `--{rpc,ws}-external` is passed,
 	///   otherwise acts as `Unsafe`.
+	/// - A comma separated list of method groups among `read`, `author`, `admin` and `debug`, e.g.
+	///   `read,author`: Exposes the methods of these groups. `Safe` is `read,author`.
 	#[structopt(
 		long,
 		value_name = "METHOD SET",
-		possible_values = &RpcMethods::variants(),
-		case_insensitive = true,
 		default_value = "Auto",
 		verbatim_doc_comment
 	)]
 	pub rpc_methods: RpcMethods,
 
+	/// RPC methods to expose on the HTTP server, instead of the ones of `--rpc-methods`.
+	#[structopt(long = "rpc-http-methods", value_name = "METHOD SET")]
+	pub rpc_http_methods: Option<RpcMethods>,
+
+	/// RPC methods to expose on the WebSockets server, instead of the ones of `--rpc-methods`.
+	#[structopt(long = "rpc-ws-methods", value_name = "METHOD SET")]
+	pub rpc_ws_methods: Option<RpcMethods>,
+
+	/// RPC methods to expose on the IPC server, instead of the ones of `--rpc-methods`.
+	#[structopt(long = "rpc-ipc-methods", value_name = "METHOD SET")]
+	pub rpc_ipc_methods: Option<RpcMethods>,
+
 	/// Listen to all Websocket interfaces.
 	///
 	/// Default is local. Note: not all RPC methods are safe to be exposed publicly. Use an RPC proxy
@@ -130,10 +145,84 @@ pub struct RunCmd {
 	#[structopt(long = "ws-port", value_name = "PORT")]
 	pub ws_port: Option<u16>,
 
+	/// Specify the path of the IPC RPC server: a Unix socket, or a named pipe on Windows.
+	///
+	/// On Unix, only the user running the node can access the socket. Disabled by default.
+	#[structopt(long = "ipc-path", value_name = "PATH")]
+	pub ipc_path: Option<String>,
+
+	/// Specify gRPC gateway TCP port.
+	///
+	/// The gateway listens on the same interface as the HTTP RPC server, and exposes the same
+	/// methods. Disabled by default. Requires a node built with the `grpc` feature.
+	#[structopt(long = "grpc-port", value_name = "PORT")]
+	pub grpc_port: Option<u16>,
+
 	/// Maximum number of WS RPC server connections.
 	#[structopt(long = "ws-max-connections", value_name = "COUNT")]
 	pub ws_max_connections: Option<usize>,
 
+	/// Maximum number of subscriptions per WS RPC server connection.
+	#[structopt(long = "ws-max-subscriptions-per-connection", value_name = "COUNT")]
+	pub ws_max_subscriptions_per_connection: Option<usize>,
+
+	/// Maximum size in MiB of the requests to the RPC servers. Default is 15 MiB.
+	#[structopt(long = "rpc-max-request-size", value_name = "MIB")]
+	pub rpc_max_request_size: Option<usize>,
+
+	/// Maximum size in MiB of the responses of the RPC servers. Default is 15 MiB.
+	#[structopt(long = "rpc-max-response-size", value_name = "MIB")]
+	pub rpc_max_response_size: Option<usize>,
+
+	/// Maximum number of calls in a batch request to the RPC servers. Default is 1024.
+	#[structopt(long = "rpc-max-batch-size", value_name = "COUNT")]
+	pub rpc_max_batch_size: Option<usize>,
+
+	/// Maximum number of calls per second to the safe RPC methods.
+	///
+	/// Calls made over a WebSockets connection are counted separately for each connection,
+	/// while the ones made over HTTP are counted together. By default there is no limit.
+	#[structopt(long = "rpc-rate-limit", value_name = "COUNT")]
+	pub rpc_rate_limit: Option<u32>,
+
+	/// Maximum number of calls per second to the unsafe RPC methods.
+	///
+	/// Counted the same way as `--rpc-rate-limit`. By default there is no limit.
+	#[structopt(long = "rpc-rate-limit-unsafe", value_name = "COUNT")]
+	pub rpc_rate_limit_unsafe: Option<u32>,
+
+	/// Comma-separated list of the only RPC methods that can be called.
+	///
+	/// Applies on top of `--rpc-methods`, to the methods of the runtime-specific RPCs too.
+	#[structopt(
+		long = "rpc-allow-methods",
+		value_name = "METHODS",
+		use_delimiter = true,
+		conflicts_with_all = &[ "rpc-deny-methods", "rpc-methods-file" ]
+	)]
+	pub rpc_allow_methods: Option<Vec<String>>,
+
+	/// Comma-separated list of RPC methods that can't be called.
+	///
+	/// Applies on top of `--rpc-methods`, to the methods of the runtime-specific RPCs too.
+	#[structopt(
+		long = "rpc-deny-methods",
+		value_name = "METHODS",
+		use_delimiter = true,
+		conflicts_with_all = &[ "rpc-allow-methods", "rpc-methods-file" ]
+	)]
+	pub rpc_deny_methods: Option<Vec<String>>,
+
+	/// JSON file listing the RPC methods that can be called, either as
+	/// `{ "allow": [ <methods> ] }` or as `{ "deny": [ <methods> ] }`.
+	#[structopt(
+		long = "rpc-methods-file",
+		value_name = "PATH",
+		parse(from_os_str),
+		conflicts_with_all = &[ "rpc-allow-methods", "rpc-deny-methods" ]
+	)]
+	pub rpc_methods_file: Option<PathBuf>,
+
 	/// Specify browser Origins allowed to access the HTTP & WS RPC servers.
 	///
 	/// A comma-separated list of origins (protocol://domain or special `null`
@@ -240,6 +329,65 @@ pub struct RunCmd {
 	#[structopt(long)]
 	pub max_runtime_instances: Option<usize>,
 
+	/// Notify systemd of the readiness of the node once it's synced, and pet its watchdog.
+	///
+	/// The unit must have `Type=notify`. With `WatchdogSec=`, systemd restarts the node if its
+	/// networking hangs.
+	#[structopt(long = "systemd-notify")]
+	pub systemd_notify: bool,
+
+	/// Maximum number of blocks the node may be behind the best block seen on the network to
+	/// notify systemd of its readiness.
+	#[structopt(
+		long = "systemd-ready-threshold",
+		value_name = "BLOCKS",
+		default_value = "10",
+		requires = "systemd-notify"
+	)]
+	pub systemd_ready_threshold: u32,
+
+	/// Serve the health and the readiness of the node over HTTP on the given TCP port.
+	///
+	/// `/health` and `/ready` respond with `200 OK` or `503 Service Unavailable`, e.g. to load
+	/// balancers and Kubernetes probes, independently of the RPC servers.
+	#[structopt(long = "health-port", value_name = "PORT")]
+	pub health_port: Option<u16>,
+
+	/// Listen to all health endpoint interfaces.
+	///
+	/// Default is local.
+	#[structopt(long = "health-external", requires = "health-port")]
+	pub health_external: bool,
+
+	/// Maximum number of blocks the node may be behind the best block seen on the network to be
+	/// reported as ready.
+	#[structopt(
+		long = "health-ready-threshold",
+		value_name = "BLOCKS",
+		default_value = "10",
+		requires = "health-port"
+	)]
+	pub health_ready_threshold: u32,
+
+	/// Maximum time since the last finalized block for the node to be reported as healthy.
+	///
+	/// The finality isn't checked by default.
+	#[structopt(long = "health-max-finality-lag", value_name = "SECONDS", requires = "health-port")]
+	pub health_max_finality_lag: Option<u64>,
+
+	/// Format of the sync progress, the imported blocks and the lifecycle events of the node.
+	///
+	/// `Json` prints them as one JSON object per line on the standard output, with an `event`
+	/// field naming the event, instead of the human-readable status lines of the logs.
+	#[structopt(
+		long = "output",
+		value_name = "FORMAT",
+		possible_values = &OutputFormat::variants(),
+		case_insensitive = true,
+		default_value = "Text"
+	)]
+	pub output: OutputFormat,
+
 	/// Specify a list of sentry node public addresses.
 	///
 	/// Can't be used with --public-addr as the sentry node would take precedence over the public address
@@ -250,6 +398,13 @@ pub struct RunCmd {
 		conflicts_with_all = &[ "sentry", "public-addr" ]
 	)]
 	pub sentry_nodes: Vec<MultiaddrWithPeerId>,
+
+	/// Only connect to and accept connections from the sentry nodes, and never publish the
+	/// addresses of the node to the DHT.
+	///
+	/// The node is only reachable through its sentry nodes, which advertise it on its behalf.
+	#[structopt(long = "private-validator", requires = "sentry-nodes")]
+	pub private_validator: bool,
 }
 
 impl RunCmd {
@@ -353,6 +508,7 @@ impl CliConfiguration for RunCmd {
 		} else if is_authority {
 			sc_service::Role::Authority {
 				sentry_nodes: self.sentry_nodes.clone(),
+				private: self.private_validator,
 			}
 		} else if !self.sentry.is_empty() {
 			sc_service::Role::Sentry {
@@ -392,6 +548,44 @@ impl CliConfiguration for RunCmd {
 		Ok(self.ws_max_connections)
 	}
 
+	fn rpc_ws_max_subscriptions_per_connection(&self) -> Result<Option<usize>> {
+		Ok(self.ws_max_subscriptions_per_connection)
+	}
+
+	fn rpc_max_request_size(&self) -> Result<Option<usize>> {
+		Ok(self.rpc_max_request_size.map(|size| size.saturating_mul(1024 * 1024)))
+	}
+
+	fn rpc_max_response_size(&self) -> Result<Option<usize>> {
+		Ok(self.rpc_max_response_size.map(|size| size.saturating_mul(1024 * 1024)))
+	}
+
+	fn rpc_max_batch_size(&self) -> Result<Option<usize>> {
+		Ok(self.rpc_max_batch_size)
+	}
+
+	fn rpc_rate_limit(&self) -> Result<RateLimitConfig> {
+		Ok(RateLimitConfig {
+			safe: self.rpc_rate_limit.map(RateLimit::per_second),
+			unsafe_methods: self.rpc_rate_limit_unsafe.map(RateLimit::per_second),
+		})
+	}
+
+	fn rpc_method_filter(&self) -> Result<RpcMethodFilter> {
+		if let Some(path) = &self.rpc_methods_file {
+			let file = fs::File::open(path)?;
+			return serde_json::from_reader(file).map_err(|e| Error::Input(
+				format!("Invalid RPC methods file {}: {}", path.display(), e)
+			))
+		}
+
+		Ok(match (&self.rpc_allow_methods, &self.rpc_deny_methods) {
+			(Some(methods), _) => RpcMethodFilter::Allow(methods.iter().cloned().collect()),
+			(None, Some(methods)) => RpcMethodFilter::Deny(methods.iter().cloned().collect()),
+			(None, None) => RpcMethodFilter::All,
+		})
+	}
+
 	fn rpc_cors(&self, is_dev: bool) -> Result<Option<Vec<String>>> {
 		Ok(self
 			.rpc_cors
@@ -435,10 +629,37 @@ impl CliConfiguration for RunCmd {
 		Ok(Some(SocketAddr::new(interface, self.ws_port.unwrap_or(9944))))
 	}
 
+	fn rpc_ipc(&self) -> Result<Option<String>> {
+		Ok(self.ipc_path.clone())
+	}
+
+	fn rpc_grpc(&self) -> Result<Option<SocketAddr>> {
+		let port = match self.grpc_port {
+			Some(port) => port,
+			None => return Ok(None),
+		};
+		let interface = rpc_interface(
+			self.rpc_external,
+			self.unsafe_rpc_external,
+			self.rpc_methods,
+			self.validator
+		)?;
+
+		Ok(Some(SocketAddr::new(interface, port)))
+	}
+
 	fn rpc_methods(&self) -> Result<sc_service::config::RpcMethods> {
 		Ok(self.rpc_methods.into())
 	}
 
+	fn rpc_interface_methods(&self) -> Result<RpcInterfaceMethods> {
+		Ok(RpcInterfaceMethods {
+			http: self.rpc_http_methods.map(Into::into),
+			ws: self.rpc_ws_methods.map(Into::into),
+			ipc: self.rpc_ipc_methods.map(Into::into),
+		})
+	}
+
 	fn transaction_pool(&self) -> Result<TransactionPoolOptions> {
 		Ok(self.pool_config.transaction_pool())
 	}
@@ -446,6 +667,34 @@ impl CliConfiguration for RunCmd {
 	fn max_runtime_instances(&self) -> Result<Option<usize>> {
 		Ok(self.max_runtime_instances.map(|x| x.min(256)))
 	}
+
+	fn systemd_notify(&self) -> Result<Option<SystemdNotifyConfig>> {
+		Ok(if self.systemd_notify {
+			Some(SystemdNotifyConfig { ready_sync_threshold: self.systemd_ready_threshold })
+		} else {
+			None
+		})
+	}
+
+	fn health(&self) -> Result<Option<HealthConfig>> {
+		Ok(self.health_port.map(|port| {
+			let interface = if self.health_external {
+				Ipv4Addr::UNSPECIFIED
+			} else {
+				Ipv4Addr::LOCALHOST
+			};
+
+			HealthConfig {
+				address: SocketAddr::new(interface.into(), port),
+				ready_sync_threshold: self.health_ready_threshold,
+				max_finality_lag: self.health_max_finality_lag.map(Duration::from_secs),
+			}
+		}))
+	}
+
+	fn output_format(&self) -> Result<sc_informant::OutputFormat> {
+		Ok(self.output.into())
+	}
 }
 
 /// Check whether a node name is considered as valid.
@@ -476,7 +725,11 @@ fn rpc_interface(
 	rpc_methods: RpcMethods,
 	is_validator: bool,
 ) -> Result<IpAddr> {
-	if is_external && is_validator && rpc_methods != RpcMethods::Unsafe {
+	let is_explicit = match rpc_methods {
+		RpcMethods::Unsafe | RpcMethods::Groups(_) => true,
+		RpcMethods::Auto | RpcMethods::Safe => false,
+	};
+	if is_external && is_validator && !is_explicit {
 		return Err(Error::Input(
 			"--rpc-external and --ws-external options shouldn't be \
 		used if the node is running as a validator. Use `--unsafe-rpc-external` \
@@ -487,7 +740,7 @@ fn rpc_interface(
 	}
 
 	if is_external || is_unsafe_external {
-		if rpc_methods == RpcMethods::Unsafe {
+		if rpc_methods != RpcMethods::Auto && rpc_methods != RpcMethods::Safe {
 			log::warn!(
 				"It isn't safe to expose RPC publicly without a proxy server that filters \
 			available set of RPC methods."
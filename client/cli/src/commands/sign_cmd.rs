@@ -0,0 +1,73 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, params::{CryptoSchemeParams, KeyPasswordParams},
+	commands::key_utils::{format_hex, read_message, read_uri},
+};
+use sp_core::Pair;
+use structopt::StructOpt;
+
+/// The `key sign` command used to sign a message with a secret key.
+#[derive(Debug, StructOpt, Clone)]
+pub struct SignCmd {
+	/// The secret key URI.
+	///
+	/// If the value is a file, the file content is used as URI. If not given, you will be prompted
+	/// for the URI.
+	#[structopt(long, value_name = "SURI")]
+	pub suri: Option<String>,
+
+	/// The message to sign. Default is the content of stdin.
+	#[structopt(long, value_name = "MESSAGE")]
+	pub message: Option<String>,
+
+	/// The message is hex-encoded data.
+	#[structopt(long)]
+	pub hex: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub crypto_scheme_params: CryptoSchemeParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub key_password_params: KeyPasswordParams,
+}
+
+impl SignCmd {
+	/// Run the `key sign` command
+	pub fn run(&self) -> error::Result<()> {
+		let suri = read_uri(self.suri.as_ref())?;
+		let message = read_message(self.message.as_ref(), self.hex)?;
+		let password = self.key_password_params.read_password()?;
+
+		let signature = with_crypto_scheme!(
+			self.crypto_scheme_params.scheme,
+			sign(&suri, password.as_deref(), &message)
+		)?;
+		println!("{}", signature);
+		Ok(())
+	}
+}
+
+/// Returns the hex-encoded signature of `message` by the secret key `suri`.
+fn sign<P: Pair>(suri: &str, password: Option<&str>, message: &[u8]) -> error::Result<String> {
+	let pair = P::from_string(suri, password).map_err(|e| format!("Invalid secret key URI: {:?}", e))?;
+	Ok(format_hex(pair.sign(message).as_ref()))
+}
@@ -0,0 +1,119 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error;
+use crate::params::{BlockNumberOrHash, DatabaseParams, PruningParams, SharedParams};
+use crate::CliConfiguration;
+use sc_client_api::{backend::Backend as _, storage_stats, MemorySize};
+use sc_client_db::Backend;
+use sc_service::Configuration;
+use sp_blockchain::HeaderBackend;
+use sp_core::hexdisplay::HexDisplay;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, NumberFor}};
+use std::{fmt::Debug, str::FromStr};
+use structopt::StructOpt;
+
+/// The `db stats` command used to show which storage entries take the most space in the state
+/// of a block.
+///
+/// The entries of the state are grouped by the first bytes of their keys, by default the 16 bytes
+/// identifying the pallets, and the number of keys and the size of the values of each group are
+/// printed, largest first.
+#[derive(Debug, StructOpt, Clone)]
+pub struct StatsDbCmd {
+	/// Block hash or number of the state.
+	///
+	/// Default is the best block.
+	#[structopt(long = "at", value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// Number of bytes of the keys grouping the entries, 32 to group them by storage item.
+	#[structopt(long = "prefix-length", value_name = "BYTES", default_value = "16")]
+	pub prefix_len: usize,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl StatsDbCmd {
+	/// Run the `db stats` command
+	pub fn run<B>(&self, config: Configuration) -> error::Result<()>
+	where
+		B: BlockT,
+		B::Hash: FromStr,
+		<B::Hash as FromStr>::Err: Debug,
+		<NumberFor<B> as FromStr>::Err: Debug,
+	{
+		let backend = Backend::<B>::new(sc_service::database_settings(&config))?;
+		let block_id = match &self.at {
+			Some(at) => at.parse::<B>()?,
+			None => BlockId::Hash(backend.blockchain().info().best_hash),
+		};
+
+		let stats = storage_stats::<B, _>(&backend.state_at(block_id)?, self.prefix_len);
+		println!("{:<68} {:>12} {:>12}", "Prefix", "Keys", "Values");
+		for prefix in &stats {
+			println!(
+				"{:<68} {:>12} {:>12}",
+				format_prefix(&prefix.prefix.0),
+				prefix.keys,
+				MemorySize::from_bytes(prefix.value_bytes as usize).to_string(),
+			);
+		}
+		let keys: u64 = stats.iter().map(|prefix| prefix.keys).sum();
+		let value_bytes: u64 = stats.iter().map(|prefix| prefix.value_bytes).sum();
+		println!(
+			"{:<68} {:>12} {:>12}",
+			"Total",
+			keys,
+			MemorySize::from_bytes(value_bytes as usize).to_string(),
+		);
+		Ok(())
+	}
+}
+
+/// Formats a prefix in hex, or as text for the well known keys.
+fn format_prefix(prefix: &[u8]) -> String {
+	match std::str::from_utf8(prefix) {
+		Ok(text) if text.starts_with(':') => format!("\"{}\"", text),
+		_ => format!("0x{}", HexDisplay::from(&prefix)),
+	}
+}
+
+impl CliConfiguration for StatsDbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -0,0 +1,117 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	error, params::CryptoSchemeParams,
+	commands::key_utils::{decode_hex, read_message, read_uri},
+};
+use sp_core::{crypto::{Public, Ss58Codec}, Pair};
+use structopt::StructOpt;
+
+/// The `key verify` command used to verify the signature of a message.
+#[derive(Debug, StructOpt, Clone)]
+pub struct VerifyCmd {
+	/// The signature, hex-encoded.
+	#[structopt(value_name = "SIGNATURE")]
+	pub sig: String,
+
+	/// The public or secret key URI.
+	///
+	/// If the value is a file, the file content is used as URI. If not given, you will be prompted
+	/// for the URI.
+	#[structopt(value_name = "URI")]
+	pub uri: Option<String>,
+
+	/// The message to verify. Default is the content of stdin.
+	#[structopt(long, value_name = "MESSAGE")]
+	pub message: Option<String>,
+
+	/// The message is hex-encoded data.
+	#[structopt(long)]
+	pub hex: bool,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub crypto_scheme_params: CryptoSchemeParams,
+}
+
+impl VerifyCmd {
+	/// Run the `key verify` command
+	pub fn run(&self) -> error::Result<()> {
+		let uri = read_uri(self.uri.as_ref())?;
+		let message = read_message(self.message.as_ref(), self.hex)?;
+		let signature = decode_hex(&self.sig)?;
+
+		let valid = with_crypto_scheme!(
+			self.crypto_scheme_params.scheme,
+			verify(&signature, &message, &uri)
+		)?;
+		if valid {
+			println!("Signature verifies correctly.");
+			Ok(())
+		} else {
+			Err("Signature invalid.".into())
+		}
+	}
+}
+
+/// Returns whether `signature` is a valid signature of `message` by the key `uri`, which may be
+/// a hex-encoded public key, a public URI or a secret URI.
+fn verify<P: Pair>(signature: &[u8], message: &[u8], uri: &str) -> error::Result<bool> where
+	P::Signature: Default + AsMut<[u8]>,
+{
+	let mut sig = P::Signature::default();
+	if signature.len() != sig.as_ref().len() {
+		return Err(format!(
+			"Signature has an invalid length: read {} bytes, expected {} bytes",
+			signature.len(),
+			sig.as_ref().len(),
+		).into())
+	}
+	sig.as_mut().copy_from_slice(signature);
+
+	let public = match decode_hex(uri) {
+		Ok(public) if public.len() == P::Public::default().as_ref().len() => P::Public::from_slice(&public),
+		_ => match P::Public::from_string(uri) {
+			Ok(public) => public,
+			Err(_) => P::from_string(uri, None)
+				.map_err(|_| "Invalid URI; expecting either a secret URI or a public URI.")?
+				.public(),
+		},
+	};
+	Ok(P::verify(&sig, message, &public))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commands::key_utils::format_hex;
+	use sp_core::sr25519;
+
+	#[test]
+	fn signatures_are_verified_with_public_or_secret_uris() {
+		let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let signature = pair.sign(b"message");
+		let public = format_hex(pair.public().as_ref());
+
+		assert!(verify::<sr25519::Pair>(signature.as_ref(), b"message", &public).unwrap());
+		assert!(verify::<sr25519::Pair>(signature.as_ref(), b"message", "//Alice").unwrap());
+		assert!(!verify::<sr25519::Pair>(signature.as_ref(), b"other message", "//Alice").unwrap());
+		assert!(verify::<sr25519::Pair>(&[0; 3], b"message", "//Alice").is_err());
+	}
+}
@@ -27,11 +27,14 @@ use crate::{
 use names::{Generator, Name};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::config::{
-	Configuration, DatabaseConfig, ExtTransport, KeystoreConfig, NetworkConfiguration,
-	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-	TaskType, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+	Configuration, DatabaseConfig, ExtTransport, HealthConfig, KeystoreConfig, NetworkConfiguration,
+	NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, BlockPruning, ColdStorage, RateLimitConfig, Role,
+	DEFAULT_CANONICALIZATION_DELAY, DeltaArchive,
+	RpcInterfaceMethods, RpcMethodFilter, RpcMethods, SystemdNotifyConfig, TaskType, TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
 };
 use sc_service::{ChainSpec, TracingReceiver};
+use sc_network::config::SyncMode;
+use sc_tracing::logging::LogFile;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -145,6 +148,32 @@ pub trait CliConfiguration: Sized {
 		})
 	}
 
+	/// Check that the sync mode can be used along with the role and the pruning of the node.
+	///
+	/// Fast and warp sync only download the state of the latest finalized block, so they can't be
+	/// combined with an explicit `--pruning archive`, and light clients never download a state.
+	fn check_sync_mode(&self, sync_mode: SyncMode, role: &Role) -> Result<()> {
+		let name = match sync_mode {
+			SyncMode::Full => return Ok(()),
+			SyncMode::Fast => "fast",
+			SyncMode::Warp => "warp",
+		};
+		if let Role::Light = role {
+			return Err(format!("`--sync {}` is only supported by full nodes", name).into())
+		}
+		let archive = self.pruning_params()
+			.and_then(|p| p.pruning.as_ref())
+			.map_or(false, |pruning| pruning == "archive");
+		if archive {
+			return Err(format!(
+				"`--sync {}` doesn't download the states of the blocks before the latest finalized \
+				block and can't be used along with `--pruning archive`, use `--sync full` instead",
+				name,
+			).into())
+		}
+		Ok(())
+	}
+
 	/// Get the keystore configuration.
 	///
 	/// Bu default this is retrieved from `KeystoreParams` if it is available. Otherwise it uses
@@ -171,6 +200,20 @@ pub trait CliConfiguration: Sized {
 		Ok(self.database_params().and_then(|x| x.database()))
 	}
 
+	/// Get the configuration of the cold storage of the ancient blocks.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn cold_storage(&self) -> Result<Option<ColdStorage>> {
+		Ok(self.database_params().and_then(|x| x.cold_storage()))
+	}
+
+	/// Get the path to the logs of the read-only secondary instance of the database.
+	///
+	/// By default this is retrieved from `DatabaseParams` if it is available. Otherwise its `None`.
+	fn secondary_database_path(&self) -> Result<Option<PathBuf>> {
+		Ok(self.database_params().and_then(|x| x.secondary_database_path()))
+	}
+
 	/// Get the database configuration object for the parameters provided
 	fn database_config(
 		&self,
@@ -178,10 +221,21 @@ pub trait CliConfiguration: Sized {
 		cache_size: usize,
 		database: Database,
 	) -> Result<DatabaseConfig> {
+		let secondary_path = self.secondary_database_path()?;
+		if secondary_path.is_some() && !matches!(database, Database::RocksDb) {
+			return Err("Secondary instances of the database are only supported by the RocksDb backend".into());
+		}
 		Ok(match database {
-			Database::RocksDb => DatabaseConfig::RocksDb {
-				path: base_path.join("db"),
-				cache_size,
+			Database::RocksDb => match secondary_path {
+				Some(secondary_path) => DatabaseConfig::RocksDbSecondary {
+					path: base_path.join("db"),
+					secondary_path,
+					cache_size,
+				},
+				None => DatabaseConfig::RocksDb {
+					path: base_path.join("db"),
+					cache_size,
+				},
 			},
 			Database::SubDb => DatabaseConfig::SubDb {
 				path: base_path.join("subdb"),
@@ -201,6 +255,43 @@ pub trait CliConfiguration: Sized {
 			.unwrap_or(Default::default()))
 	}
 
+	/// Get the trie cache size.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
+	fn trie_cache_size(&self) -> Result<usize> {
+		Ok(self.import_params()
+			.map(|x| x.trie_cache_size())
+			.unwrap_or(Default::default()))
+	}
+
+	/// Returns `true` if the extrinsics of the imported blocks should be indexed by their hash.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `false`.
+	fn index_extrinsics(&self) -> Result<bool> {
+		Ok(self.import_params()
+			.map(|x| x.index_extrinsics())
+			.unwrap_or_default())
+	}
+
+	/// Returns `true` if the imported blocks should be indexed by the keys of the storage they
+	/// change.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `false`.
+	fn index_storage_changes(&self) -> Result<bool> {
+		Ok(self.import_params()
+			.map(|x| x.index_storage_changes())
+			.unwrap_or_default())
+	}
+
+	/// Get the SCALE-encoded block hashes pinned at given heights.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its empty.
+	fn pinned_blocks(&self) -> Result<Vec<(u64, Vec<u8>)>> {
+		Ok(self.import_params()
+			.map(|x| x.pinned_blocks())
+			.unwrap_or_default())
+	}
+
 	/// Get the state cache child ratio (if any).
 	///
 	/// By default this is `None`.
@@ -218,6 +309,31 @@ pub trait CliConfiguration: Sized {
 			.unwrap_or(Ok(Default::default()))
 	}
 
+	/// Get the pruning of the bodies and justifications of the blocks.
+	///
+	/// By default this is retrieved from `PruningParams` if it is available. Otherwise all the
+	/// bodies and justifications are kept.
+	fn block_pruning(&self) -> Result<BlockPruning> {
+		Ok(self.pruning_params().map(|x| x.block_pruning()).unwrap_or_default())
+	}
+
+	/// Get the configuration of the archive of the states as deltas.
+	///
+	/// By default this is retrieved from `PruningParams` if it is available. Otherwise its `None`.
+	fn delta_archive(&self) -> Result<Option<DeltaArchive>> {
+		Ok(self.pruning_params().and_then(|x| x.delta_archive()))
+	}
+
+	/// Get the number of blocks behind the best block from which the states are canonicalized.
+	///
+	/// By default this is retrieved from `PruningParams` if it is available. Otherwise its
+	/// `DEFAULT_CANONICALIZATION_DELAY`.
+	fn canonicalization_delay(&self) -> Result<u64> {
+		Ok(self.pruning_params()
+			.and_then(|x| x.canonicalization_delay)
+			.unwrap_or(DEFAULT_CANONICALIZATION_DELAY))
+	}
+
 	/// Get the chain ID (string).
 	///
 	/// By default this is retrieved from `SharedParams`.
@@ -266,6 +382,20 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the RPC IPC path (`None` if disabled).
+	///
+	/// By default this is `None`.
+	fn rpc_ipc(&self) -> Result<Option<String>> {
+		Ok(Default::default())
+	}
+
+	/// Get the gRPC gateway address (`None` if disabled).
+	///
+	/// By default this is `None`.
+	fn rpc_grpc(&self) -> Result<Option<SocketAddr>> {
+		Ok(Default::default())
+	}
+
 	/// Returns the RPC method set to expose.
 	///
 	/// By default this is `RpcMethods::Auto` (unsafe RPCs are denied iff
@@ -274,6 +404,14 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Returns the RPC methods to expose on each RPC server, instead of the ones of
+	/// `rpc_methods`.
+	///
+	/// By default every server exposes the methods of `rpc_methods`.
+	fn rpc_interface_methods(&self) -> Result<RpcInterfaceMethods> {
+		Ok(Default::default())
+	}
+
 	/// Get the RPC websockets maximum connections (`None` if unlimited).
 	///
 	/// By default this is `None`.
@@ -281,6 +419,48 @@ pub trait CliConfiguration: Sized {
 		Ok(Default::default())
 	}
 
+	/// Get the RPC websockets maximum subscriptions per connection (`None` if default).
+	///
+	/// By default this is `None`.
+	fn rpc_ws_max_subscriptions_per_connection(&self) -> Result<Option<usize>> {
+		Ok(Default::default())
+	}
+
+	/// Get the maximum size in bytes of the RPC requests (`None` if default).
+	///
+	/// By default this is `None`.
+	fn rpc_max_request_size(&self) -> Result<Option<usize>> {
+		Ok(Default::default())
+	}
+
+	/// Get the maximum size in bytes of the RPC responses (`None` if default).
+	///
+	/// By default this is `None`.
+	fn rpc_max_response_size(&self) -> Result<Option<usize>> {
+		Ok(Default::default())
+	}
+
+	/// Get the maximum number of calls in a RPC batch request (`None` if default).
+	///
+	/// By default this is `None`.
+	fn rpc_max_batch_size(&self) -> Result<Option<usize>> {
+		Ok(Default::default())
+	}
+
+	/// Get the rate limits of the RPC calls.
+	///
+	/// By default nothing is limited.
+	fn rpc_rate_limit(&self) -> Result<RateLimitConfig> {
+		Ok(Default::default())
+	}
+
+	/// Get the filter of the RPC methods that can be called.
+	///
+	/// By default every method can be called.
+	fn rpc_method_filter(&self) -> Result<RpcMethodFilter> {
+		Ok(Default::default())
+	}
+
 	/// Get the RPC cors (`None` if disabled)
 	///
 	/// By default this is `None`.
@@ -393,6 +573,27 @@ pub trait CliConfiguration: Sized {
 		Ok(true)
 	}
 
+	/// Get the configuration of the notifications to systemd
+	///
+	/// By default this is `None`.
+	fn systemd_notify(&self) -> Result<Option<SystemdNotifyConfig>> {
+		Ok(None)
+	}
+
+	/// Get the configuration of the HTTP health endpoint (`None` if disabled)
+	///
+	/// By default this is `None`.
+	fn health(&self) -> Result<Option<HealthConfig>> {
+		Ok(None)
+	}
+
+	/// Get the format of the output of the informant.
+	///
+	/// By default this is `OutputFormat::Coloured`.
+	fn output_format(&self) -> Result<sc_informant::OutputFormat> {
+		Ok(sc_informant::OutputFormat::Coloured)
+	}
+
 	/// Create a Configuration object from the current object
 	fn create_configuration<C: SubstrateCli>(
 		&self,
@@ -404,12 +605,7 @@ pub trait CliConfiguration: Sized {
 		let chain_spec = cli.load_spec(chain_id.as_str())?;
 		let config_dir = self
 			.base_path()?
-			.unwrap_or_else(|| {
-				directories::ProjectDirs::from("", "", C::executable_name())
-					.expect("app directories exist on all supported platforms; qed")
-					.data_local_dir()
-					.into()
-			})
+			.unwrap_or_else(default_base_path::<C>)
 			.join("chains")
 			.join(chain_spec.id());
 		let net_config_dir = config_dir.join(DEFAULT_NETWORK_CONFIG_PATH);
@@ -424,31 +620,51 @@ pub trait CliConfiguration: Sized {
 			.import_params()
 			.map(|p| p.unsafe_pruning)
 			.unwrap_or(false);
+		let network = self.network_config(
+			&chain_spec,
+			is_dev,
+			net_config_dir,
+			client_id.as_str(),
+			self.node_name()?.as_str(),
+			node_key,
+		)?;
+		let pruning = self.pruning(unsafe_pruning, &role)?;
+		self.check_sync_mode(network.sync_mode, &role)?;
 
 		Ok(Configuration {
 			impl_name: C::impl_name(),
 			impl_version: C::impl_version(),
 			task_executor,
+			shared_task_manager: None,
 			transaction_pool: self.transaction_pool()?,
-			network: self.network_config(
-				&chain_spec,
-				is_dev,
-				net_config_dir,
-				client_id.as_str(),
-				self.node_name()?.as_str(),
-				node_key,
-			)?,
+			network,
 			keystore: self.keystore_config(&config_dir)?,
 			database: self.database_config(&config_dir, database_cache_size, database)?,
 			state_cache_size: self.state_cache_size()?,
 			state_cache_child_ratio: self.state_cache_child_ratio()?,
-			pruning: self.pruning(unsafe_pruning, &role)?,
+			trie_cache_size: self.trie_cache_size()?,
+			pruning,
+			block_pruning: self.block_pruning()?,
+			index_extrinsics: self.index_extrinsics()?,
+			index_storage_changes: self.index_storage_changes()?,
+			cold_storage: self.cold_storage()?,
+			delta_archive: self.delta_archive()?,
+			canonicalization_delay: self.canonicalization_delay()?,
 			wasm_method: self.wasm_method()?,
 			execution_strategies: self.execution_strategies(is_dev)?,
 			rpc_http: self.rpc_http()?,
 			rpc_ws: self.rpc_ws()?,
+			rpc_ipc: self.rpc_ipc()?,
+			rpc_grpc: self.rpc_grpc()?,
 			rpc_methods: self.rpc_methods()?,
+			rpc_interface_methods: self.rpc_interface_methods()?,
 			rpc_ws_max_connections: self.rpc_ws_max_connections()?,
+			rpc_ws_max_subscriptions_per_connection: self.rpc_ws_max_subscriptions_per_connection()?,
+			rpc_max_request_size: self.rpc_max_request_size()?,
+			rpc_max_response_size: self.rpc_max_response_size()?,
+			rpc_max_batch_size: self.rpc_max_batch_size()?,
+			rpc_rate_limit: self.rpc_rate_limit()?,
+			rpc_method_filter: self.rpc_method_filter()?,
 			rpc_cors: self.rpc_cors(is_dev)?,
 			prometheus_config: self.prometheus_config()?,
 			telemetry_endpoints: self.telemetry_endpoints(&chain_spec)?,
@@ -460,9 +676,12 @@ pub trait CliConfiguration: Sized {
 			dev_key_seed: self.dev_key_seed(is_dev)?,
 			tracing_targets: self.tracing_targets()?,
 			tracing_receiver: self.tracing_receiver()?,
+			pinned_blocks: self.pinned_blocks()?,
 			chain_spec,
 			max_runtime_instances,
 			announce_block: self.announce_block()?,
+			systemd_notify: self.systemd_notify()?,
+			health: self.health()?,
 			role,
 		})
 	}
@@ -477,6 +696,13 @@ pub trait CliConfiguration: Sized {
 		Ok(self.shared_params().log_filters().join(","))
 	}
 
+	/// Get the file the logs are written to, if any.
+	///
+	/// By default this is retrieved from `SharedParams`.
+	fn log_file(&self) -> Result<Option<LogFile>> {
+		Ok(self.shared_params().log_file())
+	}
+
 	/// Initialize substrate. This must be done only once.
 	///
 	/// This method:
@@ -490,7 +716,7 @@ pub trait CliConfiguration: Sized {
 		sp_panic_handler::set(C::support_url(), C::impl_version());
 
 		fdlimit::raise_fd_limit();
-		init_logger(&logger_pattern);
+		init_logger(&logger_pattern, self.log_file()?)?;
 
 		Ok(())
 	}
@@ -509,3 +735,11 @@ pub fn generate_node_name() -> String {
 		}
 	};
 }
+
+/// Returns the default base path of the node, in the local data directory of the user.
+pub(crate) fn default_base_path<C: SubstrateCli>() -> PathBuf {
+	directories::ProjectDirs::from("", "", C::executable_name())
+		.expect("app directories exist on all supported platforms; qed")
+		.data_local_dir()
+		.into()
+}
@@ -0,0 +1,207 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Loading of the command line options from the configuration file given with `--config`.
+//!
+//! The keys of the file are the long names of the options, e.g. `rpc-port = 9933`, and may be
+//! grouped in sections, e.g. `[network]`, which are only there for readability. Flags are
+//! booleans, and options given multiple times are arrays.
+
+use std::{ffi::OsString, fs, path::Path};
+use structopt::clap::{Error, ErrorKind, Result};
+use toml::Value;
+
+/// Option giving the path of the configuration file.
+const CONFIG_OPTION: &str = "--config";
+
+/// Short aliases of the long options, which override the file too.
+const SHORT_OPTIONS: &[(&str, &str)] = &[("--base-path", "-d"), ("--log", "-l")];
+
+/// Inserts the options of the configuration file given with `--config`, if any, right after it.
+///
+/// The options also given on the command line are left out, so that the command line overrides
+/// the file.
+pub(crate) fn expand_args<I>(iter: I) -> Result<Vec<OsString>>
+where
+	I: IntoIterator,
+	I::Item: Into<OsString>,
+{
+	let mut args = iter.into_iter().map(Into::into).collect::<Vec<OsString>>();
+	let position = match args.iter().position(|arg| is_option(arg, CONFIG_OPTION)) {
+		Some(position) => position,
+		None => return Ok(args),
+	};
+	let (path, insert_at) = if args[position] == CONFIG_OPTION {
+		let path = args.get(position + 1)
+			.ok_or_else(|| error(format!("The argument '{}' requires a path", CONFIG_OPTION)))?;
+		(Path::new(path).to_owned(), position + 2)
+	} else {
+		let arg = args[position].to_str().expect("`is_option` only matches UTF-8 arguments; qed");
+		(Path::new(&arg[CONFIG_OPTION.len() + 1..]).to_owned(), position + 1)
+	};
+
+	let content = fs::read_to_string(&path)
+		.map_err(|e| error(format!("Failed to read the configuration file {:?}: {}", path, e)))?;
+	let table = toml::from_str(&content)
+		.map_err(|e| error(format!("Invalid configuration file {:?}: {}", path, e)))?;
+	let mut options = Vec::new();
+	collect_options(table, &mut options)?;
+
+	let file_args = options.into_iter()
+		.filter(|(option, _)| !args.iter().any(|arg| is_option(arg, option)))
+		.flat_map(|(_, option_args)| option_args)
+		.map(OsString::from)
+		.collect::<Vec<_>>();
+	args.splice(insert_at..insert_at, file_args);
+	Ok(args)
+}
+
+/// Returns whether `arg` is the long option `option`, or its short alias, with or without an
+/// inline value.
+fn is_option(arg: &OsString, option: &str) -> bool {
+	arg.to_str().map_or(false, |arg| {
+		let is_long = arg == option
+			|| (arg.starts_with(option) && arg[option.len()..].starts_with('='));
+		let is_short = SHORT_OPTIONS.iter()
+			.any(|(long, short)| *long == option && arg.starts_with(short));
+		is_long || is_short
+	})
+}
+
+/// Pushes the options of `table`, and their arguments, to `options`.
+fn collect_options(table: toml::value::Table, options: &mut Vec<(String, Vec<String>)>) -> Result<()> {
+	for (key, value) in table {
+		let option = format!("--{}", key);
+		if option == CONFIG_OPTION {
+			return Err(error("The configuration file can't include another one".into()))
+		}
+		let option_args = match value {
+			Value::Table(section) => {
+				collect_options(section, options)?;
+				continue
+			},
+			Value::Boolean(true) => vec![option.clone()],
+			Value::Boolean(false) => vec![],
+			Value::Array(values) => values.into_iter()
+				.map(|value| Ok(format!("{}={}", option, scalar(&key, value)?)))
+				.collect::<Result<_>>()?,
+			value => vec![format!("{}={}", option, scalar(&key, value)?)],
+		};
+		options.push((option, option_args));
+	}
+	Ok(())
+}
+
+/// Returns the argument of the option `key` with the value `value`.
+fn scalar(key: &str, value: Value) -> Result<String> {
+	match value {
+		Value::String(value) => Ok(value),
+		Value::Integer(value) => Ok(value.to_string()),
+		Value::Float(value) => Ok(value.to_string()),
+		_ => Err(error(format!("Invalid value of `{}` in the configuration file", key))),
+	}
+}
+
+fn error(message: String) -> Error {
+	Error::with_description(&message, ErrorKind::InvalidValue)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn command_line_overrides_the_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, r#"
+			validator = true
+			unsafe-rpc-external = false
+			name = "alice"
+
+			[rpc]
+			rpc-port = 9933
+
+			[network]
+			bootnodes = ["/ip4/127.0.0.1/tcp/30333", "/ip4/127.0.0.1/tcp/30334"]
+		"#).unwrap();
+
+		let args = expand_args(vec![
+			"node".into(),
+			"--config".into(),
+			path.clone().into_os_string(),
+			"--name=bob".into(),
+		]).unwrap();
+
+		assert_eq!(args, vec![
+			"node",
+			"--config",
+			path.to_str().unwrap(),
+			"--bootnodes=/ip4/127.0.0.1/tcp/30333",
+			"--bootnodes=/ip4/127.0.0.1/tcp/30334",
+			"--rpc-port=9933",
+			"--validator",
+			"--name=bob",
+		].into_iter().map(OsString::from).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn short_aliases_override_the_file() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, r#"
+			base-path = "/tmp/alice"
+			log = ["sync=debug"]
+			name = "alice"
+		"#).unwrap();
+
+		let args = expand_args(vec![
+			"node".into(),
+			"--config".into(),
+			path.clone().into_os_string(),
+			"-d".into(),
+			"/tmp/bob".into(),
+			"-lsync=trace".into(),
+		]).unwrap();
+
+		assert_eq!(args, vec![
+			"node",
+			"--config",
+			path.to_str().unwrap(),
+			"--name=alice",
+			"-d",
+			"/tmp/bob",
+			"-lsync=trace",
+		].into_iter().map(OsString::from).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn args_without_config_are_unchanged() {
+		let args = vec![OsString::from("node"), "--configuration".into()];
+		assert_eq!(expand_args(args.clone()).unwrap(), args);
+	}
+
+	#[test]
+	fn invalid_values_are_rejected() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("node.toml");
+		fs::write(&path, "bootnodes = [[\"nested\"]]").unwrap();
+
+		assert!(expand_args(vec![OsString::from("node"), format!("--config={}", path.display()).into()]).is_err());
+	}
+}
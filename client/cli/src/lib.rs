@@ -24,6 +24,7 @@
 mod arg_enums;
 mod commands;
 mod config;
+mod config_file;
 mod error;
 mod params;
 mod runner;
@@ -38,6 +39,7 @@ pub use params::*;
 use regex::Regex;
 pub use runner::*;
 use sc_service::{ChainSpec, Configuration, TaskType};
+use sc_tracing::logging::{InitError, LogFile};
 use std::future::Future;
 use std::io::Write;
 use std::pin::Pin;
@@ -107,6 +109,9 @@ pub trait SubstrateCli: Sized {
 	/// To allow running the node without subcommand, it also sets a few more settings:
 	/// `AppSettings::ArgsNegateSubcommands` and `AppSettings::SubcommandsNegateReqs`.
 	///
+	/// The options of the configuration file given with `--config` are added to the arguments,
+	/// unless they are also given in `iter`.
+	///
 	/// Gets the struct from any iterator such as a `Vec` of your making.
 	/// Print the error message and quit the program in case of failure.
 	fn from_iter<I>(iter: I) -> Self
@@ -131,7 +136,7 @@ pub trait SubstrateCli: Sized {
 				AppSettings::SubcommandsNegateReqs,
 			]);
 
-		let matches = match app.get_matches_from_safe(iter) {
+		let matches = match config_file::expand_args(iter).and_then(|args| app.get_matches_from_safe(args)) {
 			Ok(matches) => matches,
 			Err(mut e) => {
 				// To support pipes, we can not use `writeln!` as any error
@@ -185,7 +190,7 @@ pub trait SubstrateCli: Sized {
 			.about(Self::description())
 			.version(full_version.as_str());
 
-		let matches = app.get_matches_from_safe(iter)?;
+		let matches = app.get_matches_from_safe(config_file::expand_args(iter)?)?;
 
 		Ok(<Self as StructOpt>::from_clap(&matches))
 	}
@@ -212,57 +217,24 @@ pub trait SubstrateCli: Sized {
 	}
 }
 
-/// Initialize the logger
-pub fn init_logger(pattern: &str) {
-	use ansi_term::Colour;
-
-	let mut builder = env_logger::Builder::new();
-	// Disable info logging by default for some modules:
-	builder.filter(Some("ws"), log::LevelFilter::Off);
-	builder.filter(Some("yamux"), log::LevelFilter::Off);
-	builder.filter(Some("hyper"), log::LevelFilter::Warn);
-	builder.filter(Some("cranelift_wasm"), log::LevelFilter::Warn);
-	// Always log the special target `sc_tracing`, overrides global level
-	builder.filter(Some("sc_tracing"), log::LevelFilter::Info);
-	// Enable info for others.
-	builder.filter(None, log::LevelFilter::Info);
+/// Initialize the logger, also writing to `log_file` if any.
+pub fn init_logger(pattern: &str, log_file: Option<LogFile>) -> error::Result<()> {
+	// Disable info logging by default for some modules, always log the special target
+	// `sc_tracing`, overriding the global level, and enable info for others.
+	let mut directives = String::from("ws=off,yamux=off,hyper=warn,cranelift_wasm=warn,sc_tracing=info,info");
 
 	if let Ok(lvl) = std::env::var("RUST_LOG") {
-		builder.parse_filters(&lvl);
+		directives.push(',');
+		directives.push_str(&lvl);
 	}
 
-	builder.parse_filters(pattern);
+	directives.push(',');
+	directives.push_str(pattern);
 	let isatty = atty::is(atty::Stream::Stderr);
 	let enable_color = isatty;
 
-	builder.format(move |buf, record| {
-		let now = time::now();
-		let timestamp =
-			time::strftime("%Y-%m-%d %H:%M:%S", &now).expect("Error formatting log timestamp");
-
-		let mut output = if log::max_level() <= log::LevelFilter::Info {
-			format!(
-				"{} {}",
-				Colour::Black.bold().paint(timestamp),
-				record.args(),
-			)
-		} else {
-			let name = ::std::thread::current()
-				.name()
-				.map_or_else(Default::default, |x| {
-					format!("{}", Colour::Blue.bold().paint(x))
-				});
-			let millis = (now.tm_nsec as f32 / 1000000.0).floor() as usize;
-			let timestamp = format!("{}.{}", timestamp, millis);
-			format!(
-				"{} {} {} {}  {}",
-				Colour::Black.bold().paint(timestamp),
-				name,
-				record.level(),
-				record.target(),
-				record.args()
-			)
-		};
+	let console = move |record: &log::Record| {
+		let mut output = format_record(record);
 
 		if !isatty && record.level() <= log::Level::Info && atty::is(atty::Stream::Stdout) {
 			// duplicate INFO/WARN output to console
@@ -273,11 +245,49 @@ pub fn init_logger(pattern: &str) {
 			output = kill_color(output.as_ref());
 		}
 
-		writeln!(buf, "{}", output)
-	});
+		let _ = writeln!(std::io::stderr(), "{}", output);
+	};
+	let format = |record: &log::Record| kill_color(&format_record(record));
+
+	match sc_tracing::logging::init_logger(directives, console, format, log_file) {
+		Ok(()) => Ok(()),
+		Err(InitError::AlreadyRegistered) => {
+			info!("💬 Not registering Substrate logger, as there is already a global logger registered!");
+			Ok(())
+		},
+		Err(err @ InitError::LogFile(_)) => Err(err.to_string().into()),
+	}
+}
+
+fn format_record(record: &log::Record) -> String {
+	use ansi_term::Colour;
 
-	if builder.try_init().is_err() {
-		info!("💬 Not registering Substrate logger, as there is already a global logger registered!");
+	let now = time::now();
+	let timestamp =
+		time::strftime("%Y-%m-%d %H:%M:%S", &now).expect("Error formatting log timestamp");
+
+	if log::max_level() <= log::LevelFilter::Info {
+		format!(
+			"{} {}",
+			Colour::Black.bold().paint(timestamp),
+			record.args(),
+		)
+	} else {
+		let name = ::std::thread::current()
+			.name()
+			.map_or_else(Default::default, |x| {
+				format!("{}", Colour::Blue.bold().paint(x))
+			});
+		let millis = (now.tm_nsec as f32 / 1000000.0).floor() as usize;
+		let timestamp = format!("{}.{}", timestamp, millis);
+		format!(
+			"{} {} {} {}  {}",
+			Colour::Black.bold().paint(timestamp),
+			name,
+			record.level(),
+			record.target(),
+			record.args()
+		)
 	}
 }
 
@@ -17,6 +17,8 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::arg_enums::Database;
+use sc_service::config::ColdStorage;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
 /// Parameters for block import.
@@ -34,6 +36,25 @@ pub struct DatabaseParams {
 	/// Limit the memory the database cache can use.
 	#[structopt(long = "db-cache", value_name = "MiB")]
 	pub database_cache_size: Option<usize>,
+
+	/// Move the bodies and justifications of the ancient finalized blocks to a secondary
+	/// database at this path.
+	///
+	/// The blocks are still served, reading them from the secondary database.
+	#[structopt(long = "cold-database-path", value_name = "PATH", parse(from_os_str))]
+	pub cold_database_path: Option<PathBuf>,
+
+	/// Number of finalized blocks whose bodies and justifications stay in the main database
+	/// when `--cold-database-path` is set.
+	#[structopt(long = "cold-after", value_name = "BLOCKS", default_value = "100000")]
+	pub cold_after: u32,
+
+	/// Open the RocksDb database of another node as a read-only secondary instance, writing the
+	/// logs of the secondary instance to this path.
+	///
+	/// The secondary instance regularly catches up with the blocks imported by the other node.
+	#[structopt(long = "secondary-database-path", value_name = "PATH", parse(from_os_str))]
+	pub secondary_database_path: Option<PathBuf>,
 }
 
 impl DatabaseParams {
@@ -46,4 +67,14 @@ impl DatabaseParams {
 	pub fn database_cache_size(&self) -> Option<usize> {
 		self.database_cache_size
 	}
+
+	/// Configuration of the cold storage of the ancient blocks, if enabled.
+	pub fn cold_storage(&self) -> Option<ColdStorage> {
+		self.cold_database_path.clone().map(|path| ColdStorage { path, keep_hot: self.cold_after })
+	}
+
+	/// Path to the logs of the read-only secondary instance of the database, if enabled.
+	pub fn secondary_database_path(&self) -> Option<PathBuf> {
+		self.secondary_database_path.clone()
+	}
 }
@@ -67,6 +67,29 @@ pub struct ImportParams {
 	)]
 	pub state_cache_size: usize,
 
+	/// Specify the size of the cache of trie nodes shared by all the states.
+	#[structopt(
+		long = "trie-cache-size",
+		value_name = "Bytes",
+		default_value = "67108864"
+	)]
+	pub trie_cache_size: usize,
+
+	/// Index the extrinsics of the imported blocks by their hash.
+	///
+	/// The blocks including the extrinsics can then be found with the `chain_getExtrinsic` RPC
+	/// method. The blocks imported before the index is enabled are not indexed.
+	#[structopt(long = "index-extrinsics")]
+	pub index_extrinsics: bool,
+
+	/// Index the imported blocks by the keys of the storage they change.
+	///
+	/// The `state_queryStorage` RPC methods then only read the blocks changing the queried keys.
+	/// The blocks imported before the index is enabled are not indexed, and the index is
+	/// discarded if a block is imported while it is disabled.
+	#[structopt(long = "index-storage-changes")]
+	pub index_storage_changes: bool,
+
 	/// Comma separated list of targets for tracing.
 	#[structopt(long = "tracing-targets", value_name = "TARGETS")]
 	pub tracing_targets: Option<String>,
@@ -80,6 +103,14 @@ pub struct ImportParams {
 		default_value = "Log"
 	)]
 	pub tracing_receiver: TracingReceiver,
+
+	/// Pin the hash of the block at the given height, as `NUMBER=HASH`.
+	///
+	/// Competing blocks at this height are rejected on import, which allows a coordinated
+	/// recovery of the network from a contentious fork. Can be passed multiple times, and
+	/// changed at runtime with the `system_pinBlock` and `system_unpinBlock` RPC methods.
+	#[structopt(long = "pin-block", value_name = "NUMBER=HASH", parse(try_from_str = parse_pinned_block))]
+	pub pinned_blocks: Vec<(u64, Vec<u8>)>,
 }
 
 impl ImportParams {
@@ -93,11 +124,31 @@ impl ImportParams {
 		self.tracing_targets.clone()
 	}
 
+	/// The SCALE-encoded block hashes pinned at given heights.
+	pub fn pinned_blocks(&self) -> Vec<(u64, Vec<u8>)> {
+		self.pinned_blocks.clone()
+	}
+
 	/// Specify the state cache size.
 	pub fn state_cache_size(&self) -> usize {
 		self.state_cache_size
 	}
 
+	/// Specify the trie cache size.
+	pub fn trie_cache_size(&self) -> usize {
+		self.trie_cache_size
+	}
+
+	/// Whether the extrinsics of the imported blocks are indexed by their hash.
+	pub fn index_extrinsics(&self) -> bool {
+		self.index_extrinsics
+	}
+
+	/// Whether the imported blocks are indexed by the keys of the storage they change.
+	pub fn index_storage_changes(&self) -> bool {
+		self.index_storage_changes
+	}
+
 	/// Get the WASM execution method from the parameters
 	pub fn wasm_method(&self) -> sc_service::config::WasmExecutionMethod {
 		self.wasm_method.into()
@@ -129,6 +180,18 @@ impl ImportParams {
 	}
 }
 
+fn parse_pinned_block(pinned: &str) -> Result<(u64, Vec<u8>), String> {
+	let mut parts = pinned.splitn(2, '=');
+	let number = parts.next()
+		.and_then(|number| number.parse().ok())
+		.ok_or_else(|| format!("Invalid block number in `{}`, expected `NUMBER=HASH`", pinned))?;
+	let hash = parts.next()
+		.map(|hash| hash.trim_start_matches("0x"))
+		.and_then(|hash| hex::decode(hash).ok())
+		.ok_or_else(|| format!("Invalid block hash in `{}`, expected `NUMBER=HASH`", pinned))?;
+	Ok((number, hash))
+}
+
 /// Execution strategies parameters.
 #[derive(Debug, StructOpt, Clone)]
 pub struct ExecutionStrategiesParams {
@@ -0,0 +1,51 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::arg_enums::OutputType;
+use sp_core::crypto::Ss58AddressFormat;
+use std::convert::TryFrom;
+use structopt::StructOpt;
+
+/// Parameters of the output of the key commands.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyOutputParams {
+	/// The network of the SS58 addresses, by name or by number.
+	///
+	/// Default is the generic Substrate network.
+	#[structopt(long, value_name = "NETWORK", parse(try_from_str = parse_ss58_address_format))]
+	pub network: Option<Ss58AddressFormat>,
+
+	/// The output format.
+	#[structopt(
+		long = "output-type",
+		value_name = "FORMAT",
+		possible_values = &OutputType::variants(),
+		case_insensitive = true,
+		default_value = "Text"
+	)]
+	pub output_type: OutputType,
+}
+
+fn parse_ss58_address_format(network: &str) -> Result<Ss58AddressFormat, String> {
+	Ss58AddressFormat::try_from(network).map_err(|_| {
+		let networks = Ss58AddressFormat::all().iter()
+			.map(|format| String::from(*format))
+			.collect::<Vec<_>>();
+		format!("Invalid network `{}`, expected a number or one of {}", network, networks.join(", "))
+	})
+}
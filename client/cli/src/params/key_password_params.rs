@@ -0,0 +1,50 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::error::Result;
+use structopt::StructOpt;
+
+/// Parameters of the password of a secret key.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KeyPasswordParams {
+	/// The password of the secret key.
+	#[structopt(long, conflicts_with = "password-interactive")]
+	pub password: Option<String>,
+
+	/// Use interactive shell for entering the password of the secret key.
+	#[structopt(long = "password-interactive")]
+	pub password_interactive: bool,
+}
+
+impl KeyPasswordParams {
+	/// Returns the password of the secret key, prompting for it if requested.
+	pub fn read_password(&self) -> Result<Option<String>> {
+		if self.password_interactive {
+			#[cfg(not(target_os = "unknown"))]
+			{
+				rpassword::read_password_from_tty(Some("Key password: "))
+					.map(Some)
+					.map_err(|e| format!("{:?}", e).into())
+			}
+			#[cfg(target_os = "unknown")]
+			Ok(None)
+		} else {
+			Ok(self.password.clone())
+		}
+	}
+}
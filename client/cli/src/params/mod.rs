@@ -15,8 +15,11 @@
 
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
+mod crypto_scheme_params;
 mod database_params;
 mod import_params;
+mod key_output_params;
+mod key_password_params;
 mod keystore_params;
 mod network_params;
 mod node_key_params;
@@ -28,8 +31,11 @@ mod transaction_pool_params;
 use std::{fmt::Debug, str::FromStr};
 use sp_runtime::{generic::BlockId, traits::{Block as BlockT, NumberFor}};
 
+pub use crate::params::crypto_scheme_params::*;
 pub use crate::params::database_params::*;
 pub use crate::params::import_params::*;
+pub use crate::params::key_output_params::*;
+pub use crate::params::key_password_params::*;
 pub use crate::params::keystore_params::*;
 pub use crate::params::network_params::*;
 pub use crate::params::node_key_params::*;
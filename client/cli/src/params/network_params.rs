@@ -16,13 +16,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::arg_enums::SyncMode;
 use crate::params::node_key_params::NodeKeyParams;
 use sc_network::{
-	config::{NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, TransportConfig},
+	config::{
+		ConnectionLimits, IpFilter, IpNetwork, LightServeLimits, NetworkConfiguration, NodeKeyConfig,
+		NonReservedPeerMode, TrafficDumpConfig, TransactionPropagationConfig, TransportConfig,
+	},
 	multiaddr::Protocol,
+	PeerId,
 };
 use sc_service::{ChainSpec, config::{Multiaddr, MultiaddrWithPeerId}};
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 use structopt::StructOpt;
 
 /// Parameters used to create the network configuration.
@@ -62,6 +67,35 @@ pub struct NetworkParams {
 	#[structopt(long = "no-private-ipv4")]
 	pub no_private_ipv4: bool,
 
+	/// Only accept incoming connections from this IP range, in CIDR notation (for example
+	/// `10.0.0.0/8`). Can be passed multiple times.
+	///
+	/// If not passed, incoming connections are accepted from any address not matching
+	/// `--ip-deny`. Outgoing connections are never filtered.
+	#[structopt(long = "ip-allow", value_name = "CIDR")]
+	pub ip_allow: Vec<IpNetwork>,
+
+	/// Reject incoming connections from this IP range, in CIDR notation (for example
+	/// `198.51.100.0/24`). Can be passed multiple times and takes precedence over `--ip-allow`.
+	#[structopt(long = "ip-deny", value_name = "CIDR")]
+	pub ip_deny: Vec<IpNetwork>,
+
+	/// Maximum number of simultaneous incoming connections from a single IP address.
+	///
+	/// Unlimited if not specified.
+	#[structopt(long = "max-connections-per-ip", value_name = "COUNT")]
+	pub max_connections_per_ip: Option<usize>,
+
+	/// Maximum number of simultaneous incoming connections from a single subnet.
+	///
+	/// Unlimited if not specified. See `--subnet-prefix` for the size of the subnets.
+	#[structopt(long = "max-connections-per-subnet", value_name = "COUNT")]
+	pub max_connections_per_subnet: Option<usize>,
+
+	/// Length of the prefix of the IPv4 subnets `--max-connections-per-subnet` applies to.
+	#[structopt(long = "subnet-prefix", value_name = "BITS", default_value = "24")]
+	pub subnet_prefix: u8,
+
 	/// Specify the number of outgoing connections we're trying to maintain.
 	#[structopt(long = "out-peers", value_name = "COUNT", default_value = "25")]
 	pub out_peers: u32,
@@ -88,6 +122,100 @@ pub struct NetworkParams {
 	)]
 	pub max_parallel_downloads: u32,
 
+	/// Blockchain syncing mode.
+	///
+	/// `Warp` downloads a proof of finality of the latest finalized block along with the state
+	/// of that block, instead of importing every block since genesis. It only applies when the
+	/// database is empty and requires the node to support it.
+	///
+	/// `Fast` downloads the headers of every block, then the state of the latest finalized block,
+	/// and only executes the blocks that follow it. It only applies when the database is empty.
+	///
+	/// Neither can be used by light clients or along with `--pruning archive`.
+	#[structopt(
+		long = "sync",
+		value_name = "SYNC_MODE",
+		possible_values = &SyncMode::variants(),
+		case_insensitive = true,
+		default_value = "Full"
+	)]
+	pub sync: SyncMode,
+
+	/// Only download blocks and justifications from these peers.
+	///
+	/// Block announces are still accepted from every peer. Meant for bootstrapping private
+	/// networks or recovering from an eclipse attack.
+	#[structopt(long = "trusted-sync-peer", value_name = "PEER_ID")]
+	pub trusted_sync_peers: Vec<PeerId>,
+
+	/// Record the messages exchanged with other nodes to this file, for debugging purposes.
+	///
+	/// Each message is written as a line of JSON. The file is rotated once it reaches 64 MiB, and
+	/// the 4 previous files are kept alongside it with a numeric suffix.
+	#[structopt(long = "traffic-dump", value_name = "PATH", parse(from_os_str))]
+	pub traffic_dump: Option<PathBuf>,
+
+	/// Include the payload of the messages in the file passed with `--traffic-dump`.
+	#[structopt(long = "traffic-dump-payloads", requires = "traffic-dump")]
+	pub traffic_dump_payloads: bool,
+
+	/// Refuse the connections that don't complete the Noise handshake specified by libp2p.
+	///
+	/// Nodes falling back to the legacy Noise handshake aren't able to connect to this node, and
+	/// the other way around.
+	#[structopt(long = "secure-transport-only")]
+	pub secure_transport_only: bool,
+
+	/// Send an empty message on the notifications substreams every time nothing has been sent on
+	/// them for this many seconds.
+	///
+	/// Prevents NATs and firewalls from closing idle substreams. Older nodes don't recognize
+	/// these messages, so only enable this if the other nodes of the network support it.
+	#[structopt(long = "notifications-keep-alive", value_name = "SECONDS")]
+	pub notifications_keep_alive: Option<u64>,
+
+	/// Reduce the reputation of peers that don't read the notifications we send them for this
+	/// many seconds, until they end up being disconnected.
+	///
+	/// `0` disables the check.
+	#[structopt(long = "notifications-stall-timeout", value_name = "SECONDS", default_value = "30")]
+	pub notifications_stall_timeout: u64,
+
+	/// Maximum number of transactions sent to a peer in a single message.
+	#[structopt(long = "tx-batch-size", value_name = "COUNT", default_value = "256")]
+	pub tx_batch_size: usize,
+
+	/// Maximum number of transactions sent to each peer per second.
+	///
+	/// Transactions above the limit are sent at the next periodic propagation. Unlimited if not
+	/// specified.
+	#[structopt(long = "tx-rate-limit", value_name = "COUNT")]
+	pub tx_rate_limit: Option<u32>,
+
+	/// Maximum number of peers transactions are propagated to, picked randomly every time.
+	///
+	/// Reduces the bandwidth used on networks where nodes have many peers, at the cost of a
+	/// slower propagation. Transactions are propagated to all peers if not specified.
+	#[structopt(long = "tx-propagate-to-peers", value_name = "COUNT")]
+	pub tx_propagate_to_peers: Option<usize>,
+
+	/// Maximum number of requests served to each light client per second.
+	#[structopt(long = "light-max-requests-per-sec", value_name = "COUNT")]
+	pub light_max_requests_per_sec: Option<u32>,
+
+	/// Maximum number of bytes of proofs sent to each light client per second.
+	#[structopt(long = "light-max-proof-bytes-per-sec", value_name = "BYTES")]
+	pub light_max_proof_bytes_per_sec: Option<u64>,
+
+	/// Maximum number of requests from each light client being answered at the same time.
+	#[structopt(long = "light-max-concurrent-requests", value_name = "COUNT")]
+	pub light_max_concurrent_requests: Option<usize>,
+
+	/// Serve the data written by the runtime through offchain indexing to IPFS clients, over
+	/// the Bitswap protocol.
+	#[structopt(long = "ipfs-server")]
+	pub ipfs_server: bool,
+
 	#[allow(missing_docs)]
 	#[structopt(flatten)]
 	pub node_key_params: NodeKeyParams,
@@ -152,6 +280,7 @@ impl NetworkParams {
 			listen_addresses,
 			public_addresses,
 			notifications_protocols: Vec::new(),
+			acknowledged_notifications_protocols: Default::default(),
 			node_key,
 			node_name: node_name.to_string(),
 			client_version: client_id.to_string(),
@@ -165,7 +294,49 @@ impl NetworkParams {
 			},
 			max_parallel_downloads: self.max_parallel_downloads,
 			allow_non_globals_in_dht: self.discover_local || is_dev,
+			secure_transport_only: self.secure_transport_only,
+			ip_filter: IpFilter {
+				allow: if self.ip_allow.is_empty() {
+					None
+				} else {
+					Some(self.ip_allow.clone())
+				},
+				deny: self.ip_deny.clone(),
+			},
+			connection_limits: ConnectionLimits {
+				max_per_ip: self.max_connections_per_ip,
+				max_per_subnet: self.max_connections_per_subnet,
+				ipv4_subnet_prefix: self.subnet_prefix,
+				..Default::default()
+			},
+			traffic_dump: self.traffic_dump.clone().map(|path| TrafficDumpConfig {
+				include_payloads: self.traffic_dump_payloads,
+				..TrafficDumpConfig::new(path)
+			}),
 			use_new_block_requests_protocol: !self.legacy_network_protocol,
+			sync_mode: self.sync.into(),
+			trusted_sync_peers: if self.trusted_sync_peers.is_empty() {
+				None
+			} else {
+				Some(self.trusted_sync_peers.iter().cloned().collect())
+			},
+			notifications_keep_alive: self.notifications_keep_alive.map(Duration::from_secs),
+			notifications_stall_timeout: match self.notifications_stall_timeout {
+				0 => None,
+				secs => Some(Duration::from_secs(secs)),
+			},
+			transaction_propagation: TransactionPropagationConfig {
+				max_batch_size: self.tx_batch_size,
+				max_per_peer_per_sec: self.tx_rate_limit,
+				max_peers: self.tx_propagate_to_peers,
+				..Default::default()
+			},
+			light_serve_limits: LightServeLimits {
+				max_requests_per_sec: self.light_max_requests_per_sec,
+				max_proof_bytes_per_sec: self.light_max_proof_bytes_per_sec,
+				max_concurrent_requests: self.light_max_concurrent_requests,
+			},
+			ipfs_server: self.ipfs_server,
 		}
 	}
 }
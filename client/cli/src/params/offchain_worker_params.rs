@@ -48,8 +48,9 @@ pub struct OffchainWorkerParams {
 
 	/// Enable Offchain Indexing API, which allows block import to write to Offchain DB.
 	///
-	/// Enables a runtime to write directly to a offchain workers
-	/// DB during block import.
+	/// Enables a runtime to write directly to the offchain index of the
+	/// DB during block import. The indexed values can be read with the
+	/// `offchain_indexGet` RPC.
 	#[structopt(
 		long = "enable-offchain-indexing",
 		value_name = "ENABLE_OFFCHAIN_INDEXING"
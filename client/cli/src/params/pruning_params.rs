@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::error;
-use sc_service::{PruningMode, Role};
+use sc_service::{config::DeltaArchive, BlockPruning, PruningMode, Role};
 use structopt::StructOpt;
 
 /// Parameters to define the pruning mode
@@ -28,8 +28,46 @@ pub struct PruningParams {
 	/// Default is to keep all block states if the node is running as a
 	/// validator (i.e. 'archive'), otherwise state is only kept for the last
 	/// 256 blocks.
+	///
+	/// The number of blocks can be changed between restarts of a pruned node. When it is
+	/// reduced, the states past the new window are pruned progressively as blocks are finalized.
+	/// When it is increased, the states already pruned aren't restored. Switching between
+	/// pruned and archive modes isn't possible. The status of the pruning can be queried with
+	/// the `system_pruningStatus` RPC.
 	#[structopt(long = "pruning", value_name = "PRUNING_MODE")]
 	pub pruning: Option<String>,
+
+	/// Specify the number of finalized blocks whose bodies are kept.
+	///
+	/// The bodies of older blocks are removed, independently of the state pruning, while their
+	/// headers are kept. Default is to keep all the bodies.
+	#[structopt(long = "keep-bodies", value_name = "COUNT")]
+	pub keep_bodies: Option<u32>,
+
+	/// Specify the number of finalized blocks whose justifications are kept.
+	///
+	/// The justifications of older blocks are removed, independently of the state pruning.
+	/// Default is to keep all the justifications.
+	#[structopt(long = "keep-justifications", value_name = "COUNT")]
+	pub keep_justifications: Option<u32>,
+
+	/// Specify the number of blocks behind the best block from which the states are
+	/// canonicalized.
+	///
+	/// The forks deeper than this can't be imported anymore, so it must exceed the depth of the
+	/// reorganizations expected by the consensus. Default is 4096.
+	#[structopt(long = "canonicalization-delay", value_name = "COUNT")]
+	pub canonicalization_delay: Option<u64>,
+
+	/// Archive the states of all the blocks as deltas, with a snapshot of the whole state every
+	/// `INTERVAL` blocks.
+	///
+	/// The states whose trie nodes have been pruned are reconstructed from the deltas, so they
+	/// stay available while taking much less space than with `--pruning archive`, at the cost of
+	/// slower reads. The state pruning is enabled by default with this option, which validators
+	/// must still allow with `--unsafe-pruning`.
+	#[structopt(long = "delta-archive", value_name = "INTERVAL")]
+	pub delta_archive: Option<u32>,
 }
 
 impl PruningParams {
@@ -41,21 +79,32 @@ impl PruningParams {
 		// unless `unsafe_pruning` is set.
 		Ok(match &self.pruning {
 			Some(ref s) if s == "archive" => PruningMode::ArchiveAll,
-			None if role.is_network_authority() => PruningMode::ArchiveAll,
-			None => PruningMode::default(),
-			Some(s) => {
-				if role.is_network_authority() && !unsafe_pruning {
-					return Err(error::Error::Input(
-						"Validators should run with state pruning disabled (i.e. archive). \
-						You can ignore this check with `--unsafe-pruning`."
-							.to_string(),
-					));
-				}
-
-				PruningMode::keep_blocks(s.parse().map_err(|_| {
-					error::Error::Input("Invalid pruning mode specified".to_string())
-				})?)
+			None if role.is_network_authority() && self.delta_archive.is_none() => PruningMode::ArchiveAll,
+			// the delta archive enables the state pruning, which still has to be acknowledged.
+			_ if role.is_network_authority() && !unsafe_pruning => {
+				return Err(error::Error::Input(
+					"Validators should run with state pruning disabled (i.e. archive). \
+					You can ignore this check with `--unsafe-pruning`."
+						.to_string(),
+				));
 			}
+			None => PruningMode::default(),
+			Some(s) => PruningMode::keep_blocks(s.parse().map_err(|_| {
+				error::Error::Input("Invalid pruning mode specified".to_string())
+			})?),
 		})
 	}
+
+	/// Get the configuration of the archive of the states as deltas from the parameters
+	pub fn delta_archive(&self) -> Option<DeltaArchive> {
+		self.delta_archive.map(|snapshot_interval| DeltaArchive { snapshot_interval })
+	}
+
+	/// Get the pruning of the bodies and justifications of the blocks from the parameters
+	pub fn block_pruning(&self) -> BlockPruning {
+		BlockPruning {
+			keep_bodies: self.keep_bodies,
+			keep_justifications: self.keep_justifications,
+		}
+	}
 }
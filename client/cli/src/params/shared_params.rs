@@ -16,7 +16,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use sc_tracing::logging::{LogFile, LogRotation};
 use std::path::PathBuf;
+use std::time::Duration;
 use structopt::StructOpt;
 
 /// Shared parameters used by all `CoreParams`.
@@ -45,6 +47,33 @@ pub struct SharedParams {
 	/// By default, all targets log `info`. The global log level can be set with -l<level>.
 	#[structopt(short = "l", long, value_name = "LOG_PATTERN")]
 	pub log: Vec<String>,
+
+	/// Write the logs to this file too, without colors.
+	///
+	/// Sending `SIGHUP` to the node reopens the file, and restores the log filter it was started
+	/// with.
+	#[structopt(long = "log-file", value_name = "PATH", parse(from_os_str))]
+	pub log_file: Option<PathBuf>,
+
+	/// Rotate the log file once it would exceed this size, in MiB.
+	#[structopt(long = "log-rotation-size", value_name = "MiB", requires = "log-file")]
+	pub log_rotation_size: Option<u64>,
+
+	/// Rotate the log file once it has been written to for this many hours.
+	#[structopt(long = "log-rotation-age", value_name = "HOURS", requires = "log-file")]
+	pub log_rotation_age: Option<u64>,
+
+	/// Number of rotated log files to keep, as `<PATH>.1` to `<PATH>.<COUNT>`.
+	#[structopt(long = "log-rotation-keep", value_name = "COUNT", default_value = "5")]
+	pub log_rotation_keep: usize,
+
+	/// Load the options from a TOML configuration file.
+	///
+	/// The keys of the file are the long names of the options, e.g. `rpc-port = 9933`, optionally
+	/// grouped in sections. Flags are booleans, and options given multiple times are arrays.
+	/// The options given on the command line override the ones of the file.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub config: Option<PathBuf>,
 }
 
 impl SharedParams {
@@ -76,4 +105,16 @@ impl SharedParams {
 	pub fn log_filters(&self) -> &[String] {
 		&self.log
 	}
+
+	/// Get the file the logs are written to, if any
+	pub fn log_file(&self) -> Option<LogFile> {
+		self.log_file.clone().map(|path| LogFile {
+			path,
+			rotation: LogRotation {
+				max_size: self.log_rotation_size.map(|size| size * 1024 * 1024),
+				max_age: self.log_rotation_age.map(|hours| Duration::from_secs(hours * 60 * 60)),
+				keep: self.log_rotation_keep,
+			},
+		})
+	}
 }
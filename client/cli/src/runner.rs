@@ -40,6 +40,16 @@ where
 
 	let mut stream_int = signal(SignalKind::interrupt())?;
 	let mut stream_term = signal(SignalKind::terminate())?;
+	let mut stream_hup = signal(SignalKind::hangup())?;
+
+	tokio::spawn(async move {
+		while stream_hup.recv().await.is_some() {
+			match sc_tracing::logging::reload_logger() {
+				Ok(()) => info!("🔁 Reloaded the logger"),
+				Err(err) => log::warn!("Failed to reload the logger: {}", err),
+			}
+		}
+	});
 
 	let t1 = stream_int.recv().fuse();
 	let t2 = stream_term.recv().fuse();
@@ -109,6 +119,7 @@ where
 pub struct Runner<C: SubstrateCli> {
 	config: Configuration,
 	tokio_runtime: tokio::runtime::Runtime,
+	output_format: sc_informant::OutputFormat,
 	phantom: PhantomData<C>,
 }
 
@@ -136,12 +147,13 @@ impl<C: SubstrateCli> Runner<C> {
 		Ok(Runner {
 			config: command.create_configuration(cli, task_executor)?,
 			tokio_runtime,
+			output_format: command.output_format()?,
 			phantom: PhantomData,
 		})
 	}
 
 	/// A helper function that runs an `AbstractService` with tokio and stops if the process receives
-	/// the signal `SIGTERM` or `SIGINT`.
+	/// the signal `SIGTERM` or `SIGINT`. The signal `SIGHUP` reloads the logger.
 	pub fn run_node<FNL, FNF, SL, SF>(
 		self,
 		new_light: FNL,
@@ -170,6 +182,17 @@ impl<C: SubstrateCli> Runner<C> {
 		);
 		info!("⛓  Native runtime: {}", runtime_version);
 
+		if self.output_format == sc_informant::OutputFormat::Json {
+			sc_informant::print_json_event("starting", serde_json::json!({
+				"impl_name": C::impl_name(),
+				"impl_version": C::impl_version(),
+				"chain": self.config.chain_spec.name(),
+				"node_name": self.config.network.node_name,
+				"role": self.config.display_role(),
+				"runtime_version": runtime_version.to_string(),
+			}));
+		}
+
 		match self.config.role {
 			Role::Light => self.run_service_until_exit(new_light),
 			_ => self.run_service_until_exit(new_full),
@@ -201,6 +224,11 @@ impl<C: SubstrateCli> Runner<C> {
 			Subcommand::Revert(cmd) => cmd.run(self.config, builder),
 			Subcommand::PurgeChain(cmd) => cmd.run(self.config),
 			Subcommand::ExportState(cmd) => cmd.run(self.config, builder),
+			Subcommand::ExportSnapshot(cmd) => cmd.run(self.config, builder),
+			Subcommand::ImportSnapshot(cmd) => {
+				run_until_exit(self.tokio_runtime, cmd.run(self.config, builder))
+			}
+			Subcommand::Db(cmd) => cmd.run(self.config, builder),
 		}
 	}
 
@@ -209,9 +237,15 @@ impl<C: SubstrateCli> Runner<C> {
 		F: FnOnce(Configuration) -> std::result::Result<T, sc_service::error::Error>,
 		T: AbstractService + Unpin,
 	{
-		let service = service_builder(self.config)?;
+		let mut service = service_builder(self.config)?;
+
+		if self.output_format == sc_informant::OutputFormat::Json {
+			sc_informant::print_json_event("started", serde_json::json!({
+				"peer_id": service.network().local_peer_id().to_base58(),
+			}));
+		}
 
-		let informant_future = sc_informant::build(&service, sc_informant::OutputFormat::Coloured);
+		let informant_future = sc_informant::build(&service, self.output_format);
 		let _informant_handle = self.tokio_runtime.spawn(informant_future);
 
 		// we eagerly drop the service so that the internal exit future is fired,
@@ -219,18 +253,28 @@ impl<C: SubstrateCli> Runner<C> {
 		// and drop the runtime first.
 		let _telemetry = service.telemetry();
 
-		{
-			let f = service.fuse();
-			self.tokio_runtime
-				.block_on(main(f))
-				.map_err(|e| e.to_string())?;
+		let result = self.tokio_runtime.block_on(main((&mut service).fuse()));
+
+		// The shutdown hooks run while the tasks of the service are still running, whether it was
+		// stopped by a signal or by the failure of an essential task.
+		if self.output_format == sc_informant::OutputFormat::Json {
+			sc_informant::print_json_event("stopping", serde_json::json!({
+				"error": result.as_ref().err().map(|e| e.to_string()),
+			}));
 		}
+		self.tokio_runtime.block_on(service.shutdown_hooks().run());
+		drop(service);
+		result.map_err(|e| e.to_string())?;
 
 		// The `service` **must** have been destroyed here for the shutdown signal to propagate
 		// to all the tasks. Dropping `tokio_runtime` will block the thread until all tasks have
 		// shut down.
 		drop(self.tokio_runtime);
 
+		if self.output_format == sc_informant::OutputFormat::Json {
+			sc_informant::print_json_event("stopped", serde_json::json!({}));
+		}
+
 		Ok(())
 	}
 
@@ -785,6 +785,12 @@ impl<Block: BlockT, C, I, P> BlockImport<Block> for AuraBlockImport<Block, C, I,
 		block: BlockImportParams<Block, Self::Transaction>,
 		new_cache: HashMap<CacheKeyId, Vec<u8>>,
 	) -> Result<ImportResult, Self::Error> {
+		// blocks imported along with their state may not have their parent available, and
+		// blocks imported again have already been checked.
+		if block.state.is_some() || block.import_existing {
+			return self.inner.import_block(block, new_cache).map_err(Into::into)
+		}
+
 		let hash = block.post_hash();
 		let slot_number = find_pre_digest::<Block, P>(&block.header)
 			.expect("valid Aura headers must contain a predigest; \
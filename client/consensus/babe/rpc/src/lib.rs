@@ -49,6 +49,11 @@ pub trait BabeApi {
 	/// with the keys in the keystore.
 	#[rpc(name = "babe_epochAuthorship")]
 	fn epoch_authorship(&self) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>>;
+
+	/// Returns data about which slots (primary or secondary) can be claimed in the next epoch
+	/// with the keys in the keystore.
+	#[rpc(name = "babe_nextEpochAuthorship")]
+	fn next_epoch_authorship(&self) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>>;
 }
 
 /// Implements the BabeRpc trait for interacting with Babe.
@@ -97,6 +102,25 @@ impl<B, C, SC> BabeApi for BabeRpcHandler<B, C, SC>
 		SC: SelectChain<B> + Clone + 'static,
 {
 	fn epoch_authorship(&self) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>> {
+		self.authorship(false)
+	}
+
+	fn next_epoch_authorship(&self) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>> {
+		self.authorship(true)
+	}
+}
+
+impl<B, C, SC> BabeRpcHandler<B, C, SC>
+	where
+		B: BlockT,
+		C: ProvideRuntimeApi<B> + HeaderBackend<B> + HeaderMetadata<B, Error=BlockChainError> + 'static,
+		C::Api: BabeRuntimeApi<B>,
+		<C::Api as sp_api::ApiErrorExt>::Error: fmt::Debug,
+		SC: SelectChain<B> + Clone + 'static,
+{
+	/// Computes the slots of the current epoch, or of the next one, that can be claimed with the
+	/// keys in the keystore.
+	fn authorship(&self, next_epoch: bool) -> FutureResult<HashMap<AuthorityId, EpochAuthorship>> {
 		if let Err(err) = self.deny_unsafe.check_if_safe() {
 			return Box::new(rpc_future::err(err.into()));
 		}
@@ -121,49 +145,60 @@ impl<B, C, SC> BabeApi for BabeRpcHandler<B, C, SC>
 				.map_err(|err| {
 					Error::StringError(format!("{:?}", err))
 				})?;
-			let epoch = epoch_data(&shared_epoch, &client, &babe_config, epoch_start, &select_chain)?;
-			let (epoch_start, epoch_end) = (epoch.start_slot(), epoch.end_slot());
-
-			let mut claims: HashMap<AuthorityId, EpochAuthorship> = HashMap::new();
-
-			let key_pairs = {
-				let keystore = keystore.read();
-				epoch.authorities.iter()
-					.enumerate()
-					.flat_map(|(i, a)| {
-						keystore
-							.key_pair::<sp_consensus_babe::AuthorityPair>(&a.0)
-							.ok()
-							.map(|kp| (kp, i))
-					})
-					.collect::<Vec<_>>()
-			};
-
-			for slot_number in epoch_start..epoch_end {
-				if let Some((claim, key)) =
-					authorship::claim_slot_using_key_pairs(slot_number, &epoch, &key_pairs)
-				{
-					match claim {
-						PreDigest::Primary { .. } => {
-							claims.entry(key).or_default().primary.push(slot_number);
-						}
-						PreDigest::SecondaryPlain { .. } => {
-							claims.entry(key).or_default().secondary.push(slot_number);
-						}
-						PreDigest::SecondaryVRF { .. } => {
-							claims.entry(key).or_default().secondary_vrf.push(slot_number);
-						},
-					};
-				}
+			let mut epoch = epoch_data(&shared_epoch, &client, &babe_config, epoch_start, &select_chain)?;
+			if next_epoch {
+				// the next epoch starts right after the current one, and is known as soon as the
+				// current one has started.
+				epoch = epoch_data(&shared_epoch, &client, &babe_config, epoch.end_slot(), &select_chain)?;
 			}
 
-			Ok(claims)
+			Ok(epoch_claims(&epoch, &keystore))
 		}.boxed();
 
 		Box::new(future.compat())
 	}
 }
 
+/// Returns the slots of `epoch` that can be claimed with the keys in `keystore`.
+fn epoch_claims(epoch: &Epoch, keystore: &KeyStorePtr) -> HashMap<AuthorityId, EpochAuthorship> {
+	let (epoch_start, epoch_end) = (epoch.start_slot(), epoch.end_slot());
+
+	let mut claims: HashMap<AuthorityId, EpochAuthorship> = HashMap::new();
+
+	let key_pairs = {
+		let keystore = keystore.read();
+		epoch.authorities.iter()
+			.enumerate()
+			.flat_map(|(i, a)| {
+				keystore
+					.key_pair::<sp_consensus_babe::AuthorityPair>(&a.0)
+					.ok()
+					.map(|kp| (kp, i))
+			})
+			.collect::<Vec<_>>()
+	};
+
+	for slot_number in epoch_start..epoch_end {
+		if let Some((claim, key)) =
+			authorship::claim_slot_using_key_pairs(slot_number, epoch, &key_pairs)
+		{
+			match claim {
+				PreDigest::Primary { .. } => {
+					claims.entry(key).or_default().primary.push(slot_number);
+				}
+				PreDigest::SecondaryPlain { .. } => {
+					claims.entry(key).or_default().secondary.push(slot_number);
+				}
+				PreDigest::SecondaryVRF { .. } => {
+					claims.entry(key).or_default().secondary_vrf.push(slot_number);
+				},
+			};
+		}
+	}
+
+	claims
+}
+
 /// Holds information about the `slot_number`'s that can be claimed by a given key.
 #[derive(Default, Debug, Deserialize, Serialize)]
 pub struct EpochAuthorship {
@@ -286,6 +321,25 @@ mod tests {
 		assert_eq!(Some(response.into()), io.handle_request_sync(request));
 	}
 
+	#[test]
+	fn next_epoch_authorship_works() {
+		let handler = test_babe_rpc_handler(DenyUnsafe::No);
+		let mut io = IoHandler::new();
+
+		io.extend_with(BabeApi::to_delegate(handler));
+		let request = r#"{"jsonrpc":"2.0","method":"babe_nextEpochAuthorship","params": [],"id":1}"#;
+
+		let response = io.handle_request_sync(request).unwrap();
+		let mut response: serde_json::Value = serde_json::from_str(&response).unwrap();
+		let claims: HashMap<AuthorityId, EpochAuthorship> =
+			serde_json::from_value(response["result"].take()).unwrap();
+
+		// the genesis epoch lasts 6 slots.
+		assert!(claims.values()
+			.flat_map(|c| c.primary.iter().chain(&c.secondary).chain(&c.secondary_vrf))
+			.all(|slot| (6..12).contains(slot)));
+	}
+
 	#[test]
 	fn epoch_authorship_is_unsafe() {
 		let handler = test_babe_rpc_handler(DenyUnsafe::Yes);
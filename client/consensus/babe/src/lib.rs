@@ -976,6 +976,14 @@ impl<Block, Client, Inner> BlockImport<Block> for BabeBlockImport<Block, Client,
 		// early exit if block already in chain, otherwise the check for
 		// epoch changes will error when trying to re-import an epoch change
 		match self.client.status(BlockId::Hash(hash)) {
+			// the epoch changes of a block imported again, for instance with the state it was
+			// first imported without, have already been tracked.
+			Ok(sp_blockchain::BlockStatus::InChain) if block.import_existing => {
+				if block.fork_choice.is_none() {
+					block.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+				}
+				return self.inner.import_block(block, new_cache).map_err(Into::into)
+			},
 			Ok(sp_blockchain::BlockStatus::InChain) => return Ok(ImportResult::AlreadyInChain),
 			Ok(sp_blockchain::BlockStatus::Unknown) => {},
 			Err(e) => return Err(ConsensusError::ClientImport(e.to_string())),
@@ -53,6 +53,7 @@ use sp_consensus::{
 };
 use sp_consensus::import_queue::{
 	BoxBlockImport, BasicQueue, Verifier, BoxJustificationImport, BoxFinalityProofImport,
+	PreVerifier, ParallelVerification,
 };
 use codec::{Encode, Decode};
 use prometheus_endpoint::Registry;
@@ -369,8 +370,13 @@ impl<B, I, C, S, Algorithm> BlockImport<B> for PowBlockImport<B, I, C, S, Algori
 }
 
 /// A verifier for PoW blocks.
+///
+/// It is also a pre-verifier running the preliminary verification of the seals of many blocks in
+/// parallel, which the verifier then skips.
 pub struct PowVerifier<B: BlockT, Algorithm> {
 	algorithm: Algorithm,
+	/// Whether the blocks passed to the verifier have already been pre-verified.
+	pre_verified: bool,
 	_marker: PhantomData<B>,
 }
 
@@ -378,12 +384,13 @@ impl<B: BlockT, Algorithm> PowVerifier<B, Algorithm> {
 	pub fn new(
 		algorithm: Algorithm,
 	) -> Self {
-		Self { algorithm, _marker: PhantomData }
+		Self { algorithm, pre_verified: false, _marker: PhantomData }
 	}
 
 	fn check_header(
 		&self,
 		mut header: B::Header,
+		preliminary_verify: bool,
 	) -> Result<(B::Header, DigestItem<B::Hash>), Error<B>> where
 		Algorithm: PowAlgorithm<B>,
 	{
@@ -400,16 +407,32 @@ impl<B: BlockT, Algorithm> PowVerifier<B, Algorithm> {
 			_ => return Err(Error::HeaderUnsealed(hash)),
 		};
 
-		let pre_hash = header.hash();
+		if preliminary_verify {
+			let pre_hash = header.hash();
 
-		if !self.algorithm.preliminary_verify(&pre_hash, &inner_seal)?.unwrap_or(true) {
-			return Err(Error::FailedPreliminaryVerify);
+			if !self.algorithm.preliminary_verify(&pre_hash, &inner_seal)?.unwrap_or(true) {
+				return Err(Error::FailedPreliminaryVerify);
+			}
 		}
 
 		Ok((header, seal))
 	}
 }
 
+impl<B: BlockT, Algorithm> PreVerifier<B> for PowVerifier<B, Algorithm> where
+	Algorithm: PowAlgorithm<B> + Send + Sync,
+{
+	fn pre_verify(
+		&self,
+		_origin: BlockOrigin,
+		header: &B::Header,
+		_body: Option<&[B::Extrinsic]>,
+	) -> Result<(), String> {
+		self.check_header(header.clone(), true)?;
+		Ok(())
+	}
+}
+
 impl<B: BlockT, Algorithm> Verifier<B> for PowVerifier<B, Algorithm> where
 	Algorithm: PowAlgorithm<B> + Send + Sync,
 	Algorithm::Difficulty: 'static,
@@ -422,7 +445,7 @@ impl<B: BlockT, Algorithm> Verifier<B> for PowVerifier<B, Algorithm> where
 		body: Option<Vec<B::Extrinsic>>,
 	) -> Result<(BlockImportParams<B, ()>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
 		let hash = header.hash();
-		let (checked_header, seal) = self.check_header(header)?;
+		let (checked_header, seal) = self.check_header(header, !self.pre_verified)?;
 
 		let intermediate = PowIntermediate::<Algorithm::Difficulty> {
 			difficulty: None,
@@ -460,12 +483,16 @@ pub fn register_pow_inherent_data_provider(
 pub type PowImportQueue<B, Transaction> = BasicQueue<B, Transaction>;
 
 /// Import queue for PoW engine.
+///
+/// The preliminary verification of the seals of the blocks is run in parallel by
+/// `verification_workers` workers before their import, or along with it if 0.
 pub fn import_queue<B, Transaction, Algorithm>(
 	block_import: BoxBlockImport<B, Transaction>,
 	justification_import: Option<BoxJustificationImport<B>>,
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	algorithm: Algorithm,
 	inherent_data_providers: InherentDataProviders,
+	verification_workers: usize,
 	spawner: &impl sp_core::traits::SpawnBlocking,
 	registry: Option<&Registry>,
 ) -> Result<
@@ -478,10 +505,20 @@ pub fn import_queue<B, Transaction, Algorithm>(
 {
 	register_pow_inherent_data_provider(&inherent_data_providers)?;
 
-	let verifier = PowVerifier::new(algorithm);
-
-	Ok(BasicQueue::new(
+	let mut verifier = PowVerifier::new(algorithm.clone());
+	let parallel_verification = match verification_workers {
+		0 => None,
+		workers => Some(ParallelVerification::new(
+			Arc::new(PowVerifier::new(algorithm)) as Arc<dyn PreVerifier<B>>,
+			workers,
+		)?),
+	};
+	// the seals are only left unchecked by the verifier once the workers checking them run.
+	verifier.pre_verified = parallel_verification.is_some();
+
+	Ok(BasicQueue::with_parallel_verification(
 		verifier,
+		parallel_verification,
 		block_import,
 		justification_import,
 		finality_proof_import,
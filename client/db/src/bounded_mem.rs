@@ -0,0 +1,235 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! In-memory database bounded in size, spilling the least recently used values to a temporary
+//! file.
+//!
+//! It is meant for the tests and the ephemeral nodes with large states, which would otherwise run
+//! out of memory, without having to manage the path of a database on the disk.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use sp_database::{Change, ColumnId, Transaction};
+use crate::{Database, DbHash};
+
+/// Estimation of the memory used by an entry, in addition to its key and value.
+const ENTRY_OVERHEAD: usize = 64;
+
+/// Column of the preimages stored by their hash, out of the range of the columns of the database.
+const PREIMAGES: ColumnId = ColumnId::max_value();
+
+/// Number of databases created by the process, naming their temporary directories.
+static DATABASES: AtomicUsize = AtomicUsize::new(0);
+
+type Key = (ColumnId, Vec<u8>);
+
+fn entry_size(key: &Key, value: &[u8]) -> usize {
+	key.1.len() + value.len() + ENTRY_OVERHEAD
+}
+
+struct Entries {
+	/// Values in memory, least recently used first.
+	memory: LinkedHashMap<Key, Vec<u8>>,
+	memory_size: usize,
+	/// Offsets and lengths of the values spilled to the file.
+	spilled: HashMap<Key, (u64, usize)>,
+	file: File,
+	file_size: u64,
+}
+
+impl Entries {
+	fn get(&mut self, key: &Key) -> io::Result<Option<Vec<u8>>> {
+		if let Some(value) = self.memory.get_refresh(key) {
+			return Ok(Some(value.clone()))
+		}
+		let (offset, len) = match self.spilled.get(key) {
+			Some(location) => *location,
+			None => return Ok(None),
+		};
+		let mut value = vec![0; len];
+		self.file.seek(SeekFrom::Start(offset))?;
+		self.file.read_exact(&mut value)?;
+		Ok(Some(value))
+	}
+
+	fn set(&mut self, key: Key, value: Vec<u8>, max_size: usize) -> io::Result<()> {
+		self.remove(&key);
+		self.memory_size += entry_size(&key, &value);
+		self.memory.insert(key, value);
+		while self.memory_size > max_size {
+			let (key, value) = match self.memory.pop_front() {
+				Some(entry) => entry,
+				None => break,
+			};
+			self.memory_size -= entry_size(&key, &value);
+			// the space of the values removed from the file isn't reclaimed.
+			self.file.seek(SeekFrom::Start(self.file_size))?;
+			self.file.write_all(&value)?;
+			self.spilled.insert(key, (self.file_size, value.len()));
+			self.file_size += value.len() as u64;
+		}
+		Ok(())
+	}
+
+	fn remove(&mut self, key: &Key) {
+		if let Some(value) = self.memory.remove(key) {
+			self.memory_size -= entry_size(key, &value);
+		}
+		self.spilled.remove(key);
+	}
+}
+
+/// In-memory database keeping at most a given number of bytes in memory, the least recently used
+/// values being moved to a file of a temporary directory, removed with the database.
+pub struct BoundedMemoryDb {
+	entries: Mutex<Entries>,
+	max_size: usize,
+	dir: PathBuf,
+}
+
+impl BoundedMemoryDb {
+	/// Creates an empty database keeping at most `max_size` bytes in memory.
+	pub fn new(max_size: usize) -> io::Result<Self> {
+		let dir = std::env::temp_dir().join(format!(
+			"substrate-memory-db-{}-{}",
+			std::process::id(),
+			DATABASES.fetch_add(1, Ordering::Relaxed),
+		));
+		fs::create_dir_all(&dir)?;
+		let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true)
+			.open(dir.join("spilled"))?;
+		Ok(BoundedMemoryDb {
+			entries: Mutex::new(Entries {
+				memory: LinkedHashMap::new(),
+				memory_size: 0,
+				spilled: HashMap::new(),
+				file,
+				file_size: 0,
+			}),
+			max_size,
+			dir,
+		})
+	}
+
+	/// Returns the number of bytes used in memory and in the temporary file.
+	pub fn used_size(&self) -> (usize, u64) {
+		let entries = self.entries.lock();
+		(entries.memory_size, entries.file_size)
+	}
+}
+
+fn handle_err<T>(result: io::Result<T>) -> T {
+	match result {
+		Ok(r) => r,
+		Err(e) => panic!("Critical database error: {:?}", e),
+	}
+}
+
+impl Database<DbHash> for BoundedMemoryDb {
+	fn commit(&self, transaction: Transaction<DbHash>) {
+		let mut entries = self.entries.lock();
+		for change in transaction.0.into_iter() {
+			match change {
+				Change::Set(col, key, value) => handle_err(entries.set((col, key), value, self.max_size)),
+				Change::Remove(col, key) => entries.remove(&(col, key)),
+				Change::Store(hash, preimage) =>
+					handle_err(entries.set((PREIMAGES, hash.to_vec()), preimage, self.max_size)),
+				Change::Release(hash) => entries.remove(&(PREIMAGES, hash.to_vec())),
+			}
+		}
+	}
+
+	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+		handle_err(self.entries.lock().get(&(col, key.to_vec())))
+	}
+
+	fn lookup(&self, hash: &DbHash) -> Option<Vec<u8>> {
+		handle_err(self.entries.lock().get(&(PREIMAGES, hash.to_vec())))
+	}
+}
+
+impl Drop for BoundedMemoryDb {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.dir);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn set(db: &BoundedMemoryDb, key: u8, value: Vec<u8>) {
+		let mut transaction = Transaction::new();
+		transaction.set_from_vec(0, &[key], value);
+		db.commit(transaction);
+	}
+
+	#[test]
+	fn spills_least_recently_used_values() {
+		let value_size = entry_size(&(0, vec![0]), &[0; 100]);
+		let db = BoundedMemoryDb::new(value_size * 2).unwrap();
+		set(&db, 1, vec![1; 100]);
+		set(&db, 2, vec![2; 100]);
+		assert_eq!(db.get(0, &[1]), Some(vec![1; 100]));
+		set(&db, 3, vec![3; 100]);
+		assert_eq!(db.used_size(), (value_size * 2, 100));
+
+		// the spilled value is still read, and the others stay in memory.
+		assert_eq!(db.get(0, &[2]), Some(vec![2; 100]));
+		assert_eq!(db.get(0, &[1]), Some(vec![1; 100]));
+		assert_eq!(db.get(0, &[3]), Some(vec![3; 100]));
+		assert_eq!(db.get(1, &[1]), None);
+
+		let mut transaction = Transaction::new();
+		transaction.remove(0, &[2]);
+		db.commit(transaction);
+		assert_eq!(db.get(0, &[2]), None);
+	}
+
+	#[test]
+	fn preimages_are_looked_up_by_their_hash() {
+		let db = BoundedMemoryDb::new(0).unwrap();
+		let hash: DbHash = [1; 32];
+		let mut transaction = Transaction::new();
+		transaction.store(hash, &[1; 10]);
+		db.commit(transaction);
+		// the preimage is spilled to the file, and not mixed up with the values of the columns.
+		assert_eq!(db.lookup(&hash), Some(vec![1; 10]));
+		assert_eq!(db.get(0, &hash), None);
+
+		let mut transaction = Transaction::new();
+		transaction.release(hash);
+		db.commit(transaction);
+		assert_eq!(db.lookup(&hash), None);
+	}
+
+	#[test]
+	fn temporary_directory_is_removed_with_the_database() {
+		let db = BoundedMemoryDb::new(0).unwrap();
+		set(&db, 1, vec![1; 10]);
+		let dir = db.dir.clone();
+		assert!(dir.join("spilled").exists());
+		drop(db);
+		assert!(!dir.exists());
+	}
+}
@@ -0,0 +1,323 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+//! Check of the integrity of the database of a full node.
+
+use std::fmt;
+use codec::Decode;
+use sc_client_api::backend::Backend as _;
+use sp_blockchain::{HeaderBackend, Result as ClientResult};
+use sp_runtime::{generic::BlockId, Justification};
+use sp_runtime::traits::{Block as BlockT, Hash, HashFor, Header as HeaderT, NumberFor, One, Zero};
+use sp_core::storage::{well_known_keys, ChildInfo};
+use sp_state_machine::Backend as StateBackend;
+use crate::{columns, utils, Backend};
+
+/// Length of the number at the start of the lookup keys of the blocks.
+const NUMBER_INDEX_KEY_LEN: usize = std::mem::size_of::<utils::NumberIndexKey>();
+
+/// Inconsistency of the database found by [`check_database`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inconsistency<Block: BlockT> {
+	/// No block of the canonical chain is indexed at this number.
+	MissingBlock(NumberFor<Block>),
+	/// The header of a canonical block is missing, can't be decoded, or doesn't hash to the hash
+	/// of the block.
+	InvalidHeader(NumberFor<Block>, Block::Hash, String),
+	/// The parent hash of a canonical block isn't the hash of the canonical block before it.
+	BrokenParentLink(NumberFor<Block>, Block::Hash),
+	/// The body of a canonical block can't be decoded, or doesn't match the extrinsics root of
+	/// its header.
+	InvalidBody(NumberFor<Block>, Block::Hash, String),
+	/// The justification of a canonical block can't be decoded.
+	InvalidJustification(NumberFor<Block>, Block::Hash, String),
+	/// The state of a canonical block, which isn't pruned, can't be read.
+	InvalidState(NumberFor<Block>, Block::Hash, String),
+	/// The finalized block isn't the canonical block at its number.
+	NonCanonicalFinalized(NumberFor<Block>, Block::Hash),
+	/// A leaf of the tree of blocks has no header.
+	MissingLeaf(Block::Hash),
+}
+
+impl<Block: BlockT> Inconsistency<Block> {
+	/// Returns the number of the canonical block the inconsistency is about, if any.
+	pub fn number(&self) -> Option<NumberFor<Block>> {
+		match self {
+			Inconsistency::MissingBlock(number) |
+			Inconsistency::InvalidHeader(number, _, _) |
+			Inconsistency::BrokenParentLink(number, _) |
+			Inconsistency::InvalidBody(number, _, _) |
+			Inconsistency::InvalidJustification(number, _, _) |
+			Inconsistency::InvalidState(number, _, _) |
+			Inconsistency::NonCanonicalFinalized(number, _) => Some(*number),
+			Inconsistency::MissingLeaf(_) => None,
+		}
+	}
+}
+
+impl<Block: BlockT> fmt::Display for Inconsistency<Block> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Inconsistency::MissingBlock(number) =>
+				write!(f, "No canonical block #{}", number),
+			Inconsistency::InvalidHeader(number, hash, reason) =>
+				write!(f, "Invalid header of block #{} ({}): {}", number, hash, reason),
+			Inconsistency::BrokenParentLink(number, hash) =>
+				write!(f, "Block #{} ({}) isn't the parent of the next canonical block", number, hash),
+			Inconsistency::InvalidBody(number, hash, reason) =>
+				write!(f, "Invalid body of block #{} ({}): {}", number, hash, reason),
+			Inconsistency::InvalidJustification(number, hash, reason) =>
+				write!(f, "Invalid justification of block #{} ({}): {}", number, hash, reason),
+			Inconsistency::InvalidState(number, hash, reason) =>
+				write!(f, "Invalid state of block #{} ({}): {}", number, hash, reason),
+			Inconsistency::NonCanonicalFinalized(number, hash) =>
+				write!(f, "The finalized block #{} ({}) isn't canonical", number, hash),
+			Inconsistency::MissingLeaf(hash) =>
+				write!(f, "No header for the leaf {}", hash),
+		}
+	}
+}
+
+/// Result of [`check_database`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseCheck<Block: BlockT> {
+	/// Number of the best block, the last one checked.
+	pub best_number: NumberFor<Block>,
+	/// Inconsistencies found, from the best block down to the genesis block.
+	pub inconsistencies: Vec<Inconsistency<Block>>,
+}
+
+impl<Block: BlockT> DatabaseCheck<Block> {
+	/// Returns the number of the last block before the first inconsistent one, which the chain can
+	/// be reverted to, or `None` if no inconsistency is fixed by reverting blocks.
+	pub fn last_consistent_block(&self) -> Option<NumberFor<Block>> {
+		let first = self.inconsistencies.iter().filter_map(Inconsistency::number).min()?;
+		if first.is_zero() {
+			None
+		} else {
+			Some(first - One::one())
+		}
+	}
+}
+
+/// Checks the integrity of the database of a full node.
+///
+/// The canonical chain is walked from the best block down to the genesis block, checking the
+/// hashes of the headers and their links to their parents, that the bodies match the extrinsics
+/// roots of the headers and that the justifications can be decoded. The root of the state of
+/// every block which isn't pruned is read, and the whole state of the best block. Then the
+/// finalized block and the leaves of the tree of blocks are checked. `progress` is called with
+/// the number of each checked block.
+///
+/// The bodies and justifications removed by the pruning are not reported.
+pub fn check_database<Block: BlockT, F>(
+	backend: &Backend<Block>,
+	mut progress: F,
+) -> ClientResult<DatabaseCheck<Block>> where
+	F: FnMut(NumberFor<Block>),
+{
+	let db = &*backend.storage.db;
+	let info = backend.blockchain.info();
+	let mut inconsistencies = Vec::new();
+
+	let mut number = info.best_number;
+	// hash of the canonical block after the current one, as given by its header.
+	let mut expected_hash = None;
+	loop {
+		progress(number);
+		let key = utils::block_id_to_lookup_key::<Block>(db, columns::KEY_LOOKUP, BlockId::Number(number))?;
+		match key {
+			Some(key) => {
+				let header = check_block(backend, number, &key, &mut inconsistencies);
+				if let (Some(expected), Some(hash)) = (expected_hash, lookup_key_hash::<Block>(&key)) {
+					if expected != hash {
+						inconsistencies.push(Inconsistency::BrokenParentLink(number, hash));
+					}
+				}
+				expected_hash = header.map(|header| *header.parent_hash());
+			},
+			None => {
+				inconsistencies.push(Inconsistency::MissingBlock(number));
+				expected_hash = None;
+			},
+		}
+
+		if number.is_zero() {
+			break
+		}
+		number -= One::one();
+	}
+
+	let finalized = utils::block_id_to_lookup_key::<Block>(
+		db,
+		columns::KEY_LOOKUP,
+		BlockId::Number(info.finalized_number),
+	)?;
+	let canonical = utils::number_and_hash_to_lookup_key(info.finalized_number, info.finalized_hash)?;
+	if finalized.as_ref() != Some(&canonical) {
+		inconsistencies.push(Inconsistency::NonCanonicalFinalized(info.finalized_number, info.finalized_hash));
+	}
+
+	for leaf in backend.blockchain.leaves.read().hashes() {
+		let header = utils::read_header::<Block>(db, columns::KEY_LOOKUP, columns::HEADER, BlockId::Hash(leaf));
+		if !matches!(header, Ok(Some(_))) {
+			inconsistencies.push(Inconsistency::MissingLeaf(leaf));
+		}
+	}
+
+	Ok(DatabaseCheck { best_number: info.best_number, inconsistencies })
+}
+
+/// Returns the hash of the block of a lookup key.
+fn lookup_key_hash<Block: BlockT>(key: &[u8]) -> Option<Block::Hash> {
+	key.get(NUMBER_INDEX_KEY_LEN..).and_then(|mut hash| Block::Hash::decode(&mut hash).ok())
+}
+
+/// Checks the canonical block `number` stored under the lookup `key`, and returns its header if
+/// it is valid.
+fn check_block<Block: BlockT>(
+	backend: &Backend<Block>,
+	number: NumberFor<Block>,
+	key: &[u8],
+	inconsistencies: &mut Vec<Inconsistency<Block>>,
+) -> Option<Block::Header> {
+	let db = &*backend.storage.db;
+	let hash = match lookup_key_hash::<Block>(key) {
+		Some(hash) => hash,
+		None => {
+			inconsistencies.push(Inconsistency::InvalidHeader(number, Default::default(), "invalid lookup key".into()));
+			return None
+		},
+	};
+	let mut invalid_header = |reason: String| {
+		inconsistencies.push(Inconsistency::InvalidHeader(number, hash, reason));
+		None
+	};
+	let header = match db.get(columns::HEADER, key).map(|header| Block::Header::decode(&mut &header[..])) {
+		Some(Ok(header)) => header,
+		Some(Err(e)) => return invalid_header(format!("can't be decoded: {}", e)),
+		None => return invalid_header("missing".into()),
+	};
+	if header.hash() != hash {
+		return invalid_header(format!("hashes to {}", header.hash()))
+	}
+	if *header.number() != number {
+		return invalid_header(format!("numbered #{}", header.number()))
+	}
+
+	if let Some(body) = db.get(columns::BODY, key) {
+		match Vec::<Block::Extrinsic>::decode(&mut &body[..]) {
+			Ok(body) => {
+				let root = HashFor::<Block>::ordered_trie_root(body.iter().map(codec::Encode::encode).collect());
+				if root != *header.extrinsics_root() {
+					inconsistencies.push(Inconsistency::InvalidBody(number, hash, format!(
+						"the extrinsics root is {}",
+						root,
+					)));
+				}
+			},
+			Err(e) => inconsistencies.push(Inconsistency::InvalidBody(number, hash, format!(
+				"can't be decoded: {}",
+				e,
+			))),
+		}
+	}
+
+	if let Some(justification) = db.get(columns::JUSTIFICATION, key) {
+		if let Err(e) = Justification::decode(&mut &justification[..]) {
+			inconsistencies.push(Inconsistency::InvalidJustification(number, hash, format!(
+				"can't be decoded: {}",
+				e,
+			)));
+		}
+	}
+
+	if backend.have_state_at(&hash, number) {
+		let whole = number == backend.blockchain.info().best_number;
+		if let Err(reason) = check_state(backend, hash, whole) {
+			inconsistencies.push(Inconsistency::InvalidState(number, hash, reason));
+		}
+	}
+
+	Some(header)
+}
+
+/// Reads the state of the block `hash`: all of it if `whole` is set, or else its first key,
+/// which requires the root of its trie.
+fn check_state<Block: BlockT>(backend: &Backend<Block>, hash: Block::Hash, whole: bool) -> Result<(), String> {
+	let state = backend.state_at(BlockId::Hash(hash)).map_err(|e| e.to_string())?;
+	let mut key = Vec::new();
+	while let Some(next) = state.next_storage_key(&key).map_err(|e| format!("{:?}", e))? {
+		if !whole {
+			return Ok(())
+		}
+		state.storage(&next).map_err(|e| format!("{:?}", e))?;
+		if next.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+			let child_info = ChildInfo::new_default(&next[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..]);
+			state.child_storage(&child_info, &[]).map_err(|e| format!("{:?}", e))?;
+			let mut child_key = Vec::new();
+			while let Some(next) = state.next_child_storage_key(&child_info, &child_key)
+				.map_err(|e| format!("{:?}", e))?
+			{
+				state.child_storage(&child_info, &next).map_err(|e| format!("{:?}", e))?;
+				child_key = next;
+			}
+		}
+		key = next;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::Encode;
+	use sp_runtime::testing::ExtrinsicWrapper;
+	use sp_runtime::traits::BlakeTwo256;
+	use crate::tests::{insert_header, Block};
+
+	#[test]
+	fn reports_the_corrupted_blocks() {
+		let backend = Backend::<Block>::new_test(10, 0);
+		let extrinsics_root = BlakeTwo256::ordered_trie_root(Vec::new());
+		let mut hashes = vec![insert_header(&backend, 0, Default::default(), None, extrinsics_root)];
+		for number in 1..4 {
+			hashes.push(insert_header(&backend, number, hashes[number as usize - 1], None, extrinsics_root));
+		}
+
+		let check = check_database(&backend, |_| ()).unwrap();
+		assert_eq!(check.best_number, 3);
+		assert_eq!(check.inconsistencies, vec![]);
+		assert_eq!(check.last_consistent_block(), None);
+
+		let mut transaction = sp_database::Transaction::new();
+		let body = vec![ExtrinsicWrapper::from(7u64)].encode();
+		transaction.set(columns::BODY, &utils::number_and_hash_to_lookup_key(2u64, hashes[2]).unwrap(), &body);
+		transaction.remove(columns::HEADER, &utils::number_and_hash_to_lookup_key(3u64, hashes[3]).unwrap());
+		backend.storage.db.commit(transaction);
+
+		let check = check_database(&backend, |_| ()).unwrap();
+		assert_eq!(check.inconsistencies.len(), 3);
+		assert_eq!(
+			check.inconsistencies[0],
+			Inconsistency::InvalidHeader(3, hashes[3], "missing".into()),
+		);
+		assert!(matches!(check.inconsistencies[1], Inconsistency::InvalidBody(2, _, _)));
+		assert_eq!(check.inconsistencies[2], Inconsistency::MissingLeaf(hashes[3]));
+		assert_eq!(check.last_consistent_block(), Some(1));
+	}
+}
@@ -0,0 +1,159 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cold storage of the bodies and justifications of the ancient finalized blocks.
+//!
+//! The bodies and justifications of the blocks finalized more than `keep_hot` blocks ago are
+//! moved by a background thread from the main database to a secondary one, typically on cheaper
+//! and slower disks. The reads of the blockchain fall back to the cold database.
+//!
+//! The data is written to the cold database before being removed from the main one, so it can
+//! always be read from one of them.
+
+use std::{path::PathBuf, sync::{Arc, mpsc}, thread};
+use codec::{Decode, Encode};
+use log::{debug, warn};
+use parking_lot::Mutex;
+use sp_blockchain::Result as ClientResult;
+use sp_database::Transaction;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, NumberFor, One, Zero}};
+use crate::{columns, utils, Database, DbHash};
+
+/// Key of the number of the next block to migrate, in the meta column of the cold database.
+const NEXT_BLOCK: &[u8] = b"cold_next";
+
+/// Number of blocks migrated by a transaction.
+const BATCH_SIZE: u32 = 256;
+
+/// Configuration of the cold storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColdStorage {
+	/// Path of the cold database.
+	pub path: PathBuf,
+	/// Number of finalized blocks whose bodies and justifications stay in the main database.
+	pub keep_hot: u32,
+}
+
+/// Opens the cold database at `path`.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+pub(crate) fn open(path: &std::path::Path) -> ClientResult<Arc<dyn Database<DbHash>>> {
+	let path = path.to_str()
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid cold database path".into()))?;
+	let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(utils::NUM_COLUMNS);
+	let db = kvdb_rocksdb::Database::open(&db_config, path)
+		.map_err(|err| sp_blockchain::Error::Backend(format!("{}", err)))?;
+	Ok(sp_database::as_database(db))
+}
+
+/// Opens the cold database at `path`.
+#[cfg(not(any(feature = "kvdb-rocksdb", test)))]
+pub(crate) fn open(_path: &std::path::Path) -> ClientResult<Arc<dyn Database<DbHash>>> {
+	Err(sp_blockchain::Error::Backend(
+		"`kvdb-rocksdb` feature not enabled, cold database can not be opened".into(),
+	))
+}
+
+/// Returns the number of the next block to migrate to the cold database.
+pub(crate) fn next_block<Block: BlockT>(cold: &dyn Database<DbHash>) -> ClientResult<NumberFor<Block>> {
+	match cold.get(columns::META, NEXT_BLOCK) {
+		Some(next) => Decode::decode(&mut &next[..]).map_err(|err|
+			sp_blockchain::Error::Backend(format!("Error decoding the next cold block: {}", err))
+		),
+		None => Ok(Zero::zero()),
+	}
+}
+
+/// Moves the bodies and justifications of the canonical blocks from `from` to `to` (excluded) to
+/// the cold database, in batches.
+pub(crate) fn migrate<Block: BlockT>(
+	hot: &dyn Database<DbHash>,
+	cold: &dyn Database<DbHash>,
+	from: NumberFor<Block>,
+	to: NumberFor<Block>,
+) -> ClientResult<()> {
+	let mut number = from;
+	while number < to {
+		let mut hot_transaction = Transaction::new();
+		let mut cold_transaction = Transaction::new();
+		let mut batch = 0;
+		while number < to && batch < BATCH_SIZE {
+			let lookup_key = utils::block_id_to_lookup_key::<Block>(hot, columns::KEY_LOOKUP, BlockId::Number(number))?;
+			if let Some(lookup_key) = lookup_key {
+				for column in &[columns::BODY, columns::JUSTIFICATION] {
+					if let Some(value) = hot.get(*column, &lookup_key) {
+						cold_transaction.set_from_vec(*column, &lookup_key, value);
+						hot_transaction.remove(*column, &lookup_key);
+					}
+				}
+			}
+			number += One::one();
+			batch += 1;
+		}
+		cold_transaction.set_from_vec(columns::META, NEXT_BLOCK, number.encode());
+		cold.commit(cold_transaction);
+		hot.commit(hot_transaction);
+	}
+	Ok(())
+}
+
+/// Handle to the background thread migrating the blocks to the cold database.
+pub(crate) struct ColdMigration<Block: BlockT> {
+	finalized: Mutex<mpsc::Sender<NumberFor<Block>>>,
+}
+
+impl<Block: BlockT> ColdMigration<Block> {
+	/// Starts the migration of the blocks finalized more than `keep_hot` blocks ago. The thread
+	/// stops when the handle is dropped.
+	pub(crate) fn start(
+		hot: Arc<dyn Database<DbHash>>,
+		cold: Arc<dyn Database<DbHash>>,
+		keep_hot: u32,
+	) -> ClientResult<Self> {
+		let (sender, receiver) = mpsc::channel::<NumberFor<Block>>();
+		let mut next = next_block::<Block>(&*cold)?;
+		thread::Builder::new()
+			.name("db-cold-migration".into())
+			.spawn(move || {
+				while let Ok(finalized) = receiver.recv() {
+					// only the last finalized block matters.
+					let finalized = receiver.try_iter().last().unwrap_or(finalized);
+					if finalized < keep_hot.into() {
+						continue;
+					}
+					let to = finalized - keep_hot.into();
+					if to <= next {
+						continue;
+					}
+					match migrate::<Block>(&*hot, &*cold, next, to) {
+						Ok(()) => {
+							debug!(target: "db", "Moved the blocks #{}..#{} to the cold database", next, to);
+							next = to;
+						},
+						Err(e) => warn!("Failed to move the blocks to the cold database: {:?}", e),
+					}
+				}
+			})
+			.map_err(|e| sp_blockchain::Error::Backend(format!("Failed to start the cold migration: {}", e)))?;
+		Ok(ColdMigration { finalized: Mutex::new(sender) })
+	}
+
+	/// Notes that the block `number` is finalized.
+	pub(crate) fn note_finalized(&self, number: NumberFor<Block>) {
+		let _ = self.finalized.lock().send(number);
+	}
+}
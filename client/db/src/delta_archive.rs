@@ -0,0 +1,354 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Archive of the states of the blocks as deltas against periodic snapshots.
+//!
+//! The trie nodes of the states are pruned as usual, while the changes made to the storage by
+//! every block are kept, along with the whole storage of the blocks whose number is a multiple
+//! of the snapshot interval. The state of a pruned block is reconstructed in memory from the
+//! closest snapshot of its ancestors and the changes of the blocks since then, and checked
+//! against the state root of its header.
+//!
+//! Both writing a snapshot and reconstructing a state hold the whole storage of a block in
+//! memory, so the snapshot interval trades the space taken by the snapshots against the time and
+//! memory needed to reconstruct the states, and only a few reconstructed states are cached.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use codec::{Decode, Encode};
+use hash_db::Prefix;
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use sp_blockchain::Result as ClientResult;
+use sp_core::storage::{well_known_keys, ChildInfo};
+use sp_database::Transaction;
+use sp_runtime::traits::{Block as BlockT, HashFor};
+use sp_state_machine::{
+	backend::Backend as StateBackend, ChildStorageCollection, DBValue, StorageCollection, StorageKey,
+};
+use sp_trie::{MemoryDB, PrefixedMemoryDB};
+use crate::{columns, Database, DbHash, DbState};
+
+/// Number of reconstructed states kept in memory.
+const RECONSTRUCTED_STATES_CACHE_SIZE: usize = 4;
+
+/// Prefix of the keys of the deltas, followed by the hash of their block.
+const DELTA_PREFIX: u8 = b'd';
+
+/// Prefix of the keys of the snapshots, followed by the hash of their block.
+const SNAPSHOT_PREFIX: u8 = b's';
+
+/// Configuration of the archive of the states as deltas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaArchive {
+	/// Number of blocks between two snapshots of the whole storage.
+	pub snapshot_interval: u32,
+}
+
+impl DeltaArchive {
+	/// Returns whether the whole storage of the block `number` is kept.
+	pub(crate) fn is_snapshot(&self, number: u64) -> bool {
+		number % self.snapshot_interval as u64 == 0
+	}
+}
+
+/// Changes made to the top and child storages by a block.
+#[derive(Encode, Decode)]
+struct Delta {
+	top: StorageCollection,
+	children: ChildStorageCollection,
+}
+
+/// Whole top and child storages of a block, without the roots of the child tries.
+#[derive(Encode, Decode)]
+struct Snapshot {
+	top: Vec<(StorageKey, Vec<u8>)>,
+	children: Vec<(StorageKey, Vec<(StorageKey, Vec<u8>)>)>,
+}
+
+fn key(prefix: u8, hash: &[u8]) -> Vec<u8> {
+	let mut key = Vec::with_capacity(1 + hash.len());
+	key.push(prefix);
+	key.extend_from_slice(hash);
+	key
+}
+
+fn decode<T: Decode>(data: Vec<u8>, what: &str) -> ClientResult<T> {
+	T::decode(&mut &data[..]).map_err(|err|
+		sp_blockchain::Error::Backend(format!("Error decoding the {} of the delta archive: {}", what, err))
+	)
+}
+
+/// Writes the changes made to the storage by the block `hash`.
+pub(crate) fn write_delta(
+	transaction: &mut Transaction<DbHash>,
+	hash: &[u8],
+	top: &StorageCollection,
+	children: &ChildStorageCollection,
+) {
+	let delta = Delta { top: top.clone(), children: children.clone() };
+	transaction.set_from_vec(columns::STATE_HISTORY, &key(DELTA_PREFIX, hash), delta.encode());
+}
+
+/// Writes the whole storage of the block `hash`, read from its `state`.
+pub(crate) fn write_snapshot<H, S>(
+	transaction: &mut Transaction<DbHash>,
+	hash: &[u8],
+	state: &S,
+) -> ClientResult<()>
+where
+	H: hash_db::Hasher,
+	S: StateBackend<H>,
+{
+	let mut top = Vec::new();
+	let mut children = Vec::new();
+	for (key, value) in state.pairs() {
+		if key.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+			let storage_key = key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..].to_vec();
+			let child_info = ChildInfo::new_default(&storage_key);
+			let mut pairs = Vec::new();
+			for child_key in state.child_keys(&child_info, &[]) {
+				let child_value = state.child_storage(&child_info, &child_key)
+					.map_err(|e| sp_blockchain::Error::Backend(format!("{:?}", e)))?;
+				if let Some(child_value) = child_value {
+					pairs.push((child_key, child_value));
+				}
+			}
+			children.push((storage_key, pairs));
+		} else {
+			top.push((key, value));
+		}
+	}
+	let snapshot = Snapshot { top, children };
+	transaction.set_from_vec(columns::STATE_HISTORY, &key(SNAPSHOT_PREFIX, hash), snapshot.encode());
+	Ok(())
+}
+
+/// Removes the delta or the snapshot of the block `hash`, once the block is reverted or its fork
+/// is discarded.
+pub(crate) fn remove(transaction: &mut Transaction<DbHash>, hash: &[u8]) {
+	transaction.remove(columns::STATE_HISTORY, &key(DELTA_PREFIX, hash));
+	transaction.remove(columns::STATE_HISTORY, &key(SNAPSHOT_PREFIX, hash));
+}
+
+/// Writes the whole storage of the block `hash` being committed, read from the trie nodes it
+/// inserts, `inserted`, over the ones of `db`, so that the snapshot is committed along with the
+/// block. Returns `inserted` back.
+pub(crate) fn write_pending_snapshot<Block: BlockT>(
+	transaction: &mut Transaction<DbHash>,
+	hash: &[u8],
+	state_root: Block::Hash,
+	inserted: PrefixedMemoryDB<HashFor<Block>>,
+	db: Arc<dyn sp_state_machine::Storage<HashFor<Block>>>,
+) -> ClientResult<PrefixedMemoryDB<HashFor<Block>>> {
+	let storage = Arc::new(PendingStorage { inserted, db });
+	let state = DbState::<Block>::new(storage.clone(), state_root);
+	write_snapshot(transaction, hash, &state)?;
+	drop(state);
+	let storage = Arc::try_unwrap(storage).ok().expect("the only other reference was held by the dropped state; qed");
+	Ok(storage.inserted)
+}
+
+/// Returns the whole storage of the block `hash`, from the closest snapshot of its ancestors
+/// and the deltas of the blocks since then.
+///
+/// `parent` returns the hash of the parent of a block.
+pub(crate) fn storage<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	hash: Block::Hash,
+	parent: impl Fn(Block::Hash) -> ClientResult<Block::Hash>,
+) -> ClientResult<HashMap<Option<ChildInfo>, BTreeMap<StorageKey, Vec<u8>>>> {
+	let mut deltas = Vec::new();
+	let mut current = hash;
+	let snapshot: Snapshot = loop {
+		if let Some(snapshot) = db.get(columns::STATE_HISTORY, &key(SNAPSHOT_PREFIX, current.as_ref())) {
+			break decode(snapshot, "snapshot")?
+		}
+		match db.get(columns::STATE_HISTORY, &key(DELTA_PREFIX, current.as_ref())) {
+			Some(delta) => deltas.push(decode::<Delta>(delta, "delta")?),
+			None => return Err(sp_blockchain::Error::UnknownBlock(
+				format!("State of {:?} isn't in the delta archive", hash),
+			)),
+		}
+		current = parent(current)?;
+	};
+
+	let mut storage: HashMap<_, BTreeMap<_, _>> = HashMap::new();
+	storage.insert(None, snapshot.top.into_iter().collect());
+	for (storage_key, pairs) in snapshot.children {
+		storage.insert(Some(ChildInfo::new_default_from_vec(storage_key)), pairs.into_iter().collect());
+	}
+	for delta in deltas.into_iter().rev() {
+		let children = delta.children.into_iter()
+			.map(|(storage_key, changes)| (Some(ChildInfo::new_default_from_vec(storage_key)), changes));
+		for (child_info, changes) in std::iter::once((None, delta.top)).chain(children) {
+			let entries = storage.entry(child_info).or_default();
+			for (key, value) in changes {
+				match value {
+					Some(value) => entries.insert(key, value),
+					None => entries.remove(&key),
+				};
+			}
+		}
+	}
+	Ok(storage)
+}
+
+/// Reconstructs the state of the block `hash`, failing if it doesn't match `state_root`.
+fn reconstruct_state<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	hash: Block::Hash,
+	state_root: Block::Hash,
+	parent: impl Fn(Block::Hash) -> ClientResult<Block::Hash>,
+) -> ClientResult<Arc<dyn sp_state_machine::Storage<HashFor<Block>>>> {
+	let storage = storage::<Block>(db, hash, parent)?;
+	let state: sp_state_machine::InMemoryBackend<HashFor<Block>> = storage.into();
+	if *state.root() != state_root {
+		return Err(sp_blockchain::Error::Backend(format!(
+			"The state of {:?} reconstructed from the delta archive doesn't match its state root",
+			hash,
+		)))
+	}
+	Ok(Arc::new(ReconstructedStorage(state.into_storage())))
+}
+
+/// States reconstructed from the delta archive, the ones read the longest ago being dropped
+/// first.
+///
+/// Every state holds all the trie nodes of the storage of its block, so at most
+/// `RECONSTRUCTED_STATES_CACHE_SIZE` of them are kept.
+pub(crate) struct ReconstructedStates<Block: BlockT> {
+	states: Mutex<LinkedHashMap<Block::Hash, Arc<dyn sp_state_machine::Storage<HashFor<Block>>>>>,
+}
+
+impl<Block: BlockT> ReconstructedStates<Block> {
+	pub(crate) fn new() -> Self {
+		ReconstructedStates { states: Mutex::new(LinkedHashMap::new()) }
+	}
+
+	/// Returns the state of the block `hash`, reconstructing it if it isn't cached.
+	pub(crate) fn state(
+		&self,
+		db: &dyn Database<DbHash>,
+		hash: Block::Hash,
+		state_root: Block::Hash,
+		parent: impl Fn(Block::Hash) -> ClientResult<Block::Hash>,
+	) -> ClientResult<DbState<Block>> {
+		if let Some(storage) = self.states.lock().get_refresh(&hash) {
+			return Ok(DbState::<Block>::new(storage.clone(), state_root))
+		}
+		let storage = reconstruct_state::<Block>(db, hash, state_root, parent)?;
+		let mut states = self.states.lock();
+		states.insert(hash, storage.clone());
+		while states.len() > RECONSTRUCTED_STATES_CACHE_SIZE {
+			states.pop_front();
+		}
+		Ok(DbState::<Block>::new(storage, state_root))
+	}
+}
+
+/// Trie nodes of a state reconstructed in memory.
+struct ReconstructedStorage<H: hash_db::Hasher>(MemoryDB<H>);
+
+impl<H: hash_db::Hasher> sp_state_machine::Storage<H> for ReconstructedStorage<H> {
+	fn get(&self, key: &H::Out, prefix: Prefix) -> Result<Option<DBValue>, String> {
+		Ok(hash_db::HashDB::get(&self.0, key, prefix))
+	}
+}
+
+/// Trie nodes of the state of a block being committed: the ones it inserts, over the ones of
+/// the database.
+struct PendingStorage<H: hash_db::Hasher> {
+	inserted: PrefixedMemoryDB<H>,
+	db: Arc<dyn sp_state_machine::Storage<H>>,
+}
+
+impl<H: hash_db::Hasher> sp_state_machine::Storage<H> for PendingStorage<H> {
+	fn get(&self, key: &H::Out, prefix: Prefix) -> Result<Option<DBValue>, String> {
+		match hash_db::HashDB::get(&self.inserted, key, prefix) {
+			Some(value) => Ok(Some(value)),
+			None => self.db.get(key, prefix),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+	use sp_core::H256;
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	#[test]
+	fn storage_is_the_snapshot_with_the_deltas_applied() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let (first, second, third) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+		let child = ChildInfo::new_default(b"child");
+
+		let mut transaction = Transaction::new();
+		let snapshot = Snapshot {
+			top: vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"1".to_vec())],
+			children: vec![(b"child".to_vec(), vec![(b"c".to_vec(), b"1".to_vec())])],
+		};
+		transaction.set_from_vec(columns::STATE_HISTORY, &key(SNAPSHOT_PREFIX, first.as_ref()), snapshot.encode());
+		write_delta(&mut transaction, second.as_ref(), &vec![(b"a".to_vec(), Some(b"2".to_vec()))], &vec![]);
+		write_delta(
+			&mut transaction,
+			third.as_ref(),
+			&vec![(b"b".to_vec(), None)],
+			&vec![(b"child".to_vec(), vec![(b"d".to_vec(), Some(b"3".to_vec()))])],
+		);
+		db.commit(transaction);
+
+		let parent = |hash: H256| Ok(if hash == third { second } else { first });
+		let storage = storage::<Block>(&*db, third, parent).unwrap();
+		assert_eq!(storage[&None], vec![(b"a".to_vec(), b"2".to_vec())].into_iter().collect());
+		assert_eq!(
+			storage[&Some(child)],
+			vec![(b"c".to_vec(), b"1".to_vec()), (b"d".to_vec(), b"3".to_vec())].into_iter().collect(),
+		);
+
+		assert!(storage::<Block>(&*db, H256::repeat_byte(4), parent).is_err());
+	}
+
+	#[test]
+	fn only_the_latest_reconstructed_states_are_cached() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let states = ReconstructedStates::<Block>::new();
+		let no_parent = |hash: H256| Err(sp_blockchain::Error::UnknownBlock(format!("{:?}", hash)));
+		let mut hashes = Vec::new();
+		for n in 0..=RECONSTRUCTED_STATES_CACHE_SIZE as u8 {
+			let hash = H256::repeat_byte(n);
+			let top = vec![(b"key".to_vec(), vec![n])];
+			let mut transaction = Transaction::new();
+			let snapshot = Snapshot { top: top.clone(), children: Vec::new() };
+			transaction.set_from_vec(columns::STATE_HISTORY, &key(SNAPSHOT_PREFIX, hash.as_ref()), snapshot.encode());
+			db.commit(transaction);
+
+			let state: sp_state_machine::InMemoryBackend<HashFor<Block>> =
+				vec![(None, top.into_iter().collect())].into_iter().collect::<HashMap<_, BTreeMap<_, _>>>().into();
+			let reconstructed = states.state(&*db, hash, *state.root(), no_parent).unwrap();
+			assert_eq!(reconstructed.storage(b"key").unwrap(), Some(vec![n]));
+			hashes.push(hash);
+		}
+
+		let cached = states.states.lock().keys().cloned().collect::<Vec<_>>();
+		assert_eq!(cached, hashes[1..].to_vec());
+	}
+}
@@ -34,10 +34,20 @@ pub mod offchain;
 #[cfg(any(feature = "kvdb-rocksdb", test))]
 pub mod bench;
 
+mod bounded_mem;
+mod check;
 mod children;
 mod cache;
+mod cold;
+mod delta_archive;
+mod storage_changes_index;
 mod changes_tries_storage;
+mod migrate;
+mod purge;
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+mod secondary;
 mod storage_cache;
+mod trie_cache;
 #[cfg(any(feature = "kvdb-rocksdb", test))]
 mod upgrade;
 mod utils;
@@ -54,7 +64,7 @@ use std::collections::HashMap;
 
 
 use sc_client_api::{
-	UsageInfo, MemoryInfo, IoInfo, MemorySize,
+	UsageInfo, MemoryInfo, IoInfo, MemorySize, IndexedData,
 	backend::{NewBlockState, PrunableStateChangesTrieStorage},
 	leaves::{LeafSet, FinalizationDisplaced},
 };
@@ -68,11 +78,12 @@ use sp_trie::{MemoryDB, PrefixedMemoryDB, prefixed_key};
 use sp_database::Transaction;
 use parking_lot::RwLock;
 use sp_core::ChangesTrieConfiguration;
-use sp_core::offchain::storage::{OffchainOverlayedChange, OffchainOverlayedChanges};
+use sp_core::offchain::{STORAGE_PREFIX, storage::{OffchainOverlayedChange, OffchainOverlayedChanges}};
 use sp_core::storage::{well_known_keys, ChildInfo};
 use sp_runtime::{generic::BlockId, Justification, Storage};
 use sp_runtime::traits::{
 	Block as BlockT, Header as HeaderT, NumberFor, Zero, One, SaturatedConversion, HashFor,
+	Hash as HashT,
 };
 use sp_state_machine::{
 	DBValue, ChangesTrieTransaction, ChangesTrieCacheAction, UsageInfo as StateUsageInfo,
@@ -84,18 +95,31 @@ use crate::changes_tries_storage::{DbChangesTrieStorage, DbChangesTrieStorageTra
 use sc_state_db::StateDb;
 use sp_blockchain::{CachedHeaderMetadata, HeaderMetadata, HeaderMetadataCache};
 use crate::storage_cache::{CachingState, SyncingCachingState, SharedCache, new_shared_cache};
-use crate::stats::StateUsageStats;
+use crate::stats::{DatabaseIoTotals, DatabaseUsageStats, StateUsageStats, StatsDatabase};
+use crate::trie_cache::TrieNodeCache;
 use log::{trace, debug, warn};
 
 // Re-export the Database trait so that one can pass an implementation of it.
 pub use sp_database::Database;
 pub use sc_state_db::PruningMode;
+pub use bounded_mem::BoundedMemoryDb;
+pub use check::{check_database, DatabaseCheck, Inconsistency};
+pub use cold::ColdStorage;
+pub use delta_archive::DeltaArchive;
+pub use purge::{purge_database, DatabaseData};
+pub use migrate::{migrate_database, MigrationProgress};
 
 #[cfg(any(feature = "kvdb-rocksdb", test))]
 pub use bench::BenchmarkingState;
 
 const MIN_BLOCKS_TO_KEEP_CHANGES_TRIES_FOR: u32 = 32768;
 
+/// Default number of blocks behind the best block from which the states are canonicalized.
+pub const DEFAULT_CANONICALIZATION_DELAY: u64 = 4096;
+
+/// Maximum number of blocks whose bodies or justifications are pruned at each finalization.
+const MAX_BLOCKS_PRUNED_AT_ONCE: u32 = 4096;
+
 /// Default value for storage cache child ratio.
 const DEFAULT_CHILD_RATIO: (usize, usize) = (1, 10);
 
@@ -264,12 +288,39 @@ pub struct DatabaseSettings {
 	pub state_cache_size: usize,
 	/// Ratio of cache size dedicated to child tries.
 	pub state_cache_child_ratio: Option<(usize, usize)>,
+	/// Size of the cache of trie nodes shared by all the states, in bytes.
+	pub trie_cache_size: usize,
 	/// Pruning mode.
 	pub pruning: PruningMode,
+	/// Pruning of the bodies and justifications of the blocks.
+	pub block_pruning: BlockPruning,
+	/// Whether the extrinsics of the imported blocks are indexed by their hash.
+	pub index_extrinsics: bool,
+	/// Whether the imported blocks are indexed by the keys of the top storage they change.
+	pub index_storage_changes: bool,
+	/// Secondary database the bodies and justifications of the ancient blocks are moved to.
+	pub cold_storage: Option<ColdStorage>,
+	/// Archive of the states of the blocks as deltas against periodic snapshots, the states being
+	/// reconstructed once their trie nodes are pruned.
+	pub delta_archive: Option<DeltaArchive>,
+	/// Number of blocks behind the best block from which the states are canonicalized.
+	///
+	/// The forks of the chain deeper than this can't be imported anymore.
+	pub canonicalization_delay: u64,
 	/// Where to find the database.
 	pub source: DatabaseSettingsSrc,
 }
 
+/// Pruning of the bodies and justifications of the finalized blocks, independent of the state
+/// pruning. The headers are always kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockPruning {
+	/// Number of finalized blocks whose bodies are kept, all of them if `None`.
+	pub keep_bodies: Option<u32>,
+	/// Number of finalized blocks whose justifications are kept, all of them if `None`.
+	pub keep_justifications: Option<u32>,
+}
+
 /// Where to find the database..
 #[derive(Clone)]
 pub enum DatabaseSettingsSrc {
@@ -281,6 +332,17 @@ pub enum DatabaseSettingsSrc {
 		cache_size: usize,
 	},
 
+	/// Load a RocksDB database written by another process from a given path, as a read-only
+	/// secondary instance.
+	RocksDbSecondary {
+		/// Path to the database of the primary instance.
+		path: PathBuf,
+		/// Path to the logs of the secondary instance.
+		secondary_path: PathBuf,
+		/// Cache size in MiB.
+		cache_size: usize,
+	},
+
 	/// Load a ParityDb database from a given path.
 	ParityDb {
 		/// Path to the database.
@@ -302,11 +364,19 @@ impl DatabaseSettingsSrc {
 	pub fn path(&self) -> Option<&Path> {
 		match self {
 			DatabaseSettingsSrc::RocksDb { path, .. } => Some(path.as_path()),
+			DatabaseSettingsSrc::RocksDbSecondary { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::ParityDb { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::SubDb { path, .. } => Some(path.as_path()),
 			DatabaseSettingsSrc::Custom(_) => None,
 		}
 	}
+	/// Check if the database is opened in read-only mode.
+	pub fn is_read_only(&self) -> bool {
+		match self {
+			DatabaseSettingsSrc::RocksDbSecondary { .. } => true,
+			_ => false,
+		}
+	}
 	/// Check if database supports internal ref counting for state data.
 	pub fn supports_ref_counting(&self) -> bool {
 		match self {
@@ -320,6 +390,7 @@ impl std::fmt::Display for DatabaseSettingsSrc {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let name = match self {
 			DatabaseSettingsSrc::RocksDb { .. } => "RocksDb",
+			DatabaseSettingsSrc::RocksDbSecondary { .. } => "RocksDb (secondary)",
 			DatabaseSettingsSrc::ParityDb { .. } => "ParityDb",
 			DatabaseSettingsSrc::SubDb { .. } => "SubDb",
 			DatabaseSettingsSrc::Custom(_) => "Custom",
@@ -342,6 +413,33 @@ pub(crate) mod columns {
 	/// Offchain workers local storage
 	pub const OFFCHAIN: u32 = 9;
 	pub const CACHE: u32 = 10;
+	/// Values written by the runtime through the offchain indexing API.
+	pub const OFFCHAIN_INDEX: u32 = 11;
+	/// maps hashes of extrinsics to the hashes of the blocks including them and their index in each.
+	pub const EXTRINSIC_INDEX: u32 = 12;
+	/// Deltas and snapshots of the states of the blocks, when they are archived as deltas.
+	pub const STATE_HISTORY: u32 = 13;
+	/// maps the keys of the top storage to the blocks that changed them, by chunks of blocks.
+	pub const STORAGE_CHANGES_INDEX: u32 = 14;
+
+	/// Names of the columns in the usage statistics, indexed by column.
+	pub const NAMES: &[&str] = &[
+		"meta",
+		"state",
+		"state_meta",
+		"key_lookup",
+		"header",
+		"body",
+		"justification",
+		"changes_trie",
+		"aux",
+		"offchain",
+		"cache",
+		"offchain_index",
+		"extrinsic_index",
+		"state_history",
+		"storage_changes_index",
+	];
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -365,23 +463,48 @@ impl<'a> sc_state_db::MetaDb for StateMetaDb<'a> {
 /// Block database
 pub struct BlockchainDb<Block: BlockT> {
 	db: Arc<dyn Database<DbHash>>,
+	cold: Option<Arc<dyn Database<DbHash>>>,
 	meta: Arc<RwLock<Meta<NumberFor<Block>, Block::Hash>>>,
 	leaves: RwLock<LeafSet<Block::Hash, NumberFor<Block>>>,
 	header_metadata_cache: Arc<HeaderMetadataCache<Block>>,
+	/// Number of the first block whose storage changes are indexed, if they are.
+	storage_changes_index_start: RwLock<Option<NumberFor<Block>>>,
 }
 
 impl<Block: BlockT> BlockchainDb<Block> {
-	fn new(db: Arc<dyn Database<DbHash>>) -> ClientResult<Self> {
+	fn new(db: Arc<dyn Database<DbHash>>, cold: Option<Arc<dyn Database<DbHash>>>) -> ClientResult<Self> {
 		let meta = read_meta::<Block>(&*db, columns::HEADER)?;
 		let leaves = LeafSet::read_from_db(&*db, columns::META, meta_keys::LEAF_PREFIX)?;
+		let storage_changes_index_start = read_storage_changes_index_start::<Block>(&*db)?;
 		Ok(BlockchainDb {
 			db,
+			cold,
 			leaves: RwLock::new(leaves),
 			meta: Arc::new(RwLock::new(meta)),
 			header_metadata_cache: Arc::new(HeaderMetadataCache::default()),
+			storage_changes_index_start: RwLock::new(storage_changes_index_start),
 		})
 	}
 
+	// reloads the best, finalized and leaf blocks, after the database has been written by another
+	// instance.
+	fn reload(&self) -> ClientResult<()> {
+		*self.meta.write() = read_meta::<Block>(&*self.db, columns::HEADER)?;
+		*self.leaves.write() = LeafSet::read_from_db(&*self.db, columns::META, meta_keys::LEAF_PREFIX)?;
+		*self.storage_changes_index_start.write() = read_storage_changes_index_start::<Block>(&*self.db)?;
+		Ok(())
+	}
+
+	// reads the body or the justification of a block, from the cold database if it has been moved.
+	fn read_block_data(&self, column: u32, id: BlockId<Block>) -> ClientResult<Option<Vec<u8>>> {
+		let lookup_key = match utils::block_id_to_lookup_key(&*self.db, columns::KEY_LOOKUP, id)? {
+			Some(lookup_key) => lookup_key,
+			None => return Ok(None),
+		};
+		Ok(self.db.get(column, &lookup_key)
+			.or_else(|| self.cold.as_ref().and_then(|cold| cold.get(column, &lookup_key))))
+	}
+
 	fn update_meta(
 		&self,
 		hash: Block::Hash,
@@ -454,7 +577,7 @@ impl<Block: BlockT> sc_client_api::blockchain::HeaderBackend<Block> for Blockcha
 
 impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<Block> {
 	fn body(&self, id: BlockId<Block>) -> ClientResult<Option<Vec<Block::Extrinsic>>> {
-		match read_db(&*self.db, columns::KEY_LOOKUP, columns::BODY, id)? {
+		match self.read_block_data(columns::BODY, id)? {
 			Some(body) => match Decode::decode(&mut &body[..]) {
 				Ok(body) => Ok(Some(body)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
@@ -466,7 +589,7 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 	}
 
 	fn justification(&self, id: BlockId<Block>) -> ClientResult<Option<Justification>> {
-		match read_db(&*self.db, columns::KEY_LOOKUP, columns::JUSTIFICATION, id)? {
+		match self.read_block_data(columns::JUSTIFICATION, id)? {
 			Some(justification) => match Decode::decode(&mut &justification[..]) {
 				Ok(justification) => Ok(Some(justification)),
 				Err(err) => return Err(sp_blockchain::Error::Backend(
@@ -492,6 +615,63 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 	fn children(&self, parent_hash: Block::Hash) -> ClientResult<Vec<Block::Hash>> {
 		children::read_children(&*self.db, columns::META, meta_keys::CHILDREN_PREFIX, parent_hash)
 	}
+
+	fn extrinsic_location(&self, hash: &Block::Hash) -> ClientResult<Option<(Block::Hash, u32)>> {
+		for (block_hash, index) in read_extrinsic_locations::<Block>(&*self.db, hash)? {
+			// the extrinsic may be located in retracted blocks, or in blocks whose body has been
+			// pruned.
+			let number = match self.number(block_hash)? {
+				Some(number) => number,
+				None => continue,
+			};
+			if self.hash(number)? != Some(block_hash) {
+				continue;
+			}
+			let included = self.body(BlockId::Hash(block_hash))?
+				.and_then(|body| body.get(index as usize).map(HashFor::<Block>::hash_of))
+				.map_or(false, |extrinsic_hash| extrinsic_hash == *hash);
+			if included {
+				return Ok(Some((block_hash, index)));
+			}
+		}
+		Ok(None)
+	}
+
+	fn storage_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		key: &[u8],
+	) -> ClientResult<Option<Vec<(NumberFor<Block>, Block::Hash)>>> {
+		match *self.storage_changes_index_start.read() {
+			Some(start) if start <= first =>
+				storage_changes_index::changes::<Block>(&*self.db, first, last, key).map(Some),
+			_ => Ok(None),
+		}
+	}
+}
+
+fn read_pruned_to<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	key: &[u8],
+) -> ClientResult<Option<NumberFor<Block>>> {
+	match db.get(columns::META, key) {
+		Some(number) => Decode::decode(&mut &number[..]).map(Some).map_err(|err|
+			sp_blockchain::Error::Backend(format!("Error decoding the last pruned block: {}", err))
+		),
+		None => Ok(None),
+	}
+}
+
+fn read_storage_changes_index_start<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+) -> ClientResult<Option<NumberFor<Block>>> {
+	match db.get(columns::META, meta_keys::STORAGE_CHANGES_INDEX_START) {
+		Some(start) => Decode::decode(&mut &start[..]).map(Some).map_err(|err|
+			sp_blockchain::Error::Backend(format!("Error decoding the start of the storage changes index: {}", err))
+		),
+		None => Ok(None),
+	}
 }
 
 impl<Block: BlockT> sc_client_api::blockchain::ProvideCache<Block> for BlockchainDb<Block> {
@@ -540,14 +720,17 @@ pub struct BlockImportOperation<Block: BlockT> {
 	finalized_blocks: Vec<(BlockId<Block>, Option<Justification>)>,
 	set_head: Option<BlockId<Block>>,
 	commit_state: bool,
+	imported_state: bool,
 }
 
 impl<Block: BlockT> BlockImportOperation<Block> {
 	fn apply_offchain(&mut self, transaction: &mut Transaction<DbHash>) {
 		for (key, value_operation) in self.offchain_storage_updates.drain() {
+			// the runtime indexes its values under the prefix of the persistent offchain storage.
+			let key = if key.starts_with(STORAGE_PREFIX) { &key[STORAGE_PREFIX.len()..] } else { &key[..] };
 			match value_operation {
-				OffchainOverlayedChange::SetValue(val) => transaction.set_from_vec(columns::OFFCHAIN, &key, val),
-				OffchainOverlayedChange::Remove => transaction.remove(columns::OFFCHAIN, &key),
+				OffchainOverlayedChange::SetValue(val) => transaction.set_from_vec(columns::OFFCHAIN_INDEX, key, val),
+				OffchainOverlayedChange::Remove => transaction.remove(columns::OFFCHAIN_INDEX, key),
 			}
 		}
 	}
@@ -631,6 +814,24 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block> for Bloc
 		Ok(root)
 	}
 
+	fn set_imported_state(
+		&mut self,
+		storage: Storage,
+	) -> ClientResult<Block::Hash> {
+		// the changes tries configuration cache can't be started from a block with unknown
+		// ancestry.
+		if storage.top.contains_key(well_known_keys::CHANGES_TRIE_CONFIG) {
+			return Err(sp_blockchain::Error::Backend(
+				"Importing state of chains with changes tries enabled is not supported".into()
+			));
+		}
+
+		let root = self.reset_storage(storage)?;
+		self.changes_trie_config_update = None;
+		self.imported_state = true;
+		Ok(root)
+	}
+
 	fn update_changes_trie(
 		&mut self,
 		update: ChangesTrieTransaction<HashFor<Block>, NumberFor<Block>>,
@@ -685,17 +886,25 @@ struct StorageDb<Block: BlockT> {
 	pub db: Arc<dyn Database<DbHash>>,
 	pub state_db: StateDb<Block::Hash, Vec<u8>>,
 	prefix_keys: bool,
+	trie_cache: TrieNodeCache<Block::Hash>,
 }
 
 impl<Block: BlockT> sp_state_machine::Storage<HashFor<Block>> for StorageDb<Block> {
 	fn get(&self, key: &Block::Hash, prefix: Prefix) -> Result<Option<DBValue>, String> {
-		if self.prefix_keys {
+		if let Some(node) = self.trie_cache.get(key) {
+			return Ok(Some(node))
+		}
+		let node = if self.prefix_keys {
 			let key = prefixed_key::<HashFor<Block>>(key, prefix);
 			self.state_db.get(&key, self)
 		} else {
 			self.state_db.get(key.as_ref(), self)
 		}
-		.map_err(|e| format!("Database backend error: {:?}", e))
+		.map_err(|e| format!("Database backend error: {:?}", e))?;
+		if let Some(node) = &node {
+			self.trie_cache.insert(*key, node.clone());
+		}
+		Ok(node)
 	}
 }
 
@@ -777,41 +986,159 @@ pub struct Backend<Block: BlockT> {
 	shared_cache: SharedCache<Block>,
 	import_lock: Arc<RwLock<()>>,
 	is_archive: bool,
-	io_stats: FrozenForDuration<(kvdb::IoStats, StateUsageInfo)>,
+	block_pruning: BlockPruning,
+	index_extrinsics: bool,
+	index_storage_changes: bool,
+	cold_migration: Option<cold::ColdMigration<Block>>,
+	delta_archive: Option<DeltaArchive>,
+	reconstructed_states: delta_archive::ReconstructedStates<Block>,
+	read_only: bool,
+	#[cfg(any(feature = "kvdb-rocksdb", test))]
+	secondary: Option<Arc<secondary::SecondaryDatabase>>,
+	io_stats: FrozenForDuration<(DatabaseIoTotals, StateUsageInfo)>,
 	state_usage: Arc<StateUsageStats>,
+	database_usage: Arc<DatabaseUsageStats>,
 }
 
 impl<Block: BlockT> Backend<Block> {
 	/// Create a new instance of database backend.
 	///
 	/// The pruning window is how old a block must be before the state is pruned.
-	pub fn new(config: DatabaseSettings, canonicalization_delay: u64) -> ClientResult<Self> {
+	pub fn new(config: DatabaseSettings) -> ClientResult<Self> {
+		if let Some(backend) = Self::open_secondary(&config)? {
+			return Ok(backend)
+		}
 		let db = crate::utils::open_database::<Block>(&config, DatabaseType::Full)?;
-		Self::from_database(db as Arc<_>, canonicalization_delay, &config)
+		Self::from_database(db as Arc<_>, &config)
+	}
+
+	/// Opens the backend over a read-only secondary instance of the database, if the settings
+	/// select one.
+	#[cfg(any(feature = "kvdb-rocksdb", test))]
+	fn open_secondary(config: &DatabaseSettings) -> ClientResult<Option<Self>> {
+		match &config.source {
+			DatabaseSettingsSrc::RocksDbSecondary { path, secondary_path, cache_size } => {
+				let db = crate::utils::open_secondary_database(path, secondary_path, *cache_size, DatabaseType::Full)?;
+				let mut backend = Self::from_database(db.clone() as Arc<dyn Database<DbHash>>, config)?;
+				backend.secondary = Some(db);
+				Ok(Some(backend))
+			},
+			_ => Ok(None),
+		}
+	}
+
+	/// Opens the backend over a read-only secondary instance of the database, if the settings
+	/// select one.
+	#[cfg(not(any(feature = "kvdb-rocksdb", test)))]
+	fn open_secondary(_config: &DatabaseSettings) -> ClientResult<Option<Self>> {
+		Ok(None)
+	}
+
+	/// Catches up with the changes written by the primary instance of the database, when it is
+	/// opened as a read-only secondary instance.
+	///
+	/// The secondary instance only sees the blocks imported by the primary instance until it last
+	/// caught up, so this should be called regularly.
+	#[cfg(any(feature = "kvdb-rocksdb", test))]
+	pub fn try_catch_up_with_primary(&self) -> ClientResult<()> {
+		let secondary = self.secondary.as_ref().ok_or_else(||
+			sp_blockchain::Error::Backend("The database isn't a secondary instance".into())
+		)?;
+		let _import_lock = self.import_lock.write();
+		secondary.catch_up()?;
+		self.storage.state_db.reload(self.storage.prefix_keys, &StateMetaDb(&*self.storage.db))
+			.map_err(|e| sp_blockchain::Error::from(format!("State database error: {:?}", e)))?;
+		self.blockchain.reload()
+	}
+
+	// reconstructs the pruned state of the block `hash` from the delta archive.
+	fn reconstructed_state_at(
+		&self,
+		hash: Block::Hash,
+		state_root: Block::Hash,
+	) -> ClientResult<SyncingCachingState<RefTrackingState<Block>, Block>> {
+		let db_state = self.reconstructed_states.state(
+			&*self.storage.db,
+			hash,
+			state_root,
+			|hash| Ok(self.blockchain.header_metadata(hash)?.parent),
+		)?;
+		let state = RefTrackingState::new(db_state, self.storage.clone(), None);
+		let caching_state = CachingState::new(state, self.shared_cache.clone(), None);
+		Ok(SyncingCachingState::new(
+			caching_state,
+			self.state_usage.clone(),
+			self.blockchain.meta.clone(),
+			self.import_lock.clone(),
+		))
+	}
+
+	// fails if the database is a read-only secondary instance.
+	fn ensure_writable(&self) -> ClientResult<()> {
+		if self.read_only {
+			return Err(sp_blockchain::Error::Backend("The database is opened in read-only mode".into()))
+		}
+		Ok(())
 	}
 
 	/// Create new memory-backed client backend for tests.
 	#[cfg(any(test, feature = "test-helpers"))]
 	pub fn new_test(keep_blocks: u32, canonicalization_delay: u64) -> Self {
 		let db = kvdb_memorydb::create(crate::utils::NUM_COLUMNS);
-		let db = sp_database::as_database(db);
+		Self::new_test_with_database(sp_database::as_database(db), keep_blocks, canonicalization_delay)
+	}
+
+	/// Create new memory-backed client backend for tests, keeping at most `max_memory` bytes of
+	/// the database in memory and moving the rest to a temporary file.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_with_memory_cap(keep_blocks: u32, canonicalization_delay: u64, max_memory: usize) -> Self {
+		let db = BoundedMemoryDb::new(max_memory).expect("failed to create test-db");
+		Self::new_test_with_database(Arc::new(db), keep_blocks, canonicalization_delay)
+	}
+
+	#[cfg(any(test, feature = "test-helpers"))]
+	fn new_test_with_database(db: Arc<dyn Database<DbHash>>, keep_blocks: u32, canonicalization_delay: u64) -> Self {
 		let db_setting = DatabaseSettings {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
 			pruning: PruningMode::keep_blocks(keep_blocks),
+			block_pruning: Default::default(),
+			index_extrinsics: true,
+			index_storage_changes: true,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay,
 			source: DatabaseSettingsSrc::Custom(db),
 		};
 
-		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
+		Self::new(db_setting).expect("failed to create test-db")
 	}
 
 	fn from_database(
 		db: Arc<dyn Database<DbHash>>,
-		canonicalization_delay: u64,
 		config: &DatabaseSettings,
 	) -> ClientResult<Self> {
+		let database_usage = Arc::new(DatabaseUsageStats::new(columns::NAMES));
+		let db: Arc<dyn Database<DbHash>> = Arc::new(StatsDatabase::new(db, database_usage.clone()));
 		let is_archive_pruning = config.pruning.is_archive();
-		let blockchain = BlockchainDb::new(db.clone())?;
+		if config.source.is_read_only() && config.cold_storage.is_some() {
+			return Err(sp_blockchain::Error::Backend(
+				"The cold storage can't be used by a read-only instance of the database".into(),
+			))
+		}
+		if let Some(delta_archive) = &config.delta_archive {
+			if is_archive_pruning || delta_archive.snapshot_interval == 0 {
+				return Err(sp_blockchain::Error::Backend(
+					"The delta archive requires the state pruning and a non-zero snapshot interval".into(),
+				))
+			}
+		}
+		let cold_db = match &config.cold_storage {
+			Some(cold_storage) => Some(cold::open(&cold_storage.path)?),
+			None => None,
+		};
+		let blockchain = BlockchainDb::new(db.clone(), cold_db.clone())?;
 		let meta = blockchain.meta.clone();
 		let map_e = |e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from(
 			format!("State database error: {:?}", e)
@@ -825,8 +1152,14 @@ impl<Block: BlockT> Backend<Block> {
 			db: db.clone(),
 			state_db,
 			prefix_keys: !config.source.supports_ref_counting(),
+			trie_cache: TrieNodeCache::new(config.trie_cache_size),
 		};
 		let offchain_storage = offchain::LocalStorage::new(db.clone());
+		let cold_migration = match (cold_db, &config.cold_storage) {
+			(Some(cold_db), Some(cold_storage)) =>
+				Some(cold::ColdMigration::start(db.clone(), cold_db, cold_storage.keep_hot)?),
+			_ => None,
+		};
 		let changes_tries_storage = DbChangesTrieStorage::new(
 			db,
 			blockchain.header_metadata_cache.clone(),
@@ -848,15 +1181,25 @@ impl<Block: BlockT> Backend<Block> {
 			offchain_storage,
 			changes_tries_storage,
 			blockchain,
-			canonicalization_delay,
+			canonicalization_delay: config.canonicalization_delay,
 			shared_cache: new_shared_cache(
 				config.state_cache_size,
 				config.state_cache_child_ratio.unwrap_or(DEFAULT_CHILD_RATIO),
 			),
 			import_lock: Default::default(),
 			is_archive: is_archive_pruning,
+			block_pruning: config.block_pruning,
+			index_extrinsics: config.index_extrinsics,
+			index_storage_changes: config.index_storage_changes,
+			cold_migration,
+			delta_archive: config.delta_archive,
+			reconstructed_states: delta_archive::ReconstructedStates::new(),
+			read_only: config.source.is_read_only(),
+			#[cfg(any(feature = "kvdb-rocksdb", test))]
+			secondary: None,
 			io_stats: FrozenForDuration::new(std::time::Duration::from_secs(1)),
 			state_usage: Arc::new(StateUsageStats::new()),
+			database_usage,
 		})
 	}
 
@@ -1001,6 +1344,11 @@ impl<Block: BlockT> Backend<Block> {
 						implies existence of blocks with all numbers before it; qed")
 			};
 
+			// blocks imported without their state have nothing to canonicalize.
+			if self.storage.state_db.is_pruned(&hash, new_canonical) {
+				return Ok(())
+			}
+
 			trace!(target: "db", "Canonicalize block #{} ({:?})", new_canonical, hash);
 			let commit = self.storage.state_db.canonicalize_block(&hash)
 				.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from(format!("State database error: {:?}", e)))?;
@@ -1023,6 +1371,7 @@ impl<Block: BlockT> Backend<Block> {
 		let mut last_finalized_hash = self.blockchain.meta.read().finalized_hash;
 
 		let mut changes_trie_cache_ops = None;
+		let mut storage_changes_index_start = None;
 		for (block, justification) in operation.finalized_blocks {
 			let block_hash = self.blockchain.expect_block_hash_from_id(&block)?;
 			let block_header = self.blockchain.expect_header(BlockId::Hash(block_hash))?;
@@ -1047,7 +1396,23 @@ impl<Block: BlockT> Backend<Block> {
 			// blocks are keyed by number + hash.
 			let lookup_key = utils::number_and_hash_to_lookup_key(number, hash)?;
 
-			let (enacted, retracted) = if pending_block.leaf_state.is_best() {
+			// the header of a block imported again along with its body or state is already
+			// part of the tree of leaves and children.
+			let existing_header = self.blockchain.status(BlockId::Hash(hash))? == sc_client_api::blockchain::BlockStatus::InChain;
+
+			let (enacted, retracted) = if operation.imported_state {
+				// the ancestry of a block imported along with its state is unknown, so there is
+				// no route to it from the current best block.
+				transaction.set_from_vec(columns::META, meta_keys::BEST_BLOCK, lookup_key.clone());
+				transaction.set_from_vec(columns::META, meta_keys::FINALIZED_BLOCK, lookup_key.clone());
+				utils::insert_number_to_key_mapping(
+					&mut transaction,
+					columns::KEY_LOOKUP,
+					number,
+					hash,
+				)?;
+				(Default::default(), Default::default())
+			} else if pending_block.leaf_state.is_best() {
 				self.set_head_with_transaction(&mut transaction, parent_hash, (number, hash))?
 			} else {
 				(Default::default(), Default::default())
@@ -1069,6 +1434,30 @@ impl<Block: BlockT> Backend<Block> {
 			transaction.set_from_vec(columns::HEADER, &lookup_key, pending_block.header.encode());
 			if let Some(body) = &pending_block.body {
 				transaction.set_from_vec(columns::BODY, &lookup_key, body.encode());
+				if self.index_extrinsics {
+					// an extrinsic included by several blocks, e.g. on different forks, is located
+					// in each of them.
+					let mut locations = HashMap::<Block::Hash, Vec<(Block::Hash, u32)>>::new();
+					for (index, extrinsic) in body.iter().enumerate() {
+						let extrinsic_hash = HashFor::<Block>::hash_of(extrinsic);
+						if !locations.contains_key(&extrinsic_hash) {
+							let indexed = read_extrinsic_locations::<Block>(&*self.storage.db, &extrinsic_hash)?;
+							locations.insert(extrinsic_hash, indexed);
+						}
+						let extrinsic_locations = locations.get_mut(&extrinsic_hash)
+							.expect("the locations have been inserted above; qed");
+						if !extrinsic_locations.contains(&(hash, index as u32)) {
+							extrinsic_locations.push((hash, index as u32));
+						}
+					}
+					for (extrinsic_hash, extrinsic_locations) in locations {
+						transaction.set_from_vec(
+							columns::EXTRINSIC_INDEX,
+							extrinsic_hash.as_ref(),
+							extrinsic_locations.encode(),
+						);
+					}
+				}
 			}
 			if let Some(justification) = pending_block.justification {
 				transaction.set_from_vec(columns::JUSTIFICATION, &lookup_key, justification.encode());
@@ -1090,6 +1479,27 @@ impl<Block: BlockT> Backend<Block> {
 				let mut bytes: u64 = 0;
 				let mut removal: u64 = 0;
 				let mut bytes_removal: u64 = 0;
+				let number_u64 = number.saturated_into::<u64>();
+				if let Some(delta_archive) = &self.delta_archive {
+					if operation.imported_state || delta_archive.is_snapshot(number_u64) {
+						// the snapshot is read before the inserted trie nodes are drained, so that
+						// it is committed along with the block.
+						operation.db_updates = delta_archive::write_pending_snapshot::<Block>(
+							&mut transaction,
+							hash.as_ref(),
+							*pending_block.header.state_root(),
+							std::mem::take(&mut operation.db_updates),
+							self.storage.clone(),
+						)?;
+					} else {
+						delta_archive::write_delta(
+							&mut transaction,
+							hash.as_ref(),
+							&operation.storage_updates,
+							&operation.child_storage_updates,
+						);
+					}
+				}
 				for (mut key, (val, rc)) in operation.db_updates.drain() {
 					if !self.storage.prefix_keys {
 						// Strip prefix
@@ -1098,6 +1508,12 @@ impl<Block: BlockT> Backend<Block> {
 					if rc > 0 {
 						ops += 1;
 						bytes += key.len() as u64 + val.len() as u64;
+						// the nodes of the state of a block are likely to be read when importing
+						// its children.
+						let mut hash = Block::Hash::default();
+						let hash_len = hash.as_ref().len();
+						hash.as_mut().copy_from_slice(&key[key.len() - hash_len..]);
+						self.storage.trie_cache.insert(hash, val.to_vec());
 						if rc == 1 {
 							changeset.inserted.push((key, val.to_vec()));
 						} else {
@@ -1132,13 +1548,39 @@ impl<Block: BlockT> Backend<Block> {
 						}
 				}
 				self.state_usage.tally_writes(ops, bytes);
-				let number_u64 = number.saturated_into::<u64>();
-				let commit = self.storage.state_db.insert_block(
-					&hash,
-					number_u64,
-					&pending_block.header.parent_hash(),
-					changeset,
-				).map_err(|e: sc_state_db::Error<io::Error>|
+				let index_start = *self.blockchain.storage_changes_index_start.read();
+				if self.index_storage_changes {
+					if index_start.is_none() {
+						// the blocks above the highest leaf are the first ones indexed.
+						let start = match self.blockchain.leaves.read().hashes().first() {
+							Some(leaf) => self.blockchain.number(*leaf)?.map_or(number, |leaf| leaf + One::one()),
+							None => number,
+						};
+						transaction.set_from_vec(columns::META, meta_keys::STORAGE_CHANGES_INDEX_START, start.encode());
+						storage_changes_index_start = Some(Some(start));
+					}
+					storage_changes_index::write_changes::<Block>(
+						&*self.storage.db,
+						&mut transaction,
+						number,
+						hash,
+						&operation.storage_updates,
+					)?;
+				} else if index_start.is_some() {
+					// the blocks imported while the index is disabled leave it incomplete.
+					transaction.remove(columns::META, meta_keys::STORAGE_CHANGES_INDEX_START);
+					storage_changes_index_start = Some(None);
+				}
+				let commit = if operation.imported_state {
+					self.storage.state_db.import_canonical_block(&hash, number_u64, changeset)
+				} else {
+					self.storage.state_db.insert_block(
+						&hash,
+						number_u64,
+						&pending_block.header.parent_hash(),
+						changeset,
+					)
+				}.map_err(|e: sc_state_db::Error<io::Error>|
 					sp_blockchain::Error::from(format!("State database error: {:?}", e))
 				)?;
 				apply_state_commit(&mut transaction, commit);
@@ -1154,24 +1596,34 @@ impl<Block: BlockT> Backend<Block> {
 			let is_best = pending_block.leaf_state.is_best();
 			let changes_trie_updates = operation.changes_trie_updates;
 			let changes_trie_config_update = operation.changes_trie_config_update;
-			changes_trie_cache_ops = Some(self.changes_tries_storage.commit(
-				&mut transaction,
-				changes_trie_updates,
-				cache::ComplexBlockId::new(
-					*header.parent_hash(),
-					if number.is_zero() { Zero::zero() } else { number - One::one() },
-				),
-				cache::ComplexBlockId::new(hash, number),
-				header,
-				finalized,
-				changes_trie_config_update,
-				changes_trie_cache_ops,
-			)?);
+			if !operation.imported_state {
+				changes_trie_cache_ops = Some(self.changes_tries_storage.commit(
+					&mut transaction,
+					changes_trie_updates,
+					cache::ComplexBlockId::new(
+						*header.parent_hash(),
+						if number.is_zero() { Zero::zero() } else { number - One::one() },
+					),
+					cache::ComplexBlockId::new(hash, number),
+					header,
+					finalized,
+					changes_trie_config_update,
+					changes_trie_cache_ops,
+				)?);
+			}
 			self.state_usage.merge_sm(operation.old_state.usage_info());
 			// release state reference so that it can be finalized
 			let cache = operation.old_state.into_cache_changes();
 
-			if finalized {
+			if finalized && operation.imported_state {
+				// the state database has already been made to treat the block as canonical and
+				// the finalized block has been written to the meta column above.
+				let new_displaced = self.blockchain.leaves.write().finalize_height(number);
+				match finalization_displaced_leaves {
+					Some(ref mut displaced) => displaced.merge(new_displaced),
+					None => finalization_displaced_leaves = Some(new_displaced),
+				}
+			} else if finalized {
 				// TODO: ensure best chain contains this block.
 				self.ensure_sequential_finalization(header, Some(last_finalized_hash))?;
 				self.note_finalized(
@@ -1189,7 +1641,9 @@ impl<Block: BlockT> Backend<Block> {
 
 			debug!(target: "db", "DB Commit {:?} ({}), best = {}", hash, number, is_best);
 
-			let displaced_leaf = {
+			let displaced_leaf = if existing_header {
+				None
+			} else {
 				let mut leaves = self.blockchain.leaves.write();
 				let displaced_leaf = leaves.import(hash, number, parent_hash);
 				leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
@@ -1197,20 +1651,22 @@ impl<Block: BlockT> Backend<Block> {
 				displaced_leaf
 			};
 
-			let mut children = children::read_children(
-				&*self.storage.db,
-				columns::META,
-				meta_keys::CHILDREN_PREFIX,
-				parent_hash,
-			)?;
-			children.push(hash);
-			children::write_children(
-				&mut transaction,
-				columns::META,
-				meta_keys::CHILDREN_PREFIX,
-				parent_hash,
-				children,
-			);
+			if !existing_header {
+				let mut children = children::read_children(
+					&*self.storage.db,
+					columns::META,
+					meta_keys::CHILDREN_PREFIX,
+					parent_hash,
+				)?;
+				children.push(hash);
+				children::write_children(
+					&mut transaction,
+					columns::META,
+					meta_keys::CHILDREN_PREFIX,
+					parent_hash,
+					children,
+				);
+			}
 
 			meta_updates.push((hash, number, pending_block.leaf_state.is_best(), finalized));
 
@@ -1238,8 +1694,20 @@ impl<Block: BlockT> Backend<Block> {
 			None
 		};
 
+		let last_finalized = meta_updates.iter()
+			.filter(|(_, _, _, is_finalized)| *is_finalized)
+			.map(|(hash, number, _, _)| (*number, *hash))
+			.max_by_key(|(number, _)| *number);
+		if let Some((number, hash)) = last_finalized {
+			self.prune_blocks(&mut transaction, number, hash)?;
+		}
+
 		self.storage.db.commit(transaction);
 
+		if let Some(start) = storage_changes_index_start {
+			*self.blockchain.storage_changes_index_start.write() = start;
+		}
+
 		if let Some((
 			number,
 			hash,
@@ -1271,6 +1739,9 @@ impl<Block: BlockT> Backend<Block> {
 
 		for (hash, number, is_best, is_finalized) in meta_updates {
 			self.blockchain.update_meta(hash, number, is_best, is_finalized);
+			if is_finalized {
+				self.note_cold_finalized(number);
+			}
 		}
 
 		Ok(())
@@ -1294,9 +1765,13 @@ impl<Block: BlockT> Backend<Block> {
 			let lookup_key = utils::number_and_hash_to_lookup_key(f_num, f_hash.clone())?;
 			transaction.set_from_vec(columns::META, meta_keys::FINALIZED_BLOCK, lookup_key);
 
-			let commit = self.storage.state_db.canonicalize_block(&f_hash)
-				.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from(format!("State database error: {:?}", e)))?;
-			apply_state_commit(transaction, commit);
+			// blocks imported without their state, such as the headers downloaded by fast sync,
+			// have nothing to canonicalize.
+			if !self.storage.state_db.is_pruned(&f_hash, f_num.saturated_into::<u64>()) {
+				let commit = self.storage.state_db.canonicalize_block(&f_hash)
+					.map_err(|e: sc_state_db::Error<io::Error>| sp_blockchain::Error::from(format!("State database error: {:?}", e)))?;
+				apply_state_commit(transaction, commit);
+			}
 
 			if !f_num.is_zero() {
 				let new_changes_trie_cache_ops = self.changes_tries_storage.finalize(
@@ -1319,6 +1794,124 @@ impl<Block: BlockT> Backend<Block> {
 
 		Ok(())
 	}
+
+	// removes the bodies and the justifications of the canonical blocks leaving the windows of the
+	// block pruning after the block `f_hash` is finalized, from the last pruned ones.
+	//
+	// must be called once the justifications of the transaction are written.
+	fn prune_blocks(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		f_num: NumberFor<Block>,
+		f_hash: Block::Hash,
+	) -> ClientResult<()> {
+		let windows = [
+			(columns::BODY, meta_keys::BODIES_PRUNED_TO, self.block_pruning.keep_bodies),
+			(columns::JUSTIFICATION, meta_keys::JUSTIFICATIONS_PRUNED_TO, self.block_pruning.keep_justifications),
+		];
+		let mut cold_transaction = Transaction::new();
+		for (column, pruned_key, keep) in windows.iter() {
+			let keep: NumberFor<Block> = match keep {
+				Some(keep) => (*keep).into(),
+				None => continue,
+			};
+			if f_num < keep {
+				continue;
+			}
+			let first = match read_pruned_to::<Block>(&*self.storage.db, pruned_key)? {
+				Some(pruned_to) => pruned_to + One::one(),
+				None => Zero::zero(),
+			};
+			// catching up with a long range, e.g. once the pruning is enabled, is spread across
+			// several finalizations.
+			let last = std::cmp::min(
+				f_num - keep,
+				first + (MAX_BLOCKS_PRUNED_AT_ONCE - 1).into(),
+			);
+			if first > last {
+				continue;
+			}
+
+			let mut hashes = Vec::new();
+			let mut number = first;
+			while number <= last {
+				// the finalized block may have been imported in the same transaction.
+				let hash = if number == f_num {
+					Some(f_hash)
+				} else {
+					sc_client_api::blockchain::HeaderBackend::hash(&self.blockchain, number)?
+				};
+				if let Some(hash) = hash {
+					let lookup_key = utils::number_and_hash_to_lookup_key(number, hash)?;
+					cold_transaction.remove(*column, &lookup_key);
+					transaction.remove(*column, &lookup_key);
+					hashes.push(hash);
+				}
+				number += One::one();
+			}
+			if *column == columns::BODY && self.index_extrinsics {
+				self.remove_extrinsic_locations(transaction, &hashes)?;
+			}
+			transaction.set_from_vec(columns::META, pruned_key, last.encode());
+		}
+		if let Some(cold) = &self.blockchain.cold {
+			cold.commit(cold_transaction);
+		}
+		Ok(())
+	}
+
+	// notes a finalized block to the migration of the ancient blocks to the cold database.
+	fn note_cold_finalized(&self, number: NumberFor<Block>) {
+		if let Some(cold_migration) = &self.cold_migration {
+			cold_migration.note_finalized(number);
+		}
+	}
+
+	// removes the locations in the blocks `hashes` from the index of the extrinsics.
+	fn remove_extrinsic_locations(
+		&self,
+		transaction: &mut Transaction<DbHash>,
+		hashes: &[Block::Hash],
+	) -> ClientResult<()> {
+		let mut locations = HashMap::<Block::Hash, Vec<(Block::Hash, u32)>>::new();
+		for hash in hashes {
+			let body = match sc_client_api::blockchain::Backend::body(&self.blockchain, BlockId::Hash(*hash))? {
+				Some(body) => body,
+				None => continue,
+			};
+			for extrinsic in body {
+				let extrinsic_hash = HashFor::<Block>::hash_of(&extrinsic);
+				if !locations.contains_key(&extrinsic_hash) {
+					let indexed = read_extrinsic_locations::<Block>(&*self.storage.db, &extrinsic_hash)?;
+					locations.insert(extrinsic_hash, indexed);
+				}
+				locations.get_mut(&extrinsic_hash)
+					.expect("the locations have been inserted above; qed")
+					.retain(|(block_hash, _)| block_hash != hash);
+			}
+		}
+		for (extrinsic_hash, extrinsic_locations) in locations {
+			if extrinsic_locations.is_empty() {
+				transaction.remove(columns::EXTRINSIC_INDEX, extrinsic_hash.as_ref());
+			} else {
+				transaction.set_from_vec(columns::EXTRINSIC_INDEX, extrinsic_hash.as_ref(), extrinsic_locations.encode());
+			}
+		}
+		Ok(())
+	}
+}
+
+// reads the locations of the extrinsic `hash` from the index of the extrinsics.
+fn read_extrinsic_locations<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	hash: &Block::Hash,
+) -> ClientResult<Vec<(Block::Hash, u32)>> {
+	match db.get(columns::EXTRINSIC_INDEX, hash.as_ref()) {
+		Some(locations) => Decode::decode(&mut &locations[..]).map_err(|err|
+			sp_blockchain::Error::Backend(format!("Error decoding extrinsic locations: {}", err))
+		),
+		None => Ok(Vec::new()),
+	}
 }
 
 fn apply_state_commit(transaction: &mut Transaction<DbHash>, commit: sc_state_db::CommitSet<Vec<u8>>) {
@@ -1344,6 +1937,7 @@ impl<Block> sc_client_api::backend::AuxStore for Backend<Block> where Block: Blo
 		I: IntoIterator<Item=&'a(&'c [u8], &'c [u8])>,
 		D: IntoIterator<Item=&'a &'b [u8]>,
 	>(&self, insert: I, delete: D) -> ClientResult<()> {
+		self.ensure_writable()?;
 		let mut transaction = Transaction::new();
 		for (k, v) in insert {
 			transaction.set(columns::AUX, k, v);
@@ -1384,6 +1978,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			finalized_blocks: Vec::new(),
 			set_head: None,
 			commit_state: false,
+			imported_state: false,
 		})
 	}
 
@@ -1400,6 +1995,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 	}
 
 	fn commit_operation(&self, operation: Self::BlockImportOperation) -> ClientResult<()> {
+		self.ensure_writable()?;
 		let usage = operation.old_state.usage_info();
 		self.state_usage.merge_sm(usage);
 
@@ -1418,6 +2014,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 	fn finalize_block(&self, block: BlockId<Block>, justification: Option<Justification>)
 		-> ClientResult<()>
 	{
+		self.ensure_writable()?;
 		let mut transaction = Transaction::new();
 		let hash = self.blockchain.expect_block_hash_from_id(&block)?;
 		let header = self.blockchain.expect_header(block)?;
@@ -1433,9 +2030,15 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			&mut changes_trie_cache_ops,
 			&mut displaced,
 		)?;
+		if is_finalized {
+			self.prune_blocks(&mut transaction, number, hash)?;
+		}
 		self.storage.db.commit(transaction);
 		self.blockchain.update_meta(hash, number, is_best, is_finalized);
 		self.changes_tries_storage.post_commit(changes_trie_cache_ops);
+		if is_finalized {
+			self.note_cold_finalized(number);
+		}
 		Ok(())
 	}
 
@@ -1447,11 +2050,14 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		Some(self.offchain_storage.clone())
 	}
 
+	fn offchain_index(&self) -> Option<Arc<dyn IndexedData>> {
+		Some(Arc::new(offchain::OffchainIndex::new(self.storage.db.clone())))
+	}
+
 	fn usage_info(&self) -> Option<UsageInfo> {
 		let (io_stats, state_stats) = self.io_stats.take_or_else(||
 			(
-				// TODO: implement DB stats and cache size retrieval
-				kvdb::IoStats::empty(),
+				self.database_usage.take(),
 				self.state_usage.take(),
 			)
 		);
@@ -1460,6 +2066,8 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			(*&self.shared_cache).lock().used_storage_cache_size(),
 		);
 		let state_db = self.storage.state_db.memory_info();
+		let pruning = self.storage.state_db.pruning_info();
+		let trie_cache = self.storage.trie_cache.info();
 
 		Some(UsageInfo {
 			memory: MemoryInfo {
@@ -1473,17 +2081,21 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 				bytes_written: io_stats.bytes_written,
 				writes: io_stats.writes,
 				reads: io_stats.reads,
-				average_transaction_size: io_stats.avg_transaction_size() as u64,
+				average_transaction_size: io_stats.bytes_written.checked_div(io_stats.transactions).unwrap_or(0),
 				state_reads: state_stats.reads.ops,
 				state_writes: state_stats.writes.ops,
 				state_writes_cache: state_stats.overlay_writes.ops,
 				state_reads_cache: state_stats.cache_reads.ops,
 				state_writes_nodes: state_stats.nodes_writes.ops,
 			},
+			pruning: Some(pruning),
+			trie_cache: Some(trie_cache),
+			database_columns: self.database_usage.columns(),
 		})
 	}
 
 	fn revert(&self, n: NumberFor<Block>, revert_finalized: bool) -> ClientResult<NumberFor<Block>> {
+		self.ensure_writable()?;
 		let mut best_number = self.blockchain.info().best_number;
 		let mut best_hash = self.blockchain.info().best_hash;
 
@@ -1530,6 +2142,9 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 						}
 						transaction.set_from_vec(columns::META, meta_keys::BEST_BLOCK, key);
 						transaction.remove(columns::KEY_LOOKUP, removed.hash().as_ref());
+						if self.delta_archive.is_some() {
+							delta_archive::remove(&mut transaction, removed.hash().as_ref());
+						}
 						children::remove_children(&mut transaction, columns::META, meta_keys::CHILDREN_PREFIX, best_hash);
 						self.storage.db.commit(transaction);
 						self.changes_tries_storage.post_commit(Some(changes_trie_cache_ops));
@@ -1560,6 +2175,91 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		Ok(reverted)
 	}
 
+	fn prune_abandoned_forks(&self, below: NumberFor<Block>) -> ClientResult<Vec<Block::Hash>> {
+		self.ensure_writable()?;
+		let finalized = self.blockchain.info().finalized_hash;
+		let mut leaves = self.blockchain.leaves.write();
+		let mut transaction = Transaction::new();
+		// the children of the blocks updated by the removals, not written yet.
+		let mut children = HashMap::<Block::Hash, Vec<Block::Hash>>::new();
+		let mut removed = Vec::new();
+
+		for leaf in leaves.hashes() {
+			let leaf_number = self.blockchain.header_metadata(leaf)?.number;
+			if leaf_number >= below {
+				continue;
+			}
+			let ancestor = sp_blockchain::lowest_common_ancestor(&self.blockchain, leaf, finalized)?;
+			if ancestor.hash == finalized {
+				continue;
+			}
+
+			// the fork ends at its first block that is canonical or has other children.
+			let mut hash = leaf;
+			loop {
+				let header = self.blockchain.header_metadata(hash)?;
+				if self.blockchain.hash(header.number)? == Some(hash) {
+					break;
+				}
+				let block_children = match children.get(&hash) {
+					Some(block_children) => block_children.clone(),
+					None => children::read_children(&*self.storage.db, columns::META, meta_keys::CHILDREN_PREFIX, hash)?,
+				};
+				if !block_children.is_empty() {
+					break;
+				}
+
+				let lookup_key = utils::number_and_hash_to_lookup_key(header.number, hash)?;
+				transaction.remove(columns::HEADER, &lookup_key);
+				transaction.remove(columns::BODY, &lookup_key);
+				transaction.remove(columns::JUSTIFICATION, &lookup_key);
+				transaction.remove(columns::KEY_LOOKUP, hash.as_ref());
+				if self.delta_archive.is_some() {
+					delta_archive::remove(&mut transaction, hash.as_ref());
+				}
+				children.insert(hash, Vec::new());
+
+				let parent_children = match children.remove(&header.parent) {
+					Some(parent_children) => parent_children,
+					None => children::read_children(
+						&*self.storage.db,
+						columns::META,
+						meta_keys::CHILDREN_PREFIX,
+						header.parent,
+					)?,
+				};
+				children.insert(header.parent, parent_children.into_iter().filter(|child| *child != hash).collect());
+				removed.push(hash);
+				hash = header.parent;
+			}
+			leaves.remove(leaf, leaf_number);
+		}
+		if self.index_extrinsics {
+			self.remove_extrinsic_locations(&mut transaction, &removed)?;
+		}
+
+		for (parent, parent_children) in children {
+			if parent_children.is_empty() {
+				children::remove_children(&mut transaction, columns::META, meta_keys::CHILDREN_PREFIX, parent);
+			} else {
+				children::write_children(
+					&mut transaction,
+					columns::META,
+					meta_keys::CHILDREN_PREFIX,
+					parent,
+					parent_children,
+				);
+			}
+		}
+		leaves.prepare_transaction(&mut transaction, columns::META, meta_keys::LEAF_PREFIX);
+		self.storage.db.commit(transaction);
+		for hash in &removed {
+			self.blockchain.remove_header_metadata(*hash);
+		}
+
+		Ok(removed)
+	}
+
 	fn blockchain(&self) -> &BlockchainDb<Block> {
 		&self.blockchain
 	}
@@ -1599,6 +2299,9 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		match self.blockchain.header_metadata(hash) {
 			Ok(ref hdr) => {
 				if !self.have_state_at(&hash, hdr.number) {
+					if self.delta_archive.is_some() {
+						return self.reconstructed_state_at(hash, hdr.state_root)
+					}
 					return Err(
 						sp_blockchain::Error::UnknownBlock(
 							format!("State already discarded for {:?}", block)
@@ -1731,6 +2434,35 @@ pub(crate) mod tests {
 		header_hash
 	}
 
+	/// Imports the best block `number`, child of `parent_hash`, whose state is the one of its
+	/// parent with `changes` applied. Returns its hash.
+	pub fn insert_block_with_changes(
+		backend: &Backend<Block>,
+		number: u64,
+		parent_hash: H256,
+		changes: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+	) -> H256 {
+		let mut op = backend.begin_operation().unwrap();
+		let parent = if number == 0 { BlockId::Hash(Default::default()) } else { BlockId::Number(number - 1) };
+		backend.begin_state_operation(&mut op, parent).unwrap();
+		let (root, overlay) = op.old_state.storage_root(
+			changes.iter().map(|(k, v)| (&k[..], v.as_ref().map(|v| &v[..])))
+		);
+		op.update_db_storage(overlay).unwrap();
+		op.update_storage(changes, Vec::new()).unwrap();
+		let header = Header {
+			number,
+			parent_hash,
+			state_root: root,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let hash = header.hash();
+		op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+		hash
+	}
+
 	#[test]
 	fn block_hash_inserted_correctly() {
 		let backing = {
@@ -1776,9 +2508,16 @@ pub(crate) mod tests {
 		let backend = Backend::<Block>::new(DatabaseSettings {
 			state_cache_size: 16777216,
 			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
 			pruning: PruningMode::keep_blocks(1),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
 			source: DatabaseSettingsSrc::Custom(backing),
-		}, 0).unwrap();
+		}).unwrap();
 		assert_eq!(backend.blockchain().info().best_number, 9);
 		for i in 0..10 {
 			assert!(backend.blockchain().hash(i).unwrap().is_some())
@@ -2297,4 +3036,530 @@ pub(crate) mod tests {
 			backend.commit_operation(op).unwrap_err();
 		}
 	}
+
+	#[test]
+	fn prunes_bodies_and_justifications_of_old_blocks() {
+		use sc_client_api::blockchain::Backend as BlockChainBackend;
+
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: BlockPruning { keep_bodies: Some(2), keep_justifications: Some(3) },
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let mut parent_hash = insert_header(&backend, 0, Default::default(), None, Default::default());
+		for number in 1..6 {
+			parent_hash = insert_header(&backend, number, parent_hash, None, Default::default());
+			backend.finalize_block(BlockId::Number(number), Some(vec![number as u8])).unwrap();
+		}
+
+		let blockchain = backend.blockchain();
+		for number in 0..6 {
+			let id = BlockId::Number(number);
+			assert!(blockchain.header(id).unwrap().is_some());
+			assert_eq!(blockchain.body(id).unwrap().is_some(), number >= 4, "body of #{}", number);
+			assert_eq!(blockchain.justification(id).unwrap().is_some(), number >= 3, "justification of #{}", number);
+		}
+	}
+
+	#[test]
+	fn prunes_the_blocks_finalized_at_once() {
+		use sc_client_api::blockchain::Backend as BlockChainBackend;
+
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: BlockPruning { keep_bodies: Some(1), keep_justifications: Some(0) },
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let mut parent_hash = insert_header(&backend, 0, Default::default(), None, Default::default());
+		for number in 1..6 {
+			parent_hash = insert_header(&backend, number, parent_hash, None, Default::default());
+		}
+		backend.finalize_block(BlockId::Number(5), Some(vec![5])).unwrap();
+
+		let blockchain = backend.blockchain();
+		for number in 0..6 {
+			let id = BlockId::Number(number);
+			assert_eq!(blockchain.body(id).unwrap().is_some(), number >= 4, "body of #{}", number);
+			assert!(blockchain.justification(id).unwrap().is_none(), "justification of #{}", number);
+		}
+		assert_eq!(read_pruned_to::<Block>(&*backend.storage.db, meta_keys::BODIES_PRUNED_TO).unwrap(), Some(4));
+	}
+
+	#[test]
+	fn extrinsics_are_located_by_their_hash() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: BlockPruning { keep_bodies: Some(1), keep_justifications: None },
+			index_extrinsics: true,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let insert_block = |number: u64, parent_hash: H256, body: Vec<u64>, state: NewBlockState| {
+			let body: Vec<ExtrinsicWrapper<u64>> = body.into_iter().map(Into::into).collect();
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: BlakeTwo256::trie_root(Vec::new()),
+				digest: Default::default(),
+				extrinsics_root: BlakeTwo256::ordered_trie_root(body.iter().map(Encode::encode).collect()),
+			};
+			let hash = header.hash();
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(parent_hash)).unwrap();
+			op.set_block_data(header, Some(body), None, state).unwrap();
+			backend.commit_operation(op).unwrap();
+			hash
+		};
+		let location = |extrinsic: u64| backend.blockchain()
+			.extrinsic_location(&BlakeTwo256::hash_of(&ExtrinsicWrapper::from(extrinsic)))
+			.unwrap();
+
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let block1 = insert_block(1, genesis, vec![1, 2], NewBlockState::Best);
+		insert_block(1, genesis, vec![3], NewBlockState::Normal);
+		assert_eq!(location(1), Some((block1, 0)));
+		assert_eq!(location(2), Some((block1, 1)));
+		// the extrinsics of the blocks out of the canonical chain are not located.
+		assert_eq!(location(3), None);
+		assert_eq!(location(4), None);
+
+		let block2 = insert_block(2, block1, vec![4], NewBlockState::Best);
+		backend.finalize_block(BlockId::Hash(block2), None).unwrap();
+		assert_eq!(location(4), Some((block2, 0)));
+		// the body of the block 1 is pruned, and its extrinsics with it.
+		assert_eq!(location(1), None);
+		assert!(backend.storage.db.get(columns::EXTRINSIC_INDEX, BlakeTwo256::hash_of(&ExtrinsicWrapper::from(2u64)).as_ref()).is_none());
+	}
+
+	#[test]
+	fn extrinsics_included_by_several_forks_are_located_in_each() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: BlockPruning { keep_bodies: None, keep_justifications: None },
+			index_extrinsics: true,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let insert_block = |number: u64, parent_hash: H256, body: Vec<u64>, state: NewBlockState| {
+			let body: Vec<ExtrinsicWrapper<u64>> = body.into_iter().map(Into::into).collect();
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: BlakeTwo256::trie_root(Vec::new()),
+				digest: Default::default(),
+				extrinsics_root: BlakeTwo256::ordered_trie_root(body.iter().map(Encode::encode).collect()),
+			};
+			let hash = header.hash();
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(parent_hash)).unwrap();
+			op.set_block_data(header, Some(body), None, state).unwrap();
+			backend.commit_operation(op).unwrap();
+			hash
+		};
+		let extrinsic = BlakeTwo256::hash_of(&ExtrinsicWrapper::from(1u64));
+		let locations = || read_extrinsic_locations::<Block>(&*backend.storage.db, &extrinsic).unwrap();
+
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let block1 = insert_block(1, genesis, vec![1], NewBlockState::Best);
+		// the fork imported last includes the extrinsic at another index.
+		let fork1 = insert_block(1, genesis, vec![2, 1], NewBlockState::Normal);
+		assert_eq!(locations(), vec![(block1, 0), (fork1, 1)]);
+		assert_eq!(backend.blockchain().extrinsic_location(&extrinsic).unwrap(), Some((block1, 0)));
+
+		let block2 = insert_block(2, block1, vec![3], NewBlockState::Best);
+		backend.finalize_block(BlockId::Hash(block2), None).unwrap();
+		assert_eq!(backend.prune_abandoned_forks(2).unwrap(), vec![fork1]);
+		assert_eq!(locations(), vec![(block1, 0)]);
+		assert_eq!(backend.blockchain().extrinsic_location(&extrinsic).unwrap(), Some((block1, 0)));
+	}
+
+	#[test]
+	fn ancient_blocks_are_read_from_the_cold_database() {
+		let backend = Backend::<Block>::new_test(10, 0);
+		let cold = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let insert_block = |number: u64, parent_hash: H256, body: Vec<u64>| {
+			let body: Vec<ExtrinsicWrapper<u64>> = body.into_iter().map(Into::into).collect();
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: BlakeTwo256::trie_root(Vec::new()),
+				digest: Default::default(),
+				extrinsics_root: BlakeTwo256::ordered_trie_root(body.iter().map(Encode::encode).collect()),
+			};
+			let hash = header.hash();
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(parent_hash)).unwrap();
+			op.set_block_data(header, Some(body), Some(vec![number as u8]), NewBlockState::Final).unwrap();
+			backend.commit_operation(op).unwrap();
+			hash
+		};
+
+		let genesis = insert_block(0, Default::default(), vec![]);
+		let block1 = insert_block(1, genesis, vec![1, 2]);
+		let block2 = insert_block(2, block1, vec![3]);
+		cold::migrate::<Block>(&*backend.storage.db, &*cold, 0, 2).unwrap();
+
+		let lookup_key = utils::number_and_hash_to_lookup_key(1, block1).unwrap();
+		assert!(backend.storage.db.get(columns::BODY, &lookup_key).is_none());
+		assert!(cold.get(columns::BODY, &lookup_key).is_some());
+		// without the cold database, the moved blocks are missing.
+		assert_eq!(backend.blockchain().body(BlockId::Hash(block1)).unwrap(), None);
+
+		let blockchain = BlockchainDb::<Block>::new(backend.storage.db.clone(), Some(cold.clone())).unwrap();
+		assert_eq!(blockchain.body(BlockId::Hash(block1)).unwrap(), Some(vec![1.into(), 2.into()]));
+		assert_eq!(blockchain.justification(BlockId::Number(1)).unwrap(), Some(vec![1]));
+		assert_eq!(blockchain.body(BlockId::Hash(block2)).unwrap(), Some(vec![3.into()]));
+		assert_eq!(cold::next_block::<Block>(&*cold).unwrap(), 2);
+	}
+
+	#[test]
+	fn states_are_canonicalized_with_the_delay_of_the_settings() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 2,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let mut parent_hash = Default::default();
+		for number in 0..6 {
+			parent_hash = insert_header(&backend, number, parent_hash, None, Default::default());
+		}
+
+		let pruning = backend.usage_info().unwrap().pruning.unwrap();
+		assert_eq!(pruning.last_canonicalized, Some(3));
+		assert_eq!(pruning.non_canonical, 2);
+	}
+
+	#[test]
+	fn pruned_states_are_reconstructed_from_the_delta_archive() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(1),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: Some(DeltaArchive { snapshot_interval: 4 }),
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		// each block `n` sets the key `[n]` and removes the key `[n - 2]`.
+		let mut parent_hash = Default::default();
+		for number in 0..10u64 {
+			let mut changes = vec![(vec![number as u8], Some(vec![number as u8; 2]))];
+			if number >= 2 {
+				changes.push((vec![number as u8 - 2], None));
+			}
+			parent_hash = insert_block_with_changes(&backend, number, parent_hash, changes);
+		}
+
+		for number in 0..10u64 {
+			let state = backend.state_at(BlockId::Number(number)).unwrap();
+			let key = |n: u64| state.storage(&[n as u8]).unwrap();
+			assert_eq!(key(number), Some(vec![number as u8; 2]));
+			if number >= 1 {
+				assert_eq!(key(number - 1), Some(vec![number as u8 - 1; 2]));
+			}
+			if number >= 2 {
+				assert_eq!(key(number - 2), None);
+			}
+		}
+		assert!(!backend.have_state_at(&backend.blockchain().hash(5).unwrap().unwrap(), 5));
+	}
+
+	#[test]
+	fn reverted_blocks_are_removed_from_the_delta_archive() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: Some(DeltaArchive { snapshot_interval: 4 }),
+			canonicalization_delay: 10,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let mut hashes = Vec::new();
+		let mut parent_hash = Default::default();
+		for number in 0..3u64 {
+			parent_hash = insert_block_with_changes(&backend, number, parent_hash, vec![(vec![number as u8], Some(vec![1]))]);
+			hashes.push(parent_hash);
+		}
+		assert_eq!(backend.revert(1, false).unwrap(), 1);
+
+		let parent = |hash: H256| Ok(if hash == hashes[1] { hashes[0] } else { Default::default() });
+		assert!(delta_archive::storage::<Block>(&*backend.storage.db, hashes[1], parent).is_ok());
+		assert!(delta_archive::storage::<Block>(&*backend.storage.db, hashes[2], parent).is_err());
+	}
+
+	#[test]
+	fn storage_changes_are_indexed_while_the_index_is_enabled() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		let open = |index_storage_changes| Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			pruning: PruningMode::ArchiveAll,
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(db.clone()),
+		}).unwrap();
+
+		// the block `n` sets the key `[n % 2]`.
+		let import = |backend: &Backend<Block>, number: u64, parent_hash| insert_block_with_changes(
+			backend,
+			number,
+			parent_hash,
+			vec![(vec![(number % 2) as u8], Some(vec![number as u8]))],
+		);
+
+		let backend = open(true);
+		let mut hashes = Vec::new();
+		for number in 0..5 {
+			hashes.push(import(&backend, number, hashes.last().cloned().unwrap_or_default()));
+		}
+		assert_eq!(
+			backend.blockchain().storage_changes(1, 4, &[0]).unwrap(),
+			Some(vec![(2, hashes[2]), (4, hashes[4])]),
+		);
+		drop(backend);
+
+		// the index is discarded once a block is imported while it is disabled.
+		let backend = open(false);
+		hashes.push(import(&backend, 5, hashes[4]));
+		drop(backend);
+		let backend = open(true);
+		hashes.push(import(&backend, 6, hashes[5]));
+		assert_eq!(backend.blockchain().storage_changes(1, 6, &[0]).unwrap(), None);
+		assert_eq!(backend.blockchain().storage_changes(6, 6, &[0]).unwrap(), Some(vec![(6, hashes[6])]));
+	}
+
+	#[test]
+	fn secondary_instance_catches_up_with_the_primary_one() {
+		let db_dir = tempfile::tempdir().unwrap();
+		let secondary_dir = tempfile::tempdir().unwrap();
+		let settings = |source| DatabaseSettings {
+			state_cache_size: 16777216,
+			state_cache_child_ratio: Some((50, 100)),
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source,
+		};
+		let primary = Backend::<Block>::new(settings(DatabaseSettingsSrc::RocksDb {
+			path: db_dir.path().to_owned(),
+			cache_size: 16,
+		})).unwrap();
+		let genesis = insert_header(&primary, 0, Default::default(), None, Default::default());
+
+		let secondary = Backend::<Block>::new(settings(DatabaseSettingsSrc::RocksDbSecondary {
+			path: db_dir.path().to_owned(),
+			secondary_path: secondary_dir.path().to_owned(),
+			cache_size: 16,
+		})).unwrap();
+		assert_eq!(secondary.blockchain().info().best_hash, genesis);
+
+		let block1 = insert_header(&primary, 1, genesis, None, Default::default());
+		assert_eq!(secondary.blockchain().info().best_number, 0);
+		secondary.try_catch_up_with_primary().unwrap();
+		assert_eq!(secondary.blockchain().info().best_hash, block1);
+		assert!(secondary.blockchain().header(BlockId::Hash(block1)).unwrap().is_some());
+		assert!(secondary.state_at(BlockId::Hash(block1)).is_ok());
+
+		// the secondary instance can't write.
+		assert!(secondary.finalize_block(BlockId::Hash(block1), None).is_err());
+		assert!(primary.try_catch_up_with_primary().is_err());
+	}
+
+	#[test]
+	fn memory_capped_backend_reads_spilled_blocks() {
+		let backend = Backend::<Block>::new_test_with_memory_cap(10, 0, 4096);
+		let mut hashes = vec![insert_header(&backend, 0, Default::default(), None, Default::default())];
+		for number in 1..50 {
+			let parent = *hashes.last().unwrap();
+			hashes.push(insert_header(&backend, number, parent, None, Default::default()));
+		}
+
+		assert_eq!(backend.blockchain().info().best_number, 49);
+		for (number, hash) in hashes.iter().enumerate() {
+			let header = backend.blockchain().header(BlockId::Number(number as u64)).unwrap().unwrap();
+			assert_eq!(header.hash(), *hash);
+		}
+	}
+
+	#[test]
+	fn prunes_abandoned_forks() {
+		let backend = Backend::<Block>::new_test(10, 0);
+		let insert_block = |number: u64, parent_hash: H256, fork: u64, state: NewBlockState| {
+			let header = Header {
+				number,
+				parent_hash,
+				state_root: BlakeTwo256::trie_root(Vec::new()),
+				digest: Default::default(),
+				extrinsics_root: H256::from_low_u64_be(fork),
+			};
+			let hash = header.hash();
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, BlockId::Hash(parent_hash)).unwrap();
+			op.set_block_data(header, Some(Vec::new()), None, state).unwrap();
+			backend.commit_operation(op).unwrap();
+			hash
+		};
+
+		//     / a1 - a2 - a3
+		// g --       \ d3
+		//     \ b1 - b2 - b3
+		//          \ c2
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		let a1 = insert_block(1, genesis, 0, NewBlockState::Best);
+		let a2 = insert_block(2, a1, 0, NewBlockState::Best);
+		let a3 = insert_block(3, a2, 0, NewBlockState::Best);
+		let d3 = insert_block(3, a2, 3, NewBlockState::Normal);
+		let b1 = insert_block(1, genesis, 1, NewBlockState::Normal);
+		let b2 = insert_block(2, b1, 1, NewBlockState::Normal);
+		let b3 = insert_block(3, b2, 1, NewBlockState::Normal);
+		let c2 = insert_block(2, b1, 2, NewBlockState::Normal);
+		backend.finalize_block(BlockId::Hash(a2), None).unwrap();
+
+		// `b1` is kept for `b2`.
+		assert_eq!(backend.prune_abandoned_forks(3).unwrap(), vec![c2]);
+		assert!(backend.blockchain().header(BlockId::Hash(b1)).unwrap().is_some());
+		assert_eq!(backend.blockchain().children(b1).unwrap(), vec![b2]);
+
+		let mut removed = backend.prune_abandoned_forks(4).unwrap();
+		removed.sort();
+		let mut expected = vec![b1, b2, b3];
+		expected.sort();
+		assert_eq!(removed, expected);
+		for hash in &[b1, b2, b3, c2] {
+			assert!(backend.blockchain().header(BlockId::Hash(*hash)).unwrap().is_none());
+		}
+		assert_eq!(backend.blockchain().children(genesis).unwrap(), vec![a1]);
+		assert_eq!(backend.blockchain().leaves().unwrap(), vec![a3, d3]);
+	}
+
+	#[test]
+	fn trie_nodes_of_imported_blocks_are_cached() {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 1 << 20,
+			pruning: PruningMode::keep_blocks(10),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source: DatabaseSettingsSrc::Custom(sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS))),
+		}).unwrap();
+
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, BlockId::Hash(Default::default())).unwrap();
+		let storage: Vec<(Vec<u8>, Vec<u8>)> = (0..16u8).map(|i| (vec![i; 4], vec![i; 40])).collect();
+		let header = Header {
+			number: 0,
+			parent_hash: Default::default(),
+			state_root: op.old_state.storage_root(storage.iter().map(|(k, v)| (&k[..], Some(&v[..])))).0,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		op.reset_storage(Storage {
+			top: storage.into_iter().collect(),
+			children_default: Default::default(),
+		}).unwrap();
+		op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let before = backend.storage.trie_cache.info();
+		let state = backend.state_at(BlockId::Number(0)).unwrap();
+		assert_eq!(state.storage(&[3; 4]).unwrap(), Some(vec![3; 40]));
+		let after = backend.storage.trie_cache.info();
+		assert!(after.hits > before.hits);
+		assert_eq!(after.misses, before.misses);
+		assert!(after.size.as_bytes() > 0);
+	}
+
+	#[test]
+	fn offchain_index_is_kept_apart_from_the_offchain_storage() {
+		use sp_core::offchain::OffchainStorage;
+
+		let backend = Backend::<Block>::new_test(10, 0);
+		let hash = insert_header(&backend, 0, Default::default(), None, Default::default());
+
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, BlockId::Hash(hash)).unwrap();
+		let header = Header {
+			number: 1,
+			parent_hash: hash,
+			state_root: BlakeTwo256::trie_root(Vec::new()),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let mut offchain_changes = OffchainOverlayedChanges::enabled();
+		offchain_changes.set(STORAGE_PREFIX, b"key", b"value");
+		op.update_offchain_storage(offchain_changes).unwrap();
+		op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let index = backend.offchain_index().unwrap();
+		assert_eq!(index.indexed_data(b"key"), Some(b"value".to_vec()));
+		assert_eq!(backend.offchain_storage().unwrap().get(STORAGE_PREFIX, b"key"), None);
+	}
 }
@@ -600,7 +600,11 @@ impl<Block> Storage<Block> for LightStorage<Block>
 				state_reads_cache: 0,
 				state_writes_cache: 0,
 				state_writes_nodes: 0,
-			}
+			},
+			// Light client has no state
+			pruning: None,
+			trie_cache: None,
+			database_columns: Vec::new(),
 		})
 	}
 
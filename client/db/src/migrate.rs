@@ -0,0 +1,166 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Conversion of the database of a node to another backend.
+
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use crate::DatabaseSettingsSrc;
+
+/// Number of entries written to the target database at once.
+#[cfg(all(any(feature = "kvdb-rocksdb", test), feature = "parity-db"))]
+const BATCH_SIZE: u64 = 10_000;
+
+/// Progress of a database migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationProgress {
+	/// The first `keys` entries of the column `column` were copied.
+	Copied {
+		/// The column being copied.
+		column: u32,
+		/// The number of copied entries of the column.
+		keys: u64,
+	},
+	/// The first `keys` entries of the column `column` were found in the target database.
+	Verified {
+		/// The column being verified.
+		column: u32,
+		/// The number of verified entries of the column.
+		keys: u64,
+	},
+}
+
+/// Copies the database `source` to the empty database `target`, column by column, and then
+/// verifies that the target database holds all the entries of the source database.
+///
+/// Only the migration of full RocksDB databases to ParityDb databases is supported, as ParityDb
+/// databases can't be iterated over.
+pub fn migrate_database<F>(
+	source: &DatabaseSettingsSrc,
+	target: &DatabaseSettingsSrc,
+	progress: F,
+) -> ClientResult<()> where
+	F: FnMut(MigrationProgress),
+{
+	match (source, target) {
+		#[cfg(all(any(feature = "kvdb-rocksdb", test), feature = "parity-db"))]
+		(DatabaseSettingsSrc::RocksDb { path: source, .. }, DatabaseSettingsSrc::ParityDb { path: target }) =>
+			rocksdb_to_parity_db(source, target, progress),
+		(DatabaseSettingsSrc::ParityDb { .. }, _) =>
+			Err(ClientError::Backend("ParityDb databases can't be migrated, as they can't be iterated over".into())),
+		(source, target) => Err(ClientError::Backend(format!(
+			"Migrating {} databases to {} databases isn't supported",
+			source,
+			target,
+		))),
+	}
+}
+
+#[cfg(all(any(feature = "kvdb-rocksdb", test), feature = "parity-db"))]
+fn rocksdb_to_parity_db<F>(source: &std::path::Path, target: &std::path::Path, mut progress: F) -> ClientResult<()> where
+	F: FnMut(MigrationProgress),
+{
+	use kvdb::KeyValueDB;
+	use sp_database::{Database, Transaction};
+	use crate::{utils::{DatabaseType, NUM_COLUMNS}, DbHash};
+
+	let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	let source = source.to_str()
+		.ok_or_else(|| ClientError::Backend("Invalid database path".into()))?;
+	let source = kvdb_rocksdb::Database::open(&db_config, source)
+		.map_err(|err| ClientError::Backend(format!("{}", err)))?;
+	let target: std::sync::Arc<dyn Database<DbHash>> = crate::parity_db::open(target, DatabaseType::Full)
+		.map_err(|err| ClientError::Backend(format!("{:?}", err)))?;
+
+	for column in 0..NUM_COLUMNS {
+		let mut transaction = Transaction::new();
+		let mut keys = 0;
+		for (key, value) in source.iter(column) {
+			transaction.set(column, &key, &value);
+			keys += 1;
+			if keys % BATCH_SIZE == 0 {
+				target.commit(std::mem::replace(&mut transaction, Transaction::new()));
+				progress(MigrationProgress::Copied { column, keys });
+			}
+		}
+		target.commit(transaction);
+		progress(MigrationProgress::Copied { column, keys });
+	}
+
+	for column in 0..NUM_COLUMNS {
+		let mut keys = 0;
+		for (key, value) in source.iter(column) {
+			if target.get(column, &key).as_deref() != Some(&*value) {
+				return Err(ClientError::Backend(format!(
+					"The entry {:?} of the column {} wasn't migrated",
+					key,
+					column,
+				)))
+			}
+			keys += 1;
+			if keys % BATCH_SIZE == 0 {
+				progress(MigrationProgress::Verified { column, keys });
+			}
+		}
+		progress(MigrationProgress::Verified { column, keys });
+	}
+	Ok(())
+}
+
+#[cfg(all(test, feature = "parity-db"))]
+mod tests {
+	use super::*;
+	use crate::{columns, utils::DatabaseType};
+	use kvdb::KeyValueDB;
+
+	#[test]
+	fn rocksdb_databases_are_migrated_to_parity_db() {
+		let source = tempfile::tempdir().unwrap();
+		let target = tempfile::tempdir().unwrap();
+		{
+			let db = kvdb_rocksdb::Database::open(
+				&kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS),
+				source.path().to_str().unwrap(),
+			).unwrap();
+			let mut transaction = db.transaction();
+			transaction.put(columns::META, b"meta", b"value");
+			transaction.put(columns::HEADER, b"header", b"value");
+			db.write(transaction).unwrap();
+		}
+
+		let mut copied = Vec::new();
+		migrate_database(
+			&DatabaseSettingsSrc::RocksDb { path: source.path().to_owned(), cache_size: 16 },
+			&DatabaseSettingsSrc::ParityDb { path: target.path().to_owned() },
+			|progress| if let MigrationProgress::Copied { column, keys } = progress {
+				copied.push((column, keys));
+			},
+		).unwrap();
+
+		assert!(copied.contains(&(columns::META, 1)));
+		assert!(copied.contains(&(columns::HEADER, 1)));
+		let db = crate::parity_db::open::<crate::DbHash>(target.path(), DatabaseType::Full).unwrap();
+		assert_eq!(db.get(columns::HEADER, b"header"), Some(b"value".to_vec()));
+	}
+
+	#[test]
+	fn parity_db_databases_are_not_migrated() {
+		let source = DatabaseSettingsSrc::ParityDb { path: "source".into() };
+		let target = DatabaseSettingsSrc::RocksDb { path: "target".into(), cache_size: 16 };
+		assert!(migrate_database(&source, &target, |_| {}).is_err());
+	}
+}
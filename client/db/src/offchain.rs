@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-//! RocksDB-based offchain workers local storage.
+//! RocksDB-based offchain workers local storage and offchain index.
 
 use std::{
 	collections::HashMap,
@@ -58,6 +58,26 @@ impl LocalStorage {
 	}
 }
 
+/// Values written by the runtime through the offchain indexing API, kept apart from the storage
+/// of the offchain workers.
+#[derive(Clone)]
+pub struct OffchainIndex {
+	db: Arc<dyn Database<DbHash>>,
+}
+
+impl OffchainIndex {
+	/// Create access to the offchain index of the given database.
+	pub fn new(db: Arc<dyn Database<DbHash>>) -> Self {
+		OffchainIndex { db }
+	}
+}
+
+impl sc_client_api::IndexedData for OffchainIndex {
+	fn indexed_data(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.db.get(columns::OFFCHAIN_INDEX, key)
+	}
+}
+
 impl sp_core::offchain::OffchainStorage for LocalStorage {
 	fn set(&mut self, prefix: &[u8], key: &[u8], value: &[u8]) {
 		let key: Vec<u8> = prefix.iter().chain(key).cloned().collect();
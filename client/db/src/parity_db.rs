@@ -15,10 +15,11 @@
 
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
-/// A `Database` adapter for parity-db.
+
+//! A `Database` adapter for parity-db.
 
 use sp_database::{Database, Change, Transaction, ColumnId};
-use crate::utils::NUM_COLUMNS;
+use crate::utils::{DatabaseType, NUM_COLUMNS};
 use crate::columns;
 
 struct DbAdapter(parity_db::Db);
@@ -32,13 +33,21 @@ fn handle_err<T>(result: parity_db::Result<T>) -> T {
 	}
 }
 
-/// Wrap RocksDb database into a trait object that implements `sp_database::Database`
-pub fn open<H: Clone>(path: &std::path::Path) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
+/// Wrap ParityDb database into a trait object that implements `sp_database::Database`.
+///
+/// The state column of full databases holds reference counted trie nodes keyed by their hash.
+/// Light databases have no state, and use the same column index for other data.
+pub fn open<H: Clone>(
+	path: &std::path::Path,
+	db_type: DatabaseType,
+) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
 	let mut config = parity_db::Options::with_columns(path, NUM_COLUMNS as u8);
-	let mut state_col = &mut config.columns[columns::STATE as usize];
-	state_col.ref_counted = true;
-	state_col.preimage = true;
-	state_col.uniform = true;
+	if db_type == DatabaseType::Full {
+		let mut state_col = &mut config.columns[columns::STATE as usize];
+		state_col.ref_counted = true;
+		state_col.preimage = true;
+		state_col.uniform = true;
+	}
 	let db = parity_db::Db::open(&config)?;
 	Ok(std::sync::Arc::new(DbAdapter(db)))
 }
@@ -62,3 +71,119 @@ impl<H: Clone> Database<H> for DbAdapter {
 		unimplemented!();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::{tests::Block, Backend, DatabaseSettings, DatabaseSettingsSrc, PruningMode};
+	use sc_client_api::backend::{AuxStore, Backend as _, BlockImportOperation as _, NewBlockState};
+	use sp_core::{offchain::OffchainStorage, storage::Storage};
+	use sp_runtime::{generic::BlockId, testing::Header, traits::Header as _};
+	use sp_state_machine::Backend as _;
+
+	const BLOCKS: u64 = 8;
+	const OFFCHAIN_PREFIX: &[u8] = b"test";
+
+	/// Imports a chain where each block `n` sets the key `[n]` and removes the key `[n - 2]`,
+	/// along with aux and offchain entries, into a backend opened from `source`.
+	fn import_chain(source: DatabaseSettingsSrc) -> Backend<Block> {
+		let backend = Backend::<Block>::new(DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			pruning: PruningMode::keep_blocks(2),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: 0,
+			source,
+		}).unwrap();
+
+		let mut parent_hash = Default::default();
+		for number in 0..BLOCKS {
+			let mut op = backend.begin_operation().unwrap();
+			let parent = if number == 0 { BlockId::Hash(Default::default()) } else { BlockId::Number(number - 1) };
+			backend.begin_state_operation(&mut op, parent).unwrap();
+			let mut header = Header {
+				number,
+				parent_hash,
+				state_root: Default::default(),
+				digest: Default::default(),
+				extrinsics_root: Default::default(),
+			};
+
+			if number == 0 {
+				header.state_root = op.old_state.storage_root(std::iter::once((&[0u8][..], Some(&[0u8, 0][..])))).0;
+				op.reset_storage(Storage {
+					top: std::iter::once((vec![0], vec![0, 0])).collect(),
+					children_default: Default::default(),
+				}).unwrap();
+			} else {
+				let mut changes = vec![(vec![number as u8], Some(vec![number as u8; 2]))];
+				if number >= 2 {
+					changes.push((vec![number as u8 - 2], None));
+				}
+				let (root, overlay) = op.old_state.storage_root(
+					changes.iter().map(|(k, v)| (&k[..], v.as_ref().map(|v| &v[..])))
+				);
+				op.update_db_storage(overlay).unwrap();
+				op.update_storage(changes, Vec::new()).unwrap();
+				header.state_root = root;
+			}
+
+			op.insert_aux(vec![(b"last".to_vec(), Some(vec![number as u8])), (vec![number as u8], Some(vec![]))])
+				.unwrap();
+			parent_hash = header.hash();
+			op.set_block_data(header, Some(vec![]), None, NewBlockState::Best).unwrap();
+			backend.commit_operation(op).unwrap();
+
+			let mut offchain = backend.offchain_storage().unwrap();
+			offchain.set(OFFCHAIN_PREFIX, &[number as u8], &[number as u8; 4]);
+			if number >= 1 {
+				offchain.remove(OFFCHAIN_PREFIX, &[number as u8 - 1]);
+			}
+		}
+		backend
+	}
+
+	/// Reads the key `[key]` of the state of the block `number`, if that state is still available.
+	fn storage(backend: &Backend<Block>, number: u64, key: u8) -> Option<Option<Vec<u8>>> {
+		backend.state_at(BlockId::Number(number)).ok()
+			.and_then(|state| state.storage(&[key]).ok())
+	}
+
+	#[test]
+	fn parity_db_backend_matches_rocksdb() {
+		let rocksdb_path = tempfile::tempdir().unwrap();
+		let paritydb_path = tempfile::tempdir().unwrap();
+		let rocksdb = import_chain(DatabaseSettingsSrc::RocksDb {
+			path: rocksdb_path.path().to_owned(),
+			cache_size: 16,
+		});
+		let paritydb = import_chain(DatabaseSettingsSrc::ParityDb { path: paritydb_path.path().to_owned() });
+
+		let best = BLOCKS as u8 - 1;
+		assert_eq!(storage(&paritydb, best as u64, best), Some(Some(vec![best; 2])));
+		assert_eq!(storage(&paritydb, best as u64, best - 2), Some(None));
+		for number in 0..BLOCKS {
+			for key in 0..BLOCKS as u8 {
+				assert_eq!(
+					storage(&rocksdb, number, key),
+					storage(&paritydb, number, key),
+					"state of key {} at block {}",
+					key,
+					number,
+				);
+			}
+		}
+
+		assert_eq!(paritydb.get_aux(b"last").unwrap(), Some(vec![best]));
+		let (rocksdb_offchain, paritydb_offchain) = (rocksdb.offchain_storage().unwrap(), paritydb.offchain_storage().unwrap());
+		assert_eq!(paritydb_offchain.get(OFFCHAIN_PREFIX, &[best]), Some(vec![best; 4]));
+		for key in 0..BLOCKS as u8 {
+			assert_eq!(rocksdb.get_aux(&[key]).unwrap(), paritydb.get_aux(&[key]).unwrap());
+			assert_eq!(rocksdb_offchain.get(OFFCHAIN_PREFIX, &[key]), paritydb_offchain.get(OFFCHAIN_PREFIX, &[key]));
+		}
+	}
+}
@@ -0,0 +1,149 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Removal of a part of the data of the database of a full node.
+
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use crate::{columns, DatabaseSettingsSrc};
+
+/// Data of the database of a full node that can be removed.
+///
+/// The state and the blocks can only be removed together: the state can't be rebuilt without
+/// re-importing the blocks, and the journal of the state pruning refers to the blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseData {
+	/// The state tries, along with the journal of the state pruning and the archived deltas.
+	State,
+	/// The blocks, along with the metadata of the chain, the changes tries, the caches, the
+	/// indexes built at import and the auxiliary data of the consensus engines.
+	Blocks,
+	/// The local storage of the offchain workers.
+	Offchain,
+}
+
+impl DatabaseData {
+	fn columns(&self) -> &'static [u32] {
+		match self {
+			DatabaseData::State => &[columns::STATE, columns::STATE_META, columns::STATE_HISTORY],
+			DatabaseData::Blocks => &[
+				columns::META,
+				columns::KEY_LOOKUP,
+				columns::HEADER,
+				columns::BODY,
+				columns::JUSTIFICATION,
+				columns::CHANGES_TRIE,
+				columns::AUX,
+				columns::CACHE,
+				columns::OFFCHAIN_INDEX,
+				columns::EXTRINSIC_INDEX,
+				columns::STORAGE_CHANGES_INDEX,
+			],
+			DatabaseData::Offchain => &[columns::OFFCHAIN],
+		}
+	}
+}
+
+/// Removes the given data from the database of a full node, keeping the rest of it.
+///
+/// Only RocksDB databases are supported. The state and the blocks must be removed together.
+pub fn purge_database(source: &DatabaseSettingsSrc, data: &[DatabaseData]) -> ClientResult<()> {
+	if data.contains(&DatabaseData::State) != data.contains(&DatabaseData::Blocks) {
+		return Err(ClientError::Backend(
+			"The state and the blocks can only be purged together".into()
+		))
+	}
+
+	match source {
+		#[cfg(any(feature = "kvdb-rocksdb", test))]
+		DatabaseSettingsSrc::RocksDb { path, .. } => {
+			use kvdb::KeyValueDB;
+
+			let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS);
+			let path = path.to_str()
+				.ok_or_else(|| ClientError::Backend("Invalid database path".into()))?;
+			let db = kvdb_rocksdb::Database::open(&db_config, path)
+				.map_err(|err| ClientError::Backend(format!("{}", err)))?;
+
+			let mut transaction = db.transaction();
+			for column in data.iter().flat_map(DatabaseData::columns) {
+				transaction.delete_prefix(*column, &[]);
+			}
+			db.write(transaction).map_err(|err| ClientError::Backend(format!("{}", err)))
+		},
+		_ => Err(ClientError::Backend("Only RocksDB databases can be partially purged".into())),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use kvdb::KeyValueDB;
+
+	#[test]
+	fn purges_only_the_given_data() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = DatabaseSettingsSrc::RocksDb { path: dir.path().to_owned(), cache_size: 16 };
+		{
+			let db = kvdb_rocksdb::Database::open(
+				&kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS),
+				dir.path().to_str().unwrap(),
+			).unwrap();
+			let mut transaction = db.transaction();
+			transaction.put(columns::STATE, b"state", b"value");
+			transaction.put(columns::HEADER, b"header", b"value");
+			transaction.put(columns::OFFCHAIN, b"offchain", b"value");
+			db.write(transaction).unwrap();
+		}
+
+		purge_database(&source, &[DatabaseData::State, DatabaseData::Blocks]).unwrap();
+
+		let db = kvdb_rocksdb::Database::open(
+			&kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS),
+			dir.path().to_str().unwrap(),
+		).unwrap();
+		assert_eq!(db.get(columns::STATE, b"state").unwrap(), None);
+		assert_eq!(db.get(columns::HEADER, b"header").unwrap(), None);
+		assert_eq!(db.get(columns::OFFCHAIN, b"offchain").unwrap(), Some(b"value".to_vec()));
+	}
+
+	#[test]
+	fn refuses_to_purge_the_state_or_the_blocks_on_their_own() {
+		let dir = tempfile::tempdir().unwrap();
+		let source = DatabaseSettingsSrc::RocksDb { path: dir.path().to_owned(), cache_size: 16 };
+		{
+			let db = kvdb_rocksdb::Database::open(
+				&kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS),
+				dir.path().to_str().unwrap(),
+			).unwrap();
+			let mut transaction = db.transaction();
+			transaction.put(columns::STATE, b"state", b"value");
+			transaction.put(columns::HEADER, b"header", b"value");
+			db.write(transaction).unwrap();
+		}
+
+		assert!(purge_database(&source, &[DatabaseData::State]).is_err());
+		assert!(purge_database(&source, &[DatabaseData::Blocks, DatabaseData::Offchain]).is_err());
+
+		let db = kvdb_rocksdb::Database::open(
+			&kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS),
+			dir.path().to_str().unwrap(),
+		).unwrap();
+		assert_eq!(db.get(columns::STATE, b"state").unwrap(), Some(b"value".to_vec()));
+		assert_eq!(db.get(columns::HEADER, b"header").unwrap(), Some(b"value".to_vec()));
+	}
+}
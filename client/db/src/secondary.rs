@@ -0,0 +1,80 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Read-only secondary instance of a RocksDB database.
+//!
+//! A secondary instance opens the database written by another process, the primary instance,
+//! without locking it. It only sees the changes of the primary instance written before it was
+//! opened, until it catches up with the primary instance.
+
+use std::path::Path;
+use kvdb::KeyValueDB;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_database::{ColumnId, Transaction};
+use crate::{Database, DbHash};
+
+/// Secondary instance of a RocksDB database, which can't be written to.
+pub(crate) struct SecondaryDatabase(kvdb_rocksdb::Database);
+
+impl SecondaryDatabase {
+	/// Opens the database of the primary instance at `path`, writing the logs of the secondary
+	/// instance to `secondary_path`.
+	pub(crate) fn open(
+		path: &Path,
+		secondary_path: &Path,
+		mut db_config: kvdb_rocksdb::DatabaseConfig,
+	) -> ClientResult<Self> {
+		let to_str = |path: &Path| path.to_str()
+			.map(ToOwned::to_owned)
+			.ok_or_else(|| ClientError::Backend("Invalid database path".into()));
+		db_config.secondary = Some(to_str(secondary_path)?);
+		let db = kvdb_rocksdb::Database::open(&db_config, &to_str(path)?)
+			.map_err(|err| ClientError::Backend(format!("{}", err)))?;
+		Ok(SecondaryDatabase(db))
+	}
+
+	/// Catches up with the changes written by the primary instance.
+	pub(crate) fn catch_up(&self) -> ClientResult<()> {
+		self.0.try_catch_up_with_primary()
+			.map_err(|err| ClientError::Backend(format!("Failed to catch up with the primary database: {}", err)))
+	}
+}
+
+impl Database<DbHash> for SecondaryDatabase {
+	fn commit(&self, transaction: Transaction<DbHash>) {
+		// the backend refuses the operations writing to a read-only database before they reach it.
+		if !transaction.0.is_empty() {
+			panic!(
+				"Critical database error: {} changes written to the read-only secondary database",
+				transaction.0.len(),
+			);
+		}
+	}
+
+	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+		match self.0.get(col, key) {
+			Ok(value) => value,
+			Err(err) => panic!("Critical database error: {:?}", err),
+		}
+	}
+
+	fn lookup(&self, _hash: &DbHash) -> Option<Vec<u8>> {
+		// the primary RocksDB instance doesn't store values by hash.
+		None
+	}
+}
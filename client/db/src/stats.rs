@@ -18,7 +18,12 @@
 
 //! Database usage statistics
 
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use parking_lot::Mutex;
+use sc_client_api::ColumnIoInfo;
+use sp_database::{Change, ChangeRef, ColumnId, Transaction};
+use crate::{Database, DbHash};
 
 /// Accumulated usage statistics for state queries.
 pub struct StateUsageStats {
@@ -138,3 +143,195 @@ impl StateUsageStats {
 		}
 	}
 }
+
+/// Reads and writes of the whole database.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct DatabaseIoTotals {
+	/// Number of committed transactions.
+	pub transactions: u64,
+	/// Number of values read.
+	pub reads: u64,
+	/// Bytes of the values read.
+	pub bytes_read: u64,
+	/// Number of values written or removed.
+	pub writes: u64,
+	/// Bytes of the keys and values written, and of the keys removed.
+	pub bytes_written: u64,
+}
+
+#[derive(Default)]
+struct ColumnUsageStats {
+	reads: AtomicU64,
+	bytes_read: AtomicU64,
+	writes: AtomicU64,
+	bytes_written: AtomicU64,
+}
+
+/// Accumulated usage statistics of the columns of the database.
+pub struct DatabaseUsageStats {
+	names: &'static [&'static str],
+	transactions: AtomicU64,
+	columns: Vec<ColumnUsageStats>,
+	/// Totals returned by the last call to `take`.
+	taken: Mutex<DatabaseIoTotals>,
+}
+
+impl DatabaseUsageStats {
+	/// New empty usage stats of the columns with the given names.
+	pub fn new(names: &'static [&'static str]) -> Self {
+		Self {
+			names,
+			transactions: 0.into(),
+			columns: names.iter().map(|_| Default::default()).collect(),
+			taken: Default::default(),
+		}
+	}
+
+	/// Tally one read of a value of some length, or of a missing value.
+	pub fn tally_read(&self, col: ColumnId, value_bytes: Option<usize>) {
+		if let Some(column) = self.columns.get(col as usize) {
+			column.reads.fetch_add(1, AtomicOrdering::Relaxed);
+			column.bytes_read.fetch_add(value_bytes.unwrap_or(0) as u64, AtomicOrdering::Relaxed);
+		}
+	}
+
+	/// Tally the writes and removals of a committed transaction.
+	pub fn tally_transaction(&self, transaction: &Transaction<DbHash>) {
+		self.transactions.fetch_add(1, AtomicOrdering::Relaxed);
+		for change in &transaction.0 {
+			let (col, bytes) = match change {
+				Change::Set(col, key, value) => (*col, key.len() + value.len()),
+				Change::Remove(col, key) => (*col, key.len()),
+				Change::Store(..) | Change::Release(..) => continue,
+			};
+			if let Some(column) = self.columns.get(col as usize) {
+				column.writes.fetch_add(1, AtomicOrdering::Relaxed);
+				column.bytes_written.fetch_add(bytes as u64, AtomicOrdering::Relaxed);
+			}
+		}
+	}
+
+	/// Returns the statistics of every column since the start.
+	pub fn columns(&self) -> Vec<ColumnIoInfo> {
+		self.names.iter().zip(&self.columns).map(|(&name, column)| ColumnIoInfo {
+			name,
+			reads: column.reads.load(AtomicOrdering::Relaxed),
+			bytes_read: column.bytes_read.load(AtomicOrdering::Relaxed),
+			writes: column.writes.load(AtomicOrdering::Relaxed),
+			bytes_written: column.bytes_written.load(AtomicOrdering::Relaxed),
+		}).collect()
+	}
+
+	/// Returns the reads and writes of the whole database since the previous call.
+	pub fn take(&self) -> DatabaseIoTotals {
+		let mut totals = DatabaseIoTotals {
+			transactions: self.transactions.load(AtomicOrdering::Relaxed),
+			..Default::default()
+		};
+		for column in self.columns() {
+			totals.reads += column.reads;
+			totals.bytes_read += column.bytes_read;
+			totals.writes += column.writes;
+			totals.bytes_written += column.bytes_written;
+		}
+		let taken = std::mem::replace(&mut *self.taken.lock(), totals);
+		DatabaseIoTotals {
+			transactions: totals.transactions - taken.transactions,
+			reads: totals.reads - taken.reads,
+			bytes_read: totals.bytes_read - taken.bytes_read,
+			writes: totals.writes - taken.writes,
+			bytes_written: totals.bytes_written - taken.bytes_written,
+		}
+	}
+}
+
+/// Database tallying the reads and writes of its columns.
+pub struct StatsDatabase {
+	db: Arc<dyn Database<DbHash>>,
+	stats: Arc<DatabaseUsageStats>,
+}
+
+impl StatsDatabase {
+	/// Wraps `db`, tallying its usage in `stats`.
+	pub fn new(db: Arc<dyn Database<DbHash>>, stats: Arc<DatabaseUsageStats>) -> Self {
+		StatsDatabase { db, stats }
+	}
+}
+
+impl Database<DbHash> for StatsDatabase {
+	fn commit(&self, transaction: Transaction<DbHash>) {
+		self.stats.tally_transaction(&transaction);
+		self.db.commit(transaction)
+	}
+
+	fn commit_ref<'a>(&self, transaction: &mut dyn Iterator<Item=ChangeRef<'a, DbHash>>) {
+		let mut tx = Transaction::new();
+		for change in transaction {
+			match change {
+				ChangeRef::Set(col, key, value) => tx.set(col, key, value),
+				ChangeRef::Remove(col, key) => tx.remove(col, key),
+				ChangeRef::Store(hash, preimage) => tx.store(hash, preimage),
+				ChangeRef::Release(hash) => tx.release(hash),
+			}
+		}
+		self.commit(tx);
+	}
+
+	fn get(&self, col: ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+		let value = self.db.get(col, key);
+		self.stats.tally_read(col, value.as_ref().map(|value| value.len()));
+		value
+	}
+
+	fn with_get(&self, col: ColumnId, key: &[u8], f: &mut dyn FnMut(&[u8])) {
+		let mut value_bytes = None;
+		self.db.with_get(col, key, &mut |value| {
+			value_bytes = Some(value.len());
+			f(value)
+		});
+		self.stats.tally_read(col, value_bytes);
+	}
+
+	fn lookup(&self, hash: &DbHash) -> Option<Vec<u8>> {
+		self.db.lookup(hash)
+	}
+
+	fn with_lookup(&self, hash: &DbHash, f: &mut dyn FnMut(&[u8])) {
+		self.db.with_lookup(hash, f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_and_writes_are_tallied_by_column() {
+		let stats = Arc::new(DatabaseUsageStats::new(&["meta", "state"]));
+		let db = StatsDatabase::new(
+			sp_database::as_database(kvdb_memorydb::create(2)),
+			stats.clone(),
+		);
+
+		let mut transaction = Transaction::new();
+		transaction.set(1, b"key", b"value");
+		transaction.set(0, b"meta", b"data");
+		transaction.remove(1, b"gone");
+		db.commit(transaction);
+		assert_eq!(db.get(1, b"key"), Some(b"value".to_vec()));
+		assert_eq!(db.get(1, b"missing"), None);
+
+		let columns = stats.columns();
+		assert_eq!(columns[0].name, "meta");
+		assert_eq!((columns[0].reads, columns[0].writes, columns[0].bytes_written), (0, 1, 8));
+		assert_eq!(
+			(columns[1].reads, columns[1].bytes_read, columns[1].writes, columns[1].bytes_written),
+			(2, 5, 2, 12),
+		);
+
+		let expected = DatabaseIoTotals { transactions: 1, reads: 2, bytes_read: 5, writes: 3, bytes_written: 20 };
+		assert_eq!(stats.take(), expected);
+		db.get(0, b"meta");
+		assert_eq!(stats.take(), DatabaseIoTotals { reads: 1, bytes_read: 4, ..Default::default() });
+	}
+}
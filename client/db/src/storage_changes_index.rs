@@ -0,0 +1,131 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Index of the blocks changing the entries of the top storage.
+//!
+//! For every key of the top storage, the numbers and hashes of the imported blocks that changed
+//! its value are kept in chunks of `CHUNK_SIZE` consecutive block numbers, so that the blocks of
+//! a range changing a key are found without reading the state of every block of the range.
+//!
+//! The blocks of every fork are indexed, and the entries of the blocks removed from the
+//! database are left in the index.
+
+use codec::{Decode, Encode};
+use sp_blockchain::Result as ClientResult;
+use sp_database::Transaction;
+use sp_runtime::traits::{Block as BlockT, NumberFor, SaturatedConversion};
+use sp_state_machine::StorageCollection;
+use crate::{columns, Database, DbHash};
+
+/// Number of consecutive block numbers whose changes of a key are kept in the same entry.
+const CHUNK_SIZE: u64 = 256;
+
+fn key(storage_key: &[u8], chunk: u64) -> Vec<u8> {
+	let mut key = Vec::with_capacity(storage_key.len() + 8);
+	key.extend_from_slice(storage_key);
+	key.extend_from_slice(&chunk.to_be_bytes());
+	key
+}
+
+fn read_chunk<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	key: &[u8],
+) -> ClientResult<Vec<(NumberFor<Block>, Block::Hash)>> {
+	match db.get(columns::STORAGE_CHANGES_INDEX, key) {
+		Some(chunk) => Decode::decode(&mut &chunk[..]).map_err(|err|
+			sp_blockchain::Error::Backend(format!("Error decoding the storage changes index: {}", err))
+		),
+		None => Ok(Vec::new()),
+	}
+}
+
+/// Records that the block `hash` changed the keys of `changes`.
+pub(crate) fn write_changes<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	transaction: &mut Transaction<DbHash>,
+	number: NumberFor<Block>,
+	hash: Block::Hash,
+	changes: &StorageCollection,
+) -> ClientResult<()> {
+	let chunk = number.saturated_into::<u64>() / CHUNK_SIZE;
+	for (storage_key, _) in changes {
+		let key = key(storage_key, chunk);
+		let mut blocks = read_chunk::<Block>(db, &key)?;
+		if !blocks.contains(&(number, hash)) {
+			blocks.push((number, hash));
+			transaction.set_from_vec(columns::STORAGE_CHANGES_INDEX, &key, blocks.encode());
+		}
+	}
+	Ok(())
+}
+
+/// Returns the indexed blocks numbered from `first` to `last` that changed the key
+/// `storage_key`, ordered by number.
+pub(crate) fn changes<Block: BlockT>(
+	db: &dyn Database<DbHash>,
+	first: NumberFor<Block>,
+	last: NumberFor<Block>,
+	storage_key: &[u8],
+) -> ClientResult<Vec<(NumberFor<Block>, Block::Hash)>> {
+	let mut changes = Vec::new();
+	let first_chunk = first.saturated_into::<u64>() / CHUNK_SIZE;
+	let last_chunk = last.saturated_into::<u64>() / CHUNK_SIZE;
+	for chunk in first_chunk..=last_chunk {
+		changes.extend(
+			read_chunk::<Block>(db, &key(storage_key, chunk))?
+				.into_iter()
+				.filter(|(number, _)| *number >= first && *number <= last)
+		);
+	}
+	changes.sort_by_key(|(number, _)| *number);
+	Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
+	use sp_core::H256;
+
+	type Block = RawBlock<ExtrinsicWrapper<u64>>;
+
+	#[test]
+	fn changes_of_a_range_are_read_from_the_chunks() {
+		let db = sp_database::as_database(kvdb_memorydb::create(crate::utils::NUM_COLUMNS));
+		for &(number, changed) in &[(1, true), (255, false), (256, true), (300, true), (600, true)] {
+			let mut transaction = Transaction::new();
+			let key = if changed { b"key".to_vec() } else { b"other".to_vec() };
+			write_changes::<Block>(
+				&*db,
+				&mut transaction,
+				number,
+				H256::from_low_u64_be(number),
+				&vec![(key, None)],
+			).unwrap();
+			db.commit(transaction);
+		}
+
+		let blocks = |first, last| changes::<Block>(&*db, first, last, b"key").unwrap()
+			.into_iter()
+			.map(|(number, _)| number)
+			.collect::<Vec<_>>();
+		assert_eq!(blocks(0, 1000), vec![1, 256, 300, 600]);
+		assert_eq!(blocks(2, 300), vec![256, 300]);
+		assert_eq!(blocks(301, 599), Vec::<u64>::new());
+	}
+}
@@ -0,0 +1,139 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Cache of the trie nodes of the state, shared by all the states of the database.
+//!
+//! The nodes are identified by their hash, so a cached node never becomes invalid. A node
+//! removed from the database by the pruning stays in the cache until it is evicted, which is
+//! harmless as the states referencing it can't be read anymore.
+
+use std::hash::Hash as StdHash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use linked_hash_map::LinkedHashMap;
+use parking_lot::Mutex;
+use sc_client_api::{MemorySize, TrieCacheInfo};
+
+/// Estimation of the memory used by an entry of the cache, in addition to its node and hash.
+const ENTRY_OVERHEAD: usize = 32;
+
+/// Least recently used trie nodes, bounded by the memory they use.
+struct Nodes<H> {
+	lru: LinkedHashMap<H, Vec<u8>>,
+	used_size: usize,
+}
+
+/// Size-bounded cache of trie nodes, with statistics of its hits and misses.
+pub struct TrieNodeCache<H> {
+	nodes: Mutex<Nodes<H>>,
+	max_size: usize,
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+fn entry_size<H: AsRef<[u8]>>(hash: &H, node: &[u8]) -> usize {
+	hash.as_ref().len() + node.len() + ENTRY_OVERHEAD
+}
+
+impl<H: StdHash + Eq + AsRef<[u8]>> TrieNodeCache<H> {
+	/// Creates a cache using at most `max_size` bytes, disabled if `max_size` is 0.
+	pub fn new(max_size: usize) -> Self {
+		TrieNodeCache {
+			nodes: Mutex::new(Nodes { lru: LinkedHashMap::new(), used_size: 0 }),
+			max_size,
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	/// Returns the node with the given hash, if it is cached.
+	pub fn get(&self, hash: &H) -> Option<Vec<u8>> {
+		if self.max_size == 0 {
+			return None
+		}
+		let node = self.nodes.lock().lru.get_refresh(hash).cloned();
+		match node {
+			Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+			None => self.misses.fetch_add(1, Ordering::Relaxed),
+		};
+		node
+	}
+
+	/// Caches the node with the given hash, evicting the least recently used nodes if the cache
+	/// is full.
+	pub fn insert(&self, hash: H, node: Vec<u8>) {
+		let size = entry_size(&hash, &node);
+		if size > self.max_size {
+			return
+		}
+		let mut nodes = self.nodes.lock();
+		let nodes = &mut *nodes;
+		if nodes.lru.insert(hash, node).is_some() {
+			// the replaced node is the same, as nodes are identified by their hash.
+			return
+		}
+		nodes.used_size += size;
+		while nodes.used_size > self.max_size {
+			match nodes.lru.pop_front() {
+				Some((hash, node)) => nodes.used_size -= entry_size(&hash, &node),
+				None => break,
+			}
+		}
+	}
+
+	/// Returns the memory used by the cache and the number of hits and misses since its creation.
+	pub fn info(&self) -> TrieCacheInfo {
+		TrieCacheInfo {
+			size: MemorySize::from_bytes(self.nodes.lock().used_size),
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::H256;
+
+	#[test]
+	fn evicts_least_recently_used_nodes() {
+		let node_size = entry_size(&H256::default(), &[0; 68]);
+		let cache = TrieNodeCache::new(node_size * 2);
+		let (first, second, third) = (H256::repeat_byte(1), H256::repeat_byte(2), H256::repeat_byte(3));
+
+		cache.insert(first, vec![1; 68]);
+		cache.insert(second, vec![2; 68]);
+		assert_eq!(cache.get(&first), Some(vec![1; 68]));
+		cache.insert(third, vec![3; 68]);
+
+		assert_eq!(cache.get(&second), None);
+		assert_eq!(cache.get(&first), Some(vec![1; 68]));
+		assert_eq!(cache.get(&third), Some(vec![3; 68]));
+		let info = cache.info();
+		assert_eq!(info.size.as_bytes(), node_size * 2);
+		assert_eq!((info.hits, info.misses), (3, 1));
+	}
+
+	#[test]
+	fn disabled_cache_stores_nothing() {
+		let cache = TrieNodeCache::new(0);
+		cache.insert(H256::default(), vec![1]);
+		assert_eq!(cache.get(&H256::default()), None);
+		assert_eq!(cache.info().misses, 0);
+	}
+}
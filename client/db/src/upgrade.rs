@@ -20,14 +20,27 @@ use std::fs;
 use std::io::{Read, Write, ErrorKind};
 use std::path::{Path, PathBuf};
 
+use kvdb::KeyValueDB;
+use sp_core::offchain::STORAGE_PREFIX;
 use sp_runtime::traits::Block as BlockT;
-use crate::utils::DatabaseType;
+use crate::{columns, utils::DatabaseType};
 
 /// Version file name.
 const VERSION_FILE_NAME: &'static str = "db_version";
 
 /// Current db version.
-const CURRENT_VERSION: u32 = 1;
+const CURRENT_VERSION: u32 = 5;
+
+/// Number of columns in the v1 database.
+///
+/// Every later version adds one column, left empty by the migration unless stated otherwise:
+/// - v2: the offchain index, moved to its own column, see `copy_offchain_index`.
+/// - v3: the index of the extrinsics, the blocks imported before the migration are not indexed.
+/// - v4: the delta archive of the states, the states of the blocks imported before the migration
+/// are not archived.
+/// - v5: the index of the storage changes, the blocks imported before the migration are not
+/// indexed.
+const V1_NUM_COLUMNS: u32 = 11;
 
 /// Upgrade database to current version.
 pub fn upgrade_db<Block: BlockT>(db_path: &Path, _db_type: DatabaseType) -> sp_blockchain::Result<()> {
@@ -36,7 +49,15 @@ pub fn upgrade_db<Block: BlockT>(db_path: &Path, _db_type: DatabaseType) -> sp_b
 		let db_version = current_version(db_path)?;
 		match db_version {
 			0 => Err(sp_blockchain::Error::Backend(format!("Unsupported database version: {}", db_version)))?,
-			1 => (),
+			version if version < CURRENT_VERSION => {
+				for version in version..CURRENT_VERSION {
+					add_column(db_path, num_columns(version))?;
+					if version == 1 {
+						copy_offchain_index(db_path)?;
+					}
+				}
+			},
+			CURRENT_VERSION => (),
 			_ => Err(sp_blockchain::Error::Backend(format!("Future database version: {}", db_version)))?,
 		}
 	}
@@ -44,6 +65,36 @@ pub fn upgrade_db<Block: BlockT>(db_path: &Path, _db_type: DatabaseType) -> sp_b
 	update_version(db_path)
 }
 
+/// Returns the number of columns in the database of the given version.
+fn num_columns(version: u32) -> u32 {
+	V1_NUM_COLUMNS + version - 1
+}
+
+/// Adds a column to the database at `db_path`, which has `from_columns` columns.
+fn add_column(db_path: &Path, from_columns: u32) -> sp_blockchain::Result<()> {
+	let db_path = db_path.to_str()
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
+	let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(from_columns);
+	let db = kvdb_rocksdb::Database::open(&db_config, db_path).map_err(db_err)?;
+	db.add_column().map_err(db_err)
+}
+
+/// Copies the values indexed by the runtime before the offchain index had its own column, which
+/// are under the prefix of the persistent offchain storage, to the offchain index.
+///
+/// The values written by the offchain workers under the same prefix can't be told apart from
+/// them, so they are copied as well. All of them are left in the offchain storage.
+fn copy_offchain_index(db_path: &Path) -> sp_blockchain::Result<()> {
+	let db_path = db_path.to_str()
+		.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
+	let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(num_columns(2));
+	let db = kvdb_rocksdb::Database::open(&db_config, db_path).map_err(db_err)?;
+	let mut transaction = db.transaction();
+	for (key, value) in db.iter_with_prefix(columns::OFFCHAIN, STORAGE_PREFIX) {
+		transaction.put(columns::OFFCHAIN_INDEX, &key[STORAGE_PREFIX.len()..], &value);
+	}
+	db.write(transaction).map_err(db_err)
+}
 
 /// Reads current database version from the file at given path.
 /// If the file does not exist returns 0.
@@ -101,7 +152,14 @@ mod tests {
 		crate::utils::open_database::<Block>(&DatabaseSettings {
 			state_cache_size: 0,
 			state_cache_child_ratio: None,
+			trie_cache_size: 0,
 			pruning: PruningMode::ArchiveAll,
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: crate::DEFAULT_CANONICALIZATION_DELAY,
 			source: DatabaseSettingsSrc::RocksDb { path: db_path.to_owned(), cache_size: 128 },
 		}, DatabaseType::Full).map(|_| ())
 	}
@@ -113,6 +171,49 @@ mod tests {
 		assert!(open_database(db_dir.path()).is_err());
 	}
 
+	#[test]
+	fn upgrade_from_every_version_works() {
+		for version in 1..CURRENT_VERSION {
+			let db_dir = tempfile::TempDir::new().unwrap();
+			let db_path = db_dir.path().to_str().unwrap();
+			let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(num_columns(version));
+			drop(kvdb_rocksdb::Database::open(&db_config, db_path).unwrap());
+			create_db(db_dir.path(), Some(version));
+
+			open_database(db_dir.path()).unwrap();
+			assert_eq!(current_version(db_dir.path()).unwrap(), CURRENT_VERSION);
+			let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS);
+			assert_eq!(
+				kvdb_rocksdb::Database::open(&db_config, db_path).unwrap().num_columns(),
+				crate::utils::NUM_COLUMNS,
+				"upgrade from version {}",
+				version,
+			);
+		}
+	}
+
+	#[test]
+	fn upgrade_from_1_copies_the_offchain_index() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let db_path = db_dir.path().to_str().unwrap();
+		let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(num_columns(1));
+		let db = kvdb_rocksdb::Database::open(&db_config, db_path).unwrap();
+		let mut transaction = db.transaction();
+		let key = STORAGE_PREFIX.iter().chain(b"key").cloned().collect::<Vec<_>>();
+		transaction.put(columns::OFFCHAIN, &key, b"value");
+		transaction.put(columns::OFFCHAIN, b"local", b"value");
+		db.write(transaction).unwrap();
+		drop(db);
+		create_db(db_dir.path(), Some(1));
+
+		open_database(db_dir.path()).unwrap();
+		let db_config = kvdb_rocksdb::DatabaseConfig::with_columns(crate::utils::NUM_COLUMNS);
+		let db = kvdb_rocksdb::Database::open(&db_config, db_path).unwrap();
+		assert_eq!(db.get(columns::OFFCHAIN_INDEX, b"key").unwrap(), Some(b"value".to_vec()));
+		assert_eq!(db.get(columns::OFFCHAIN_INDEX, b"local").unwrap(), None);
+		assert_eq!(db.get(columns::OFFCHAIN, &key).unwrap(), Some(b"value".to_vec()));
+	}
+
 	#[test]
 	fn open_empty_database_works() {
 		let db_dir = tempfile::TempDir::new().unwrap();
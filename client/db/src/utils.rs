@@ -37,7 +37,7 @@ use crate::{DatabaseSettings, DatabaseSettingsSrc, Database, DbHash};
 /// Number of columns in the db. Must be the same for both full && light dbs.
 /// Otherwise RocksDb will fail to open database && check its type.
 #[cfg(any(feature = "kvdb-rocksdb", feature = "test-helpers", test))]
-pub const NUM_COLUMNS: u32 = 11;
+pub const NUM_COLUMNS: u32 = 15;
 /// Meta column. The set of keys in the column is shared by full && light storages.
 pub const COLUMN_META: u32 = 0;
 
@@ -59,6 +59,12 @@ pub mod meta_keys {
 	pub const LEAF_PREFIX: &[u8; 4] = b"leaf";
 	/// Children prefix list key.
 	pub const CHILDREN_PREFIX: &[u8; 8] = b"children";
+	/// Number of the first block whose storage changes are indexed.
+	pub const STORAGE_CHANGES_INDEX_START: &[u8; 5] = b"scidx";
+	/// Number of the last block whose body is pruned.
+	pub const BODIES_PRUNED_TO: &[u8; 5] = b"pbody";
+	/// Number of the last block whose justification is pruned.
+	pub const JUSTIFICATIONS_PRUNED_TO: &[u8; 5] = b"pjust";
 }
 
 /// Database metadata.
@@ -225,32 +231,9 @@ pub fn open_database<Block: BlockT>(
 			crate::upgrade::upgrade_db::<Block>(&path, db_type)?;
 
 			// and now open database assuming that it has the latest version
-			let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
-			let state_col_budget = (*cache_size as f64 * 0.9) as usize;
-			let other_col_budget = (cache_size - state_col_budget) / (NUM_COLUMNS as usize - 1);
-			let mut memory_budget = std::collections::HashMap::new();
+			let db_config = rocksdb_config(path, *cache_size);
 			let path = path.to_str()
 				.ok_or_else(|| sp_blockchain::Error::Backend("Invalid database path".into()))?;
-
-			for i in 0..NUM_COLUMNS {
-				if i == crate::columns::STATE {
-					memory_budget.insert(i, state_col_budget);
-				} else {
-					memory_budget.insert(i, other_col_budget);
-				}
-			}
-
-			db_config.memory_budget = memory_budget;
-
-			log::trace!(
-				target: "db",
-				"Open RocksDB database at {}, state column budget: {} MiB, others({}) column cache: {} MiB",
-				path,
-				state_col_budget,
-				NUM_COLUMNS,
-				other_col_budget,
-			);
-
 			let db = kvdb_rocksdb::Database::open(&db_config, &path)
 				.map_err(|err| sp_blockchain::Error::Backend(format!("{}", err)))?;
 			sp_database::as_database(db)
@@ -259,6 +242,16 @@ pub fn open_database<Block: BlockT>(
 		DatabaseSettingsSrc::RocksDb { .. } => {
 			return db_open_error("kvdb-rocksdb");
 		},
+		#[cfg(any(feature = "kvdb-rocksdb", test))]
+		DatabaseSettingsSrc::RocksDbSecondary { .. } => {
+			return Err(sp_blockchain::Error::Backend(
+				"Secondary instances of the database are only supported by full nodes".into(),
+			));
+		},
+		#[cfg(not(any(feature = "kvdb-rocksdb", test)))]
+		DatabaseSettingsSrc::RocksDbSecondary { .. } => {
+			return db_open_error("kvdb-rocksdb");
+		},
 		#[cfg(feature = "subdb")]
 		DatabaseSettingsSrc::SubDb { path } => {
 			crate::subdb::open(&path, NUM_COLUMNS)
@@ -270,7 +263,7 @@ pub fn open_database<Block: BlockT>(
 		},
 		#[cfg(feature = "parity-db")]
 		DatabaseSettingsSrc::ParityDb { path } => {
-			crate::parity_db::open(&path)
+			crate::parity_db::open(&path, db_type)
 				.map_err(|e| sp_blockchain::Error::Backend(format!("{:?}", e)))?
 		},
 		#[cfg(not(feature = "parity-db"))]
@@ -285,6 +278,59 @@ pub fn open_database<Block: BlockT>(
 	Ok(db)
 }
 
+/// Returns the configuration of a RocksDB database using at most `cache_size` MiB of cache.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+fn rocksdb_config(path: &std::path::Path, cache_size: usize) -> kvdb_rocksdb::DatabaseConfig {
+	let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	let state_col_budget = (cache_size as f64 * 0.9) as usize;
+	let other_col_budget = (cache_size - state_col_budget) / (NUM_COLUMNS as usize - 1);
+	let mut memory_budget = std::collections::HashMap::new();
+
+	for i in 0..NUM_COLUMNS {
+		if i == crate::columns::STATE {
+			memory_budget.insert(i, state_col_budget);
+		} else {
+			memory_budget.insert(i, other_col_budget);
+		}
+	}
+
+	db_config.memory_budget = memory_budget;
+
+	log::trace!(
+		target: "db",
+		"Open RocksDB database at {}, state column budget: {} MiB, others({}) column cache: {} MiB",
+		path.display(),
+		state_col_budget,
+		NUM_COLUMNS,
+		other_col_budget,
+	);
+
+	db_config
+}
+
+/// Opens the read-only secondary instance of the RocksDB database at `path`, writing its logs to
+/// `secondary_path`.
+///
+/// The database isn't upgraded, this is left to the primary instance.
+#[cfg(any(feature = "kvdb-rocksdb", test))]
+pub fn open_secondary_database(
+	path: &std::path::Path,
+	secondary_path: &std::path::Path,
+	cache_size: usize,
+	db_type: DatabaseType,
+) -> sp_blockchain::Result<Arc<crate::secondary::SecondaryDatabase>> {
+	let db_config = rocksdb_config(path, cache_size);
+	let db = Arc::new(crate::secondary::SecondaryDatabase::open(path, secondary_path, db_config)?);
+	// the type of a database is written by its primary instance when creating it.
+	if db.get(COLUMN_META, meta_keys::TYPE).is_none() {
+		return Err(sp_blockchain::Error::Backend(
+			format!("The database at {} hasn't been created by a primary instance", path.display())
+		));
+	}
+	check_database_type(&*db, db_type)?;
+	Ok(db)
+}
+
 /// Check database type.
 pub fn check_database_type(db: &dyn Database<DbHash>, db_type: DatabaseType) -> sp_blockchain::Result<()> {
 	match db.get(COLUMN_META, meta_keys::TYPE) {
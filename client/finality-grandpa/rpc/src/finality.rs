@@ -0,0 +1,51 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use sc_client_api::backend::Backend;
+use sc_finality_grandpa::FinalityProofProvider;
+use sp_core::Bytes;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// SCALE-encoded finality proof, as returned by `grandpa_proveFinality`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EncodedFinalityProof(pub Bytes);
+
+/// Provides the finality proofs served over RPC.
+pub trait RpcFinalityProofProvider<Block: BlockT>: Send + Sync {
+	/// Prove the finality of the given block, or return `None` if it isn't finalized yet.
+	fn rpc_prove_finality(
+		&self,
+		block: Block::Hash,
+	) -> Result<Option<EncodedFinalityProof>, sp_blockchain::Error>;
+}
+
+impl<B, Block> RpcFinalityProofProvider<Block> for FinalityProofProvider<B, Block>
+where
+	Block: BlockT,
+	NumberFor<Block>: finality_grandpa::BlockNumberOps,
+	B: Backend<Block> + Send + Sync + 'static,
+{
+	fn rpc_prove_finality(
+		&self,
+		block: Block::Hash,
+	) -> Result<Option<EncodedFinalityProof>, sp_blockchain::Error> {
+		self.prove_block_finality(block)
+			.map(|proof| proof.map(|proof| EncodedFinalityProof(proof.into())))
+	}
+}
@@ -19,68 +19,184 @@
 //! RPC API for GRANDPA.
 #![warn(missing_docs)]
 
-use futures::{FutureExt, TryFutureExt};
+use futures::{FutureExt, TryFutureExt, StreamExt, TryStreamExt};
+use jsonrpc_core::futures::{Future as _, Sink as _};
 use jsonrpc_derive::rpc;
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use log::warn;
+use sc_finality_grandpa::GrandpaJustificationStream;
+use sc_rpc::{Metadata, Subscriptions};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::sync::Arc;
 
 mod error;
+mod finality;
+mod notification;
 mod report;
 
+pub use finality::{EncodedFinalityProof, RpcFinalityProofProvider};
+pub use notification::JustificationNotification;
 use report::{ReportAuthoritySet, ReportVoterState, ReportedRoundStates};
 
 /// Returned when Grandpa RPC endpoint is not ready.
 pub const NOT_READY_ERROR_CODE: i64 = 1;
 
+/// Returned when the finality proof of a block couldn't be generated.
+pub const PROVE_FINALITY_ERROR_CODE: i64 = 2;
+
 type FutureResult<T> =
 	Box<dyn jsonrpc_core::futures::Future<Item = T, Error = jsonrpc_core::Error> + Send>;
 
 /// Provides RPC methods for interacting with GRANDPA.
 #[rpc]
-pub trait GrandpaApi {
+pub trait GrandpaApi<Hash, Number> {
+	/// RPC Metadata
+	type Metadata;
+
 	/// Returns the state of the current best round state as well as the
 	/// ongoing background rounds.
 	#[rpc(name = "grandpa_roundState")]
 	fn round_state(&self) -> FutureResult<ReportedRoundStates>;
+
+	/// Prove the finality of the given block to a verifier knowing the authority set of its
+	/// parent: returns the justification of the earliest finalized descendant of the block,
+	/// preceded by the justifications and headers of the blocks enacting the authority set
+	/// changes in between. Returns `None` if the block isn't finalized yet.
+	#[rpc(name = "grandpa_proveFinality")]
+	fn prove_finality(&self, block: Hash) -> FutureResult<Option<EncodedFinalityProof>>;
+
+	/// Returns the blocks finalized by GRANDPA from now on, each along with the encoded
+	/// justification proving its finality.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		subscribe,
+		name = "grandpa_subscribeJustifications"
+	)]
+	fn subscribe_justifications(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<JustificationNotification<Hash, Number>>,
+	);
+
+	/// Unsubscribe from receiving the justifications of finalized blocks.
+	#[pubsub(
+		subscription = "grandpa_justifications",
+		unsubscribe,
+		name = "grandpa_unsubscribeJustifications"
+	)]
+	fn unsubscribe_justifications(
+		&self,
+		metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool>;
 }
 
 /// Implements the GrandpaApi RPC trait for interacting with GRANDPA.
-pub struct GrandpaRpcHandler<AuthoritySet, VoterState> {
+pub struct GrandpaRpcHandler<AuthoritySet, VoterState, Block: BlockT, ProofProvider> {
 	authority_set: AuthoritySet,
 	voter_state: VoterState,
+	justification_stream: GrandpaJustificationStream<Block>,
+	subscriptions: Subscriptions,
+	finality_proof_provider: Arc<ProofProvider>,
 }
 
-impl<AuthoritySet, VoterState> GrandpaRpcHandler<AuthoritySet, VoterState> {
+impl<AuthoritySet, VoterState, Block: BlockT, ProofProvider>
+	GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
+{
 	/// Creates a new GrandpaRpcHander instance.
-	pub fn new(authority_set: AuthoritySet, voter_state: VoterState) -> Self {
+	pub fn new(
+		authority_set: AuthoritySet,
+		voter_state: VoterState,
+		justification_stream: GrandpaJustificationStream<Block>,
+		subscriptions: Subscriptions,
+		finality_proof_provider: Arc<ProofProvider>,
+	) -> Self {
 		Self {
 			authority_set,
 			voter_state,
+			justification_stream,
+			subscriptions,
+			finality_proof_provider,
 		}
 	}
 }
 
-impl<AuthoritySet, VoterState> GrandpaApi for GrandpaRpcHandler<AuthoritySet, VoterState>
+impl<AuthoritySet, VoterState, Block, ProofProvider> GrandpaApi<Block::Hash, NumberFor<Block>>
+	for GrandpaRpcHandler<AuthoritySet, VoterState, Block, ProofProvider>
 where
 	VoterState: ReportVoterState + Send + Sync + 'static,
 	AuthoritySet: ReportAuthoritySet + Send + Sync + 'static,
+	Block: BlockT,
+	ProofProvider: RpcFinalityProofProvider<Block> + 'static,
 {
+	type Metadata = Metadata;
+
 	fn round_state(&self) -> FutureResult<ReportedRoundStates> {
 		let round_states = ReportedRoundStates::from(&self.authority_set, &self.voter_state);
 		let future = async move { round_states }.boxed();
 		Box::new(future.map_err(jsonrpc_core::Error::from).compat())
 	}
+
+	fn prove_finality(&self, block: Block::Hash) -> FutureResult<Option<EncodedFinalityProof>> {
+		let proof = self.finality_proof_provider
+			.rpc_prove_finality(block)
+			.map_err(|e| error::Error::ProveFinalityFailed(e).into());
+		Box::new(jsonrpc_core::futures::future::result(proof))
+	}
+
+	fn subscribe_justifications(
+		&self,
+		_metadata: Self::Metadata,
+		subscriber: Subscriber<JustificationNotification<Block::Hash, NumberFor<Block>>>,
+	) {
+		let stream = self.justification_stream.subscribe()
+			.map(|notification| Ok::<_, ()>(Ok(JustificationNotification::from(notification))))
+			.compat();
+
+		self.subscriptions.add(subscriber, |sink| {
+			sink
+				.sink_map_err(|e| warn!("Error sending justification notifications: {:?}", e))
+				.send_all(stream)
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_justifications(
+		&self,
+		_metadata: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> jsonrpc_core::Result<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use jsonrpc_core::IoHandler;
-	use sc_finality_grandpa::{report, AuthorityId};
+	use jsonrpc_core::{futures::{future as future01, sync::mpsc, Stream as _}, MetaIoHandler};
+	use sc_finality_grandpa::{report, AuthorityId, GrandpaJustificationSender};
 	use sp_core::crypto::Public;
-	use std::{collections::HashSet, convert::TryInto};
+	use std::{collections::HashSet, convert::TryInto, sync::Arc};
+	use substrate_test_runtime_client::runtime::Block;
 
 	struct TestAuthoritySet;
 	struct TestVoterState;
 	struct EmptyVoterState;
+	struct TestFinalityProofProvider;
+
+	impl RpcFinalityProofProvider<Block> for TestFinalityProofProvider {
+		fn rpc_prove_finality(
+			&self,
+			block: <Block as BlockT>::Hash,
+		) -> Result<Option<EncodedFinalityProof>, sp_blockchain::Error> {
+			if block == Default::default() {
+				Ok(Some(EncodedFinalityProof(vec![1, 2, 3].into())))
+			} else {
+				Ok(None)
+			}
+		}
+	}
 
 	fn voters() -> HashSet<AuthorityId> {
 		let voter_id_1 = AuthorityId::from_slice(&[1; 32]);
@@ -133,23 +249,52 @@ mod tests {
 		}
 	}
 
+	type Boxed01Future01 = Box<dyn future01::Future<Item = (), Error = ()> + Send + 'static>;
+
+	struct TestExecutor;
+	impl future01::Executor<Boxed01Future01> for TestExecutor {
+		fn execute(
+			&self,
+			future: Boxed01Future01,
+		) -> std::result::Result<(), future01::ExecuteError<Boxed01Future01>> {
+			std::thread::spawn(move || { let _ = future.wait(); });
+			Ok(())
+		}
+	}
+
+	fn setup_io_handler<VoterState>(
+		voter_state: VoterState,
+	) -> (MetaIoHandler<Metadata>, GrandpaJustificationSender<Block>)
+	where
+		VoterState: ReportVoterState + Send + Sync + 'static,
+	{
+		let (justification_sender, justification_stream) = GrandpaJustificationStream::channel();
+		let handler = GrandpaRpcHandler::new(
+			TestAuthoritySet,
+			voter_state,
+			justification_stream,
+			Subscriptions::new(Arc::new(TestExecutor)),
+			Arc::new(TestFinalityProofProvider),
+		);
+		let mut io = MetaIoHandler::default();
+		io.extend_with(GrandpaApi::to_delegate(handler));
+
+		(io, justification_sender)
+	}
+
 	#[test]
 	fn uninitialized_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, EmptyVoterState);
-		let mut io = IoHandler::new();
-		io.extend_with(GrandpaApi::to_delegate(handler));
+		let (io, _) = setup_io_handler(EmptyVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = r#"{"jsonrpc":"2.0","error":{"code":1,"message":"GRANDPA RPC endpoint not ready"},"id":1}"#;
 
-		assert_eq!(Some(response.into()), io.handle_request_sync(request));
+		assert_eq!(Some(response.into()), io.handle_request_sync(request, Default::default()));
 	}
 
 	#[test]
 	fn working_rpc_handler() {
-		let handler = GrandpaRpcHandler::new(TestAuthoritySet, TestVoterState);
-		let mut io = IoHandler::new();
-		io.extend_with(GrandpaApi::to_delegate(handler));
+		let (io, _) = setup_io_handler(TestVoterState);
 
 		let request = r#"{"jsonrpc":"2.0","method":"grandpa_roundState","params":[],"id":1}"#;
 		let response = "{\"jsonrpc\":\"2.0\",\"result\":{\
@@ -166,6 +311,49 @@ mod tests {
 			\"setId\":1\
 		},\"id\":1}";
 
-		assert_eq!(io.handle_request_sync(request), Some(response.into()));
+		assert_eq!(io.handle_request_sync(request, Default::default()), Some(response.into()));
+	}
+
+	#[test]
+	fn prove_finality_with_test_finality_proof_provider() {
+		let (io, _) = setup_io_handler(TestVoterState);
+
+		let request = "{\"jsonrpc\":\"2.0\",\"method\":\"grandpa_proveFinality\",\"params\":[\
+			\"0x0000000000000000000000000000000000000000000000000000000000000000\"],\"id\":1}";
+		let response = r#"{"jsonrpc":"2.0","result":"0x010203","id":1}"#;
+		assert_eq!(io.handle_request_sync(request, Default::default()), Some(response.into()));
+
+		let request = "{\"jsonrpc\":\"2.0\",\"method\":\"grandpa_proveFinality\",\"params\":[\
+			\"0x0101010101010101010101010101010101010101010101010101010101010101\"],\"id\":1}";
+		let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+		assert_eq!(io.handle_request_sync(request, Default::default()), Some(response.into()));
+	}
+
+	#[test]
+	fn subscribe_and_receive_justifications() {
+		let (io, justification_sender) = setup_io_handler(TestVoterState);
+		let (tx, rx) = mpsc::channel(1);
+		let meta = Metadata::new(tx);
+
+		let request = r#"{"jsonrpc":"2.0","method":"grandpa_subscribeJustifications","params":[],"id":1}"#;
+		let response = r#"{"jsonrpc":"2.0","result":1,"id":1}"#;
+		assert_eq!(io.handle_request_sync(request, meta.clone()), Some(response.into()));
+
+		justification_sender.notify(sc_finality_grandpa::JustificationNotification {
+			hash: Default::default(),
+			number: 1,
+			justification: vec![1, 2, 3],
+		});
+
+		let notification = rx.take(1).collect().wait().unwrap();
+		let notification: serde_json::Value = serde_json::from_str(&notification[0]).unwrap();
+		assert_eq!(notification["method"], "grandpa_justifications");
+		assert_eq!(notification["params"]["subscription"], 1);
+		assert_eq!(notification["params"]["result"]["number"], 1);
+		assert_eq!(notification["params"]["result"]["justification"], "0x010203");
+
+		let request = r#"{"jsonrpc":"2.0","method":"grandpa_unsubscribeJustifications","params":[1],"id":2}"#;
+		let response = r#"{"jsonrpc":"2.0","result":true,"id":2}"#;
+		assert_eq!(io.handle_request_sync(request, meta), Some(response.into()));
 	}
 }
@@ -0,0 +1,45 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use serde::{Deserialize, Serialize};
+use sp_core::Bytes;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+/// Block finalized by GRANDPA, as sent to the subscribers of `grandpa_subscribeJustifications`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JustificationNotification<Hash, Number> {
+	/// Hash of the finalized block.
+	pub hash: Hash,
+	/// Number of the finalized block.
+	pub number: Number,
+	/// SCALE-encoded `GrandpaJustification` of the block.
+	pub justification: Bytes,
+}
+
+impl<Block: BlockT> From<sc_finality_grandpa::JustificationNotification<Block>>
+	for JustificationNotification<Block::Hash, NumberFor<Block>>
+{
+	fn from(notification: sc_finality_grandpa::JustificationNotification<Block>) -> Self {
+		JustificationNotification {
+			hash: notification.hash,
+			number: notification.number,
+			justification: notification.justification.into(),
+		}
+	}
+}
@@ -19,7 +19,7 @@
 //! Utilities for dealing with authorities, authority sets, and handoffs.
 
 use fork_tree::ForkTree;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use finality_grandpa::voter_set::VoterSet;
 use parity_scale_codec::{Encode, Decode};
 use log::debug;
@@ -43,11 +43,17 @@ pub enum Error<E> {
 /// A shared authority set.
 pub struct SharedAuthoritySet<H, N> {
 	inner: Arc<RwLock<AuthoritySet<H, N>>>,
+	/// Authority set proven by a warp sync proof to be in charge of finality after the block
+	/// with the given hash, until that block is imported along with its state.
+	warp_sync_target: Arc<Mutex<Option<(H, AuthoritySet<H, N>)>>>,
 }
 
 impl<H, N> Clone for SharedAuthoritySet<H, N> {
 	fn clone(&self) -> Self {
-		SharedAuthoritySet { inner: self.inner.clone() }
+		SharedAuthoritySet {
+			inner: self.inner.clone(),
+			warp_sync_target: self.warp_sync_target.clone(),
+		}
 	}
 }
 
@@ -56,6 +62,29 @@ impl<H, N> SharedAuthoritySet<H, N> {
 	pub(crate) fn inner(&self) -> &RwLock<AuthoritySet<H, N>> {
 		&*self.inner
 	}
+
+	/// Remember the authority set proven by a warp sync proof to be in charge of finality after
+	/// the block `hash`. It replaces the current set once that block is imported.
+	pub(crate) fn set_warp_sync_target(&self, hash: H, set: AuthoritySet<H, N>) {
+		*self.warp_sync_target.lock() = Some((hash, set));
+	}
+
+	/// Take the authority set proven by a warp sync proof to be in charge of finality after the
+	/// block `hash`, if any.
+	pub(crate) fn take_warp_sync_target(&self, hash: &H) -> Option<AuthoritySet<H, N>> where H: PartialEq {
+		let mut target = self.warp_sync_target.lock();
+		match &*target {
+			Some((target_hash, _)) if target_hash == hash => target.take().map(|(_, set)| set),
+			_ => None,
+		}
+	}
+}
+
+impl<H: Clone, N: Clone> SharedAuthoritySet<H, N> {
+	/// Clone the inner `AuthoritySet`.
+	pub fn clone_inner(&self) -> AuthoritySet<H, N> {
+		self.inner.read().clone()
+	}
 }
 
 impl<H: Eq, N> SharedAuthoritySet<H, N>
@@ -85,7 +114,10 @@ where N: Add<Output=N> + Ord + Clone + Debug,
 
 impl<H, N> From<AuthoritySet<H, N>> for SharedAuthoritySet<H, N> {
 	fn from(set: AuthoritySet<H, N>) -> Self {
-		SharedAuthoritySet { inner: Arc::new(RwLock::new(set)) }
+		SharedAuthoritySet {
+			inner: Arc::new(RwLock::new(set)),
+			warp_sync_target: Arc::new(Mutex::new(None)),
+		}
 	}
 }
 
@@ -101,7 +133,7 @@ pub(crate) struct Status<H, N> {
 
 /// A set of authorities.
 #[derive(Debug, Clone, Encode, Decode, PartialEq)]
-pub(crate) struct AuthoritySet<H, N> {
+pub struct AuthoritySet<H, N> {
 	/// The current active authorities.
 	pub(crate) current_authorities: AuthorityList,
 	/// The current set id.
@@ -1163,4 +1195,27 @@ mod tests {
 			Err(Error::InvalidAuthoritySet)
 		));
 	}
+
+	#[test]
+	fn warp_sync_target_is_only_taken_by_its_block() {
+		let genesis_authorities = vec![(AuthorityId::from_slice(&[1; 32]), 1)];
+		let proven_authorities = vec![(AuthorityId::from_slice(&[2; 32]), 1)];
+		let shared: SharedAuthoritySet<&str, u64> =
+			AuthoritySet::genesis(genesis_authorities.clone()).unwrap().into();
+
+		shared.set_warp_sync_target("B", AuthoritySet::new(
+			proven_authorities.clone(),
+			3,
+			ForkTree::new(),
+			Vec::new(),
+		).unwrap());
+
+		// the current set is untouched until the target block is imported.
+		assert_eq!(shared.clone_inner().current(), (0, &genesis_authorities[..]));
+		assert!(shared.take_warp_sync_target(&"A").is_none());
+
+		let set = shared.clone().take_warp_sync_target(&"B").unwrap();
+		assert_eq!(set.current(), (3, &proven_authorities[..]));
+		assert!(shared.take_warp_sync_target(&"B").is_none());
+	}
 }
@@ -39,6 +39,7 @@ const SET_STATE_KEY: &[u8] = b"grandpa_completed_round";
 const CONCLUDED_ROUNDS: &[u8] = b"grandpa_concluded_rounds";
 const AUTHORITY_SET_KEY: &[u8] = b"grandpa_voters";
 const CONSENSUS_CHANGES_KEY: &[u8] = b"grandpa_consensus_changes";
+const AUTHORITY_SET_CHANGES_KEY: &[u8] = b"grandpa_authority_set_changes";
 
 const CURRENT_VERSION: u32 = 2;
 
@@ -259,6 +260,11 @@ fn migrate_from_version1<Block: BlockT, B, G>(
 	Ok(None)
 }
 
+/// Whether the GRANDPA data was initialized in the backend.
+pub(crate) fn is_initialized<B: AuxStore>(backend: &B) -> ClientResult<bool> {
+	Ok(backend.get_aux(AUTHORITY_SET_KEY)?.is_some())
+}
+
 /// Load or initialize persistent data from backend.
 pub(crate) fn load_persistent<Block: BlockT, B, G>(
 	backend: &B,
@@ -433,6 +439,28 @@ pub(crate) fn update_consensus_changes<H, N, F, R>(
 	write_aux(&[(CONSENSUS_CHANGES_KEY, set.encode().as_slice())])
 }
 
+/// Blocks that enacted an authority set change, along with the id of the set that finalized
+/// them, in ascending order.
+pub(crate) type AuthoritySetChanges<N> = Vec<(SetId, N)>;
+
+/// Load the list of blocks that enacted an authority set change.
+pub(crate) fn load_authority_set_changes<B: AuxStore, N: Decode>(
+	backend: &B,
+) -> ClientResult<AuthoritySetChanges<N>> {
+	Ok(load_decode(backend, AUTHORITY_SET_CHANGES_KEY)?.unwrap_or_default())
+}
+
+/// Update the list of blocks that enacted an authority set change.
+pub(crate) fn update_authority_set_changes<N, F, R>(
+	changes: &AuthoritySetChanges<N>,
+	write_aux: F
+) -> R where
+	N: Encode,
+	F: FnOnce(&[(&'static [u8], &[u8])]) -> R,
+{
+	write_aux(&[(AUTHORITY_SET_CHANGES_KEY, changes.encode().as_slice())])
+}
+
 #[cfg(test)]
 pub(crate) fn load_authorities<B: AuxStore, H: Decode, N: Decode>(backend: &B)
 	-> Option<AuthoritySet<H, N>> {
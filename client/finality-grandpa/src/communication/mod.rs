@@ -195,7 +195,8 @@ impl<B: BlockT, N: Network<B>> NetworkBridge<B, N> {
 			service.clone(),
 			GRANDPA_ENGINE_ID,
 			GRANDPA_PROTOCOL_NAME,
-			validator.clone()
+			validator.clone(),
+			&Default::default(),
 		)));
 
 		{
@@ -53,6 +53,7 @@ use crate::authorities::{AuthoritySet, SharedAuthoritySet};
 use crate::communication::Network as NetworkT;
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::justification::GrandpaJustification;
+use crate::notification::{GrandpaJustificationSender, JustificationNotification};
 use crate::until_imported::UntilVoteTargetImported;
 use crate::voting_rule::VotingRule;
 use sp_finality_grandpa::{
@@ -405,6 +406,7 @@ pub(crate) struct Environment<Backend, Block: BlockT, C, N: NetworkT<Block>, SC,
 	pub(crate) voter_set_state: SharedVoterSetState<Block>,
 	pub(crate) voting_rule: VR,
 	pub(crate) metrics: Option<Metrics>,
+	pub(crate) justification_sender: GrandpaJustificationSender<Block>,
 	pub(crate) _phantom: PhantomData<Backend>,
 }
 
@@ -1005,6 +1007,7 @@ where
 			&self.authority_set,
 			&self.consensus_changes,
 			Some(self.config.justification_period.into()),
+			Some(&self.justification_sender),
 			hash,
 			number,
 			(round, commit).into(),
@@ -1069,6 +1072,7 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 	authority_set: &SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
 	consensus_changes: &SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
 	justification_period: Option<NumberFor<Block>>,
+	justification_sender: Option<&GrandpaJustificationSender<Block>>,
 	hash: Block::Hash,
 	number: NumberFor<Block>,
 	justification_or_commit: JustificationOrCommit<Block>,
@@ -1102,6 +1106,9 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 	// holds the old consensus changes in case it is changed below, needed for
 	// reverting in case of failure
 	let mut old_consensus_changes = None;
+	// sent to the subscribers once the finality has been written to the database
+	let mut notification = None;
+	let notify_subscribers = justification_sender.map_or(false, |sender| sender.has_subscribers());
 
 	let mut consensus_changes = consensus_changes.lock();
 	let canon_at_height = |canon_number| {
@@ -1143,8 +1150,8 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 		// `N+1`. this assumption is required to make sure we store
 		// justifications for transition blocks which will be requested by
 		// syncing clients.
-		let justification = match justification_or_commit {
-			JustificationOrCommit::Justification(justification) => Some(justification.encode()),
+		let (justification, store_justification) = match justification_or_commit {
+			JustificationOrCommit::Justification(justification) => (Some(justification.encode()), true),
 			JustificationOrCommit::Commit((round_number, commit)) => {
 				let mut justification_required =
 					// justification is always required when block that enacts new authorities
@@ -1164,20 +1171,30 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 					}
 				}
 
-				if justification_required {
+				// the subscribers are sent a justification even when it isn't stored
+				if justification_required || notify_subscribers {
 					let justification = GrandpaJustification::from_commit(
 						&client,
 						round_number,
 						commit,
 					)?;
 
-					Some(justification.encode())
+					(Some(justification.encode()), justification_required)
 				} else {
-					None
+					(None, false)
 				}
 			},
 		};
 
+		if notify_subscribers {
+			notification = justification.clone().map(|justification| JustificationNotification {
+				hash,
+				number,
+				justification,
+			});
+		}
+		let justification = justification.filter(|_| store_justification);
+
 		debug!(target: "afg", "Finalizing blocks up to ({:?}, {})", number, hash);
 
 		// ideally some handle to a synchronization oracle would be used
@@ -1236,9 +1253,33 @@ pub(crate) fn finalize_block<BE, Block, Client>(
 			}
 		}
 
+		if let Some(new_set) = &new_authorities {
+			// remember which blocks enacted a set change, along with the set that finalized them,
+			// so that warp sync proofs can be generated from the justifications stored for them.
+			let write_result = crate::aux_schema::load_authority_set_changes(&*client)
+				.and_then(|mut changes| {
+					changes.push((new_set.set_id - 1, number));
+					crate::aux_schema::update_authority_set_changes(
+						&changes,
+						|insert| apply_aux(import_op, insert, &[]),
+					)
+				});
+
+			if let Err(e) = write_result {
+				warn!(target: "afg", "Failed to write authority set changes to disk. Bailing.");
+				warn!(target: "afg", "Node is in a potentially inconsistent state.");
+
+				return Err(e.into());
+			}
+		}
+
 		Ok(new_authorities.map(VoterCommand::ChangeAuthorities))
 	});
 
+	if let (Ok(_), Some(sender), Some(notification)) = (&update_res, justification_sender, notification) {
+		sender.notify(notification);
+	}
+
 	match update_res {
 		Ok(Some(command)) => Err(CommandOrError::VoterCommand(command)),
 		Ok(None) => Ok(()),
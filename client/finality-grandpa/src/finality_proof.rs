@@ -49,7 +49,7 @@ use parity_scale_codec::{Encode, Decode};
 use finality_grandpa::BlockNumberOps;
 use sp_runtime::{
 	Justification, generic::BlockId,
-	traits::{NumberFor, Block as BlockT, Header as HeaderT, One},
+	traits::{NumberFor, Block as BlockT, Header as HeaderT, One, Zero},
 };
 use sp_core::storage::StorageKey;
 use sc_telemetry::{telemetry, CONSENSUS_INFO};
@@ -171,6 +171,41 @@ impl<B, Block: BlockT> FinalityProofProvider<B, Block>
 	}
 }
 
+impl<B, Block> FinalityProofProvider<B, Block>
+	where
+		Block: BlockT,
+		NumberFor<Block>: BlockNumberOps,
+		B: Backend<Block> + Send + Sync + 'static,
+{
+	/// Prove the finality of the given block, to a verifier knowing the authority set of its
+	/// parent.
+	///
+	/// The proof is made of the justification of the earliest finalized descendant of the block,
+	/// preceded by the justifications of the blocks enacting the authority set changes in between.
+	/// Returns `None` if the block isn't finalized yet.
+	pub fn prove_block_finality(&self, block: Block::Hash) -> ClientResult<Option<Vec<u8>>> {
+		let blockchain = self.backend.blockchain();
+		let header = blockchain.expect_header(BlockId::Hash(block))?;
+		if header.number().is_zero() {
+			return Err(ClientError::Backend("The genesis block is final by definition".into()));
+		}
+		if blockchain.hash(*header.number())? != Some(block) {
+			return Err(ClientError::Backend(
+				format!("Cannot generate finality proof for non-canonical block: {}", block),
+			));
+		}
+
+		prove_finality::<_, _, GrandpaJustification<Block>>(
+			&*blockchain,
+			&*self.authority_provider,
+			// the verifier knows the set it expects the justifications to be signed by.
+			None,
+			*header.parent_hash(),
+			block,
+		)
+	}
+}
+
 impl<B, Block> sc_network::config::FinalityProofProvider<Block> for FinalityProofProvider<B, Block>
 	where
 		Block: BlockT,
@@ -191,7 +226,7 @@ impl<B, Block> sc_network::config::FinalityProofProvider<Block> for FinalityProo
 			FinalityProofRequest::Original(request) => prove_finality::<_, _, GrandpaJustification<Block>>(
 				&*self.backend.blockchain(),
 				&*self.authority_provider,
-				request.authorities_set_id,
+				Some(request.authorities_set_id),
 				request.last_finalized,
 				for_block,
 			),
@@ -268,11 +303,14 @@ pub(crate) fn make_finality_proof_request<H: Encode + Decode>(last_finalized: H,
 /// It is assumed that the caller already have a proof-of-finality for the block 'begin'.
 /// It is assumed that the caller already knows all blocks in the range (begin; end].
 ///
+/// If `authorities_set_id` is given, the proof is only returned if its first justification is
+/// signed by that set.
+///
 /// Returns None if there are no finalized blocks unknown to the caller.
 pub(crate) fn prove_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
 	blockchain: &B,
 	authorities_provider: &dyn AuthoritySetForFinalityProver<Block>,
-	authorities_set_id: u64,
+	authorities_set_id: Option<u64>,
 	begin: Block::Hash,
 	end: Block::Hash,
 ) -> ::sp_blockchain::Result<Option<Vec<u8>>>
@@ -353,7 +391,7 @@ pub(crate) fn prove_finality<Block: BlockT, B: BlockchainBackend<Block>, J>(
 			let justifies_authority_set_change = proof_fragment.authorities_proof.is_some();
 			if justifies_end_block || justifies_authority_set_change {
 				// check if the proof is generated by the requested authority set
-				if finality_proof.is_empty() {
+				if let (true, Some(authorities_set_id)) = (finality_proof.is_empty(), authorities_set_id) {
 					let justification_check_result = J::decode_and_verify(
 						&proof_fragment.justification,
 						authorities_set_id,
@@ -683,7 +721,7 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(2).hash(),
 			header(2).hash(),
 		).unwrap_err();
@@ -703,7 +741,7 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
@@ -729,7 +767,7 @@ pub(crate) mod tests {
 				|_| unreachable!("should return before calling GetAuthorities"),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			side_header(4).hash(),
 			second_side_header(5).hash(),
 		).unwrap_err();
@@ -748,7 +786,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("authorities didn't change => ProveAuthorities won't be called"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
@@ -772,7 +810,7 @@ pub(crate) mod tests {
 				|_| Ok(authorities.clone()),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(5).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -798,7 +836,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(5).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -842,7 +880,7 @@ pub(crate) mod tests {
 					_ => unreachable!("no other authorities should be proved: {:?}", block_id),
 				},
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(6).hash(),
 		).unwrap().unwrap()[..]).unwrap();
@@ -1019,7 +1057,7 @@ pub(crate) mod tests {
 				|_| Ok(vec![(AuthorityId::from_slice(&[1u8; 32]), 1u64)]),
 				|_| unreachable!("should return before calling ProveAuthorities"),
 			),
-			0,
+			Some(0),
 			header(3).hash(),
 			header(4).hash(),
 		).unwrap();
@@ -44,6 +44,7 @@ use crate::authorities::{AuthoritySet, SharedAuthoritySet, DelayKind, PendingCha
 use crate::consensus_changes::SharedConsensusChanges;
 use crate::environment::finalize_block;
 use crate::justification::GrandpaJustification;
+use crate::notification::GrandpaJustificationSender;
 use std::marker::PhantomData;
 
 /// A block-import handler for GRANDPA.
@@ -62,6 +63,7 @@ pub struct GrandpaBlockImport<Backend, Block: BlockT, Client, SC> {
 	send_voter_commands: TracingUnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 	consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
 	authority_set_hard_forks: HashMap<Block::Hash, PendingChange<Block::Hash, NumberFor<Block>>>,
+	justification_sender: GrandpaJustificationSender<Block>,
 	_phantom: PhantomData<Backend>,
 }
 
@@ -76,6 +78,7 @@ impl<Backend, Block: BlockT, Client, SC: Clone> Clone for
 			send_voter_commands: self.send_voter_commands.clone(),
 			consensus_changes: self.consensus_changes.clone(),
 			authority_set_hard_forks: self.authority_set_hard_forks.clone(),
+			justification_sender: self.justification_sender.clone(),
 			_phantom: PhantomData,
 		}
 	}
@@ -182,7 +185,7 @@ impl<'a, Block: 'a + BlockT> Drop for PendingSetChanges<'a, Block> {
 	}
 }
 
-fn find_scheduled_change<B: BlockT>(header: &B::Header)
+pub(crate) fn find_scheduled_change<B: BlockT>(header: &B::Header)
 	-> Option<ScheduledChange<NumberFor<B>>>
 {
 	let id = OpaqueDigestItemId::Consensus(&GRANDPA_ENGINE_ID);
@@ -197,7 +200,7 @@ fn find_scheduled_change<B: BlockT>(header: &B::Header)
 	header.digest().convert_first(|l| l.try_to(id).and_then(filter_log))
 }
 
-fn find_forced_change<B: BlockT>(header: &B::Header)
+pub(crate) fn find_forced_change<B: BlockT>(header: &B::Header)
 	-> Option<(NumberFor<B>, ScheduledChange<NumberFor<B>>)>
 {
 	let id = OpaqueDigestItemId::Consensus(&GRANDPA_ENGINE_ID);
@@ -421,6 +424,13 @@ impl<BE, Block: BlockT, Client, SC> BlockImport<Block>
 		let hash = block.post_hash();
 		let number = *block.header.number();
 
+		// the block is already in chain without its state or body, which is now being imported.
+		// authority set changes have been tracked when its header was first imported.
+		if block.import_existing {
+			return (&*self.inner).import_block(block, new_cache)
+				.map_err(|e| ConsensusError::ClientImport(e.to_string()))
+		}
+
 		// early exit if block already in chain, otherwise the check for
 		// authority changes will error when trying to re-import a change block
 		match self.inner.status(BlockId::Hash(hash)) {
@@ -429,6 +439,43 @@ impl<BE, Block: BlockT, Client, SC> BlockImport<Block>
 			Err(e) => return Err(ConsensusError::ClientImport(e.to_string())),
 		}
 
+		if block.state.is_some() {
+			// the block has been downloaded along with its state. if it is the target of a warp
+			// sync, the set in charge of finality after this block has been proven by the warp
+			// sync proof, otherwise (e.g. a state snapshot) the current set is kept. persist it
+			// along with the block and restart the voter on top of it once the block is
+			// imported. the justification is kept in the block, which is imported as finalized.
+			let authority_set = self.authority_set.take_warp_sync_target(&hash)
+				.unwrap_or_else(|| self.authority_set.clone_inner());
+			let (set_id, authorities) = authority_set.current();
+			let new_set = NewAuthoritySet {
+				canon_hash: hash,
+				canon_number: number,
+				set_id,
+				authorities: authorities.to_vec(),
+			};
+
+			crate::aux_schema::update_authority_set::<Block, _, _>(
+				&authority_set,
+				Some(&new_set),
+				|insert| block.auxiliary.extend(
+					insert.iter().map(|(k, v)| (k.to_vec(), Some(v.to_vec())))
+				)
+			);
+
+			return match (&*self.inner).import_block(block, new_cache) {
+				Ok(ImportResult::Imported(aux)) => {
+					*self.authority_set.inner().write() = authority_set;
+					let _ = self.send_voter_commands.unbounded_send(
+						VoterCommand::ChangeAuthorities(new_set)
+					);
+					Ok(ImportResult::Imported(aux))
+				},
+				Ok(r) => Ok(r),
+				Err(e) => Err(ConsensusError::ClientImport(e.to_string())),
+			}
+		}
+
 		// on initial sync we will restrict logging under info to avoid spam.
 		let initial_sync = block.origin == BlockOrigin::NetworkInitialSync;
 
@@ -560,6 +607,7 @@ impl<Backend, Block: BlockT, Client, SC> GrandpaBlockImport<Backend, Block, Clie
 		send_voter_commands: TracingUnboundedSender<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		consensus_changes: SharedConsensusChanges<Block::Hash, NumberFor<Block>>,
 		authority_set_hard_forks: Vec<(SetId, PendingChange<Block::Hash, NumberFor<Block>>)>,
+		justification_sender: GrandpaJustificationSender<Block>,
 	) -> GrandpaBlockImport<Backend, Block, Client, SC> {
 		// check for and apply any forced authority set hard fork that applies
 		// to the *current* authority set.
@@ -603,6 +651,7 @@ impl<Backend, Block: BlockT, Client, SC> GrandpaBlockImport<Backend, Block, Clie
 			send_voter_commands,
 			consensus_changes,
 			authority_set_hard_forks,
+			justification_sender,
 			_phantom: PhantomData,
 		}
 	}
@@ -644,6 +693,7 @@ where
 			&self.authority_set,
 			&self.consensus_changes,
 			None,
+			Some(&self.justification_sender),
 			hash,
 			number,
 			justification.into(),
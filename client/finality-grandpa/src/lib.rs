@@ -113,18 +113,24 @@ mod finality_proof;
 mod import;
 mod justification;
 mod light_import;
+mod notification;
 mod observer;
+mod revert;
 mod until_imported;
 mod voting_rule;
+mod warp_proof;
 
-pub use authorities::SharedAuthoritySet;
+pub use authorities::{AuthoritySet, SharedAuthoritySet};
 pub use finality_proof::{FinalityProofProvider, StorageAndProofProvider};
 pub use import::GrandpaBlockImport;
 pub use justification::GrandpaJustification;
 pub use light_import::light_block_import;
+pub use revert::revert;
+pub use notification::{GrandpaJustificationSender, GrandpaJustificationStream, JustificationNotification};
 pub use voting_rule::{
 	BeforeBestBlockBy, ThreeQuartersOfTheUnfinalizedChain, VotingRule, VotingRulesBuilder
 };
+pub use warp_proof::WarpSyncProofProvider;
 pub use finality_grandpa::voter::report;
 
 use aux_schema::PersistentData;
@@ -440,6 +446,8 @@ pub struct LinkHalf<Block: BlockT, C, SC> {
 	select_chain: SC,
 	persistent_data: PersistentData<Block>,
 	voter_commands_rx: TracingUnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
+	justification_sender: GrandpaJustificationSender<Block>,
+	justification_stream: GrandpaJustificationStream<Block>,
 }
 
 impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
@@ -447,6 +455,11 @@ impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
 	pub fn shared_authority_set(&self) -> &SharedAuthoritySet<Block::Hash, NumberFor<Block>> {
 		&self.persistent_data.authority_set
 	}
+
+	/// Get the stream of the justifications of the blocks finalized by GRANDPA.
+	pub fn justification_stream(&self) -> GrandpaJustificationStream<Block> {
+		self.justification_stream.clone()
+	}
 }
 
 /// Provider for the Grandpa authority set configured on the genesis block.
@@ -459,26 +472,34 @@ impl<Block: BlockT, E> GenesisAuthoritySetProvider<Block> for Arc<dyn ExecutorPr
 	where E: CallExecutor<Block>,
 {
 	fn get(&self) -> Result<AuthorityList, ClientError> {
-		// This implementation uses the Grandpa runtime API instead of reading directly from the
-		// `GRANDPA_AUTHORITIES_KEY` as the data may have been migrated since the genesis block of
-		// the chain, whereas the runtime API is backwards compatible.
-		self.executor()
-			.call(
-				&BlockId::Number(Zero::zero()),
-				"GrandpaApi_grandpa_authorities",
-				&[],
-				ExecutionStrategy::NativeElseWasm,
-				None,
-			)
-			.and_then(|call_result| {
-				Decode::decode(&mut &call_result[..])
-					.map_err(|err| ClientError::CallResultDecode(
-						"failed to decode GRANDPA authorities set proof", err
-					))
-			})
+		authorities_at(self.executor(), &BlockId::Number(Zero::zero()))
 	}
 }
 
+/// Get the authority set of the runtime at the given block, whose state must be available.
+pub(crate) fn authorities_at<Block: BlockT, E: CallExecutor<Block>>(
+	executor: &E,
+	id: &BlockId<Block>,
+) -> Result<AuthorityList, ClientError> {
+	// This implementation uses the Grandpa runtime API instead of reading directly from the
+	// `GRANDPA_AUTHORITIES_KEY` as the data may have been migrated since the given block, whereas
+	// the runtime API is backwards compatible.
+	executor
+		.call(
+			id,
+			"GrandpaApi_grandpa_authorities",
+			&[],
+			ExecutionStrategy::NativeElseWasm,
+			None,
+		)
+		.and_then(|call_result| {
+			Decode::decode(&mut &call_result[..])
+				.map_err(|err| ClientError::CallResultDecode(
+					"failed to decode GRANDPA authorities set proof", err
+				))
+		})
+}
+
 /// Make block importer and link half necessary to tie the background voter
 /// to it.
 pub fn block_import<BE, Block: BlockT, Client, SC>(
@@ -545,6 +566,8 @@ where
 
 	let (voter_commands_tx, voter_commands_rx) = tracing_unbounded("mpsc_grandpa_voter_command");
 
+	let (justification_sender, justification_stream) = GrandpaJustificationStream::channel();
+
 	// create pending change objects with 0 delay and enacted on finality
 	// (i.e. standard changes) for each authority set hard fork.
 	let authority_set_hard_forks = authority_set_hard_forks
@@ -571,12 +594,15 @@ where
 			voter_commands_tx,
 			persistent_data.consensus_changes.clone(),
 			authority_set_hard_forks,
+			justification_sender.clone(),
 		),
 		LinkHalf {
 			client,
 			select_chain,
 			persistent_data,
 			voter_commands_rx,
+			justification_sender,
+			justification_stream,
 		},
 	))
 }
@@ -711,6 +737,8 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		select_chain,
 		persistent_data,
 		voter_commands_rx,
+		justification_sender,
+		justification_stream: _,
 	} = link;
 
 	let network = NetworkBridge::new(
@@ -759,6 +787,7 @@ pub fn run_grandpa_voter<Block: BlockT, BE: 'static, C, N, SC, VR>(
 		voter_commands_rx,
 		prometheus_registry,
 		shared_voter_state,
+		justification_sender,
 	);
 
 	let voter_work = voter_work
@@ -819,6 +848,7 @@ where
 		voter_commands_rx: TracingUnboundedReceiver<VoterCommand<Block::Hash, NumberFor<Block>>>,
 		prometheus_registry: Option<prometheus_endpoint::Registry>,
 		shared_voter_state: SharedVoterState,
+		justification_sender: GrandpaJustificationSender<Block>,
 	) -> Self {
 		let metrics = match prometheus_registry.as_ref().map(Metrics::register) {
 			Some(Ok(metrics)) => Some(metrics),
@@ -842,6 +872,7 @@ where
 			consensus_changes: persistent_data.consensus_changes.clone(),
 			voter_set_state: persistent_data.set_state,
 			metrics: metrics.as_ref().map(|m| m.environment.clone()),
+			justification_sender,
 			_phantom: PhantomData,
 		});
 
@@ -981,6 +1012,7 @@ where
 					network: self.env.network.clone(),
 					voting_rule: self.env.voting_rule.clone(),
 					metrics: self.env.metrics.clone(),
+					justification_sender: self.env.justification_sender.clone(),
 					_phantom: PhantomData,
 				});
 
@@ -0,0 +1,84 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Notifications of the justifications of the blocks finalized by GRANDPA.
+
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use sp_runtime::{Justification, traits::{Block as BlockT, NumberFor}};
+use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
+
+type SharedSubscribers<Block> = Arc<Mutex<Vec<TracingUnboundedSender<JustificationNotification<Block>>>>>;
+
+/// Block finalized by GRANDPA, along with the encoded `GrandpaJustification` proving it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JustificationNotification<Block: BlockT> {
+	/// Hash of the finalized block.
+	pub hash: Block::Hash,
+	/// Number of the finalized block.
+	pub number: NumberFor<Block>,
+	/// Encoded justification of the block.
+	pub justification: Justification,
+}
+
+/// Sending half of the justification notifications, used where blocks are finalized.
+#[derive(Clone)]
+pub struct GrandpaJustificationSender<Block: BlockT> {
+	subscribers: SharedSubscribers<Block>,
+}
+
+impl<Block: BlockT> GrandpaJustificationSender<Block> {
+	/// Returns true if anyone is subscribed to the notifications. If not, the justifications that
+	/// aren't stored don't need to be created.
+	pub(crate) fn has_subscribers(&self) -> bool {
+		!self.subscribers.lock().is_empty()
+	}
+
+	/// Sends the notification to every subscriber, dropping the subscribers that are gone.
+	pub fn notify(&self, notification: JustificationNotification<Block>) {
+		self.subscribers.lock()
+			.retain(|subscriber| subscriber.unbounded_send(notification.clone()).is_ok());
+	}
+}
+
+/// Receiving half of the justification notifications, from which any number of subscriptions
+/// can be made.
+#[derive(Clone)]
+pub struct GrandpaJustificationStream<Block: BlockT> {
+	subscribers: SharedSubscribers<Block>,
+}
+
+impl<Block: BlockT> GrandpaJustificationStream<Block> {
+	/// Creates a new pair of sending and receiving halves.
+	pub fn channel() -> (GrandpaJustificationSender<Block>, Self) {
+		let subscribers = SharedSubscribers::default();
+		let sender = GrandpaJustificationSender { subscribers: subscribers.clone() };
+		(sender, GrandpaJustificationStream { subscribers })
+	}
+
+	/// Subscribes to the justifications of the blocks finalized from now on.
+	///
+	/// A notification is sent for every block finalized with a GRANDPA commit or justification,
+	/// whether the justification is stored or not, but not for the ancestors finalized along.
+	pub fn subscribe(&self) -> TracingUnboundedReceiver<JustificationNotification<Block>> {
+		let (sender, receiver) = tracing_unbounded("mpsc_justification_notification_stream");
+		self.subscribers.lock().push(sender);
+		receiver
+	}
+}
@@ -123,6 +123,7 @@ fn grandpa_observer<BE, Block: BlockT, Client, S, F>(
 				&authority_set,
 				&consensus_changes,
 				None,
+				None,
 				finalized_hash,
 				finalized_number,
 				(round, commit).into(),
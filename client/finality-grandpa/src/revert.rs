@@ -0,0 +1,264 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Revert of the GRANDPA data, along with the blocks of the chain.
+
+use log::warn;
+use fork_tree::ForkTree;
+use sc_client_api::{backend::AuxStore, utils::is_descendent_of, ExecutorProvider};
+use sp_blockchain::{Error as ClientError, HeaderBackend, HeaderMetadata};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT, NumberFor, One, Zero};
+use std::sync::Arc;
+
+use crate::authorities::{AuthoritySet, DelayKind, PendingChange};
+use crate::aux_schema::{self, PersistentData};
+use crate::import::{find_forced_change, find_scheduled_change};
+use crate::{authorities_at, NewAuthoritySet};
+
+/// Reverts the GRANDPA data of the client to its best block, once the blocks above it have been
+/// reverted.
+///
+/// The pending authority set changes announced by the reverted blocks are dropped. If finalized
+/// blocks were reverted, the authority set that was active at the best block is restored along
+/// with the changes it had pending, and the voter restarts from the best block. This requires the
+/// state of the best block, to read its authorities from the runtime.
+pub fn revert<Block, Client>(client: Arc<Client>) -> Result<(), ClientError> where
+	Block: BlockT,
+	Client: AuxStore
+		+ HeaderBackend<Block>
+		+ HeaderMetadata<Block, Error = ClientError>
+		+ ExecutorProvider<Block>,
+{
+	if !aux_schema::is_initialized(&*client)? {
+		return Ok(())
+	}
+
+	let info = client.info();
+	let (best_hash, best_number) = (info.best_hash, info.best_number);
+	let persistent_data: PersistentData<Block> = aux_schema::load_persistent(
+		&*client,
+		info.genesis_hash,
+		Zero::zero(),
+		|| Err(ClientError::Backend("Missing GRANDPA authority set".into())),
+	)?;
+	let authority_set = persistent_data.authority_set.clone_inner();
+	let finalized_reverted = persistent_data.set_state.read().completed_rounds().iter().any(|round| {
+		round.base.1 > best_number ||
+			round.state.finalized.as_ref().map_or(false, |(_, number)| *number > best_number)
+	});
+
+	let mut changes = aux_schema::load_authority_set_changes::<_, NumberFor<Block>>(&*client)?;
+	let kept = changes.iter().take_while(|(_, number)| *number <= best_number).count();
+	let reverted_changes = changes.split_off(kept);
+
+	let (set_id, authorities, pending_changes) = match reverted_changes.first() {
+		Some(&(set_id, _)) => {
+			// the set that finalized the last reverted change is the current one, unless some
+			// changes weren't recorded.
+			if reverted_changes.last().map(|(id, _)| id + 1) != Some(authority_set.set_id) {
+				return Err(ClientError::Backend(
+					"The GRANDPA authority set changes aren't all recorded, can't revert them".into()
+				))
+			}
+			warn!(
+				target: "afg",
+				"Reverting {} GRANDPA authority set changes, back to the set #{}",
+				reverted_changes.len(),
+				set_id,
+			);
+
+			let authorities = authorities_at(client.executor(), &BlockId::Hash(best_hash))?;
+			let pending_changes = announced_changes(&*client, changes.last().map(|(_, number)| *number), best_number)?;
+			(set_id, authorities, pending_changes)
+		},
+		None => (
+			authority_set.set_id,
+			authority_set.current_authorities.clone(),
+			authority_set.pending_changes()
+				.filter(|change| change.canon_height <= best_number)
+				.cloned()
+				.collect(),
+		),
+	};
+
+	let mut new_authority_set = AuthoritySet::new(authorities, set_id, ForkTree::new(), Vec::new())
+		.ok_or_else(|| ClientError::Backend("Invalid GRANDPA authority set at the best block".into()))?;
+	// the changes are ordered, with the changes of a branch after the ones of its ancestors.
+	let is_descendent_of = is_descendent_of(&*client, None);
+	for change in pending_changes {
+		new_authority_set.add_pending_change(change, &is_descendent_of)
+			.map_err(|e| ClientError::Backend(format!("Failed to restore a GRANDPA change: {}", e)))?;
+	}
+
+	// the voter restarts from the best block if it had gone past it.
+	let new_set = if finalized_reverted || !reverted_changes.is_empty() {
+		Some(NewAuthoritySet {
+			canon_hash: best_hash,
+			canon_number: best_number,
+			set_id,
+			authorities: new_authority_set.current_authorities.clone(),
+		})
+	} else {
+		None
+	};
+
+	aux_schema::update_authority_set::<Block, _, _>(
+		&new_authority_set,
+		new_set.as_ref(),
+		|values| client.insert_aux(values, &[]),
+	)?;
+	if !reverted_changes.is_empty() {
+		aux_schema::update_authority_set_changes(&changes, |values| client.insert_aux(values, &[]))?;
+	}
+
+	Ok(())
+}
+
+/// Returns the standard changes announced by the blocks of the chain between the given ones, and
+/// not enacted at the block `to`.
+fn announced_changes<Block, Client>(
+	client: &Client,
+	from: Option<NumberFor<Block>>,
+	to: NumberFor<Block>,
+) -> Result<Vec<PendingChange<Block::Hash, NumberFor<Block>>>, ClientError> where
+	Block: BlockT,
+	Client: HeaderBackend<Block>,
+{
+	let mut changes = Vec::new();
+	let mut number = from.unwrap_or_else(Zero::zero);
+	while number <= to {
+		let header = client.expect_header(BlockId::Number(number))?;
+		if let Some(change) = find_scheduled_change::<Block>(&header) {
+			if number + change.delay > to {
+				changes.push(PendingChange {
+					next_authorities: change.next_authorities,
+					delay: change.delay,
+					canon_height: number,
+					canon_hash: header.hash(),
+					delay_kind: DelayKind::Finalized,
+				});
+			}
+		}
+		if let Some((_, change)) = find_forced_change::<Block>(&header) {
+			if number + change.delay > to {
+				return Err(ClientError::Backend(format!(
+					"A GRANDPA forced change announced at #{} is pending, can't revert it",
+					number,
+				)))
+			}
+		}
+		number += One::one();
+	}
+	Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_block_builder::BlockBuilderProvider;
+	use sp_consensus::BlockOrigin;
+	use sp_finality_grandpa::AuthorityId;
+	use substrate_test_runtime_client::{runtime::Block, ClientBlockImportExt};
+
+	#[test]
+	fn pending_changes_of_reverted_blocks_are_dropped() {
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let mut hashes = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			hashes.push(block.hash());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+
+		let authorities = vec![(AuthorityId::default(), 1)];
+		let genesis_hash = client.info().genesis_hash;
+		aux_schema::load_persistent::<Block, _, _>(&*client, genesis_hash, 0, || Ok(authorities.clone())).unwrap();
+
+		let change = |number: u64, hash| PendingChange {
+			next_authorities: authorities.clone(),
+			delay: 10,
+			canon_height: number,
+			canon_hash: hash,
+			delay_kind: DelayKind::Finalized,
+		};
+		let mut authority_set = AuthoritySet::genesis(authorities.clone()).unwrap();
+		let is_descendent_of = is_descendent_of(&*client, None);
+		authority_set.add_pending_change(change(1, hashes[0]), &is_descendent_of).unwrap();
+		authority_set.add_pending_change(change(3, hashes[2]), &is_descendent_of).unwrap();
+		aux_schema::update_authority_set::<Block, _, _>(
+			&authority_set,
+			None,
+			|values| client.insert_aux(values, &[]),
+		).unwrap();
+
+		client.revert(1).unwrap();
+		revert(client.clone()).unwrap();
+
+		let authority_set = aux_schema::load_authorities::<_, <Block as BlockT>::Hash, u64>(&*client).unwrap();
+		assert_eq!(
+			authority_set.pending_changes().map(|change| change.canon_height).collect::<Vec<_>>(),
+			vec![1],
+		);
+		assert_eq!(authority_set.set_id, 0);
+	}
+
+	#[test]
+	fn voter_restarts_from_the_best_block_once_finalized_blocks_are_reverted() {
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let mut hashes = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			hashes.push(block.hash());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+
+		let authorities = vec![(AuthorityId::default(), 1)];
+		let genesis_hash = client.info().genesis_hash;
+		aux_schema::load_persistent::<Block, _, _>(&*client, genesis_hash, 0, || Ok(authorities.clone())).unwrap();
+
+		// the voter had finalized the block #3 before it was reverted with `--force-finalized`.
+		let authority_set = AuthoritySet::genesis(authorities.clone()).unwrap();
+		let new_set = NewAuthoritySet {
+			canon_hash: hashes[2],
+			canon_number: 3,
+			set_id: 0,
+			authorities: authorities.clone(),
+		};
+		aux_schema::update_authority_set::<Block, _, _>(
+			&authority_set,
+			Some(&new_set),
+			|values| client.insert_aux(values, &[]),
+		).unwrap();
+
+		client.revert(2).unwrap();
+		revert(client.clone()).unwrap();
+
+		let persistent_data = aux_schema::load_persistent::<Block, _, _>(
+			&*client,
+			genesis_hash,
+			0,
+			|| unreachable!("the GRANDPA data is initialized; qed"),
+		).unwrap();
+		let bases = persistent_data.set_state.read().completed_rounds().iter()
+			.map(|round| round.base)
+			.collect::<Vec<_>>();
+		assert_eq!(bases, vec![(hashes[0], 1)]);
+		assert_eq!(persistent_data.authority_set.clone_inner().set_id, 0);
+	}
+}
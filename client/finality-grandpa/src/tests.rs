@@ -1569,6 +1569,7 @@ fn grandpa_environment_respects_voting_rules() {
 			network,
 			voting_rule,
 			metrics: None,
+			justification_sender: link.justification_sender.clone(),
 			_phantom: PhantomData,
 		}
 	};
@@ -586,6 +586,7 @@ mod tests {
 				header,
 				is_new_best: false,
 				retracted: vec![],
+				storage_changes: None,
 			}).unwrap();
 		}
 	}
@@ -0,0 +1,227 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! GRANDPA warp sync proof generation and check.
+//!
+//! A warp sync proof is made of one fragment per authority set change since the requested block,
+//! each holding the header of the block that enacted the change and the justification that
+//! finalized it. The justification is verified against the set known so far, and the
+//! `ScheduledChange` digest of the header gives the next set. The proof ends with a fragment
+//! for the latest finalized block that has a justification.
+//!
+//! This relies on justifications being stored for every block that enacts a set change, which
+//! holds as long as standard changes are scheduled with no delay. Forced changes can't be
+//! proven and make verification fail.
+
+use std::sync::Arc;
+use log::debug;
+
+use sc_client_api::backend::Backend;
+use sc_network::config::{WarpSyncProvider, WarpSyncVerification};
+use sp_blockchain::{Backend as BlockchainBackend, HeaderBackend, Error as ClientError, Result as ClientResult};
+use parity_scale_codec::{Encode, Decode};
+use finality_grandpa::BlockNumberOps;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{NumberFor, Block as BlockT, Header as HeaderT, One},
+};
+use sp_finality_grandpa::{AuthorityList, SetId};
+
+use crate::authorities::{AuthoritySet, SharedAuthoritySet};
+use crate::aux_schema::load_authority_set_changes;
+use crate::import::find_scheduled_change;
+use crate::justification::GrandpaJustification;
+use crate::VoterSet;
+
+/// Maximum size of the fragments of a single warp sync proof. Longer proofs are split, and the
+/// rest is requested starting at the last block covered.
+const MAX_WARP_PROOF_SIZE: usize = 8 * 1024 * 1024;
+
+/// Maximum number of blocks below the latest finalized one searched for a justification to end
+/// the proof with.
+const MAX_JUSTIFICATION_SEARCH: usize = 4096;
+
+/// A block that enacted an authority set change, or the latest finalized block, along with the
+/// justification that finalized it.
+#[derive(Encode, Decode)]
+struct WarpSyncFragment<Block: BlockT> {
+	header: Block::Header,
+	justification: GrandpaJustification<Block>,
+}
+
+/// Proof of the authority set changes since a given block.
+#[derive(Encode, Decode)]
+struct WarpSyncProof<Block: BlockT> {
+	proofs: Vec<WarpSyncFragment<Block>>,
+	/// Whether the last fragment is the latest finalized block known to the prover.
+	is_finished: bool,
+}
+
+/// Warp sync proof provider for serving and verifying network requests.
+pub struct WarpSyncProofProvider<B, Block: BlockT> {
+	backend: Arc<B>,
+	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+}
+
+impl<B, Block: BlockT> WarpSyncProofProvider<B, Block>
+	where B: Backend<Block> + Send + Sync + 'static
+{
+	/// Create new warp sync proof provider using:
+	///
+	/// - backend for accessing blockchain data;
+	/// - the authority set shared with the block import, which is moved to the latest set once
+	///   the last block of a complete proof has been imported along with its state.
+	pub fn new(
+		backend: Arc<B>,
+		authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+	) -> Self {
+		WarpSyncProofProvider { backend, authority_set }
+	}
+
+	fn generate_proof(&self, begin: Block::Hash) -> ClientResult<WarpSyncProof<Block>> {
+		let blockchain = self.backend.blockchain();
+		let begin_number = blockchain.expect_block_number_from_id(&BlockId::Hash(begin))?;
+		let finalized_number = blockchain.info().finalized_number;
+		if begin_number >= finalized_number {
+			return Err(ClientError::Backend("Warp sync proof requested from a non-finalized block".into()))
+		}
+
+		let fragment = |number: NumberFor<Block>| -> ClientResult<Option<WarpSyncFragment<Block>>> {
+			let id = BlockId::Number(number);
+			let justification = match blockchain.justification(id)? {
+				Some(justification) => justification,
+				None => return Ok(None),
+			};
+			let justification = GrandpaJustification::<Block>::decode(&mut &justification[..])
+				.map_err(|_| ClientError::JustificationDecode)?;
+			let header = blockchain.expect_header(id)?;
+			Ok(Some(WarpSyncFragment { header, justification }))
+		};
+
+		let mut proofs = Vec::new();
+		let mut proofs_size = 0;
+		let mut last_number = begin_number;
+		for (_, number) in load_authority_set_changes::<_, NumberFor<Block>>(&*self.backend)? {
+			if number <= begin_number {
+				continue
+			}
+
+			let fragment = fragment(number)?.ok_or_else(|| ClientError::Backend(
+				format!("Missing justification for block #{} that enacted a set change", number)
+			))?;
+			proofs_size += fragment.encode().len();
+			if proofs_size > MAX_WARP_PROOF_SIZE && !proofs.is_empty() {
+				return Ok(WarpSyncProof { proofs, is_finished: false })
+			}
+
+			proofs.push(fragment);
+			last_number = number;
+		}
+
+		// finish with the latest finalized block that has a justification.
+		let mut number = finalized_number;
+		let mut searched = 0;
+		while number > last_number && searched < MAX_JUSTIFICATION_SEARCH {
+			if let Some(fragment) = fragment(number)? {
+				proofs.push(fragment);
+				break
+			}
+			number = number - One::one();
+			searched += 1;
+		}
+
+		if proofs.is_empty() {
+			return Err(ClientError::Backend("No justification to prove finality with".into()))
+		}
+
+		Ok(WarpSyncProof { proofs, is_finished: true })
+	}
+}
+
+impl<B, Block> WarpSyncProvider<Block> for WarpSyncProofProvider<B, Block>
+	where
+		Block: BlockT,
+		NumberFor<Block>: BlockNumberOps,
+		B: Backend<Block> + Send + Sync + 'static,
+{
+	fn generate(&self, begin: Block::Hash) -> Result<Vec<u8>, ClientError> {
+		self.generate_proof(begin).map(|proof| proof.encode())
+	}
+
+	fn verify(
+		&self,
+		proof: &[u8],
+		authorities: Vec<u8>,
+	) -> Result<WarpSyncVerification<Block>, ClientError> {
+		let proof = WarpSyncProof::<Block>::decode(&mut &proof[..])
+			.map_err(|_| ClientError::Backend("Invalid warp sync proof".into()))?;
+		let (mut set_id, mut authorities) = <(SetId, AuthorityList)>::decode(&mut &authorities[..])
+			.map_err(|_| ClientError::InvalidAuthoritiesSet)?;
+
+		let last = proof.proofs.len().checked_sub(1)
+			.ok_or_else(|| ClientError::Backend("Empty warp sync proof".into()))?;
+		for (index, fragment) in proof.proofs.iter().enumerate() {
+			let voters = VoterSet::new(authorities.iter().cloned())
+				.ok_or(ClientError::InvalidAuthoritiesSet)?;
+			let target = (fragment.justification.commit.target_hash, fragment.justification.commit.target_number);
+			if target != (fragment.header.hash(), *fragment.header.number()) {
+				return Err(ClientError::BadJustification("Warp sync proof commit target mismatch".into()))
+			}
+			fragment.justification.verify(set_id, &voters)?;
+
+			match find_scheduled_change::<Block>(&fragment.header) {
+				Some(change) => {
+					set_id += 1;
+					authorities = change.next_authorities;
+				},
+				None if proof.is_finished && index == last => {},
+				None => return Err(ClientError::Backend(
+					format!("Warp sync proof fragment #{} doesn't enact a set change", fragment.header.number())
+				)),
+			}
+		}
+
+		let fragment = proof.proofs.into_iter().last()
+			.expect("proofs have been checked to be non-empty above; qed");
+		if !proof.is_finished {
+			debug!(target: "afg", "Verified warp sync proof up to set {}", set_id);
+			return Ok(WarpSyncVerification::Partial {
+				authorities: (set_id, authorities).encode(),
+				last_hash: fragment.header.hash(),
+			})
+		}
+
+		// the block of the last fragment is about to be imported along with its state. the set
+		// is only applied by the block import once that block has been imported, so that a
+		// failed import leaves the current set untouched.
+		let set = AuthoritySet::new(authorities, set_id, fork_tree::ForkTree::new(), Vec::new())
+			.ok_or(ClientError::InvalidAuthoritiesSet)?;
+		self.authority_set.set_warp_sync_target(fragment.header.hash(), set);
+
+		Ok(WarpSyncVerification::Complete {
+			justification: fragment.justification.encode(),
+			header: fragment.header,
+		})
+	}
+
+	fn current_authorities(&self) -> Vec<u8> {
+		let authority_set = self.authority_set.inner().read();
+		let (set_id, authorities) = authority_set.current();
+		(set_id, authorities).encode()
+	}
+}
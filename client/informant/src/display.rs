@@ -18,11 +18,12 @@ use ansi_term::Colour;
 use sc_client_api::ClientInfo;
 use log::info;
 use sc_network::SyncState;
-use sp_runtime::traits::{Block as BlockT, CheckedDiv, NumberFor, Zero, Saturating};
+use serde_json::json;
+use sp_runtime::traits::{Block as BlockT, CheckedDiv, NumberFor, Zero, Saturating, UniqueSaturatedInto};
 use sc_service::NetworkStatus;
 use std::{convert::{TryFrom, TryInto}, fmt};
 use wasm_timer::Instant;
-use crate::OutputFormat;
+use crate::{json_block, print_json_event, OutputFormat};
 
 /// State of the informant display system.
 ///
@@ -57,7 +58,7 @@ impl<B: BlockT> InformantDisplay<B> {
 		}
 	}
 
-	/// Displays the informant by calling `info!`.
+	/// Displays the informant by calling `info!`, or by printing a `status` JSON event.
 	pub fn display(&mut self, info: &ClientInfo<B>, net_status: NetworkStatus<B>) {
 		let best_number = info.chain.best_number;
 		let best_hash = info.chain.best_hash;
@@ -67,11 +68,41 @@ impl<B: BlockT> InformantDisplay<B> {
 		self.last_update = Instant::now();
 		self.last_number = Some(best_number);
 
+		if self.format == OutputFormat::Json {
+			let state = match (net_status.sync_state, net_status.best_seen_block) {
+				(SyncState::Idle, _) => "idle",
+				(SyncState::Downloading, None) => "preparing",
+				(SyncState::Downloading, Some(_)) => "syncing",
+			};
+			let best_seen: Option<u64> = net_status.best_seen_block.map(UniqueSaturatedInto::unique_saturated_into);
+			let gap = net_status.gap_sync.map(|gap| json!({
+				"current": UniqueSaturatedInto::<u64>::unique_saturated_into(gap.current),
+				"end": UniqueSaturatedInto::<u64>::unique_saturated_into(gap.end),
+			}));
+			print_json_event("status", json!({
+				"state": state,
+				"blocks_per_sec": speed,
+				"target": best_seen,
+				"backfilling": gap,
+				"peers": num_connected_peers,
+				"best": json_block(best_number, &best_hash),
+				"finalized": json_block(finalized_number, &info.chain.finalized_hash),
+				"download_bytes_per_sec": net_status.average_download_per_sec,
+				"upload_bytes_per_sec": net_status.average_upload_per_sec,
+			}));
+			return
+		}
+
+		let speed = speed.map_or_else(String::new, |speed| format!(" {:4.1} bps", speed));
 		let (status, target) = match (net_status.sync_state, net_status.best_seen_block) {
 			(SyncState::Idle, _) => ("💤 Idle".into(), "".into()),
 			(SyncState::Downloading, None) => (format!("⚙️  Preparing{}", speed), "".into()),
 			(SyncState::Downloading, Some(n)) => (format!("⚙️  Syncing{}", speed), format!(", target=#{}", n)),
 		};
+		let target = match net_status.gap_sync {
+			Some(gap) => format!("{}, backfilling #{}/#{}", target, gap.current, gap.end),
+			None => target,
+		};
 
 		if self.format == OutputFormat::Coloured {
 			info!(
@@ -105,13 +136,13 @@ impl<B: BlockT> InformantDisplay<B> {
 	}
 }
 
-/// Calculates `(best_number - last_number) / (now - last_update)` and returns a `String`
-/// representing the speed of import.
+/// Calculates `(best_number - last_number) / (now - last_update)`, the speed of import in blocks
+/// per second, or `None` if `display` has never been called.
 fn speed<B: BlockT>(
 	best_number: NumberFor<B>,
 	last_number: Option<NumberFor<B>>,
 	last_update: Instant
-) -> String {
+) -> Option<f64> {
 	// Number of milliseconds elapsed since last time.
 	let elapsed_ms = {
 		let elapsed = last_update.elapsed();
@@ -122,7 +153,7 @@ fn speed<B: BlockT>(
 
 	// Number of blocks that have been imported since last time.
 	let diff = match last_number {
-		None => return String::new(),
+		None => return None,
 		Some(n) => best_number.saturating_sub(n)
 	};
 
@@ -131,7 +162,7 @@ fn speed<B: BlockT>(
 		// do the math and turn it into a `f64`.
 		let speed = diff.saturating_mul(10_000).checked_div(u128::from(elapsed_ms))
 			.map_or(0.0, |s| s as f64) / 10.0;
-		format!(" {:4.1} bps", speed)
+		Some(speed)
 
 	} else {
 		// If the number of blocks can't be converted to a regular integer, then we need a more
@@ -143,7 +174,7 @@ fn speed<B: BlockT>(
 
 		let speed = diff.saturating_mul(one_thousand).checked_div(&elapsed)
 			.unwrap_or_else(Zero::zero);
-		format!(" {} bps", speed)
+		Some(UniqueSaturatedInto::<u128>::unique_saturated_into(speed) as f64)
 	}
 }
 
@@ -22,17 +22,48 @@ use ansi_term::Colour;
 use sc_client_api::{BlockchainEvents, UsageProvider};
 use futures::prelude::*;
 use log::{info, warn, trace};
-use sp_runtime::traits::Header;
+use serde_json::{json, Value};
+use sp_runtime::traits::{Header, UniqueSaturatedInto};
 use sc_service::AbstractService;
-use std::time::Duration;
+use std::{fmt::Debug, io::Write, time::Duration};
 
 mod display;
 
 /// The format to print telemetry output in.
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
+	/// Human-readable lines, highlighted with colours.
 	Coloured,
+	/// Human-readable lines.
 	Plain,
+	/// One JSON object per line on the standard output, instead of the human-readable lines.
+	///
+	/// Every object has an `event` field naming the event and a `timestamp` field with the
+	/// number of milliseconds since the UNIX epoch.
+	Json,
+}
+
+/// Prints an event as a JSON line on the standard output.
+///
+/// `fields` should be a JSON object, whose fields are added to the `event` and `timestamp` ones.
+pub fn print_json_event(event: &str, fields: Value) {
+	let timestamp = wasm_timer::SystemTime::now()
+		.duration_since(wasm_timer::UNIX_EPOCH)
+		.map(|elapsed| elapsed.as_millis() as u64)
+		.unwrap_or(0);
+	let mut object = json!({ "event": event, "timestamp": timestamp });
+	if let (Some(object), Value::Object(fields)) = (object.as_object_mut(), fields) {
+		object.extend(fields);
+	}
+
+	let stdout = std::io::stdout();
+	let mut stdout = stdout.lock();
+	let _ = writeln!(stdout, "{}", object).and_then(|_| stdout.flush());
+}
+
+/// Returns the JSON representation of a block, out of its number and its hash.
+pub(crate) fn json_block<N: UniqueSaturatedInto<u64>, H: Debug>(number: N, hash: &H) -> Value {
+	json!({ "number": number.unique_saturated_into(), "hash": format!("{:?}", hash) })
 }
 
 /// Creates an informant in the form of a `Future` that must be polled regularly.
@@ -81,6 +112,12 @@ pub fn build(service: &impl AbstractService, format: OutputFormat) -> impl futur
 				);
 
 				match maybe_ancestor {
+					Ok(ref ancestor) if ancestor.hash != *last_hash && format == OutputFormat::Json =>
+						print_json_event("reorg", json!({
+							"from": json_block(*last_num, last_hash),
+							"to": json_block(*n.header.number(), &n.hash),
+							"common_ancestor": json_block(ancestor.number, &ancestor.hash),
+						})),
 					Ok(ref ancestor) if ancestor.hash != *last_hash => info!(
 						"♻️  Reorg on #{},{} to #{},{}, common ancestor #{},{}",
 						Colour::Red.bold().paint(format!("{}", last_num)), last_hash,
@@ -97,7 +134,13 @@ pub fn build(service: &impl AbstractService, format: OutputFormat) -> impl futur
 			last_best = Some((n.header.number().clone(), n.hash.clone()));
 		}
 
-		info!(target: "substrate", "✨ Imported #{} ({})", Colour::White.bold().paint(format!("{}", n.header.number())), n.hash);
+		if format == OutputFormat::Json {
+			let mut block = json_block(*n.header.number(), &n.hash);
+			block["is_new_best"] = n.is_new_best.into();
+			print_json_event("imported", block);
+		} else {
+			info!(target: "substrate", "✨ Imported #{} ({})", Colour::White.bold().paint(format!("{}", n.header.number())), n.hash);
+		}
 		future::ready(())
 	});
 
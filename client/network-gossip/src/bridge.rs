@@ -15,7 +15,9 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{Network, Validator};
-use crate::state_machine::{ConsensusGossip, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL};
+use crate::state_machine::{
+	CacheOccupancy, ConsensusGossip, GossipConfig, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL,
+};
 
 use sc_network::{Event, ReputationChange};
 
@@ -66,11 +68,14 @@ impl<B: BlockT> Unpin for GossipEngine<B> {}
 
 impl<B: BlockT> GossipEngine<B> {
 	/// Create a new instance.
+	///
+	/// The caches of the engine are configured with the entry of `config` for `engine_id`.
 	pub fn new<N: Network<B> + Send + Clone + 'static>(
 		network: N,
 		engine_id: ConsensusEngineId,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
 		validator: Arc<dyn Validator<B>>,
+		config: &GossipConfig,
 	) -> Self where B: 'static {
 		// We grab the event stream before registering the notifications protocol, otherwise we
 		// might miss events.
@@ -78,7 +83,7 @@ impl<B: BlockT> GossipEngine<B> {
 		network.register_notifications_protocol(engine_id, protocol_name.into());
 
 		GossipEngine {
-			state_machine: ConsensusGossip::new(validator, engine_id),
+			state_machine: ConsensusGossip::new(validator, engine_id, config.cache_config(engine_id).clone()),
 			network: Box::new(network),
 			periodic_maintenance_interval: futures_timer::Delay::new(PERIODIC_MAINTENANCE_INTERVAL),
 			engine_id,
@@ -93,6 +98,11 @@ impl<B: BlockT> GossipEngine<B> {
 		self.network.report_peer(who, reputation);
 	}
 
+	/// Returns the current occupancy of the caches, for instance to tune their configuration.
+	pub fn cache_occupancy(&self) -> CacheOccupancy {
+		self.state_machine.cache_occupancy()
+	}
+
 	/// Registers a message without propagating it to any peers. The message
 	/// becomes available to new peers or when the service is asked to gossip
 	/// the message's topic. No validation is performed on the message, if the
@@ -210,6 +220,7 @@ impl<B: BlockT> Future for GossipEngine<B> {
 
 								this.forwarding_state = ForwardingState::Busy(to_forward.into());
 							},
+							Event::NotificationHandshakeReceived { .. } => {},
 							Event::Dht(_) => {}
 						}
 						// The network event stream closed. Do the same for [`GossipValidator`].
@@ -364,6 +375,7 @@ mod tests {
 			[1, 2, 3, 4],
 			"my_protocol".as_bytes(),
 			Arc::new(AllowAll{}),
+			&Default::default(),
 		);
 
 		// Drop network event stream sender side.
@@ -392,6 +404,7 @@ mod tests {
 			engine_id.clone(),
 			"my_protocol".as_bytes(),
 			Arc::new(AllowAll{}),
+			&Default::default(),
 		);
 
 		let mut event_sender = network.inner.lock()
@@ -527,6 +540,7 @@ mod tests {
 				engine_id.clone(),
 				"my_protocol".as_bytes(),
 				Arc::new(TestValidator{}),
+				&Default::default(),
 			);
 
 			// Create channels.
@@ -34,7 +34,8 @@
 //!   already implemented on `sc_network::NetworkService`.
 //! - Implement the `Validator` trait. See the section below.
 //! - Decide on a `ConsensusEngineId`. Each gossiping protocol should have a different one.
-//! - Build a `GossipEngine` using these three elements.
+//! - Build a `GossipEngine` using these three elements, along with a `GossipConfig` setting the
+//!   size and expiration of its caches.
 //! - Use the methods of the `GossipEngine` in order to send out messages and receive incoming
 //!   messages.
 //!
@@ -55,7 +56,7 @@
 //! used to inform peers of a current view of protocol state.
 
 pub use self::bridge::GossipEngine;
-pub use self::state_machine::TopicNotification;
+pub use self::state_machine::{CacheOccupancy, GossipCacheConfig, GossipConfig, TopicNotification};
 pub use self::validator::{DiscardAll, MessageIntent, Validator, ValidatorContext, ValidationResult};
 
 use futures::prelude::*;
@@ -81,6 +82,13 @@ pub trait Network<B: BlockT> {
 	/// Send a notification to a peer.
 	fn write_notification(&self, who: PeerId, engine_id: ConsensusEngineId, message: Vec<u8>);
 
+	/// Send the same notification to several peers.
+	fn write_notification_group(&self, peers: Vec<PeerId>, engine_id: ConsensusEngineId, message: Vec<u8>) {
+		for who in peers {
+			self.write_notification(who, engine_id, message.clone());
+		}
+	}
+
 	/// Registers a notifications protocol.
 	///
 	/// See the documentation of [`NetworkService:register_notifications_protocol`] for more information.
@@ -114,6 +122,11 @@ impl<B: BlockT, H: ExHashT> Network<B> for Arc<NetworkService<B, H>> {
 		NetworkService::write_notification(self, who, engine_id, message)
 	}
 
+	fn write_notification_group(&self, peers: Vec<PeerId>, engine_id: ConsensusEngineId, message: Vec<u8>) {
+		// The message is sent as soon as `send` is called, and we don't need the results.
+		let _ = NetworkService::notification_sender_group(self, peers, engine_id).send(message);
+	}
+
 	fn register_notifications_protocol(
 		&self,
 		engine_id: ConsensusEngineId,
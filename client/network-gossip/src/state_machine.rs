@@ -18,7 +18,7 @@
 
 use crate::{Network, MessageIntent, Validator, ValidatorContext, ValidationResult};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::iter;
 use std::time;
@@ -33,6 +33,8 @@ use wasm_timer::Instant;
 // FIXME: Add additional spam/DoS attack protection: https://github.com/paritytech/substrate/issues/1115
 const KNOWN_MESSAGES_CACHE_SIZE: usize = 4096;
 
+const PEER_KNOWN_MESSAGES_CACHE_SIZE: usize = 4096;
+
 const REBROADCAST_INTERVAL: time::Duration = time::Duration::from_secs(30);
 
 pub(crate) const PERIODIC_MAINTENANCE_INTERVAL: time::Duration = time::Duration::from_millis(1100);
@@ -46,7 +48,57 @@ mod rep {
 }
 
 struct PeerConsensus<H> {
-	known_messages: HashSet<H>,
+	known_messages: LruCache<H, ()>,
+}
+
+/// Sizes and expiration of the caches of a gossip engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GossipCacheConfig {
+	/// Number of hashes of messages we have seen remembered, to ignore messages we already know.
+	pub known_messages: usize,
+	/// Number of hashes of messages each peer is known to have remembered, to avoid sending
+	/// peers messages they already know.
+	pub peer_known_messages: usize,
+	/// Time after which the hash of a message we have seen is forgotten, even if the cache isn't
+	/// full. If `None`, hashes are only forgotten when the cache is full.
+	pub expiration: Option<time::Duration>,
+}
+
+impl Default for GossipCacheConfig {
+	fn default() -> Self {
+		GossipCacheConfig {
+			known_messages: KNOWN_MESSAGES_CACHE_SIZE,
+			peer_known_messages: PEER_KNOWN_MESSAGES_CACHE_SIZE,
+			expiration: None,
+		}
+	}
+}
+
+/// Cache configuration of gossip engines, with overrides for specific protocols.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GossipConfig {
+	/// Configuration of the protocols without an override.
+	pub default: GossipCacheConfig,
+	/// Configuration of specific protocols.
+	pub overrides: HashMap<ConsensusEngineId, GossipCacheConfig>,
+}
+
+impl GossipConfig {
+	/// Returns the cache configuration of the given protocol.
+	pub fn cache_config(&self, engine_id: ConsensusEngineId) -> &GossipCacheConfig {
+		self.overrides.get(&engine_id).unwrap_or(&self.default)
+	}
+}
+
+/// Current occupancy of the caches of a gossip engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheOccupancy {
+	/// Number of hashes of messages we have seen.
+	pub known_messages: usize,
+	/// Number of messages kept to be sent to peers.
+	pub messages: usize,
+	/// Number of hashes of messages known by each peer.
+	pub peer_known_messages: HashMap<PeerId, usize>,
 }
 
 /// Topic stream message with sender.
@@ -111,8 +163,9 @@ fn propagate<'a, B: BlockT, I>(
 {
 	let mut message_allowed = validator.message_allowed();
 
-	for (id, ref mut peer) in peers.iter_mut() {
-		for (message_hash, topic, message) in messages.clone() {
+	for (message_hash, topic, message) in messages {
+		let mut targets = Vec::new();
+		for (id, ref mut peer) in peers.iter_mut() {
 			let intent = match intent {
 				MessageIntent::Broadcast { .. } =>
 					if peer.known_messages.contains(&message_hash) {
@@ -135,10 +188,14 @@ fn propagate<'a, B: BlockT, I>(
 				continue;
 			}
 
-			peer.known_messages.insert(message_hash.clone());
+			peer.known_messages.put(message_hash.clone(), ());
 
 			trace!(target: "gossip", "Propagating to {}: {:?}", id, message);
-			network.write_notification(id.clone(), engine_id, message.clone());
+			targets.push(id.clone());
+		}
+
+		if !targets.is_empty() {
+			network.write_notification_group(targets, engine_id, message.clone());
 		}
 	}
 }
@@ -147,22 +204,29 @@ fn propagate<'a, B: BlockT, I>(
 pub struct ConsensusGossip<B: BlockT> {
 	peers: HashMap<PeerId, PeerConsensus<B::Hash>>,
 	messages: Vec<MessageEntry<B>>,
-	known_messages: LruCache<B::Hash, ()>,
+	/// Hashes of the messages we have seen, along with when we first saw them.
+	known_messages: LruCache<B::Hash, Instant>,
 	engine_id: ConsensusEngineId,
 	validator: Arc<dyn Validator<B>>,
 	next_broadcast: Instant,
+	config: GossipCacheConfig,
 }
 
 impl<B: BlockT> ConsensusGossip<B> {
-	/// Create a new instance using the given validator.
-	pub fn new(validator: Arc<dyn Validator<B>>, engine_id: ConsensusEngineId) -> Self {
+	/// Create a new instance using the given validator and cache configuration.
+	pub fn new(
+		validator: Arc<dyn Validator<B>>,
+		engine_id: ConsensusEngineId,
+		config: GossipCacheConfig,
+	) -> Self {
 		ConsensusGossip {
 			peers: HashMap::new(),
 			messages: Default::default(),
-			known_messages: LruCache::new(KNOWN_MESSAGES_CACHE_SIZE),
+			known_messages: LruCache::new(config.known_messages),
 			engine_id,
 			validator,
 			next_broadcast: Instant::now() + REBROADCAST_INTERVAL,
+			config,
 		}
 	}
 
@@ -175,7 +239,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 
 		trace!(target:"gossip", "Registering {:?} {}", role, who);
 		self.peers.insert(who.clone(), PeerConsensus {
-			known_messages: HashSet::new(),
+			known_messages: LruCache::new(self.config.peer_known_messages),
 		});
 
 		let validator = self.validator.clone();
@@ -190,7 +254,8 @@ impl<B: BlockT> ConsensusGossip<B> {
 		message: Vec<u8>,
 		sender: Option<PeerId>,
 	) {
-		if self.known_messages.put(message_hash.clone(), ()).is_none() {
+		if !self.known_messages.contains(&message_hash) {
+			self.known_messages.put(message_hash.clone(), Instant::now());
 			self.messages.push(MessageEntry {
 				message_hash,
 				topic,
@@ -259,6 +324,17 @@ impl<B: BlockT> ConsensusGossip<B> {
 		let mut message_expired = self.validator.message_expired();
 		self.messages.retain(|entry| !message_expired(entry.topic, &entry.message));
 
+		if let Some(expiration) = self.config.expiration {
+			let now = Instant::now();
+			let expired = known_messages.iter()
+				.filter(|(_, seen)| now.duration_since(**seen) >= expiration)
+				.map(|(hash, _)| hash.clone())
+				.collect::<Vec<_>>();
+			for hash in expired {
+				known_messages.pop(&hash);
+			}
+		}
+
 		trace!(target: "gossip", "Cleaned up {} stale messages, {} left ({} known)",
 			before - self.messages.len(),
 			self.messages.len(),
@@ -266,7 +342,24 @@ impl<B: BlockT> ConsensusGossip<B> {
 		);
 
 		for (_, ref mut peer) in self.peers.iter_mut() {
-			peer.known_messages.retain(|h| known_messages.contains(h));
+			let forgotten = peer.known_messages.iter()
+				.filter(|(hash, _)| !known_messages.contains(hash))
+				.map(|(hash, _)| hash.clone())
+				.collect::<Vec<_>>();
+			for hash in forgotten {
+				peer.known_messages.pop(&hash);
+			}
+		}
+	}
+
+	/// Returns the current occupancy of the caches.
+	pub fn cache_occupancy(&self) -> CacheOccupancy {
+		CacheOccupancy {
+			known_messages: self.known_messages.len(),
+			messages: self.messages.len(),
+			peer_known_messages: self.peers.iter()
+				.map(|(who, peer)| (who.clone(), peer.known_messages.len()))
+				.collect(),
 		}
 	}
 
@@ -326,7 +419,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 			};
 
 			network.report_peer(who.clone(), rep::GOSSIP_SUCCESS);
-			peer.known_messages.insert(message_hash);
+			peer.known_messages.put(message_hash, ());
 			to_forward.push((topic, TopicNotification {
 				message: message.clone(),
 				sender: Some(who.clone())
@@ -371,7 +464,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 					continue;
 				}
 
-				peer.known_messages.insert(entry.message_hash.clone());
+				peer.known_messages.put(entry.message_hash.clone(), ());
 
 				trace!(target: "gossip", "Sending topic message to {}: {:?}", who, entry.message);
 				network.write_notification(who.clone(), self.engine_id, entry.message.clone());
@@ -410,7 +503,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 
 		trace!(target: "gossip", "Sending direct to {}: {:?}", who, message);
 
-		peer.known_messages.insert(message_hash);
+		peer.known_messages.put(message_hash, ());
 		network.write_notification(who.clone(), self.engine_id, message);
 	}
 }
@@ -427,7 +520,7 @@ mod tests {
 
 	macro_rules! push_msg {
 		($consensus:expr, $topic:expr, $hash: expr, $m:expr) => {
-			if $consensus.known_messages.put($hash, ()).is_none() {
+			if $consensus.known_messages.put($hash, Instant::now()).is_none() {
 				$consensus.messages.push(MessageEntry {
 					message_hash: $hash,
 					topic: $topic,
@@ -520,7 +613,7 @@ mod tests {
 
 		let prev_hash = H256::random();
 		let best_hash = H256::random();
-		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0]);
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], Default::default());
 		let m1_hash = H256::random();
 		let m2_hash = H256::random();
 		let m1 = vec![1, 2, 3];
@@ -528,8 +621,8 @@ mod tests {
 
 		push_msg!(consensus, prev_hash, m1_hash, m1);
 		push_msg!(consensus, best_hash, m2_hash, m2);
-		consensus.known_messages.put(m1_hash, ());
-		consensus.known_messages.put(m2_hash, ());
+		consensus.known_messages.put(m1_hash, Instant::now());
+		consensus.known_messages.put(m2_hash, Instant::now());
 
 		consensus.collect_garbage();
 		assert_eq!(consensus.messages.len(), 2);
@@ -547,7 +640,7 @@ mod tests {
 
 	#[test]
 	fn message_stream_include_those_sent_before_asking() {
-		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0]);
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], Default::default());
 
 		// Register message.
 		let message = vec![4, 5, 6];
@@ -562,7 +655,7 @@ mod tests {
 
 	#[test]
 	fn can_keep_multiple_messages_per_topic() {
-		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0]);
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], Default::default());
 
 		let topic = [1; 32].into();
 		let msg_a = vec![1, 2, 3];
@@ -576,7 +669,7 @@ mod tests {
 
 	#[test]
 	fn peer_is_removed_on_disconnect() {
-		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0]);
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], Default::default());
 
 		let mut network = NoOpNetwork::default();
 
@@ -593,6 +686,7 @@ mod tests {
 		let to_forward = ConsensusGossip::<Block>::new(
 			Arc::new(DiscardAll),
 			[0, 0, 0, 0],
+			Default::default(),
 		).on_incoming(
 			&mut NoOpNetwork::default(),
 			PeerId::random(),
@@ -613,6 +707,7 @@ mod tests {
 		let to_forward = ConsensusGossip::<Block>::new(
 			Arc::new(AllowAll),
 			[0, 0, 0, 0],
+			Default::default(),
 		).on_incoming(
 			&mut network,
 			// Unregistered peer.
@@ -626,4 +721,37 @@ mod tests {
 			to_forward,
 		);
 	}
+
+	#[test]
+	fn known_messages_expire() {
+		let config = GossipCacheConfig {
+			expiration: Some(time::Duration::from_secs(0)),
+			..Default::default()
+		};
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], config);
+
+		consensus.register_message(H256::random(), vec![1, 2, 3]);
+		assert_eq!(consensus.cache_occupancy().known_messages, 1);
+
+		consensus.collect_garbage();
+		let occupancy = consensus.cache_occupancy();
+		assert_eq!(occupancy.known_messages, 0);
+		// messages are only pruned by the validator.
+		assert_eq!(occupancy.messages, 1);
+	}
+
+	#[test]
+	fn peer_known_messages_are_bounded() {
+		let config = GossipCacheConfig { peer_known_messages: 2, ..Default::default() };
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), [0, 0, 0, 0], config);
+		let mut network = NoOpNetwork::default();
+
+		let peer_id = PeerId::random();
+		consensus.new_peer(&mut network, peer_id.clone(), ObservedRole::Full);
+		consensus.on_incoming(&mut network, peer_id.clone(), vec![vec![1], vec![2], vec![3]]);
+
+		let occupancy = consensus.cache_occupancy();
+		assert_eq!(occupancy.known_messages, 3);
+		assert_eq!(occupancy.peer_known_messages.get(&peer_id), Some(&2));
+	}
 }
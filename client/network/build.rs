@@ -1,7 +1,10 @@
 const PROTOS: &[&str] = &[
+	"src/schema/bitswap.v1.2.0.proto",
 	"src/schema/api.v1.proto",
 	"src/schema/finality.v1.proto",
-	"src/schema/light.v1.proto"
+	"src/schema/light.v1.proto",
+	"src/schema/state.v1.proto",
+	"src/schema/warp.v1.proto"
 ];
 
 fn main() {
@@ -15,9 +15,9 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
-	config::{ProtocolId, Role}, block_requests, light_client_handler, finality_requests,
-	debug_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
-	protocol::{message::{self, Roles}, CustomMessageOutcome, Protocol},
+	config::{ProtocolId, Role}, bitswap::Bitswap, block_requests, light_client_handler, finality_requests,
+	state_requests, warp_requests, debug_info, discovery::{DiscoveryBehaviour, DiscoveryConfig, DiscoveryOut},
+	protocol::{message::{self, Roles}, CustomMessageOutcome, NotificationsPriority, Protocol},
 	Event, ObservedRole, DhtEvent, ExHashT,
 };
 
@@ -25,7 +25,7 @@ use codec::Encode as _;
 use libp2p::NetworkBehaviour;
 use libp2p::core::{Multiaddr, PeerId, PublicKey};
 use libp2p::kad::record;
-use libp2p::swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters};
+use libp2p::swarm::{NetworkBehaviourAction, NetworkBehaviourEventProcess, PollParameters, toggle::Toggle};
 use log::debug;
 use sp_consensus::{BlockOrigin, import_queue::{IncomingBlock, Origin}};
 use sp_runtime::{traits::{Block as BlockT, NumberFor}, ConsensusEngineId, Justification};
@@ -46,8 +46,14 @@ pub struct Behaviour<B: BlockT, H: ExHashT> {
 	block_requests: block_requests::BlockRequests<B>,
 	/// Finality proof request handling.
 	finality_proof_requests: finality_requests::FinalityProofRequests<B>,
+	/// Warp sync proof request handling.
+	warp_proof_requests: warp_requests::WarpProofRequests<B>,
+	/// State request handling.
+	state_requests: state_requests::StateRequests<B>,
 	/// Light client request handling.
 	light_client_handler: light_client_handler::LightClientHandler<B>,
+	/// Serving of the indexed data to IPFS clients, if enabled.
+	bitswap: Toggle<Bitswap>,
 
 	/// Queue of events to produce for the outside.
 	#[behaviour(ignore)]
@@ -108,7 +114,10 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 		local_public_key: PublicKey,
 		block_requests: block_requests::BlockRequests<B>,
 		finality_proof_requests: finality_requests::FinalityProofRequests<B>,
+		warp_proof_requests: warp_requests::WarpProofRequests<B>,
+		state_requests: state_requests::StateRequests<B>,
 		light_client_handler: light_client_handler::LightClientHandler<B>,
+		bitswap: Option<Bitswap>,
 		disco_config: DiscoveryConfig,
 	) -> Self {
 		Behaviour {
@@ -117,7 +126,10 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 			discovery: disco_config.finish(),
 			block_requests,
 			finality_proof_requests,
+			warp_proof_requests,
+			state_requests,
 			light_client_handler,
+			bitswap: bitswap.into(),
 			events: VecDeque::new(),
 			role,
 		}
@@ -166,16 +178,27 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 	///
 	/// You are very strongly encouraged to call this method very early on. Any connection open
 	/// will retain the protocols that were registered then, and not any new one.
+	///
+	/// See [`NotificationsPriority`] for the meaning of `priority`. If `acknowledged` is true,
+	/// the delivery of the notifications sent on this protocol can be confirmed.
 	pub fn register_notifications_protocol(
 		&mut self,
 		engine_id: ConsensusEngineId,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
+		priority: NotificationsPriority,
+		acknowledged: bool,
 	) {
 		// This is the message that we will send to the remote as part of the initial handshake.
 		// At the moment, we force this to be an encoded `Roles`.
 		let handshake_message = Roles::from(&self.role).encode();
 
-		let list = self.substrate.register_notifications_protocol(engine_id, protocol_name, handshake_message);
+		let list = self.substrate.register_notifications_protocol(
+			engine_id,
+			protocol_name,
+			handshake_message,
+			priority,
+			acknowledged,
+		);
 		for (remote, roles) in list {
 			let role = reported_roles_to_observed_role(&self.role, remote, roles);
 			let ev = Event::NotificationStreamOpened {
@@ -203,8 +226,8 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 	}
 
 	/// Starts putting a record into DHT. Will later produce either a `ValuePut` or a `ValuePutFailed` event.
-	pub fn put_value(&mut self, key: record::Key, value: Vec<u8>) {
-		self.discovery.put_value(key, value);
+	pub fn put_value(&mut self, key: record::Key, value: Vec<u8>, ttl: Option<Duration>) {
+		self.discovery.put_value(key, value, ttl);
 	}
 
 	/// Issue a light client request.
@@ -216,7 +239,7 @@ impl<B: BlockT, H: ExHashT> Behaviour<B, H> {
 fn reported_roles_to_observed_role(local_role: &Role, remote: &PeerId, roles: Roles) -> ObservedRole {
 	if roles.is_authority() {
 		match local_role {
-			Role::Authority { sentry_nodes }
+			Role::Authority { sentry_nodes, .. }
 				if sentry_nodes.iter().any(|s| s.peer_id == *remote) => ObservedRole::OurSentry,
 			Role::Sentry { validators }
 				if validators.iter().any(|s| s.peer_id == *remote) => ObservedRole::OurGuardedAuthority,
@@ -272,6 +295,12 @@ Behaviour<B, H> {
 			CustomMessageOutcome::FinalityProofRequest { target, block_hash, request } => {
 				self.finality_proof_requests.send_request(&target, block_hash, request);
 			},
+			CustomMessageOutcome::WarpProofRequest { target, begin } => {
+				self.warp_proof_requests.send_request(&target, begin);
+			},
+			CustomMessageOutcome::StateRequest { target, block_hash, start } => {
+				self.state_requests.send_request(&target, block_hash, start);
+			},
 			CustomMessageOutcome::NotificationStreamOpened { remote, protocols, roles } => {
 				let role = reported_roles_to_observed_role(&self.role, &remote, roles);
 				for engine_id in protocols {
@@ -289,6 +318,10 @@ Behaviour<B, H> {
 						engine_id,
 					}));
 				},
+			CustomMessageOutcome::NotificationHandshakeReceived { remote, engine_id, handshake } => {
+				let ev = Event::NotificationHandshakeReceived { remote, engine_id, handshake: handshake.into() };
+				self.events.push_back(BehaviourOut::Event(ev));
+			},
 			CustomMessageOutcome::NotificationsReceived { remote, messages } => {
 				let ev = Event::NotificationsReceived { remote, messages };
 				self.events.push_back(BehaviourOut::Event(ev));
@@ -355,6 +388,34 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<finality_requests::Even
 	}
 }
 
+impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<warp_requests::Event<B>> for Behaviour<B, H> {
+	fn inject_event(&mut self, event: warp_requests::Event<B>) {
+		match event {
+			warp_requests::Event::Response { peer, begin: _, proof } => {
+				let ev = self.substrate.on_warp_proof_response(peer, proof);
+				self.inject_event(ev);
+			}
+			warp_requests::Event::RequestFailed { peer, .. } => {
+				self.substrate.on_warp_sync_request_failed(&peer);
+			}
+		}
+	}
+}
+
+impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<state_requests::Event<B>> for Behaviour<B, H> {
+	fn inject_event(&mut self, event: state_requests::Event<B>) {
+		match event {
+			state_requests::Event::Response { peer, block_hash: _, response } => {
+				let ev = self.substrate.on_state_response(peer, response);
+				self.inject_event(ev);
+			}
+			state_requests::Event::RequestFailed { peer, .. } => {
+				self.substrate.on_warp_sync_request_failed(&peer);
+			}
+		}
+	}
+}
+
 impl<B: BlockT, H: ExHashT> NetworkBehaviourEventProcess<debug_info::DebugInfoEvent>
 	for Behaviour<B, H> {
 	fn inject_event(&mut self, event: debug_info::DebugInfoEvent) {
@@ -0,0 +1,363 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `NetworkBehaviour` implementation which serves blocks over the Bitswap protocol.
+//!
+//! IPFS clients fetch blocks by sending us their want-list, made of CIDs, on a substream. Each
+//! block is looked up in the data written by the runtime through offchain indexing, using the
+//! digest of the CID as key, and is only sent back if it hashes to that digest. Responses are
+//! sent on a substream of our own, as the protocol expects. Only the serving side of the
+//! protocol is implemented: we never ask for blocks.
+
+use crate::{
+	chain::IndexedData,
+	schema::bitswap::{
+		Message as BitswapMessage,
+		message::{Block as MessageBlock, BlockPresence, BlockPresenceType, wantlist::WantType},
+	},
+	traffic_dump::{Direction, TrafficDump},
+};
+use futures::{future::BoxFuture, prelude::*};
+use libp2p::{
+	core::{
+		Multiaddr,
+		PeerId,
+		connection::ConnectionId,
+		upgrade::{InboundUpgrade, OutboundUpgrade, ReadOneError, UpgradeInfo},
+		upgrade::{read_one, write_one},
+	},
+	swarm::{
+		NetworkBehaviour,
+		NetworkBehaviourAction,
+		NotifyHandler,
+		OneShotHandler,
+		OneShotHandlerConfig,
+		PollParameters,
+		SubstreamProtocol,
+	},
+};
+use log::{debug, trace};
+use prost::Message;
+use sp_core::hashing::{blake2_256, sha2_256};
+use std::{
+	collections::VecDeque,
+	io,
+	iter,
+	sync::Arc,
+	task::{Context, Poll},
+};
+use unsigned_varint::{decode, encode};
+use void::Void;
+
+/// Name of the protocol.
+const PROTOCOL_NAME: &[u8] = b"/ipfs/bitswap/1.2.0";
+
+/// Max. size of the messages we accept.
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// Max. number of blocks answered for a single want-list. The entries after that are ignored.
+const MAX_WANTED_BLOCKS: usize = 16;
+
+/// Multicodec of the DAG-PB codec, implied by version 0 CIDs.
+const DAG_PB: u64 = 0x70;
+
+/// Multihash code of SHA2-256.
+const SHA2_256: u64 = 0x12;
+
+/// Multihash code of BLAKE2b-256.
+const BLAKE2B_256: u64 = 0xb220;
+
+/// Content identifier of a block, as found in want-lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cid {
+	version: u64,
+	codec: u64,
+	/// Multihash code of the hash function.
+	hash: u64,
+	digest: Vec<u8>,
+}
+
+impl Cid {
+	/// Parses a binary CID. Returns `None` if it is malformed.
+	fn parse(bytes: &[u8]) -> Option<Cid> {
+		// version 0 CIDs are bare SHA2-256 multihashes.
+		if bytes.len() == 34 && bytes[0] == SHA2_256 as u8 && bytes[1] == 32 {
+			return Some(Cid { version: 0, codec: DAG_PB, hash: SHA2_256, digest: bytes[2..].to_vec() })
+		}
+
+		let (version, rest) = decode::u64(bytes).ok()?;
+		if version != 1 {
+			return None
+		}
+		let (codec, rest) = decode::u64(rest).ok()?;
+		let (hash, rest) = decode::u64(rest).ok()?;
+		let (len, digest) = decode::usize(rest).ok()?;
+		if digest.len() != len {
+			return None
+		}
+
+		Some(Cid { version, codec, hash, digest: digest.to_vec() })
+	}
+
+	/// Version, codec, hash function and digest length of the CID, as sent along with a block
+	/// so that the remote can rebuild the CID.
+	fn prefix(&self) -> Vec<u8> {
+		let mut prefix = Vec::new();
+		for n in &[self.version, self.codec, self.hash, self.digest.len() as u64] {
+			prefix.extend_from_slice(encode::u64(*n, &mut encode::u64_buffer()));
+		}
+		prefix
+	}
+
+	/// Returns true if `data` hashes to the digest of the CID.
+	fn matches(&self, data: &[u8]) -> bool {
+		match self.hash {
+			SHA2_256 => sha2_256(data)[..] == self.digest[..],
+			BLAKE2B_256 => blake2_256(data)[..] == self.digest[..],
+			_ => false,
+		}
+	}
+}
+
+/// The Bitswap serving behaviour.
+pub struct Bitswap {
+	/// Data indexed by the runtime.
+	indexed_data: Arc<dyn IndexedData>,
+	/// Responses to send as soon as possible from `poll`.
+	pending_responses: VecDeque<(PeerId, BitswapMessage)>,
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
+}
+
+impl Bitswap {
+	/// Initializes the behaviour.
+	pub fn new(indexed_data: Arc<dyn IndexedData>, traffic_dump: TrafficDump) -> Self {
+		Bitswap {
+			indexed_data,
+			pending_responses: VecDeque::new(),
+			traffic_dump,
+		}
+	}
+
+	/// Builds the answer to the want-list received from `peer`. Returns `None` if there is
+	/// nothing to send back.
+	fn on_request(&self, peer: &PeerId, request: BitswapMessage) -> Option<BitswapMessage> {
+		let wantlist = request.wantlist?;
+		let mut response = BitswapMessage::default();
+
+		for entry in wantlist.entries.into_iter().filter(|e| !e.cancel).take(MAX_WANTED_BLOCKS) {
+			let cid = match Cid::parse(&entry.block) {
+				Some(cid) => cid,
+				None => {
+					debug!(target: "bitswap", "Invalid CID from {}: {:?}", peer, entry.block);
+					continue
+				}
+			};
+
+			let data = self.indexed_data.indexed_data(&cid.digest).filter(|data| cid.matches(data));
+			trace!(target: "bitswap", "Want from {} for {:?}: found = {}", peer, cid, data.is_some());
+
+			match data {
+				Some(data) if entry.want_type == WantType::Block as i32 =>
+					response.payload.push(MessageBlock { prefix: cid.prefix(), data }),
+				Some(_) => response.block_presences.push(BlockPresence {
+					cid: entry.block,
+					r#type: BlockPresenceType::Have as i32,
+				}),
+				None if entry.send_dont_have => response.block_presences.push(BlockPresence {
+					cid: entry.block,
+					r#type: BlockPresenceType::DontHave as i32,
+				}),
+				None => {},
+			}
+		}
+
+		if response.payload.is_empty() && response.block_presences.is_empty() {
+			None
+		} else {
+			Some(response)
+		}
+	}
+}
+
+impl NetworkBehaviour for Bitswap {
+	type ProtocolsHandler = OneShotHandler<InboundProtocol, OutboundProtocol, HandlerEvent>;
+	type OutEvent = Void;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		OneShotHandler::new(SubstreamProtocol::new(InboundProtocol), OneShotHandlerConfig::default())
+	}
+
+	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn inject_connected(&mut self, _peer: &PeerId) {
+	}
+
+	fn inject_disconnected(&mut self, _peer: &PeerId) {
+	}
+
+	fn inject_event(&mut self, peer: PeerId, _connection: ConnectionId, event: HandlerEvent) {
+		let request = match event {
+			HandlerEvent::Request(request) => request,
+			HandlerEvent::ResponseSent => return,
+		};
+
+		self.traffic_dump.record_message(Direction::In, PROTOCOL_NAME, &peer, &request);
+		if let Some(response) = self.on_request(&peer, request) {
+			self.pending_responses.push_back((peer, response));
+		}
+	}
+
+	fn poll(&mut self, _: &mut Context, _: &mut impl PollParameters)
+		-> Poll<NetworkBehaviourAction<OutboundProtocol, Void>>
+	{
+		if let Some((peer, response)) = self.pending_responses.pop_front() {
+			let mut data = Vec::with_capacity(response.encoded_len());
+			if let Err(e) = response.encode(&mut data) {
+				debug!(target: "bitswap", "Error encoding response for {}: {}", peer, e);
+			} else {
+				self.traffic_dump.record(Direction::Out, PROTOCOL_NAME, &peer, &data);
+				return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
+					peer_id: peer,
+					handler: NotifyHandler::Any,
+					event: OutboundProtocol { message: data },
+				})
+			}
+		}
+
+		Poll::Pending
+	}
+}
+
+/// Event produced by the substream upgrades.
+#[derive(Debug)]
+pub enum HandlerEvent {
+	/// Want-list received from the remote.
+	Request(BitswapMessage),
+	/// A response has been sent to the remote.
+	ResponseSent,
+}
+
+impl From<BitswapMessage> for HandlerEvent {
+	fn from(request: BitswapMessage) -> Self {
+		HandlerEvent::Request(request)
+	}
+}
+
+impl From<()> for HandlerEvent {
+	fn from(_: ()) -> Self {
+		HandlerEvent::ResponseSent
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Reads a want-list from the remote.
+#[derive(Debug, Clone, Default)]
+pub struct InboundProtocol;
+
+impl UpgradeInfo for InboundProtocol {
+	type Info = &'static [u8];
+	type InfoIter = iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(PROTOCOL_NAME)
+	}
+}
+
+impl<T> InboundUpgrade<T> for InboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = BitswapMessage;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			let vec = read_one(&mut s, MAX_REQUEST_SIZE).await?;
+			BitswapMessage::decode(&vec[..])
+				.map_err(|e| ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e)))
+		}.boxed()
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Sends a response to the remote.
+#[derive(Debug, Clone)]
+pub struct OutboundProtocol {
+	/// The serialized protobuf message.
+	message: Vec<u8>,
+}
+
+impl UpgradeInfo for OutboundProtocol {
+	type Info = &'static [u8];
+	type InfoIter = iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(PROTOCOL_NAME)
+	}
+}
+
+impl<T> OutboundUpgrade<T> for OutboundProtocol
+where
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = ();
+	type Error = io::Error;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move { write_one(&mut s, &self.message).await }.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{BLAKE2B_256, Cid, DAG_PB, SHA2_256};
+	use sp_core::hashing::{blake2_256, sha2_256};
+
+	#[test]
+	fn parses_cids() {
+		let digest = sha2_256(b"hello");
+		let mut v0 = vec![0x12, 32];
+		v0.extend_from_slice(&digest);
+		let cid = Cid::parse(&v0).unwrap();
+		assert_eq!(cid, Cid { version: 0, codec: DAG_PB, hash: SHA2_256, digest: digest.to_vec() });
+		assert!(cid.matches(b"hello"));
+		assert!(!cid.matches(b"world"));
+
+		// version 1, raw codec, BLAKE2b-256 multihash.
+		let digest = blake2_256(b"hello");
+		let mut v1 = vec![0x01, 0x55, 0xa0, 0xe4, 0x02, 32];
+		v1.extend_from_slice(&digest);
+		let cid = Cid::parse(&v1).unwrap();
+		assert_eq!(cid, Cid { version: 1, codec: 0x55, hash: BLAKE2B_256, digest: digest.to_vec() });
+		assert_eq!(cid.prefix(), &v1[..6]);
+		assert!(cid.matches(b"hello"));
+
+		// truncated digest.
+		assert_eq!(Cid::parse(&v1[..v1.len() - 1]), None);
+		// unknown version.
+		v1[0] = 2;
+		assert_eq!(Cid::parse(&v1), None);
+	}
+}
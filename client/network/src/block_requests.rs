@@ -29,6 +29,7 @@ use crate::{
 	config::ProtocolId,
 	protocol::{message::{self, BlockAttributes}},
 	schema,
+	traffic_dump::{Direction, TrafficDump},
 };
 use futures::{future::BoxFuture, prelude::*, stream::FuturesUnordered};
 use futures_timer::Delay;
@@ -119,6 +120,7 @@ pub enum Event<B: Block> {
 #[derive(Debug, Clone)]
 pub struct Config {
 	max_block_data_response: u32,
+	max_block_data_response_len: usize,
 	max_request_len: usize,
 	max_response_len: usize,
 	inactivity_timeout: Duration,
@@ -130,6 +132,7 @@ impl Config {
 	/// Create a fresh configuration with the following options:
 	///
 	/// - max. block data in response = 128
+	/// - max. size of the block data in a response = 8 MiB
 	/// - max. request size = 1 MiB
 	/// - max. response size = 16 MiB
 	/// - inactivity timeout = 15s
@@ -137,6 +140,7 @@ impl Config {
 	pub fn new(id: &ProtocolId) -> Self {
 		let mut c = Config {
 			max_block_data_response: 128,
+			max_block_data_response_len: 8 * 1024 * 1024,
 			max_request_len: 1024 * 1024,
 			max_response_len: 16 * 1024 * 1024,
 			inactivity_timeout: Duration::from_secs(15),
@@ -153,6 +157,15 @@ impl Config {
 		self
 	}
 
+	/// Limit the total size of the block data we send in a response.
+	///
+	/// Responses that would be larger are cut short, and tell the requester where to resume from.
+	/// A response always holds at least one block, whatever its size.
+	pub fn set_max_block_data_response_len(&mut self, v: usize) -> &mut Self {
+		self.max_block_data_response_len = v;
+		self
+	}
+
 	/// Limit the max. length of incoming block request bytes.
 	pub fn set_max_request_len(&mut self, v: usize) -> &mut Self {
 		self.max_request_len = v;
@@ -195,6 +208,8 @@ pub struct BlockRequests<B: Block> {
 	outgoing: FuturesUnordered<BoxFuture<'static, (PeerId, Duration)>>,
 	/// Events to return as soon as possible from `poll`.
 	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>,
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
 }
 
 /// Local tracking of a libp2p connection.
@@ -208,7 +223,13 @@ struct Connection<B: Block> {
 struct OngoingRequest<B: Block> {
 	/// `Instant` when the request has been emitted. Used for diagnostic purposes.
 	emitted: Instant,
+	/// Request passed to `send_request`.
 	request: message::BlockRequest<B>,
+	/// Request the remote is currently answering. Differs from `request` once the remote has cut
+	/// a response short and we are asking for the rest of the blocks.
+	in_flight: message::BlockRequest<B>,
+	/// Blocks of the responses that have been cut short so far.
+	received: Vec<message::BlockData<B>>,
 	timeout: Delay,
 }
 
@@ -236,13 +257,14 @@ impl<B> BlockRequests<B>
 where
 	B: Block,
 {
-	pub fn new(cfg: Config, chain: Arc<dyn Client<B>>) -> Self {
+	pub fn new(cfg: Config, chain: Arc<dyn Client<B>>, traffic_dump: TrafficDump) -> Self {
 		BlockRequests {
 			config: cfg,
 			chain,
 			peers: HashMap::new(),
 			outgoing: FuturesUnordered::new(),
 			pending_events: VecDeque::new(),
+			traffic_dump,
 		}
 	}
 
@@ -277,41 +299,22 @@ where
 			return SendRequestOutcome::NotConnected;
 		};
 
-		let protobuf_rq = schema::v1::BlockRequest {
-			fields: u32::from_be_bytes([req.fields.bits(), 0, 0, 0]),
-			from_block: match req.from {
-				message::FromBlock::Hash(h) =>
-					Some(schema::v1::block_request::FromBlock::Hash(h.encode())),
-				message::FromBlock::Number(n) =>
-					Some(schema::v1::block_request::FromBlock::Number(n.encode())),
-			},
-			to_block: req.to.map(|h| h.encode()).unwrap_or_default(),
-			direction: match req.direction {
-				message::Direction::Ascending => schema::v1::Direction::Ascending as i32,
-				message::Direction::Descending => schema::v1::Direction::Descending as i32,
-			},
-			max_blocks: req.max.unwrap_or(0),
+		let buf = match encode_request(&req) {
+			Ok(buf) => buf,
+			Err(err) => return SendRequestOutcome::EncodeError(err),
 		};
 
-		let mut buf = Vec::with_capacity(protobuf_rq.encoded_len());
-		if let Err(err) = protobuf_rq.encode(&mut buf) {
-			log::warn!(
-				target: "sync",
-				"Failed to encode block request {:?}: {:?}",
-				protobuf_rq,
-				err
-			);
-			return SendRequestOutcome::EncodeError(err);
-		}
-
 		let previous_request = connection.ongoing_request.take();
 		connection.ongoing_request = Some(OngoingRequest {
 			emitted: Instant::now(),
 			request: req.clone(),
+			in_flight: req.clone(),
+			received: Vec::new(),
 			timeout: Delay::new(self.config.request_timeout),
 		});
 
-		log::trace!(target: "sync", "Enqueueing block request to {:?}: {:?}", target, protobuf_rq);
+		log::trace!(target: "sync", "Enqueueing block request to {:?}: {:?}", target, req);
+		self.traffic_dump.record(Direction::Out, &self.config.protocol, target, &buf);
 		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
 			peer_id: target.clone(),
 			handler: NotifyHandler::One(connection.id),
@@ -338,6 +341,75 @@ where
 		}
 	}
 
+	/// Handles the blocks received from `peer` in answer to `ongoing_request`.
+	///
+	/// If the remote has cut its response short, because of its size, the rest of the blocks is
+	/// requested starting at `continue_from`. Otherwise, all the blocks received for the request
+	/// are reported at once.
+	fn on_response_blocks(
+		&mut self,
+		peer: PeerId,
+		connection_id: ConnectionId,
+		mut ongoing_request: OngoingRequest<B>,
+		blocks: Vec<message::BlockData<B>>,
+		continue_from: Option<B::Hash>,
+	) {
+		let progressed = !blocks.is_empty();
+		ongoing_request.received.extend(blocks);
+
+		let request = &ongoing_request.request;
+		let max = request.max.unwrap_or(self.config.max_block_data_response) as usize;
+		let remaining = max.saturating_sub(ongoing_request.received.len());
+		let reached_end = request.to
+			.map_or(false, |to| ongoing_request.received.iter().any(|block| block.hash == to));
+
+		if let Some(hash) = continue_from.filter(|_| progressed && remaining > 0 && !reached_end) {
+			let follow_up = message::BlockRequest::<B> {
+				from: message::FromBlock::Hash(hash),
+				max: Some(remaining as u32),
+				..request.clone()
+			};
+			let connection = self.peers.get_mut(&peer)
+				.and_then(|connections| connections.iter_mut().find(|c| c.id == connection_id));
+			match (connection, encode_request(&follow_up)) {
+				(Some(connection), Ok(buf)) => {
+					log::trace!(
+						target: "sync",
+						"Block response from {} cut short, resuming from {:?}",
+						peer, hash
+					);
+					self.traffic_dump.record(Direction::Out, &self.config.protocol, &peer, &buf);
+					ongoing_request.in_flight = follow_up.clone();
+					ongoing_request.timeout = Delay::new(self.config.request_timeout);
+					connection.ongoing_request = Some(ongoing_request);
+					self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
+						peer_id: peer,
+						handler: NotifyHandler::One(connection_id),
+						event: OutboundProtocol {
+							request: buf,
+							original_request: follow_up,
+							max_response_size: self.config.max_response_len,
+							protocol: self.config.protocol.clone(),
+						},
+					});
+					return
+				}
+				// the follow-up can't be tracked without the connection, report the blocks we
+				// already have.
+				_ => {}
+			}
+		}
+
+		let OngoingRequest { emitted, request, received, .. } = ongoing_request;
+		let ev = Event::Response {
+			peer,
+			response: message::BlockResponse::<B> { id: request.id, blocks: received },
+			original_request: request,
+			request_duration: emitted.elapsed(),
+		};
+		self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
+	}
+
 	/// Callback, invoked when a new block request has been received from remote.
 	fn on_block_request
 		( &mut self
@@ -392,6 +464,8 @@ where
 		let get_justification = attributes.contains(BlockAttributes::JUSTIFICATION);
 
 		let mut blocks = Vec::new();
+		let mut total_size = 0;
+		let mut continue_from = Vec::new();
 		let mut block_id = from_block_id;
 		while let Some(header) = self.chain.header(block_id).unwrap_or(None) {
 			if blocks.len() >= max_blocks as usize {
@@ -430,6 +504,11 @@ where
 				is_empty_justification,
 			};
 
+			total_size += block_data.encoded_len();
+			if total_size > self.config.max_block_data_response_len && !blocks.is_empty() {
+				continue_from = block_data.hash;
+				break
+			}
 			blocks.push(block_data);
 
 			match direction {
@@ -445,7 +524,7 @@ where
 			}
 		}
 
-		Ok(schema::v1::BlockResponse { blocks })
+		Ok(schema::v1::BlockResponse { blocks, continue_from })
 	}
 }
 
@@ -535,6 +614,7 @@ where
 	) {
 		match node_event {
 			NodeEvent::Request(request, mut stream, handling_start) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &request);
 				match self.on_block_request(&peer, &request) {
 					Ok(res) => {
 						log::trace!(
@@ -550,6 +630,7 @@ where
 								peer, e
 							)
 						} else {
+							self.traffic_dump.record(Direction::Out, &self.config.protocol, &peer, &data);
 							self.outgoing.push(async move {
 								if let Err(e) = write_one(&mut stream, data).await {
 									log::debug!(
@@ -569,18 +650,18 @@ where
 				}
 			}
 			NodeEvent::Response(original_request, response) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &response);
 				log::trace!(
 					target: "sync",
 					"Received block response from peer {} with {} blocks",
 					peer, response.blocks.len()
 				);
-				let request_duration = if let Some(connections) = self.peers.get_mut(&peer) {
+				let ongoing_request = if let Some(connections) = self.peers.get_mut(&peer) {
 					if let Some(connection) = connections.iter_mut().find(|c| c.id == connection_id) {
 						if let Some(ongoing_request) = &mut connection.ongoing_request {
-							if ongoing_request.request == original_request {
-								let request_duration = ongoing_request.emitted.elapsed();
-								connection.ongoing_request = None;
-								request_duration
+							if ongoing_request.in_flight == original_request {
+								connection.ongoing_request.take()
+									.expect("ongoing_request has been checked to be Some above; qed")
 							} else {
 								// We're no longer interested in that request.
 								log::debug!(
@@ -617,6 +698,11 @@ where
 					return;
 				};
 
+				let continue_from = if response.continue_from.is_empty() {
+					Ok(None)
+				} else {
+					Decode::decode(&mut response.continue_from.as_ref()).map(Some)
+				};
 				let blocks = response.blocks.into_iter().map(|block_data| {
 					Ok(message::BlockData::<B> {
 						hash: Decode::decode(&mut block_data.hash.as_ref())?,
@@ -652,18 +738,15 @@ where
 					})
 				}).collect::<Result<Vec<_>, codec::Error>>();
 
-				match blocks {
-					Ok(blocks) => {
-						let id = original_request.id;
-						let ev = Event::Response {
-							peer,
-							original_request,
-							response: message::BlockResponse::<B> { id, blocks },
-							request_duration,
-						};
-						self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
-					}
-					Err(err) => {
+				match (blocks, continue_from) {
+					(Ok(blocks), Ok(continue_from)) => self.on_response_blocks(
+						peer,
+						connection_id,
+						ongoing_request,
+						blocks,
+						continue_from,
+					),
+					(Err(err), _) | (_, Err(err)) => {
 						log::debug!(
 							target: "sync",
 							"Failed to decode block response from peer {}: {}", peer, err
@@ -720,6 +803,37 @@ where
 	}
 }
 
+/// Encodes a block request as sent on the wire.
+fn encode_request<B: Block>(req: &message::BlockRequest<B>) -> Result<Vec<u8>, prost::EncodeError> {
+	let protobuf_rq = schema::v1::BlockRequest {
+		fields: u32::from_be_bytes([req.fields.bits(), 0, 0, 0]),
+		from_block: match req.from {
+			message::FromBlock::Hash(h) =>
+				Some(schema::v1::block_request::FromBlock::Hash(h.encode())),
+			message::FromBlock::Number(n) =>
+				Some(schema::v1::block_request::FromBlock::Number(n.encode())),
+		},
+		to_block: req.to.map(|h| h.encode()).unwrap_or_default(),
+		direction: match req.direction {
+			message::Direction::Ascending => schema::v1::Direction::Ascending as i32,
+			message::Direction::Descending => schema::v1::Direction::Descending as i32,
+		},
+		max_blocks: req.max.unwrap_or(0),
+	};
+
+	let mut buf = Vec::with_capacity(protobuf_rq.encoded_len());
+	if let Err(err) = protobuf_rq.encode(&mut buf) {
+		log::warn!(
+			target: "sync",
+			"Failed to encode block request {:?}: {:?}",
+			protobuf_rq,
+			err
+		);
+		return Err(err);
+	}
+	Ok(buf)
+}
+
 /// Output type of inbound and outbound substream upgrades.
 #[derive(Debug)]
 pub enum NodeEvent<B: Block, T> {
@@ -826,3 +940,66 @@ where
 		}.boxed()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use libp2p::core::ConnectedPoint;
+	use sc_block_builder::BlockBuilderProvider;
+	use sp_consensus::BlockOrigin;
+	use substrate_test_runtime_client::{runtime::Block, ClientBlockImportExt};
+
+	#[test]
+	fn response_cut_short_is_completed_by_follow_up_requests() {
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let mut hashes = Vec::new();
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			hashes.push(block.hash());
+			client.import(BlockOrigin::Own, block).unwrap();
+		}
+
+		// every response holds a single block.
+		let mut config = Config::new(&ProtocolId::from(&b"test"[..]));
+		config.set_max_block_data_response_len(1);
+		let mut behaviour = BlockRequests::new(config, client, Default::default());
+		let peer = PeerId::random();
+		let connection = ConnectionId::new(1);
+		let dialer = ConnectedPoint::Dialer { address: Multiaddr::empty() };
+		behaviour.inject_connection_established(&peer, &connection, &dialer);
+
+		let request = message::BlockRequest::<Block> {
+			id: 0,
+			fields: BlockAttributes::HEADER,
+			from: message::FromBlock::Number(1),
+			to: None,
+			direction: message::Direction::Ascending,
+			max: Some(3),
+		};
+		assert!(matches!(behaviour.send_request(&peer, request), SendRequestOutcome::Ok));
+
+		// the requests are answered by the behaviour itself, which has the blocks.
+		let mut requests = 0;
+		let blocks = loop {
+			match behaviour.pending_events.pop_front() {
+				Some(NetworkBehaviourAction::NotifyHandler { event, .. }) => {
+					requests += 1;
+					let request = schema::v1::BlockRequest::decode(&event.request[..]).unwrap();
+					let response = behaviour.on_block_request(&peer, &request).unwrap();
+					assert_eq!(response.blocks.len(), 1);
+					behaviour.inject_event(
+						peer.clone(),
+						connection,
+						NodeEvent::Response(event.original_request, response),
+					);
+				}
+				Some(NetworkBehaviourAction::GenerateEvent(Event::Response { response, .. })) =>
+					break response.blocks,
+				_ => panic!("Unexpected event"),
+			}
+		};
+
+		assert_eq!(requests, 3);
+		assert_eq!(blocks.iter().map(|block| block.hash).collect::<Vec<_>>(), hashes);
+	}
+}
@@ -20,7 +20,9 @@
 
 use sp_blockchain::{Error, HeaderBackend, HeaderMetadata};
 use sc_client_api::{BlockBackend, ProofProvider};
-use sp_runtime::traits::{Block as BlockT, BlockIdTo};
+use sp_runtime::{Justification, traits::{Block as BlockT, BlockIdTo}};
+
+pub use sc_client_api::IndexedData;
 
 /// Local client abstraction for the network.
 pub trait Client<Block: BlockT>: HeaderBackend<Block> + ProofProvider<Block> + BlockIdTo<Block, Error = Error>
@@ -44,3 +46,44 @@ impl<Block: BlockT> FinalityProofProvider<Block> for () {
 		Ok(None)
 	}
 }
+
+/// Result of [`WarpSyncProvider::verify`].
+#[derive(Debug)]
+pub enum WarpSyncVerification<Block: BlockT> {
+	/// The proof is valid but doesn't reach the latest finalized block. A new proof must be
+	/// requested, starting at `last_hash` and verified against `authorities`.
+	Partial {
+		/// Opaque authority set that finalized the blocks following `last_hash`.
+		authorities: Vec<u8>,
+		/// Hash of the last block covered by the proof.
+		last_hash: Block::Hash,
+	},
+	/// The proof is valid and ends with the finality of the given block.
+	Complete {
+		/// Header of the latest finalized block.
+		header: Block::Header,
+		/// Justification finalizing `header`.
+		justification: Justification,
+	},
+}
+
+/// Warp sync proof provider and verifier.
+///
+/// A warp sync proof proves the finality of the latest finalized block known to a node, by
+/// following the changes of the set of authorities in charge of finality since a given block.
+pub trait WarpSyncProvider<Block: BlockT>: Send + Sync {
+	/// Generate a proof starting at the block `begin`, which must be the genesis block or the
+	/// last block covered by a previous proof.
+	fn generate(&self, begin: Block::Hash) -> Result<Vec<u8>, Error>;
+
+	/// Verify a proof against the opaque `authorities` in charge of finality at the start of
+	/// the proof.
+	///
+	/// Once a proof is complete, the provider must be ready for the last block of the proof to
+	/// be imported along with its state.
+	fn verify(&self, proof: &[u8], authorities: Vec<u8>) -> Result<WarpSyncVerification<Block>, Error>;
+
+	/// Opaque authority set in charge of finality at our latest finalized block, which proofs
+	/// starting at that block are verified against.
+	fn current_authorities(&self) -> Vec<u8>;
+}
@@ -21,9 +21,13 @@
 //! The [`Params`] struct is the struct that must be passed in order to initialize the networking.
 //! See the documentation of [`Params`].
 
-pub use crate::chain::{Client, FinalityProofProvider};
+pub use crate::chain::{Client, FinalityProofProvider, IndexedData, WarpSyncProvider, WarpSyncVerification};
+pub use crate::ip_filter::{ConnectionLimits, IpFilter};
+pub use crate::traffic_dump::TrafficDumpConfig;
 pub use crate::on_demand_layer::{AlwaysBadChecker, OnDemand};
+pub use crate::protocol::NotificationsPriority;
 pub use libp2p::{identity, core::PublicKey, wasm_ext::ExtTransport, build_multiaddr};
+pub use ip_network::IpNetwork;
 
 // Note: this re-export shouldn't be part of the public API of the crate and will be removed in
 // the future.
@@ -38,17 +42,19 @@ use libp2p::identity::{ed25519, Keypair};
 use libp2p::wasm_ext;
 use libp2p::{multiaddr, Multiaddr, PeerId};
 use prometheus_endpoint::Registry;
+use rand::seq::SliceRandom as _;
 use sp_consensus::{block_validation::BlockAnnounceValidator, import_queue::ImportQueue};
 use sp_runtime::{traits::Block as BlockT, ConsensusEngineId};
 use std::{borrow::Cow, convert::TryFrom, future::Future, pin::Pin, str::FromStr};
 use std::{
-	collections::HashMap,
+	collections::{HashMap, HashSet},
 	error::Error,
 	fs,
 	io::{self, Write},
 	net::Ipv4Addr,
 	path::{Path, PathBuf},
 	sync::Arc,
+	time::Duration,
 };
 use zeroize::Zeroize;
 
@@ -78,6 +84,19 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// This object, if `Some`, is used when we need a proof of finality from another node.
 	pub finality_proof_request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
 
+	/// Warp sync proof provider.
+	///
+	/// This object, if `Some`, is used to answer requests for warp sync proofs from other nodes,
+	/// and to verify the proofs we receive when [`NetworkConfiguration::sync_mode`] is
+	/// [`SyncMode::Warp`].
+	pub warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+
+	/// Data written by the runtime through offchain indexing.
+	///
+	/// If `Some` and [`NetworkConfiguration::ipfs_server`] is set, the blocks indexed under their
+	/// hash are served to other nodes over the Bitswap protocol.
+	pub indexed_data: Option<Arc<dyn IndexedData>>,
+
 	/// The `OnDemand` object acts as a "receiver" for block data requests from the client.
 	/// If `Some`, the network worker will process these requests and answer them.
 	/// Normally used only for light clients.
@@ -101,6 +120,11 @@ pub struct Params<B: BlockT, H: ExHashT> {
 	/// Type to check incoming block announcements.
 	pub block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 
+	/// Decides which peers transactions are propagated to. Use [`PropagateToAll`] to send them
+	/// to all the full nodes we are connected to, or [`TransactionPropagationConfig::policy`] to
+	/// follow the network configuration.
+	pub transaction_propagation_policy: Box<dyn TransactionPropagationPolicy>,
+
 	/// Registry for recording prometheus metrics to.
 	pub metrics_registry: Option<Registry>,
 }
@@ -123,6 +147,12 @@ pub enum Role {
 	Authority {
 		/// List of public addresses and identities of our sentry nodes.
 		sentry_nodes: Vec<MultiaddrWithPeerId>,
+		/// If true, the authority is only reachable through its sentry nodes.
+		///
+		/// It only connects to and accepts connections from `sentry_nodes`, and doesn't take
+		/// part in the discovery of the other nodes. Its addresses are never published to the
+		/// DHT, the sentry nodes store and advertise the records of the authority instead.
+		private: bool,
 	}
 }
 
@@ -132,6 +162,11 @@ impl Role {
 		matches!(self, Role::Authority { .. })
 	}
 
+	/// True for `Role::Authority` only reachable through its sentry nodes.
+	pub fn is_private_authority(&self) -> bool {
+		matches!(self, Role::Authority { private: true, .. })
+	}
+
 	/// True for `Role::Authority` and `Role::Sentry` since they're both
 	/// announced as having the authority role to the network.
 	pub fn is_network_authority(&self) -> bool {
@@ -233,6 +268,92 @@ impl<H: ExHashT + Default, B: BlockT> TransactionPool<H, B> for EmptyTransaction
 	fn transaction(&self, _h: &H) -> Option<B::Extrinsic> { None }
 }
 
+/// Decides which peers transactions are propagated to.
+///
+/// Every time transactions are propagated, the policy is given the list of the full nodes we
+/// are connected to, and returns the ones to send the transactions to. Transactions that aren't
+/// sent to a peer are offered again the next time.
+pub trait TransactionPropagationPolicy: Send {
+	/// Returns the subset of `peers` to send transactions to this time.
+	fn select_peers(&mut self, peers: Vec<PeerId>) -> Vec<PeerId>;
+}
+
+/// [`TransactionPropagationPolicy`] sending transactions to all peers.
+#[derive(Debug, Clone, Default)]
+pub struct PropagateToAll;
+
+impl TransactionPropagationPolicy for PropagateToAll {
+	fn select_peers(&mut self, peers: Vec<PeerId>) -> Vec<PeerId> {
+		peers
+	}
+}
+
+/// [`TransactionPropagationPolicy`] sending transactions to at most the given number of peers,
+/// picked randomly every time.
+///
+/// Reduces the bandwidth used to propagate transactions on networks where nodes have many
+/// peers, at the cost of a slower propagation.
+#[derive(Debug, Clone)]
+pub struct PropagateToRandomPeers(pub usize);
+
+impl TransactionPropagationPolicy for PropagateToRandomPeers {
+	fn select_peers(&mut self, mut peers: Vec<PeerId>) -> Vec<PeerId> {
+		peers.shuffle(&mut rand::thread_rng());
+		peers.truncate(self.0);
+		peers
+	}
+}
+
+/// Configuration of the propagation of transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionPropagationConfig {
+	/// Maximum number of transactions sent to a peer in a single message.
+	pub max_batch_size: usize,
+	/// Newly imported transactions are propagated together, at this interval.
+	pub batch_interval: Duration,
+	/// Maximum number of transactions sent to each peer per second. Transactions above the
+	/// limit are offered to the peer again later.
+	pub max_per_peer_per_sec: Option<u32>,
+	/// If set, transactions are propagated to at most this number of peers picked randomly
+	/// every time, with [`PropagateToRandomPeers`], rather than to all of them.
+	pub max_peers: Option<usize>,
+}
+
+impl TransactionPropagationConfig {
+	/// Returns the policy deciding which peers transactions are propagated to.
+	pub fn policy(&self) -> Box<dyn TransactionPropagationPolicy> {
+		match self.max_peers {
+			Some(max_peers) => Box::new(PropagateToRandomPeers(max_peers)),
+			None => Box::new(PropagateToAll),
+		}
+	}
+}
+
+impl Default for TransactionPropagationConfig {
+	fn default() -> Self {
+		TransactionPropagationConfig {
+			max_batch_size: 256,
+			batch_interval: Duration::from_millis(100),
+			max_per_peer_per_sec: None,
+			max_peers: None,
+		}
+	}
+}
+
+/// Limits on the requests served to each light client, all unlimited by default.
+///
+/// Requests above the limits are refused and lower the reputation of the sender. They are
+/// reported to the `sub_libp2p_light_requests_denied_total` Prometheus metric.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LightServeLimits {
+	/// Maximum number of requests served to a peer per second.
+	pub max_requests_per_sec: Option<u32>,
+	/// Maximum number of bytes of proofs sent to a peer per second.
+	pub max_proof_bytes_per_sec: Option<u64>,
+	/// Maximum number of responses being sent to a peer at the same time.
+	pub max_concurrent_requests: Option<usize>,
+}
+
 /// Name of a protocol, transmitted on the wire. Should be unique for each chain.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ProtocolId(smallvec::SmallVec<[u8; 6]>);
@@ -406,7 +527,15 @@ pub struct NetworkConfiguration {
 	pub node_key: NodeKeyConfig,
 	/// List of notifications protocols that the node supports. Must also include a
 	/// `ConsensusEngineId` for backwards-compatibility.
+	///
+	/// These protocols are given a [`NotificationsPriority::High`] priority.
 	pub notifications_protocols: Vec<(ConsensusEngineId, Cow<'static, [u8]>)>,
+	/// Notifications protocols of `notifications_protocols` used in acknowledged mode, in which
+	/// remotes acknowledge the notifications they receive. See
+	/// `NetworkService::write_notification_acknowledged`.
+	///
+	/// All the nodes using one of these protocols must use it in acknowledged mode.
+	pub acknowledged_notifications_protocols: HashSet<ConsensusEngineId>,
 	/// Maximum allowed number of incoming connections.
 	pub in_peers: u32,
 	/// Number of outgoing connections we're trying to maintain.
@@ -425,9 +554,42 @@ pub struct NetworkConfiguration {
 	pub max_parallel_downloads: u32,
 	/// Should we insert non-global addresses into the DHT?
 	pub allow_non_globals_in_dht: bool,
+	/// If true, connections are refused unless they complete the Noise handshake specified by
+	/// libp2p. Connections falling back to the legacy handshake are refused, and reported to the
+	/// `sub_libp2p_insecure_connections_rejected_total` Prometheus metric.
+	pub secure_transport_only: bool,
+	/// Filter applied to the IP address of the remote of incoming connections. Can later be
+	/// updated with `NetworkService::set_ip_filter`.
+	pub ip_filter: IpFilter,
+	/// Limits on the number of incoming connections from the same IP address or subnet.
+	pub connection_limits: ConnectionLimits,
+	/// If set, the messages exchanged with other nodes are recorded to a file from startup. The
+	/// dump can later be started or stopped with `NetworkService::set_traffic_dump`.
+	pub traffic_dump: Option<TrafficDumpConfig>,
 	/// If true, uses the `/<chainid>/block-requests/<version>` experimental protocol rather than
 	/// the legacy substream. This option is meant to be hard-wired to `true` in the future.
 	pub use_new_block_requests_protocol: bool,
+	/// How the node catches up with the chain when its database is empty.
+	pub sync_mode: SyncMode,
+	/// If set, blocks, justifications, finality proofs and state are only downloaded from these
+	/// peers. Block announces are still accepted from every peer. Can later be changed with
+	/// `NetworkService::set_trusted_sync_peers`.
+	pub trusted_sync_peers: Option<HashSet<PeerId>>,
+	/// How transactions are propagated to other nodes.
+	pub transaction_propagation: TransactionPropagationConfig,
+	/// Limits on the requests served to each light client.
+	pub light_serve_limits: LightServeLimits,
+	/// If set, an empty message is sent on the notifications substreams every time nothing has
+	/// been sent on them for this long, so that NATs and firewalls don't close them for being
+	/// idle. Nodes that don't support this report the empty messages to their users.
+	pub notifications_keep_alive: Option<Duration>,
+	/// If set, peers that don't read the notifications we send them for this long, or don't
+	/// accept our notifications substreams in time, have their reputation reduced. Peers for
+	/// which this keeps happening end up being disconnected.
+	pub notifications_stall_timeout: Option<Duration>,
+	/// If true, the data written by the runtime through offchain indexing is served to IPFS
+	/// clients over the Bitswap protocol. See [`Params::indexed_data`].
+	pub ipfs_server: bool,
 }
 
 impl NetworkConfiguration {
@@ -445,6 +607,7 @@ impl NetworkConfiguration {
 			boot_nodes: Vec::new(),
 			node_key,
 			notifications_protocols: Vec::new(),
+			acknowledged_notifications_protocols: HashSet::new(),
 			in_peers: 25,
 			out_peers: 75,
 			reserved_nodes: Vec::new(),
@@ -459,7 +622,18 @@ impl NetworkConfiguration {
 			},
 			max_parallel_downloads: 5,
 			allow_non_globals_in_dht: false,
+			secure_transport_only: false,
+			ip_filter: IpFilter::default(),
+			connection_limits: ConnectionLimits::default(),
+			traffic_dump: None,
 			use_new_block_requests_protocol: true,
+			sync_mode: SyncMode::Full,
+			trusted_sync_peers: None,
+			transaction_propagation: Default::default(),
+			light_serve_limits: Default::default(),
+			notifications_keep_alive: None,
+			notifications_stall_timeout: Some(Duration::from_secs(30)),
+			ipfs_server: false,
 		}
 	}
 }
@@ -535,6 +709,24 @@ pub enum TransportConfig {
 	MemoryOnly,
 }
 
+/// Strategy used to catch up with the chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncMode {
+	/// Download and execute every block since genesis.
+	Full,
+	/// Download a proof of finality of the latest finalized block, following the authority set
+	/// changes since genesis, then download the state of that block and continue from there.
+	///
+	/// Only used when the database is empty and a warp sync provider has been configured,
+	/// otherwise the node falls back to a full sync.
+	Warp,
+	/// Download the headers and justifications of every block since genesis, then download the
+	/// state of the latest finalized block and execute the blocks that follow it.
+	///
+	/// Only used when the database is empty, otherwise the node falls back to a full sync.
+	Fast,
+}
+
 /// The policy for connections to non-reserved peers.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum NonReservedPeerMode {
@@ -54,7 +54,7 @@ use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, Pr
 use libp2p::swarm::protocols_handler::multi::MultiHandler;
 use libp2p::kad::{Kademlia, KademliaConfig, KademliaEvent, QueryResult, Quorum, Record};
 use libp2p::kad::GetClosestPeersError;
-use libp2p::kad::handler::KademliaHandler;
+use libp2p::kad::handler::{KademliaHandler, KademliaHandlerEvent};
 use libp2p::kad::QueryId;
 use libp2p::kad::record::{self, store::{MemoryStore, RecordStore}};
 #[cfg(not(target_os = "unknown"))]
@@ -78,6 +78,8 @@ pub struct DiscoveryConfig {
 	allow_non_globals_in_dht: bool,
 	discovery_only_if_under_num: u64,
 	enable_mdns: bool,
+	allowed_peers: Option<HashSet<PeerId>>,
+	hidden_peers: HashSet<PeerId>,
 	kademlias: HashMap<ProtocolId, Kademlia<MemoryStore>>
 }
 
@@ -91,10 +93,38 @@ impl DiscoveryConfig {
 			allow_non_globals_in_dht: false,
 			discovery_only_if_under_num: std::u64::MAX,
 			enable_mdns: false,
+			allowed_peers: None,
+			hidden_peers: HashSet::new(),
 			kademlias: HashMap::new()
 		}
 	}
 
+	/// Restricts the discovery to the given nodes, e.g. the sentry nodes of a private authority.
+	///
+	/// The other nodes are neither added to the Kademlia routing table nor dialed, so that the
+	/// DHT is only reached through the given nodes. Must be called before `with_user_defined` and
+	/// `add_protocol`.
+	pub fn with_allowed_peers(&mut self, peers: HashSet<PeerId>) -> &mut Self {
+		self.allowed_peers = Some(peers);
+		self
+	}
+
+	/// Hides the given nodes from the DHT, e.g. the private authorities guarded by a sentry node.
+	///
+	/// Their addresses can be dialed, but are never added to the Kademlia routing table, and
+	/// therefore never given to the other nodes. Must be called before `with_user_defined` and
+	/// `add_protocol`.
+	pub fn with_hidden_peers(&mut self, peers: HashSet<PeerId>) -> &mut Self {
+		self.hidden_peers = peers;
+		self
+	}
+
+	/// Returns true if the addresses of `peer_id` may be added to the Kademlia routing table.
+	fn is_in_dht(&self, peer_id: &PeerId) -> bool {
+		!self.hidden_peers.contains(peer_id) &&
+			self.allowed_peers.as_ref().map_or(true, |allowed| allowed.contains(peer_id))
+	}
+
 	/// Set the number of active connections at which we pause discovery.
 	pub fn discovery_limit(&mut self, limit: u64) -> &mut Self {
 		self.discovery_only_if_under_num = limit;
@@ -107,8 +137,10 @@ impl DiscoveryConfig {
 		I: IntoIterator<Item = (PeerId, Multiaddr)>
 	{
 		for (peer_id, addr) in user_defined {
-			for kad in self.kademlias.values_mut() {
-				kad.add_address(&peer_id, addr.clone())
+			if self.is_in_dht(&peer_id) {
+				for kad in self.kademlias.values_mut() {
+					kad.add_address(&peer_id, addr.clone())
+				}
 			}
 			self.user_defined.push((peer_id, addr))
 		}
@@ -164,7 +196,9 @@ impl DiscoveryConfig {
 		let mut kad = Kademlia::with_config(self.local_peer_id.clone(), store, config);
 
 		for (peer_id, addr) in &self.user_defined {
-			kad.add_address(peer_id, addr.clone());
+			if self.is_in_dht(peer_id) {
+				kad.add_address(peer_id, addr.clone());
+			}
 		}
 
 		self.kademlias.insert(id, kad);
@@ -178,6 +212,8 @@ impl DiscoveryConfig {
 			next_kad_random_query: Delay::new(Duration::new(0, 0)),
 			duration_to_next_kad: Duration::from_secs(1),
 			pending_events: VecDeque::new(),
+			allowed_peers: self.allowed_peers,
+			hidden_peers: self.hidden_peers,
 			local_peer_id: self.local_peer_id,
 			num_connections: 0,
 			allow_private_ipv4: self.allow_private_ipv4,
@@ -215,6 +251,10 @@ pub struct DiscoveryBehaviour {
 	duration_to_next_kad: Duration,
 	/// Events to return in priority when polled.
 	pending_events: VecDeque<DiscoveryOut>,
+	/// If `Some`, the only nodes that are added to the Kademlia routing table or dialed.
+	allowed_peers: Option<HashSet<PeerId>>,
+	/// Nodes that are never added to the Kademlia routing table.
+	hidden_peers: HashSet<PeerId>,
 	/// Identity of our local node.
 	local_peer_id: PeerId,
 	/// Number of nodes we're currently connected to.
@@ -229,6 +269,16 @@ pub struct DiscoveryBehaviour {
 }
 
 impl DiscoveryBehaviour {
+	/// Returns true if `peer_id` may be dialed.
+	fn is_allowed(&self, peer_id: &PeerId) -> bool {
+		self.allowed_peers.as_ref().map_or(true, |allowed| allowed.contains(peer_id))
+	}
+
+	/// Returns true if the addresses of `peer_id` may be added to the Kademlia routing table.
+	fn is_in_dht(&self, peer_id: &PeerId) -> bool {
+		!self.hidden_peers.contains(peer_id) && self.is_allowed(peer_id)
+	}
+
 	/// Returns the list of nodes that we know exist in the network.
 	pub fn known_peers(&mut self) -> impl Iterator<Item = &PeerId> {
 		let mut set = HashSet::new();
@@ -244,9 +294,16 @@ impl DiscoveryBehaviour {
 	///
 	/// If we didn't know this address before, also generates a `Discovered` event.
 	pub fn add_known_address(&mut self, peer_id: PeerId, addr: Multiaddr) {
+		if !self.is_allowed(&peer_id) {
+			trace!(target: "sub-libp2p", "Ignoring address {} of {}, which isn't allowed", addr, peer_id);
+			return
+		}
+
 		if self.user_defined.iter().all(|(p, a)| *p != peer_id && *a != addr) {
-			for k in self.kademlias.values_mut() {
-				k.add_address(&peer_id, addr.clone())
+			if self.is_in_dht(&peer_id) {
+				for k in self.kademlias.values_mut() {
+					k.add_address(&peer_id, addr.clone())
+				}
 			}
 			self.pending_events.push_back(DiscoveryOut::Discovered(peer_id.clone()));
 			self.user_defined.push((peer_id, addr));
@@ -258,7 +315,9 @@ impl DiscoveryBehaviour {
 	/// **Note**: It is important that you call this method, otherwise the discovery mechanism will
 	/// not properly work.
 	pub fn add_self_reported_address(&mut self, peer_id: &PeerId, addr: Multiaddr) {
-		if self.allow_non_globals_in_dht || self.can_add_to_dht(&addr) {
+		if !self.is_in_dht(peer_id) {
+			log::trace!(target: "sub-libp2p", "Ignoring self-reported address {} from hidden {}", addr, peer_id);
+		} else if self.allow_non_globals_in_dht || self.can_add_to_dht(&addr) {
 			for k in self.kademlias.values_mut() {
 				k.add_address(peer_id, addr.clone())
 			}
@@ -279,10 +338,14 @@ impl DiscoveryBehaviour {
 	/// Start putting a record into the DHT. Other nodes can later fetch that value with
 	/// `get_value`.
 	///
+	/// If `ttl` is `Some`, the record expires after that duration instead of the default one.
+	///
 	/// A corresponding `ValuePut` or `ValuePutFailed` event will later be generated.
-	pub fn put_value(&mut self, key: record::Key, value: Vec<u8>) {
+	pub fn put_value(&mut self, key: record::Key, value: Vec<u8>, ttl: Option<Duration>) {
 		for k in self.kademlias.values_mut() {
-			if let Err(e) = k.put_record(Record::new(key.clone(), value.clone()), Quorum::All) {
+			let mut record = Record::new(key.clone(), value.clone());
+			record.expires = ttl.map(|ttl| wasm_timer::Instant::now() + ttl);
+			if let Err(e) = k.put_record(record, Quorum::All) {
 				warn!(target: "sub-libp2p", "Libp2p => Failed to put record: {:?}", e);
 				self.pending_events.push_back(DiscoveryOut::ValuePutFailed(key.clone()));
 			}
@@ -378,6 +441,11 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 	}
 
 	fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+		if !self.is_allowed(peer_id) {
+			trace!(target: "sub-libp2p", "Addresses of {:?}: none (peer not allowed)", peer_id);
+			return Vec::new()
+		}
+
 		let mut list = self.user_defined.iter()
 			.filter_map(|(p, a)| if p == peer_id { Some(a.clone()) } else { None })
 			.collect::<Vec<_>>();
@@ -429,12 +497,19 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 
 	fn inject_connection_established(&mut self, peer_id: &PeerId, conn: &ConnectionId, endpoint: &ConnectedPoint) {
 		self.num_connections += 1;
+		// Kademlia inserts the nodes it's connected to in its routing table.
+		if self.hidden_peers.contains(peer_id) {
+			return
+		}
 		for k in self.kademlias.values_mut() {
 			NetworkBehaviour::inject_connection_established(k, peer_id, conn, endpoint)
 		}
 	}
 
 	fn inject_connected(&mut self, peer_id: &PeerId) {
+		if self.hidden_peers.contains(peer_id) {
+			return
+		}
 		for k in self.kademlias.values_mut() {
 			NetworkBehaviour::inject_connected(k, peer_id)
 		}
@@ -442,12 +517,18 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 
 	fn inject_connection_closed(&mut self, peer_id: &PeerId, conn: &ConnectionId, endpoint: &ConnectedPoint) {
 		self.num_connections -= 1;
+		if self.hidden_peers.contains(peer_id) {
+			return
+		}
 		for k in self.kademlias.values_mut() {
 			NetworkBehaviour::inject_connection_closed(k, peer_id, conn, endpoint)
 		}
 	}
 
 	fn inject_disconnected(&mut self, peer_id: &PeerId) {
+		if self.hidden_peers.contains(peer_id) {
+			return
+		}
 		for k in self.kademlias.values_mut() {
 			NetworkBehaviour::inject_disconnected(k, peer_id)
 		}
@@ -470,6 +551,13 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 		connection: ConnectionId,
 		(pid, event): <Self::ProtocolsHandler as ProtocolsHandler>::OutEvent,
 	) {
+		// Kademlia inserts the nodes confirming its protocol in its routing table, but still
+		// answers the requests of the hidden nodes, e.g. to let them reach the DHT.
+		if let KademliaHandlerEvent::ProtocolConfirmed { .. } = event {
+			if self.hidden_peers.contains(&peer_id) {
+				return
+			}
+		}
 		if let Some(kad) = self.kademlias.get_mut(&pid) {
 			return kad.inject_event(peer_id, connection, event)
 		}
@@ -704,9 +792,12 @@ mod tests {
 	use libp2p::identity::Keypair;
 	use libp2p::Multiaddr;
 	use libp2p::core::upgrade;
+	use libp2p::core::{connection::ConnectionId, ConnectedPoint};
 	use libp2p::core::transport::{Transport, MemoryTransport};
 	use libp2p::core::upgrade::{InboundUpgradeExt, OutboundUpgradeExt};
-	use libp2p::swarm::Swarm;
+	use libp2p::kad::handler::KademliaHandlerEvent;
+	use libp2p::swarm::{NetworkBehaviour, Swarm};
+	use libp2p::PeerId;
 	use std::{collections::HashSet, task::Poll};
 	use super::{DiscoveryConfig, DiscoveryOut};
 
@@ -807,4 +898,38 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	#[test]
+	fn restricted_and_hidden_peers_stay_out_of_the_dht() {
+		let sentry = PeerId::random();
+		let validator = PeerId::random();
+		let stranger = PeerId::random();
+		let address = |n: u64| -> Multiaddr { format!("/memory/{}", n).parse().unwrap() };
+
+		let protocol_id: &[u8] = b"/test/kad/1.0.0";
+		let mut config = DiscoveryConfig::new(Keypair::generate_ed25519().public());
+		config.with_allowed_peers(vec![sentry.clone(), validator.clone()].into_iter().collect())
+			.with_hidden_peers(vec![validator.clone()].into_iter().collect())
+			.with_user_defined(vec![
+				(sentry.clone(), address(1)),
+				(validator.clone(), address(2)),
+				(stranger.clone(), address(3)),
+			])
+			.add_protocol(ProtocolId::from(protocol_id));
+		let mut discovery = config.finish();
+		discovery.add_known_address(stranger.clone(), address(4));
+		discovery.add_self_reported_address(&validator, address(5));
+
+		// the validator confirming the protocol on a connection it opened.
+		let endpoint = ConnectedPoint::Dialer { address: address(2) };
+		discovery.inject_event(
+			validator.clone(),
+			ConnectionId::new(0),
+			(ProtocolId::from(protocol_id), KademliaHandlerEvent::ProtocolConfirmed { endpoint }),
+		);
+
+		assert_eq!(discovery.known_peers().collect::<Vec<_>>(), vec![&sentry]);
+		assert_eq!(discovery.addresses_of_peer(&validator), vec![address(2)]);
+		assert!(discovery.addresses_of_peer(&stranger).is_empty());
+	}
 }
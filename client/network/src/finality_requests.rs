@@ -29,6 +29,7 @@ use crate::{
 	config::ProtocolId,
 	protocol::message,
 	schema,
+	traffic_dump::{Direction, TrafficDump},
 };
 use futures::{future::BoxFuture, prelude::*, stream::FuturesUnordered};
 use libp2p::{
@@ -146,6 +147,8 @@ pub struct FinalityProofRequests<B: Block> {
 	outgoing: FuturesUnordered<BoxFuture<'static, ()>>,
 	/// Events to return as soon as possible from `poll`.
 	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>,
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
 }
 
 impl<B> FinalityProofRequests<B>
@@ -156,12 +159,17 @@ where
 	///
 	/// If the proof provider is `None`, then the behaviour will not support the finality proof
 	/// requests protocol.
-	pub fn new(cfg: Config, finality_proof_provider: Option<Arc<dyn FinalityProofProvider<B>>>) -> Self {
+	pub fn new(
+		cfg: Config,
+		finality_proof_provider: Option<Arc<dyn FinalityProofProvider<B>>>,
+		traffic_dump: TrafficDump,
+	) -> Self {
 		FinalityProofRequests {
 			config: cfg,
 			finality_proof_provider,
 			outgoing: FuturesUnordered::new(),
 			pending_events: VecDeque::new(),
+			traffic_dump,
 		}
 	}
 
@@ -182,6 +190,7 @@ where
 		}
 
 		log::trace!("enqueueing finality proof request to {:?}: {:?}", target, protobuf_rq);
+		self.traffic_dump.record(Direction::Out, &self.config.protocol, target, &buf);
 		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
 			peer_id: target.clone(),
 			handler: NotifyHandler::Any,
@@ -256,6 +265,7 @@ where
 	) {
 		match event {
 			NodeEvent::Request(request, mut stream) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &request);
 				match self.on_finality_request(&peer, &request) {
 					Ok(res) => {
 						log::trace!("enqueueing finality response for peer {}", peer);
@@ -263,6 +273,7 @@ where
 						if let Err(e) = res.encode(&mut data) {
 							log::debug!("error encoding finality response for peer {}: {}", peer, e)
 						} else {
+							self.traffic_dump.record(Direction::Out, &self.config.protocol, &peer, &data);
 							let future = async move {
 								if let Err(e) = write_one(&mut stream, data).await {
 									log::debug!("error writing finality response: {}", e)
@@ -275,6 +286,7 @@ where
 				}
 			}
 			NodeEvent::Response(response, block_hash) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &response);
 				let ev = Event::Response {
 					peer,
 					block_hash,
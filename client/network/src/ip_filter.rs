@@ -0,0 +1,267 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Filtering of incoming connections based on the IP address of the remote.
+//!
+//! The [`IpFilter`] is shared between the transport, which consults it every time a new
+//! connection arrives on one of our listening sockets, and the `NetworkService`, which can
+//! replace it at runtime. Outgoing connections are never filtered.
+//!
+//! Similarly, the [`ConnectionTracker`] counts the incoming connections open from each IP address
+//! and subnet, and lets the transport refuse new ones once the [`ConnectionLimits`] are reached,
+//! so that a single host can't take all our inbound slots by connecting with many identities.
+
+use ip_network::IpNetwork;
+use libp2p::{Multiaddr, multiaddr::Protocol};
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv4Addr, Ipv6Addr},
+	sync::Arc,
+};
+
+/// List of IP ranges incoming connections are checked against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilter {
+	/// If `Some`, only incoming connections whose remote IP address belongs to one of these
+	/// ranges are accepted.
+	pub allow: Option<Vec<IpNetwork>>,
+	/// Incoming connections whose remote IP address belongs to one of these ranges are always
+	/// rejected, even if they also match the allow-list.
+	pub deny: Vec<IpNetwork>,
+}
+
+impl IpFilter {
+	/// Returns true if incoming connections from `ip` should be accepted.
+	pub fn is_allowed(&self, ip: IpAddr) -> bool {
+		if self.deny.iter().any(|range| range.contains(ip)) {
+			return false
+		}
+
+		match &self.allow {
+			Some(allow) => allow.iter().any(|range| range.contains(ip)),
+			None => true,
+		}
+	}
+
+	/// Returns true if incoming connections from the remote at `addr` should be accepted.
+	///
+	/// Addresses that don't start with an IP address (for example `/memory/...` addresses) are
+	/// always accepted.
+	pub fn is_address_allowed(&self, addr: &Multiaddr) -> bool {
+		remote_ip(addr).map_or(true, |ip| self.is_allowed(ip))
+	}
+}
+
+/// Returns the IP address `addr` starts with, if any.
+fn remote_ip(addr: &Multiaddr) -> Option<IpAddr> {
+	match addr.iter().next() {
+		Some(Protocol::Ip4(ip)) => Some(IpAddr::V4(ip)),
+		Some(Protocol::Ip6(ip)) => Some(IpAddr::V6(ip)),
+		_ => None,
+	}
+}
+
+/// Limits on the number of simultaneous incoming connections from the same remote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionLimits {
+	/// Max. number of incoming connections from a single IP address. Unlimited if `None`.
+	pub max_per_ip: Option<usize>,
+	/// Max. number of incoming connections from a single subnet. Unlimited if `None`.
+	pub max_per_subnet: Option<usize>,
+	/// Length of the prefix of the IPv4 subnets, 24 by default.
+	pub ipv4_subnet_prefix: u8,
+	/// Length of the prefix of the IPv6 subnets, 64 by default.
+	pub ipv6_subnet_prefix: u8,
+}
+
+impl Default for ConnectionLimits {
+	fn default() -> Self {
+		ConnectionLimits {
+			max_per_ip: None,
+			max_per_subnet: None,
+			ipv4_subnet_prefix: 24,
+			ipv6_subnet_prefix: 64,
+		}
+	}
+}
+
+impl ConnectionLimits {
+	/// Returns the subnet `ip` belongs to, as its first address.
+	fn subnet(&self, ip: IpAddr) -> IpAddr {
+		match ip {
+			IpAddr::V4(ip) => {
+				let shift = 32u32.saturating_sub(u32::from(self.ipv4_subnet_prefix));
+				let mask = u32::max_value().checked_shl(shift).unwrap_or(0);
+				IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+			},
+			IpAddr::V6(ip) => {
+				let shift = 128u32.saturating_sub(u32::from(self.ipv6_subnet_prefix));
+				let mask = u128::max_value().checked_shl(shift).unwrap_or(0);
+				IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+			},
+		}
+	}
+}
+
+/// Limit reached by a connection refused by the [`ConnectionTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitReached {
+	/// Too many connections from the IP address of the remote.
+	PerIp,
+	/// Too many connections from the subnet of the remote.
+	PerSubnet,
+}
+
+/// Number of incoming connections open from each IP address and subnet.
+#[derive(Debug, Default)]
+pub struct ConnectionTracker {
+	limits: ConnectionLimits,
+	counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+	per_ip: HashMap<IpAddr, usize>,
+	/// Subnets, keyed by their first address.
+	per_subnet: HashMap<IpAddr, usize>,
+}
+
+impl ConnectionTracker {
+	/// Creates a tracker enforcing `limits`.
+	pub fn new(limits: ConnectionLimits) -> Self {
+		ConnectionTracker { limits, counts: Mutex::new(Counts::default()) }
+	}
+
+	/// Registers a new incoming connection from the remote at `addr`, unless it would exceed one
+	/// of the limits. The connection is counted until the returned guard is dropped.
+	///
+	/// Connections from addresses that don't start with an IP address (for example `/memory/...`
+	/// addresses) are always accepted, and no guard is returned for them.
+	pub fn try_register(self: &Arc<Self>, addr: &Multiaddr) -> Result<Option<ConnectionGuard>, LimitReached> {
+		let ip = match remote_ip(addr) {
+			Some(ip) => ip,
+			None => return Ok(None),
+		};
+		let subnet = self.limits.subnet(ip);
+
+		let mut counts = self.counts.lock();
+		let per_ip = counts.per_ip.get(&ip).copied().unwrap_or(0);
+		if self.limits.max_per_ip.map_or(false, |max| per_ip >= max) {
+			return Err(LimitReached::PerIp)
+		}
+		let per_subnet = counts.per_subnet.get(&subnet).copied().unwrap_or(0);
+		if self.limits.max_per_subnet.map_or(false, |max| per_subnet >= max) {
+			return Err(LimitReached::PerSubnet)
+		}
+
+		counts.per_ip.insert(ip, per_ip + 1);
+		counts.per_subnet.insert(subnet, per_subnet + 1);
+		Ok(Some(ConnectionGuard { tracker: self.clone(), ip, subnet }))
+	}
+}
+
+/// Incoming connection counted by a [`ConnectionTracker`]. Stops being counted when dropped.
+#[derive(Debug)]
+pub struct ConnectionGuard {
+	tracker: Arc<ConnectionTracker>,
+	ip: IpAddr,
+	subnet: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+	fn drop(&mut self) {
+		fn decrement(counts: &mut HashMap<IpAddr, usize>, key: &IpAddr) {
+			if let Some(count) = counts.get_mut(key) {
+				*count -= 1;
+				if *count == 0 {
+					counts.remove(key);
+				}
+			}
+		}
+
+		let mut counts = self.tracker.counts.lock();
+		decrement(&mut counts.per_ip, &self.ip);
+		decrement(&mut counts.per_subnet, &self.subnet);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ConnectionLimits, ConnectionTracker, IpFilter, LimitReached};
+	use std::sync::Arc;
+
+	fn filter(allow: Option<&[&str]>, deny: &[&str]) -> IpFilter {
+		IpFilter {
+			allow: allow.map(|a| a.iter().map(|r| r.parse().unwrap()).collect()),
+			deny: deny.iter().map(|r| r.parse().unwrap()).collect(),
+		}
+	}
+
+	#[test]
+	fn empty_filter_accepts_everything() {
+		let filter = IpFilter::default();
+		assert!(filter.is_address_allowed(&"/ip4/198.51.100.19/tcp/30333".parse().unwrap()));
+		assert!(filter.is_address_allowed(&"/ip6/::1/tcp/30333".parse().unwrap()));
+		assert!(filter.is_address_allowed(&"/memory/5".parse().unwrap()));
+	}
+
+	#[test]
+	fn deny_list_is_applied() {
+		let filter = filter(None, &["198.51.100.0/24"]);
+		assert!(!filter.is_address_allowed(&"/ip4/198.51.100.19/tcp/30333".parse().unwrap()));
+		assert!(filter.is_address_allowed(&"/ip4/198.51.101.19/tcp/30333".parse().unwrap()));
+	}
+
+	#[test]
+	fn allow_list_is_applied() {
+		let filter = filter(Some(&["10.0.0.0/8", "fd00::/8"]), &[]);
+		assert!(filter.is_address_allowed(&"/ip4/10.1.2.3/tcp/30333".parse().unwrap()));
+		assert!(filter.is_address_allowed(&"/ip6/fd00::1/tcp/30333/ws".parse().unwrap()));
+		assert!(!filter.is_address_allowed(&"/ip4/198.51.100.19/tcp/30333".parse().unwrap()));
+	}
+
+	#[test]
+	fn deny_list_has_priority() {
+		let filter = filter(Some(&["10.0.0.0/8"]), &["10.1.0.0/16"]);
+		assert!(filter.is_address_allowed(&"/ip4/10.2.0.1/tcp/30333".parse().unwrap()));
+		assert!(!filter.is_address_allowed(&"/ip4/10.1.0.1/tcp/30333".parse().unwrap()));
+	}
+
+	#[test]
+	fn connection_limits_are_enforced() {
+		let tracker = Arc::new(ConnectionTracker::new(ConnectionLimits {
+			max_per_ip: Some(2),
+			max_per_subnet: Some(3),
+			..Default::default()
+		}));
+		let register = |addr: &str| tracker.try_register(&addr.parse().unwrap());
+
+		let first = register("/ip4/198.51.100.19/tcp/1").unwrap();
+		let _second = register("/ip4/198.51.100.19/tcp/2").unwrap();
+		assert_eq!(register("/ip4/198.51.100.19/tcp/3").unwrap_err(), LimitReached::PerIp);
+		// dropping a guard frees its slot.
+		drop(first);
+		let _third = register("/ip4/198.51.100.19/tcp/3").unwrap();
+		let _fourth = register("/ip4/198.51.100.20/tcp/1").unwrap();
+		assert_eq!(register("/ip4/198.51.100.21/tcp/1").unwrap_err(), LimitReached::PerSubnet);
+		// other subnets and non-IP addresses aren't affected.
+		assert!(register("/ip4/198.51.101.21/tcp/1").unwrap().is_some());
+		assert!(register("/memory/5").unwrap().is_none());
+	}
+}
@@ -59,6 +59,10 @@
 //! us their view of the network. More information about Kademlia can be found [on
 //! Wikipedia](https://en.wikipedia.org/wiki/Kademlia).
 //!
+//! The Kademlia DHTs can also store arbitrary records, published and queried with
+//! `NetworkService::put_dht_value` and `NetworkService::get_dht_value`. The results are reported
+//! on the stream returned by `NetworkService::dht_event_stream`.
+//!
 //! ## Connection establishment
 //!
 //! When node Alice knows node Bob's identity and address, it can establish a connection with Bob.
@@ -140,6 +144,10 @@
 //! block announces are pushed to other nodes. The handshake is empty on both sides. The message
 //! format is a SCALE-encoded tuple containing a block header followed with an opaque list of
 //! bytes containing some data associated with this block announcement, e.g. a candidate message.
+//! - **[`/ipfs/bitswap/1.2.0`](https://github.com/ipfs/specs/blob/master/BITSWAP.md)**, only if
+//! enabled with `NetworkConfiguration::ipfs_server`. IPFS clients send us their want-list and we
+//! answer with the blocks written by the runtime through offchain indexing, looked up by the
+//! hash in their CID.
 //! - Notifications protocols that are registered using the `register_notifications_protocol`
 //! method. For example: `/paritytech/grandpa/1`. See below for more information.
 //!
@@ -243,32 +251,40 @@
 //!
 
 mod behaviour;
+mod bitswap;
 mod block_requests;
 mod chain;
 mod debug_info;
 mod discovery;
 mod finality_requests;
+mod ip_filter;
 mod light_client_handler;
 mod on_demand_layer;
 mod protocol;
 mod schema;
 mod service;
+mod state_requests;
+mod traffic_dump;
 mod transport;
 mod utils;
+mod warp_requests;
 
 pub mod config;
 pub mod error;
 pub mod network_state;
 
-pub use service::{NetworkService, NetworkWorker};
-pub use protocol::PeerInfo;
+pub use service::{
+	NetworkService, NetworkWorker, NotificationSendError, NotificationSenderGroup, Signature,
+};
+pub use protocol::{PeerDetails, PeerInfo, RequestStats};
 pub use protocol::event::{Event, DhtEvent, ObservedRole};
-pub use protocol::sync::SyncState;
+pub use protocol::sync::{GapSyncProgress, PeerSyncDetails, SyncRequest, SyncState};
 pub use libp2p::{Multiaddr, PeerId};
+pub use libp2p::kad::record::Key as KademliaKey;
 #[doc(inline)]
 pub use libp2p::multiaddr;
 
-pub use sc_peerset::ReputationChange;
+pub use sc_peerset::{Observation, PeerScorer, RatioScorer, ReputationChange};
 
 /// The maximum allowed number of established connections per peer.
 ///
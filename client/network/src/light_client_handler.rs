@@ -31,6 +31,7 @@ use crate::{
 	config::ProtocolId,
 	protocol::message::BlockAttributes,
 	schema,
+	traffic_dump::{Direction, TrafficDump},
 };
 use futures::{channel::oneshot, future::BoxFuture, prelude::*, stream::FuturesUnordered};
 use libp2p::{
@@ -54,6 +55,7 @@ use libp2p::{
 	}
 };
 use nohash_hasher::IntMap;
+use prometheus_endpoint::{Counter, CounterVec, U64};
 use prost::Message;
 use sc_client_api::{
 	StorageProof,
@@ -87,6 +89,9 @@ use wasm_timer::Instant;
 /// Reputation change for a peer when a request timed out.
 pub(crate) const TIMEOUT_REPUTATION_CHANGE: i32 = -(1 << 8);
 
+/// Reputation change for a peer when it exceeds the limits on the requests we serve it.
+pub(crate) const QUOTA_EXCEEDED_REPUTATION_CHANGE: i32 = -(1 << 10);
+
 /// Configuration options for `LightClientHandler` behaviour.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -95,6 +100,9 @@ pub struct Config {
 	max_pending_requests: usize,
 	inactivity_timeout: Duration,
 	request_timeout: Duration,
+	max_requests_per_sec: Option<u32>,
+	max_proof_bytes_per_sec: Option<u64>,
+	max_concurrent_requests: Option<usize>,
 	light_protocol: Bytes,
 	block_protocol: Bytes,
 }
@@ -107,6 +115,9 @@ impl Config {
 	/// - max. pending requests = 128
 	/// - inactivity timeout = 15s
 	/// - request timeout = 15s
+	/// - max. requests served per peer per second = unlimited
+	/// - max. proof bytes sent per peer per second = unlimited
+	/// - max. concurrent requests served per peer = unlimited
 	pub fn new(id: &ProtocolId) -> Self {
 		let mut c = Config {
 			max_request_size: 1 * 1024 * 1024,
@@ -114,6 +125,9 @@ impl Config {
 			max_pending_requests: 128,
 			inactivity_timeout: Duration::from_secs(15),
 			request_timeout: Duration::from_secs(15),
+			max_requests_per_sec: None,
+			max_proof_bytes_per_sec: None,
+			max_concurrent_requests: None,
 			light_protocol: Bytes::new(),
 			block_protocol: Bytes::new(),
 		};
@@ -151,6 +165,24 @@ impl Config {
 		self
 	}
 
+	/// Limit the max. number of requests served to a single peer per second.
+	pub fn set_max_requests_per_sec(&mut self, v: Option<u32>) -> &mut Self {
+		self.max_requests_per_sec = v;
+		self
+	}
+
+	/// Limit the max. number of bytes of proofs sent to a single peer per second.
+	pub fn set_max_proof_bytes_per_sec(&mut self, v: Option<u64>) -> &mut Self {
+		self.max_proof_bytes_per_sec = v;
+		self
+	}
+
+	/// Limit the max. number of responses being sent to a single peer at the same time.
+	pub fn set_max_concurrent_requests(&mut self, v: Option<usize>) -> &mut Self {
+		self.max_concurrent_requests = v;
+		self
+	}
+
 	/// Set protocol to use for upgrade negotiation.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
 		let mut vl = Vec::new();
@@ -282,6 +314,78 @@ enum PeerStatus {
 	BusyWith(RequestId),
 }
 
+/// Reason for refusing to serve a request from a remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Denial {
+	/// The remote sent more requests than allowed during the last second.
+	RequestRate,
+	/// We sent the remote more proof bytes than allowed during the last second.
+	ProofRate,
+	/// Too many responses are being sent to the remote.
+	Concurrency,
+}
+
+impl Denial {
+	/// Label of the reason in the Prometheus metrics.
+	fn as_str(&self) -> &'static str {
+		match self {
+			Denial::RequestRate => "request-rate",
+			Denial::ProofRate => "proof-rate",
+			Denial::Concurrency => "concurrency",
+		}
+	}
+}
+
+/// Requests served to a remote, checked against the limits of the `Config`.
+#[derive(Debug)]
+struct ServeQuota {
+	/// Start of the one second window `requests` and `proof_bytes` apply to.
+	window_start: Instant,
+	/// Number of requests accepted during the current window.
+	requests: u32,
+	/// Number of proof bytes sent during the current window.
+	proof_bytes: u64,
+	/// Number of responses being sent.
+	in_flight: usize,
+}
+
+impl ServeQuota {
+	fn new(now: Instant) -> Self {
+		ServeQuota { window_start: now, requests: 0, proof_bytes: 0, in_flight: 0 }
+	}
+
+	/// Accounts for a request received at `now`, or returns why it must be refused.
+	fn on_request(&mut self, config: &Config, now: Instant) -> Result<(), Denial> {
+		if now >= self.window_start + Duration::from_secs(1) {
+			self.window_start = now;
+			self.requests = 0;
+			self.proof_bytes = 0;
+		}
+
+		if config.max_concurrent_requests.map_or(false, |max| self.in_flight >= max) {
+			return Err(Denial::Concurrency)
+		}
+		if config.max_requests_per_sec.map_or(false, |max| self.requests >= max) {
+			return Err(Denial::RequestRate)
+		}
+		if config.max_proof_bytes_per_sec.map_or(false, |max| self.proof_bytes >= max) {
+			return Err(Denial::ProofRate)
+		}
+
+		self.requests += 1;
+		Ok(())
+	}
+}
+
+/// Prometheus counters of the requests served to light clients.
+#[derive(Clone)]
+pub struct ServeMetrics {
+	/// Number of requests answered.
+	pub served: Counter<U64>,
+	/// Number of requests refused because of the limits of the `Config`, by reason.
+	pub denied: CounterVec<U64>,
+}
+
 /// The light client handler behaviour.
 pub struct LightClientHandler<B: Block> {
 	/// This behaviour's configuration.
@@ -292,8 +396,10 @@ pub struct LightClientHandler<B: Block> {
 	checker: Arc<dyn light::FetchChecker<B>>,
 	/// Peer information (addresses, their best block, etc.)
 	peers: HashMap<PeerId, PeerInfo<B>>,
-	/// Futures sending back response to remote clients.
-	responses: FuturesUnordered<BoxFuture<'static, ()>>,
+	/// Futures sending back response to remote clients. Each one yields the remote once done.
+	responses: FuturesUnordered<BoxFuture<'static, PeerId>>,
+	/// Requests served to each remote, for enforcing the limits of the `Config`.
+	quotas: HashMap<PeerId, ServeQuota>,
 	/// Pending (local) requests.
 	pending_requests: VecDeque<RequestWrapper<B, ()>>,
 	/// Requests on their way to remote peers.
@@ -302,6 +408,10 @@ pub struct LightClientHandler<B: Block> {
 	next_request_id: RequestId,
 	/// Handle to use for reporting misbehaviour of peers.
 	peerset: sc_peerset::PeersetHandle,
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
+	/// Prometheus counters, if enabled.
+	metrics: Option<ServeMetrics>,
 }
 
 impl<B> LightClientHandler<B>
@@ -314,6 +424,8 @@ where
 		chain: Arc<dyn Client<B>>,
 		checker: Arc<dyn light::FetchChecker<B>>,
 		peerset: sc_peerset::PeersetHandle,
+		traffic_dump: TrafficDump,
+		metrics: Option<ServeMetrics>,
 	) -> Self {
 		LightClientHandler {
 			config: cfg,
@@ -321,10 +433,13 @@ where
 			checker,
 			peers: HashMap::new(),
 			responses: FuturesUnordered::new(),
+			quotas: HashMap::new(),
 			pending_requests: VecDeque::new(),
 			outstanding: IntMap::default(),
 			next_request_id: 1,
 			peerset,
+			traffic_dump,
+			metrics,
 		}
 	}
 
@@ -783,6 +898,7 @@ where
 
 	fn inject_disconnected(&mut self, peer: &PeerId) {
 		log::trace!("peer {} disconnected", peer);
+		self.quotas.remove(peer);
 		self.remove_peer(peer)
 	}
 
@@ -821,6 +937,20 @@ where
 			// An incoming request from remote has been received.
 			Event::Request(request, mut stream) => {
 				log::trace!("incoming request from {}", peer);
+				self.traffic_dump.record_message(Direction::In, &self.config.light_protocol, &peer, &request);
+
+				let now = Instant::now();
+				let quota = self.quotas.entry(peer.clone()).or_insert_with(|| ServeQuota::new(now));
+				if let Err(denial) = quota.on_request(&self.config, now) {
+					log::debug!("refusing request from {}: {:?} limit reached", peer, denial);
+					if let Some(metrics) = &self.metrics {
+						metrics.denied.with_label_values(&[denial.as_str()]).inc();
+					}
+					let change = ReputationChange::new(QUOTA_EXCEEDED_REPUTATION_CHANGE, "light request quota exceeded");
+					self.peerset.report_peer(peer, change);
+					return
+				}
+
 				let result = match &request.request {
 					Some(schema::v1::light::request::Request::RemoteCallRequest(r)) =>
 						self.on_remote_call_request(&peer, r),
@@ -844,10 +974,20 @@ where
 						if let Err(e) = response.encode(&mut data) {
 							log::debug!("error encoding response for peer {}: {}", peer, e)
 						} else {
+							self.traffic_dump.record(Direction::Out, &self.config.light_protocol, &peer, &data);
+							if let Some(quota) = self.quotas.get_mut(&peer) {
+								quota.proof_bytes += data.len() as u64;
+								quota.in_flight += 1;
+							}
+							if let Some(metrics) = &self.metrics {
+								metrics.served.inc();
+							}
+							let peer = peer.clone();
 							let future = async move {
 								if let Err(e) = write_one(&mut stream, data).await {
 									log::debug!("error writing response: {}", e)
 								}
+								peer
 							};
 							self.responses.push(future.boxed())
 						}
@@ -861,6 +1001,12 @@ where
 			}
 			// A response to one of our own requests has been received.
 			Event::Response(id, response) => {
+				match &response {
+					Response::Light(r) =>
+						self.traffic_dump.record_message(Direction::In, &self.config.light_protocol, &peer, r),
+					Response::Block(r) =>
+						self.traffic_dump.record_message(Direction::In, &self.config.block_protocol, &peer, r),
+				}
 				if let Some(request) = self.outstanding.remove(&id) {
 					// We first just check if the response originates from the expected peer
 					// and connection.
@@ -938,7 +1084,11 @@ where
 
 	fn poll(&mut self, cx: &mut Context, _: &mut impl PollParameters) -> Poll<NetworkBehaviourAction<OutboundProtocol, Void>> {
 		// Process response sending futures.
-		while let Poll::Ready(Some(_)) = self.responses.poll_next_unpin(cx) {}
+		while let Poll::Ready(Some(peer)) = self.responses.poll_next_unpin(cx) {
+			if let Some(quota) = self.quotas.get_mut(&peer) {
+				quota.in_flight = quota.in_flight.saturating_sub(1);
+			}
+		}
 
 		// If we have a pending request to send, try to find an available peer and send it.
 		let now = Instant::now();
@@ -994,6 +1144,7 @@ where
 					};
 
 					log::trace!("sending request {} to peer {}", request_id, peer_id);
+					self.traffic_dump.record(Direction::Out, &event.protocol, &peer_id, &event.request);
 
 					return Poll::Ready(NetworkBehaviourAction::NotifyHandler {
 						peer_id,
@@ -1334,7 +1485,7 @@ mod tests {
 		task::{Context, Poll}
 	};
 	use sp_runtime::{generic::Header, traits::{BlakeTwo256, Block as BlockT, NumberFor}};
-	use super::{Event, LightClientHandler, Request, Response, OutboundProtocol, PeerStatus};
+	use super::{Denial, Event, LightClientHandler, Request, Response, OutboundProtocol, PeerStatus, ServeQuota};
 	use void::Void;
 
 	type Block = sp_runtime::generic::Block<Header<u64, BlakeTwo256>, substrate_test_runtime::Extrinsic>;
@@ -1358,7 +1509,7 @@ mod tests {
 			.map(|(peer, muxer), _| (peer, StreamMuxerBox::new(muxer)))
 			.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
 			.boxed();
-		Swarm::new(transport, LightClientHandler::new(cf, client, checker, ps), local_peer)
+		Swarm::new(transport, LightClientHandler::new(cf, client, checker, ps, Default::default(), None), local_peer)
 	}
 
 	struct DummyFetchChecker<B> {
@@ -1490,6 +1641,7 @@ mod tests {
 			bootnodes: Vec::new(),
 			reserved_only: false,
 			priority_groups: Vec::new(),
+			allowed_peers: None,
 		};
 		sc_peerset::Peerset::from_config(cfg)
 	}
@@ -1502,7 +1654,7 @@ mod tests {
 	{
 		let client = Arc::new(substrate_test_runtime_client::new());
 		let checker = Arc::new(DummyFetchChecker { ok, _mark: std::marker::PhantomData });
-		LightClientHandler::new(cf, client, checker, ps)
+		LightClientHandler::new(cf, client, checker, ps, Default::default(), None)
 	}
 
 	fn empty_dialer() -> ConnectedPoint {
@@ -1532,6 +1684,29 @@ mod tests {
 		assert_eq!(0, behaviour.peers.len())
 	}
 
+	#[test]
+	fn serve_quota_enforces_limits() {
+		let mut config = make_config();
+		config.set_max_requests_per_sec(Some(2)).set_max_concurrent_requests(Some(1));
+		let now = wasm_timer::Instant::now();
+		let mut quota = ServeQuota::new(now);
+
+		assert_eq!(Ok(()), quota.on_request(&config, now));
+		quota.in_flight += 1;
+		assert_eq!(Err(Denial::Concurrency), quota.on_request(&config, now));
+		quota.in_flight -= 1;
+		assert_eq!(Ok(()), quota.on_request(&config, now));
+		assert_eq!(Err(Denial::RequestRate), quota.on_request(&config, now));
+
+		// the counters are reset every second.
+		let later = now + std::time::Duration::from_secs(1);
+		assert_eq!(Ok(()), quota.on_request(&config, later));
+
+		config.set_max_proof_bytes_per_sec(Some(100));
+		quota.proof_bytes = 100;
+		assert_eq!(Err(Denial::ProofRate), quota.on_request(&config, later));
+	}
+
 	#[test]
 	fn disconnects_from_peer_if_request_times_out() {
 		let peer0 = PeerId::random();
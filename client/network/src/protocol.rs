@@ -18,9 +18,13 @@
 
 use crate::{
 	ExHashT,
-	chain::{Client, FinalityProofProvider},
-	config::{BoxFinalityProofRequestBuilder, ProtocolId, TransactionPool, TransactionImportFuture, TransactionImport},
-	error,
+	chain::{Client, FinalityProofProvider, WarpSyncProvider},
+	config::{
+		BoxFinalityProofRequestBuilder, ProtocolId, TransactionPool, TransactionImportFuture, TransactionImport,
+		TransactionPropagationConfig, TransactionPropagationPolicy,
+	},
+	error, schema,
+	traffic_dump::TrafficDump,
 	utils::interval
 };
 
@@ -37,7 +41,7 @@ use sp_core::{
 };
 use sp_consensus::{
 	BlockOrigin,
-	block_validation::BlockAnnounceValidator,
+	block_validation::{BlockAnnounceValidator, PeerChainInfo},
 	import_queue::{BlockImportResult, BlockImportError, IncomingBlock, Origin}
 };
 use codec::{Decode, Encode};
@@ -48,16 +52,18 @@ use sp_runtime::traits::{
 use sp_arithmetic::traits::SaturatedConversion;
 use message::{BlockAnnounce, Message};
 use message::generic::{Message as GenericMessage, ConsensusMessage, Roles};
-use prometheus_endpoint::{Registry, Gauge, GaugeVec, HistogramVec, PrometheusError, Opts, register, U64};
-use sync::{ChainSync, SyncState};
+use prometheus_endpoint::{
+	Registry, CounterVec, Gauge, GaugeVec, HistogramVec, PrometheusError, Opts, register, U64,
+};
+use sync::{ChainSync, GapSyncProgress, SyncState};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::fmt::Write;
-use std::{cmp, io, num::NonZeroUsize, pin::Pin, task::Poll, time};
+use std::{cmp, io, mem, num::NonZeroUsize, pin::Pin, task::Poll, time};
 use log::{log, Level, trace, debug, warn, error};
 use sc_client_api::{ChangesProof, StorageProof};
-use util::LruHashSet;
+use util::{LruHashSet, RateLimiter};
 use wasm_timer::Instant;
 
 mod generic_proto;
@@ -67,9 +73,11 @@ pub mod message;
 pub mod event;
 pub mod sync;
 
-pub use generic_proto::LegacyConnectionKillError;
+pub use generic_proto::{DeliveryConfirmer, LegacyConnectionKillError, NotificationsPriority};
 
 const REQUEST_TIMEOUT_SEC: u64 = 40;
+/// Weight of the latest sample in the moving average of the latency of a peer.
+const LATENCY_SMOOTHING: f64 = 0.3;
 /// Interval at which we perform time based maintenance
 const TICK_TIMEOUT: time::Duration = time::Duration::from_millis(1100);
 /// Interval at which we propagate extrinsics;
@@ -100,6 +108,10 @@ mod rep {
 	/// Reputation change when a peer is "clogged", meaning that it's not fast enough to process our
 	/// messages.
 	pub const CLOGGED_PEER: Rep = Rep::new(-(1 << 12), "Clogged message queue");
+	/// Reputation change when a peer doesn't accept our notifications substream in time, or
+	/// doesn't read the notifications we send it for a while. Peers for which this keeps
+	/// happening end up being disconnected.
+	pub const SLOW_PEER: Rep = Rep::new(-(1 << 28), "Slow to process notifications");
 	/// Reputation change when a peer doesn't respond in time to our messages.
 	pub const TIMEOUT: Rep = Rep::new(-(1 << 10), "Request timeout");
 	/// Reputation change when a peer sends us a status message while we already received one.
@@ -135,8 +147,14 @@ mod rep {
 	pub const BAD_ROLE: Rep = Rep::new_fatal("Unsupported role");
 	/// Peer response data does not have requested bits.
 	pub const BAD_RESPONSE: Rep = Rep::new(-(1 << 12), "Incomplete response");
+	/// Most of the recent block announcements of the peer were about already finalized blocks.
+	pub const STALE_BLOCK_ANNOUNCES: Rep = Rep::new(-(1 << 10), "Stale block announcements");
 }
 
+/// Kind of the observations reported to the peerset about block announcements. Announcements of
+/// already finalized blocks are bad.
+const BLOCK_ANNOUNCE_OBSERVATION: &str = "block-announce";
+
 struct Metrics {
 	handshaking_peers: Gauge<U64>,
 	obsolete_requests: Gauge<U64>,
@@ -145,6 +163,7 @@ struct Metrics {
 	fork_targets: Gauge<U64>,
 	finality_proofs: GaugeVec<U64>,
 	justifications: GaugeVec<U64>,
+	slow_peers: CounterVec<U64>,
 }
 
 impl Metrics {
@@ -190,6 +209,16 @@ impl Metrics {
 				)?;
 				register(g, r)?
 			},
+			slow_peers: {
+				let c = CounterVec::new(
+					Opts::new(
+						"sub_libp2p_notifications_slow_peers_total",
+						"Number of times a peer has been reported for being slow to process notifications",
+					),
+					&["protocol", "reason"],
+				)?;
+				register(c, r)?
+			},
 		})
 	}
 }
@@ -218,6 +247,12 @@ pub struct Protocol<B: BlockT, H: ExHashT> {
 	tick_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
 	/// Interval at which we call `propagate_extrinsics`.
 	propagate_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
+	/// Interval at which we propagate the extrinsics in `pending_propagation`.
+	propagate_batch_timeout: Pin<Box<dyn Stream<Item = ()> + Send>>,
+	/// Extrinsics passed to `propagate_extrinsic` and not propagated yet.
+	pending_propagation: Vec<H>,
+	/// Decides which peers extrinsics are propagated to.
+	transaction_propagation_policy: Box<dyn TransactionPropagationPolicy>,
 	/// Pending list of messages to return from `poll` as a priority.
 	pending_messages: VecDeque<CustomMessageOutcome<B>>,
 	/// Pending extrinsic verification tasks.
@@ -242,6 +277,9 @@ pub struct Protocol<B: BlockT, H: ExHashT> {
 	protocol_name_by_engine: HashMap<ConsensusEngineId, Cow<'static, [u8]>>,
 	/// For each protocol name, the legacy equivalent.
 	legacy_equiv_by_name: HashMap<Cow<'static, [u8]>, Fallback>,
+	/// For each peer, the notifications protocols for which the peer accepted a dedicated
+	/// substream. The other protocols fall back to the legacy substream.
+	open_notif_substreams: HashMap<PeerId, HashSet<Cow<'static, [u8]>>>,
 	/// Name of the protocol used for transactions.
 	transactions_protocol: Cow<'static, [u8]>,
 	/// Name of the protocol used for block announces.
@@ -279,10 +317,43 @@ struct Peer<B: BlockT, H: ExHashT> {
 	obsolete_requests: HashMap<message::RequestId, Instant>,
 	/// Holds a set of transactions known to this peer.
 	known_extrinsics: LruHashSet<H>,
+	/// Limits the rate at which we send transactions to this peer, if configured.
+	extrinsics_rate_limit: Option<RateLimiter>,
 	/// Holds a set of blocks known to this peer.
 	known_blocks: LruHashSet<B::Hash>,
 	/// Request counter,
 	next_request_id: message::RequestId,
+	/// Latest finalized block reported by the peer in its block announces handshake.
+	finalized: Option<(NumberFor<B>, B::Hash)>,
+	/// Statistics about the block requests sent to the peer.
+	request_stats: RequestStats,
+}
+
+/// Statistics about the block requests sent to a peer.
+#[derive(Clone, Debug, Default)]
+pub struct RequestStats {
+	/// Number of requests sent.
+	pub sent: u64,
+	/// Number of requests answered.
+	pub answered: u64,
+	/// Number of requests that timed out.
+	pub timed_out: u64,
+	/// Moving average of the time taken by the peer to answer. `None` until the peer has
+	/// answered at least one request.
+	pub average_latency: Option<time::Duration>,
+}
+
+impl RequestStats {
+	fn on_response(&mut self, latency: time::Duration) {
+		self.answered += 1;
+		let sample = latency.as_secs_f64();
+		let average = match self.average_latency {
+			Some(average) =>
+				average.as_secs_f64() * (1.0 - LATENCY_SMOOTHING) + sample * LATENCY_SMOOTHING,
+			None => sample,
+		};
+		self.average_latency = Some(time::Duration::from_secs_f64(average));
+	}
 }
 
 /// Info about a peer's known state.
@@ -298,6 +369,22 @@ pub struct PeerInfo<B: BlockT> {
 	pub best_number: <B::Header as HeaderT>::Number,
 }
 
+/// Detailed information about a peer we are connected to, for diagnostic purposes.
+#[derive(Clone, Debug)]
+pub struct PeerDetails<B: BlockT> {
+	/// Information sent by the peer in its handshake and updated since.
+	pub info: PeerInfo<B>,
+	/// Sync status of the peer.
+	pub sync: Option<sync::PeerSyncDetails<B>>,
+	/// Notifications protocols for which the peer accepted a dedicated substream. Notifications
+	/// of the other protocols are sent on the legacy substream.
+	pub notifications_protocols: Vec<Cow<'static, [u8]>>,
+	/// Number and hash of the latest finalized block reported by the peer, if it did.
+	pub finalized: Option<(NumberFor<B>, B::Hash)>,
+	/// Statistics about the block requests sent to the peer.
+	pub requests: RequestStats,
+}
+
 /// Data necessary to create a context.
 struct ContextData<B: BlockT, H: ExHashT> {
 	// All connected peers
@@ -313,6 +400,17 @@ pub struct ProtocolConfig {
 	pub roles: Roles,
 	/// Maximum number of peers to ask the same blocks in parallel.
 	pub max_parallel_downloads: u32,
+	/// Catch up with the chain by downloading the state of the latest finalized block rather
+	/// than executing every block.
+	pub fast_sync: bool,
+	/// If set, blocks and justifications are only downloaded from these peers.
+	pub trusted_sync_peers: Option<HashSet<PeerId>>,
+	/// Interval after which an empty message is sent on idle notifications substreams, if any.
+	pub notifications_keep_alive: Option<time::Duration>,
+	/// Duration after which a peer whose notifications queue can't be flushed is reported, if any.
+	pub notifications_stall_timeout: Option<time::Duration>,
+	/// How transactions are propagated to other nodes.
+	pub transaction_propagation: TransactionPropagationConfig,
 }
 
 impl Default for ProtocolConfig {
@@ -320,6 +418,11 @@ impl Default for ProtocolConfig {
 		ProtocolConfig {
 			roles: Roles::FULL,
 			max_parallel_downloads: 5,
+			fast_sync: false,
+			trusted_sync_peers: None,
+			notifications_keep_alive: None,
+			notifications_stall_timeout: None,
+			transaction_propagation: Default::default(),
 		}
 	}
 }
@@ -335,6 +438,10 @@ struct BlockAnnouncesHandshake<B: BlockT> {
 	best_hash: B::Hash,
 	/// Genesis block hash.
 	genesis_hash: B::Hash,
+	/// Finalized block number.
+	finalized_number: NumberFor<B>,
+	/// Finalized block hash.
+	finalized_hash: B::Hash,
 }
 
 impl<B: BlockT> BlockAnnouncesHandshake<B> {
@@ -345,6 +452,8 @@ impl<B: BlockT> BlockAnnouncesHandshake<B> {
 			roles: protocol_config.roles,
 			best_number: info.best_number,
 			best_hash: info.best_hash,
+			finalized_number: info.finalized_number,
+			finalized_hash: info.finalized_hash,
 		}
 	}
 }
@@ -369,23 +478,30 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		transaction_pool: Arc<dyn TransactionPool<H, B>>,
 		finality_proof_provider: Option<Arc<dyn FinalityProofProvider<B>>>,
 		finality_proof_request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
+		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
 		protocol_id: ProtocolId,
 		peerset_config: sc_peerset::PeersetConfig,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
+		transaction_propagation_policy: Box<dyn TransactionPropagationPolicy>,
 		metrics_registry: Option<&Registry>,
 		boot_node_ids: Arc<HashSet<PeerId>>,
 		use_new_block_requests_protocol: bool,
 		queue_size_report: Option<HistogramVec>,
+		open_duration_report: Option<HistogramVec>,
+		traffic_dump: TrafficDump,
 	) -> error::Result<(Protocol<B, H>, sc_peerset::PeersetHandle)> {
 		let info = chain.info();
-		let sync = ChainSync::new(
+		let mut sync = ChainSync::new(
 			config.roles,
 			chain.clone(),
 			&info,
 			finality_proof_request_builder,
+			warp_sync_provider,
+			config.fast_sync,
 			block_announce_validator,
 			config.max_parallel_downloads,
 		);
+		sync.set_trusted_peers(config.trusted_sync_peers.clone());
 
 		let important_peers = {
 			let mut imp_p = HashSet::new();
@@ -397,13 +513,23 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		};
 
 		let (peerset, peerset_handle) = sc_peerset::Peerset::from_config(peerset_config);
+		peerset_handle.add_scorer("stale-block-announces", Box::new(sc_peerset::RatioScorer::new(
+			BLOCK_ANNOUNCE_OBSERVATION,
+			32,
+			90,
+			rep::STALE_BLOCK_ANNOUNCES,
+		)));
 		let versions = &((MIN_VERSION as u8)..=(CURRENT_VERSION as u8)).collect::<Vec<u8>>();
 		let mut behaviour = GenericProto::new(
 			local_peer_id,
 			protocol_id.clone(),
 			versions,
 			peerset,
-			queue_size_report
+			config.notifications_keep_alive,
+			config.notifications_stall_timeout,
+			queue_size_report,
+			open_duration_report,
+			traffic_dump,
 		);
 
 		let mut legacy_equiv_by_name = HashMap::new();
@@ -414,7 +540,12 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			proto.extend(b"/transactions/1");
 			proto
 		});
-		behaviour.register_notif_protocol(transactions_protocol.clone(), Vec::new());
+		behaviour.register_notif_protocol(
+			transactions_protocol.clone(),
+			Vec::new(),
+			NotificationsPriority::Low,
+			false,
+		);
 		legacy_equiv_by_name.insert(transactions_protocol.clone(), Fallback::Transactions);
 
 		let block_announces_protocol: Cow<'static, [u8]> = Cow::from({
@@ -425,13 +556,18 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		});
 		behaviour.register_notif_protocol(
 			block_announces_protocol.clone(),
-			BlockAnnouncesHandshake::build(&config, &chain).encode()
+			BlockAnnouncesHandshake::build(&config, &chain).encode(),
+			NotificationsPriority::Normal,
+			false,
 		);
 		legacy_equiv_by_name.insert(block_announces_protocol.clone(), Fallback::BlockAnnounce);
 
 		let protocol = Protocol {
 			tick_timeout: Box::pin(interval(TICK_TIMEOUT)),
 			propagate_timeout: Box::pin(interval(PROPAGATE_TIMEOUT)),
+			propagate_batch_timeout: Box::pin(interval(config.transaction_propagation.batch_interval)),
+			pending_propagation: Vec::new(),
+			transaction_propagation_policy,
 			pending_messages: VecDeque::new(),
 			pending_transactions: FuturesUnordered::new(),
 			config,
@@ -450,6 +586,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			behaviour,
 			protocol_name_by_engine: HashMap::new(),
 			legacy_equiv_by_name,
+			open_notif_substreams: HashMap::new(),
 			transactions_protocol,
 			block_announces_protocol,
 			metrics: if let Some(r) = metrics_registry {
@@ -533,6 +670,11 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.status().queued_blocks
 	}
 
+	/// Progress of the download of the bodies of old blocks, if any.
+	pub fn gap_sync_progress(&self) -> Option<GapSyncProgress<NumberFor<B>>> {
+		self.sync.status().gap_sync
+	}
+
 	/// Number of processed blocks.
 	pub fn num_processed_blocks(&self) -> usize {
 		self.sync.num_processed_blocks()
@@ -563,6 +705,23 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.context_data.peers.iter().map(|(id, peer)| (id, &peer.info))
 	}
 
+	/// Returns detailed information about all the peers we are connected to after the handshake
+	/// message.
+	pub fn peers_details(&self) -> impl Iterator<Item = (&PeerId, PeerDetails<B>)> {
+		self.context_data.peers.iter().map(move |(id, peer)| {
+			let notifications_protocols = self.open_notif_substreams.get(id)
+				.map(|protocols| protocols.iter().cloned().collect())
+				.unwrap_or_default();
+			(id, PeerDetails {
+				info: peer.info.clone(),
+				sync: self.sync.peer_details(id),
+				notifications_protocols,
+				finalized: peer.finalized,
+				requests: peer.request_stats.clone(),
+			})
+		})
+	}
+
 	pub fn on_custom_message(
 		&mut self,
 		who: PeerId,
@@ -702,6 +861,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		// lock all the the peer lists so that add/remove peer events are in order
 		let removed = {
 			self.handshaking_peers.remove(&peer);
+			self.open_notif_substreams.remove(&peer);
 			self.context_data.peers.remove(&peer)
 		};
 		if let Some(_peer_data) = removed {
@@ -832,7 +992,10 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			}
 			// Clear the request. If the response is invalid peer will be disconnected anyway.
 			match p.block_request.take() {
-				Some((_, request)) if request.id == response.id => request,
+				Some((sent_at, request)) if request.id == response.id => {
+					p.request_stats.on_response(sent_at.elapsed());
+					request
+				},
 				Some(_) =>  {
 					trace!(target: "sync", "Ignoring obsolete block response packet from {} ({})", peer, response.id);
 					return CustomMessageOutcome::None;
@@ -925,6 +1088,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.behaviour.disconnect_peer(peer);
 	}
 
+	/// Must be called in response to a [`CustomMessageOutcome::WarpProofRequest`] or a
+	/// [`CustomMessageOutcome::StateRequest`] if it has failed.
+	pub fn on_warp_sync_request_failed(&mut self, peer: &PeerId) {
+		self.sync.on_warp_sync_request_failed(peer);
+		self.peerset_handle.report_peer(peer.clone(), rep::TIMEOUT);
+		self.behaviour.disconnect_peer(peer);
+	}
+
 	/// Perform time based maintenance.
 	///
 	/// > **Note**: This method normally doesn't have to be called except for testing purposes.
@@ -937,13 +1108,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		let tick = Instant::now();
 		let mut aborting = Vec::new();
 		{
-			for (who, peer) in self.context_data.peers.iter() {
+			for (who, peer) in self.context_data.peers.iter_mut() {
 				if peer.block_request.as_ref().map_or(false, |(t, _)| (tick - *t).as_secs() > REQUEST_TIMEOUT_SEC) {
 					log!(
 						target: "sync",
 						if self.important_peers.contains(who) { Level::Warn } else { Level::Trace },
 						"Request timeout {}", who
 					);
+					peer.request_stats.timed_out += 1;
 					aborting.push(who.clone());
 				} else if peer.obsolete_requests.values().any(|t| (tick - *t).as_secs() > REQUEST_TIMEOUT_SEC) {
 					log!(
@@ -1065,10 +1237,14 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				block_request: None,
 				known_extrinsics: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_EXTRINSICS)
 					.expect("Constant is nonzero")),
+				extrinsics_rate_limit: self.config.transaction_propagation.max_per_peer_per_sec
+					.map(RateLimiter::new),
 				known_blocks: LruHashSet::new(NonZeroUsize::new(MAX_KNOWN_BLOCKS)
 					.expect("Constant is nonzero")),
 				next_request_id: 0,
 				obsolete_requests: HashMap::new(),
+				finalized: None,
+				request_stats: RequestStats::default(),
 			};
 			self.context_data.peers.insert(who.clone(), peer);
 
@@ -1111,11 +1287,15 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 	///
 	/// Doesn't do anything if we don't have a notifications substream for that protocol with that
 	/// peer.
+	///
+	/// If the protocol is in acknowledged mode, `confirmer` is used to confirm the delivery of the
+	/// notification.
 	pub fn write_notification(
 		&mut self,
 		target: PeerId,
 		engine_id: ConsensusEngineId,
 		message: impl Into<Vec<u8>>,
+		confirmer: Option<DeliveryConfirmer>,
 	) {
 		if let Some(protocol_name) = self.protocol_name_by_engine.get(&engine_id) {
 			let message = message.into();
@@ -1123,7 +1303,7 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 				engine_id,
 				data: message.clone(),
 			}).encode();
-			self.behaviour.write_notification(&target, protocol_name.clone(), message, fallback);
+			self.behaviour.write_notification(&target, protocol_name.clone(), message, fallback, confirmer);
 		} else {
 			error!(
 				target: "sub-libp2p",
@@ -1133,22 +1313,69 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		}
 	}
 
+	/// Send the same notification to each of the given peers. The message and its
+	/// backwards-compatible equivalent are only encoded once and shared between all the peers.
+	///
+	/// Returns, for each peer, whether we have a notifications channel open with it, or `None` if
+	/// the protocol isn't registered.
+	pub fn write_notification_group(
+		&mut self,
+		targets: &[PeerId],
+		engine_id: ConsensusEngineId,
+		message: Bytes,
+	) -> Option<Vec<bool>> {
+		let protocol_name = match self.protocol_name_by_engine.get(&engine_id) {
+			Some(protocol_name) => protocol_name,
+			None => {
+				error!(
+					target: "sub-libp2p",
+					"Sending a notification with a protocol that wasn't registered: {:?}",
+					engine_id
+				);
+				return None
+			}
+		};
+
+		let fallback = Bytes::from(GenericMessage::<(), (), (), ()>::Consensus(ConsensusMessage {
+			engine_id,
+			data: message.to_vec(),
+		}).encode());
+		let sent = targets.iter()
+			.map(|target| self.behaviour.write_notification(
+				target,
+				protocol_name.clone(),
+				message.clone(),
+				fallback.clone(),
+				None,
+			))
+			.collect();
+		Some(sent)
+	}
+
 	/// Registers a new notifications protocol.
 	///
 	/// While registering a protocol while we already have open connections is discouraged, we
 	/// nonetheless handle it by notifying that we opened channels with everyone. This function
 	/// returns a list of substreams to open as a result.
+	///
+	/// The `priority` decides which messages are sent first when the connection with a peer is
+	/// congested. See [`NotificationsPriority`].
+	///
+	/// If `acknowledged` is true, the remotes acknowledge the notifications they receive, and the
+	/// delivery of the notifications we send can be confirmed.
 	pub fn register_notifications_protocol<'a>(
 		&'a mut self,
 		engine_id: ConsensusEngineId,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
 		handshake_message: Vec<u8>,
+		priority: NotificationsPriority,
+		acknowledged: bool,
 	) -> impl ExactSizeIterator<Item = (&'a PeerId, Roles)> + 'a {
 		let protocol_name = protocol_name.into();
 		if self.protocol_name_by_engine.insert(engine_id, protocol_name.clone()).is_some() {
 			error!(target: "sub-libp2p", "Notifications protocol already registered: {:?}", protocol_name);
 		} else {
-			self.behaviour.register_notif_protocol(protocol_name.clone(), handshake_message);
+			self.behaviour.register_notif_protocol(protocol_name.clone(), handshake_message, priority, acknowledged);
 			self.legacy_equiv_by_name.insert(protocol_name, Fallback::Consensus(engine_id));
 		}
 
@@ -1156,6 +1383,19 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			.map(|(peer_id, peer)| (peer_id, peer.info.roles))
 	}
 
+	/// Modifies the handshake sent to remotes on the substreams of the notifications protocol
+	/// registered under `engine_id`.
+	///
+	/// Only the substreams opened afterwards use the new handshake. Has no effect if no protocol
+	/// is registered under `engine_id`.
+	pub fn set_notifications_handshake(&mut self, engine_id: ConsensusEngineId, handshake: Vec<u8>) {
+		if let Some(protocol_name) = self.protocol_name_by_engine.get(&engine_id) {
+			self.behaviour.set_notif_protocol_handshake(protocol_name, handshake);
+		} else {
+			error!(target: "sub-libp2p", "Handshake update for unknown protocol {:?}", engine_id);
+		}
+	}
+
 	/// Called when peer sends us new extrinsics
 	fn on_extrinsics(
 		&mut self,
@@ -1210,6 +1450,10 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 	}
 
 	/// Propagate one extrinsic.
+	///
+	/// The extrinsic is sent along with the other ones passed to this method at the next
+	/// `TransactionPropagationConfig::batch_interval`, or as soon as there are enough of them to
+	/// fill a batch.
 	pub fn propagate_extrinsic(
 		&mut self,
 		hash: &H,
@@ -1219,36 +1463,82 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		if self.sync.status().state != SyncState::Idle {
 			return;
 		}
-		if let Some(extrinsic) = self.transaction_pool.transaction(hash) {
-			let propagated_to = self.do_propagate_extrinsics(&[(hash.clone(), extrinsic)]);
-			self.transaction_pool.on_broadcasted(propagated_to);
+		self.pending_propagation.push(hash.clone());
+		if self.pending_propagation.len() >= self.config.transaction_propagation.max_batch_size {
+			self.propagate_pending_extrinsics();
 		}
 	}
 
+	/// Propagate the extrinsics passed to `propagate_extrinsic` since the last call.
+	fn propagate_pending_extrinsics(&mut self) {
+		if self.pending_propagation.is_empty() {
+			return;
+		}
+		// the node may have started syncing since the extrinsics were queued, they are sent again
+		// along with the whole pool by `propagate_extrinsics` once synced.
+		if self.sync.status().state != SyncState::Idle {
+			self.pending_propagation.clear();
+			return;
+		}
+
+		let extrinsics = mem::replace(&mut self.pending_propagation, Vec::new())
+			.into_iter()
+			.filter_map(|hash| self.transaction_pool.transaction(&hash).map(|extrinsic| (hash, extrinsic)))
+			.collect::<Vec<_>>();
+		let propagated_to = self.do_propagate_extrinsics(&extrinsics);
+		self.transaction_pool.on_broadcasted(propagated_to);
+	}
+
 	fn do_propagate_extrinsics(
 		&mut self,
 		extrinsics: &[(H, B::Extrinsic)],
 	) -> HashMap<H, Vec<String>> {
 		let mut propagated_to = HashMap::new();
-		for (who, peer) in self.context_data.peers.iter_mut() {
-			// never send extrinsics to the light node
-			if !peer.info.roles.is_full() {
-				continue;
-			}
+		if extrinsics.is_empty() {
+			return propagated_to;
+		}
+
+		// never send extrinsics to the light node
+		let full_peers = self.context_data.peers.iter()
+			.filter(|(_, peer)| peer.info.roles.is_full())
+			.map(|(who, _)| who.clone())
+			.collect();
+		let max_batch_size = cmp::max(self.config.transaction_propagation.max_batch_size, 1);
+
+		for who in self.transaction_propagation_policy.select_peers(full_peers) {
+			let peer = match self.context_data.peers.get_mut(&who) {
+				Some(peer) => peer,
+				None => continue,
+			};
 
-			let (hashes, to_send): (Vec<_>, Vec<_>) = extrinsics
+			let unknown = extrinsics
 				.iter()
-				.filter(|&(ref hash, _)| peer.known_extrinsics.insert(hash.clone()))
-				.cloned()
-				.unzip();
+				.filter(|(hash, _)| !peer.known_extrinsics.contains(hash))
+				.collect::<Vec<_>>();
+			let allowed = match peer.extrinsics_rate_limit.as_mut() {
+				Some(limit) => limit.take(unknown.len()),
+				None => unknown.len(),
+			};
+			if allowed < unknown.len() {
+				trace!(
+					target: "sync",
+					"Rate limit reached, delaying {} transactions to {}",
+					unknown.len() - allowed,
+					who,
+				);
+			}
 
-			if !to_send.is_empty() {
-				for hash in hashes {
+			for batch in unknown[..allowed].chunks(max_batch_size) {
+				let mut to_send = Vec::with_capacity(batch.len());
+				for (hash, extrinsic) in batch {
+					peer.known_extrinsics.insert(hash.clone());
 					propagated_to
-						.entry(hash)
+						.entry(hash.clone())
 						.or_insert_with(Vec::new)
 						.push(who.to_base58());
+					to_send.push(extrinsic.clone());
 				}
+
 				trace!(target: "sync", "Sending {} transactions to {}", to_send.len(), who);
 				let encoded = to_send.encode();
 				send_message::<B> (
@@ -1369,6 +1659,12 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 			peer.known_blocks.insert(hash.clone());
 		}
 
+		let is_stale = number <= self.context_data.chain.info().finalized_number;
+		self.peerset_handle.observe(
+			who.clone(),
+			sc_peerset::Observation { kind: BLOCK_ANNOUNCE_OBSERVATION, good: !is_stale },
+		);
+
 		let is_their_best = match announce.state.unwrap_or(message::BlockState::Best) {
 			message::BlockState::Best => true,
 			message::BlockState::Normal => false,
@@ -1439,10 +1735,69 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		}
 	}
 
+	/// Called when a peer has sent its handshake on the block announces substream.
+	///
+	/// Peers on another chain are disconnected right away, and the others have their chain
+	/// position passed to the sync layer.
+	fn on_block_announces_handshake(&mut self, who: PeerId, handshake: Vec<u8>) {
+		let handshake = match BlockAnnouncesHandshake::<B>::decode(&mut &handshake[..]) {
+			Ok(handshake) => handshake,
+			Err(err) => {
+				// Older nodes don't send the finalized block.
+				debug!(target: "sync", "Couldn't decode block announces handshake from {}: {}", who, err.what());
+				return
+			}
+		};
+
+		if handshake.genesis_hash != self.genesis_hash {
+			log!(
+				target: "sync",
+				if self.important_peers.contains(&who) { Level::Warn } else { Level::Trace },
+				"Peer is on different chain (our genesis: {} theirs: {})",
+				self.genesis_hash, handshake.genesis_hash
+			);
+			self.peerset_handle.report_peer(who.clone(), rep::GENESIS_MISMATCH);
+			self.behaviour.disconnect_peer(&who);
+
+			if self.boot_node_ids.contains(&who) {
+				error!(
+					target: "sync",
+					"Bootnode with peer id `{}` is on a different chain (our genesis: {} theirs: {})",
+					who,
+					self.genesis_hash,
+					handshake.genesis_hash,
+				);
+			}
+			return
+		}
+
+		if let Some(peer) = self.context_data.peers.get_mut(&who) {
+			peer.finalized = Some((handshake.finalized_number, handshake.finalized_hash));
+		}
+
+		let info = PeerChainInfo {
+			genesis_hash: handshake.genesis_hash,
+			best_number: handshake.best_number,
+			best_hash: handshake.best_hash,
+			finalized_number: handshake.finalized_number,
+			finalized_hash: handshake.finalized_hash,
+		};
+		if let Err(sync::BadPeer(id, repu)) = self.sync.on_block_announce_handshake(&who, &info) {
+			self.behaviour.disconnect_peer(&id);
+			self.peerset_handle.report_peer(id, repu);
+			return
+		}
+		self.update_peer_info(&who);
+	}
+
 	/// Call this when a block has been finalized. The sync layer may have some additional
 	/// requesting to perform.
 	pub fn on_block_finalized(&mut self, hash: B::Hash, header: &B::Header) {
-		self.sync.on_block_finalized(&hash, *header.number())
+		self.sync.on_block_finalized(&hash, *header.number());
+		self.behaviour.set_notif_protocol_handshake(
+			&self.block_announces_protocol,
+			BlockAnnouncesHandshake::build(&self.config, &self.context_data.chain).encode()
+		);
 	}
 
 	fn on_remote_call_request(
@@ -1500,6 +1855,12 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		self.sync.set_sync_fork_request(peers, hash, number)
 	}
 
+	/// Only download blocks and justifications from the given peers, or from any peer if `None`
+	/// is passed.
+	pub fn set_trusted_sync_peers(&mut self, peers: Option<HashSet<PeerId>>) {
+		self.sync.set_trusted_peers(peers)
+	}
+
 	/// A batch of blocks have been processed, with or without errors.
 	/// Call this when a batch of blocks have been processed by the importqueue, with or without
 	/// errors.
@@ -1832,6 +2193,37 @@ impl<B: BlockT, H: ExHashT> Protocol<B, H> {
 		}
 	}
 
+	/// Must be called after a [`CustomMessageOutcome::WarpProofRequest`] has been emitted,
+	/// to notify of the response having arrived.
+	pub fn on_warp_proof_response(&mut self, who: PeerId, proof: Vec<u8>) -> CustomMessageOutcome<B> {
+		trace!(target: "sync", "Warp sync proof response from {}", who);
+		if let Err(sync::BadPeer(id, repu)) = self.sync.on_warp_proof_response(&who, proof) {
+			self.behaviour.disconnect_peer(&id);
+			self.peerset_handle.report_peer(id, repu);
+		}
+		CustomMessageOutcome::None
+	}
+
+	/// Must be called after a [`CustomMessageOutcome::StateRequest`] has been emitted,
+	/// to notify of the response having arrived.
+	pub fn on_state_response(
+		&mut self,
+		who: PeerId,
+		response: schema::v1::state::StateResponse,
+	) -> CustomMessageOutcome<B> {
+		trace!(target: "sync", "State response from {} with {} entries", who, response.entries.len());
+		match self.sync.on_state_response(&who, response) {
+			Ok(sync::OnStateData::Continue) => CustomMessageOutcome::None,
+			Ok(sync::OnStateData::Import(origin, block)) =>
+				CustomMessageOutcome::BlockImport(origin, vec![block]),
+			Err(sync::BadPeer(id, repu)) => {
+				self.behaviour.disconnect_peer(&id);
+				self.peerset_handle.report_peer(id, repu);
+				CustomMessageOutcome::None
+			}
+		}
+	}
+
 	fn format_stats(&self) -> String {
 		let mut out = String::new();
 		for (id, stats) in &self.context_data.stats {
@@ -1902,6 +2294,8 @@ pub enum CustomMessageOutcome<B: BlockT> {
 	NotificationStreamOpened { remote: PeerId, protocols: Vec<ConsensusEngineId>, roles: Roles },
 	/// Notification protocols have been closed with a remote.
 	NotificationStreamClosed { remote: PeerId, protocols: Vec<ConsensusEngineId> },
+	/// A remote has sent its handshake on a notifications substream.
+	NotificationHandshakeReceived { remote: PeerId, engine_id: ConsensusEngineId, handshake: Vec<u8> },
 	/// Messages have been received on one or more notifications protocols.
 	NotificationsReceived { remote: PeerId, messages: Vec<(ConsensusEngineId, Bytes)> },
 	/// A new block request must be emitted.
@@ -1917,6 +2311,14 @@ pub enum CustomMessageOutcome<B: BlockT> {
 	/// If the request times out, or the peer responds in an invalid way, the peer has to be
 	/// disconnect. This will inform the state machine that the request it has emitted is stale.
 	FinalityProofRequest { target: PeerId, block_hash: B::Hash, request: Vec<u8> },
+	/// A new warp sync proof request must be emitted, for a proof starting at the block `begin`.
+	/// Once you have the response, you must call `Protocol::on_warp_proof_response`.
+	/// It is the responsibility of the handler to ensure that a timeout exists.
+	WarpProofRequest { target: PeerId, begin: B::Hash },
+	/// A new state request must be emitted.
+	/// Once you have the response, you must call `Protocol::on_state_response`.
+	/// It is the responsibility of the handler to ensure that a timeout exists.
+	StateRequest { target: PeerId, block_hash: B::Hash, start: Vec<Vec<u8>> },
 	/// Peer has a reported a new head of chain.
 	PeerNewBest(PeerId, NumberFor<B>),
 	None,
@@ -1938,6 +2340,7 @@ fn send_request<B: BlockT, H: ExHashT>(
 				peer.obsolete_requests.insert(request.id, timestamp);
 			}
 			peer.block_request = Some((Instant::now(), r.clone()));
+			peer.request_stats.sent += 1;
 		}
 	}
 	send_message::<B>(behaviour, stats, who, None, message)
@@ -1956,6 +2359,7 @@ fn update_peer_request<B: BlockT, H: ExHashT>(
 			peer.obsolete_requests.insert(request.id, timestamp);
 		}
 		peer.block_request = Some((Instant::now(), request.clone()));
+		peer.request_stats.sent += 1;
 	}
 }
 
@@ -1971,7 +2375,7 @@ fn send_message<B: BlockT>(
 	stats.bytes_out += encoded.len() as u64;
 	stats.count_out += 1;
 	if let Some((proto, msg)) = message {
-		behaviour.write_notification(who, proto, msg, encoded);
+		behaviour.write_notification(who, proto, msg, encoded, None);
 	} else {
 		behaviour.send_packet(who, encoded);
 	}
@@ -2036,6 +2440,10 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 			self.propagate_extrinsics();
 		}
 
+		while let Poll::Ready(Some(())) = self.propagate_batch_timeout.poll_next_unpin(cx) {
+			self.propagate_pending_extrinsics();
+		}
+
 		for (id, mut r) in self.sync.block_requests() {
 			if self.use_new_block_requests_protocol {
 				update_peer_request(&mut self.context_data.peers, &id, &mut r);
@@ -2090,6 +2498,18 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 				)
 			}
 		}
+		if let Some((id, begin)) = self.sync.warp_proof_request() {
+			let event = CustomMessageOutcome::WarpProofRequest { target: id, begin };
+			self.pending_messages.push_back(event);
+		}
+		if let Some((id, r)) = self.sync.state_request() {
+			let event = CustomMessageOutcome::StateRequest {
+				target: id,
+				block_hash: r.block,
+				start: r.start,
+			};
+			self.pending_messages.push_back(event);
+		}
 		if let Poll::Ready(Some((peer_id, result))) = self.pending_transactions.poll_next_unpin(cx) {
 			self.on_handle_extrinsic_import(peer_id, result);
 		}
@@ -2151,6 +2571,45 @@ impl<B: BlockT, H: ExHashT> NetworkBehaviour for Protocol<B, H> {
 						CustomMessageOutcome::None
 					}
 				}
+			GenericProtoOut::NotificationHandshake { peer_id, protocol_name, handshake } => {
+				self.open_notif_substreams.entry(peer_id.clone())
+					.or_default()
+					.insert(protocol_name.clone());
+				match self.legacy_equiv_by_name.get(&protocol_name) {
+					Some(Fallback::BlockAnnounce) => {
+						self.on_block_announces_handshake(peer_id, handshake);
+						CustomMessageOutcome::None
+					}
+					Some(Fallback::Consensus(engine_id)) =>
+						CustomMessageOutcome::NotificationHandshakeReceived {
+							remote: peer_id,
+							engine_id: *engine_id,
+							handshake,
+						},
+					_ => CustomMessageOutcome::None,
+				}
+			}
+			GenericProtoOut::NotificationStreamClosed { peer_id, protocol_name } => {
+				if let Some(protocols) = self.open_notif_substreams.get_mut(&peer_id) {
+					protocols.remove(&protocol_name);
+				}
+				CustomMessageOutcome::None
+			}
+			GenericProtoOut::SlowPeer { peer_id, protocol_name, reason } => {
+				let protocol_name = String::from_utf8_lossy(&protocol_name);
+				debug!(
+					target: "sync",
+					"Peer {} is slow to process {} notifications: {:?}",
+					peer_id,
+					protocol_name,
+					reason,
+				);
+				if let Some(metrics) = &self.metrics {
+					metrics.slow_peers.with_label_values(&[&*protocol_name, reason.as_str()]).inc();
+				}
+				self.peerset_handle.report_peer(peer_id, rep::SLOW_PEER);
+				CustomMessageOutcome::None
+			}
 			GenericProtoOut::Clogged { peer_id, messages } => {
 				debug!(target: "sync", "{} clogging messages:", messages.len());
 				for msg in messages.into_iter().take(5) {
@@ -2213,10 +2672,10 @@ impl<B: BlockT, H: ExHashT> Drop for Protocol<B, H> {
 mod tests {
 	use crate::PeerId;
 	use crate::config::EmptyTransactionPool;
-	use super::{CustomMessageOutcome, Protocol, ProtocolConfig};
+	use super::{CustomMessageOutcome, Protocol, ProtocolConfig, RequestStats};
 
 	use sp_consensus::block_validation::DefaultBlockAnnounceValidator;
-	use std::sync::Arc;
+	use std::{sync::Arc, time::Duration};
 	use substrate_test_runtime_client::{TestClientBuilder, TestClientBuilderExt};
 	use substrate_test_runtime_client::runtime::{Block, Hash};
 
@@ -2238,6 +2697,7 @@ mod tests {
 				bootnodes: Vec::new(),
 				reserved_only: false,
 				priority_groups: Vec::new(),
+				allowed_peers: None,
 			},
 			Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
 			None,
@@ -2253,4 +2713,17 @@ mod tests {
 			_ => panic!()
 		};
 	}
+
+	#[test]
+	fn request_latency_is_averaged() {
+		let mut stats = RequestStats::default();
+		assert_eq!(stats.average_latency, None);
+
+		stats.on_response(Duration::from_millis(100));
+		assert_eq!(stats.average_latency, Some(Duration::from_millis(100)));
+		stats.on_response(Duration::from_millis(200));
+		let average = stats.average_latency.unwrap().as_millis();
+		assert!(average > 100 && average < 200);
+		assert_eq!(stats.answered, 2);
+	}
 }
@@ -67,6 +67,21 @@ pub enum Event {
 		engine_id: ConsensusEngineId,
 	},
 
+	/// Received the handshake sent by the given node when opening its substream with the given
+	/// notifications protocol.
+	///
+	/// The handshake is initially the SCALE-encoded roles of the remote, unless it has been
+	/// modified with `NetworkService::set_notifications_handshake`. It can be received again,
+	/// with a different content, if the substream gets reopened.
+	NotificationHandshakeReceived {
+		/// Node that sent the handshake.
+		remote: PeerId,
+		/// The concerned protocol.
+		engine_id: ConsensusEngineId,
+		/// Handshake sent by the remote.
+		handshake: Bytes,
+	},
+
 	/// Received one or more messages from the given node using the given protocol.
 	NotificationsReceived {
 		/// Node we received the message from.
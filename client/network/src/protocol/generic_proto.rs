@@ -21,7 +21,8 @@
 //! network, then performs the Substrate protocol handling on top.
 
 pub use self::behaviour::{GenericProto, GenericProtoOut};
-pub use self::handler::LegacyConnectionKillError;
+pub use self::handler::{LegacyConnectionKillError, NotificationsPriority, SlowReason};
+pub use self::upgrade::DeliveryConfirmer;
 
 mod behaviour;
 mod handler;
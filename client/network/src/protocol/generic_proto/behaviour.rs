@@ -15,10 +15,13 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::config::ProtocolId;
-use crate::protocol::generic_proto::handler::{NotifsHandlerProto, NotifsHandlerOut, NotifsHandlerIn};
-use crate::protocol::generic_proto::upgrade::RegisteredProtocol;
+use crate::protocol::generic_proto::handler::{
+	NotifsHandlerProto, NotifsHandlerOut, NotifsHandlerIn, NotificationsPriority, SlowReason,
+};
+use crate::protocol::generic_proto::upgrade::{DeliveryConfirmer, RegisteredProtocol};
+use crate::traffic_dump::{Direction, TrafficDump};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use fnv::FnvHashMap;
 use futures::prelude::*;
 use libp2p::core::{ConnectedPoint, Multiaddr, PeerId, connection::ConnectionId};
@@ -116,9 +119,16 @@ pub struct GenericProto {
 	legacy_protocol: RegisteredProtocol,
 
 	/// Notification protocols. Entries are only ever added and not removed.
-	/// Contains, for each protocol, the protocol name and the message to send as part of the
-	/// initial handshake.
-	notif_protocols: Vec<(Cow<'static, [u8]>, Vec<u8>)>,
+	/// Contains, for each protocol, the protocol name, the message to send as part of the
+	/// initial handshake, the priority of the protocol, and whether it is used in acknowledged
+	/// mode.
+	notif_protocols: Vec<(Cow<'static, [u8]>, Vec<u8>, NotificationsPriority, bool)>,
+
+	/// Interval after which an empty message is sent on idle notifications substreams, if any.
+	notifications_keep_alive: Option<Duration>,
+
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
 
 	/// Receiver for instructions about who to connect to or disconnect from.
 	peerset: sc_peerset::Peerset,
@@ -149,8 +159,15 @@ pub struct GenericProto {
 	/// Events to produce from `poll()`.
 	events: VecDeque<NetworkBehaviourAction<NotifsHandlerIn, GenericProtoOut>>,
 
+	/// If `Some`, the peers whose notifications queue can't be flushed for this long are reported
+	/// with [`GenericProtoOut::SlowPeer`].
+	notifications_stall_timeout: Option<Duration>,
+
 	/// If `Some`, report the message queue sizes on this `Histogram`.
 	queue_size_report: Option<HistogramVec>,
+
+	/// If `Some`, report the time it takes to open notifications substreams on this `Histogram`.
+	open_duration_report: Option<HistogramVec>,
 }
 
 /// Identifier for a delay firing.
@@ -324,6 +341,35 @@ pub enum GenericProtoOut {
 		message: BytesMut,
 	},
 
+	/// A notifications substream has been opened with a peer, which sent back a handshake.
+	NotificationHandshake {
+		/// Id of the peer the handshake came from.
+		peer_id: PeerId,
+		/// Engine corresponding to the substream.
+		protocol_name: Cow<'static, [u8]>,
+		/// Handshake message sent by the peer.
+		handshake: Vec<u8>,
+	},
+
+	/// A notifications substream previously reported with `NotificationHandshake` has been
+	/// closed.
+	NotificationStreamClosed {
+		/// Id of the peer the substream was opened with.
+		peer_id: PeerId,
+		/// Engine corresponding to the substream.
+		protocol_name: Cow<'static, [u8]>,
+	},
+
+	/// A peer is slow to process the notifications we send it.
+	SlowPeer {
+		/// Id of the peer.
+		peer_id: PeerId,
+		/// Engine corresponding to the substream.
+		protocol_name: Cow<'static, [u8]>,
+		/// What the peer is slow at.
+		reason: SlowReason,
+	},
+
 	/// The substream used by the protocol is pretty large. We should print avoid sending more
 	/// messages on it if possible.
 	Clogged {
@@ -337,14 +383,25 @@ pub enum GenericProtoOut {
 impl GenericProto {
 	/// Creates a `CustomProtos`.
 	///
-	/// The `queue_size_report` is an optional Prometheus metric that can report the size of the
-	/// messages queue. If passed, it must have one label for the protocol name.
+	/// If `notifications_keep_alive` is `Some`, an empty message is sent on the notifications
+	/// substreams every time nothing has been sent on them for this long.
+	///
+	/// If `notifications_stall_timeout` is `Some`, [`GenericProtoOut::SlowPeer`] is emitted every
+	/// time the notifications queue of a peer couldn't be flushed for this long.
+	///
+	/// The `queue_size_report` and `open_duration_report` are optional Prometheus metrics that
+	/// can report the size of the messages queue and the time it takes to open a notifications
+	/// substream. If passed, they must have one label for the protocol name.
 	pub fn new(
 		local_peer_id: PeerId,
 		protocol: impl Into<ProtocolId>,
 		versions: &[u8],
 		peerset: sc_peerset::Peerset,
+		notifications_keep_alive: Option<Duration>,
+		notifications_stall_timeout: Option<Duration>,
 		queue_size_report: Option<HistogramVec>,
+		open_duration_report: Option<HistogramVec>,
+		traffic_dump: TrafficDump,
 	) -> Self {
 		let legacy_protocol = RegisteredProtocol::new(protocol, versions);
 
@@ -352,6 +409,8 @@ impl GenericProto {
 			local_peer_id,
 			legacy_protocol,
 			notif_protocols: Vec::new(),
+			notifications_keep_alive,
+			traffic_dump,
 			peerset,
 			peers: FnvHashMap::default(),
 			delays: Default::default(),
@@ -359,7 +418,9 @@ impl GenericProto {
 			incoming: SmallVec::new(),
 			next_incoming_index: sc_peerset::IncomingIndex(0),
 			events: VecDeque::new(),
+			notifications_stall_timeout,
 			queue_size_report,
+			open_duration_report,
 		}
 	}
 
@@ -367,12 +428,17 @@ impl GenericProto {
 	///
 	/// You are very strongly encouraged to call this method very early on. Any open connection
 	/// will retain the protocols that were registered then, and not any new one.
+	///
+	/// If `acknowledged` is true, the remotes acknowledge the notifications they receive. They
+	/// must have registered the protocol in acknowledged mode as well.
 	pub fn register_notif_protocol(
 		&mut self,
 		protocol_name: impl Into<Cow<'static, [u8]>>,
-		handshake_msg: impl Into<Vec<u8>>
+		handshake_msg: impl Into<Vec<u8>>,
+		priority: NotificationsPriority,
+		acknowledged: bool,
 	) {
-		self.notif_protocols.push((protocol_name.into(), handshake_msg.into()));
+		self.notif_protocols.push((protocol_name.into(), handshake_msg.into(), priority, acknowledged));
 	}
 
 	/// Modifies the handshake of the given notifications protocol.
@@ -384,7 +450,7 @@ impl GenericProto {
 		handshake_message: impl Into<Vec<u8>>
 	) {
 		let handshake_message = handshake_message.into();
-		if let Some(protocol) = self.notif_protocols.iter_mut().find(|(name, _)| name == &protocol_name) {
+		if let Some(protocol) = self.notif_protocols.iter_mut().find(|(name, _, _, _)| name == &protocol_name) {
 			protocol.1 = handshake_message.clone();
 		} else {
 			return;
@@ -547,7 +613,8 @@ impl GenericProto {
 
 	/// Sends a notification to a peer.
 	///
-	/// Has no effect if the custom protocol is not open with the given peer.
+	/// Has no effect if the custom protocol is not open with the given peer, in which case false
+	/// is returned.
 	///
 	/// Also note that even if we have a valid open substream, it may in fact be already closed
 	/// without us knowing, in which case the packet will not be received.
@@ -555,19 +622,23 @@ impl GenericProto {
 	/// The `fallback` parameter is used for backwards-compatibility reason if the remote doesn't
 	/// support our protocol. One needs to pass the equivalent of what would have been passed
 	/// with `send_packet`.
+	///
+	/// If the protocol is in acknowledged mode, `confirmer` is used to confirm the delivery of the
+	/// notification. The confirmation is cancelled if the notification is lost.
 	pub fn write_notification(
 		&mut self,
 		target: &PeerId,
 		protocol_name: Cow<'static, [u8]>,
-		message: impl Into<Vec<u8>>,
-		encoded_fallback_message: Vec<u8>,
-	) {
+		message: impl Into<Bytes>,
+		encoded_fallback_message: impl Into<Bytes>,
+		confirmer: Option<DeliveryConfirmer>,
+	) -> bool {
 		let conn = match self.peers.get(target).and_then(|p| p.get_open()) {
 			None => {
 				debug!(target: "sub-libp2p",
 					"Tried to sent notification to {:?} without an open channel.",
 					target);
-				return
+				return false
 			},
 			Some(conn) => conn
 		};
@@ -580,15 +651,20 @@ impl GenericProto {
 		);
 		trace!(target: "sub-libp2p", "Handler({:?}) <= Packet", target);
 
+		let message = message.into();
+		self.traffic_dump.record(Direction::Out, &protocol_name, target, &message);
+
 		self.events.push_back(NetworkBehaviourAction::NotifyHandler {
 			peer_id: target.clone(),
 			handler: NotifyHandler::One(conn),
 			event: NotifsHandlerIn::SendNotification {
-				message: message.into(),
-				encoded_fallback_message,
+				message,
+				encoded_fallback_message: encoded_fallback_message.into(),
 				protocol_name,
+				confirmer,
 			},
 		});
+		true
 	}
 
 	/// Sends a message to a peer.
@@ -610,6 +686,7 @@ impl GenericProto {
 
 		trace!(target: "sub-libp2p", "External API => Packet for {:?}", target);
 		trace!(target: "sub-libp2p", "Handler({:?}) <= Packet", target);
+		self.traffic_dump.record(Direction::Out, self.legacy_protocol.base_name(), target, &message);
 		self.events.push_back(NetworkBehaviourAction::NotifyHandler {
 			peer_id: target.clone(),
 			handler: NotifyHandler::One(conn),
@@ -897,7 +974,10 @@ impl NetworkBehaviour for GenericProto {
 		NotifsHandlerProto::new(
 			self.legacy_protocol.clone(),
 			self.notif_protocols.clone(),
-			self.queue_size_report.clone()
+			self.notifications_keep_alive,
+			self.notifications_stall_timeout,
+			self.queue_size_report.clone(),
+			self.open_duration_report.clone(),
 		)
 	}
 
@@ -1302,6 +1382,7 @@ impl NetworkBehaviour for GenericProto {
 				debug_assert!(self.is_open(&source));
 				trace!(target: "sub-libp2p", "Handler({:?}) => Message", source);
 				trace!(target: "sub-libp2p", "External API <= Message({:?})", source);
+				self.traffic_dump.record(Direction::In, self.legacy_protocol.base_name(), &source, &message);
 				let event = GenericProtoOut::LegacyMessage {
 					peer_id: source,
 					message,
@@ -1319,6 +1400,7 @@ impl NetworkBehaviour for GenericProto {
 					str::from_utf8(&protocol_name)
 				);
 				trace!(target: "sub-libp2p", "External API <= Message({:?}, {:?})", protocol_name, source);
+				self.traffic_dump.record(Direction::In, &protocol_name, &source, &message);
 				let event = GenericProtoOut::Notification {
 					peer_id: source,
 					protocol_name,
@@ -1328,6 +1410,53 @@ impl NetworkBehaviour for GenericProto {
 				self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
 			}
 
+			NotifsHandlerOut::NotificationHandshake { protocol_name, handshake } => {
+				trace!(
+					target: "sub-libp2p",
+					"Handler({:?}) => NotificationHandshake({:?})",
+					source,
+					str::from_utf8(&protocol_name)
+				);
+				trace!(target: "sub-libp2p", "External API <= Handshake({:?}, {:?})", protocol_name, source);
+				let event = GenericProtoOut::NotificationHandshake {
+					peer_id: source,
+					protocol_name,
+					handshake,
+				};
+
+				self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+			}
+
+			NotifsHandlerOut::NotificationStreamClosed { protocol_name } => {
+				trace!(
+					target: "sub-libp2p",
+					"Handler({:?}) => NotificationStreamClosed({:?})",
+					source,
+					str::from_utf8(&protocol_name)
+				);
+				let event = GenericProtoOut::NotificationStreamClosed {
+					peer_id: source,
+					protocol_name,
+				};
+
+				self.events.push_back(NetworkBehaviourAction::GenerateEvent(event));
+			}
+
+			NotifsHandlerOut::Slow { protocol_name, reason } => {
+				debug!(
+					target: "sub-libp2p",
+					"Handler({:?}) => Slow({:?}, {:?})",
+					source,
+					str::from_utf8(&protocol_name),
+					reason,
+				);
+				self.events.push_back(NetworkBehaviourAction::GenerateEvent(GenericProtoOut::SlowPeer {
+					peer_id: source,
+					protocol_name,
+					reason,
+				}));
+			}
+
 			NotifsHandlerOut::Clogged { messages } => {
 				debug_assert!(self.is_open(&source));
 				trace!(target: "sub-libp2p", "Handler({:?}) => Clogged", source);
@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 pub use self::group::{NotifsHandlerProto, NotifsHandler, NotifsHandlerIn, NotifsHandlerOut};
 pub use self::legacy::ConnectionKillError as LegacyConnectionKillError;
+pub use self::notif_out::{NotificationsPriority, SlowReason};
 
 mod group;
 mod legacy;
@@ -44,15 +44,29 @@
 //! The user has the choice between sending a message with `SendNotification`, to send a
 //! notification, and `SendLegacy`, to send any other kind of message.
 //!
+//! # Priorities
+//!
+//! Each notification protocol has a [`NotificationsPriority`]. The outbound substreams are
+//! flushed in order of priority, and the substreams of a given priority are only flushed once
+//! the queues of all the substreams with a higher priority are empty. While the queue of a
+//! substream with a higher priority isn't empty, notifications of protocols with a
+//! [`NotificationsPriority::Low`] priority are dropped instead of being queued.
+//!
 
 use crate::protocol::generic_proto::{
 	handler::legacy::{LegacyProtoHandler, LegacyProtoHandlerProto, LegacyProtoHandlerIn, LegacyProtoHandlerOut},
 	handler::notif_in::{NotifsInHandlerProto, NotifsInHandler, NotifsInHandlerIn, NotifsInHandlerOut},
-	handler::notif_out::{NotifsOutHandlerProto, NotifsOutHandler, NotifsOutHandlerIn, NotifsOutHandlerOut},
-	upgrade::{NotificationsIn, NotificationsOut, NotificationsHandshakeError, RegisteredProtocol, UpgradeCollec},
+	handler::notif_out::{
+		NotificationsPriority, NotifsOutHandlerProto, NotifsOutHandler, NotifsOutHandlerIn, NotifsOutHandlerOut,
+		SlowReason,
+	},
+	upgrade::{
+		DeliveryConfirmer, NotificationsIn, NotificationsOut, NotificationsHandshakeError, RegisteredProtocol,
+		UpgradeCollec,
+	},
 };
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use libp2p::core::{either::{EitherError, EitherOutput}, ConnectedPoint, PeerId};
 use libp2p::core::upgrade::{EitherUpgrade, UpgradeError, SelectUpgrade, InboundUpgrade, OutboundUpgrade};
 use libp2p::swarm::{
@@ -65,7 +79,7 @@ use libp2p::swarm::{
 };
 use log::{debug, error};
 use prometheus_endpoint::HistogramVec;
-use std::{borrow::Cow, error, io, str, task::{Context, Poll}};
+use std::{borrow::Cow, error, io, str, task::{Context, Poll}, time::Duration};
 
 /// Implements the `IntoProtocolsHandler` trait of libp2p.
 ///
@@ -80,6 +94,7 @@ pub struct NotifsHandlerProto {
 	in_handlers: Vec<(NotifsInHandlerProto, Vec<u8>)>,
 
 	/// Prototypes for handlers for outbound substreams, and the initial handshake message we send.
+	/// Ordered by decreasing priority.
 	out_handlers: Vec<(NotifsOutHandlerProto, Vec<u8>)>,
 
 	/// Prototype for handler for backwards-compatibility.
@@ -93,7 +108,8 @@ pub struct NotifsHandler {
 	/// Handlers for inbound substreams, and the message we respond with in the handshake.
 	in_handlers: Vec<(NotifsInHandler, Vec<u8>)>,
 
-	/// Handlers for outbound substreams, and the initial handshake message we send.
+	/// Handlers for outbound substreams, and the initial handshake message we send. Ordered by
+	/// decreasing priority.
 	out_handlers: Vec<(NotifsOutHandler, Vec<u8>)>,
 
 	/// Handler for backwards-compatibility.
@@ -106,6 +122,10 @@ pub struct NotifsHandler {
 	/// we push the corresponding index here and process them when the handler
 	/// gets enabled/disabled.
 	pending_in: Vec<usize>,
+
+	/// Priority of the outbound substream with the highest priority whose messages queue wasn't
+	/// empty the last time we polled it, if any.
+	congested: Option<NotificationsPriority>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -139,6 +159,7 @@ impl IntoProtocolsHandler for NotifsHandlerProto {
 			legacy: self.legacy.into_handler(remote_peer_id, connected_point),
 			enabled: EnabledState::Initial,
 			pending_in: Vec::new(),
+			congested: None,
 		}
 	}
 }
@@ -184,10 +205,15 @@ pub enum NotifsHandlerIn {
 		/// Message to send on the legacy substream if the protocol isn't available.
 		///
 		/// This corresponds to what you would have sent with `SendLegacy`.
-		encoded_fallback_message: Vec<u8>,
+		encoded_fallback_message: Bytes,
 
 		/// The message to send.
-		message: Vec<u8>,
+		message: Bytes,
+
+		/// Confirms the delivery of the message if the protocol is in acknowledged mode.
+		///
+		/// The confirmation is cancelled if the message is sent on the legacy substream.
+		confirmer: Option<DeliveryConfirmer>,
 	},
 }
 
@@ -226,6 +252,31 @@ pub enum NotifsHandlerOut {
 		message: BytesMut,
 	},
 
+	/// A notifications substream to the remote has been opened, and the remote sent back its
+	/// handshake.
+	NotificationHandshake {
+		/// Name of the protocol of the substream.
+		protocol_name: Cow<'static, [u8]>,
+
+		/// Handshake message sent by the remote.
+		handshake: Vec<u8>,
+	},
+
+	/// A notifications substream to the remote, previously reported with
+	/// `NotificationHandshake`, has been closed.
+	NotificationStreamClosed {
+		/// Name of the protocol of the substream.
+		protocol_name: Cow<'static, [u8]>,
+	},
+
+	/// The remote is slow to process the notifications we send it.
+	Slow {
+		/// Name of the protocol of the substream.
+		protocol_name: Cow<'static, [u8]>,
+		/// What the remote is slow at.
+		reason: SlowReason,
+	},
+
 	/// A substream to the remote is clogged. The send buffer is very large, and we should print
 	/// a diagnostic message and/or avoid sending more data.
 	Clogged {
@@ -245,38 +296,61 @@ pub enum NotifsHandlerOut {
 impl NotifsHandlerProto {
 	/// Builds a new handler.
 	///
-	/// `list` is a list of notification protocols names, and the message to send as part of the
-	/// handshake. At the moment, the message is always the same whether we open a substream
-	/// ourselves or respond to handshake from the remote.
+	/// `list` is a list of notification protocols names, the message to send as part of the
+	/// handshake, the priority of the protocol, and whether it is used in acknowledged mode. At the
+	/// moment, the message is always the same whether we open a substream ourselves or respond to
+	/// handshake from the remote.
+	///
+	/// If `keep_alive` is `Some`, an empty message is sent on the outbound substreams every time
+	/// nothing has been sent on them for this long.
 	///
-	/// The `queue_size_report` is an optional Prometheus metric that can report the size of the
-	/// messages queue. If passed, it must have one label for the protocol name.
+	/// If `stall_timeout` is `Some`, [`NotifsHandlerOut::Slow`] is emitted every time the
+	/// messages queue of an outbound substream couldn't be flushed for this long.
+	///
+	/// The `queue_size_report` and `open_duration_report` are optional Prometheus metrics that
+	/// can report the size of the messages queue and the time it takes to open a substream. If
+	/// passed, they must have one label for the protocol name.
 	pub fn new(
 		legacy: RegisteredProtocol,
-		list: impl Into<Vec<(Cow<'static, [u8]>, Vec<u8>)>>,
-		queue_size_report: Option<HistogramVec>
+		list: impl Into<Vec<(Cow<'static, [u8]>, Vec<u8>, NotificationsPriority, bool)>>,
+		keep_alive: Option<Duration>,
+		stall_timeout: Option<Duration>,
+		queue_size_report: Option<HistogramVec>,
+		open_duration_report: Option<HistogramVec>,
 	) -> Self {
 		let list = list.into();
 
-		let out_handlers = list
-			.clone()
+		let mut by_priority = list.clone();
+		by_priority.sort_by(|(_, _, a, _), (_, _, b, _)| b.cmp(a));
+		let out_handlers = by_priority
 			.into_iter()
-			.map(|(proto_name, initial_message)| {
-				let queue_size_report = queue_size_report.as_ref().and_then(|qs| {
+			.map(|(proto_name, initial_message, priority, acknowledged)| {
+				let with_label = |metric: &HistogramVec| {
 					if let Ok(utf8) = str::from_utf8(&proto_name) {
-						Some(qs.with_label_values(&[utf8]))
+						Some(metric.with_label_values(&[utf8]))
 					} else {
 						log::warn!("Ignoring Prometheus metric because {:?} isn't UTF-8", proto_name);
 						None
 					}
-				});
-
-				(NotifsOutHandlerProto::new(proto_name, queue_size_report), initial_message)
+				};
+				let queue_size_report = queue_size_report.as_ref().and_then(with_label);
+				let open_duration_report = open_duration_report.as_ref().and_then(with_label);
+
+				let proto = NotifsOutHandlerProto::new(
+					proto_name,
+					priority,
+					keep_alive,
+					stall_timeout,
+					queue_size_report,
+					open_duration_report,
+					acknowledged,
+				);
+				(proto, initial_message)
 			}).collect();
 
-		let in_handlers = list.clone()
+		let in_handlers = list
 			.into_iter()
-			.map(|(proto_name, msg)| (NotifsInHandlerProto::new(proto_name), msg))
+			.map(|(proto_name, msg, _, acknowledged)| (NotifsInHandlerProto::new(proto_name, acknowledged), msg))
 			.collect();
 
 		NotifsHandlerProto {
@@ -387,20 +461,30 @@ impl ProtocolsHandler for NotifsHandler {
 					}
 				}
 			}
-			NotifsHandlerIn::SendNotification { message, encoded_fallback_message, protocol_name } => {
+			NotifsHandlerIn::SendNotification { message, encoded_fallback_message, protocol_name, confirmer } => {
 				for (handler, _) in &mut self.out_handlers {
 					if handler.protocol_name() != &protocol_name[..] {
 						continue;
 					}
 
+					let priority = handler.priority();
+					if priority == NotificationsPriority::Low && self.congested.map_or(false, |p| p > priority) {
+						debug!(
+							target: "sub-libp2p",
+							"Connection is congested, dropped notification (protocol: {:?})",
+							protocol_name,
+						);
+						return;
+					}
+
 					if handler.is_open() {
-						handler.inject_event(NotifsOutHandlerIn::Send(message));
+						handler.inject_event(NotifsOutHandlerIn::Send(message, confirmer));
 						return;
 					}
 				}
 
 				self.legacy.inject_event(LegacyProtoHandlerIn::SendCustomMessage {
-					message: encoded_fallback_message,
+					message: encoded_fallback_message.to_vec(),
 				});
 			},
 		}
@@ -545,7 +629,11 @@ impl ProtocolsHandler for NotifsHandler {
 			}
 		}
 
+		// The handlers are ordered by decreasing priority. Once a queue isn't empty after having
+		// been polled, the queues of lower priorities are left untouched until it drains.
+		let mut congested = None;
 		for (handler_num, (handler, _)) in self.out_handlers.iter_mut().enumerate() {
+			handler.set_flush_paused(congested.map_or(false, |p| p > handler.priority()));
 			while let Poll::Ready(ev) = handler.poll(cx) {
 				match ev {
 					ProtocolsHandlerEvent::OutboundSubstreamRequest { protocol, info: () } =>
@@ -555,18 +643,40 @@ impl ProtocolsHandler for NotifsHandler {
 						}),
 					ProtocolsHandlerEvent::Close(err) => void::unreachable(err),
 
-					// At the moment we don't actually care whether any notifications protocol
-					// opens or closes.
 					// Whether our communications with the remote are open or closed entirely
 					// depends on the legacy substream, because as long as we are open the user of
 					// this struct might try to send legacy protocol messages which we need to
-					// deliver for things to work properly.
-					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Open { .. }) => {},
-					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Closed) => {},
+					// deliver for things to work properly. We only report the handshake of the
+					// remote, which can carry useful information about it.
+					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Open { handshake }) => {
+						let msg = NotifsHandlerOut::NotificationHandshake {
+							protocol_name: handler.protocol_name().to_owned().into(),
+							handshake,
+						};
+						return Poll::Ready(ProtocolsHandlerEvent::Custom(msg));
+					},
+					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Closed) => {
+						let msg = NotifsHandlerOut::NotificationStreamClosed {
+							protocol_name: handler.protocol_name().to_owned().into(),
+						};
+						return Poll::Ready(ProtocolsHandlerEvent::Custom(msg));
+					},
 					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Refused) => {},
+					ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Slow(reason)) => {
+						let msg = NotifsHandlerOut::Slow {
+							protocol_name: handler.protocol_name().to_owned().into(),
+							reason,
+						};
+						return Poll::Ready(ProtocolsHandlerEvent::Custom(msg));
+					},
 				}
 			}
+
+			if congested.is_none() && handler.queue_len() > 0 {
+				congested = Some(handler.priority());
+			}
 		}
+		self.congested = congested;
 
 		Poll::Pending
 	}
@@ -108,11 +108,14 @@ pub enum NotifsInHandlerOut {
 
 impl NotifsInHandlerProto {
 	/// Builds a new `NotifsInHandlerProto`.
+	///
+	/// If `acknowledged` is true, the notifications received are acknowledged to the remote.
 	pub fn new(
-		protocol_name: impl Into<Cow<'static, [u8]>>
+		protocol_name: impl Into<Cow<'static, [u8]>>,
+		acknowledged: bool,
 	) -> Self {
 		NotifsInHandlerProto {
-			in_protocol: NotificationsIn::new(protocol_name),
+			in_protocol: NotificationsIn::new(protocol_name).with_acknowledgements(acknowledged),
 		}
 	}
 }
@@ -236,21 +239,26 @@ impl ProtocolsHandler for NotifsInHandler {
 			return Poll::Ready(event)
 		}
 
-		match self.substream.as_mut().map(|s| Stream::poll_next(Pin::new(s), cx)) {
-			None | Some(Poll::Pending) => {},
-			Some(Poll::Ready(Some(Ok(msg)))) => {
-				if self.pending_accept_refuses != 0 {
-					warn!(
-						target: "sub-libp2p",
-						"Bad state in inbound-only handler: notif before accepting substream"
-					);
-				}
-				return Poll::Ready(ProtocolsHandlerEvent::Custom(NotifsInHandlerOut::Notif(msg)))
-			},
-			Some(Poll::Ready(None)) | Some(Poll::Ready(Some(Err(_)))) => {
-				self.substream = None;
-				return Poll::Ready(ProtocolsHandlerEvent::Custom(NotifsInHandlerOut::Closed));
-			},
+		loop {
+			match self.substream.as_mut().map(|s| Stream::poll_next(Pin::new(s), cx)) {
+				None | Some(Poll::Pending) => break,
+				// Empty messages are keep-alive frames sent by the remote while the substream is
+				// idle, and aren't reported.
+				Some(Poll::Ready(Some(Ok(ref msg)))) if msg.is_empty() => {},
+				Some(Poll::Ready(Some(Ok(msg)))) => {
+					if self.pending_accept_refuses != 0 {
+						warn!(
+							target: "sub-libp2p",
+							"Bad state in inbound-only handler: notif before accepting substream"
+						);
+					}
+					return Poll::Ready(ProtocolsHandlerEvent::Custom(NotifsInHandlerOut::Notif(msg)))
+				},
+				Some(Poll::Ready(None)) | Some(Poll::Ready(Some(Err(_)))) => {
+					self.substream = None;
+					return Poll::Ready(ProtocolsHandlerEvent::Custom(NotifsInHandlerOut::Closed));
+				},
+			}
 		}
 
 		Poll::Pending
@@ -21,8 +21,12 @@
 //! >			protocols, you need to create multiple instances and group them.
 //!
 
-use crate::protocol::generic_proto::upgrade::{NotificationsOut, NotificationsOutSubstream, NotificationsHandshakeError};
+use crate::protocol::generic_proto::upgrade::{
+	DeliveryConfirmer, NotificationsOut, NotificationsOutSubstream, NotificationsHandshakeError,
+};
+use bytes::Bytes;
 use futures::prelude::*;
+use futures_timer::Delay;
 use libp2p::core::{ConnectedPoint, PeerId};
 use libp2p::core::upgrade::{DeniedUpgrade, InboundUpgrade, OutboundUpgrade};
 use libp2p::swarm::{
@@ -46,6 +50,46 @@ const OPEN_TIMEOUT: Duration = Duration::from_secs(10);
 /// open substreams.
 const INITIAL_KEEPALIVE_TIME: Duration = Duration::from_secs(5);
 
+/// Priority of a notifications protocol.
+///
+/// When the connection with a remote is congested, the messages of the protocols with a higher
+/// priority are sent first, and new messages of protocols with a [`NotificationsPriority::Low`]
+/// priority are dropped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NotificationsPriority {
+	/// Messages that can be lost without harm, such as transactions.
+	Low,
+	/// Default priority.
+	Normal,
+	/// Time-sensitive messages, such as consensus votes.
+	High,
+}
+
+impl Default for NotificationsPriority {
+	fn default() -> Self {
+		NotificationsPriority::Normal
+	}
+}
+
+/// Why a remote is considered slow to process our notifications.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SlowReason {
+	/// The remote didn't accept the substream within `OPEN_TIMEOUT`.
+	OpenTimeout,
+	/// The messages queue hasn't been flushed to the substream for this long.
+	Stalled(Duration),
+}
+
+impl SlowReason {
+	/// Returns a short description of the reason, for logs and metrics labels.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SlowReason::OpenTimeout => "open-timeout",
+			SlowReason::Stalled(_) => "stalled",
+		}
+	}
+}
+
 /// Implements the `IntoProtocolsHandler` trait of libp2p.
 ///
 /// Every time a connection with a remote starts, an instance of this struct is created and
@@ -56,17 +100,49 @@ const INITIAL_KEEPALIVE_TIME: Duration = Duration::from_secs(5);
 pub struct NotifsOutHandlerProto {
 	/// Name of the protocol to negotiate.
 	protocol_name: Cow<'static, [u8]>,
+	/// Priority of the protocol.
+	priority: NotificationsPriority,
+	/// Interval after which a keep-alive message is sent on an idle substream, if any.
+	keep_alive: Option<Duration>,
+	/// Duration after which a messages queue that can't be flushed is reported, if any.
+	stall_timeout: Option<Duration>,
 	/// Optional Prometheus histogram to report message queue size variations.
 	queue_size_report: Option<Histogram>,
+	/// Optional Prometheus histogram to report the time it takes to open the substream.
+	open_duration_report: Option<Histogram>,
+	/// If true, the remote acknowledges the notifications it receives.
+	acknowledged: bool,
 }
 
 impl NotifsOutHandlerProto {
 	/// Builds a new [`NotifsOutHandlerProto`]. Will use the given protocol name for the
 	/// notifications substream.
-	pub fn new(protocol_name: impl Into<Cow<'static, [u8]>>, queue_size_report: Option<Histogram>) -> Self {
+	///
+	/// If `keep_alive` is `Some`, an empty message is sent on the substream every time nothing
+	/// has been sent for this long.
+	///
+	/// If `stall_timeout` is `Some`, [`NotifsOutHandlerOut::Slow`] is emitted every time the
+	/// messages queue couldn't be flushed for this long.
+	///
+	/// If `acknowledged` is true, the substream is opened in acknowledged mode, and the delivery
+	/// of notifications can be confirmed. See [`NotifsOutHandlerIn::Send`].
+	pub fn new(
+		protocol_name: impl Into<Cow<'static, [u8]>>,
+		priority: NotificationsPriority,
+		keep_alive: Option<Duration>,
+		stall_timeout: Option<Duration>,
+		queue_size_report: Option<Histogram>,
+		open_duration_report: Option<Histogram>,
+		acknowledged: bool,
+	) -> Self {
 		NotifsOutHandlerProto {
 			protocol_name: protocol_name.into(),
+			priority,
+			keep_alive,
+			stall_timeout,
 			queue_size_report,
+			open_duration_report,
+			acknowledged,
 		}
 	}
 }
@@ -81,11 +157,19 @@ impl IntoProtocolsHandler for NotifsOutHandlerProto {
 	fn into_handler(self, peer_id: &PeerId, _: &ConnectedPoint) -> Self::Handler {
 		NotifsOutHandler {
 			protocol_name: self.protocol_name,
+			priority: self.priority,
+			keep_alive: self.keep_alive,
+			stall_timeout: self.stall_timeout,
 			when_connection_open: Instant::now(),
 			queue_size_report: self.queue_size_report,
+			open_duration_report: self.open_duration_report,
+			acknowledged: self.acknowledged,
+			open_started: None,
+			stalled: None,
 			state: State::Disabled,
 			events_queue: VecDeque::new(),
 			peer_id: peer_id.clone(),
+			flush_paused: false,
 		}
 	}
 }
@@ -102,15 +186,37 @@ pub struct NotifsOutHandler {
 	/// Name of the protocol to negotiate.
 	protocol_name: Cow<'static, [u8]>,
 
+	/// Priority of the protocol.
+	priority: NotificationsPriority,
+
+	/// Interval after which a keep-alive message is sent on an idle substream, if any.
+	keep_alive: Option<Duration>,
+
 	/// Relationship with the node we're connected to.
 	state: State,
 
 	/// When the connection with the remote has been successfully established.
 	when_connection_open: Instant,
 
+	/// Duration after which a messages queue that can't be flushed is reported, if any.
+	stall_timeout: Option<Duration>,
+
 	/// Optional prometheus histogram to report message queue sizes variations.
 	queue_size_report: Option<Histogram>,
 
+	/// Optional prometheus histogram to report the time it takes to open the substream.
+	open_duration_report: Option<Histogram>,
+
+	/// If true, the remote acknowledges the notifications it receives.
+	acknowledged: bool,
+
+	/// When we last requested a substream to be opened, if we are waiting for it.
+	open_started: Option<Instant>,
+
+	/// If the messages queue can't be flushed, when that started and the timer firing once it
+	/// has lasted for `stall_timeout`.
+	stalled: Option<(Instant, Delay)>,
+
 	/// Queue of events to send to the outside.
 	///
 	/// This queue must only ever be modified to insert elements at the back, or remove the first
@@ -119,6 +225,10 @@ pub struct NotifsOutHandler {
 
 	/// Who we are connected to.
 	peer_id: PeerId,
+
+	/// If true, the messages queue isn't flushed to the substream. See
+	/// [`NotifsOutHandler::set_flush_paused`].
+	flush_paused: bool,
 }
 
 /// Our relationship with the node we're connected to.
@@ -176,8 +286,12 @@ pub enum NotifsOutHandlerIn {
 
 	/// Sends a message on the notifications substream. Ignored if the substream isn't open.
 	///
+	/// If the substream is in acknowledged mode, the [`DeliveryConfirmer`] is used to confirm
+	/// the delivery of the message once the remote acknowledges it. Otherwise, or if the message
+	/// is lost, the confirmation is cancelled.
+	///
 	/// It is only valid to send this if the notifications substream has been enabled.
-	Send(Vec<u8>),
+	Send(Bytes, Option<DeliveryConfirmer>),
 }
 
 /// Event that can be emitted by a `NotifsOutHandler`.
@@ -196,6 +310,9 @@ pub enum NotifsOutHandlerOut {
 	///
 	/// Can only happen if we're in a closed state.
 	Refused,
+
+	/// The remote is slow to process our notifications.
+	Slow(SlowReason),
 }
 
 impl NotifsOutHandler {
@@ -216,6 +333,29 @@ impl NotifsOutHandler {
 	pub fn protocol_name(&self) -> &[u8] {
 		&self.protocol_name
 	}
+
+	/// Returns the priority of the protocol.
+	pub fn priority(&self) -> NotificationsPriority {
+		self.priority
+	}
+
+	/// Returns the number of messages waiting to be sent to the remote. Always 0 if the
+	/// substream isn't open.
+	pub fn queue_len(&self) -> u32 {
+		match &self.state {
+			State::Open { substream, .. } => substream.queue_len(),
+			_ => 0,
+		}
+	}
+
+	/// Pauses or resumes flushing the messages queue to the substream.
+	///
+	/// While paused, messages are still accepted and queued, and the substream is still opened
+	/// and closed as requested. This is used to leave the bandwidth to substreams of protocols
+	/// with a higher priority.
+	pub fn set_flush_paused(&mut self, paused: bool) {
+		self.flush_paused = paused;
+	}
 }
 
 impl ProtocolsHandler for NotifsOutHandler {
@@ -245,6 +385,9 @@ impl ProtocolsHandler for NotifsOutHandler {
 	) {
 		match mem::replace(&mut self.state, State::Poisoned) {
 			State::Opening { initial_message } => {
+				if let (Some(started), Some(metric)) = (self.open_started.take(), &self.open_duration_report) {
+					metric.observe(started.elapsed().as_secs_f64());
+				}
 				let ev = NotifsOutHandlerOut::Open { handshake: handshake_msg };
 				self.events_queue.push_back(ProtocolsHandlerEvent::Custom(ev));
 				self.state = State::Open { substream, initial_message };
@@ -265,11 +408,14 @@ impl ProtocolsHandler for NotifsOutHandler {
 			NotifsOutHandlerIn::Enable { initial_message } => {
 				match mem::replace(&mut self.state, State::Poisoned) {
 					State::Disabled => {
-						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message.clone());
+						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message.clone())
+							.with_keep_alive(self.keep_alive)
+							.with_acknowledgements(self.acknowledged);
 						self.events_queue.push_back(ProtocolsHandlerEvent::OutboundSubstreamRequest {
 							protocol: SubstreamProtocol::new(proto).with_timeout(OPEN_TIMEOUT),
 							info: (),
 						});
+						self.open_started = Some(Instant::now());
 						self.state = State::Opening { initial_message };
 					},
 					State::DisabledOpening => self.state = State::Opening { initial_message },
@@ -285,11 +431,14 @@ impl ProtocolsHandler for NotifsOutHandler {
 							);
 						}
 
-						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message.clone());
+						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message.clone())
+							.with_keep_alive(self.keep_alive)
+							.with_acknowledgements(self.acknowledged);
 						self.events_queue.push_back(ProtocolsHandlerEvent::OutboundSubstreamRequest {
 							protocol: SubstreamProtocol::new(proto).with_timeout(OPEN_TIMEOUT),
 							info: (),
 						});
+						self.open_started = Some(Instant::now());
 						self.state = State::Opening { initial_message };
 					},
 					st @ State::Opening { .. } | st @ State::Refused | st @ State::Open { .. } => {
@@ -315,9 +464,9 @@ impl ProtocolsHandler for NotifsOutHandler {
 				}
 			}
 
-			NotifsOutHandlerIn::Send(msg) =>
+			NotifsOutHandlerIn::Send(msg, confirmer) =>
 				if let State::Open { substream, .. } = &mut self.state {
-					if substream.push_message(msg).is_err() {
+					if substream.push_acknowledged_message(msg, confirmer).is_err() {
 						warn!(
 							target: "sub-libp2p",
 							"📞 Notifications queue with peer {} is full, dropped message (protocol: {:?})",
@@ -338,13 +487,18 @@ impl ProtocolsHandler for NotifsOutHandler {
 		}
 	}
 
-	fn inject_dial_upgrade_error(&mut self, _: (), _: ProtocolsHandlerUpgrErr<NotificationsHandshakeError>) {
+	fn inject_dial_upgrade_error(&mut self, _: (), err: ProtocolsHandlerUpgrErr<NotificationsHandshakeError>) {
+		self.open_started = None;
 		match mem::replace(&mut self.state, State::Poisoned) {
 			State::Disabled => {},
 			State::DisabledOpen(_) | State::Refused | State::Open { .. } =>
 				error!("☎️ State mismatch in NotificationsOut"),
 			State::Opening { .. } => {
 				self.state = State::Refused;
+				if let ProtocolsHandlerUpgrErr::Timeout = err {
+					let ev = NotifsOutHandlerOut::Slow(SlowReason::OpenTimeout);
+					self.events_queue.push_back(ProtocolsHandlerEvent::Custom(ev));
+				}
 				let ev = NotifsOutHandlerOut::Refused;
 				self.events_queue.push_back(ProtocolsHandlerEvent::Custom(ev));
 			},
@@ -375,18 +529,37 @@ impl ProtocolsHandler for NotifsOutHandler {
 		}
 
 		match &mut self.state {
+			// Not flushing is our choice, the remote isn't to blame.
+			State::Open { .. } if self.flush_paused => self.stalled = None,
 			State::Open { substream, initial_message } =>
 				match Sink::poll_flush(Pin::new(substream), cx) {
-					Poll::Pending | Poll::Ready(Ok(())) => {},
+					Poll::Ready(Ok(())) => self.stalled = None,
+					Poll::Pending => if let Some(timeout) = self.stall_timeout {
+						let (since, delay) = self.stalled
+							.get_or_insert_with(|| (Instant::now(), Delay::new(timeout)));
+						if let Poll::Ready(()) = delay.poll_unpin(cx) {
+							let duration = since.elapsed();
+							// Keep reporting the remote for as long as the queue stays stuck.
+							*delay = Delay::new(timeout);
+							let _ = delay.poll_unpin(cx);
+							return Poll::Ready(ProtocolsHandlerEvent::Custom(
+								NotifsOutHandlerOut::Slow(SlowReason::Stalled(duration))
+							));
+						}
+					},
 					Poll::Ready(Err(_)) => {
+						self.stalled = None;
 						// We try to re-open a substream.
 						let initial_message = mem::replace(initial_message, Vec::new());
 						self.state = State::Opening { initial_message: initial_message.clone() };
-						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message);
+						let proto = NotificationsOut::new(self.protocol_name.clone(), initial_message)
+							.with_keep_alive(self.keep_alive)
+							.with_acknowledgements(self.acknowledged);
 						self.events_queue.push_back(ProtocolsHandlerEvent::OutboundSubstreamRequest {
 							protocol: SubstreamProtocol::new(proto).with_timeout(OPEN_TIMEOUT),
 							info: (),
 						});
+						self.open_started = Some(Instant::now());
 						return Poll::Ready(ProtocolsHandlerEvent::Custom(NotifsOutHandlerOut::Closed));
 					}
 				},
@@ -399,7 +572,7 @@ impl ProtocolsHandler for NotifsOutHandler {
 				},
 			},
 
-			_ => {}
+			_ => self.stalled = None,
 		}
 
 		Poll::Pending
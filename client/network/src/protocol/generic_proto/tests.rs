@@ -80,10 +80,11 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 			},
 			reserved_only: false,
 			priority_groups: Vec::new(),
+			allowed_peers: None,
 		});
 
 		let behaviour = CustomProtoWithAddr {
-			inner: GenericProto::new(local_peer_id, &b"test"[..], &[1], peerset, None),
+			inner: GenericProto::new(local_peer_id, &b"test"[..], &[1], peerset, None, None, None, None, Default::default()),
 			addrs: addrs
 				.iter()
 				.enumerate()
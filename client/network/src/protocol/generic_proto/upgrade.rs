@@ -23,6 +23,7 @@ pub use self::legacy::{
 	RegisteredProtocolSubstream
 };
 pub use self::notifications::{
+	DeliveryConfirmer,
 	NotificationsIn,
 	NotificationsInSubstream,
 	NotificationsOut,
@@ -62,6 +62,13 @@ impl RegisteredProtocol {
 	}
 }
 
+impl RegisteredProtocol {
+	/// Returns the name of the protocol as advertised on the network, without the version.
+	pub fn base_name(&self) -> &[u8] {
+		&self.base_name
+	}
+}
+
 impl Clone for RegisteredProtocol {
 	fn clone(&self) -> Self {
 		RegisteredProtocol {
@@ -28,17 +28,33 @@
 /// - Node A can then send notifications to B, prefixed with a variable-length integer indicating
 ///   the length of the message.
 /// - Node A closes its writing side if it doesn't want the notifications substream anymore.
+/// - Optionally, node A sends empty notifications when it has nothing else to send for a while,
+///   so that the substream isn't closed by an intermediary for being idle. These must be
+///   ignored by B.
 ///
 /// Notification substreams are unidirectional. If A opens a substream with B, then B is
 /// encouraged but not required to open a substream to A as well.
 ///
+/// Protocols can optionally be used in acknowledged mode, which both sides must agree on, for
+/// example by using a dedicated protocol name. In this mode, B doesn't close its sending side
+/// after the handshake. Instead, every time it has received notifications, it sends back a
+/// message containing the number of non-empty notifications received since its previous
+/// acknowledgement, as a variable-length integer. This lets A know which of its notifications
+/// have been received.
+///
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use futures::{prelude::*, ready};
 use futures_codec::Framed;
+use futures_timer::Delay;
 use libp2p::core::{UpgradeInfo, InboundUpgrade, OutboundUpgrade, upgrade};
+use futures::channel::oneshot;
 use log::error;
-use std::{borrow::Cow, collections::VecDeque, convert::TryFrom as _, io, iter, mem, pin::Pin, task::{Context, Poll}};
+use parking_lot::Mutex;
+use std::{
+	borrow::Cow, collections::VecDeque, convert::TryFrom as _, io, iter, mem, pin::Pin,
+	sync::Arc, task::{Context, Poll}, time::Duration,
+};
 use unsigned_varint::codec::UviBytes;
 
 /// Maximum allowed size of the two handshake messages, in bytes.
@@ -52,6 +68,8 @@ const MAX_PENDING_MESSAGES: usize = 512;
 pub struct NotificationsIn {
 	/// Protocol name to use when negotiating the substream.
 	protocol_name: Cow<'static, [u8]>,
+	/// If true, the notifications received are acknowledged.
+	acknowledged: bool,
 }
 
 /// Upgrade that opens a substream, waits for the remote to accept by sending back a status
@@ -62,6 +80,35 @@ pub struct NotificationsOut {
 	protocol_name: Cow<'static, [u8]>,
 	/// Message to send when we start the handshake.
 	initial_message: Vec<u8>,
+	/// Interval after which an empty message is sent on an idle substream, if any.
+	keep_alive: Option<Duration>,
+	/// If true, the remote acknowledges the notifications it receives.
+	acknowledged: bool,
+}
+
+/// Confirms the delivery of a notification sent on a substream in acknowledged mode.
+///
+/// The [`oneshot::Receiver`] returned by [`DeliveryConfirmer::new`] resolves once the remote
+/// has acknowledged the notification, or is cancelled if the notification is lost, for example
+/// because the substream has been closed or isn't in acknowledged mode.
+///
+/// Cloning is only supported so that the confirmer can be passed around in events that must be
+/// `Clone`. The confirmation is only cancelled once all the clones have been dropped.
+#[derive(Debug, Clone)]
+pub struct DeliveryConfirmer(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+impl DeliveryConfirmer {
+	/// Builds a new confirmer, and the receiver of its confirmation.
+	pub fn new() -> (Self, oneshot::Receiver<()>) {
+		let (tx, rx) = oneshot::channel();
+		(DeliveryConfirmer(Arc::new(Mutex::new(Some(tx)))), rx)
+	}
+
+	fn confirm(self) {
+		if let Some(tx) = self.0.lock().take() {
+			let _ = tx.send(());
+		}
+	}
 }
 
 /// A substream for incoming notification messages.
@@ -73,6 +120,12 @@ pub struct NotificationsInSubstream<TSubstream> {
 	#[pin]
 	socket: Framed<TSubstream, UviBytes<io::Cursor<Vec<u8>>>>,
 	handshake: NotificationsInSubstreamHandshake,
+	/// If true, the notifications received are acknowledged.
+	acknowledged: bool,
+	/// Number of notifications received and not acknowledged yet.
+	pending_acks: u64,
+	/// If true, an acknowledgement has been pushed to `socket`, which needs to be flushed.
+	acks_need_flush: bool,
 }
 
 /// State of the handshake sending back process.
@@ -81,7 +134,8 @@ enum NotificationsInSubstreamHandshake {
 	NotSent,
 	/// User gave us the handshake message. Trying to push it in the socket.
 	PendingSend(Vec<u8>),
-	/// Handshake message was pushed in the socket. Still need to flush.
+	/// Handshake message was pushed in the socket. Still need to flush, and to close the sending
+	/// side unless in acknowledged mode.
 	Close,
 	/// Handshake message successfully sent.
 	Sent,
@@ -92,11 +146,19 @@ enum NotificationsInSubstreamHandshake {
 pub struct NotificationsOutSubstream<TSubstream> {
 	/// Substream where to send messages.
 	#[pin]
-	socket: Framed<TSubstream, UviBytes<io::Cursor<Vec<u8>>>>,
-	/// Queue of messages waiting to be sent.
-	messages_queue: VecDeque<Vec<u8>>,
+	socket: Framed<TSubstream, UviBytes<Bytes>>,
+	/// Queue of messages waiting to be sent, and their delivery confirmers. Messages are
+	/// reference-counted, so that the same message can be queued on many substreams without
+	/// being copied.
+	messages_queue: VecDeque<(Bytes, Option<DeliveryConfirmer>)>,
 	/// If true, we need to flush `socket`.
 	need_flush: bool,
+	/// If `Some`, interval after which an empty message is sent if nothing else has been, and
+	/// timer that fires once it has elapsed since the last message.
+	keep_alive: Option<(Duration, Delay)>,
+	/// If `Some`, the remote acknowledges the notifications it receives. Contains the delivery
+	/// confirmers of the non-empty notifications sent and not acknowledged yet, in order.
+	unacknowledged: Option<VecDeque<Option<DeliveryConfirmer>>>,
 }
 
 impl NotificationsIn {
@@ -104,9 +166,18 @@ impl NotificationsIn {
 	pub fn new(protocol_name: impl Into<Cow<'static, [u8]>>) -> Self {
 		NotificationsIn {
 			protocol_name: protocol_name.into(),
+			acknowledged: false,
 		}
 	}
 
+	/// Acknowledges the notifications received, if `acknowledged` is true.
+	///
+	/// The remote must have opened the substream in acknowledged mode as well.
+	pub fn with_acknowledgements(mut self, acknowledged: bool) -> Self {
+		self.acknowledged = acknowledged;
+		self
+	}
+
 	/// Returns the name of the protocol that we accept.
 	pub fn protocol_name(&self) -> &[u8] {
 		&self.protocol_name
@@ -151,6 +222,9 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 			let substream = NotificationsInSubstream {
 				socket: Framed::new(socket, UviBytes::default()),
 				handshake: NotificationsInSubstreamHandshake::NotSent,
+				acknowledged: self.acknowledged,
+				pending_acks: 0,
+				acks_need_flush: false,
 			};
 
 			Ok((initial_message, substream))
@@ -183,8 +257,35 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 		// This `Stream` implementation first tries to send back the handshake if necessary.
 		loop {
 			match mem::replace(this.handshake, NotificationsInSubstreamHandshake::Sent) {
-				NotificationsInSubstreamHandshake::Sent =>
+				NotificationsInSubstreamHandshake::Sent if !*this.acknowledged =>
 					return Stream::poll_next(this.socket.as_mut(), cx),
+				NotificationsInSubstreamHandshake::Sent => {
+					// Acknowledge the notifications returned by the previous calls. Sending is
+					// best effort: if the socket isn't ready, the acknowledgements accumulate.
+					if *this.pending_acks != 0 {
+						if let Poll::Ready(()) = Sink::poll_ready(this.socket.as_mut(), cx)? {
+							let mut buf = unsigned_varint::encode::u64_buffer();
+							let ack = unsigned_varint::encode::u64(*this.pending_acks, &mut buf).to_vec();
+							Sink::start_send(this.socket.as_mut(), io::Cursor::new(ack))?;
+							*this.pending_acks = 0;
+							*this.acks_need_flush = true;
+						}
+					}
+					if *this.acks_need_flush {
+						if let Poll::Ready(()) = Sink::poll_flush(this.socket.as_mut(), cx)? {
+							*this.acks_need_flush = false;
+						}
+					}
+
+					let msg = ready!(Stream::poll_next(this.socket.as_mut(), cx));
+					if let Some(Ok(msg)) = &msg {
+						// Empty messages are keep-alives, which aren't acknowledged.
+						if !msg.is_empty() {
+							*this.pending_acks += 1;
+						}
+					}
+					return Poll::Ready(msg)
+				},
 				NotificationsInSubstreamHandshake::NotSent => {
 					*this.handshake = NotificationsInSubstreamHandshake::NotSent;
 					return Poll::Pending
@@ -203,15 +304,23 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin,
 							return Poll::Pending
 						}
 					},
-				NotificationsInSubstreamHandshake::Close =>
-					match Sink::poll_close(this.socket.as_mut(), cx)? {
+				NotificationsInSubstreamHandshake::Close => {
+					// In acknowledged mode, the sending side is used for acknowledgements and
+					// stays open.
+					let flushed = if *this.acknowledged {
+						Sink::poll_flush(this.socket.as_mut(), cx)?
+					} else {
+						Sink::poll_close(this.socket.as_mut(), cx)?
+					};
+					match flushed {
 						Poll::Ready(()) =>
 							*this.handshake = NotificationsInSubstreamHandshake::Sent,
 						Poll::Pending => {
 							*this.handshake = NotificationsInSubstreamHandshake::Close;
 							return Poll::Pending
 						}
-					},
+					}
+				},
 			}
 		}
 	}
@@ -228,8 +337,27 @@ impl NotificationsOut {
 		NotificationsOut {
 			protocol_name: protocol_name.into(),
 			initial_message,
+			keep_alive: None,
+			acknowledged: false,
 		}
 	}
+
+	/// Sends an empty message on the substream every time nothing has been sent for `interval`.
+	///
+	/// Prevents NATs and firewalls from closing the substream when no notification is sent for
+	/// a long time. The remote must ignore empty messages, which nodes without support for this
+	/// report as regular notifications.
+	pub fn with_keep_alive(mut self, interval: Option<Duration>) -> Self {
+		self.keep_alive = interval;
+		self
+	}
+
+	/// Expects the remote to acknowledge the notifications it receives, if `acknowledged` is
+	/// true. See [`NotificationsOutSubstream::push_acknowledged_message`].
+	pub fn with_acknowledgements(mut self, acknowledged: bool) -> Self {
+		self.acknowledged = acknowledged;
+		self
+	}
 }
 
 impl UpgradeInfo for NotificationsOut {
@@ -274,6 +402,8 @@ where TSubstream: AsyncRead + AsyncWrite + Unpin + Send + 'static,
 				socket: Framed::new(socket, UviBytes::default()),
 				messages_queue: VecDeque::with_capacity(MAX_PENDING_MESSAGES),
 				need_flush: false,
+				keep_alive: self.keep_alive.map(|interval| (interval, Delay::new(interval))),
+				unacknowledged: if self.acknowledged { Some(VecDeque::new()) } else { None },
 			}))
 		})
 	}
@@ -288,12 +418,25 @@ impl<TSubstream> NotificationsOutSubstream<TSubstream> {
 	/// Push a message to the queue of messages.
 	///
 	/// This has the same effect as the `Sink::start_send` implementation.
-	pub fn push_message(&mut self, item: Vec<u8>) -> Result<(), NotificationsOutError> {
+	pub fn push_message(&mut self, item: impl Into<Bytes>) -> Result<(), NotificationsOutError> {
+		self.push_acknowledged_message(item, None)
+	}
+
+	/// Push a message to the queue of messages, whose delivery is confirmed through `confirmer`
+	/// once the remote acknowledges it.
+	///
+	/// The confirmation is cancelled if the substream isn't in acknowledged mode or if the message
+	/// is empty, as empty messages are never acknowledged.
+	pub fn push_acknowledged_message(
+		&mut self,
+		item: impl Into<Bytes>,
+		confirmer: Option<DeliveryConfirmer>,
+	) -> Result<(), NotificationsOutError> {
 		if self.messages_queue.len() >= MAX_PENDING_MESSAGES {
 			return Err(NotificationsOutError::Clogged);
 		}
 
-		self.messages_queue.push_back(item);
+		self.messages_queue.push_back((item.into(), confirmer));
 		Ok(())
 	}
 }
@@ -314,16 +457,56 @@ impl<TSubstream> Sink<Vec<u8>> for NotificationsOutSubstream<TSubstream>
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
 		let mut this = self.project();
 
-		while !this.messages_queue.is_empty() {
-			match Sink::poll_ready(this.socket.as_mut(), cx) {
-				Poll::Ready(Err(err)) => return Poll::Ready(Err(From::from(err))),
-				Poll::Ready(Ok(())) => {
-					let msg = this.messages_queue.pop_front()
-						.expect("checked for !is_empty above; qed");
-					Sink::start_send(this.socket.as_mut(), io::Cursor::new(msg))?;
-					*this.need_flush = true;
-				},
-				Poll::Pending => return Poll::Pending,
+		loop {
+			while !this.messages_queue.is_empty() {
+				match Sink::poll_ready(this.socket.as_mut(), cx) {
+					Poll::Ready(Err(err)) => return Poll::Ready(Err(From::from(err))),
+					Poll::Ready(Ok(())) => {
+						let (msg, confirmer) = this.messages_queue.pop_front()
+							.expect("checked for !is_empty above; qed");
+						if let (Some(unacknowledged), false) = (this.unacknowledged.as_mut(), msg.is_empty()) {
+							unacknowledged.push_back(confirmer);
+						}
+						Sink::start_send(this.socket.as_mut(), msg)?;
+						*this.need_flush = true;
+						if let Some((interval, timer)) = this.keep_alive.as_mut() {
+							timer.reset(*interval);
+						}
+					},
+					Poll::Pending => return Poll::Pending,
+				}
+			}
+
+			// Nothing left to send. Queue a keep-alive message if we have been idle for long
+			// enough, which also registers the timer for a wake-up otherwise.
+			match this.keep_alive.as_mut() {
+				Some((_, timer)) if Future::poll(Pin::new(timer), cx).is_ready() =>
+					this.messages_queue.push_back((Bytes::new(), None)),
+				_ => break,
+			}
+		}
+
+		// Process the acknowledgements sent by the remote, if any. Each of them contains the
+		// number of notifications received since the previous one.
+		if let Some(unacknowledged) = this.unacknowledged.as_mut() {
+			loop {
+				match Stream::poll_next(this.socket.as_mut(), cx) {
+					Poll::Ready(Some(Ok(ack))) => {
+						let num = unsigned_varint::decode::u64(&ack)
+							.map_err(|_| NotificationsOutError::InvalidAck)?.0;
+						for _ in 0..num {
+							match unacknowledged.pop_front() {
+								Some(Some(confirmer)) => confirmer.confirm(),
+								Some(None) => {},
+								None => return Poll::Ready(Err(NotificationsOutError::InvalidAck)),
+							}
+						}
+					},
+					Poll::Ready(Some(Err(err))) => return Poll::Ready(Err(From::from(err))),
+					Poll::Ready(None) =>
+						return Poll::Ready(Err(From::from(io::Error::from(io::ErrorKind::UnexpectedEof)))),
+					Poll::Pending => break,
+				}
 			}
 		}
 
@@ -393,16 +576,20 @@ pub enum NotificationsOutError {
 	/// >           local node sending data too quickly. Properly doing back-pressure, however,
 	/// >           would require a deep refactoring effort in Substrate as a whole.
 	Clogged,
+
+	/// Remote has sent an invalid acknowledgement, or acknowledged more notifications than we
+	/// have sent.
+	InvalidAck,
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{NotificationsIn, NotificationsOut};
+	use super::{DeliveryConfirmer, NotificationsIn, NotificationsOut};
 
 	use async_std::net::{TcpListener, TcpStream};
 	use futures::{prelude::*, channel::oneshot};
 	use libp2p::core::upgrade;
-	use std::pin::Pin;
+	use std::{pin::Pin, time::Duration};
 
 	#[test]
 	fn basic_works() {
@@ -480,6 +667,101 @@ mod tests {
 		async_std::task::block_on(client);
 	}
 
+	#[test]
+	fn keep_alive_sends_empty_messages() {
+		const PROTO_NAME: &'static [u8] = b"/test/proto/1";
+		let (listener_addr_tx, listener_addr_rx) = oneshot::channel();
+
+		let client = async_std::task::spawn(async move {
+			let socket = TcpStream::connect(listener_addr_rx.await.unwrap()).await.unwrap();
+			let (_, mut substream) = upgrade::apply_outbound(
+				socket,
+				NotificationsOut::new(PROTO_NAME, vec![]).with_keep_alive(Some(Duration::from_millis(50))),
+				upgrade::Version::V1
+			).await.unwrap();
+
+			// Nothing has been sent yet, so the keep-alive message is sent as soon as the
+			// interval has elapsed.
+			futures_timer::Delay::new(Duration::from_millis(100)).await;
+			substream.flush().await.unwrap();
+			substream.send(b"test message".to_vec()).await.unwrap();
+		});
+
+		async_std::task::block_on(async move {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			listener_addr_tx.send(listener.local_addr().unwrap()).unwrap();
+
+			let (socket, _) = listener.accept().await.unwrap();
+			let (_, mut substream) = upgrade::apply_inbound(
+				socket,
+				NotificationsIn::new(PROTO_NAME)
+			).await.unwrap();
+			substream.send_handshake(vec![]);
+
+			let msg = substream.next().await.unwrap().unwrap();
+			assert!(msg.as_ref().is_empty());
+			let msg = substream.next().await.unwrap().unwrap();
+			assert_eq!(msg.as_ref(), b"test message");
+		});
+
+		async_std::task::block_on(client);
+	}
+
+	#[test]
+	fn acknowledged_notifications_are_confirmed() {
+		const PROTO_NAME: &'static [u8] = b"/test/proto/1";
+		let (listener_addr_tx, listener_addr_rx) = oneshot::channel();
+
+		let client = async_std::task::spawn(async move {
+			let socket = TcpStream::connect(listener_addr_rx.await.unwrap()).await.unwrap();
+			let (_, mut substream) = upgrade::apply_outbound(
+				socket,
+				NotificationsOut::new(PROTO_NAME, vec![]).with_acknowledgements(true),
+				upgrade::Version::V1
+			).await.unwrap();
+
+			let (confirmer1, delivered1) = DeliveryConfirmer::new();
+			let (confirmer2, delivered2) = DeliveryConfirmer::new();
+			substream.push_acknowledged_message(b"first".to_vec(), Some(confirmer1)).unwrap();
+			substream.push_message(b"second".to_vec()).unwrap();
+			substream.push_acknowledged_message(b"third".to_vec(), Some(confirmer2)).unwrap();
+
+			// Acknowledgements are only processed while the substream is being flushed.
+			let mut delivered = future::join(delivered1, delivered2);
+			let (result1, result2) = future::poll_fn(|cx| {
+				let _ = Sink::poll_flush(Pin::new(&mut substream), cx);
+				delivered.poll_unpin(cx)
+			}).await;
+			assert!(result1.is_ok() && result2.is_ok());
+		});
+
+		async_std::task::block_on(async move {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			listener_addr_tx.send(listener.local_addr().unwrap()).unwrap();
+
+			let (socket, _) = listener.accept().await.unwrap();
+			let (_, mut substream) = upgrade::apply_inbound(
+				socket,
+				NotificationsIn::new(PROTO_NAME).with_acknowledgements(true)
+			).await.unwrap();
+			substream.send_handshake(vec![]);
+
+			for expected in &[&b"first"[..], b"second", b"third"] {
+				let msg = substream.next().await.unwrap().unwrap();
+				assert_eq!(msg.as_ref(), *expected);
+			}
+
+			// Keep polling so that the acknowledgements get sent, until the client is done.
+			let mut client = client;
+			loop {
+				match future::select(client, substream.next()).await {
+					future::Either::Left(_) => break,
+					future::Either::Right((_, c)) => client = c,
+				}
+			}
+		});
+	}
+
 	#[test]
 	fn refused() {
 		const PROTO_NAME: &'static [u8] = b"/test/proto/1";
@@ -31,11 +31,13 @@ use codec::Encode;
 use blocks::BlockCollection;
 use sp_blockchain::{Error as ClientError, Info as BlockchainInfo, HeaderMetadata};
 use sp_consensus::{BlockOrigin, BlockStatus,
-	block_validation::{BlockAnnounceValidator, Validation},
+	block_validation::{BlockAnnounceValidator, PeerChainInfo, Validation},
 	import_queue::{IncomingBlock, BlockImportResult, BlockImportError}
 };
 use crate::{
+	chain::WarpSyncProvider,
 	config::BoxFinalityProofRequestBuilder,
+	schema::v1::state::StateResponse,
 	protocol::message::{self, generic::FinalityProofRequest, BlockAnnounce, BlockAttributes, BlockRequest, BlockResponse,
 	FinalityProofResponse, Roles},
 };
@@ -49,13 +51,27 @@ use sp_runtime::{
 	traits::{Block as BlockT, Header, NumberFor, Zero, One, CheckedSub, SaturatedConversion, Hash, HashFor}
 };
 use std::{fmt, ops::Range, collections::{HashMap, HashSet, VecDeque}, sync::Arc};
+use wasm_timer::Instant;
 
 mod blocks;
 mod extra_requests;
+mod gap;
+mod state;
+mod warp;
+
+pub use gap::GapSyncProgress;
+
+pub use state::StateRequest;
 
 /// Maximum blocks to request in a single packet.
 const MAX_BLOCKS_TO_REQUEST: usize = 128;
 
+/// Minimum blocks to request in a single packet, even from the slowest peers.
+const MIN_BLOCKS_TO_REQUEST: usize = 16;
+
+/// Weight of the most recent sample in the moving average of a peer's download throughput.
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+
 /// Maximum blocks to store in the import queue.
 const MAX_IMPORTING_BLOCKS: usize = 2048;
 
@@ -72,6 +88,10 @@ const MAJOR_SYNC_BLOCKS: u8 = 5;
 /// Number of recently announced blocks to track for each peer.
 const ANNOUNCE_HISTORY_SIZE: usize = 64;
 
+/// Fast sync starts downloading the state of our latest finalized block once it is at most this
+/// many blocks behind the median best block of our peers.
+const STATE_SYNC_FINALITY_THRESHOLD: u32 = 8;
+
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
 	/// Reputation change when a peer sent us a message that led to a
@@ -105,6 +125,15 @@ mod rep {
 
 	/// Reputation change when a peer sent us invlid ancestry result.
 	pub const UNKNOWN_ANCESTOR:Rep = Rep::new(-(1 << 16), "DB Error");
+
+	/// Reputation change for peers which send us a warp sync proof we fail to verify.
+	pub const BAD_WARP_PROOF: Rep = Rep::new(-(1 << 29), "Bad warp sync proof");
+
+	/// Reputation change for peers which send us an invalid state response.
+	pub const BAD_STATE: Rep = Rep::new(-(1 << 29), "Bad state");
+
+	/// Reputation change for peers whose block announces handshake fails validation.
+	pub const BAD_HANDSHAKE: Rep = Rep::new_fatal("Bad block announces handshake");
 }
 
 enum PendingRequests {
@@ -151,6 +180,19 @@ impl Default for PendingRequests {
 	}
 }
 
+/// Progress of a fast sync.
+enum FastSync<B: BlockT> {
+	/// Downloading the headers and justifications of the blocks, without their body.
+	Headers,
+	/// Downloading the state of the latest finalized block. No blocks are downloaded meanwhile.
+	State(state::StateSync<B>),
+	/// The state has been handed over to the import queue.
+	Importing { hash: B::Hash, number: NumberFor<B> },
+	/// Downloading again, along with their body, the blocks above `target` which only had their
+	/// header imported, up to `tip`.
+	Reimport { target: NumberFor<B>, tip: NumberFor<B> },
+}
+
 /// The main data structure which contains all the state for a chains
 /// active syncing strategy.
 pub struct ChainSync<B: BlockT> {
@@ -191,6 +233,15 @@ pub struct ChainSync<B: BlockT> {
 	max_parallel_downloads: u32,
 	/// Total number of processed blocks (imported or failed).
 	processed_blocks: usize,
+	/// Warp sync in progress, if any. No blocks are downloaded until it is over.
+	warp_sync: Option<warp::WarpSync<B>>,
+	/// Fast sync in progress, if any.
+	fast_sync: Option<FastSync<B>>,
+	/// Download of the bodies of the blocks below the state imported by fast sync, if any.
+	gap_sync: Option<gap::GapSync<B>>,
+	/// If set, blocks, justifications, finality proofs and state are only requested from and
+	/// accepted from these peers.
+	trusted_peers: Option<HashSet<PeerId>>,
 }
 
 /// All the data we have about a Peer that we are trying to sync with
@@ -208,7 +259,41 @@ pub struct PeerSync<B: BlockT> {
 	pub state: PeerSyncState<B>,
 	/// A queue of blocks that this peer has announced to us, should only
 	/// contain `ANNOUNCE_HISTORY_SIZE` entries.
-	pub recently_announced: VecDeque<B::Hash>
+	pub recently_announced: VecDeque<B::Hash>,
+	/// How fast this peer answers our requests for new blocks.
+	pub download_stats: DownloadStats,
+}
+
+/// Throughput of a peer when downloading new blocks from it.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadStats {
+	/// When the request for new blocks currently in flight was sent, if any.
+	pub request_started: Option<Instant>,
+	/// Moving average of the number of blocks per second received in response to our requests
+	/// for new blocks. `None` until the peer has answered at least one of them.
+	pub blocks_per_sec: Option<f64>,
+}
+
+impl DownloadStats {
+	/// Accounts for a response to the request in flight containing `num_blocks` blocks.
+	fn on_response(&mut self, num_blocks: usize) {
+		let started = match self.request_started.take() {
+			Some(started) => started,
+			None => return,
+		};
+
+		if num_blocks == 0 {
+			return
+		}
+
+		// Avoid dividing by zero for answers that arrive within the timer resolution.
+		let elapsed = started.elapsed().as_secs_f64().max(0.001);
+		let sample = num_blocks as f64 / elapsed;
+		self.blocks_per_sec = Some(match self.blocks_per_sec {
+			Some(average) => average * (1.0 - THROUGHPUT_SMOOTHING) + sample * THROUGHPUT_SMOOTHING,
+			None => sample,
+		});
+	}
 }
 
 /// The sync status of a peer we are trying to sync with
@@ -249,7 +334,14 @@ pub enum PeerSyncState<B: BlockT> {
 	/// Downloading justification for given block hash.
 	DownloadingJustification(B::Hash),
 	/// Downloading finality proof for given block hash.
-	DownloadingFinalityProof(B::Hash)
+	DownloadingFinalityProof(B::Hash),
+	/// Downloading a warp sync proof.
+	DownloadingWarpProof,
+	/// Downloading a chunk of the state of the block warp sync is targeting.
+	DownloadingState,
+	/// Downloading the bodies of blocks below the state imported by fast sync, starting from
+	/// the given Number.
+	DownloadingGap(NumberFor<B>),
 }
 
 impl<B: BlockT> PeerSyncState<B> {
@@ -260,6 +352,54 @@ impl<B: BlockT> PeerSyncState<B> {
 			false
 		}
 	}
+
+	/// Returns the request in flight in this state, if any.
+	pub fn request(&self) -> Option<SyncRequest<B>> {
+		match *self {
+			PeerSyncState::Available => None,
+			PeerSyncState::AncestorSearch { current, .. } => Some(SyncRequest::Ancestor(current)),
+			PeerSyncState::DownloadingNew(start) => Some(SyncRequest::NewBlocks(start)),
+			PeerSyncState::DownloadingStale(hash) => Some(SyncRequest::StaleBlock(hash)),
+			PeerSyncState::DownloadingJustification(hash) => Some(SyncRequest::Justification(hash)),
+			PeerSyncState::DownloadingFinalityProof(hash) => Some(SyncRequest::FinalityProof(hash)),
+			PeerSyncState::DownloadingWarpProof => Some(SyncRequest::WarpProof),
+			PeerSyncState::DownloadingState => Some(SyncRequest::State),
+			PeerSyncState::DownloadingGap(start) => Some(SyncRequest::Gap(start)),
+		}
+	}
+}
+
+/// Request sent to a peer and not answered yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncRequest<B: BlockT> {
+	/// Block with the given number, to find the latest block we have in common with the peer.
+	Ancestor(NumberFor<B>),
+	/// New blocks, starting from the given number.
+	NewBlocks(NumberFor<B>),
+	/// Block with the given hash, below our best block.
+	StaleBlock(B::Hash),
+	/// Justification for the given block.
+	Justification(B::Hash),
+	/// Finality proof for the given block.
+	FinalityProof(B::Hash),
+	/// Warp sync proof.
+	WarpProof,
+	/// Chunk of the state of the block warp or fast sync is targeting.
+	State,
+	/// Bodies of blocks below the state imported by fast sync, starting from the given number.
+	Gap(NumberFor<B>),
+}
+
+/// Detailed sync status of a peer, for diagnostic purposes.
+#[derive(Debug, Clone)]
+pub struct PeerSyncDetails<B: BlockT> {
+	/// Number of the latest block we know to be in both the chain of the peer and ours.
+	pub common_number: NumberFor<B>,
+	/// Request sent to the peer and not answered yet, if any.
+	pub request: Option<SyncRequest<B>>,
+	/// Number of blocks per second received in response to our requests for new blocks, if the
+	/// peer has answered any.
+	pub blocks_per_sec: Option<f64>,
 }
 
 /// Reported sync state.
@@ -282,6 +422,8 @@ pub struct Status<B: BlockT> {
 	pub num_peers: u32,
 	/// Number of blocks queued for import
 	pub queued_blocks: u32,
+	/// Progress of the download of the bodies of old blocks, if any.
+	pub gap_sync: Option<GapSyncProgress<NumberFor<B>>>,
 }
 
 /// A peer did not behave as expected and should be reported.
@@ -342,19 +484,55 @@ pub enum OnBlockFinalityProof<B: BlockT> {
 	}
 }
 
+/// Result of [`ChainSync::on_state_response`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OnStateData<B: BlockT> {
+	/// The state is complete and the block should be imported along with it.
+	Import(BlockOrigin, IncomingBlock<B>),
+	/// More of the state needs to be downloaded.
+	Continue,
+}
+
 impl<B: BlockT> ChainSync<B> {
 	/// Create a new instance.
+	///
+	/// If a `warp_sync_provider` is passed and our database is empty, the chain is caught up with
+	/// using warp sync. Otherwise, if `fast_sync` is set and our database is empty, the chain is
+	/// caught up with using fast sync.
 	pub fn new(
 		role: Roles,
 		client: Arc<dyn crate::chain::Client<B>>,
 		info: &BlockchainInfo<B>,
 		request_builder: Option<BoxFinalityProofRequestBuilder<B>>,
+		warp_sync_provider: Option<Arc<dyn WarpSyncProvider<B>>>,
+		fast_sync: bool,
 		block_announce_validator: Box<dyn BlockAnnounceValidator<B> + Send>,
 		max_parallel_downloads: u32,
 	) -> Self {
+		let warp_sync = match warp_sync_provider {
+			Some(provider) if role.is_full() && info.best_number.is_zero() =>
+				Some(warp::WarpSync::new(client.clone(), info.finalized_hash, provider)),
+			Some(_) => {
+				info!("Database isn't empty, warp sync is skipped.");
+				None
+			},
+			None => None,
+		};
+
+		let fast_sync = match fast_sync {
+			true if role.is_full() && info.best_number.is_zero() && warp_sync.is_none() =>
+				Some(FastSync::Headers),
+			true => {
+				info!("Database isn't empty, fast sync is skipped.");
+				None
+			},
+			false => None,
+		};
+
 		let mut required_block_attributes = BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION;
 
-		if role.is_full() {
+		// fast sync only downloads the bodies of the blocks above the state it downloads.
+		if role.is_full() && fast_sync.is_none() {
 			required_block_attributes |= BlockAttributes::BODY
 		}
 
@@ -376,6 +554,10 @@ impl<B: BlockT> ChainSync<B> {
 			block_announce_validator,
 			max_parallel_downloads,
 			processed_blocks: 0,
+			warp_sync,
+			fast_sync,
+			gap_sync: None,
+			trusted_peers: None,
 		}
 	}
 
@@ -386,11 +568,28 @@ impl<B: BlockT> ChainSync<B> {
 		self.peers.get(who).map(|p| PeerInfo { best_hash: p.best_hash, best_number: p.best_number })
 	}
 
+	/// Returns the detailed sync status of the given peer.
+	///
+	/// Returns `None` if the peer is unknown.
+	pub fn peer_details(&self, who: &PeerId) -> Option<PeerSyncDetails<B>> {
+		self.peers.get(who).map(|p| PeerSyncDetails {
+			common_number: p.common_number,
+			request: p.state.request(),
+			blocks_per_sec: p.download_stats.blocks_per_sec,
+		})
+	}
+
 	/// Returns the current sync status.
 	pub fn status(&self) -> Status<B> {
 		let best_seen = self.peers.values().max_by_key(|p| p.best_number).map(|p| p.best_number);
+		let downloading_state = match self.fast_sync {
+			Some(FastSync::State(_)) | Some(FastSync::Importing { .. }) => true,
+			_ => false,
+		};
 		let sync_state =
-			if let Some(n) = best_seen {
+			if downloading_state {
+				SyncState::Downloading
+			} else if let Some(n) = best_seen {
 				// A chain is classified as downloading if the provided best block is
 				// more than `MAJOR_SYNC_BLOCKS` behind the best queued block.
 				if n > self.best_queued_number && n - self.best_queued_number > MAJOR_SYNC_BLOCKS.into() {
@@ -407,6 +606,7 @@ impl<B: BlockT> ChainSync<B> {
 			best_seen_block: best_seen,
 			num_peers: self.peers.len() as u32,
 			queued_blocks: self.queue_blocks.len() as u32,
+			gap_sync: self.gap_sync.as_ref().map(|gap_sync| gap_sync.progress()),
 		}
 	}
 
@@ -456,7 +656,8 @@ impl<B: BlockT> ChainSync<B> {
 						best_hash,
 						best_number,
 						state: PeerSyncState::Available,
-						recently_announced: Default::default()
+						recently_announced: Default::default(),
+						download_stats: Default::default(),
 					});
 					return Ok(None)
 				}
@@ -470,6 +671,7 @@ impl<B: BlockT> ChainSync<B> {
 						best_number,
 						state: PeerSyncState::Available,
 						recently_announced: Default::default(),
+						download_stats: Default::default(),
 					});
 					self.pending_requests.add(&who);
 					return Ok(None)
@@ -493,7 +695,8 @@ impl<B: BlockT> ChainSync<B> {
 						start: self.best_queued_number,
 						state: AncestorSearchState::ExponentialBackoff(One::one()),
 					},
-					recently_announced: Default::default()
+					recently_announced: Default::default(),
+					download_stats: Default::default(),
 				});
 
 				Ok(Some(ancestry_request::<B>(common_best)))
@@ -506,6 +709,7 @@ impl<B: BlockT> ChainSync<B> {
 					best_number,
 					state: PeerSyncState::Available,
 					recently_announced: Default::default(),
+					download_stats: Default::default(),
 				});
 				self.pending_requests.add(&who);
 				Ok(None)
@@ -535,6 +739,26 @@ impl<B: BlockT> ChainSync<B> {
 		})
 	}
 
+	/// Only request and accept blocks, justifications, finality proofs and state from the given
+	/// peers, or from any peer if `None` is passed. Block announces are still processed from
+	/// every peer.
+	///
+	/// Responses to the requests already sent to other peers are ignored.
+	pub fn set_trusted_peers(&mut self, peers: Option<HashSet<PeerId>>) {
+		match &peers {
+			Some(peers) => info!("🔒 Only syncing from {} trusted peers", peers.len()),
+			None if self.trusted_peers.is_some() => info!("🔓 Syncing from any peer"),
+			None => {},
+		}
+		self.trusted_peers = peers;
+		self.pending_requests.set_all();
+	}
+
+	/// Whether data can be requested from and accepted from `who`.
+	fn is_trusted(&self, who: &PeerId) -> bool {
+		self.trusted_peers.as_ref().map_or(true, |peers| peers.contains(who))
+	}
+
 	/// Request syncing for the given block from given set of peers.
 	// The implementation is similar to on_block_announce with unknown parent hash.
 	pub fn set_sync_fork_request(
@@ -593,7 +817,7 @@ impl<B: BlockT> ChainSync<B> {
 	/// Get an iterator over all scheduled justification requests.
 	pub fn justification_requests(&mut self) -> impl Iterator<Item = (PeerId, BlockRequest<B>)> + '_ {
 		let peers = &mut self.peers;
-		let mut matcher = self.extra_justifications.matcher();
+		let mut matcher = self.extra_justifications.matcher().only_to(self.trusted_peers.as_ref());
 		std::iter::from_fn(move || {
 			if let Some((peer, request)) = matcher.next(&peers) {
 				peers.get_mut(&peer)
@@ -618,7 +842,7 @@ impl<B: BlockT> ChainSync<B> {
 	pub fn finality_proof_requests(&mut self) -> impl Iterator<Item = (PeerId, FinalityProofRequest<B::Hash>)> + '_ {
 		let peers = &mut self.peers;
 		let request_builder = &mut self.request_builder;
-		let mut matcher = self.extra_finality_proofs.matcher();
+		let mut matcher = self.extra_finality_proofs.matcher().only_to(self.trusted_peers.as_ref());
 		std::iter::from_fn(move || {
 			if let Some((peer, request)) = matcher.next(&peers) {
 				peers.get_mut(&peer)
@@ -640,7 +864,10 @@ impl<B: BlockT> ChainSync<B> {
 
 	/// Get an iterator over all block requests of all peers.
 	pub fn block_requests(&mut self) -> impl Iterator<Item = (&PeerId, BlockRequest<B>)> + '_ {
-		if self.pending_requests.is_empty() {
+		if self.pending_requests.is_empty() || self.warp_sync.is_some() {
+			return Either::Left(std::iter::empty())
+		}
+		if let Some(FastSync::State(_)) | Some(FastSync::Importing { .. }) = self.fast_sync {
 			return Either::Left(std::iter::empty())
 		}
 		if self.queue_blocks.len() > MAX_IMPORTING_BLOCKS {
@@ -658,21 +885,46 @@ impl<B: BlockT> ChainSync<B> {
 		let queue = &self.queue_blocks;
 		let pending_requests = self.pending_requests.take();
 		let max_parallel = if major_sync { 1 } else { self.max_parallel_downloads };
+		let best_throughput = self.peers.values()
+			.filter_map(|peer| peer.download_stats.blocks_per_sec)
+			.fold(None, |best: Option<f64>, rate| Some(best.map_or(rate, |best| best.max(rate))));
+		// The bodies of old blocks are downloaded from a single peer at a time, and only when we
+		// are not catching up with the chain.
+		let gap_sync = if major_sync { None } else { self.gap_sync.as_mut() };
+		let mut gap_requested = self.peers.values().any(|peer| {
+			if let PeerSyncState::DownloadingGap(_) = peer.state { true } else { false }
+		});
+		let trusted_peers = &self.trusted_peers;
 		let iter = self.peers.iter_mut().filter_map(move |(id, peer)| {
 			if !peer.state.is_available() || !pending_requests.contains(id) {
 				return None
 			}
+			if trusted_peers.as_ref().map_or(false, |trusted| !trusted.contains(id)) {
+				return None
+			}
+
+			// Hand out smaller ranges to peers that are slower than the fastest one, so that they
+			// don't hold back the import of the ranges that follow.
+			let count = match (peer.download_stats.blocks_per_sec, best_throughput) {
+				(Some(rate), Some(best)) if best > 0.0 => {
+					let count = (MAX_BLOCKS_TO_REQUEST as f64 * rate / best) as usize;
+					count.max(MIN_BLOCKS_TO_REQUEST).min(MAX_BLOCKS_TO_REQUEST)
+				},
+				_ => MAX_BLOCKS_TO_REQUEST,
+			};
 
 			if let Some((range, req)) = peer_block_request(
 				id,
 				peer,
 				blocks,
 				attrs,
+				count,
 				max_parallel,
 				last_finalized,
 				best_queued,
 			) {
 				peer.state = PeerSyncState::DownloadingNew(range.start);
+				peer.download_stats.request_started = Some(Instant::now());
 				trace!(
 					target: "sync",
 					"New block request for {}, (best:{}, common:{}) {:?}",
@@ -699,6 +951,14 @@ impl<B: BlockT> ChainSync<B> {
 				peer.state = PeerSyncState::DownloadingStale(hash);
 				have_requests = true;
 				Some((id, req))
+			} else if let Some((start, req)) = gap_sync.as_mut()
+				.filter(|_| !gap_requested)
+				.and_then(|gap_sync| gap_sync.request(id, peer.best_number, count))
+			{
+				peer.state = PeerSyncState::DownloadingGap(start);
+				gap_requested = true;
+				have_requests = true;
+				Some((id, req))
 			} else {
 				None
 			}
@@ -719,6 +979,7 @@ impl<B: BlockT> ChainSync<B> {
 		request: Option<BlockRequest<B>>,
 		response: BlockResponse<B>
 	) -> Result<OnBlockData<B>, BadPeer> {
+		let trusted = self.is_trusted(who);
 		let mut new_blocks: Vec<IncomingBlock<B>> =
 			if let Some(peer) = self.peers.get_mut(who) {
 				let mut blocks = response.blocks;
@@ -727,14 +988,39 @@ impl<B: BlockT> ChainSync<B> {
 					blocks.reverse()
 				}
 				self.pending_requests.add(who);
+				if request.is_some() && !trusted {
+					match peer.state {
+						PeerSyncState::DownloadingNew(_)
+						| PeerSyncState::DownloadingStale(_)
+						| PeerSyncState::DownloadingGap(_) => {
+							// The request has been sent before the peer stopped being trusted.
+							debug!(target: "sync", "Ignoring blocks from untrusted peer {}", who);
+							peer.state = PeerSyncState::Available;
+							self.blocks.clear_peer_download(who);
+							if let Some(gap_sync) = &mut self.gap_sync {
+								gap_sync.peer_disconnected(who);
+							}
+							return Ok(OnBlockData::Import(BlockOrigin::NetworkInitialSync, Vec::new()))
+						},
+						_ => {},
+					}
+				}
 				if request.is_some() {
 					match &mut peer.state {
 						PeerSyncState::DownloadingNew(start_block) => {
-							self.blocks.clear_peer_download(who);
 							let start_block = *start_block;
+							// If the request stalled, the range might have been downloaded from
+							// another peer and drained in the meantime.
+							let still_needed = self.blocks.contains(start_block);
+							self.blocks.clear_peer_download(who);
 							peer.state = PeerSyncState::Available;
+							peer.download_stats.on_response(blocks.len());
 							validate_blocks::<B>(&blocks, who)?;
-							self.blocks.insert(start_block, blocks, who.clone());
+							if still_needed {
+								self.blocks.insert(start_block, blocks, who.clone());
+							} else {
+								trace!(target: "sync", "Ignoring late response from {} for #{}", who, start_block);
+							}
 							self.blocks
 								.drain(self.best_queued_number + One::one())
 								.into_iter()
@@ -747,9 +1033,27 @@ impl<B: BlockT> ChainSync<B> {
 										origin: block_data.origin,
 										allow_missing_state: true,
 										import_existing: false,
+										state: None,
 									}
 								}).collect()
 						}
+						PeerSyncState::DownloadingGap(start_block) => {
+							let start_block = *start_block;
+							peer.state = PeerSyncState::Available;
+							let gap_sync = match &mut self.gap_sync {
+								Some(gap_sync) => gap_sync,
+								None => return Ok(OnBlockData::Import(BlockOrigin::NetworkInitialSync, Vec::new())),
+							};
+							if let Err(e) = validate_blocks::<B>(&blocks, who) {
+								gap_sync.peer_disconnected(who);
+								return Err(e)
+							}
+							// These blocks are known, as only their header has been imported, and
+							// mustn't go through the checks below.
+							let new_blocks = gap_sync.on_response(who, start_block, blocks);
+							self.queue_blocks.extend(new_blocks.iter().map(|b| b.hash));
+							return Ok(OnBlockData::Import(BlockOrigin::NetworkInitialSync, new_blocks))
+						}
 						PeerSyncState::DownloadingStale(_) => {
 							peer.state = PeerSyncState::Available;
 							if blocks.is_empty() {
@@ -766,6 +1070,7 @@ impl<B: BlockT> ChainSync<B> {
 									origin: Some(who.clone()),
 									allow_missing_state: true,
 									import_existing: false,
+									state: None,
 								}
 							}).collect()
 						}
@@ -837,7 +1142,9 @@ impl<B: BlockT> ChainSync<B> {
 
 						| PeerSyncState::Available
 						| PeerSyncState::DownloadingJustification(..)
-						| PeerSyncState::DownloadingFinalityProof(..) => Vec::new()
+						| PeerSyncState::DownloadingFinalityProof(..)
+						| PeerSyncState::DownloadingWarpProof
+						| PeerSyncState::DownloadingState => Vec::new()
 					}
 				} else {
 					// When request.is_none() this is a block announcement. Just accept blocks.
@@ -851,6 +1158,7 @@ impl<B: BlockT> ChainSync<B> {
 							origin: Some(who.clone()),
 							allow_missing_state: true,
 							import_existing: false,
+							state: None,
 						}
 					}).collect()
 				}
@@ -858,9 +1166,6 @@ impl<B: BlockT> ChainSync<B> {
 				Vec::new()
 			};
 
-		// When doing initial sync we don't request blocks in parallel.
-		// So the only way this can happen is when peers lie about the
-		// common block.
 		let is_recent = new_blocks.first()
 			.map(|block| {
 				self.peers.iter().any(|(_, peer)| peer.recently_announced.contains(&block.hash))
@@ -868,9 +1173,9 @@ impl<B: BlockT> ChainSync<B> {
 			.unwrap_or(false);
 
 		if !is_recent && new_blocks.last().map_or(false, |b| self.is_known(&b.hash)) {
-			// When doing initial sync we don't request blocks in parallel.
-			// So the only way this can happen is when peers lie about the
-			// common block.
+			// When doing initial sync we only request the same blocks from several peers when a
+			// request has stalled, and late answers are discarded above. So the only way this
+			// can happen is when peers lie about the common block.
 			debug!(target: "sync", "Ignoring known blocks from {}", who);
 			return Err(BadPeer(who.clone(), rep::KNOWN_BLOCK));
 		}
@@ -880,6 +1185,13 @@ impl<B: BlockT> ChainSync<B> {
 			debug!(target: "sync", "Ignoring {} blocks that are already queued", orig_len - new_blocks.len());
 		}
 
+		if let Some(FastSync::Reimport { .. }) = self.fast_sync {
+			for block in &mut new_blocks {
+				block.import_existing = self.client.block_status(&BlockId::Hash(block.hash)).ok()
+					== Some(BlockStatus::InChainPruned);
+			}
+		}
+
 		let origin =
 			if is_recent {
 				BlockOrigin::NetworkBroadcast
@@ -906,6 +1218,7 @@ impl<B: BlockT> ChainSync<B> {
 	pub fn on_block_justification
 		(&mut self, who: PeerId, response: BlockResponse<B>) -> Result<OnBlockJustification<B>, BadPeer>
 	{
+		let trusted = self.is_trusted(&who);
 		let peer =
 			if let Some(peer) = self.peers.get_mut(&who) {
 				peer
@@ -919,7 +1232,10 @@ impl<B: BlockT> ChainSync<B> {
 			peer.state = PeerSyncState::Available;
 
 			// We only request one justification at a time
-			let justification = if let Some(block) = response.blocks.into_iter().next() {
+			let justification = if !trusted {
+				debug!(target: "sync", "Ignoring justification from untrusted peer {}", who);
+				None
+			} else if let Some(block) = response.blocks.into_iter().next() {
 				if hash != block.hash {
 					info!(
 						target: "sync",
@@ -953,6 +1269,7 @@ impl<B: BlockT> ChainSync<B> {
 	pub fn on_block_finality_proof
 		(&mut self, who: PeerId, resp: FinalityProofResponse<B::Hash>) -> Result<OnBlockFinalityProof<B>, BadPeer>
 	{
+		let trusted = self.is_trusted(&who);
 		let peer =
 			if let Some(peer) = self.peers.get_mut(&who) {
 				peer
@@ -976,7 +1293,13 @@ impl<B: BlockT> ChainSync<B> {
 				return Err(BadPeer(who, rep::BAD_FINALITY_PROOF));
 			}
 
-			if let Some((peer, hash, number, p)) = self.extra_finality_proofs.on_response(who, resp.proof) {
+			let proof = if trusted {
+				resp.proof
+			} else {
+				debug!(target: "sync", "Ignoring finality proof from untrusted peer {}", who);
+				None
+			};
+			if let Some((peer, hash, number, p)) = self.extra_finality_proofs.on_response(who, proof) {
 				return Ok(OnBlockFinalityProof::Import { peer, hash, number, proof: p })
 			}
 		}
@@ -984,6 +1307,131 @@ impl<B: BlockT> ChainSync<B> {
 		Ok(OnBlockFinalityProof::Nothing)
 	}
 
+	/// Get the warp sync proof request to send, if any.
+	///
+	/// Only one warp sync proof is downloaded at a time, from the peer with the highest best block.
+	pub fn warp_proof_request(&mut self) -> Option<(PeerId, B::Hash)> {
+		let begin = self.warp_sync.as_ref()?.next_warp_proof_request()?;
+		let peer = self.warp_sync_peer(PeerSyncState::DownloadingWarpProof)?;
+		trace!(target: "sync", "New warp sync proof request to {}, starting at {}", peer, begin);
+		Some((peer, begin))
+	}
+
+	/// Get the state request to send, if any.
+	///
+	/// Only one chunk of state is downloaded at a time, from the peer with the highest best block.
+	pub fn state_request(&mut self) -> Option<(PeerId, StateRequest<B>)> {
+		let request = match (&self.warp_sync, &self.fast_sync) {
+			(Some(warp_sync), _) => warp_sync.next_state_request()?,
+			(None, Some(FastSync::State(state_sync))) => state_sync.next_request(),
+			_ => return None,
+		};
+		let peer = self.warp_sync_peer(PeerSyncState::DownloadingState)?;
+		trace!(target: "sync", "New state request to {} for block {}", peer, request.block);
+		Some((peer, request))
+	}
+
+	/// Pick the peer to send the next warp sync proof or state request to and move it to `state`.
+	///
+	/// Returns `None` if a request of that kind is already in flight or no peer is available.
+	fn warp_sync_peer(&mut self, state: PeerSyncState<B>) -> Option<PeerId> {
+		if self.peers.values().any(|peer| peer.state == state) {
+			return None
+		}
+
+		let trusted_peers = &self.trusted_peers;
+		let (who, peer) = self.peers.iter_mut()
+			.filter(|(_, peer)| peer.state.is_available())
+			.filter(|(who, _)| trusted_peers.as_ref().map_or(true, |trusted| trusted.contains(who)))
+			.max_by_key(|(_, peer)| peer.best_number)?;
+		peer.state = state;
+		Some(who.clone())
+	}
+
+	/// Handle a response from the remote to a warp sync proof request that we made.
+	pub fn on_warp_proof_response(&mut self, who: &PeerId, proof: Vec<u8>) -> Result<(), BadPeer> {
+		match self.peers.get_mut(who) {
+			Some(peer) if peer.state == PeerSyncState::DownloadingWarpProof =>
+				peer.state = PeerSyncState::Available,
+			_ => {
+				trace!(target: "sync", "Unexpected warp sync proof response from {}", who);
+				return Ok(())
+			}
+		}
+
+		let warp_sync = match &mut self.warp_sync {
+			Some(warp_sync) => warp_sync,
+			None => return Ok(()),
+		};
+
+		warp_sync.on_warp_proof_response(&proof).map_err(|()| {
+			debug!(target: "sync", "Bad warp sync proof response from {}", who);
+			BadPeer(who.clone(), rep::BAD_WARP_PROOF)
+		})
+	}
+
+	/// Call when a warp sync proof or state request to `who` has failed or timed out.
+	///
+	/// The peer is about to be disconnected, it is forgotten right away so that the request is
+	/// sent to another peer.
+	pub fn on_warp_sync_request_failed(&mut self, who: &PeerId) {
+		match self.peers.get(who).map(|peer| &peer.state) {
+			Some(PeerSyncState::DownloadingWarpProof) | Some(PeerSyncState::DownloadingState) => {
+				debug!(target: "sync", "Warp sync request to {} failed", who);
+				self.peer_disconnected(who);
+			},
+			_ => {},
+		}
+	}
+
+	/// Handle a response from the remote to a state request that we made.
+	///
+	/// Once the whole state has been downloaded, the block it belongs to is returned for import.
+	pub fn on_state_response(
+		&mut self,
+		who: &PeerId,
+		response: StateResponse,
+	) -> Result<OnStateData<B>, BadPeer> {
+		match self.peers.get_mut(who) {
+			Some(peer) if peer.state == PeerSyncState::DownloadingState =>
+				peer.state = PeerSyncState::Available,
+			_ => {
+				trace!(target: "sync", "Unexpected state response from {}", who);
+				return Ok(OnStateData::Continue)
+			}
+		}
+
+		let result = match (&mut self.warp_sync, &mut self.fast_sync) {
+			(Some(warp_sync), _) => warp_sync.on_state_response(response),
+			(None, Some(fast_sync)) => match fast_sync {
+				FastSync::State(state_sync) => {
+					let result = state_sync.on_response(response);
+					if let Ok(Some(block)) = &result {
+						let number = *block.header.as_ref()
+							.expect("state sync always returns the header of the block; qed")
+							.number();
+						*fast_sync = FastSync::Importing { hash: block.hash, number };
+					}
+					result
+				},
+				_ => return Ok(OnStateData::Continue),
+			},
+			_ => return Ok(OnStateData::Continue),
+		};
+
+		match result {
+			Ok(Some(block)) => {
+				self.queue_blocks.insert(block.hash);
+				Ok(OnStateData::Import(BlockOrigin::NetworkInitialSync, block))
+			},
+			Ok(None) => Ok(OnStateData::Continue),
+			Err(()) => {
+				debug!(target: "sync", "Bad state response from {}", who);
+				Err(BadPeer(who.clone(), rep::BAD_STATE))
+			},
+		}
+	}
+
 	/// A batch of blocks have been processed, with or without errors.
 	///
 	/// Call this when a batch of blocks have been processed by the import
@@ -1007,6 +1455,70 @@ impl<B: BlockT> ChainSync<B> {
 		self.processed_blocks += results.len();
 
 		for (result, hash) in results {
+			let warp_target = self.warp_sync.as_ref().and_then(|warp| warp.importing_block());
+			if warp_target.map_or(false, |(target, _)| target == hash) {
+				match &result {
+					Ok(_) => info!("✅ Warp sync finished, continuing with full sync from {:?}", hash),
+					Err(e) => warn!(
+						"💔 Error importing warp sync target {:?}: {:?}, falling back to full sync",
+						hash,
+						e,
+					),
+				}
+				self.warp_sync = None;
+				output.extend(self.restart());
+				continue;
+			}
+
+			if let Some(FastSync::Importing { hash: target, number }) = self.fast_sync {
+				if target == hash {
+					match &result {
+						Ok(_) => {
+							let tip = self.best_imported_number;
+							info!(
+								"✅ Fast sync imported the state of block #{}, executing the blocks up to #{}",
+								number,
+								tip,
+							);
+							// the blocks above the state only had their header imported, which
+							// doesn't count as imported from now on.
+							self.best_imported_number = number;
+							self.required_block_attributes |= BlockAttributes::BODY;
+							if !number.is_zero() {
+								info!("⏩ Downloading the bodies of blocks #1 to #{} in the background", number);
+								self.gap_sync = Some(gap::GapSync::new(One::one(), number));
+							}
+							self.fast_sync = if tip > number {
+								Some(FastSync::Reimport { target: number, tip })
+							} else {
+								None
+							};
+						},
+						Err(e) => {
+							warn!(
+								"💔 Error importing fast sync state of {:?}: {:?}, downloading it again",
+								hash,
+								e,
+							);
+							self.fast_sync = Some(FastSync::Headers);
+						},
+					}
+					output.extend(self.restart());
+					continue;
+				}
+			}
+
+			if let Some(gap_sync) = self.gap_sync.as_mut().filter(|gap_sync| gap_sync.is_importing(&hash)) {
+				match &result {
+					Ok(_) => gap_sync.on_block_imported(&hash),
+					Err(e) => {
+						warn!("💔 Error importing the body of old block {:?}: {:?}, stopping gap sync", hash, e);
+						self.gap_sync = None;
+					},
+				}
+				continue;
+			}
+
 			if has_error {
 				continue;
 			}
@@ -1084,6 +1596,18 @@ impl<B: BlockT> ChainSync<B> {
 			};
 		}
 
+		if let Some(FastSync::Reimport { tip, .. }) = self.fast_sync {
+			if self.best_imported_number >= tip {
+				info!("✅ Fast sync finished, continuing with full sync from #{}", self.best_imported_number);
+				self.fast_sync = None;
+			}
+		}
+
+		if self.gap_sync.as_ref().map_or(false, |gap_sync| gap_sync.is_complete()) {
+			info!("✅ Gap sync finished, all block bodies have been downloaded");
+			self.gap_sync = None;
+		}
+
 		self.pending_requests.set_all();
 		output.into_iter()
 	}
@@ -1120,6 +1644,39 @@ impl<B: BlockT> ChainSync<B> {
 		if let Err(err) = r {
 			warn!(target: "sync", "💔 Error cleaning up pending extra justification data requests: {:?}", err);
 		}
+
+		if let Some(FastSync::Headers) = self.fast_sync {
+			self.start_state_sync(hash, number);
+		}
+	}
+
+	/// Start downloading the state of the finalized block `hash`, if it is close enough to the
+	/// best block of our peers.
+	fn start_state_sync(&mut self, hash: &B::Hash, number: NumberFor<B>) {
+		let mut peer_best: Vec<_> = self.peers.values().map(|peer| peer.best_number).collect();
+		if peer_best.is_empty() {
+			return
+		}
+		peer_best.sort();
+		let median_best = peer_best[peer_best.len() / 2];
+		if number + STATE_SYNC_FINALITY_THRESHOLD.into() < median_best {
+			return
+		}
+
+		let header = match self.client.header(BlockId::Hash(*hash)) {
+			Ok(Some(header)) => header,
+			Ok(None) => {
+				debug!(target: "sync", "Missing header of finalized block {:?}", hash);
+				return
+			},
+			Err(e) => {
+				debug!(target: "sync", "Error reading header of finalized block {:?}: {:?}", hash, e);
+				return
+			},
+		};
+
+		info!("⏩ Fast sync downloading the state of block #{} ({})", number, hash);
+		self.fast_sync = Some(FastSync::State(state::StateSync::new(self.client.clone(), header, None, true)));
 	}
 
 	/// Called when a block has been queued for import.
@@ -1263,9 +1820,50 @@ impl<B: BlockT> ChainSync<B> {
 		OnBlockAnnounce::Nothing
 	}
 
+	/// Call when a peer has sent its chain position in the block announces handshake.
+	///
+	/// The handshake may arrive before or after the peer is known to sync. In the latter case, the
+	/// best block of the peer is updated if it is ahead of what we knew, which saves waiting for
+	/// the first announcement to start downloading from it.
+	pub fn on_block_announce_handshake(&mut self, who: &PeerId, info: &PeerChainInfo<B>) -> Result<(), BadPeer> {
+		match self.block_announce_validator.validate_handshake(info) {
+			Ok(Validation::Success) => (),
+			Ok(Validation::Failure) => {
+				debug!(target: "sync", "Block announces handshake validation from {} failed", who);
+				return Err(BadPeer(who.clone(), rep::BAD_HANDSHAKE))
+			}
+			Err(e) => {
+				error!(target: "sync", "💔 Block announces handshake validation errored: {}", e);
+				return Err(BadPeer(who.clone(), rep::BAD_HANDSHAKE))
+			}
+		}
+
+		if let Some(peer) = self.peers.get_mut(who) {
+			if let PeerSyncState::Available = peer.state {
+				if info.best_number > peer.best_number {
+					trace!(
+						target: "sync",
+						"Peer {} reported best block #{} ({}) in handshake",
+						who,
+						info.best_number,
+						info.best_hash,
+					);
+					peer.best_number = info.best_number;
+					peer.best_hash = info.best_hash;
+					self.pending_requests.add(who);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
 	/// Call when a peer has disconnected.
 	pub fn peer_disconnected(&mut self, who: &PeerId) {
 		self.blocks.clear_peer_download(who);
+		if let Some(gap_sync) = &mut self.gap_sync {
+			gap_sync.peer_disconnected(who);
+		}
 		self.peers.remove(who);
 		self.extra_justifications.peer_disconnected(who);
 		self.extra_finality_proofs.peer_disconnected(who);
@@ -1276,6 +1874,9 @@ impl<B: BlockT> ChainSync<B> {
 	fn restart<'a>(&'a mut self) -> impl Iterator<Item = Result<(PeerId, BlockRequest<B>), BadPeer>> + 'a {
 		self.processed_blocks = 0;
 		self.blocks.clear();
+		if let Some(gap_sync) = &mut self.gap_sync {
+			gap_sync.restart();
+		}
 		let info = self.client.info();
 		self.best_queued_hash = info.best_hash;
 		self.best_queued_number = std::cmp::max(info.best_number, self.best_imported_number);
@@ -1296,7 +1897,17 @@ impl<B: BlockT> ChainSync<B> {
 		if self.queue_blocks.contains(hash) {
 			return Ok(BlockStatus::Queued)
 		}
-		self.client.block_status(&BlockId::Hash(*hash))
+		let status = self.client.block_status(&BlockId::Hash(*hash))?;
+
+		// the blocks above the state downloaded by fast sync are still to be downloaded along
+		// with their body.
+		if let Some(FastSync::Reimport { target, .. }) = self.fast_sync {
+			if status == BlockStatus::InChainPruned && self.client.number(*hash)?.map_or(false, |n| n > target) {
+				return Ok(BlockStatus::Unknown)
+			}
+		}
+
+		Ok(status)
 	}
 
 	/// Is the block corresponding to the given hash known?
@@ -1410,6 +2021,7 @@ fn peer_block_request<B: BlockT>(
 	peer: &PeerSync<B>,
 	blocks: &mut BlockCollection<B>,
 	attrs: &message::BlockAttributes,
+	count: usize,
 	max_parallel_downloads: u32,
 	finalized: NumberFor<B>,
 	best_num: NumberFor<B>,
@@ -1427,7 +2039,7 @@ fn peer_block_request<B: BlockT>(
 	}
 	if let Some(range) = blocks.needed_blocks(
 		id.clone(),
-		MAX_BLOCKS_TO_REQUEST,
+		count,
 		peer.best_number,
 		peer.common_number,
 		max_parallel_downloads,
@@ -1571,6 +2183,8 @@ mod test {
 			client.clone(),
 			&info,
 			None,
+			None,
+			false,
 			block_announce_validator,
 			1,
 		);
@@ -1632,4 +2246,95 @@ mod test {
 			})
 		);
 	}
+
+	#[test]
+	fn block_announce_handshake_updates_peer_best_block() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let block_announce_validator = Box::new(DefaultBlockAnnounceValidator::new(client.clone()));
+		let peer_id = PeerId::random();
+
+		let mut sync = ChainSync::new(
+			Roles::AUTHORITY,
+			client.clone(),
+			&info,
+			None,
+			None,
+			false,
+			block_announce_validator,
+			1,
+		);
+
+		// the peer is at genesis as far as we know
+		sync.new_peer(peer_id.clone(), info.genesis_hash, 0).unwrap();
+
+		let best_hash = sp_core::H256::repeat_byte(1);
+		let handshake = PeerChainInfo {
+			genesis_hash: info.genesis_hash,
+			best_number: 10,
+			best_hash,
+			finalized_number: 5,
+			finalized_hash: sp_core::H256::repeat_byte(2),
+		};
+		assert!(sync.on_block_announce_handshake(&peer_id, &handshake).is_ok());
+
+		let peer = sync.peer_info(&peer_id).unwrap();
+		assert_eq!(peer.best_number, 10);
+		assert_eq!(peer.best_hash, best_hash);
+
+		// an older best block doesn't go back in time
+		let handshake = PeerChainInfo { best_number: 3, best_hash: Default::default(), ..handshake };
+		assert!(sync.on_block_announce_handshake(&peer_id, &handshake).is_ok());
+		assert_eq!(sync.peer_info(&peer_id).unwrap().best_number, 10);
+	}
+
+	#[test]
+	fn failed_warp_proof_request_is_sent_to_another_peer() {
+		struct NoWarpProofs;
+
+		impl WarpSyncProvider<Block> for NoWarpProofs {
+			fn generate(&self, _: <Block as BlockT>::Hash) -> Result<Vec<u8>, sp_blockchain::Error> {
+				Ok(Vec::new())
+			}
+
+			fn verify(
+				&self,
+				_: &[u8],
+				_: Vec<u8>,
+			) -> Result<crate::chain::WarpSyncVerification<Block>, sp_blockchain::Error> {
+				Err(sp_blockchain::Error::Backend("No warp sync proof".into()))
+			}
+
+			fn current_authorities(&self) -> Vec<u8> {
+				Vec::new()
+			}
+		}
+
+		let client = Arc::new(TestClientBuilder::new().build());
+		let info = client.info();
+		let block_announce_validator = Box::new(DefaultBlockAnnounceValidator::new(client.clone()));
+		let (peer1, peer2) = (PeerId::random(), PeerId::random());
+
+		let mut sync = ChainSync::new(
+			Roles::FULL,
+			client.clone(),
+			&info,
+			None,
+			Some(Arc::new(NoWarpProofs)),
+			false,
+			block_announce_validator,
+			1,
+		);
+
+		sync.new_peer(peer1.clone(), sp_core::H256::repeat_byte(1), 10).unwrap();
+		sync.new_peer(peer2.clone(), sp_core::H256::repeat_byte(2), 5).unwrap();
+
+		// the proof is requested from the peer with the highest best block, one at a time.
+		assert_eq!(sync.warp_proof_request(), Some((peer1.clone(), info.genesis_hash)));
+		assert_eq!(sync.warp_proof_request(), None);
+
+		// once the request fails, the proof is requested from the other peer.
+		sync.on_warp_sync_request_failed(&peer1);
+		assert_eq!(sync.warp_proof_request(), Some((peer2, info.genesis_hash)));
+	}
 }
@@ -19,11 +19,17 @@
 use std::cmp;
 use std::ops::Range;
 use std::collections::{HashMap, BTreeMap};
+use std::time::Duration;
 use log::trace;
 use libp2p::PeerId;
 use sp_runtime::traits::{Block as BlockT, NumberFor, One};
+use wasm_timer::Instant;
 use crate::protocol::message;
 
+/// Time after which a range that is still being downloaded is considered stalled. A stalled range
+/// is handed out to one more peer, even if that exceeds the maximum number of parallel downloads.
+const STALLED_RANGE_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Block data with origin.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BlockData<B: BlockT> {
@@ -38,6 +44,8 @@ enum BlockRangeState<B: BlockT> {
 	Downloading {
 		len: NumberFor<B>,
 		downloading: u32,
+		/// When the range was last handed out to a peer.
+		assigned: Instant,
 	},
 	Complete(Vec<BlockData<B>>),
 }
@@ -118,8 +126,8 @@ impl<B: BlockT> BlockCollection<B> {
 			loop {
 				let next = downloading_iter.next();
 				break match (prev, next) {
-					(Some((start, &BlockRangeState::Downloading { ref len, downloading })), _)
-						if downloading < max_parallel =>
+					(Some((start, &BlockRangeState::Downloading { ref len, downloading, assigned })), _)
+						if downloading < max_parallel || assigned.elapsed() >= STALLED_RANGE_TIMEOUT =>
 						(*start .. *start + *len, downloading),
 					(Some((start, r)), Some((next_start, _))) if *start + r.len() < *next_start =>
 						(*start + r.len() .. cmp::min(*next_start, *start + r.len() + count), 0), // gap
@@ -149,9 +157,13 @@ impl<B: BlockT> BlockCollection<B> {
 		}
 
 		self.peer_requests.insert(who, range.start);
+		if downloading > 0 {
+			trace!(target: "sync", "Requesting {:?} from one more peer: {}", range, who);
+		}
 		self.blocks.insert(range.start, BlockRangeState::Downloading {
 			len: range.end - range.start,
-			downloading: downloading + 1
+			downloading: downloading + 1,
+			assigned: Instant::now(),
 		});
 		if range.end <= range.start {
 			panic!("Empty range {:?}, count={}, peer_best={}, common={}, blocks={:?}",
@@ -185,6 +197,12 @@ impl<B: BlockT> BlockCollection<B> {
 		drained
 	}
 
+	/// Returns true if the range starting at `start` is being downloaded or has been downloaded
+	/// but not drained yet.
+	pub fn contains(&self, start: NumberFor<B>) -> bool {
+		self.blocks.contains_key(&start)
+	}
+
 	pub fn clear_peer_download(&mut self, who: &PeerId) {
 		if let Some(start) = self.peer_requests.remove(who) {
 			let remove = match self.blocks.get_mut(&start) {
@@ -208,10 +226,11 @@ impl<B: BlockT> BlockCollection<B> {
 
 #[cfg(test)]
 mod test {
-	use super::{BlockCollection, BlockData, BlockRangeState};
+	use super::{BlockCollection, BlockData, BlockRangeState, STALLED_RANGE_TIMEOUT};
 	use crate::{protocol::message, PeerId};
 	use sp_runtime::testing::{Block as RawBlock, ExtrinsicWrapper};
 	use sp_core::H256;
+	use wasm_timer::Instant;
 
 	type Block = RawBlock<ExtrinsicWrapper<u64>>;
 
@@ -295,6 +314,7 @@ mod test {
 		bc.blocks.insert(100, BlockRangeState::Downloading {
 			len: 128,
 			downloading: 1,
+			assigned: Instant::now(),
 		});
 		let blocks = generate_blocks(10).into_iter().map(|b| BlockData { block: b, origin: None }).collect();
 		bc.blocks.insert(114305, BlockRangeState::Complete(blocks));
@@ -304,4 +324,31 @@ mod test {
 		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200), None); // too far ahead
 		assert_eq!(bc.needed_blocks(peer0.clone(), 128, 10000, 600, 1, 200000), Some(100 + 128 .. 100 + 128 + 128));
 	}
+
+	#[test]
+	fn stalled_range_is_requested_from_another_peer() {
+		let mut bc: BlockCollection<Block> = BlockCollection::new();
+		let peer0 = PeerId::random();
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+
+		assert_eq!(bc.needed_blocks(peer0.clone(), 40, 150, 0, 1, 200), Some(1 .. 41));
+
+		// Pretend that `peer0` has been sitting on its request for too long.
+		if let Some(BlockRangeState::Downloading { assigned, .. }) = bc.blocks.get_mut(&1) {
+			*assigned = Instant::now() - STALLED_RANGE_TIMEOUT;
+		}
+
+		assert_eq!(bc.needed_blocks(peer1.clone(), 40, 150, 0, 1, 200), Some(1 .. 41));
+		// The range has just been handed out again and isn't considered stalled anymore.
+		assert_eq!(bc.needed_blocks(peer2.clone(), 40, 150, 0, 1, 200), Some(41 .. 81));
+
+		// The first answer completes the range, the late one doesn't remove it.
+		bc.clear_peer_download(&peer1);
+		bc.insert(1, generate_blocks(40), peer1.clone());
+		bc.clear_peer_download(&peer0);
+		assert!(bc.contains(1));
+		assert_eq!(bc.drain(1).len(), 40);
+		assert!(!bc.contains(1));
+	}
 }
@@ -269,17 +269,26 @@ pub(crate) struct Matcher<'a, B: BlockT> {
 	/// Length of pending requests collection.
 	/// Used to ensure we do not loop more than once over all pending requests.
 	remaining: usize,
-	extras: &'a mut ExtraRequests<B>
+	extras: &'a mut ExtraRequests<B>,
+	/// If set, only these peers are asked.
+	allowed: Option<&'a HashSet<PeerId>>,
 }
 
 impl<'a, B: BlockT> Matcher<'a, B> {
 	fn new(extras: &'a mut ExtraRequests<B>) -> Self {
 		Matcher {
 			remaining: extras.pending_requests.len(),
-			extras
+			extras,
+			allowed: None,
 		}
 	}
 
+	/// Only send requests to the given peers, if any.
+	pub(crate) fn only_to(mut self, allowed: Option<&'a HashSet<PeerId>>) -> Self {
+		self.allowed = allowed;
+		self
+	}
+
 	/// Finds a peer to which a pending request can be sent.
 	///
 	/// Peers are filtered according to the current known best block (i.e. we won't
@@ -310,6 +319,9 @@ impl<'a, B: BlockT> Matcher<'a, B> {
 				if sync.best_number < request.1 {
 					continue
 				}
+				if self.allowed.map_or(false, |allowed| !allowed.contains(peer)) {
+					continue
+				}
 				// don't request to any peers that already have pending requests
 				if self.extras.active_requests.contains_key(peer) {
 					continue
@@ -550,7 +562,8 @@ mod tests {
 				best_hash: Hash::random(),
 				best_number: g.gen(),
 				state: ArbitraryPeerSyncState::arbitrary(g).0,
-				recently_announced: Default::default()
+				recently_announced: Default::default(),
+				download_stats: Default::default(),
 			};
 			ArbitraryPeerSync(ps)
 		}
@@ -0,0 +1,224 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Gap sync: downloading the bodies of the blocks below the state imported by fast sync.
+//!
+//! Fast sync only downloads the headers of the blocks below the block whose state it imports.
+//! Once the node has caught up with the chain, the bodies of these blocks are downloaded in the
+//! background, one range at a time and only from peers that have nothing else to do, and are
+//! imported on top of the existing headers.
+//!
+//! Warp sync doesn't download the headers below the block whose state it imports, so there is
+//! no gap sync after a warp sync.
+
+use crate::protocol::message::{self, BlockAttributes, BlockRequest};
+use libp2p::PeerId;
+use log::trace;
+use sp_consensus::import_queue::IncomingBlock;
+use sp_runtime::traits::{Block as BlockT, Header, NumberFor, One, SaturatedConversion};
+use std::{cmp, collections::HashMap};
+use super::{MAX_DOWNLOAD_AHEAD, blocks::BlockCollection};
+
+/// Progress of a gap sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GapSyncProgress<N> {
+	/// First block of the gap.
+	pub start: N,
+	/// Latest block whose body has been imported.
+	pub current: N,
+	/// Last block of the gap.
+	pub end: N,
+}
+
+/// State of a gap sync in progress.
+pub struct GapSync<B: BlockT> {
+	/// Ranges of blocks being downloaded.
+	blocks: BlockCollection<B>,
+	/// First block of the gap.
+	start: NumberFor<B>,
+	/// Last block of the gap.
+	end: NumberFor<B>,
+	/// Latest block handed over to the import queue.
+	queued: NumberFor<B>,
+	/// Latest block imported.
+	imported: NumberFor<B>,
+	/// Blocks handed over to the import queue and not processed yet.
+	importing: HashMap<B::Hash, NumberFor<B>>,
+}
+
+impl<B: BlockT> GapSync<B> {
+	/// Start downloading the bodies of the blocks `start..=end`.
+	pub fn new(start: NumberFor<B>, end: NumberFor<B>) -> Self {
+		let before_start = start - One::one();
+		GapSync {
+			blocks: BlockCollection::new(),
+			start,
+			end,
+			queued: before_start,
+			imported: before_start,
+			importing: HashMap::new(),
+		}
+	}
+
+	/// Returns the progress of the gap sync.
+	pub fn progress(&self) -> GapSyncProgress<NumberFor<B>> {
+		GapSyncProgress { start: self.start, current: self.imported, end: self.end }
+	}
+
+	/// Returns true once the body of every block of the gap has been imported.
+	pub fn is_complete(&self) -> bool {
+		self.imported >= self.end && self.importing.is_empty()
+	}
+
+	/// Get the next request to send to `who`, whose best block is `peer_best`, along with the
+	/// number of the first block requested.
+	pub fn request(
+		&mut self,
+		who: &PeerId,
+		peer_best: NumberFor<B>,
+		count: usize,
+	) -> Option<(NumberFor<B>, BlockRequest<B>)> {
+		let range = self.blocks.needed_blocks(
+			who.clone(),
+			count,
+			cmp::min(peer_best, self.end),
+			self.queued,
+			1,
+			MAX_DOWNLOAD_AHEAD,
+		)?;
+		trace!(target: "sync", "New gap sync request to {}: {:?}", who, range);
+		let request = message::generic::BlockRequest {
+			id: 0,
+			fields: BlockAttributes::HEADER | BlockAttributes::BODY | BlockAttributes::JUSTIFICATION,
+			from: message::FromBlock::Number(range.start),
+			to: None,
+			direction: message::Direction::Ascending,
+			max: Some((range.end - range.start).saturated_into::<u32>()),
+		};
+		Some((range.start, request))
+	}
+
+	/// Handle the blocks sent by `who` in response to the request starting at `start`. Returns
+	/// the blocks that can be imported.
+	pub fn on_response(
+		&mut self,
+		who: &PeerId,
+		start: NumberFor<B>,
+		blocks: Vec<message::BlockData<B>>,
+	) -> Vec<IncomingBlock<B>> {
+		let still_needed = self.blocks.contains(start);
+		self.blocks.clear_peer_download(who);
+		if still_needed {
+			self.blocks.insert(start, blocks, who.clone());
+		} else {
+			trace!(target: "sync", "Ignoring late gap sync response from {} for #{}", who, start);
+		}
+
+		let drained = self.blocks.drain(self.queued + One::one());
+		let mut new_blocks = Vec::with_capacity(drained.len());
+		for block_data in drained {
+			let number = match block_data.block.header.as_ref() {
+				Some(header) => *header.number(),
+				None => break,
+			};
+			self.queued = number;
+			self.importing.insert(block_data.block.hash, number);
+			new_blocks.push(IncomingBlock {
+				hash: block_data.block.hash,
+				header: block_data.block.header,
+				body: block_data.block.body,
+				justification: block_data.block.justification,
+				origin: block_data.origin,
+				allow_missing_state: true,
+				import_existing: true,
+				state: None,
+			});
+		}
+		new_blocks
+	}
+
+	/// Returns true if `hash` is a block of the gap being imported.
+	pub fn is_importing(&self, hash: &B::Hash) -> bool {
+		self.importing.contains_key(hash)
+	}
+
+	/// Call when the block `hash` of the gap has been successfully imported.
+	pub fn on_block_imported(&mut self, hash: &B::Hash) {
+		if let Some(number) = self.importing.remove(hash) {
+			self.imported = cmp::max(self.imported, number);
+		}
+	}
+
+	/// Call when `who` has disconnected.
+	pub fn peer_disconnected(&mut self, who: &PeerId) {
+		self.blocks.clear_peer_download(who);
+	}
+
+	/// Forget the ranges being downloaded and not handed over to the import queue yet.
+	pub fn restart(&mut self) {
+		self.blocks.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::GapSync;
+	use crate::protocol::message;
+	use libp2p::PeerId;
+	use sp_runtime::traits::Header as _;
+	use substrate_test_runtime_client::runtime::{Block, Header};
+
+	fn block_data(number: u64) -> message::BlockData<Block> {
+		let header = Header::new(number, Default::default(), Default::default(), Default::default(), Default::default());
+		message::BlockData::<Block> {
+			hash: header.hash(),
+			header: Some(header),
+			body: Some(Vec::new()),
+			receipt: None,
+			message_queue: None,
+			justification: None,
+		}
+	}
+
+	#[test]
+	fn downloads_and_imports_the_gap_in_order() {
+		let mut gap = GapSync::<Block>::new(1, 4);
+		let peer1 = PeerId::random();
+		let peer2 = PeerId::random();
+
+		let (start1, _) = gap.request(&peer1, 100, 2).unwrap();
+		let (start2, _) = gap.request(&peer2, 100, 2).unwrap();
+		assert_eq!((start1, start2), (1, 3));
+		// the whole gap is being downloaded.
+		assert!(gap.request(&PeerId::random(), 100, 2).is_none());
+
+		// blocks are only handed over once the ones before them are.
+		assert!(gap.on_response(&peer2, 3, vec![block_data(3), block_data(4)]).is_empty());
+		let blocks = gap.on_response(&peer1, 1, vec![block_data(1), block_data(2)]);
+		assert_eq!(blocks.len(), 4);
+		assert!(blocks.iter().all(|b| b.import_existing && b.body.is_some()));
+
+		for block in &blocks {
+			assert!(!gap.is_complete());
+			assert!(gap.is_importing(&block.hash));
+			gap.on_block_imported(&block.hash);
+		}
+		assert!(gap.is_complete());
+		assert_eq!(gap.progress().current, 4);
+	}
+}
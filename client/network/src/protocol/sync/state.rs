@@ -0,0 +1,152 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Download of the state of a single block, one chunk at a time.
+//!
+//! Every chunk comes with a proof of the trie nodes holding it, which is checked against the
+//! state root of the block before the entries are kept. A peer sending a bad chunk is caught
+//! right away, rather than once the whole state has been downloaded and fails to import.
+
+use crate::{chain::Client, schema::v1::state::StateResponse};
+use codec::Decode;
+use log::{debug, info};
+use sc_client_api::StorageProof;
+use sp_consensus::{ImportedState, import_queue::IncomingBlock};
+use sp_core::storage::ChildInfo;
+use sp_runtime::{Justification, traits::{Block as BlockT, Header, NumberFor}};
+use std::sync::Arc;
+
+/// Request for a chunk of the state of the block being downloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateRequest<B: BlockT> {
+	/// Hash of the block.
+	pub block: B::Hash,
+	/// Keys to resume from, empty to start from the beginning of the state.
+	pub start: Vec<Vec<u8>>,
+}
+
+/// State of a state download in progress.
+pub struct StateSync<B: BlockT> {
+	client: Arc<dyn Client<B>>,
+	header: B::Header,
+	justification: Option<Justification>,
+	state: ImportedState<B>,
+	/// Keys the next chunk starts after. See [`StateRequest::start`].
+	start: Vec<Vec<u8>>,
+	/// Whether the header of the block has already been imported.
+	import_existing: bool,
+}
+
+impl<B: BlockT> StateSync<B> {
+	/// Start downloading the state of the block with the given `header`.
+	///
+	/// Once complete, the block is imported along with its state and `justification`. If
+	/// `import_existing` is set, the header of the block is already in the database.
+	pub fn new(
+		client: Arc<dyn Client<B>>,
+		header: B::Header,
+		justification: Option<Justification>,
+		import_existing: bool,
+	) -> Self {
+		let state = ImportedState { block: header.hash(), top: Vec::new(), children: Vec::new() };
+		StateSync {
+			client,
+			header,
+			justification,
+			state,
+			start: Vec::new(),
+			import_existing,
+		}
+	}
+
+	/// Returns the hash and number of the block the state is downloaded for.
+	pub fn target(&self) -> (B::Hash, NumberFor<B>) {
+		(self.header.hash(), *self.header.number())
+	}
+
+	/// Returns the next chunk of state to request.
+	pub fn next_request(&self) -> StateRequest<B> {
+		StateRequest { block: self.header.hash(), start: self.start.clone() }
+	}
+
+	/// Handle a chunk of state. Returns the block to import once the state is complete, or an
+	/// error if the response doesn't prove a chunk of the state starting where we are at.
+	pub fn on_response(&mut self, response: StateResponse) -> Result<Option<IncomingBlock<B>>, ()> {
+		let proof = StorageProof::decode(&mut &response.proof[..]).map_err(|e| {
+			debug!(target: "sync", "Undecodable state proof: {:?}", e);
+		})?;
+		let (entries, complete) = self.client
+			.verify_range_proof(*self.header.state_root(), proof, &self.start)
+			.map_err(|e| {
+				debug!(target: "sync", "Bad state proof: {:?}", e);
+			})?;
+
+		if entries.is_empty() && !complete {
+			debug!(target: "sync", "Empty state response for incomplete state");
+			return Err(())
+		}
+
+		for entry in entries {
+			let storage_key = match entry.child {
+				None => {
+					self.start = vec![entry.key.clone()];
+					self.state.top.push((entry.key, entry.value));
+					continue
+				},
+				Some(storage_key) => storage_key,
+			};
+
+			if self.state.children.last().map_or(true, |(key, _)| *key != storage_key) {
+				self.state.children.push((storage_key.clone(), Vec::new()));
+			}
+			let (_, child) = self.state.children.last_mut()
+				.expect("an entry for the child trie has been pushed above; qed");
+			let prefixed_key = ChildInfo::new_default(&storage_key).prefixed_storage_key().into_inner();
+			self.start = vec![prefixed_key, entry.key.clone()];
+			child.push((entry.key, entry.value));
+		}
+
+		if !complete {
+			return Ok(None)
+		}
+
+		let (hash, number) = self.target();
+		info!(
+			"⏩ Downloaded the state of block #{} ({}): {} top entries, {} child tries",
+			number,
+			hash,
+			self.state.top.len(),
+			self.state.children.len(),
+		);
+
+		let state = std::mem::replace(
+			&mut self.state,
+			ImportedState { block: hash, top: Vec::new(), children: Vec::new() },
+		);
+		Ok(Some(IncomingBlock {
+			hash,
+			header: Some(self.header.clone()),
+			body: None,
+			justification: self.justification.take(),
+			origin: None,
+			allow_missing_state: true,
+			import_existing: self.import_existing,
+			state: Some(state),
+		}))
+	}
+}
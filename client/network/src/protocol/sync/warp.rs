@@ -0,0 +1,144 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Warp sync: catching up with the chain by downloading a proof of finality of the latest
+//! finalized block followed by the state of that block, rather than every block since genesis.
+
+use crate::{
+	chain::{Client, WarpSyncProvider, WarpSyncVerification},
+	schema::v1::state::StateResponse,
+};
+use log::{debug, info};
+use sp_consensus::import_queue::IncomingBlock;
+use sp_runtime::traits::{Block as BlockT, Header, NumberFor};
+use std::{fmt, sync::Arc};
+use super::state::{StateRequest, StateSync};
+
+enum Phase<B: BlockT> {
+	/// Downloading the proofs of finality, starting at `last_hash`, to be verified against
+	/// `authorities`.
+	WarpProof { last_hash: B::Hash, authorities: Vec<u8> },
+	/// Downloading the state of the latest finalized block.
+	State(StateSync<B>),
+	/// The latest finalized block has been handed over to the import queue along with its state.
+	Importing { hash: B::Hash, number: NumberFor<B> },
+}
+
+/// State of a warp sync in progress.
+pub struct WarpSync<B: BlockT> {
+	phase: Phase<B>,
+	client: Arc<dyn Client<B>>,
+	provider: Arc<dyn WarpSyncProvider<B>>,
+}
+
+impl<B: BlockT> fmt::Debug for WarpSync<B> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let phase = match self.phase {
+			Phase::WarpProof { .. } => "WarpProof",
+			Phase::State(_) => "State",
+			Phase::Importing { .. } => "Importing",
+		};
+		f.debug_struct("WarpSync").field("phase", &phase).finish()
+	}
+}
+
+impl<B: BlockT> WarpSync<B> {
+	/// Start a warp sync from our latest finalized block, `finalized_hash`.
+	pub fn new(
+		client: Arc<dyn Client<B>>,
+		finalized_hash: B::Hash,
+		provider: Arc<dyn WarpSyncProvider<B>>,
+	) -> Self {
+		let authorities = provider.current_authorities();
+		WarpSync {
+			phase: Phase::WarpProof { last_hash: finalized_hash, authorities },
+			client,
+			provider,
+		}
+	}
+
+	/// Returns the block the next warp sync proof must start at, if we are downloading proofs.
+	pub fn next_warp_proof_request(&self) -> Option<B::Hash> {
+		match &self.phase {
+			Phase::WarpProof { last_hash, .. } => Some(*last_hash),
+			_ => None,
+		}
+	}
+
+	/// Returns the next chunk of state to request, if we are downloading the state.
+	pub fn next_state_request(&self) -> Option<StateRequest<B>> {
+		match &self.phase {
+			Phase::State(state_sync) => Some(state_sync.next_request()),
+			_ => None,
+		}
+	}
+
+	/// Returns the block being imported along with its state, if any.
+	pub fn importing_block(&self) -> Option<(B::Hash, NumberFor<B>)> {
+		match &self.phase {
+			Phase::Importing { hash, number } => Some((*hash, *number)),
+			_ => None,
+		}
+	}
+
+	/// Handle a warp sync proof. Returns an error if the proof is invalid.
+	pub fn on_warp_proof_response(&mut self, proof: &[u8]) -> Result<(), ()> {
+		let authorities = match &self.phase {
+			Phase::WarpProof { authorities, .. } => authorities.clone(),
+			_ => return Ok(()),
+		};
+
+		match self.provider.verify(proof, authorities) {
+			Err(e) => {
+				debug!(target: "sync", "Invalid warp sync proof: {:?}", e);
+				Err(())
+			},
+			Ok(WarpSyncVerification::Partial { authorities, last_hash }) => {
+				debug!(target: "sync", "Verified warp sync proof up to {}", last_hash);
+				self.phase = Phase::WarpProof { last_hash, authorities };
+				Ok(())
+			},
+			Ok(WarpSyncVerification::Complete { header, justification }) => {
+				info!(
+					"⏩ Warp sync proof verified, downloading the state of block #{} ({})",
+					header.number(),
+					header.hash(),
+				);
+				let state_sync = StateSync::new(self.client.clone(), header, Some(justification), false);
+				self.phase = Phase::State(state_sync);
+				Ok(())
+			},
+		}
+	}
+
+	/// Handle a chunk of state. Returns the block to import once the state is complete, or an
+	/// error if the response is invalid.
+	pub fn on_state_response(&mut self, response: StateResponse) -> Result<Option<IncomingBlock<B>>, ()> {
+		let state_sync = match &mut self.phase {
+			Phase::State(state_sync) => state_sync,
+			_ => return Ok(None),
+		};
+
+		let block = state_sync.on_response(response)?;
+		if block.is_some() {
+			let (hash, number) = state_sync.target();
+			self.phase = Phase::Importing { hash, number };
+		}
+		Ok(block)
+	}
+}
@@ -15,7 +15,8 @@
 // along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
 
 use linked_hash_set::LinkedHashSet;
-use std::{hash::Hash, num::NonZeroUsize};
+use std::{cmp, hash::Hash, num::NonZeroUsize};
+use wasm_timer::Instant;
 
 /// Wrapper around `LinkedHashSet` which grows bounded.
 ///
@@ -46,6 +47,43 @@ impl<T: Hash + Eq> LruHashSet<T> {
 		}
 		false
 	}
+
+	/// Returns `true` if the set contains the element. Doesn't update its LRU position.
+	pub(crate) fn contains(&self, e: &T) -> bool {
+		self.set.contains(e)
+	}
+}
+
+/// Limits the rate of an operation to a number of times per second, allowing bursts of up to
+/// one second worth of operations.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+	/// Maximum number of operations per second.
+	rate: u32,
+	/// Number of operations that can be performed right now.
+	available: f64,
+	/// When `available` was last updated.
+	last_update: Instant,
+}
+
+impl RateLimiter {
+	/// Create a new `RateLimiter` allowing `rate` operations per second.
+	pub(crate) fn new(rate: u32) -> Self {
+		RateLimiter { rate, available: f64::from(rate), last_update: Instant::now() }
+	}
+
+	/// Accounts for performing up to `wanted` operations, and returns how many of them are
+	/// allowed.
+	pub(crate) fn take(&mut self, wanted: usize) -> usize {
+		let now = Instant::now();
+		let elapsed = now.duration_since(self.last_update).as_secs_f64();
+		self.available = (self.available + elapsed * f64::from(self.rate)).min(f64::from(self.rate));
+		self.last_update = now;
+
+		let allowed = cmp::min(wanted, self.available as usize);
+		self.available -= allowed as f64;
+		allowed
+	}
 }
 
 #[cfg(test)]
@@ -73,4 +111,12 @@ mod tests {
 		assert!(set.insert(3));
 		assert_eq!(vec![&1, &3], set.set.iter().collect::<Vec<_>>());
 	}
+
+	#[test]
+	fn rate_limiter_allows_bursts_up_to_rate() {
+		let mut limiter = RateLimiter::new(10);
+		assert_eq!(limiter.take(4), 4);
+		assert_eq!(limiter.take(10), 6);
+		assert_eq!(limiter.take(1), 0);
+	}
 }
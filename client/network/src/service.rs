@@ -30,25 +30,35 @@
 use crate::{
 	ExHashT, NetworkStateInfo,
 	behaviour::{Behaviour, BehaviourOut},
-	config::{parse_addr, parse_str_addr, NonReservedPeerMode, Params, Role, TransportConfig},
+	config::{
+		parse_addr, parse_str_addr, IpFilter, NonReservedPeerMode, Params, Role, SyncMode,
+		TrafficDumpConfig, TransportConfig,
+	},
 	discovery::DiscoveryConfig,
 	error::Error,
+	ip_filter::ConnectionTracker,
 	network_state::{
 		NetworkState, NotConnectedPeer as NetworkStateNotConnectedPeer, Peer as NetworkStatePeer,
 	},
 	on_demand_layer::AlwaysBadChecker,
-	light_client_handler, block_requests, finality_requests,
-	protocol::{self, event::Event, LegacyConnectionKillError, sync::SyncState, PeerInfo, Protocol},
-	transport, ReputationChange,
+	bitswap::Bitswap, light_client_handler, block_requests, finality_requests, state_requests, warp_requests,
+	protocol::{
+		self, event::{DhtEvent, Event}, DeliveryConfirmer, LegacyConnectionKillError, NotificationsPriority,
+		sync::{GapSyncProgress, SyncState}, PeerDetails, PeerInfo, Protocol,
+	},
+	traffic_dump::TrafficDump,
+	transport, Observation, PeerScorer, ReputationChange,
 };
-use futures::prelude::*;
+use bytes::Bytes;
+use futures::{channel::oneshot, prelude::*};
 use libp2p::{PeerId, Multiaddr};
+use libp2p::identity::{Keypair, PublicKey, error::SigningError};
 use libp2p::core::{ConnectedPoint, Executor, connection::{ConnectionError, PendingConnectionError}, either::EitherError};
 use libp2p::kad::record;
 use libp2p::ping::handler::PingFailure;
 use libp2p::swarm::{NetworkBehaviour, SwarmBuilder, SwarmEvent, protocols_handler::NodeHandlerWrapperError};
-use log::{error, info, trace, warn};
-use parking_lot::Mutex;
+use log::{debug, error, info, trace, warn};
+use parking_lot::{Mutex, RwLock};
 use prometheus_endpoint::{
 	register, Counter, CounterVec, Gauge, GaugeVec, HistogramOpts, HistogramVec, Opts, PrometheusError, Registry, U64,
 };
@@ -72,6 +82,7 @@ use std::{
 		Arc,
 	},
 	task::Poll,
+	time::Duration,
 };
 
 mod out_events;
@@ -88,11 +99,17 @@ pub struct NetworkService<B: BlockT + 'static, H: ExHashT> {
 	is_major_syncing: Arc<AtomicBool>,
 	/// Local copy of the `PeerId` of the local node.
 	local_peer_id: PeerId,
+	/// Network key of the local node.
+	local_identity: Keypair,
 	/// Bandwidth logging system. Can be queried to know the average bandwidth consumed.
 	bandwidth: Arc<transport::BandwidthSinks>,
 	/// Peerset manager (PSM); manages the reputation of nodes and indicates the network which
 	/// nodes it should be connected to or not.
 	peerset: PeersetHandle,
+	/// Filter applied by the transport to incoming connections.
+	ip_filter: Arc<RwLock<IpFilter>>,
+	/// Recording of the messages exchanged with other nodes.
+	traffic_dump: TrafficDump,
 	/// Channel that sends messages to the actual worker.
 	to_worker: TracingUnboundedSender<ServiceToWorkerMsg<B, H>>,
 	/// Marker to pin the `H` generic. Serves no purpose except to not break backwards
@@ -160,7 +177,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 						known_addresses.push((validator.peer_id.clone(), validator.multiaddr.clone()));
 					}
 				}
-				Role::Authority { sentry_nodes } => {
+				Role::Authority { sentry_nodes, .. } => {
 					for sentry_node in sentry_nodes {
 						sentries_and_validators.insert(sentry_node.peer_id.clone());
 						known_addresses.push((sentry_node.peer_id.clone(), sentry_node.multiaddr.clone()));
@@ -175,12 +192,30 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			]
 		};
 
+		// A private authority only connects to its sentry nodes, and the sentry nodes keep the
+		// authorities they guard out of the DHT.
+		let allowed_peers = match &params.role {
+			Role::Authority { sentry_nodes, private: true } => {
+				if sentry_nodes.is_empty() {
+					warn!(target: "sub-libp2p", "Private authority without sentry nodes, no node can be reached");
+				}
+				Some(sentry_nodes.iter().map(|sentry_node| sentry_node.peer_id.clone()).collect::<HashSet<_>>())
+			},
+			_ => None,
+		};
+		let hidden_peers = match &params.role {
+			Role::Sentry { validators } =>
+				validators.iter().map(|validator| validator.peer_id.clone()).collect::<HashSet<_>>(),
+			_ => HashSet::new(),
+		};
+
 		let peerset_config = sc_peerset::PeersetConfig {
 			in_peers: params.network_config.in_peers,
 			out_peers: params.network_config.out_peers,
 			bootnodes,
 			reserved_only: params.network_config.non_reserved_mode == NonReservedPeerMode::Deny,
 			priority_groups,
+			allowed_peers: allowed_peers.clone(),
 		};
 
 		// Private and public keys configuration.
@@ -201,6 +236,13 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			None => None
 		};
 
+		let ip_filter = Arc::new(RwLock::new(params.network_config.ip_filter.clone()));
+
+		let traffic_dump = TrafficDump::default();
+		if let Some(config) = params.network_config.traffic_dump.clone() {
+			traffic_dump.start(config)?;
+		}
+
 		let checker = params.on_demand.as_ref()
 			.map(|od| od.checker().clone())
 			.unwrap_or_else(|| Arc::new(AlwaysBadChecker));
@@ -211,19 +253,31 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			protocol::ProtocolConfig {
 				roles: From::from(&params.role),
 				max_parallel_downloads: params.network_config.max_parallel_downloads,
+				fast_sync: params.network_config.sync_mode == SyncMode::Fast,
+				trusted_sync_peers: params.network_config.trusted_sync_peers.clone(),
+				notifications_keep_alive: params.network_config.notifications_keep_alive,
+				notifications_stall_timeout: params.network_config.notifications_stall_timeout,
+				transaction_propagation: params.network_config.transaction_propagation.clone(),
 			},
 			local_peer_id.clone(),
 			params.chain.clone(),
 			params.transaction_pool,
 			params.finality_proof_provider.clone(),
 			params.finality_proof_request_builder,
+			match params.network_config.sync_mode {
+				SyncMode::Warp => params.warp_sync_provider.clone(),
+				SyncMode::Full | SyncMode::Fast => None,
+			},
 			params.protocol_id.clone(),
 			peerset_config,
 			params.block_announce_validator,
+			params.transaction_propagation_policy,
 			params.metrics_registry.as_ref(),
 			boot_node_ids.clone(),
 			params.network_config.use_new_block_requests_protocol,
 			metrics.as_ref().map(|m| m.notifications_queues_size.clone()),
+			metrics.as_ref().map(|m| m.notifications_open_duration.clone()),
+			traffic_dump.clone(),
 		)?;
 
 		// Build the swarm.
@@ -235,24 +289,62 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			);
 			let block_requests = {
 				let config = block_requests::Config::new(&params.protocol_id);
-				block_requests::BlockRequests::new(config, params.chain.clone())
+				block_requests::BlockRequests::new(config, params.chain.clone(), traffic_dump.clone())
 			};
 			let finality_proof_requests = {
 				let config = finality_requests::Config::new(&params.protocol_id);
-				finality_requests::FinalityProofRequests::new(config, params.finality_proof_provider.clone())
+				finality_requests::FinalityProofRequests::new(
+					config,
+					params.finality_proof_provider.clone(),
+					traffic_dump.clone(),
+				)
+			};
+			let warp_proof_requests = {
+				let config = warp_requests::Config::new(&params.protocol_id);
+				warp_requests::WarpProofRequests::new(
+					config,
+					params.warp_sync_provider.clone(),
+					traffic_dump.clone(),
+				)
+			};
+			let state_requests = {
+				let config = state_requests::Config::new(&params.protocol_id);
+				state_requests::StateRequests::new(config, params.chain.clone(), traffic_dump.clone())
 			};
 			let light_client_handler = {
-				let config = light_client_handler::Config::new(&params.protocol_id);
+				let limits = &params.network_config.light_serve_limits;
+				let mut config = light_client_handler::Config::new(&params.protocol_id);
+				config
+					.set_max_requests_per_sec(limits.max_requests_per_sec)
+					.set_max_proof_bytes_per_sec(limits.max_proof_bytes_per_sec)
+					.set_max_concurrent_requests(limits.max_concurrent_requests);
 				light_client_handler::LightClientHandler::new(
 					config,
 					params.chain,
 					checker,
 					peerset_handle.clone(),
+					traffic_dump.clone(),
+					metrics.as_ref().map(|m| light_client_handler::ServeMetrics {
+						served: m.light_requests_served_total.clone(),
+						denied: m.light_requests_denied_total.clone(),
+					}),
 				)
 			};
+			let bitswap = match (&params.indexed_data, params.network_config.ipfs_server) {
+				(Some(indexed_data), true) => Some(Bitswap::new(indexed_data.clone(), traffic_dump.clone())),
+				(None, true) => {
+					warn!(target: "sub-libp2p", "IPFS server enabled, but no indexed data to serve");
+					None
+				},
+				(_, false) => None,
+			};
 
 			let discovery_config = {
 				let mut config = DiscoveryConfig::new(local_public.clone());
+				if let Some(allowed_peers) = allowed_peers.clone() {
+					config.with_allowed_peers(allowed_peers);
+				}
+				config.with_hidden_peers(hidden_peers);
 				config.with_user_defined(known_addresses);
 				config.discovery_limit(u64::from(params.network_config.out_peers) + 15);
 				config.add_protocol(params.protocol_id.clone());
@@ -264,7 +356,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 						config.allow_private_ipv4(false);
 					}
 					TransportConfig::Normal { enable_mdns, allow_private_ipv4, .. } => {
-						config.with_mdns(enable_mdns);
+						config.with_mdns(enable_mdns && allowed_peers.is_none());
 						config.allow_private_ipv4(allow_private_ipv4);
 					}
 				}
@@ -279,12 +371,20 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 				local_public,
 				block_requests,
 				finality_proof_requests,
+				warp_proof_requests,
+				state_requests,
 				light_client_handler,
+				bitswap,
 				discovery_config
 			);
 
 			for (engine_id, protocol_name) in &params.network_config.notifications_protocols {
-				behaviour.register_notifications_protocol(*engine_id, protocol_name.clone());
+				behaviour.register_notifications_protocol(
+					*engine_id,
+					protocol_name.clone(),
+					NotificationsPriority::High,
+					params.network_config.acknowledged_notifications_protocols.contains(engine_id),
+				);
 			}
 			let (transport, bandwidth) = {
 				let (config_mem, config_wasm, flowctrl) = match params.network_config.transport {
@@ -292,7 +392,16 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 					TransportConfig::Normal { wasm_external_transport, use_yamux_flow_control, .. } =>
 						(false, wasm_external_transport, use_yamux_flow_control)
 				};
-				transport::build_transport(local_identity, config_mem, config_wasm, flowctrl)
+				transport::build_transport(
+					local_identity.clone(),
+					config_mem,
+					config_wasm,
+					flowctrl,
+					ip_filter.clone(),
+					Arc::new(ConnectionTracker::new(params.network_config.connection_limits.clone())),
+					params.network_config.secure_transport_only,
+					metrics.as_ref().map(|m| m.insecure_connections_rejected_total.clone()),
+				)
 			};
 			let mut builder = SwarmBuilder::new(transport, behaviour, local_peer_id.clone())
 				.peer_connection_limit(crate::MAX_CONNECTIONS_PER_PEER)
@@ -330,7 +439,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			num_connected: num_connected.clone(),
 			is_major_syncing: is_major_syncing.clone(),
 			peerset: peerset_handle,
+			ip_filter,
+			traffic_dump,
 			local_peer_id,
+			local_identity,
 			to_worker,
 			_marker: PhantomData,
 		});
@@ -347,6 +459,7 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			event_streams: out_events::OutChannels::new(params.metrics_registry.as_ref())?,
 			metrics,
 			boot_node_ids,
+			allowed_peers,
 		})
 	}
 
@@ -390,6 +503,11 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 		self.network_service.user_protocol().num_queued_blocks()
 	}
 
+	/// Progress of the download of the bodies of old blocks, if any.
+	pub fn gap_sync_progress(&self) -> Option<GapSyncProgress<NumberFor<B>>> {
+		self.network_service.user_protocol().gap_sync_progress()
+	}
+
 	/// Returns the number of processed blocks.
 	pub fn num_processed_blocks(&self) -> usize {
 		self.network_service.user_protocol().num_processed_blocks()
@@ -504,6 +622,15 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkWorker<B, H> {
 			.collect()
 	}
 
+	/// Get detailed information about the currently connected peers, including their sync
+	/// status and statistics about the requests sent to them.
+	pub fn peers_details(&mut self) -> Vec<(PeerId, PeerDetails<B>)> {
+		self.network_service.user_protocol_mut()
+			.peers_details()
+			.map(|(id, details)| (id.clone(), details))
+			.collect()
+	}
+
 	/// Removes a `PeerId` from the list of reserved peers.
 	pub fn remove_reserved_peer(&self, peer: PeerId) {
 		self.service.remove_reserved_peer(peer);
@@ -538,7 +665,48 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 			target,
 			engine_id,
 			message,
+			confirmer: None,
+		});
+	}
+
+	/// Writes a message on an open notifications channel, like `write_notification`, and returns
+	/// a future that resolves once the remote has acknowledged receiving it.
+	///
+	/// The protocol must have been registered in acknowledged mode, by listing it in
+	/// `NetworkConfiguration::acknowledged_notifications_protocols`. The future resolves to an
+	/// error if the message is lost, for example because the channel is closed, or if the
+	/// protocol isn't in acknowledged mode. Empty messages are never acknowledged.
+	pub fn write_notification_acknowledged(
+		&self,
+		target: PeerId,
+		engine_id: ConsensusEngineId,
+		message: Vec<u8>,
+	) -> impl Future<Output = Result<(), ()>> {
+		let (confirmer, delivered) = DeliveryConfirmer::new();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::WriteNotification {
+			target,
+			engine_id,
+			message,
+			confirmer: Some(confirmer),
 		});
+		delivered.map_err(|_| ())
+	}
+
+	/// Returns a [`NotificationSenderGroup`] sending notifications of the protocol registered
+	/// under `engine_id` to all of the given `peers` at once.
+	///
+	/// Each notification sent through the group is only serialized once, and is then shared
+	/// between the queues of all the peers instead of being copied for each of them.
+	pub fn notification_sender_group(
+		&self,
+		peers: impl IntoIterator<Item = PeerId>,
+		engine_id: ConsensusEngineId,
+	) -> NotificationSenderGroup<B, H> {
+		NotificationSenderGroup {
+			peers: peers.into_iter().collect(),
+			engine_id,
+			to_worker: self.to_worker.clone(),
+		}
 	}
 
 	/// Returns a stream containing the events that happen on the network.
@@ -557,6 +725,22 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		rx
 	}
 
+	/// Returns a stream containing the results of the DHT queries started with
+	/// [`NetworkService::get_dht_value`] and [`NetworkService::put_dht_value`].
+	///
+	/// The DHT is shared by everyone using the network, so the stream also contains the results
+	/// of queries started by other users of the service. These can be told apart by their key.
+	///
+	/// See [`NetworkService::event_stream`] for the meaning of `name`.
+	pub fn dht_event_stream(&self, name: &'static str) -> impl Stream<Item = DhtEvent> {
+		self.event_stream(name).filter_map(|event| async move {
+			match event {
+				Event::Dht(event) => Some(event),
+				_ => None,
+			}
+		})
+	}
+
 	/// Registers a new notifications protocol.
 	///
 	/// After a protocol has been registered, you can call `write_notifications`.
@@ -569,6 +753,9 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 	///
 	/// Please call `event_stream` before registering a protocol, otherwise you may miss events
 	/// about the protocol that you have registered.
+	///
+	/// Like the ones of `NetworkConfiguration::notifications_protocols`, the protocol is given
+	/// a [`NotificationsPriority::High`] priority, as it is expected to carry consensus messages.
 	// TODO: remove this method after https://github.com/paritytech/substrate/issues/4587
 	pub fn register_notifications_protocol(
 		&self,
@@ -597,6 +784,17 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::PropagateExtrinsic(hash));
 	}
 
+	/// Modifies the handshake sent to remotes when opening a substream with the notifications
+	/// protocol registered under `engine_id`, for example to advertise a new best block.
+	///
+	/// Only the substreams opened afterwards use the new handshake. Remotes receive it through
+	/// an [`Event::NotificationHandshakeReceived`].
+	pub fn set_notifications_handshake(&self, engine_id: ConsensusEngineId, handshake: Vec<u8>) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::SetNotificationsHandshake { engine_id, handshake });
+	}
+
 	/// Make sure an important block is propagated to peers.
 	///
 	/// In chain-based consensus, we often need to make sure non-best forks are
@@ -611,6 +809,24 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		self.peerset.report_peer(who, cost_benefit);
 	}
 
+	/// Report an observation about the behaviour of a peer. The scorers registered with
+	/// [`NetworkService::add_peer_scorer`] decide how it affects the reputation of the peer.
+	pub fn observe_peer(&self, who: PeerId, observation: Observation) {
+		self.peerset.observe(who, observation);
+	}
+
+	/// Registers a scorer turning the observations reported with [`NetworkService::observe_peer`]
+	/// into reputation changes. Replaces the scorer previously registered under the same name,
+	/// if any.
+	pub fn add_peer_scorer(&self, name: &'static str, scorer: Box<dyn PeerScorer>) {
+		self.peerset.add_scorer(name, scorer);
+	}
+
+	/// Removes the scorer registered under the given name.
+	pub fn remove_peer_scorer(&self, name: &'static str) {
+		self.peerset.remove_scorer(name);
+	}
+
 	/// Disconnect from a node as soon as possible.
 	///
 	/// This triggers the same effects as if the connection had closed itself spontaneously.
@@ -618,6 +834,16 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::DisconnectPeer(who));
 	}
 
+	/// Returns detailed information about all the peers we are connected to, including their
+	/// sync status and the notifications substreams open with them.
+	///
+	/// Returns an error if the `NetworkWorker` has been shut down.
+	pub async fn peers_info(&self) -> Result<Vec<(PeerId, PeerDetails<B>)>, ()> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::PeersInfo(tx));
+		rx.await.map_err(|_| ())
+	}
+
 	/// Request a justification for the given block from the network.
 	///
 	/// On success, the justification will be passed to the import queue that was part at
@@ -635,9 +861,10 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 
 	/// Start getting a value from the DHT.
 	///
-	/// This will generate either a `ValueFound` or a `ValueNotFound` event and pass it as an
-	/// item on the [`NetworkWorker`] stream.
-	pub fn get_value(&self, key: &record::Key) {
+	/// This will generate either a `ValueFound` or a `ValueNotFound` event, which is delivered on
+	/// the streams returned by [`NetworkService::dht_event_stream`] and
+	/// [`NetworkService::event_stream`].
+	pub fn get_dht_value(&self, key: &record::Key) {
 		let _ = self
 			.to_worker
 			.unbounded_send(ServiceToWorkerMsg::GetValue(key.clone()));
@@ -645,12 +872,30 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 
 	/// Start putting a value in the DHT.
 	///
-	/// This will generate either a `ValuePut` or a `ValuePutFailed` event and pass it as an
-	/// item on the [`NetworkWorker`] stream.
-	pub fn put_value(&self, key: record::Key, value: Vec<u8>) {
+	/// This will generate either a `ValuePut` or a `ValuePutFailed` event, which is delivered on
+	/// the streams returned by [`NetworkService::dht_event_stream`] and
+	/// [`NetworkService::event_stream`].
+	pub fn put_dht_value(&self, key: record::Key, value: Vec<u8>) {
 		let _ = self
 			.to_worker
-			.unbounded_send(ServiceToWorkerMsg::PutValue(key, value));
+			.unbounded_send(ServiceToWorkerMsg::PutValue(key, value, None));
+	}
+
+	/// Start putting a value in the DHT, expiring after `ttl` rather than after the default
+	/// time-to-live of the DHT records.
+	///
+	/// Generates the same events as [`NetworkService::put_dht_value`].
+	pub fn put_dht_value_with_ttl(&self, key: record::Key, value: Vec<u8>, ttl: Duration) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::PutValue(key, value, Some(ttl)));
+	}
+
+	/// Signs `message` with the network key of the local node, the one its `PeerId` is derived
+	/// from.
+	pub fn sign_with_local_identity(&self, message: &[u8]) -> Result<Signature, SigningError> {
+		let bytes = self.local_identity.sign(message)?;
+		Ok(Signature { public_key: self.local_identity.public(), bytes })
 	}
 
 	/// Connect to unreserved peers and allow unreserved peers to connect.
@@ -679,6 +924,39 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		Ok(())
 	}
 
+	/// Returns the filter currently applied to incoming connections.
+	pub fn ip_filter(&self) -> IpFilter {
+		self.ip_filter.read().clone()
+	}
+
+	/// Replaces the filter applied to incoming connections.
+	///
+	/// The new filter only applies to connections opened after this call. Peers that are
+	/// already connected are not affected.
+	pub fn set_ip_filter(&self, filter: IpFilter) {
+		*self.ip_filter.write() = filter;
+	}
+
+	/// Returns the configuration of the traffic dump currently running, if any.
+	pub fn traffic_dump(&self) -> Option<TrafficDumpConfig> {
+		self.traffic_dump.config()
+	}
+
+	/// Starts recording the messages exchanged with other nodes according to `config`, or stops
+	/// if `None` is passed.
+	///
+	/// Replaces the dump currently running, if any. Returns an error if the dump file can't be
+	/// opened, in which case the current dump keeps running.
+	pub fn set_traffic_dump(&self, config: Option<TrafficDumpConfig>) -> io::Result<()> {
+		match config {
+			Some(config) => self.traffic_dump.start(config),
+			None => {
+				self.traffic_dump.stop();
+				Ok(())
+			},
+		}
+	}
+
 	/// Configure an explicit fork sync request.
 	/// Note that this function should not be used for recent blocks.
 	/// Sync should be able to download all the recent forks normally.
@@ -691,6 +969,16 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 			.unbounded_send(ServiceToWorkerMsg::SyncFork(peers, hash, number));
 	}
 
+	/// Only download blocks, justifications, finality proofs and state from the given peers, or
+	/// from any peer if `None` is passed. Block announces are still accepted from every peer.
+	///
+	/// Useful to bootstrap a private network, or to recover from an eclipse attack.
+	pub fn set_trusted_sync_peers(&self, peers: Option<HashSet<PeerId>>) {
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::SetTrustedSyncPeers(peers));
+	}
+
 	/// Modify a peerset priority group.
 	pub fn set_priority_group(&self, group_id: String, peers: HashSet<Multiaddr>) -> Result<(), String> {
 		let peers = peers.into_iter().map(|p| {
@@ -755,6 +1043,71 @@ impl<B, H> NetworkStateInfo for NetworkService<B, H>
 	}
 }
 
+/// Signature made with the network key of a node.
+#[derive(Debug, Clone)]
+pub struct Signature {
+	/// Public key of the node, from which its `PeerId` is derived.
+	pub public_key: PublicKey,
+	/// Signature made with the matching private key.
+	pub bytes: Vec<u8>,
+}
+
+impl Signature {
+	/// Returns true if this is a valid signature of `message`.
+	pub fn verify(&self, message: &[u8]) -> bool {
+		self.public_key.verify(message, &self.bytes)
+	}
+}
+
+/// Sends the same notifications to a group of peers. See
+/// [`NetworkService::notification_sender_group`].
+pub struct NotificationSenderGroup<B: BlockT + 'static, H: ExHashT> {
+	/// Peers to send the notifications to.
+	peers: Vec<PeerId>,
+	/// Protocol of the notifications.
+	engine_id: ConsensusEngineId,
+	/// Channel for sending requests to worker.
+	to_worker: TracingUnboundedSender<ServiceToWorkerMsg<B, H>>,
+}
+
+impl<B: BlockT + 'static, H: ExHashT> NotificationSenderGroup<B, H> {
+	/// Returns the peers the notifications are sent to.
+	pub fn peers(&self) -> &[PeerId] {
+		&self.peers
+	}
+
+	/// Sends `message` to all the peers of the group. The returned future resolves to the result
+	/// for each peer once the message has been queued for all of them, or to an error if the
+	/// network worker has shut down.
+	///
+	/// As with `NetworkService::write_notification`, a message successfully queued can still be
+	/// lost if the connection with the peer is closed or congested.
+	pub fn send(
+		&self,
+		message: impl Into<Bytes>,
+	) -> impl Future<Output = Result<Vec<(PeerId, Result<(), NotificationSendError>)>, ()>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::WriteNotificationGroup {
+			message: message.into(),
+			engine_id: self.engine_id,
+			targets: self.peers.clone(),
+			results: tx,
+		});
+		rx.map_err(|_| ())
+	}
+}
+
+/// Error sending a notification to a peer of a [`NotificationSenderGroup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum NotificationSendError {
+	/// No notifications channel is open with the peer.
+	#[display(fmt = "No notifications channel open with the peer")]
+	ChannelClosed,
+	/// The protocol of the notification isn't registered.
+	#[display(fmt = "Notifications protocol not registered")]
+	UnknownProtocol,
+}
+
 /// Messages sent from the `NetworkService` to the `NetworkWorker`.
 ///
 /// Each entry corresponds to a method of `NetworkService`.
@@ -764,20 +1117,33 @@ enum ServiceToWorkerMsg<B: BlockT, H: ExHashT> {
 	RequestJustification(B::Hash, NumberFor<B>),
 	AnnounceBlock(B::Hash, Vec<u8>),
 	GetValue(record::Key),
-	PutValue(record::Key, Vec<u8>),
+	PutValue(record::Key, Vec<u8>, Option<Duration>),
 	AddKnownAddress(PeerId, Multiaddr),
 	SyncFork(Vec<PeerId>, B::Hash, NumberFor<B>),
+	SetTrustedSyncPeers(Option<HashSet<PeerId>>),
 	EventStream(out_events::Sender),
 	WriteNotification {
 		message: Vec<u8>,
 		engine_id: ConsensusEngineId,
 		target: PeerId,
+		confirmer: Option<DeliveryConfirmer>,
+	},
+	WriteNotificationGroup {
+		message: Bytes,
+		engine_id: ConsensusEngineId,
+		targets: Vec<PeerId>,
+		results: oneshot::Sender<Vec<(PeerId, Result<(), NotificationSendError>)>>,
 	},
 	RegisterNotifProtocol {
 		engine_id: ConsensusEngineId,
 		protocol_name: Cow<'static, [u8]>,
 	},
+	SetNotificationsHandshake {
+		engine_id: ConsensusEngineId,
+		handshake: Vec<u8>,
+	},
 	DisconnectPeer(PeerId),
+	PeersInfo(oneshot::Sender<Vec<(PeerId, PeerDetails<B>)>>),
 }
 
 /// Main network worker. Must be polled in order for the network to advance.
@@ -807,6 +1173,8 @@ pub struct NetworkWorker<B: BlockT + 'static, H: ExHashT> {
 	metrics: Option<Metrics>,
 	/// The `PeerId`'s of all boot nodes.
 	boot_node_ids: Arc<HashSet<PeerId>>,
+	/// If `Some`, the only nodes the connections are kept open with.
+	allowed_peers: Option<HashSet<PeerId>>,
 }
 
 struct Metrics {
@@ -818,15 +1186,19 @@ struct Metrics {
 	import_queue_justifications_submitted: Counter<U64>,
 	incoming_connections_errors_total: CounterVec<U64>,
 	incoming_connections_total: Counter<U64>,
+	insecure_connections_rejected_total: CounterVec<U64>,
 	is_major_syncing: Gauge<U64>,
 	issued_light_requests: Counter<U64>,
 	kademlia_random_queries_total: CounterVec<U64>,
 	kademlia_records_count: GaugeVec<U64>,
 	kademlia_records_sizes_total: GaugeVec<U64>,
 	kbuckets_num_nodes: GaugeVec<U64>,
+	light_requests_denied_total: CounterVec<U64>,
+	light_requests_served_total: Counter<U64>,
 	listeners_local_addresses: Gauge<U64>,
 	listeners_errors_total: Counter<U64>,
 	network_per_sec_bytes: GaugeVec<U64>,
+	notifications_open_duration: HistogramVec,
 	notifications_queues_size: HistogramVec,
 	notifications_sizes: HistogramVec,
 	notifications_streams_closed_total: CounterVec<U64>,
@@ -883,6 +1255,14 @@ impl Metrics {
 				"sub_libp2p_incoming_connections_total",
 				"Total number of incoming connections on the listening sockets"
 			)?, registry)?,
+			insecure_connections_rejected_total: register(CounterVec::new(
+				Opts::new(
+					"sub_libp2p_insecure_connections_rejected_total",
+					"Total number of connections refused because they failed to negotiate a secure \
+					encryption protocol, when only secure transports are allowed"
+				),
+				&["reason"]
+			)?, registry)?,
 			is_major_syncing: register(Gauge::new(
 				"sub_libp2p_is_major_syncing", "Whether the node is performing a major sync or not.",
 			)?, registry)?,
@@ -918,6 +1298,18 @@ impl Metrics {
 				),
 				&["protocol"]
 			)?, registry)?,
+			light_requests_denied_total: register(CounterVec::new(
+				Opts::new(
+					"sub_libp2p_light_requests_denied_total",
+					"Total number of requests from light clients refused for exceeding the limits, \
+					by reason"
+				),
+				&["reason"]
+			)?, registry)?,
+			light_requests_served_total: register(Counter::new(
+				"sub_libp2p_light_requests_served_total",
+				"Total number of requests from light clients answered"
+			)?, registry)?,
 			listeners_local_addresses: register(Gauge::new(
 				"sub_libp2p_listeners_local_addresses", "Number of local addresses we're listening on"
 			)?, registry)?,
@@ -932,6 +1324,16 @@ impl Metrics {
 				),
 				&["direction"]
 			)?, registry)?,
+			notifications_open_duration: register(HistogramVec::new(
+				HistogramOpts {
+					common_opts: Opts::new(
+						"sub_libp2p_notifications_open_duration_seconds",
+						"Time it takes to open a notifications substream with a remote"
+					),
+					buckets: vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0],
+				},
+				&["protocol"]
+			)?, registry)?,
 			notifications_queues_size: register(HistogramVec::new(
 				HistogramOpts {
 					common_opts: Opts::new(
@@ -1084,28 +1486,61 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 					this.network_service.user_protocol_mut().propagate_extrinsics(),
 				ServiceToWorkerMsg::GetValue(key) =>
 					this.network_service.get_value(&key),
-				ServiceToWorkerMsg::PutValue(key, value) =>
-					this.network_service.put_value(key, value),
+				ServiceToWorkerMsg::PutValue(key, value, ttl) =>
+					this.network_service.put_value(key, value, ttl),
 				ServiceToWorkerMsg::AddKnownAddress(peer_id, addr) =>
 					this.network_service.add_known_address(peer_id, addr),
 				ServiceToWorkerMsg::SyncFork(peer_ids, hash, number) =>
 					this.network_service.user_protocol_mut().set_sync_fork_request(peer_ids, &hash, number),
+				ServiceToWorkerMsg::SetTrustedSyncPeers(peers) =>
+					this.network_service.user_protocol_mut().set_trusted_sync_peers(peers),
 				ServiceToWorkerMsg::EventStream(sender) =>
 					this.event_streams.push(sender),
-				ServiceToWorkerMsg::WriteNotification { message, engine_id, target } => {
+				ServiceToWorkerMsg::WriteNotification { message, engine_id, target, confirmer } => {
 					if let Some(metrics) = this.metrics.as_ref() {
 						metrics.notifications_sizes
 							.with_label_values(&["out", &maybe_utf8_bytes_to_string(&engine_id)])
 							.observe(message.len() as f64);
 					}
-					this.network_service.user_protocol_mut().write_notification(target, engine_id, message)
+					this.network_service.user_protocol_mut().write_notification(target, engine_id, message, confirmer)
+				},
+				ServiceToWorkerMsg::WriteNotificationGroup { message, engine_id, targets, results } => {
+					if let Some(metrics) = this.metrics.as_ref() {
+						let histogram = metrics.notifications_sizes
+							.with_label_values(&["out", &maybe_utf8_bytes_to_string(&engine_id)]);
+						for _ in &targets {
+							histogram.observe(message.len() as f64);
+						}
+					}
+					let sent = this.network_service.user_protocol_mut()
+						.write_notification_group(&targets, engine_id, message);
+					let outcome = match sent {
+						Some(sent) => targets.into_iter().zip(sent)
+							.map(|(target, sent)| {
+								(target, if sent { Ok(()) } else { Err(NotificationSendError::ChannelClosed) })
+							})
+							.collect(),
+						None => targets.into_iter()
+							.map(|target| (target, Err(NotificationSendError::UnknownProtocol)))
+							.collect(),
+					};
+					let _ = results.send(outcome);
 				},
 				ServiceToWorkerMsg::RegisterNotifProtocol { engine_id, protocol_name } => {
 					this.network_service
-						.register_notifications_protocol(engine_id, protocol_name);
+						.register_notifications_protocol(engine_id, protocol_name, NotificationsPriority::High, false);
 				},
+				ServiceToWorkerMsg::SetNotificationsHandshake { engine_id, handshake } =>
+					this.network_service.user_protocol_mut().set_notifications_handshake(engine_id, handshake),
 				ServiceToWorkerMsg::DisconnectPeer(who) =>
 					this.network_service.user_protocol_mut().disconnect_peer(&who),
+				ServiceToWorkerMsg::PeersInfo(sender) => {
+					let peers = this.network_service.user_protocol_mut()
+						.peers_details()
+						.map(|(id, details)| (id.clone(), details))
+						.collect();
+					let _ = sender.send(peers);
+				},
 			}
 		}
 
@@ -1171,6 +1606,10 @@ impl<B: BlockT + 'static, H: ExHashT> Future for NetworkWorker<B, H> {
 				},
 				Poll::Ready(SwarmEvent::ConnectionEstablished { peer_id, endpoint, .. }) => {
 					trace!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
+					if this.allowed_peers.as_ref().map_or(false, |allowed| !allowed.contains(&peer_id)) {
+						debug!(target: "sub-libp2p", "Libp2p => Banning {:?}, which isn't allowed", peer_id);
+						Swarm::<B, H>::ban_peer_id(&mut this.network_service, peer_id.clone());
+					}
 					if let Some(metrics) = this.metrics.as_ref() {
 						match endpoint {
 							ConnectedPoint::Dialer { .. } =>
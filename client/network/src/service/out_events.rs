@@ -236,6 +236,11 @@ impl Metrics {
 					.with_label_values(&[&format!("notif-closed-{:?}", engine_id), "sent", name])
 					.inc_by(num);
 			},
+			Event::NotificationHandshakeReceived { engine_id, .. } => {
+				self.events_total
+					.with_label_values(&[&format!("notif-handshake-{:?}", engine_id), "sent", name])
+					.inc_by(num);
+			},
 			Event::NotificationsReceived { messages, .. } => {
 				for (engine_id, message) in messages {
 					self.events_total
@@ -266,6 +271,11 @@ impl Metrics {
 					.with_label_values(&[&format!("notif-closed-{:?}", engine_id), "received", name])
 					.inc();
 			},
+			Event::NotificationHandshakeReceived { engine_id, .. } => {
+				self.events_total
+					.with_label_values(&[&format!("notif-handshake-{:?}", engine_id), "received", name])
+					.inc();
+			},
 			Event::NotificationsReceived { messages, .. } => {
 				for (engine_id, message) in messages {
 					self.events_total
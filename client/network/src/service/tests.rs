@@ -16,7 +16,10 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{config, Event, NetworkService, NetworkWorker};
+use crate::{config, DhtEvent, Event, KademliaKey, NetworkService, NetworkWorker, NotificationSendError, PeerId};
+use crate::protocol::message::Roles;
+
+use codec::Encode;
 
 use futures::prelude::*;
 use sp_runtime::traits::{Block as BlockT, Header as _};
@@ -98,6 +101,8 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		chain: client.clone(),
 		finality_proof_provider: None,
 		finality_proof_request_builder: None,
+		warp_sync_provider: None,
+		indexed_data: None,
 		on_demand: None,
 		transaction_pool: Arc::new(crate::config::EmptyTransactionPool),
 		protocol_id: config::ProtocolId::from(&b"/test-protocol-name"[..]),
@@ -105,6 +110,7 @@ fn build_test_full_node(config: config::NetworkConfiguration)
 		block_announce_validator: Box::new(
 			sp_consensus::block_validation::DefaultBlockAnnounceValidator::new(client.clone()),
 		),
+		transaction_propagation_policy: Box::new(config::PropagateToAll),
 		metrics_registry: None,
 	})
 	.unwrap();
@@ -266,9 +272,135 @@ fn notifications_state_consistent() {
 				}
 
 				// Add new events here.
+				future::Either::Left(Event::NotificationHandshakeReceived { .. }) => {}
+				future::Either::Right(Event::NotificationHandshakeReceived { .. }) => {}
 				future::Either::Left(Event::Dht(_)) => {}
 				future::Either::Right(Event::Dht(_)) => {}
 			};
 		}
 	});
 }
+
+#[test]
+fn notifications_handshake_is_reported() {
+	let (_node1, mut events_stream1, node2, _) = build_nodes_one_proto();
+
+	async_std::task::block_on(async move {
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationHandshakeReceived { remote, engine_id, handshake } => {
+					assert_eq!(remote, *node2.local_peer_id());
+					assert_eq!(engine_id, ENGINE_ID);
+					// The default handshake is the roles of the remote.
+					assert_eq!(&handshake[..], &Roles::FULL.encode()[..]);
+					break
+				}
+				_ => {}
+			}
+		}
+	});
+}
+
+#[test]
+fn notification_sender_group_reports_closed_channels() {
+	let (node1, mut events_stream1, node2, mut events_stream2) = build_nodes_one_proto();
+	let unknown = PeerId::random();
+
+	async_std::task::block_on(async move {
+		// Wait for the channel from node1 to node2 to be open.
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamOpened { .. } => break,
+				_ => {}
+			}
+		}
+
+		let group = node1.notification_sender_group(
+			vec![node2.local_peer_id().clone(), unknown.clone()],
+			ENGINE_ID,
+		);
+		let results = group.send(&b"hello world"[..]).await.unwrap();
+		assert_eq!(results, vec![
+			(node2.local_peer_id().clone(), Ok(())),
+			(unknown, Err(NotificationSendError::ChannelClosed)),
+		]);
+
+		loop {
+			match events_stream2.next().await.unwrap() {
+				Event::NotificationsReceived { messages, .. } => {
+					assert_eq!(messages, vec![(ENGINE_ID, From::from(&b"hello world"[..]))]);
+					break
+				}
+				_ => {}
+			}
+		}
+	});
+}
+
+#[test]
+fn dht_values_can_be_put_and_retrieved() {
+	let (node1, mut events_stream1, node2, _) = build_nodes_one_proto();
+	let mut dht_events1 = Box::pin(node1.dht_event_stream("test-dht"));
+	let mut dht_events2 = Box::pin(node2.dht_event_stream("test-dht"));
+
+	let key = KademliaKey::new(&b"some key"[..]);
+	let value = b"some value".to_vec();
+
+	async_std::task::block_on(async move {
+		// Wait for the two nodes to be connected.
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamOpened { .. } => break,
+				_ => {}
+			}
+		}
+
+		// The record can't be stored on node2 until it is in the routing table of node1.
+		loop {
+			node1.put_dht_value(key.clone(), value.clone());
+			match dht_events1.next().await.unwrap() {
+				DhtEvent::ValuePut(k) => {
+					assert_eq!(k, key);
+					break
+				}
+				DhtEvent::ValuePutFailed(k) => {
+					assert_eq!(k, key);
+					futures_timer::Delay::new(Duration::from_millis(100)).await;
+				}
+				event => panic!("Unexpected event: {:?}", event),
+			}
+		}
+
+		node2.get_dht_value(&key);
+		match dht_events2.next().await.unwrap() {
+			DhtEvent::ValueFound(values) => assert!(values.contains(&(key, value))),
+			event => panic!("Unexpected event: {:?}", event),
+		}
+	});
+}
+
+#[test]
+fn peers_info_reports_connected_peers() {
+	let (node1, mut events_stream1, node2, _) = build_nodes_one_proto();
+
+	async_std::task::block_on(async move {
+		// Wait for the two nodes to be connected.
+		loop {
+			match events_stream1.next().await.unwrap() {
+				Event::NotificationStreamOpened { .. } => break,
+				_ => {}
+			}
+		}
+
+		let peers = node1.peers_info().await.unwrap();
+		assert_eq!(peers.len(), 1);
+		let (peer_id, details) = &peers[0];
+		assert_eq!(peer_id, node2.local_peer_id());
+		assert_eq!(details.info.best_number, 0);
+
+		// Both nodes are at genesis, so there is nothing to request.
+		let sync = details.sync.as_ref().unwrap();
+		assert_eq!(sync.common_number, 0);
+		assert!(sync.request.is_none());
+	});
+}
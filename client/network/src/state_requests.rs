@@ -0,0 +1,507 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! `NetworkBehaviour` implementation which handles state requests.
+//!
+//! The state of a block is downloaded in chunks of consecutive key-value pairs, ordered by key.
+//! Every request is coming in on a separate connection substream which gets closed after we have
+//! sent the response back. Requests and responses are encoded as protocol buffers
+//! (cf. `state.v1.proto`).
+
+#![allow(unused)]
+
+use bytes::Bytes;
+use codec::{Encode, Decode};
+use crate::{
+	chain::Client,
+	config::ProtocolId,
+	protocol::message,
+	schema,
+	traffic_dump::{Direction, TrafficDump},
+};
+use futures::{future::BoxFuture, prelude::*, stream::FuturesUnordered};
+use futures_timer::Delay;
+use libp2p::{
+	core::{
+		ConnectedPoint,
+		Multiaddr,
+		PeerId,
+		connection::ConnectionId,
+		upgrade::{InboundUpgrade, OutboundUpgrade, ReadOneError, UpgradeInfo, Negotiated},
+		upgrade::{DeniedUpgrade, read_one, write_one}
+	},
+	swarm::{
+		NegotiatedSubstream,
+		NetworkBehaviour,
+		NetworkBehaviourAction,
+		NotifyHandler,
+		OneShotHandler,
+		OneShotHandlerConfig,
+		PollParameters,
+		SubstreamProtocol
+	}
+};
+use prost::Message;
+use sp_core::storage::ChildInfo;
+use sp_runtime::{generic::BlockId, traits::{Block, Header, One, Zero}};
+use std::{
+	cmp::min,
+	collections::{HashMap, VecDeque},
+	io,
+	iter,
+	marker::PhantomData,
+	pin::Pin,
+	sync::Arc,
+	time::Duration,
+	task::{Context, Poll}
+};
+use void::{Void, unreachable};
+
+// Type alias for convenience.
+pub type Error = Box<dyn std::error::Error + 'static>;
+
+/// Event generated by the state requests behaviour.
+#[derive(Debug)]
+pub enum Event<B: Block> {
+	/// A response to a state request has arrived.
+	Response {
+		peer: PeerId,
+		/// Block hash originally passed to `send_request`.
+		block_hash: B::Hash,
+		/// Chunk of the state returned by the remote.
+		response: schema::v1::state::StateResponse,
+	},
+
+	/// A state request has failed, because the remote has disconnected or hasn't answered in
+	/// time.
+	RequestFailed {
+		peer: PeerId,
+		/// Block hash originally passed to `send_request`.
+		block_hash: B::Hash,
+	},
+}
+
+/// Configuration options for `StateRequests`.
+#[derive(Debug, Clone)]
+pub struct Config {
+	max_request_len: usize,
+	max_response_len: usize,
+	max_state_response_len: usize,
+	inactivity_timeout: Duration,
+	request_timeout: Duration,
+	protocol: Bytes,
+}
+
+impl Config {
+	/// Create a fresh configuration with the following options:
+	///
+	/// - max. request size = 1 MiB
+	/// - max. response size = 16 MiB
+	/// - max. size of the state entries in a response = 2 MiB
+	/// - inactivity timeout = 15s
+	/// - request timeout = 40s
+	pub fn new(id: &ProtocolId) -> Self {
+		let mut c = Config {
+			max_request_len: 1024 * 1024,
+			max_response_len: 16 * 1024 * 1024,
+			max_state_response_len: 2 * 1024 * 1024,
+			inactivity_timeout: Duration::from_secs(15),
+			request_timeout: Duration::from_secs(40),
+			protocol: Bytes::new(),
+		};
+		c.set_protocol(id);
+		c
+	}
+
+	/// Limit the max. length of incoming state request bytes.
+	pub fn set_max_request_len(&mut self, v: usize) -> &mut Self {
+		self.max_request_len = v;
+		self
+	}
+
+	/// Limit the max. length of incoming state response bytes.
+	pub fn set_max_response_len(&mut self, v: usize) -> &mut Self {
+		self.max_response_len = v;
+		self
+	}
+
+	/// Limit the total size of the keys and values of the state entries we send in a response.
+	pub fn set_max_state_response_len(&mut self, v: usize) -> &mut Self {
+		self.max_state_response_len = v;
+		self
+	}
+
+	/// Limit the max. duration the substream may remain inactive before closing it.
+	pub fn set_inactivity_timeout(&mut self, v: Duration) -> &mut Self {
+		self.inactivity_timeout = v;
+		self
+	}
+
+	/// Limit the max. duration of a request, after which it is reported as failed.
+	pub fn set_request_timeout(&mut self, v: Duration) -> &mut Self {
+		self.request_timeout = v;
+		self
+	}
+
+	/// Set protocol to use for upgrade negotiation.
+	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
+		let mut v = Vec::new();
+		v.extend_from_slice(b"/");
+		v.extend_from_slice(id.as_bytes());
+		v.extend_from_slice(b"/state/1");
+		self.protocol = v.into();
+		self
+	}
+}
+
+/// The state request handling behaviour.
+pub struct StateRequests<B: Block> {
+	/// This behaviour's configuration.
+	config: Config,
+	/// Blockchain client.
+	chain: Arc<dyn Client<B>>,
+	/// Requests we have sent and are waiting for the response of, one per peer at most.
+	ongoing: HashMap<PeerId, OngoingRequest<B>>,
+	/// Futures sending back the state request responses.
+	outgoing: FuturesUnordered<BoxFuture<'static, ()>>,
+	/// Events to return as soon as possible from `poll`.
+	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>,
+	/// Recording of the messages sent and received, if enabled.
+	traffic_dump: TrafficDump,
+}
+
+/// State request waiting for its response.
+struct OngoingRequest<B: Block> {
+	/// Block hash passed to `send_request`.
+	block_hash: B::Hash,
+	timeout: Delay,
+}
+
+impl<B> StateRequests<B>
+where
+	B: Block,
+{
+	/// Initializes the behaviour.
+	pub fn new(cfg: Config, chain: Arc<dyn Client<B>>, traffic_dump: TrafficDump) -> Self {
+		StateRequests {
+			config: cfg,
+			chain,
+			ongoing: HashMap::new(),
+			outgoing: FuturesUnordered::new(),
+			pending_events: VecDeque::new(),
+			traffic_dump,
+		}
+	}
+
+	/// Issue a new state request.
+	///
+	/// `start` is empty to request the beginning of the state of the block, and otherwise
+	/// holds the keys to resume from, as found in the previous response. The state is always
+	/// requested along with a proof, so that each chunk can be checked on its own.
+	///
+	/// Replaces any request in flight to the same target. If the response doesn't arrive in time,
+	/// or if the target disconnects, an [`Event::RequestFailed`] is emitted.
+	pub fn send_request(&mut self, target: &PeerId, block_hash: B::Hash, start: Vec<Vec<u8>>) {
+		let protobuf_rq = schema::v1::state::StateRequest {
+			block: block_hash.encode(),
+			start,
+			no_proof: false,
+		};
+
+		let mut buf = Vec::with_capacity(protobuf_rq.encoded_len());
+		if let Err(err) = protobuf_rq.encode(&mut buf) {
+			log::warn!("failed to encode state request {:?}: {:?}", protobuf_rq, err);
+			return;
+		}
+
+		log::trace!("enqueueing state request to {:?}: {:?}", target, protobuf_rq);
+		self.ongoing.insert(target.clone(), OngoingRequest {
+			block_hash,
+			timeout: Delay::new(self.config.request_timeout),
+		});
+		self.traffic_dump.record(Direction::Out, &self.config.protocol, target, &buf);
+		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
+			peer_id: target.clone(),
+			handler: NotifyHandler::Any,
+			event: OutboundProtocol {
+				request: buf,
+				block_hash,
+				max_response_size: self.config.max_response_len,
+				protocol: self.config.protocol.clone(),
+			},
+		});
+	}
+
+	/// Callback, invoked when a new state request has been received from remote.
+	fn on_state_request(&mut self, peer: &PeerId, request: &schema::v1::state::StateRequest)
+		-> Result<schema::v1::state::StateResponse, Error>
+	{
+		let block_hash: B::Hash = Decode::decode(&mut request.block.as_ref())?;
+
+		log::trace!(target: "sync", "State request from {} for {}", peer, block_hash);
+
+		if !request.no_proof {
+			let proof = self.chain.read_proof_collection(
+				&BlockId::Hash(block_hash),
+				&request.start,
+				self.config.max_state_response_len,
+			)?;
+			return Ok(schema::v1::state::StateResponse {
+				entries: Vec::new(),
+				complete: false,
+				proof: proof.encode(),
+			})
+		}
+
+		let (entries, complete) = self.chain.state_chunk(
+			&BlockId::Hash(block_hash),
+			&request.start,
+			self.config.max_state_response_len,
+		)?;
+
+		// group consecutive entries of the same trie.
+		let mut response = schema::v1::state::StateResponse {
+			entries: Vec::new(),
+			complete,
+			proof: Vec::new(),
+		};
+		for entry in entries {
+			let child = entry.child
+				.map(|storage_key| ChildInfo::new_default(&storage_key).prefixed_storage_key().into_inner())
+				.unwrap_or_default();
+			let pair = schema::v1::state::StateEntry { key: entry.key, value: entry.value };
+			match response.entries.last_mut() {
+				Some(last) if last.child == child => last.entries.push(pair),
+				_ => response.entries.push(schema::v1::state::KeyValueStateEntry {
+					child,
+					entries: vec![pair],
+				}),
+			}
+		}
+
+		Ok(response)
+	}
+}
+
+impl<B> NetworkBehaviour for StateRequests<B>
+where
+	B: Block
+{
+	type ProtocolsHandler = OneShotHandler<InboundProtocol<B>, OutboundProtocol<B>, NodeEvent<B, NegotiatedSubstream>>;
+	type OutEvent = Event<B>;
+
+	fn new_handler(&mut self) -> Self::ProtocolsHandler {
+		let p = InboundProtocol {
+			max_request_len: self.config.max_request_len,
+			protocol: Some(self.config.protocol.clone()),
+			marker: PhantomData,
+		};
+		let mut cfg = OneShotHandlerConfig::default();
+		cfg.inactive_timeout = self.config.inactivity_timeout;
+		cfg.substream_timeout = self.config.request_timeout;
+		OneShotHandler::new(SubstreamProtocol::new(p), cfg)
+	}
+
+	fn addresses_of_peer(&mut self, _: &PeerId) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn inject_connected(&mut self, _peer: &PeerId) {
+	}
+
+	fn inject_disconnected(&mut self, peer: &PeerId) {
+		if let Some(request) = self.ongoing.remove(peer) {
+			log::debug!(target: "sync", "State request to {} cancelled by the disconnection", peer);
+			let ev = Event::RequestFailed { peer: peer.clone(), block_hash: request.block_hash };
+			self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
+		}
+	}
+
+	fn inject_event(
+		&mut self,
+		peer: PeerId,
+		connection: ConnectionId,
+		event: NodeEvent<B, NegotiatedSubstream>
+	) {
+		match event {
+			NodeEvent::Request(request, mut stream) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &request);
+				match self.on_state_request(&peer, &request) {
+					Ok(res) => {
+						log::trace!("enqueueing state response for peer {}", peer);
+						let mut data = Vec::with_capacity(res.encoded_len());
+						if let Err(e) = res.encode(&mut data) {
+							log::debug!("error encoding state response for peer {}: {}", peer, e)
+						} else {
+							self.traffic_dump.record(Direction::Out, &self.config.protocol, &peer, &data);
+							let future = async move {
+								if let Err(e) = write_one(&mut stream, data).await {
+									log::debug!("error writing state response: {}", e)
+								}
+							};
+							self.outgoing.push(future.boxed())
+						}
+					}
+					Err(e) => log::debug!("error handling state request from peer {}: {}", peer, e)
+				}
+			}
+			NodeEvent::Response(response, block_hash) => {
+				self.traffic_dump.record_message(Direction::In, &self.config.protocol, &peer, &response);
+				if self.ongoing.get(&peer).map_or(false, |request| request.block_hash == block_hash) {
+					self.ongoing.remove(&peer);
+				}
+				let ev = Event::Response {
+					peer,
+					block_hash,
+					response,
+				};
+				self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
+			}
+		}
+	}
+
+	fn poll(&mut self, cx: &mut Context, _: &mut impl PollParameters)
+		-> Poll<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>
+	{
+		if let Some(ev) = self.pending_events.pop_front() {
+			return Poll::Ready(ev);
+		}
+
+		// Check the request timeouts.
+		let timed_out = self.ongoing.iter_mut()
+			.find_map(|(peer, request)| match Pin::new(&mut request.timeout).poll(cx) {
+				Poll::Ready(()) => Some(peer.clone()),
+				Poll::Pending => None,
+			});
+		if let Some(peer) = timed_out {
+			let request = self.ongoing.remove(&peer)
+				.expect("peer has just been found in the map; qed");
+			log::debug!(target: "sync", "State request timeout for {}", peer);
+			let ev = Event::RequestFailed { peer, block_hash: request.block_hash };
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(ev));
+		}
+
+		while let Poll::Ready(Some(_)) = self.outgoing.poll_next_unpin(cx) {}
+		Poll::Pending
+	}
+}
+
+/// Output type of inbound and outbound substream upgrades.
+#[derive(Debug)]
+pub enum NodeEvent<B: Block, T> {
+	/// Incoming request from remote and substream to use for the response.
+	Request(schema::v1::state::StateRequest, T),
+	/// Incoming response from remote.
+	Response(schema::v1::state::StateResponse, B::Hash),
+}
+
+/// Substream upgrade protocol.
+///
+/// We attempt to parse an incoming protobuf encoded request (cf. `Request`)
+/// which will be handled by the `StateRequests` behaviour, i.e. the request
+/// will become visible via `inject_node_event` which then dispatches to the
+/// relevant callback to process the message and prepare a response.
+#[derive(Debug, Clone)]
+pub struct InboundProtocol<B> {
+	/// The max. request length in bytes.
+	max_request_len: usize,
+	/// The protocol to use during upgrade negotiation. If `None`, then the incoming protocol
+	/// is simply disabled.
+	protocol: Option<Bytes>,
+	/// Marker to pin the block type.
+	marker: PhantomData<B>,
+}
+
+impl<B: Block> UpgradeInfo for InboundProtocol<B> {
+	type Info = Bytes;
+	// This iterator will return either 0 elements if `self.protocol` is `None`, or 1 element if
+	// it is `Some`.
+	type InfoIter = std::option::IntoIter<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		self.protocol.clone().into_iter()
+	}
+}
+
+impl<B, T> InboundUpgrade<T> for InboundProtocol<B>
+where
+	B: Block,
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = NodeEvent<B, T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_inbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			let len = self.max_request_len;
+			let vec = read_one(&mut s, len).await?;
+			match schema::v1::state::StateRequest::decode(&vec[..]) {
+				Ok(r) => Ok(NodeEvent::Request(r, s)),
+				Err(e) => Err(ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e)))
+			}
+		}.boxed()
+	}
+}
+
+/// Substream upgrade protocol.
+///
+/// Sends a request to remote and awaits the response.
+#[derive(Debug, Clone)]
+pub struct OutboundProtocol<B: Block> {
+	/// The serialized protobuf request.
+	request: Vec<u8>,
+	/// Block hash that has been requested.
+	block_hash: B::Hash,
+	/// The max. response length in bytes.
+	max_response_size: usize,
+	/// The protocol to use for upgrade negotiation.
+	protocol: Bytes,
+}
+
+impl<B: Block> UpgradeInfo for OutboundProtocol<B> {
+	type Info = Bytes;
+	type InfoIter = iter::Once<Self::Info>;
+
+	fn protocol_info(&self) -> Self::InfoIter {
+		iter::once(self.protocol.clone())
+	}
+}
+
+impl<B, T> OutboundUpgrade<T> for OutboundProtocol<B>
+where
+	B: Block,
+	T: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	type Output = NodeEvent<B, T>;
+	type Error = ReadOneError;
+	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
+
+	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+		async move {
+			write_one(&mut s, &self.request).await?;
+			let vec = read_one(&mut s, self.max_response_size).await?;
+
+			schema::v1::state::StateResponse::decode(&vec[..])
+				.map(|r| NodeEvent::Response(r, self.block_hash))
+				.map_err(|e| {
+					ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e))
+				})
+		}.boxed()
+	}
+}
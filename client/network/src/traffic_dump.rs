@@ -0,0 +1,302 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Recording of the messages exchanged with other nodes, for debugging purposes.
+//!
+//! The [`TrafficDump`] is shared between the notifications and request-response behaviours,
+//! which report every message they send or receive, and the `NetworkService`, which can start
+//! and stop the dump at runtime.
+//!
+//! Messages are written to a file as one JSON object per line, holding the time at which the
+//! message was seen, its direction, the protocol name, the remote and the size of the payload.
+//! The payload itself is hex-encoded and included only if configured. Once the file grows above
+//! the configured size, it is moved to `<path>.1`, the previous `<path>.1` to `<path>.2`, and so
+//! on, and a new file is started.
+
+use libp2p::PeerId;
+use log::warn;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::PathBuf,
+	sync::{Arc, atomic::{AtomicBool, Ordering}},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Configuration of a traffic dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrafficDumpConfig {
+	/// File the messages are written to.
+	pub path: PathBuf,
+	/// Size in bytes above which the file is rotated.
+	pub max_file_size: u64,
+	/// Number of rotated files kept in addition to the current one. Older ones are deleted.
+	pub max_rotated_files: usize,
+	/// If true, the payload of each message is written to the file along with its size.
+	pub include_payloads: bool,
+}
+
+impl TrafficDumpConfig {
+	/// Creates a configuration writing to `path`, with the following options:
+	///
+	/// - max. file size = 64 MiB
+	/// - rotated files kept = 4
+	/// - payloads not included
+	pub fn new(path: PathBuf) -> Self {
+		TrafficDumpConfig {
+			path,
+			max_file_size: 64 * 1024 * 1024,
+			max_rotated_files: 4,
+			include_payloads: false,
+		}
+	}
+}
+
+/// Direction of a recorded message.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+	/// Message received from the remote.
+	In,
+	/// Message sent to the remote.
+	Out,
+}
+
+/// Line of the dump file.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Record<'a> {
+	/// Milliseconds since the UNIX epoch.
+	timestamp: u64,
+	direction: Direction,
+	protocol: &'a str,
+	peer: String,
+	size: usize,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	payload: Option<String>,
+}
+
+/// File currently being written to.
+struct Writer {
+	config: TrafficDumpConfig,
+	file: File,
+	/// Number of bytes in `file`.
+	written: u64,
+}
+
+impl Writer {
+	fn open(config: TrafficDumpConfig) -> io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(&config.path)?;
+		let written = file.metadata()?.len();
+		Ok(Writer { config, file, written })
+	}
+
+	fn rotated_path(&self, index: usize) -> PathBuf {
+		let mut path = self.config.path.clone().into_os_string();
+		path.push(format!(".{}", index));
+		path.into()
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		if self.config.max_rotated_files == 0 {
+			self.file = File::create(&self.config.path)?;
+			self.written = 0;
+			return Ok(())
+		}
+
+		let _ = fs::remove_file(self.rotated_path(self.config.max_rotated_files));
+		for index in (1..self.config.max_rotated_files).rev() {
+			let from = self.rotated_path(index);
+			if from.exists() {
+				fs::rename(from, self.rotated_path(index + 1))?;
+			}
+		}
+		fs::rename(&self.config.path, self.rotated_path(1))?;
+
+		self.file = File::create(&self.config.path)?;
+		self.written = 0;
+		Ok(())
+	}
+
+	fn write(&mut self, record: &Record) -> io::Result<()> {
+		let mut line = serde_json::to_vec(record)?;
+		line.push(b'\n');
+
+		if self.written > 0 && self.written + line.len() as u64 > self.config.max_file_size {
+			self.rotate()?;
+		}
+		self.file.write_all(&line)?;
+		self.written += line.len() as u64;
+		Ok(())
+	}
+}
+
+/// Handle to the traffic dump. Cloning it gives another handle to the same dump.
+///
+/// Recording messages is a no-op, apart from an atomic load, as long as no dump is running.
+#[derive(Clone, Default)]
+pub struct TrafficDump {
+	enabled: Arc<AtomicBool>,
+	writer: Arc<Mutex<Option<Writer>>>,
+}
+
+impl TrafficDump {
+	/// Starts dumping messages according to `config`, replacing the dump currently running if
+	/// any. Messages are appended if the file already exists.
+	pub fn start(&self, config: TrafficDumpConfig) -> io::Result<()> {
+		let writer = Writer::open(config)?;
+		*self.writer.lock() = Some(writer);
+		self.enabled.store(true, Ordering::Relaxed);
+		Ok(())
+	}
+
+	/// Stops dumping messages.
+	pub fn stop(&self) {
+		self.enabled.store(false, Ordering::Relaxed);
+		*self.writer.lock() = None;
+	}
+
+	/// Returns the configuration of the dump currently running, if any.
+	pub fn config(&self) -> Option<TrafficDumpConfig> {
+		self.writer.lock().as_ref().map(|writer| writer.config.clone())
+	}
+
+	/// Records a message sent to or received from `peer`.
+	pub fn record(&self, direction: Direction, protocol: &[u8], peer: &PeerId, payload: &[u8]) {
+		self.record_with(direction, protocol, peer, || payload.to_vec())
+	}
+
+	/// Records a protobuf message sent to or received from `peer`. The message is only encoded
+	/// if a dump is running.
+	pub fn record_message(
+		&self,
+		direction: Direction,
+		protocol: &[u8],
+		peer: &PeerId,
+		message: &impl prost::Message,
+	) {
+		self.record_with(direction, protocol, peer, || {
+			let mut buf = Vec::with_capacity(message.encoded_len());
+			// Encoding into a `Vec` can't fail for lack of space.
+			let _ = message.encode(&mut buf);
+			buf
+		})
+	}
+
+	fn record_with(
+		&self,
+		direction: Direction,
+		protocol: &[u8],
+		peer: &PeerId,
+		payload: impl FnOnce() -> Vec<u8>,
+	) {
+		if !self.enabled.load(Ordering::Relaxed) {
+			return
+		}
+
+		let mut writer = self.writer.lock();
+		let result = match writer.as_mut() {
+			Some(writer) => {
+				let payload = payload();
+				let protocol = String::from_utf8_lossy(protocol);
+				let record = Record {
+					timestamp: SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.map(|d| d.as_millis() as u64)
+						.unwrap_or(0),
+					direction,
+					protocol: &protocol,
+					peer: peer.to_base58(),
+					size: payload.len(),
+					payload: if writer.config.include_payloads {
+						Some(hex::encode(&payload))
+					} else {
+						None
+					},
+				};
+				writer.write(&record)
+			},
+			None => return,
+		};
+
+		if let Err(err) = result {
+			warn!(target: "sub-libp2p", "Failed to write to the traffic dump, stopping it: {}", err);
+			self.enabled.store(false, Ordering::Relaxed);
+			*writer = None;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Direction, TrafficDump, TrafficDumpConfig};
+	use libp2p::PeerId;
+	use std::fs;
+
+	#[test]
+	fn records_are_written_only_while_running() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("traffic");
+		let dump = TrafficDump::default();
+		let peer = PeerId::random();
+
+		dump.record(Direction::In, b"/foo", &peer, b"ignored");
+
+		dump.start(TrafficDumpConfig { include_payloads: true, ..TrafficDumpConfig::new(path.clone()) })
+			.unwrap();
+		dump.record(Direction::Out, b"/foo", &peer, b"hello");
+		dump.stop();
+		dump.record(Direction::In, b"/foo", &peer, b"ignored");
+
+		let content = fs::read_to_string(&path).unwrap();
+		let lines = content.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 1);
+
+		let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+		assert_eq!(record["direction"], "out");
+		assert_eq!(record["protocol"], "/foo");
+		assert_eq!(record["peer"], peer.to_base58());
+		assert_eq!(record["size"], 5);
+		assert_eq!(record["payload"], hex::encode(b"hello"));
+	}
+
+	#[test]
+	fn files_are_rotated() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("traffic");
+		let dump = TrafficDump::default();
+		let peer = PeerId::random();
+
+		dump.start(TrafficDumpConfig {
+			max_file_size: 1,
+			max_rotated_files: 2,
+			..TrafficDumpConfig::new(path.clone())
+		}).unwrap();
+		for _ in 0..4 {
+			dump.record(Direction::In, b"/foo", &peer, b"hello");
+		}
+
+		// every record gets a file of its own, and only two older ones are kept.
+		assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+		assert!(dir.path().join("traffic.1").exists());
+		assert!(dir.path().join("traffic.2").exists());
+		assert!(!dir.path().join("traffic.3").exists());
+	}
+}
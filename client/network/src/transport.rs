@@ -16,18 +16,21 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use futures::prelude::*;
+use crate::ip_filter::{ConnectionGuard, ConnectionTracker, IpFilter, LimitReached};
+use futures::{future, prelude::*};
 use libp2p::{
 	InboundUpgradeExt, OutboundUpgradeExt, PeerId, Transport,
 	core::{
-		self, either::{EitherError, EitherOutput}, muxing::StreamMuxerBox,
+		self, ConnectedPoint, either::{EitherError, EitherOutput}, muxing::StreamMuxerBox,
 		transport::{boxed::Boxed, OptionalTransport}, upgrade
 	},
 	mplex, identity, bandwidth, wasm_ext, noise
 };
 #[cfg(not(target_os = "unknown"))]
 use libp2p::{tcp, dns, websocket};
-use std::{io, sync::Arc, time::Duration, usize};
+use parking_lot::RwLock;
+use prometheus_endpoint::{CounterVec, U64};
+use std::{io, pin::Pin, sync::Arc, task::{Context, Poll}, time::Duration, usize};
 
 pub use self::bandwidth::BandwidthSinks;
 
@@ -36,13 +39,29 @@ pub use self::bandwidth::BandwidthSinks;
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
 /// addresses with the format `/memory/...` are allowed.
 ///
+/// Incoming connections whose remote address is rejected by `ip_filter` are closed before any
+/// handshake takes place. The filter is read again for every connection, meaning that it can be
+/// modified after the transport has been built.
+///
+/// Incoming connections are also closed before any handshake if there are already as many
+/// connections from the same IP address or subnet as allowed by `connection_tracker`.
+///
+/// If `secure_only` is true, only the Noise `XX` handshake as specified by libp2p is offered, and
+/// the connections to nodes only supporting the legacy `IX` handshake are refused as they fail
+/// to negotiate an encryption protocol. Refused connections are reported to `rejections`, if
+/// passed, with the reason as the only label.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
 /// the connections spawned with this transport.
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
 	wasm_external_transport: Option<wasm_ext::ExtTransport>,
-	use_yamux_flow_control: bool
+	use_yamux_flow_control: bool,
+	ip_filter: Arc<RwLock<IpFilter>>,
+	connection_tracker: Arc<ConnectionTracker>,
+	secure_only: bool,
+	rejections: Option<CounterVec<U64>>,
 ) -> (Boxed<(PeerId, StreamMuxerBox), io::Error>, Arc<bandwidth::BandwidthSinks>) {
 	// Build configuration objects for encryption mechanisms.
 	let noise_config = {
@@ -60,7 +79,11 @@ pub fn build_transport(
 
 		core::upgrade::SelectUpgrade::new(
 			noise::NoiseConfig::xx(noise_keypair_spec),
-			noise::NoiseConfig::ix(noise_keypair_legacy)
+			if secure_only {
+				upgrade::OptionalUpgrade::none()
+			} else {
+				upgrade::OptionalUpgrade::some(noise::NoiseConfig::ix(noise_keypair_legacy))
+			},
 		)
 	};
 
@@ -106,15 +129,41 @@ pub fn build_transport(
 
 	let (transport, sinks) = bandwidth::BandwidthLogging::new(transport, Duration::from_secs(5));
 
+	// Incoming connections filtering
+	let transport = transport.and_then(move |stream, endpoint| {
+		let guard = match &endpoint {
+			ConnectedPoint::Listener { send_back_addr, .. } =>
+				if !ip_filter.read().is_address_allowed(send_back_addr) {
+					Err(io::Error::new(io::ErrorKind::PermissionDenied, "Remote IP address is not allowed"))
+				} else {
+					connection_tracker.try_register(send_back_addr).map_err(|limit| {
+						let reason = match limit {
+							LimitReached::PerIp => "Too many connections from the remote IP address",
+							LimitReached::PerSubnet => "Too many connections from the remote subnet",
+						};
+						io::Error::new(io::ErrorKind::PermissionDenied, reason)
+					})
+				},
+			ConnectedPoint::Dialer { .. } => Ok(None),
+		};
+
+		future::ready(guard.map(|guard| Counted { inner: stream, _guard: guard }))
+	});
+
 	// Encryption
 	let transport = transport.and_then(move |stream, endpoint| {
 		core::upgrade::apply(stream, noise_config, endpoint, upgrade::Version::V1)
-			.map_err(|err|
+			.map_err(move |err| {
+				if secure_only {
+					if let (upgrade::UpgradeError::Select(_), Some(rejections)) = (&err, &rejections) {
+						rejections.with_label_values(&["negotiation-failed"]).inc();
+					}
+				}
 				err.map_err(|err| match err {
 					EitherError::A(err) => err,
 					EitherError::B(err) => err,
 				})
-			)
+			})
 			.and_then(|result| async move {
 				let remote_key = match &result {
 					EitherOutput::First((noise::RemoteIdentity::IdentityKey(key), _)) => key.clone(),
@@ -147,3 +196,31 @@ pub fn build_transport(
 
 	(transport, sinks)
 }
+
+/// Connection that is counted by a [`ConnectionTracker`] for as long as it is alive.
+#[pin_project::pin_project]
+struct Counted<S> {
+	#[pin]
+	inner: S,
+	_guard: Option<ConnectionGuard>,
+}
+
+impl<S: AsyncRead> AsyncRead for Counted<S> {
+	fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+		self.project().inner.poll_read(cx, buf)
+	}
+}
+
+impl<S: AsyncWrite> AsyncWrite for Counted<S> {
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		self.project().inner.poll_write(cx, buf)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_flush(cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_close(cx)
+	}
+}
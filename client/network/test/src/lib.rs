@@ -48,7 +48,9 @@ use sp_consensus::Error as ConsensusError;
 use sp_consensus::{BlockOrigin, ForkChoiceStrategy, BlockImportParams, BlockCheckParams, JustificationImport};
 use futures::prelude::*;
 use sc_network::{NetworkWorker, NetworkService, config::ProtocolId};
-use sc_network::config::{NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder};
+use sc_network::config::{
+	NetworkConfiguration, TransportConfig, BoxFinalityProofRequestBuilder, PropagateToAll,
+};
 use libp2p::PeerId;
 use parking_lot::Mutex;
 use sp_core::H256;
@@ -637,11 +639,14 @@ pub trait TestNetFactory: Sized {
 				PeersClient::Full(client.clone(), backend.clone()),
 			),
 			finality_proof_request_builder,
+			warp_sync_provider: None,
+			indexed_data: None,
 			on_demand: None,
 			transaction_pool: Arc::new(EmptyTransactionPool),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
 			import_queue,
 			block_announce_validator: Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+			transaction_propagation_policy: Box::new(PropagateToAll),
 			metrics_registry: None,
 		}).unwrap();
 
@@ -716,11 +721,14 @@ pub trait TestNetFactory: Sized {
 				PeersClient::Light(client.clone(), backend.clone())
 			),
 			finality_proof_request_builder,
+			warp_sync_provider: None,
+			indexed_data: None,
 			on_demand: None,
 			transaction_pool: Arc::new(EmptyTransactionPool),
 			protocol_id: ProtocolId::from(&b"test-protocol-name"[..]),
 			import_queue,
 			block_announce_validator: Box::new(DefaultBlockAnnounceValidator::new(client.clone())),
+			transaction_propagation_policy: Box::new(PropagateToAll),
 			metrics_registry: None,
 		}).unwrap();
 
@@ -23,14 +23,13 @@ use std::{
 
 use sp_core::offchain::OffchainStorage;
 use futures::Future;
-use log::error;
 use sc_network::{PeerId, Multiaddr, NetworkStateInfo};
 use codec::{Encode, Decode};
 use sp_core::offchain::{
 	Externalities as OffchainExt, HttpRequestId, Timestamp, HttpRequestStatus, HttpError,
 	OpaqueNetworkState, OpaquePeerId, OpaqueMultiaddr, StorageKind,
 };
-pub use sp_offchain::STORAGE_PREFIX;
+pub use sp_offchain::{LOCAL_STORAGE_PREFIX, STORAGE_PREFIX};
 
 #[cfg(not(target_os = "unknown"))]
 mod http;
@@ -56,16 +55,14 @@ pub(crate) struct Api<Storage> {
 	http: http::HttpApi,
 }
 
-fn unavailable_yet<R: Default>(name: &str) -> R {
-	error!(
-		"The {:?} API is not available for offchain workers yet. Follow \
-		https://github.com/paritytech/substrate/issues/1458 for details", name
-	);
-	Default::default()
+/// Returns the prefix of the database entries of the storage `kind`.
+fn storage_prefix(kind: StorageKind) -> &'static [u8] {
+	match kind {
+		StorageKind::PERSISTENT => STORAGE_PREFIX,
+		StorageKind::LOCAL => LOCAL_STORAGE_PREFIX,
+	}
 }
 
-const LOCAL_DB: &str = "LOCAL (fork-aware) DB";
-
 impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 	fn is_validator(&self) -> bool {
 		self.is_validator
@@ -94,17 +91,11 @@ impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 	}
 
 	fn local_storage_set(&mut self, kind: StorageKind, key: &[u8], value: &[u8]) {
-		match kind {
-			StorageKind::PERSISTENT => self.db.set(STORAGE_PREFIX, key, value),
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.set(storage_prefix(kind), key, value)
 	}
 
 	fn local_storage_clear(&mut self, kind: StorageKind, key: &[u8]) {
-		match kind {
-			StorageKind::PERSISTENT => self.db.remove(STORAGE_PREFIX, key),
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.remove(storage_prefix(kind), key)
 	}
 
 	fn local_storage_compare_and_set(
@@ -114,19 +105,11 @@ impl<Storage: OffchainStorage> OffchainExt for Api<Storage> {
 		old_value: Option<&[u8]>,
 		new_value: &[u8],
 	) -> bool {
-		match kind {
-			StorageKind::PERSISTENT => {
-				self.db.compare_and_set(STORAGE_PREFIX, key, old_value, new_value)
-			},
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.compare_and_set(storage_prefix(kind), key, old_value, new_value)
 	}
 
 	fn local_storage_get(&mut self, kind: StorageKind, key: &[u8]) -> Option<Vec<u8>> {
-		match kind {
-			StorageKind::PERSISTENT => self.db.get(STORAGE_PREFIX, key),
-			StorageKind::LOCAL => unavailable_yet(LOCAL_DB),
-		}
+		self.db.get(storage_prefix(kind), key)
 	}
 
 	fn http_request_start(
@@ -365,6 +348,20 @@ mod tests {
 		assert_eq!(api.local_storage_get(kind, key), Some(b"value".to_vec()));
 	}
 
+	#[test]
+	fn should_keep_storage_kinds_apart() {
+		// given
+		let mut api = offchain_api().0;
+		let key = b"test";
+
+		// when
+		api.local_storage_set(StorageKind::LOCAL, key, b"value");
+
+		// then
+		assert_eq!(api.local_storage_get(StorageKind::LOCAL, key), Some(b"value".to_vec()));
+		assert_eq!(api.local_storage_get(StorageKind::PERSISTENT, key), None);
+	}
+
 	#[test]
 	fn should_compare_and_set_local_storage() {
 		// given
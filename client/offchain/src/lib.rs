@@ -46,7 +46,7 @@ use sp_runtime::{generic::BlockId, traits::{self, Header}};
 
 mod api;
 
-pub use sp_offchain::{OffchainWorkerApi, STORAGE_PREFIX};
+pub use sp_offchain::{OffchainWorkerApi, LOCAL_STORAGE_PREFIX, STORAGE_PREFIX};
 
 /// An offchain workers manager.
 pub struct OffchainWorkers<Client, Storage, Block: traits::Block> {
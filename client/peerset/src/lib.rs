@@ -20,6 +20,7 @@
 //! connected to.
 
 mod peersstate;
+mod scoring;
 
 use std::{collections::{HashSet, HashMap}, collections::VecDeque};
 use futures::prelude::*;
@@ -30,6 +31,7 @@ use wasm_timer::Instant;
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender, TracingUnboundedReceiver};
 
 pub use libp2p::PeerId;
+pub use scoring::{Observation, PeerScorer, RatioScorer};
 
 /// We don't accept nodes whose reputation is under this value.
 const BANNED_THRESHOLD: i32 = 82 * (i32::min_value() / 100);
@@ -50,6 +52,9 @@ enum Action {
 	SetPriorityGroup(String, HashSet<PeerId>),
 	AddToPriorityGroup(String, PeerId),
 	RemoveFromPriorityGroup(String, PeerId),
+	AddScorer(scoring::NamedScorer),
+	RemoveScorer(&'static str),
+	Observe(PeerId, Observation),
 }
 
 /// Description of a reputation adjustment for a node.
@@ -122,6 +127,25 @@ impl PeersetHandle {
 	pub fn remove_from_priority_group(&self, group_id: String, peer_id: PeerId) {
 		let _ = self.tx.unbounded_send(Action::RemoveFromPriorityGroup(group_id, peer_id));
 	}
+
+	/// Registers a scorer turning the observations reported with [`PeersetHandle::observe`]
+	/// into reputation changes.
+	///
+	/// Replaces the scorer previously registered under the same name, if any.
+	pub fn add_scorer(&self, name: &'static str, scorer: Box<dyn PeerScorer>) {
+		let _ = self.tx.unbounded_send(Action::AddScorer(scoring::NamedScorer { name, scorer }));
+	}
+
+	/// Removes the scorer registered under the given name.
+	pub fn remove_scorer(&self, name: &'static str) {
+		let _ = self.tx.unbounded_send(Action::RemoveScorer(name));
+	}
+
+	/// Reports an observation about the given peer to the registered scorers, which adjust its
+	/// reputation accordingly.
+	pub fn observe(&self, peer_id: PeerId, observation: Observation) {
+		let _ = self.tx.unbounded_send(Action::Observe(peer_id, observation));
+	}
 }
 
 /// Message that can be sent by the peer set manager (PSM).
@@ -174,6 +198,13 @@ pub struct PeersetConfig {
 	/// > **Note**: Keep in mind that the networking has to know an address for these nodes,
 	/// >			otherwise it will not be able to connect to them.
 	pub priority_groups: Vec<(String, HashSet<PeerId>)>,
+
+	/// If `Some`, the only nodes we connect to or accept connections from, whatever the
+	/// bootnodes, the priority groups and the discovered nodes, e.g. the sentry nodes of a private
+	/// validator.
+	///
+	/// Other nodes are ignored, and can't be added at runtime.
+	pub allowed_peers: Option<HashSet<PeerId>>,
 }
 
 /// Side of the peer set manager owned by the network. In other words, the "receiving" side.
@@ -186,6 +217,8 @@ pub struct Peerset {
 	data: peersstate::PeersState,
 	/// If true, we only accept reserved nodes.
 	reserved_only: bool,
+	/// If `Some`, the only nodes we connect to or accept connections from.
+	allowed_peers: Option<HashSet<PeerId>>,
 	/// Lists of nodes that don't occupy slots and that we should try to always be connected to.
 	/// Is kept in sync with the list of reserved nodes in [`Peerset::data`].
 	priority_groups: HashMap<String, HashSet<PeerId>>,
@@ -199,6 +232,8 @@ pub struct Peerset {
 	created: Instant,
 	/// Last time when we updated the reputations of connected nodes.
 	latest_time_update: Instant,
+	/// Scorers turning observations into reputation changes.
+	scorers: Vec<scoring::NamedScorer>,
 }
 
 impl Peerset {
@@ -217,17 +252,23 @@ impl Peerset {
 			tx,
 			rx,
 			reserved_only: config.reserved_only,
-			priority_groups: config.priority_groups.clone().into_iter().collect(),
+			allowed_peers: config.allowed_peers,
+			priority_groups: HashMap::new(),
 			message_queue: VecDeque::new(),
 			created: now,
 			latest_time_update: now,
+			scorers: Vec::new(),
 		};
 
-		for node in config.priority_groups.into_iter().flat_map(|(_, l)| l) {
-			peerset.data.add_no_slot_node(node);
+		for (group_id, peers) in config.priority_groups {
+			let peers = peers.into_iter().filter(|peer_id| peerset.is_allowed(peer_id)).collect::<HashSet<_>>();
+			for peer_id in &peers {
+				peerset.data.add_no_slot_node(peer_id.clone());
+			}
+			peerset.priority_groups.insert(group_id, peers);
 		}
 
-		for peer_id in config.bootnodes {
+		for peer_id in config.bootnodes.into_iter().filter(|peer_id| peerset.is_allowed(peer_id)) {
 			if let peersstate::Peer::Unknown(entry) = peerset.data.peer(&peer_id) {
 				entry.discover();
 			} else {
@@ -239,6 +280,11 @@ impl Peerset {
 		(peerset, handle)
 	}
 
+	/// Returns true if we may connect to or accept connections from `peer_id`.
+	fn is_allowed(&self, peer_id: &PeerId) -> bool {
+		self.allowed_peers.as_ref().map_or(true, |allowed| allowed.contains(peer_id))
+	}
+
 	fn on_add_reserved_peer(&mut self, peer_id: PeerId) {
 		self.on_add_to_priority_group(RESERVED_NODES, peer_id);
 	}
@@ -268,7 +314,9 @@ impl Peerset {
 		}
 	}
 
-	fn on_set_priority_group(&mut self, group_id: &str, peers: HashSet<PeerId>) {
+	fn on_set_priority_group(&mut self, group_id: &str, mut peers: HashSet<PeerId>) {
+		peers.retain(|peer_id| self.is_allowed(peer_id));
+
 		// Determine the difference between the current group and the new list.
 		let (to_insert, to_remove) = {
 			let current_group = self.priority_groups.entry(group_id.to_owned()).or_default();
@@ -298,6 +346,11 @@ impl Peerset {
 	}
 
 	fn on_add_to_priority_group(&mut self, group_id: &str, peer_id: PeerId) {
+		if !self.is_allowed(&peer_id) {
+			debug!(target: "peerset", "Ignoring {:?} added to {}, which isn't allowed", peer_id, group_id);
+			return
+		}
+
 		self.priority_groups.entry(group_id.to_owned()).or_default().insert(peer_id.clone());
 		self.data.add_no_slot_node(peer_id);
 		self.alloc_slots();
@@ -348,10 +401,29 @@ impl Peerset {
 				}
 			},
 			peersstate::Peer::NotConnected(mut peer) => peer.add_reputation(change.value),
+			peersstate::Peer::Unknown(_) if !self.is_allowed(&peer_id) => {},
 			peersstate::Peer::Unknown(peer) => peer.discover().add_reputation(change.value),
 		}
 	}
 
+	fn on_add_scorer(&mut self, scorer: scoring::NamedScorer) {
+		self.on_remove_scorer(scorer.name);
+		self.scorers.push(scorer);
+	}
+
+	fn on_remove_scorer(&mut self, name: &'static str) {
+		self.scorers.retain(|s| s.name != name);
+	}
+
+	fn on_observe(&mut self, peer_id: PeerId, observation: Observation) {
+		let changes = self.scorers.iter_mut()
+			.filter_map(|s| s.scorer.on_observation(&peer_id, observation))
+			.collect::<Vec<_>>();
+		for change in changes {
+			self.on_report_peer(peer_id.clone(), change);
+		}
+	}
+
 	/// Updates the value of `self.latest_time_update` and performs all the updates that happen
 	/// over time, such as reputation increases for staying connected.
 	fn update_time(&mut self) {
@@ -520,6 +592,12 @@ impl Peerset {
 		trace!(target: "peerset", "Incoming {:?}", peer_id);
 		self.update_time();
 
+		if !self.is_allowed(&peer_id) {
+			debug!(target: "peerset", "Rejecting incoming {:?}, which isn't allowed", peer_id);
+			self.message_queue.push_back(Message::Reject(index));
+			return
+		}
+
 		let not_connected = match self.data.peer(&peer_id) {
 			// If we're already connected, don't answer, as the docs mention.
 			peersstate::Peer::Connected(_) => return,
@@ -556,6 +634,9 @@ impl Peerset {
 				// Decrease the node's reputation so that we don't try it again and again and again.
 				entry.add_reputation(DISCONNECT_REPUTATION_CHANGE);
 				entry.disconnect();
+				for s in &mut self.scorers {
+					s.scorer.on_disconnected(&peer_id);
+				}
 			}
 			peersstate::Peer::NotConnected(_) | peersstate::Peer::Unknown(_) =>
 				error!(target: "peerset", "Received dropped() for non-connected node"),
@@ -572,6 +653,9 @@ impl Peerset {
 		let mut discovered_any = false;
 
 		for peer_id in peer_ids {
+			if !self.is_allowed(&peer_id) {
+				continue
+			}
 			if let peersstate::Peer::Unknown(entry) = self.data.peer(&peer_id) {
 				entry.discover();
 				discovered_any = true;
@@ -658,6 +742,12 @@ impl Stream for Peerset {
 					self.on_add_to_priority_group(&group_id, peer_id),
 				Action::RemoveFromPriorityGroup(group_id, peer_id) =>
 					self.on_remove_from_priority_group(&group_id, peer_id),
+				Action::AddScorer(scorer) =>
+					self.on_add_scorer(scorer),
+				Action::RemoveScorer(name) =>
+					self.on_remove_scorer(name),
+				Action::Observe(peer_id, observation) =>
+					self.on_observe(peer_id, observation),
 			}
 		}
 	}
@@ -667,7 +757,10 @@ impl Stream for Peerset {
 mod tests {
 	use libp2p::PeerId;
 	use futures::prelude::*;
-	use super::{PeersetConfig, Peerset, Message, IncomingIndex, ReputationChange, BANNED_THRESHOLD};
+	use super::{
+		PeersetConfig, Peerset, Message, IncomingIndex, ReputationChange, BANNED_THRESHOLD,
+		Observation, RatioScorer,
+	};
 	use std::{pin::Pin, task::Poll, thread, time::Duration};
 
 	fn assert_messages(mut peerset: Peerset, messages: Vec<Message>) -> Peerset {
@@ -696,6 +789,7 @@ mod tests {
 			bootnodes: vec![bootnode],
 			reserved_only: true,
 			priority_groups: Vec::new(),
+			allowed_peers: None,
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
@@ -724,6 +818,7 @@ mod tests {
 			bootnodes: vec![bootnode.clone()],
 			reserved_only: false,
 			priority_groups: Vec::new(),
+			allowed_peers: None,
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -751,6 +846,7 @@ mod tests {
 			bootnodes: vec![bootnode.clone()],
 			reserved_only: false,
 			priority_groups: vec![],
+			allowed_peers: None,
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -772,6 +868,7 @@ mod tests {
 			bootnodes: vec![],
 			reserved_only: false,
 			priority_groups: vec![],
+			allowed_peers: None,
 		});
 
 		// We ban a node by setting its reputation under the threshold.
@@ -804,4 +901,53 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	#[test]
+	fn test_peerset_scorers() {
+		let peer_id = PeerId::random();
+		let (peerset, handle) = Peerset::from_config(PeersetConfig {
+			in_peers: 0,
+			out_peers: 1,
+			bootnodes: vec![peer_id.clone()],
+			reserved_only: false,
+			priority_groups: vec![],
+			allowed_peers: None,
+		});
+
+		let scorer = RatioScorer::new("announce", 1, 100, ReputationChange::new_fatal("stale"));
+		handle.add_scorer("stale-announces", Box::new(scorer));
+		// observations of kinds no scorer is interested in have no effect.
+		handle.observe(peer_id.clone(), Observation::bad("other"));
+		handle.observe(peer_id.clone(), Observation::bad("announce"));
+
+		assert_messages(peerset, vec![
+			Message::Connect(peer_id.clone()),
+			Message::Drop(peer_id),
+		]);
+	}
+
+	#[test]
+	fn test_peerset_allowed_peers() {
+		let sentry = PeerId::random();
+		let bootnode = PeerId::random();
+		let stranger = PeerId::random();
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			in_peers: 5,
+			out_peers: 5,
+			bootnodes: vec![bootnode.clone()],
+			reserved_only: false,
+			priority_groups: vec![("sentries".to_string(), vec![sentry.clone()].into_iter().collect())],
+			allowed_peers: Some(vec![sentry.clone()].into_iter().collect()),
+		});
+
+		handle.add_reserved_peer(stranger.clone());
+		peerset.discovered(vec![stranger.clone(), bootnode]);
+		peerset.incoming(stranger, IncomingIndex(1));
+
+		let peerset = assert_messages(peerset, vec![
+			Message::Connect(sentry),
+			Message::Reject(IncomingIndex(1)),
+		]);
+		assert_eq!(peerset.num_discovered_peers(), 1);
+	}
 }
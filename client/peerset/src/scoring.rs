@@ -0,0 +1,171 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Custom scoring of peers.
+//!
+//! Rather than adjusting the reputation of a peer directly with `report_peer`, code noticing
+//! something about a peer can report an [`Observation`] through `PeersetHandle::observe`. The
+//! observation is passed to each of the [`PeerScorer`]s registered on the peerset, which decide
+//! whether it results in a reputation change. This keeps the policy, for example how many stale
+//! block announces are tolerated and how much they cost, in one place.
+
+use crate::ReputationChange;
+use libp2p::PeerId;
+use std::{collections::{HashMap, VecDeque}, fmt};
+
+/// Something noticed about the behaviour of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Observation {
+	/// What has been observed, for example `"block-announce"`.
+	pub kind: &'static str,
+	/// Whether the peer behaved as expected.
+	pub good: bool,
+}
+
+impl Observation {
+	/// Observation of a peer behaving as expected.
+	pub const fn good(kind: &'static str) -> Observation {
+		Observation { kind, good: true }
+	}
+
+	/// Observation of a peer misbehaving.
+	pub const fn bad(kind: &'static str) -> Observation {
+		Observation { kind, good: false }
+	}
+}
+
+/// Turns the observations made about peers into reputation changes.
+pub trait PeerScorer: Send {
+	/// Called for every observation reported to the peerset. Returns the reputation change to
+	/// apply to `peer`, if any.
+	fn on_observation(&mut self, peer: &PeerId, observation: Observation) -> Option<ReputationChange>;
+
+	/// Called when we are disconnected from `peer`, so that the state kept about it can be
+	/// discarded.
+	fn on_disconnected(&mut self, _peer: &PeerId) {}
+}
+
+/// [`PeerScorer`] registered on the peerset under a name.
+pub(crate) struct NamedScorer {
+	pub(crate) name: &'static str,
+	pub(crate) scorer: Box<dyn PeerScorer>,
+}
+
+impl fmt::Debug for NamedScorer {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("NamedScorer").field(&self.name).finish()
+	}
+}
+
+/// [`PeerScorer`] applying a penalty to peers for which observations of a given kind are
+/// consistently bad.
+///
+/// The latest `window` observations of the kind are kept for each peer. Once the window is
+/// full and at least `max_bad_percent` percent of them are bad, the penalty is applied and the
+/// window starts over.
+#[derive(Debug)]
+pub struct RatioScorer {
+	kind: &'static str,
+	window: usize,
+	max_bad_percent: u8,
+	penalty: ReputationChange,
+	history: HashMap<PeerId, VecDeque<bool>>,
+}
+
+impl RatioScorer {
+	/// Creates a scorer for the observations of kind `kind`.
+	pub fn new(
+		kind: &'static str,
+		window: usize,
+		max_bad_percent: u8,
+		penalty: ReputationChange,
+	) -> Self {
+		RatioScorer {
+			kind,
+			window: window.max(1),
+			max_bad_percent,
+			penalty,
+			history: HashMap::new(),
+		}
+	}
+}
+
+impl PeerScorer for RatioScorer {
+	fn on_observation(&mut self, peer: &PeerId, observation: Observation) -> Option<ReputationChange> {
+		if observation.kind != self.kind {
+			return None
+		}
+
+		let history = self.history.entry(peer.clone()).or_default();
+		if history.len() == self.window {
+			history.pop_front();
+		}
+		history.push_back(observation.good);
+		if history.len() < self.window {
+			return None
+		}
+
+		let bad = history.iter().filter(|good| !**good).count();
+		if bad * 100 >= self.window * usize::from(self.max_bad_percent) {
+			history.clear();
+			Some(self.penalty)
+		} else {
+			None
+		}
+	}
+
+	fn on_disconnected(&mut self, peer: &PeerId) {
+		self.history.remove(peer);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Observation, PeerScorer, RatioScorer};
+	use crate::ReputationChange;
+	use libp2p::PeerId;
+
+	const PENALTY: ReputationChange = ReputationChange::new(-100, "test");
+
+	#[test]
+	fn ratio_scorer_penalizes_consistently_bad_peers() {
+		let mut scorer = RatioScorer::new("announce", 4, 75, PENALTY);
+		let peer = PeerId::random();
+
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), None);
+		assert_eq!(scorer.on_observation(&peer, Observation::good("announce")), None);
+		// other kinds of observations are ignored.
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("other")), None);
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), None);
+		// 3 bad out of 4.
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), Some(PENALTY));
+		// the window starts over once the penalty has been applied.
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), None);
+	}
+
+	#[test]
+	fn ratio_scorer_forgets_disconnected_peers() {
+		let mut scorer = RatioScorer::new("announce", 2, 100, PENALTY);
+		let peer = PeerId::random();
+
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), None);
+		scorer.on_disconnected(&peer);
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), None);
+		assert_eq!(scorer.on_observation(&peer, Observation::bad("announce")), Some(PENALTY));
+	}
+}
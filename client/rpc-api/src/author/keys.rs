@@ -0,0 +1,46 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Session keys returned by the key rotation.
+
+use sp_core::Bytes;
+use serde::{Serialize, Deserialize};
+
+/// Public key of a set of session keys, along with a proof that the node has its private key.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionKey {
+	/// Key type ID of the key, e.g. `gran`.
+	pub key_type: String,
+	/// ID of the crypto of the key, e.g. `ed25`.
+	pub crypto: String,
+	/// Raw public key.
+	pub public: Bytes,
+	/// SCALE encoded signature of the SCALE encoded session keys, made with the key.
+	pub signature: Bytes,
+}
+
+/// Session keys generated by a key rotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotatedKeys {
+	/// SCALE encoded session keys, as expected by `set_keys`.
+	pub keys: Bytes,
+	/// Public key of every key type of the session keys, in order.
+	pub session_keys: Vec<SessionKey>,
+}
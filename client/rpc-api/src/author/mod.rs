@@ -20,12 +20,14 @@
 
 pub mod error;
 pub mod hash;
+pub mod keys;
 
 use jsonrpc_derive::rpc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_core::Bytes;
-use sp_transaction_pool::TransactionStatus;
+use sp_transaction_pool::{PoolEvent, TransactionStatus};
 use self::error::{FutureResult, Result};
+use self::keys::RotatedKeys;
 
 pub use self::gen_client::Client as AuthorClient;
 
@@ -52,6 +54,11 @@ pub trait AuthorApi<Hash, BlockHash> {
 	#[rpc(name = "author_rotateKeys")]
 	fn rotate_keys(&self) -> Result<Bytes>;
 
+	/// Generate new session keys and returns the public key of every key type, each with a
+	/// signature of the session keys proving that the node has the private key.
+	#[rpc(name = "author_rotateKeysWithProof")]
+	fn rotate_keys_with_proof(&self) -> Result<RotatedKeys>;
+
 	/// Checks if the keystore has private keys for the given session public keys.
 	///
 	/// `session_keys` is the SCALE encoded session keys object from the runtime.
@@ -101,4 +108,28 @@ pub trait AuthorApi<Hash, BlockHash> {
 		metadata: Option<Self::Metadata>,
 		id: SubscriptionId
 	) -> Result<bool>;
+
+	/// Subscribe to the life-cycle events of all the extrinsics of the pool.
+	///
+	/// See [`PoolEvent`](sp_transaction_pool::PoolEvent) for details on the events.
+	#[pubsub(
+		subscription = "author_allExtrinsicsUpdate",
+		subscribe,
+		name = "author_subscribeAllExtrinsics"
+	)]
+	fn subscribe_all_extrinsics(&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<PoolEvent<Hash, BlockHash>>,
+	);
+
+	/// Unsubscribe from the events of all the extrinsics of the pool.
+	#[pubsub(
+		subscription = "author_allExtrinsicsUpdate",
+		unsubscribe,
+		name = "author_unsubscribeAllExtrinsics"
+	)]
+	fn unsubscribe_all_extrinsics(&self,
+		metadata: Option<Self::Metadata>,
+		id: SubscriptionId
+	) -> Result<bool>;
 }
@@ -0,0 +1,47 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Substrate blockchain API helpers.
+
+use serde::{Serialize, Deserialize};
+
+/// Location of an extrinsic in the canonical chain returned by the RPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtrinsicLocation<Hash> {
+	/// Hash of the block including the extrinsic
+	pub block_hash: Hash,
+	/// Index of the extrinsic in the body of the block
+	pub index: u32,
+}
+
+/// Leaf of the block tree returned by the RPC
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeafInfo<Hash, Number> {
+	/// Hash of the leaf
+	pub hash: Hash,
+	/// Number of the leaf
+	pub number: Number,
+	/// Hash of the lowest common ancestor of the leaf and the best block
+	pub ancestor_hash: Hash,
+	/// Number of the lowest common ancestor of the leaf and the best block
+	pub ancestor_number: Number,
+	/// Whether the leaf isn't a descendant of the last finalized block
+	pub abandoned: bool,
+}
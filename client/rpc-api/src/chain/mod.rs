@@ -19,6 +19,7 @@
 //! Substrate blockchain API.
 
 pub mod error;
+pub mod helpers;
 
 use jsonrpc_core::Result as RpcResult;
 use jsonrpc_core::futures::Future;
@@ -28,6 +29,7 @@ use sp_rpc::{number::NumberOrHex, list::ListOrValue};
 use self::error::{FutureResult, Result};
 
 pub use self::gen_client::Client as ChainClient;
+pub use self::helpers::{ExtrinsicLocation, LeafInfo};
 
 /// Substrate blockchain API
 #[rpc]
@@ -56,6 +58,25 @@ pub trait ChainApi<Number, Hash, Header, SignedBlock> {
 	#[rpc(name = "chain_getFinalizedHead", alias("chain_getFinalisedHead"))]
 	fn finalized_head(&self) -> Result<Hash>;
 
+	/// Get the block including the extrinsic with the given hash in the canon chain, and the index
+	/// of the extrinsic in its body.
+	///
+	/// Returns `null` if the extrinsic is unknown, or if the node doesn't index the extrinsics.
+	#[rpc(name = "chain_getExtrinsic")]
+	fn extrinsic(&self, hash: Hash) -> FutureResult<Option<ExtrinsicLocation<Hash>>>;
+
+	/// Get the leaves of the block tree, best first, with their lowest common ancestor with the
+	/// best chain.
+	#[rpc(name = "chain_getLeaves")]
+	fn leaves(&self) -> FutureResult<Vec<LeafInfo<Hash, Number>>>;
+
+	/// Remove from the database the forks that aren't descendants of the last finalized block,
+	/// and whose leaves are below the given height.
+	///
+	/// Returns the hashes of the removed blocks.
+	#[rpc(name = "chain_pruneForks")]
+	fn prune_forks(&self, below: Number) -> FutureResult<Vec<Hash>>;
+
 	/// All head subscription
 	#[pubsub(subscription = "chain_allHead", subscribe, name = "chain_subscribeAllHeads")]
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Header>);
@@ -18,8 +18,10 @@
 
 //! Substrate state API.
 
+use jsonrpc_core::Result as RpcResult;
 use jsonrpc_derive::rpc;
-use sp_core::storage::{StorageKey, PrefixedStorageKey, StorageData};
+use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
+use sp_core::storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet};
 use crate::state::error::FutureResult;
 
 pub use self::gen_client::Client as ChildStateClient;
@@ -42,6 +44,19 @@ pub trait ChildStateApi<Hash> {
 		hash: Option<Hash>
 	) -> FutureResult<Vec<StorageKey>>;
 
+	/// Returns the keys with prefix from a child storage with pagination support.
+	/// Up to `count` keys will be returned.
+	/// If `start_key` is passed, return next keys in storage in lexicographic order.
+	#[rpc(name = "childstate_getKeysPaged", alias("childstate_getKeysPagedAt"))]
+	fn storage_keys_paged(
+		&self,
+		child_storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		hash: Option<Hash>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns a child storage entry at a specific block's state.
 	#[rpc(name = "childstate_getStorage")]
 	fn storage(
@@ -51,6 +66,15 @@ pub trait ChildStateApi<Hash> {
 		hash: Option<Hash>
 	) -> FutureResult<Option<StorageData>>;
 
+	/// Returns child storage entries for multiple keys at a specific block's state.
+	#[rpc(name = "childstate_getStorageEntries")]
+	fn storage_entries(
+		&self,
+		child_storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+		hash: Option<Hash>
+	) -> FutureResult<Vec<Option<StorageData>>>;
+
 	/// Returns the hash of a child storage entry at a block's state.
 	#[rpc(name = "childstate_getStorageHash")]
 	fn storage_hash(
@@ -68,4 +92,23 @@ pub trait ChildStateApi<Hash> {
 		key: StorageKey,
 		hash: Option<Hash>
 	) -> FutureResult<Option<u64>>;
+
+	/// New child storage subscription.
+	///
+	/// Notifications only hold the changes made to the given child storage. If `keys` is given,
+	/// only the changes of these keys are sent, starting with their current values.
+	#[pubsub(subscription = "childstate_storage", subscribe, name = "childstate_subscribeStorage")]
+	fn subscribe_storage(
+		&self,
+		metadata: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Hash>>,
+		child_storage_key: PrefixedStorageKey,
+		keys: Option<Vec<StorageKey>>,
+	);
+
+	/// Unsubscribe from child storage subscription
+	#[pubsub(subscription = "childstate_storage", unsubscribe, name = "childstate_unsubscribeStorage")]
+	fn unsubscribe_storage(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
 }
@@ -28,7 +28,7 @@ mod subscriptions;
 pub use jsonrpc_core::IoHandlerExtension as RpcExtension;
 pub use subscriptions::{Subscriptions, TaskExecutor};
 pub use helpers::Receiver;
-pub use policy::DenyUnsafe;
+pub use policy::{DenyUnsafe, AUTHOR_METHODS, DEBUG_METHODS, UNSAFE_METHODS};
 
 pub mod author;
 pub mod chain;
@@ -29,6 +29,9 @@ pub enum Error {
 	/// Unavailable storage kind error.
 	#[display(fmt="This storage kind is not available yet.")]
 	UnavailableStorageKind,
+	/// The node doesn't index the offchain data written by the runtime.
+	#[display(fmt="Offchain indexing is not enabled on this node.")]
+	IndexingDisabled,
 	/// Call to an unsafe RPC was denied.
 	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
 }
@@ -53,6 +56,11 @@ impl From<Error> for rpc::Error {
 				message: "This storage kind is not available yet" .into(),
 				data: None,
 			},
+			Error::IndexingDisabled => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 2),
+				message: "Offchain indexing is not enabled on this node".into(),
+				data: None,
+			},
 			Error::UnsafeRpcCalled(e) => e.into(),
 		}
 	}
@@ -36,4 +36,8 @@ pub trait OffchainApi {
 	/// Get offchain local storage under given key and prefix.
 	#[rpc(name = "offchain_localStorageGet")]
 	fn get_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<Option<Bytes>>;
+
+	/// Get the value written under given key by the runtime through offchain indexing.
+	#[rpc(name = "offchain_indexGet")]
+	fn get_indexed(&self, key: Bytes) -> Result<Option<Bytes>>;
 }
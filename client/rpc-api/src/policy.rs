@@ -19,10 +19,66 @@
 //! Policy-related types.
 //!
 //! Contains a `DenyUnsafe` type that can be used to deny potentially unsafe
-//! RPC when accessed externally.
+//! RPC when accessed externally, and the list of the methods concerned.
+//!
+//! The unsafe methods are split between the administration and the debugging ones, which servers
+//! can expose separately, along with the methods authoring transactions.
 
 use jsonrpc_core as rpc;
 
+/// Names of the RPC methods that are denied by `DenyUnsafe::Yes`.
+pub const UNSAFE_METHODS: &[&str] = &[
+	"author_hasKey",
+	"author_hasSessionKeys",
+	"author_insertKey",
+	"author_removeExtrinsic",
+	"author_rotateKeys",
+	"author_rotateKeysWithProof",
+	"babe_epochAuthorship",
+	"babe_nextEpochAuthorship",
+	"chain_getLeaves",
+	"chain_pruneForks",
+	"offchain_localStorageGet",
+	"offchain_localStorageSet",
+	"state_traceBlock",
+	"sync_state_genSyncSpec",
+	"system_addReservedPeer",
+	"system_dryRun",
+	"system_dryRunAt",
+	"system_ipFilter",
+	"system_networkState",
+	"system_peers",
+	"system_pinBlock",
+	"system_pinnedBlocks",
+	"system_removeReservedPeer",
+	"system_resetLogFilter",
+	"system_setIpFilter",
+	"system_setLogFilter",
+	"system_setTrafficDump",
+	"system_trafficDump",
+	"system_unpinBlock",
+];
+
+/// Names of the unsafe RPC methods that are meant for debugging. The other unsafe methods are
+/// administration ones.
+pub const DEBUG_METHODS: &[&str] = &[
+	"state_traceBlock",
+	"system_dryRun",
+	"system_dryRunAt",
+	"system_setTrafficDump",
+	"system_trafficDump",
+];
+
+/// Names of the safe RPC methods that submit transactions or watch them.
+pub const AUTHOR_METHODS: &[&str] = &[
+	"author_pendingExtrinsics",
+	"author_submitAndWatchExtrinsic",
+	"author_submitExtrinsic",
+	"author_subscribeAllExtrinsics",
+	"author_unsubscribeAllExtrinsics",
+	"author_unwatchExtrinsic",
+];
+
 /// Signifies whether a potentially unsafe RPC should be denied.
 #[derive(Clone, Copy, Debug)]
 pub enum DenyUnsafe {
@@ -51,12 +51,18 @@ pub enum Error {
 		/// Maximum allowed value
 		max: u32,
 	},
+	/// Provided count is zero, while at least one item must be returned.
+	#[display(fmt = "count must be greater than zero")]
+	ZeroCount,
+	/// Call to an unsafe RPC was denied.
+	UnsafeRpcCalled(crate::policy::UnsafeRpcError),
 }
 
 impl std::error::Error for Error {
 	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
 		match self {
 			Error::Client(ref err) => Some(&**err),
+			Error::UnsafeRpcCalled(ref err) => Some(err),
 			_ => None,
 		}
 	}
@@ -73,11 +79,12 @@ impl From<Error> for rpc::Error {
 				message: format!("{}", e),
 				data: None,
 			},
-			Error::InvalidCount { .. } => rpc::Error {
+			Error::InvalidCount { .. } | Error::ZeroCount => rpc::Error {
 				code: rpc::ErrorCode::ServerError(BASE_ERROR + 2),
 				message: format!("{}", e),
 				data: None,
 			},
+			Error::UnsafeRpcCalled(e) => e.into(),
 			e => errors::internal(e),
 		}
 	}
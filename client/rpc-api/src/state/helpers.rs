@@ -18,8 +18,9 @@
 
 //! Substrate state API helpers.
 
-use sp_core::Bytes;
+use sp_core::{Bytes, storage::{PrefixedStorageKey, StorageChangeSet, StorageKey}};
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 
 /// ReadProof struct returned by the RPC
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -30,3 +31,69 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// Keys of a child trie to include in a read proof
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildReadProofKeys {
+	/// Prefixed storage key of the child trie
+	pub storage_key: PrefixedStorageKey,
+	/// Keys of the child trie to prove
+	pub keys: Vec<StorageKey>,
+}
+
+/// Page of historical storage changes returned by the RPC
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageChangesPage<Hash> {
+	/// Changes of the keys, in block order
+	pub changes: Vec<StorageChangeSet<Hash>>,
+	/// First block that hasn't been queried yet, if the range doesn't end with this page
+	pub next: Option<Hash>,
+}
+
+/// Spans and events recorded while re-executing a block, returned by the RPC
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTrace<Hash> {
+	/// Hash of the block that was re-executed
+	pub block_hash: Hash,
+	/// Targets the spans and events were filtered with
+	pub targets: String,
+	/// Spans created during the execution, in order of creation
+	pub spans: Vec<TraceSpan>,
+	/// Events emitted during the execution, storage accesses included, in order of emission
+	pub events: Vec<TraceEvent>,
+}
+
+/// Span recorded while re-executing a block
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceSpan {
+	/// Identifier of the span, unique within the trace
+	pub id: u64,
+	/// Identifier of the span this one was created in, if any
+	pub parent_id: Option<u64>,
+	/// Name of the span
+	pub name: String,
+	/// Target of the span
+	pub target: String,
+	/// Line of the source file the span was created at
+	pub line: u32,
+	/// Time spent inside of the span, in nanoseconds
+	pub overall_time: u64,
+	/// Fields of the span
+	pub values: BTreeMap<String, String>,
+}
+
+/// Event recorded while re-executing a block
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceEvent {
+	/// Target of the event, `state` for storage accesses
+	pub target: String,
+	/// Identifier of the span the event was emitted in, if any
+	pub parent_id: Option<u64>,
+	/// Fields of the event
+	pub values: BTreeMap<String, String>,
+}
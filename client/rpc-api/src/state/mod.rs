@@ -31,7 +31,7 @@ use sp_version::RuntimeVersion;
 use self::error::FutureResult;
 
 pub use self::gen_client::Client as StateClient;
-pub use self::helpers::ReadProof;
+pub use self::helpers::{BlockTrace, ChildReadProofKeys, ReadProof, StorageChangesPage, TraceEvent, TraceSpan};
 
 /// Substrate state API
 #[rpc]
@@ -77,9 +77,17 @@ pub trait StateApi<Hash> {
 	fn storage_size(&self, key: StorageKey, hash: Option<Hash>) -> FutureResult<Option<u64>>;
 
 	/// Returns the runtime metadata as an opaque blob.
+	///
+	/// The metadata of blocks whose runtime can't be instantiated any more is the one stored
+	/// when their runtime was enacted, if any.
 	#[rpc(name = "state_getMetadata")]
 	fn metadata(&self, hash: Option<Hash>) -> FutureResult<Bytes>;
 
+	/// Returns the metadata of the runtime with the given `spec_version` as an opaque blob, if
+	/// it has been stored when the runtime was enacted.
+	#[rpc(name = "state_getMetadataAtVersion")]
+	fn metadata_at_version(&self, spec_version: u32) -> FutureResult<Option<Bytes>>;
+
 	/// Get the runtime version.
 	#[rpc(name = "state_getRuntimeVersion", alias("chain_getRuntimeVersion"))]
 	fn runtime_version(&self, hash: Option<Hash>) -> FutureResult<RuntimeVersion>;
@@ -96,6 +104,21 @@ pub trait StateApi<Hash> {
 		hash: Option<Hash>
 	) -> FutureResult<Vec<StorageChangeSet<Hash>>>;
 
+	/// Query historical storage entries (by key) of the blocks from the second parameter to the
+	/// third one, at most `count` blocks with changes at a time.
+	///
+	/// The next page is queried by passing the returned `next` block as the second parameter.
+	/// Like with `state_queryStorage`, the first result of a page contains the state of storage
+	/// for all keys.
+	#[rpc(name = "state_queryStorageEntries")]
+	fn query_storage_entries(
+		&self,
+		keys: Vec<StorageKey>,
+		from: Hash,
+		to: Option<Hash>,
+		count: u32,
+	) -> FutureResult<StorageChangesPage<Hash>>;
+
 	/// Query storage entries (by key) starting at block hash given as the second parameter.
 	#[rpc(name = "state_queryStorageAt")]
 	fn query_storage_at(
@@ -105,8 +128,33 @@ pub trait StateApi<Hash> {
 	) -> FutureResult<Vec<StorageChangeSet<Hash>>>;
 
 	/// Returns proof of storage entries at a specific block's state.
+	///
+	/// Entries of child tries can be proven along with the ones of the top trie, in the same
+	/// proof, by passing their keys in `child_keys`.
 	#[rpc(name = "state_getReadProof")]
-	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> FutureResult<ReadProof<Hash>>;
+	fn read_proof(
+		&self,
+		keys: Vec<StorageKey>,
+		hash: Option<Hash>,
+		child_keys: Option<Vec<ChildReadProofKeys>>,
+	) -> FutureResult<ReadProof<Hash>>;
+
+	/// Re-executes the given block on top of the state of its parent, and returns the spans and
+	/// events emitted in the meantime, storage accesses included.
+	///
+	/// `targets` is a comma separated list of the targets to record, for example
+	/// `pallet,state=trace`. It defaults to the targets of the runtime and of the storage.
+	/// `storage_keys` is a comma separated list of hex-encoded key prefixes. If it is given, only
+	/// the storage accesses to keys starting with one of them are returned.
+	///
+	/// This RPC is unsafe, as re-executing a block is expensive.
+	#[rpc(name = "state_traceBlock")]
+	fn trace_block(
+		&self,
+		block: Hash,
+		targets: Option<String>,
+		storage_keys: Option<String>,
+	) -> FutureResult<BlockTrace<Hash>>;
 
 	/// New runtime version subscription
 	#[pubsub(
@@ -137,4 +185,19 @@ pub trait StateApi<Hash> {
 	fn unsubscribe_storage(
 		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
 	) -> RpcResult<bool>;
+
+	/// New subscription to the keys starting with one of the given prefixes.
+	///
+	/// Every notification holds all the changes made by a block to these keys. The current values
+	/// aren't sent when subscribing.
+	#[pubsub(subscription = "state_storagePrefix", subscribe, name = "state_subscribeStoragePrefix")]
+	fn subscribe_storage_prefix(
+		&self, metadata: Self::Metadata, subscriber: Subscriber<StorageChangeSet<Hash>>, prefixes: Vec<StorageKey>
+	);
+
+	/// Unsubscribe from storage prefix subscription
+	#[pubsub(subscription = "state_storagePrefix", unsubscribe, name = "state_unsubscribeStoragePrefix")]
+	fn unsubscribe_storage_prefix(
+		&self, metadata: Option<Self::Metadata>, id: SubscriptionId
+	) -> RpcResult<bool>;
 }
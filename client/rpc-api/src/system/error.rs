@@ -32,6 +32,15 @@ pub enum Error {
 	NotHealthy(Health),
 	/// Peer argument is malformatted.
 	MalformattedPeerArg(String),
+	/// IP range argument is malformatted.
+	#[display(fmt = "Invalid IP range: {}", _0)]
+	MalformattedIpRange(String),
+	/// The traffic dump couldn't be started.
+	#[display(fmt = "Failed to start the traffic dump: {}", _0)]
+	TrafficDumpFailed(String),
+	/// The filter of the logger couldn't be changed.
+	#[display(fmt = "Failed to change the log filter: {}", _0)]
+	LogFilterFailed(String),
 }
 
 impl std::error::Error for Error {}
@@ -51,7 +60,22 @@ impl From<Error> for rpc::Error {
 				code :rpc::ErrorCode::ServerError(BASE_ERROR + 2),
 				message: e.clone(),
 				data: None,
-			}
+			},
+			Error::MalformattedIpRange(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 3),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::TrafficDumpFailed(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 4),
+				message: format!("{}", e),
+				data: None,
+			},
+			Error::LogFilterFailed(_) => rpc::Error {
+				code: rpc::ErrorCode::ServerError(BASE_ERROR + 5),
+				message: format!("{}", e),
+				data: None,
+			},
 		}
 	}
 }
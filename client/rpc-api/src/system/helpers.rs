@@ -73,6 +73,93 @@ pub struct PeerInfo<Hash, Number> {
 	pub best_hash: Hash,
 	/// Peer best block number
 	pub best_number: Number,
+	/// Peer finalized block hash, if it reported it
+	pub finalized_hash: Option<Hash>,
+	/// Peer finalized block number, if it reported it
+	pub finalized_number: Option<Number>,
+	/// Notification protocols for which a substream is open with the peer
+	pub notification_protocols: Vec<String>,
+	/// Statistics about the block requests sent to the peer
+	pub requests: PeerRequestStats,
+}
+
+/// Statistics about the block requests sent to a peer
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerRequestStats {
+	/// Number of requests sent
+	pub sent: u64,
+	/// Number of requests answered
+	pub answered: u64,
+	/// Number of requests that timed out
+	pub timed_out: u64,
+	/// Average time taken by the peer to answer, in milliseconds
+	pub average_latency_ms: Option<u64>,
+}
+
+/// Filter applied to the IP address of incoming connections.
+///
+/// Ranges are expressed in CIDR notation, for example `198.51.100.0/24` or `fd00::/8`.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpFilter {
+	/// If set, only connections coming from one of these ranges are accepted.
+	pub allow: Option<Vec<String>>,
+	/// Connections coming from one of these ranges are rejected.
+	pub deny: Vec<String>,
+}
+
+/// Recording of the messages exchanged with other nodes.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficDump {
+	/// File on the node's filesystem the messages are written to.
+	pub path: String,
+	/// Whether the payload of the messages is included.
+	pub include_payloads: bool,
+}
+
+/// State of the syncing of the node.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncState<Number> {
+	/// Height of the best block when the node started.
+	pub starting_block: Number,
+	/// Height of the current best block of the node.
+	pub current_block: Number,
+	/// Height of the highest block seen from our peers, if any.
+	pub highest_block: Option<Number>,
+	/// Download of the bodies of old blocks in progress, if any.
+	pub gap_sync: Option<GapSync<Number>>,
+}
+
+/// Download of the bodies of the blocks below the state imported by fast sync.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GapSync<Number> {
+	/// First block of the gap.
+	pub start: Number,
+	/// Latest block whose body has been imported.
+	pub current: Number,
+	/// Last block of the gap.
+	pub end: Number,
+}
+
+/// Status of the pruning of the states of the blocks.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruningStatus {
+	/// Number of finalized blocks whose states are kept, `None` for archive nodes.
+	pub window: Option<u64>,
+	/// Height of the oldest block whose state is available.
+	pub oldest_block: u64,
+	/// Height of the last block whose state was canonicalized, if any.
+	pub last_canonicalized: Option<u64>,
+	/// Number of blocks whose states aren't canonicalized yet.
+	pub non_canonical: u64,
+	/// Number of blocks past the window whose states are still to be pruned, e.g. after the
+	/// window was reduced.
+	pub pending_prunes: u64,
 }
 
 /// The role the node is running as
@@ -113,8 +200,54 @@ mod tests {
 				protocol_version: 2,
 				best_hash: 5u32,
 				best_number: 6u32,
+				finalized_hash: Some(3u32),
+				finalized_number: Some(4u32),
+				notification_protocols: vec!["/a/1".into()],
+				requests: PeerRequestStats {
+					sent: 10,
+					answered: 8,
+					timed_out: 1,
+					average_latency_ms: Some(120),
+				},
+			}).unwrap(),
+			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6,"#.to_owned() +
+			r#""finalizedHash":3,"finalizedNumber":4,"notificationProtocols":["/a/1"],"# +
+			r#""requests":{"sent":10,"answered":8,"timedOut":1,"averageLatencyMs":120}}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_ip_filter() {
+		assert_eq!(
+			::serde_json::to_string(&IpFilter {
+				allow: None,
+				deny: vec!["198.51.100.0/24".into()],
+			}).unwrap(),
+			r#"{"allow":null,"deny":["198.51.100.0/24"]}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_traffic_dump() {
+		assert_eq!(
+			::serde_json::to_string(&TrafficDump {
+				path: "/tmp/traffic".into(),
+				include_payloads: true,
+			}).unwrap(),
+			r#"{"path":"/tmp/traffic","includePayloads":true}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_sync_state() {
+		assert_eq!(
+			::serde_json::to_string(&SyncState {
+				starting_block: 0u32,
+				current_block: 100,
+				highest_block: Some(200),
+				gap_sync: Some(GapSync { start: 1, current: 30, end: 80 }),
 			}).unwrap(),
-			r#"{"peerId":"2","roles":"a","protocolVersion":2,"bestHash":5,"bestNumber":6}"#,
+			r#"{"startingBlock":0,"currentBlock":100,"highestBlock":200,"gapSync":{"start":1,"current":30,"end":80}}"#,
 		);
 	}
 }
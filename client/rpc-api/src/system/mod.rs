@@ -27,7 +27,9 @@ use futures::{future::BoxFuture, compat::Compat};
 
 use self::error::Result as SystemResult;
 
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{
+	SystemInfo, Health, PeerInfo, PeerRequestStats, NodeRole, IpFilter, TrafficDump, SyncState, GapSync, PruningStatus,
+};
 pub use self::gen_client::Client as SystemClient;
 
 /// Substrate system RPC API
@@ -100,7 +102,72 @@ pub trait SystemApi<Hash, Number> {
 	fn system_remove_reserved_peer(&self, peer_id: String)
 		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
 
+	/// Returns the filter applied to the IP address of incoming connections.
+	#[rpc(name = "system_ipFilter", returns = "IpFilter")]
+	fn system_ip_filter(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<IpFilter>>>;
+
+	/// Replaces the filter applied to the IP address of incoming connections. Returns an error
+	/// if one of the ranges isn't valid CIDR notation.
+	///
+	/// The new filter only applies to connections opened afterwards.
+	#[rpc(name = "system_setIpFilter", returns = "()")]
+	fn system_set_ip_filter(&self, filter: IpFilter)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Returns the recording of the messages exchanged with other nodes currently running, if
+	/// any.
+	#[rpc(name = "system_trafficDump", returns = "Option<TrafficDump>")]
+	fn system_traffic_dump(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Option<TrafficDump>>>>;
+
+	/// Starts recording the messages exchanged with other nodes to a file, or stops if `null`
+	/// is passed. Returns an error if the file can't be opened.
+	#[rpc(name = "system_setTrafficDump", returns = "()")]
+	fn system_set_traffic_dump(&self, dump: Option<TrafficDump>)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Adds the given filter directives, with the syntax of `RUST_LOG`, e.g. `sync=debug`, to
+	/// the ones the logger of the node was started with, replacing the ones of a previous call.
+	#[rpc(name = "system_setLogFilter", returns = "()")]
+	fn system_set_log_filter(&self, directives: String)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Restores the filter directives the logger of the node was started with.
+	#[rpc(name = "system_resetLogFilter", returns = "()")]
+	fn system_reset_log_filter(&self)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
 	/// Returns the roles the node is running as.
 	#[rpc(name = "system_nodeRoles", returns = "Vec<NodeRole>")]
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>>;
+
+	/// Returns the block hashes pinned at given heights, ordered by height.
+	#[rpc(name = "system_pinnedBlocks", returns = "Vec<(Number, Hash)>")]
+	fn system_pinned_blocks(&self)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<Vec<(Number, Hash)>>>>;
+
+	/// Pins the hash of the block at the given height, replacing the one pinned there, if any.
+	///
+	/// Competing blocks at this height are rejected on import from now on. The blocks already
+	/// imported are left untouched, they can be removed by reverting the chain.
+	#[rpc(name = "system_pinBlock", returns = "()")]
+	fn system_pin_block(&self, number: Number, hash: Hash)
+		-> Compat<BoxFuture<'static, Result<(), jsonrpc_core::Error>>>;
+
+	/// Unpins the hash of the block at the given height. Returns `false` if there was none.
+	#[rpc(name = "system_unpinBlock", returns = "bool")]
+	fn system_unpin_block(&self, number: Number)
+		-> Compat<BoxFuture<'static, jsonrpc_core::Result<bool>>>;
+
+	/// Returns the state of the syncing of the node: starting block, current and highest
+	/// block, and the progress of the download of the bodies of old blocks, if any.
+	#[rpc(name = "system_syncState", returns = "SyncState<Number>")]
+	fn system_sync_state(&self) -> Receiver<SyncState<Number>>;
+
+	/// Returns the status of the pruning of the states of the blocks: the window, the oldest
+	/// block whose state is available and the blocks whose states are still to be pruned or
+	/// canonicalized. Returns `null` for nodes without states, e.g. light clients.
+	#[rpc(name = "system_pruningStatus", returns = "Option<PruningStatus>")]
+	fn system_pruning_status(&self) -> Receiver<Option<PruningStatus>>;
 }
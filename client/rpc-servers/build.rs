@@ -0,0 +1,9 @@
+fn main() {
+	// the server of the gateway and the client used by its tests are both generated from the schema.
+	#[cfg(feature = "grpc")]
+	tonic_build::configure()
+		.build_server(true)
+		.build_client(true)
+		.compile(&["src/schema/grpc.v1.proto"], &["src/schema"])
+		.unwrap();
+}
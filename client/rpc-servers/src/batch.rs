@@ -0,0 +1,153 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Execution of batch requests.
+//!
+//! The handlers of the calls of a batch would otherwise run one after the other on the thread of
+//! the server. The [`BatchExecutor`] middleware rejects the batches with too many calls, and
+//! runs the calls of the others concurrently on a pool of threads shared by the servers. Each
+//! call gets a response of its own, so a failing call doesn't affect the others.
+
+use std::{io, sync::Arc};
+use jsonrpc_core::{
+	Error, ErrorCode, FutureOutput, FutureResponse, Id, Metadata, Middleware, Output, Request, Response, Version,
+	futures::future::{self, Either, Future},
+};
+use rayon::prelude::*;
+
+/// JSON-RPC error code of the batches rejected because they have too many calls.
+pub const BATCH_TOO_LARGE_ERROR_CODE: i64 = -32008;
+
+fn batch_too_large(size: usize, max: usize) -> Error {
+	Error {
+		code: ErrorCode::ServerError(BATCH_TOO_LARGE_ERROR_CODE),
+		message: format!("Batch of {} calls exceeds the maximum of {} calls", size, max),
+		data: None,
+	}
+}
+
+/// Middleware limiting the size of the batches and executing their calls concurrently.
+///
+/// The default value doesn't limit anything, and executes the calls of the batches in order on
+/// the thread of the server.
+#[derive(Clone, Default)]
+pub struct BatchExecutor {
+	max_batch_size: Option<usize>,
+	pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl BatchExecutor {
+	/// Creates a middleware rejecting the batches of more than `max_batch_size` calls, and
+	/// executing the calls of the others on a pool of `threads` threads.
+	pub fn new(max_batch_size: Option<usize>, threads: usize) -> io::Result<Self> {
+		let pool = rayon::ThreadPoolBuilder::new()
+			.num_threads(threads)
+			.thread_name(|index| format!("rpc-batch-{}", index))
+			.build()
+			.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+		Ok(BatchExecutor { max_batch_size, pool: Some(Arc::new(pool)) })
+	}
+}
+
+impl<M: Metadata> Middleware<M> for BatchExecutor {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_request<F, X>(&self, request: Request, meta: M, next: F) -> Either<Self::Future, X> where
+		F: Fn(Request, M) -> X + Send + Sync,
+		X: Future<Item = Option<Response>, Error = ()> + Send + 'static,
+	{
+		let calls = match request {
+			Request::Batch(calls) => calls,
+			request => return Either::B(next(request, meta)),
+		};
+
+		if let Some(max) = self.max_batch_size.filter(|max| calls.len() > *max) {
+			let output = Output::from(Err(batch_too_large(calls.len(), max)), Id::Null, Some(Version::V2));
+			return Either::A(Box::new(future::ok(Some(Response::Single(output)))))
+		}
+
+		let calls = calls.into_iter().map(|call| (call, meta.clone())).collect::<Vec<_>>();
+		let responses = match &self.pool {
+			Some(pool) if calls.len() > 1 => pool.install(|| calls.into_par_iter()
+				.map(|(call, meta)| next(Request::Single(call), meta))
+				.collect::<Vec<_>>()
+			),
+			_ => calls.into_iter()
+				.map(|(call, meta)| next(Request::Single(call), meta))
+				.collect(),
+		};
+
+		// a call whose future fails gets no response, like a notification.
+		let responses = responses.into_iter()
+			.map(|response| response.then(|response| Ok::<_, ()>(response.ok().and_then(|r| r))));
+		Either::A(Box::new(future::join_all(responses).map(|responses| {
+			let outputs = responses.into_iter()
+				.filter_map(|response| match response {
+					Some(Response::Single(output)) => Some(output),
+					_ => None,
+				})
+				.collect::<Vec<_>>();
+			if outputs.is_empty() {
+				None
+			} else {
+				Some(Response::Batch(outputs))
+			}
+		})))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{MetaIoHandler, Value};
+
+	fn io(max_batch_size: Option<usize>) -> MetaIoHandler<(), BatchExecutor> {
+		let mut io = MetaIoHandler::with_middleware(BatchExecutor::new(max_batch_size, 2).unwrap());
+		io.add_method("ok", |_| Ok(Value::Bool(true)));
+		io.add_method("fail", |_| Err(Error::internal_error()));
+		io
+	}
+
+	fn batch(methods: &[&str]) -> String {
+		let calls = methods.iter().enumerate()
+			.map(|(id, method)| format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":{}}}"#, method, id))
+			.collect::<Vec<_>>();
+		format!("[{}]", calls.join(","))
+	}
+
+	#[test]
+	fn calls_of_batches_get_responses_of_their_own() {
+		let response = io(None).handle_request_sync(&batch(&["ok", "fail", "ok"]), ()).unwrap();
+
+		assert_eq!(
+			response,
+			r#"[{"jsonrpc":"2.0","result":true,"id":0},"#.to_owned() +
+				r#"{"jsonrpc":"2.0","error":{"code":-32603,"message":"Internal error"},"id":1},"# +
+				r#"{"jsonrpc":"2.0","result":true,"id":2}]"#,
+		);
+	}
+
+	#[test]
+	fn large_batches_are_rejected() {
+		let io = io(Some(2));
+
+		assert!(io.handle_request_sync(&batch(&["ok", "ok", "ok"]), ()).unwrap().contains("-32008"));
+		assert!(!io.handle_request_sync(&batch(&["ok", "ok"]), ()).unwrap().contains("-32008"));
+	}
+}
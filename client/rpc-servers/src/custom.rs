@@ -0,0 +1,116 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Middleware provided by the node.
+//!
+//! Nodes can wrap the calls to the RPC handlers in middleware of their own, to check
+//! authentication tokens or log the requests for instance, by implementing [`RpcCallMiddleware`].
+//! It is called after the standard middlewares, so the calls it sees have already been filtered
+//! and rate limited.
+
+use std::sync::Arc;
+use jsonrpc_core::{
+	Call, FutureOutput, FutureResponse, Metadata, Middleware, Output,
+	futures::future::{Either, Future},
+};
+
+/// Middleware that can be provided by the node.
+///
+/// Unlike [`jsonrpc_core::Middleware`], it can be used as a trait object.
+pub trait RpcCallMiddleware<M>: Send + Sync + 'static {
+	/// Handles `call`, made with `meta`. Calling `next` hands the call over to the RPC handlers.
+	fn on_call(&self, call: Call, meta: M, next: &dyn Fn(Call, M) -> FutureOutput) -> FutureOutput;
+}
+
+/// Middleware calling the [`RpcCallMiddleware`] provided by the node, if any.
+///
+/// The default value lets every call through.
+pub struct CustomMiddleware<M>(Option<Arc<dyn RpcCallMiddleware<M>>>);
+
+impl<M> CustomMiddleware<M> {
+	/// Creates a middleware calling `middleware`.
+	pub fn new(middleware: Option<Arc<dyn RpcCallMiddleware<M>>>) -> Self {
+		CustomMiddleware(middleware)
+	}
+}
+
+impl<M> Clone for CustomMiddleware<M> {
+	fn clone(&self) -> Self {
+		CustomMiddleware(self.0.clone())
+	}
+}
+
+impl<M> Default for CustomMiddleware<M> {
+	fn default() -> Self {
+		CustomMiddleware(None)
+	}
+}
+
+impl<M: Metadata> Middleware<M> for CustomMiddleware<M> {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		match &self.0 {
+			Some(middleware) => Either::A(middleware.on_call(
+				call,
+				meta,
+				&|call, meta| Box::new(next(call, meta)) as FutureOutput,
+			)),
+			None => Either::B(next(call, meta)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{Error, MetaIoHandler, Value, futures::future};
+
+	/// Answers the calls to `secret` itself.
+	struct HideSecret;
+
+	impl RpcCallMiddleware<()> for HideSecret {
+		fn on_call(&self, call: Call, meta: (), next: &dyn Fn(Call, ()) -> FutureOutput) -> FutureOutput {
+			match call {
+				Call::MethodCall(call) if call.method == "secret" => Box::new(future::ok(Some(
+					Output::from(Err(Error::invalid_request()), call.id, call.jsonrpc)
+				))),
+				call => next(call, meta),
+			}
+		}
+	}
+
+	fn call(io: &MetaIoHandler<(), CustomMiddleware<()>>, method: &str) -> String {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		io.handle_request_sync(&request, ()).unwrap()
+	}
+
+	#[test]
+	fn calls_go_through_the_custom_middleware() {
+		let mut io = MetaIoHandler::with_middleware(CustomMiddleware::new(Some(Arc::new(HideSecret))));
+		io.add_method("public", |_| Ok(Value::Bool(true)));
+		io.add_method("secret", |_| Ok(Value::Bool(true)));
+
+		assert_eq!(call(&io, "public"), r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+		assert!(call(&io, "secret").contains("-32600"));
+	}
+}
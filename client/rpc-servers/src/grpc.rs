@@ -0,0 +1,530 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! gRPC gateway to the core RPC APIs.
+//!
+//! The gateway serves the chain, state and author services of `src/schema/grpc.v1.proto`. Their
+//! calls are turned into JSON-RPC calls to the same [`RpcHandler`] as the other servers, so they
+//! go through the same middlewares, and the results are turned back into protobuf messages.
+//! Subscriptions are made on a session of their own, which is dropped along with the stream of
+//! the gRPC call.
+
+use std::{io, net::SocketAddr, pin::Pin, sync::Arc, thread};
+use futures::{
+	channel::{mpsc, oneshot}, compat::{Future01CompatExt, Stream01CompatExt},
+	FutureExt, SinkExt, Stream, StreamExt,
+};
+use jsonrpc_core::{ErrorCode, Output};
+use log::{debug, error};
+use pubsub::PubSubMetadata;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use sp_core::Bytes;
+use tonic::{Code, Request, Response, Status, transport::Server};
+
+use crate::{RpcHandler, RATE_LIMITED_ERROR_CODE};
+
+/// Protobuf messages and gRPC services of the gateway.
+pub mod schema {
+	include!(concat!(env!("OUT_DIR"), "/grpc.v1.rs"));
+}
+
+use self::schema::{
+	author_server::{Author, AuthorServer}, chain_server::{Chain, ChainServer},
+	state_server::{State, StateServer}, block_number_request, storage_change, storage_response,
+	transaction_status, BlockHashResponse, BlockNumberRequest, BlockRequest, Empty, Extrinsic,
+	ExtrinsicHash, Header, HeaderResponse, MetadataResponse, Peers, RuntimeApi, RuntimeVersion,
+	StorageChange, StorageChangeSet, StorageKeys, StorageRequest, StorageResponse, TransactionStatus,
+};
+
+/// Maximum number of notifications of a subscription waiting to be sent.
+const SUBSCRIPTION_BUFFER: usize = 256;
+
+/// Stream of the notifications of a subscription.
+type Notifications<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + Sync>>;
+
+/// Handle of a running gRPC server, which stops when it is dropped.
+pub struct GrpcServer {
+	address: SocketAddr,
+	shutdown: Option<oneshot::Sender<()>>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+impl GrpcServer {
+	/// Returns the address the server is listening on.
+	pub fn address(&self) -> &SocketAddr {
+		&self.address
+	}
+}
+
+impl Drop for GrpcServer {
+	fn drop(&mut self) {
+		if let Some(shutdown) = self.shutdown.take() {
+			let _ = shutdown.send(());
+		}
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Start the gRPC gateway listening on given address.
+///
+/// **Note**: Only available with the `grpc` feature, if `not(target_os = "unknown")`.
+pub fn start_grpc<M>(addr: &SocketAddr, io: RpcHandler<M>) -> io::Result<GrpcServer> where
+	M: PubSubMetadata + Default + From<jsonrpc_core::futures::sync::mpsc::Sender<String>> + Send + Sync,
+{
+	// bind right away to report the errors to the caller.
+	let listener = std::net::TcpListener::bind(addr)?;
+	listener.set_nonblocking(true)?;
+	let address = listener.local_addr()?;
+	let mut runtime = tokio::runtime::Builder::new()
+		.threaded_scheduler()
+		.enable_all()
+		.thread_name("grpc-worker")
+		.build()?;
+
+	let gateway = Gateway { io: Arc::new(io) };
+	let (shutdown, on_shutdown) = oneshot::channel();
+	let thread = thread::Builder::new().name("grpc-server".into()).spawn(move || {
+		let result = runtime.block_on(async move {
+			let mut listener = tokio::net::TcpListener::from_std(listener)?;
+			Server::builder()
+				.add_service(ChainServer::new(gateway.clone()))
+				.add_service(StateServer::new(gateway.clone()))
+				.add_service(AuthorServer::new(gateway))
+				.serve_with_incoming_shutdown(listener.incoming(), on_shutdown.map(drop))
+				.await
+				.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+		});
+		if let Err(e) = result {
+			error!("gRPC server failed: {}", e);
+		}
+	})?;
+
+	Ok(GrpcServer { address, shutdown: Some(shutdown), thread: Some(thread) })
+}
+
+/// Turns the error of a JSON-RPC call into a gRPC status.
+fn status(error: jsonrpc_core::Error) -> Status {
+	let code = match error.code {
+		ErrorCode::ParseError | ErrorCode::InvalidRequest | ErrorCode::InvalidParams => Code::InvalidArgument,
+		ErrorCode::MethodNotFound => Code::Unimplemented,
+		ErrorCode::ServerError(RATE_LIMITED_ERROR_CODE) => Code::ResourceExhausted,
+		ErrorCode::InternalError | ErrorCode::ServerError(_) => Code::Internal,
+	};
+	Status::new(code, error.message)
+}
+
+fn parse<T: DeserializeOwned>(value: Value) -> Result<T, Status> {
+	serde_json::from_value(value).map_err(|e| Status::internal(format!("Unexpected RPC result: {}", e)))
+}
+
+/// Returns the hash of a block, `None` if it is empty, i.e. the best block.
+fn block_hash(hash: Vec<u8>) -> Option<Bytes> {
+	if hash.is_empty() {
+		None
+	} else {
+		Some(Bytes(hash))
+	}
+}
+
+fn number(number: &str) -> Result<u64, Status> {
+	u64::from_str_radix(number.trim_start_matches("0x"), 16)
+		.map_err(|e| Status::internal(format!("Unexpected block number: {}", e)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonHeader {
+	parent_hash: Bytes,
+	number: String,
+	state_root: Bytes,
+	extrinsics_root: Bytes,
+	digest: JsonDigest,
+}
+
+#[derive(Deserialize)]
+struct JsonDigest {
+	logs: Vec<Bytes>,
+}
+
+impl JsonHeader {
+	fn into_header(self) -> Result<Header, Status> {
+		Ok(Header {
+			parent_hash: self.parent_hash.0,
+			number: number(&self.number)?,
+			state_root: self.state_root.0,
+			extrinsics_root: self.extrinsics_root.0,
+			digest_logs: self.digest.logs.into_iter().map(|log| log.0).collect(),
+		})
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonRuntimeVersion {
+	spec_name: String,
+	impl_name: String,
+	authoring_version: u32,
+	spec_version: u32,
+	impl_version: u32,
+	#[serde(default)]
+	transaction_version: u32,
+	apis: Vec<(Bytes, u32)>,
+}
+
+#[derive(Deserialize)]
+struct JsonStorageChangeSet {
+	block: Bytes,
+	changes: Vec<(Bytes, Option<Bytes>)>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum JsonTransactionStatus {
+	Future,
+	Ready,
+	Broadcast(Vec<String>),
+	InBlock(Bytes),
+	Retracted(Bytes),
+	FinalityTimeout(Bytes),
+	Finalized(Bytes),
+	Usurped(Bytes),
+	Dropped,
+	Invalid,
+}
+
+impl From<JsonTransactionStatus> for TransactionStatus {
+	fn from(status: JsonTransactionStatus) -> Self {
+		use transaction_status::Status;
+
+		let status = match status {
+			JsonTransactionStatus::Future => Status::Future(Empty {}),
+			JsonTransactionStatus::Ready => Status::Ready(Empty {}),
+			JsonTransactionStatus::Broadcast(peers) => Status::Broadcast(Peers { peers }),
+			JsonTransactionStatus::InBlock(hash) => Status::InBlock(hash.0),
+			JsonTransactionStatus::Retracted(hash) => Status::Retracted(hash.0),
+			JsonTransactionStatus::FinalityTimeout(hash) => Status::FinalityTimeout(hash.0),
+			JsonTransactionStatus::Finalized(hash) => Status::Finalized(hash.0),
+			JsonTransactionStatus::Usurped(hash) => Status::Usurped(hash.0),
+			JsonTransactionStatus::Dropped => Status::Dropped(Empty {}),
+			JsonTransactionStatus::Invalid => Status::Invalid(Empty {}),
+		};
+		TransactionStatus { status: Some(status) }
+	}
+}
+
+/// Implementation of the gRPC services making JSON-RPC calls to a handler.
+struct Gateway<M: PubSubMetadata> {
+	io: Arc<RpcHandler<M>>,
+}
+
+impl<M: PubSubMetadata> Clone for Gateway<M> {
+	fn clone(&self) -> Self {
+		Gateway { io: self.io.clone() }
+	}
+}
+
+impl<M> Gateway<M> where
+	M: PubSubMetadata + Default + From<jsonrpc_core::futures::sync::mpsc::Sender<String>> + Send + Sync,
+{
+	async fn request(&self, method: &str, params: Value, meta: M) -> Result<Value, Status> {
+		let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 0 });
+		let response = self.io.handle_request(&request.to_string(), meta).compat().await
+			.ok()
+			.and_then(|response| response)
+			.ok_or_else(|| Status::internal("The RPC call got no response"))?;
+
+		match serde_json::from_str(&response) {
+			Ok(Output::Success(success)) => Ok(success.result),
+			Ok(Output::Failure(failure)) => Err(status(failure.error)),
+			Err(e) => Err(Status::internal(format!("Invalid RPC response: {}", e))),
+		}
+	}
+
+	/// Calls the RPC `method` with `params`.
+	async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Status> {
+		parse(self.request(method, params, M::default()).await?)
+	}
+
+	/// Subscribes with the RPC `method` and `params`, and returns the notifications turned into
+	/// protobuf messages by `convert`.
+	async fn subscribe<T, R, F>(&self, method: &str, params: Value, convert: F) -> Result<Notifications<R>, Status> where
+		T: DeserializeOwned,
+		R: Send + 'static,
+		F: Fn(T) -> Result<R, Status> + Send + 'static,
+	{
+		let (sender, receiver) = jsonrpc_core::futures::sync::mpsc::channel(SUBSCRIPTION_BUFFER);
+		let meta = M::from(sender);
+		self.request(method, params, meta.clone()).await?;
+
+		// the stream of the call is `Sync` as required by tonic, unlike the receiver of the
+		// session. dropping it makes the forwarding stop and drop the session, which cancels
+		// the subscription.
+		let (mut forward, notifications) = mpsc::channel(SUBSCRIPTION_BUFFER);
+		let mut receiver = receiver.compat();
+		tokio::spawn(async move {
+			let _session = meta;
+			while let Some(Ok(notification)) = receiver.next().await {
+				let mut notification: Value = match serde_json::from_str(&notification) {
+					Ok(notification) => notification,
+					Err(e) => {
+						debug!("Invalid RPC notification: {}", e);
+						continue
+					},
+				};
+				let result = parse(notification["params"]["result"].take()).and_then(&convert);
+				if forward.send(result).await.is_err() {
+					break
+				}
+			}
+		});
+
+		Ok(Box::pin(notifications))
+	}
+}
+
+#[tonic::async_trait]
+impl<M> Chain for Gateway<M> where
+	M: PubSubMetadata + Default + From<jsonrpc_core::futures::sync::mpsc::Sender<String>> + Send + Sync,
+{
+	async fn get_block_hash(
+		&self,
+		request: Request<BlockNumberRequest>,
+	) -> Result<Response<BlockHashResponse>, Status> {
+		let number = request.into_inner().block.map(|block| match block {
+			block_number_request::Block::Number(number) => number,
+		});
+		let hash: Option<Bytes> = self.call("chain_getBlockHash", json!([number])).await?;
+		Ok(Response::new(BlockHashResponse { hash: hash.map(|hash| hash.0).unwrap_or_default() }))
+	}
+
+	async fn get_finalized_head(&self, _: Request<Empty>) -> Result<Response<BlockHashResponse>, Status> {
+		let hash: Bytes = self.call("chain_getFinalizedHead", json!([])).await?;
+		Ok(Response::new(BlockHashResponse { hash: hash.0 }))
+	}
+
+	async fn get_header(&self, request: Request<BlockRequest>) -> Result<Response<HeaderResponse>, Status> {
+		let hash = block_hash(request.into_inner().hash);
+		let header: Option<JsonHeader> = self.call("chain_getHeader", json!([hash])).await?;
+		Ok(Response::new(HeaderResponse { header: header.map(JsonHeader::into_header).transpose()? }))
+	}
+
+	type SubscribeNewHeadsStream = Notifications<Header>;
+
+	async fn subscribe_new_heads(
+		&self,
+		_: Request<Empty>,
+	) -> Result<Response<Self::SubscribeNewHeadsStream>, Status> {
+		self.subscribe("chain_subscribeNewHeads", json!([]), JsonHeader::into_header).await.map(Response::new)
+	}
+
+	type SubscribeFinalizedHeadsStream = Notifications<Header>;
+
+	async fn subscribe_finalized_heads(
+		&self,
+		_: Request<Empty>,
+	) -> Result<Response<Self::SubscribeFinalizedHeadsStream>, Status> {
+		self.subscribe("chain_subscribeFinalizedHeads", json!([]), JsonHeader::into_header).await.map(Response::new)
+	}
+}
+
+#[tonic::async_trait]
+impl<M> State for Gateway<M> where
+	M: PubSubMetadata + Default + From<jsonrpc_core::futures::sync::mpsc::Sender<String>> + Send + Sync,
+{
+	async fn get_storage(&self, request: Request<StorageRequest>) -> Result<Response<StorageResponse>, Status> {
+		let StorageRequest { key, block_hash: hash } = request.into_inner();
+		let data: Option<Bytes> = self.call("state_getStorage", json!([Bytes(key), block_hash(hash)])).await?;
+		Ok(Response::new(StorageResponse { value: data.map(|data| storage_response::Value::Data(data.0)) }))
+	}
+
+	async fn get_metadata(&self, request: Request<BlockRequest>) -> Result<Response<MetadataResponse>, Status> {
+		let hash = block_hash(request.into_inner().hash);
+		let metadata: Bytes = self.call("state_getMetadata", json!([hash])).await?;
+		Ok(Response::new(MetadataResponse { metadata: metadata.0 }))
+	}
+
+	async fn get_runtime_version(&self, request: Request<BlockRequest>) -> Result<Response<RuntimeVersion>, Status> {
+		let hash = block_hash(request.into_inner().hash);
+		let version: JsonRuntimeVersion = self.call("state_getRuntimeVersion", json!([hash])).await?;
+		Ok(Response::new(RuntimeVersion {
+			spec_name: version.spec_name,
+			impl_name: version.impl_name,
+			authoring_version: version.authoring_version,
+			spec_version: version.spec_version,
+			impl_version: version.impl_version,
+			transaction_version: version.transaction_version,
+			apis: version.apis.into_iter().map(|(id, version)| RuntimeApi { id: id.0, version }).collect(),
+		}))
+	}
+
+	type SubscribeStorageStream = Notifications<StorageChangeSet>;
+
+	async fn subscribe_storage(
+		&self,
+		request: Request<StorageKeys>,
+	) -> Result<Response<Self::SubscribeStorageStream>, Status> {
+		let keys = request.into_inner().keys;
+		let keys = if keys.is_empty() {
+			None
+		} else {
+			Some(keys.into_iter().map(Bytes).collect::<Vec<_>>())
+		};
+		self.subscribe("state_subscribeStorage", json!([keys]), |changes: JsonStorageChangeSet| Ok(StorageChangeSet {
+			block_hash: changes.block.0,
+			changes: changes.changes.into_iter()
+				.map(|(key, data)| StorageChange {
+					key: key.0,
+					value: data.map(|data| storage_change::Value::Data(data.0)),
+				})
+				.collect(),
+		})).await.map(Response::new)
+	}
+}
+
+#[tonic::async_trait]
+impl<M> Author for Gateway<M> where
+	M: PubSubMetadata + Default + From<jsonrpc_core::futures::sync::mpsc::Sender<String>> + Send + Sync,
+{
+	async fn submit_extrinsic(&self, request: Request<Extrinsic>) -> Result<Response<ExtrinsicHash>, Status> {
+		let extrinsic = Bytes(request.into_inner().extrinsic);
+		let hash: Bytes = self.call("author_submitExtrinsic", json!([extrinsic])).await?;
+		Ok(Response::new(ExtrinsicHash { hash: hash.0 }))
+	}
+
+	type SubmitAndWatchExtrinsicStream = Notifications<TransactionStatus>;
+
+	async fn submit_and_watch_extrinsic(
+		&self,
+		request: Request<Extrinsic>,
+	) -> Result<Response<Self::SubmitAndWatchExtrinsicStream>, Status> {
+		let extrinsic = Bytes(request.into_inner().extrinsic);
+		self.subscribe(
+			"author_submitAndWatchExtrinsic",
+			json!([extrinsic]),
+			|status: JsonTransactionStatus| Ok(status.into()),
+		).await.map(Response::new)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pubsub::Session;
+	use crate::{BatchExecutor, CallMetrics, CustomMiddleware, MethodFilter, RateLimiter, ResourceLimiter, Transport};
+
+	#[derive(Clone, Default)]
+	struct Meta(Option<Arc<Session>>);
+
+	impl jsonrpc_core::Metadata for Meta {}
+
+	impl PubSubMetadata for Meta {
+		fn session(&self) -> Option<Arc<Session>> {
+			self.0.clone()
+		}
+	}
+
+	impl From<jsonrpc_core::futures::sync::mpsc::Sender<String>> for Meta {
+		fn from(sender: jsonrpc_core::futures::sync::mpsc::Sender<String>) -> Self {
+			Meta(Some(Arc::new(Session::new(sender))))
+		}
+	}
+
+	#[test]
+	fn calls_round_trip_through_the_gateway() {
+		let middleware = (
+			CallMetrics::new(None, Transport::Grpc),
+			MethodFilter::default(),
+			RateLimiter::default(),
+			(ResourceLimiter::default(), CustomMiddleware::new(None), BatchExecutor::default()),
+		);
+		let mut io: RpcHandler<Meta> = pubsub::PubSubHandler::new(jsonrpc_core::MetaIoHandler::with_middleware(middleware));
+		io.add_method("chain_getBlockHash", |params: jsonrpc_core::Params| -> jsonrpc_core::Result<Value> {
+			let (number,): (Option<u64>,) = params.parse()?;
+			Ok(match number {
+				Some(7) => json!("0x0707"),
+				_ => Value::Null,
+			})
+		});
+		let server = start_grpc(&"127.0.0.1:0".parse().unwrap(), io).unwrap();
+		let url = format!("http://{}", server.address());
+
+		let mut runtime = tokio::runtime::Builder::new()
+			.threaded_scheduler()
+			.enable_all()
+			.build()
+			.unwrap();
+		let (known, unknown, finalized) = runtime.block_on(async move {
+			let mut client = schema::chain_client::ChainClient::connect(url).await.unwrap();
+			let request = |number| BlockNumberRequest { block: Some(block_number_request::Block::Number(number)) };
+			let known = client.get_block_hash(request(7)).await.unwrap().into_inner();
+			let unknown = client.get_block_hash(request(8)).await.unwrap().into_inner();
+			let finalized = client.get_finalized_head(Empty {}).await.unwrap_err();
+			(known, unknown, finalized)
+		});
+
+		assert_eq!(known, BlockHashResponse { hash: vec![7, 7] });
+		assert_eq!(unknown, BlockHashResponse { hash: Vec::new() });
+		assert_eq!(finalized.code(), Code::Unimplemented);
+	}
+
+	#[test]
+	fn json_results_are_converted_to_messages() {
+		let header: JsonHeader = parse(json!({
+			"parentHash": "0x01",
+			"number": "0x1a",
+			"stateRoot": "0x02",
+			"extrinsicsRoot": "0x03",
+			"digest": { "logs": ["0x0400"] },
+		})).unwrap();
+		assert_eq!(header.into_header().unwrap(), Header {
+			parent_hash: vec![1],
+			number: 26,
+			state_root: vec![2],
+			extrinsics_root: vec![3],
+			digest_logs: vec![vec![4, 0]],
+		});
+
+		let status: JsonTransactionStatus = parse(json!({ "inBlock": "0x05" })).unwrap();
+		assert_eq!(
+			TransactionStatus::from(status).status,
+			Some(transaction_status::Status::InBlock(vec![5])),
+		);
+		let status: JsonTransactionStatus = parse(json!("ready")).unwrap();
+		assert_eq!(
+			TransactionStatus::from(status).status,
+			Some(transaction_status::Status::Ready(Empty {})),
+		);
+	}
+
+	#[test]
+	fn json_rpc_errors_are_converted_to_statuses() {
+		assert_eq!(status(jsonrpc_core::Error::method_not_found()).code(), Code::Unimplemented);
+		assert_eq!(status(jsonrpc_core::Error::invalid_params("bad")).code(), Code::InvalidArgument);
+		assert_eq!(
+			status(jsonrpc_core::Error {
+				code: ErrorCode::ServerError(RATE_LIMITED_ERROR_CODE),
+				message: "Too many calls".into(),
+				data: None,
+			}).code(),
+			Code::ResourceExhausted,
+		);
+	}
+}
@@ -20,31 +20,78 @@
 
 #![warn(missing_docs)]
 
+mod batch;
+mod custom;
+#[cfg(all(feature = "grpc", not(target_os = "unknown")))]
+mod grpc;
+mod limits;
+mod method_filter;
+mod metrics;
+mod rate_limit;
+
 use std::io;
-use jsonrpc_core::IoHandlerExtension;
+use jsonrpc_core::{IoHandlerExtension, MetaIoHandler};
 use log::error;
 use pubsub::PubSubMetadata;
 
-/// Maximal payload accepted by RPC servers.
-const MAX_PAYLOAD: usize = 15 * 1024 * 1024;
+/// Default maximal payload accepted by RPC servers.
+pub const MAX_PAYLOAD: usize = 15 * 1024 * 1024;
 
 /// Default maximum number of connections for WS RPC servers.
-const WS_MAX_CONNECTIONS: usize = 100;
+pub const WS_MAX_CONNECTIONS: usize = 100;
+
+/// Default maximal size of the responses of RPC servers.
+pub const MAX_RESPONSE_SIZE: usize = 15 * 1024 * 1024;
+
+/// Default maximum number of subscriptions per connection for WS RPC servers.
+pub const WS_MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 1024;
+
+/// Default maximum number of calls in a batch request.
+pub const MAX_BATCH_SIZE: usize = 1024;
+
+/// Number of threads executing the calls of batch requests.
+pub const BATCH_THREADS: usize = 4;
+
+/// The middlewares every call goes through, in order.
+///
+/// `jsonrpc_core` only combines up to four middlewares in a tuple, hence the nested one.
+pub type RpcMiddleware<M> = (
+	CallMetrics,
+	MethodFilter,
+	RateLimiter,
+	(ResourceLimiter, CustomMiddleware<M>, BatchExecutor),
+);
 
 /// The RPC IoHandler containing all requested APIs.
-pub type RpcHandler<T> = pubsub::PubSubHandler<T>;
+pub type RpcHandler<T> = pubsub::PubSubHandler<T, RpcMiddleware<T>>;
 
+pub use self::batch::{BatchExecutor, BATCH_TOO_LARGE_ERROR_CODE};
+pub use self::custom::{CustomMiddleware, RpcCallMiddleware};
+#[cfg(all(feature = "grpc", not(target_os = "unknown")))]
+pub use self::grpc::{schema as grpc_schema, start_grpc, GrpcServer};
 pub use self::inner::*;
-
-/// Construct rpc `IoHandler`
+pub use self::limits::{
+	ResourceLimiter, ResourceLimits, RESPONSE_TOO_LARGE_ERROR_CODE, TOO_MANY_SUBSCRIPTIONS_ERROR_CODE,
+};
+pub use self::method_filter::{MethodFilter, MethodGroups, RpcMethodFilter, RpcMethodGroup, RpcMethodGroups};
+pub use self::metrics::{CallMetrics, ServerMetrics, Transport};
+pub use self::rate_limit::{RateLimit, RateLimitConfig, RateLimiter, RATE_LIMITED_ERROR_CODE};
+
+/// Construct rpc `IoHandler`, whose calls go through `middleware`.
 pub fn rpc_handler<M: PubSubMetadata>(
-	extension: impl IoHandlerExtension<M>
+	extension: impl IoHandlerExtension<M>,
+	middleware: RpcMiddleware<M>,
 ) -> RpcHandler<M> {
-	let mut io = pubsub::PubSubHandler::default();
+	let call_metrics = middleware.0.clone();
+	let filter = middleware.1.clone();
+	let mut io = pubsub::PubSubHandler::new(MetaIoHandler::with_middleware(middleware));
 	extension.augment(&mut io);
 
 	// add an endpoint to list all available methods.
-	let mut methods = io.iter().map(|x| x.0.clone()).collect::<Vec<String>>();
+	let mut methods = io.iter()
+		.map(|x| x.0.clone())
+		.filter(|method| filter.is_allowed(method))
+		.collect::<Vec<String>>();
 	io.add_method("rpc_methods", {
 		methods.sort();
 		let methods = serde_json::to_value(&methods)
@@ -55,6 +102,7 @@ pub fn rpc_handler<M: PubSubMetadata>(
 			"methods": methods.clone(),
 		}))
 	});
+	call_metrics.register_methods(io.iter().map(|x| x.0.clone()));
 	io
 }
 
@@ -66,12 +114,15 @@ mod inner {
 	pub type HttpServer = http::Server;
 	/// Type alias for ws server
 	pub type WsServer = ws::Server;
+	/// Type alias for ipc server
+	pub type IpcServer = ipc::Server;
 
 	/// Start HTTP server listening on given address.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
 	pub fn start_http<M: pubsub::PubSubMetadata + Default>(
 		addr: &std::net::SocketAddr,
+		max_payload: Option<usize>,
 		cors: Option<&Vec<String>>,
 		io: RpcHandler<M>,
 	) -> io::Result<http::Server> {
@@ -85,21 +136,31 @@ mod inner {
 				http::RestApi::Unsecure
 			})
 			.cors(map_cors::<http::AccessControlAllowOrigin>(cors))
-			.max_request_body_size(MAX_PAYLOAD)
+			.max_request_body_size(max_payload.unwrap_or(MAX_PAYLOAD))
 			.start_http(addr)
 	}
 
-	/// Start WS server listening on given address.
+	/// Start WS server listening on given address, reporting the number of connections to
+	/// `metrics`.
 	///
 	/// **Note**: Only available if `not(target_os = "unknown")`.
 	pub fn start_ws<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
 		addr: &std::net::SocketAddr,
 		max_connections: Option<usize>,
+		max_payload: Option<usize>,
 		cors: Option<&Vec<String>>,
 		io: RpcHandler<M>,
+		metrics: Option<ServerMetrics>,
 	) -> io::Result<ws::Server> {
-		ws::ServerBuilder::with_meta_extractor(io, |context: &ws::RequestContext| context.sender().into())
-			.max_payload(MAX_PAYLOAD)
+		let extractor = move |context: &ws::RequestContext| {
+			let meta: M = context.sender().into();
+			if let (Some(metrics), Some(session)) = (&metrics, meta.session()) {
+				metrics.on_connection(&session);
+			}
+			meta
+		};
+		ws::ServerBuilder::with_meta_extractor(io, extractor)
+			.max_payload(max_payload.unwrap_or(MAX_PAYLOAD))
 			.max_connections(max_connections.unwrap_or(WS_MAX_CONNECTIONS))
 			.allowed_origins(map_cors(cors))
 			.allowed_hosts(hosts_filtering(cors.is_some()))
@@ -114,6 +175,27 @@ mod inner {
 			})
 	}
 
+	/// Start IPC server listening on the given Unix socket or, on Windows, named pipe. On Unix, the
+	/// socket can only be accessed by the user running the node.
+	///
+	/// **Note**: Only available if `not(target_os = "unknown")`.
+	pub fn start_ipc<M: pubsub::PubSubMetadata + From<jsonrpc_core::futures::sync::mpsc::Sender<String>>> (
+		path: &str,
+		io: RpcHandler<M>,
+	) -> io::Result<ipc::Server> {
+		let server = ipc::ServerBuilder::with_meta_extractor(io, |context: &ipc::RequestContext| {
+			context.sender.clone().into()
+		}).start(path)?;
+
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+		}
+
+		Ok(server)
+	}
+
 	fn map_cors<T: for<'a> From<&'a str>>(
 		cors: Option<&Vec<String>>
 	) -> http::DomainsValidation<T> {
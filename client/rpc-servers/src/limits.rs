@@ -0,0 +1,289 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Limits of the resources a single client can make the node use.
+//!
+//! The size of the requests and the number of connections are enforced by the servers
+//! themselves. The size of the responses and the number of subscriptions per connection are
+//! enforced by the [`ResourceLimiter`] middleware.
+//!
+//! Subscriptions are recognized by the name of their methods: a method whose name has a part
+//! starting with `subscribe` (e.g. `state_subscribeStorage` or `subscribe_newHead`) opens one,
+//! and a method whose name has a part starting with `unsubscribe` closes one if it returns
+//! `true`. Subscriptions opened by other methods, such as `author_submitAndWatchExtrinsic`,
+//! aren't counted.
+
+use std::{collections::HashMap, io, sync::Arc};
+use jsonrpc_core::{
+	Call, Error, ErrorCode, FutureOutput, FutureResponse, Middleware, Output, Value,
+	futures::future::{self, Either, Future},
+};
+use parking_lot::Mutex;
+use pubsub::{PubSubMetadata, Session};
+use crate::metrics::{ServerMetrics, Transport};
+
+/// JSON-RPC error code of the subscriptions rejected because the connection has too many.
+pub const TOO_MANY_SUBSCRIPTIONS_ERROR_CODE: i64 = -32006;
+
+/// JSON-RPC error code of the calls whose response exceeds the maximum size.
+pub const RESPONSE_TOO_LARGE_ERROR_CODE: i64 = -32007;
+
+/// Limits enforced by the [`ResourceLimiter`]. `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+	/// Maximum size in bytes of the result of a call.
+	pub max_response_size: Option<usize>,
+	/// Maximum number of subscriptions open at the same time on a connection.
+	pub max_subscriptions_per_connection: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubscriptionCall {
+	Subscribe,
+	Unsubscribe,
+}
+
+fn subscription_call(method: &str) -> Option<SubscriptionCall> {
+	if method.split('_').any(|part| part.starts_with("unsubscribe")) {
+		Some(SubscriptionCall::Unsubscribe)
+	} else if method.split('_').any(|part| part.starts_with("subscribe")) {
+		Some(SubscriptionCall::Subscribe)
+	} else {
+		None
+	}
+}
+
+fn connection_id(session: &Session) -> usize {
+	session as *const Session as usize
+}
+
+/// Writer counting the bytes written to it.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0 += buf.len();
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Middleware enforcing [`ResourceLimits`] and keeping track of the subscriptions.
+///
+/// The default value doesn't limit anything.
+#[derive(Clone, Default)]
+pub struct ResourceLimiter {
+	limits: ResourceLimits,
+	metrics: Option<ServerMetrics>,
+	transport: Transport,
+	/// Number of subscriptions open, indexed by connection.
+	subscriptions: Arc<Mutex<HashMap<usize, usize>>>,
+}
+
+impl ResourceLimiter {
+	/// Creates a middleware enforcing `limits` on the calls made over `transport`, and reporting
+	/// to `metrics`.
+	pub fn new(limits: ResourceLimits, metrics: Option<ServerMetrics>, transport: Transport) -> Self {
+		ResourceLimiter { limits, metrics, transport, subscriptions: Default::default() }
+	}
+
+	/// Counts a subscription opened on `session`, unless the connection already has the maximum
+	/// number of them. Returns false in that case.
+	fn try_subscribe(&self, session: &Arc<Session>) -> bool {
+		let id = connection_id(session);
+		let mut subscriptions = self.subscriptions.lock();
+		let count = subscriptions.entry(id).or_insert_with(|| {
+			// Forget the subscriptions of the connection once it is closed.
+			let subscriptions = self.subscriptions.clone();
+			let metrics = self.metrics.clone();
+			let transport = self.transport;
+			session.on_drop(move || {
+				let count = subscriptions.lock().remove(&id).unwrap_or(0);
+				if let Some(metrics) = metrics {
+					metrics.on_unsubscribe(transport, count);
+				}
+			});
+			0
+		});
+
+		if self.limits.max_subscriptions_per_connection.map_or(false, |max| *count >= max) {
+			return false
+		}
+		*count += 1;
+		if let Some(metrics) = &self.metrics {
+			metrics.on_subscribe(self.transport);
+		}
+		true
+	}
+
+	/// Forgets a subscription opened on `session`.
+	fn unsubscribe(&self, session: &Session) {
+		if let Some(count) = self.subscriptions.lock().get_mut(&connection_id(session)) {
+			if *count > 0 {
+				*count -= 1;
+				if let Some(metrics) = &self.metrics {
+					metrics.on_unsubscribe(self.transport, 1);
+				}
+			}
+		}
+	}
+
+	/// Replaces `output` with an error if its result is too large.
+	fn limit_response_size(&self, output: Output) -> Output {
+		let max = match self.limits.max_response_size {
+			Some(max) => max,
+			None => return output,
+		};
+
+		match output {
+			Output::Success(success) => {
+				let mut counter = ByteCounter(0);
+				let _ = serde_json::to_writer(&mut counter, &success.result);
+				if counter.0 > max {
+					Output::from(Err(response_too_large(counter.0, max)), success.id, success.jsonrpc)
+				} else {
+					Output::Success(success)
+				}
+			},
+			output => output,
+		}
+	}
+}
+
+fn too_many_subscriptions() -> Error {
+	Error {
+		code: ErrorCode::ServerError(TOO_MANY_SUBSCRIPTIONS_ERROR_CODE),
+		message: "Too many subscriptions on the connection".into(),
+		data: None,
+	}
+}
+
+fn response_too_large(size: usize, max: usize) -> Error {
+	Error {
+		code: ErrorCode::ServerError(RESPONSE_TOO_LARGE_ERROR_CODE),
+		message: format!("Response of {} bytes exceeds the maximum of {} bytes", size, max),
+		data: None,
+	}
+}
+
+impl<M: PubSubMetadata> Middleware<M> for ResourceLimiter {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let kind = match &call {
+			Call::MethodCall(call) => subscription_call(&call.method),
+			_ => None,
+		};
+		let session = match kind {
+			Some(_) => meta.session(),
+			None => None,
+		};
+
+		if let (Some(SubscriptionCall::Subscribe), Some(session)) = (kind, &session) {
+			if !self.try_subscribe(session) {
+				let output = match call {
+					Call::MethodCall(call) =>
+						Some(Output::from(Err(too_many_subscriptions()), call.id, call.jsonrpc)),
+					_ => None,
+				};
+				return Either::A(Box::new(future::ok(output)))
+			}
+		}
+
+		let this = self.clone();
+		Either::A(Box::new(next(call, meta).map(move |output| {
+			if let (Some(kind), Some(session)) = (kind, session) {
+				match (kind, &output) {
+					(SubscriptionCall::Subscribe, Some(Output::Success(_))) => {},
+					// the subscription counted beforehand hasn't been opened.
+					(SubscriptionCall::Subscribe, _) => this.unsubscribe(&session),
+					(SubscriptionCall::Unsubscribe, Some(Output::Success(success)))
+						if success.result == Value::Bool(true) => this.unsubscribe(&session),
+					(SubscriptionCall::Unsubscribe, _) => {},
+				}
+			}
+			output.map(|output| this.limit_response_size(output))
+		})))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{futures::sync::mpsc, MetaIoHandler};
+
+	#[derive(Clone, Default)]
+	struct Meta(Option<Arc<Session>>);
+
+	impl jsonrpc_core::Metadata for Meta {}
+
+	impl PubSubMetadata for Meta {
+		fn session(&self) -> Option<Arc<Session>> {
+			self.0.clone()
+		}
+	}
+
+	fn io(limits: ResourceLimits) -> MetaIoHandler<Meta, ResourceLimiter> {
+		let mut io = MetaIoHandler::with_middleware(ResourceLimiter::new(limits, None, Transport::Ws));
+		io.add_method("test_subscribe", |_| Ok(Value::from(1)));
+		io.add_method("test_unsubscribe", |_| Ok(Value::Bool(true)));
+		io.add_method("test_large", |_| Ok(Value::String("x".repeat(100))));
+		io
+	}
+
+	fn call(io: &MetaIoHandler<Meta, ResourceLimiter>, method: &str, meta: &Meta) -> String {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		io.handle_request_sync(&request, meta.clone()).unwrap()
+	}
+
+	#[test]
+	fn subscriptions_are_recognized_by_name() {
+		assert_eq!(subscription_call("state_subscribeStorage"), Some(SubscriptionCall::Subscribe));
+		assert_eq!(subscription_call("subscribe_newHead"), Some(SubscriptionCall::Subscribe));
+		assert_eq!(subscription_call("state_unsubscribeStorage"), Some(SubscriptionCall::Unsubscribe));
+		assert_eq!(subscription_call("chain_getHeader"), None);
+	}
+
+	#[test]
+	fn subscriptions_per_connection_are_limited() {
+		let io = io(ResourceLimits { max_subscriptions_per_connection: Some(1), ..Default::default() });
+		let (tx, _rx) = mpsc::channel(1);
+		let meta = Meta(Some(Arc::new(Session::new(tx))));
+		let subscribed = r#"{"jsonrpc":"2.0","result":1,"id":1}"#;
+
+		assert_eq!(call(&io, "test_subscribe", &meta), subscribed);
+		assert!(call(&io, "test_subscribe", &meta).contains("-32006"));
+		call(&io, "test_unsubscribe", &meta);
+		assert_eq!(call(&io, "test_subscribe", &meta), subscribed);
+	}
+
+	#[test]
+	fn large_responses_are_replaced_by_an_error() {
+		let io = io(ResourceLimits { max_response_size: Some(64), ..Default::default() });
+
+		assert!(call(&io, "test_large", &Meta::default()).contains("-32007"));
+		assert!(call(&io, "test_unsubscribe", &Meta::default()).contains("true"));
+	}
+}
@@ -0,0 +1,302 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Filtering of the RPC methods exposed by the servers.
+//!
+//! The methods are classified in groups, and every server exposes the groups it has been
+//! configured with. On top of that, operators can restrict the methods that can be called to an
+//! explicit list, or forbid some of them. Calls to a filtered out method are answered as if the
+//! method didn't exist, and the method isn't listed by `rpc_methods`.
+
+use std::{collections::{HashMap, HashSet}, fmt, str::FromStr, sync::Arc};
+use jsonrpc_core::{
+	Call, Error, FutureOutput, FutureResponse, Metadata, Middleware, Output,
+	futures::future::{self, Either, Future},
+};
+use serde::{Deserialize, Serialize};
+
+/// Methods that can be called.
+///
+/// In a JSON file, an allow-list is written as `{ "allow": ["system_health", ...] }` and a
+/// deny-list as `{ "deny": ["author_submitExtrinsic", ...] }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcMethodFilter {
+	/// Every method can be called.
+	All,
+	/// Only the listed methods can be called.
+	Allow(HashSet<String>),
+	/// Every method but the listed ones can be called.
+	Deny(HashSet<String>),
+}
+
+impl Default for RpcMethodFilter {
+	fn default() -> Self {
+		RpcMethodFilter::All
+	}
+}
+
+impl RpcMethodFilter {
+	/// Returns true if `method` can be called.
+	pub fn is_allowed(&self, method: &str) -> bool {
+		match self {
+			RpcMethodFilter::All => true,
+			RpcMethodFilter::Allow(methods) => methods.contains(method),
+			RpcMethodFilter::Deny(methods) => !methods.contains(method),
+		}
+	}
+}
+
+/// Group of RPC methods, exposed or not as a whole by a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RpcMethodGroup {
+	/// Methods reading the state of the node and of the chain.
+	Read,
+	/// Methods submitting transactions and watching their status.
+	Author,
+	/// Methods changing the node, such as its keys or its peers.
+	Admin,
+	/// Methods meant for debugging, which can use a lot of resources.
+	Debug,
+}
+
+impl RpcMethodGroup {
+	/// Every group.
+	pub const ALL: [RpcMethodGroup; 4] =
+		[RpcMethodGroup::Read, RpcMethodGroup::Author, RpcMethodGroup::Admin, RpcMethodGroup::Debug];
+
+	fn bit(self) -> u8 {
+		1 << self as u8
+	}
+
+	/// Name of the group, as written on the command line.
+	pub fn name(self) -> &'static str {
+		match self {
+			RpcMethodGroup::Read => "read",
+			RpcMethodGroup::Author => "author",
+			RpcMethodGroup::Admin => "admin",
+			RpcMethodGroup::Debug => "debug",
+		}
+	}
+}
+
+/// Set of method groups exposed by a server.
+///
+/// It is written as a comma separated list of groups, e.g. `read,author`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RpcMethodGroups(u8);
+
+impl RpcMethodGroups {
+	/// No group at all.
+	pub fn none() -> Self {
+		RpcMethodGroups(0)
+	}
+
+	/// Every group.
+	pub fn all() -> Self {
+		RpcMethodGroup::ALL.iter().fold(Self::none(), |groups, group| groups.with(*group))
+	}
+
+	/// The groups that are safe to expose publicly: `read` and `author`.
+	pub fn safe() -> Self {
+		Self::none().with(RpcMethodGroup::Read).with(RpcMethodGroup::Author)
+	}
+
+	/// Returns the set with `group` added.
+	pub fn with(self, group: RpcMethodGroup) -> Self {
+		RpcMethodGroups(self.0 | group.bit())
+	}
+
+	/// Returns true if `group` is in the set.
+	pub fn contains(self, group: RpcMethodGroup) -> bool {
+		self.0 & group.bit() != 0
+	}
+}
+
+impl fmt::Debug for RpcMethodGroups {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		fmt::Display::fmt(self, f)
+	}
+}
+
+impl fmt::Display for RpcMethodGroups {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let names = RpcMethodGroup::ALL.iter()
+			.filter(|group| self.contains(**group))
+			.map(|group| group.name())
+			.collect::<Vec<_>>();
+		write!(f, "{}", names.join(","))
+	}
+}
+
+impl FromStr for RpcMethodGroups {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.split(',').map(str::trim).filter(|name| !name.is_empty()).try_fold(Self::none(), |groups, name| {
+			RpcMethodGroup::ALL.iter()
+				.find(|group| group.name().eq_ignore_ascii_case(name))
+				.map(|group| groups.with(*group))
+				.ok_or_else(|| format!("Unknown RPC method group: {}", name))
+		})
+	}
+}
+
+/// Classification of the methods in groups. The methods that aren't classified are
+/// [`RpcMethodGroup::Read`] ones.
+#[derive(Debug, Clone, Default)]
+pub struct MethodGroups(HashMap<String, RpcMethodGroup>);
+
+impl MethodGroups {
+	/// Classifies the listed methods in their group. A method listed in several groups belongs to
+	/// the last one.
+	pub fn new(groups: &[(RpcMethodGroup, &[&str])]) -> Self {
+		let mut methods = HashMap::new();
+		for (group, names) in groups {
+			for name in names.iter() {
+				methods.insert(name.to_string(), *group);
+			}
+		}
+		MethodGroups(methods)
+	}
+
+	/// Returns the group of `method`.
+	pub fn group(&self, method: &str) -> RpcMethodGroup {
+		self.0.get(method).copied().unwrap_or(RpcMethodGroup::Read)
+	}
+}
+
+/// Middleware rejecting the calls to the methods of the groups that aren't exposed, and to the
+/// ones filtered out by a [`RpcMethodFilter`].
+///
+/// The default value lets every call through.
+#[derive(Debug, Clone, Default)]
+pub struct MethodFilter {
+	filter: Arc<RpcMethodFilter>,
+	groups: Option<(RpcMethodGroups, Arc<MethodGroups>)>,
+}
+
+impl MethodFilter {
+	/// Creates a middleware applying `filter`.
+	pub fn new(filter: RpcMethodFilter) -> Self {
+		MethodFilter { filter: Arc::new(filter), groups: None }
+	}
+
+	/// Only lets through the calls to the methods of the `exposed` groups, the methods being
+	/// classified by `groups`.
+	pub fn with_groups(self, exposed: RpcMethodGroups, groups: Arc<MethodGroups>) -> Self {
+		MethodFilter { groups: Some((exposed, groups)), ..self }
+	}
+
+	/// Returns true if `method` can be called.
+	pub fn is_allowed(&self, method: &str) -> bool {
+		let exposed = match &self.groups {
+			Some((exposed, groups)) => exposed.contains(groups.group(method)),
+			None => true,
+		};
+		exposed && self.filter.is_allowed(method)
+	}
+}
+
+impl<M: Metadata> Middleware<M> for MethodFilter {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let allowed = match &call {
+			Call::MethodCall(call) => self.is_allowed(&call.method),
+			Call::Notification(notification) => self.is_allowed(&notification.method),
+			Call::Invalid { .. } => true,
+		};
+		if allowed {
+			return Either::B(next(call, meta))
+		}
+
+		let output = match call {
+			Call::MethodCall(call) => Some(Output::from(Err(Error::method_not_found()), call.id, call.jsonrpc)),
+			_ => None,
+		};
+		Either::A(Box::new(future::ok(output)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{MetaIoHandler, Value};
+
+	fn call(io: &MetaIoHandler<(), MethodFilter>, method: &str) -> String {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		io.handle_request_sync(&request, ()).unwrap()
+	}
+
+	const OK: &str = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	const NOT_FOUND: &str = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#;
+
+	#[test]
+	fn filtered_out_methods_are_not_found() {
+		let filter: RpcMethodFilter = serde_json::from_str(r#"{"deny":["denied"]}"#).unwrap();
+		let mut io = MetaIoHandler::with_middleware(MethodFilter::new(filter));
+		io.add_method("allowed", |_| Ok(Value::Bool(true)));
+		io.add_method("denied", |_| Ok(Value::Bool(true)));
+
+		assert_eq!(call(&io, "allowed"), OK);
+		assert_eq!(call(&io, "denied"), NOT_FOUND);
+	}
+
+	#[test]
+	fn methods_of_groups_that_are_not_exposed_are_not_found() {
+		let groups = Arc::new(MethodGroups::new(&[
+			(RpcMethodGroup::Admin, &["admin", "debug"][..]),
+			(RpcMethodGroup::Debug, &["debug"][..]),
+		]));
+		let exposed = "read, Debug".parse().unwrap();
+		let mut io = MetaIoHandler::with_middleware(
+			MethodFilter::new(RpcMethodFilter::All).with_groups(exposed, groups)
+		);
+		io.add_method("read", |_| Ok(Value::Bool(true)));
+		io.add_method("admin", |_| Ok(Value::Bool(true)));
+		io.add_method("debug", |_| Ok(Value::Bool(true)));
+
+		assert_eq!(call(&io, "read"), OK);
+		assert_eq!(call(&io, "admin"), NOT_FOUND);
+		assert_eq!(call(&io, "debug"), OK);
+	}
+
+	#[test]
+	fn method_groups_are_parsed_and_displayed() {
+		let groups: RpcMethodGroups = "author,read".parse().unwrap();
+
+		assert_eq!(groups, RpcMethodGroups::safe());
+		assert_eq!(groups.to_string(), "read,author");
+		assert_eq!(RpcMethodGroups::all().to_string(), "read,author,admin,debug");
+		assert!("read,other".parse::<RpcMethodGroups>().is_err());
+	}
+
+	#[test]
+	fn only_listed_methods_are_allowed() {
+		let filter: RpcMethodFilter = serde_json::from_str(r#"{"allow":["allowed"]}"#).unwrap();
+
+		assert!(filter.is_allowed("allowed"));
+		assert!(!filter.is_allowed("other"));
+		assert!(RpcMethodFilter::All.is_allowed("other"));
+	}
+}
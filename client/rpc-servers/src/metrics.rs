@@ -0,0 +1,254 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Prometheus metrics of the RPC servers.
+//!
+//! The calls are counted and timed by the [`CallMetrics`] middleware, which has to come first so
+//! that it also sees the calls rejected by the other middlewares. Calls to methods that aren't
+//! registered by the handler are reported under the `<unknown>` method, so that clients can't
+//! create an unbounded number of time series.
+
+use std::{collections::HashSet, sync::Arc, time::Instant};
+use jsonrpc_core::{
+	Call, FutureOutput, FutureResponse, Metadata, Middleware, Output,
+	futures::future::{Either, Future},
+};
+use prometheus_endpoint::{
+	register, CounterVec, Gauge, GaugeVec, HistogramOpts, HistogramVec, Opts, PrometheusError,
+	Registry, U64,
+};
+use parking_lot::RwLock;
+use pubsub::Session;
+
+/// Label of the calls to methods that aren't registered.
+const UNKNOWN_METHOD: &str = "<unknown>";
+
+/// Transport an RPC server accepts calls over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+	/// HTTP requests.
+	Http,
+	/// WebSocket connections.
+	Ws,
+	/// Unix socket or named pipe connections.
+	Ipc,
+	/// Calls of the gRPC gateway.
+	Grpc,
+	/// Calls made from within the node, e.g. by the browser light client.
+	Local,
+}
+
+impl Default for Transport {
+	fn default() -> Self {
+		Transport::Local
+	}
+}
+
+impl Transport {
+	/// Returns the value of the `transport` label of the metrics.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Transport::Http => "http",
+			Transport::Ws => "ws",
+			Transport::Ipc => "ipc",
+			Transport::Grpc => "grpc",
+			Transport::Local => "local",
+		}
+	}
+}
+
+/// Prometheus metrics of the RPC servers.
+#[derive(Clone)]
+pub struct ServerMetrics {
+	ws_connections: Gauge<U64>,
+	subscriptions: GaugeVec<U64>,
+	calls: CounterVec<U64>,
+	calls_time: HistogramVec,
+	errors: CounterVec<U64>,
+}
+
+impl ServerMetrics {
+	/// Registers the metrics in `registry`.
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(ServerMetrics {
+			ws_connections: register(
+				Gauge::new("rpc_ws_connections", "Number of open WebSockets RPC connections")?,
+				registry,
+			)?,
+			subscriptions: register(GaugeVec::new(
+				Opts::new("rpc_subscriptions", "Number of open RPC subscriptions, by transport"),
+				&["transport"]
+			)?, registry)?,
+			calls: register(CounterVec::new(
+				Opts::new(
+					"rpc_calls_total",
+					"Total number of RPC calls answered, by transport, method and outcome"
+				),
+				&["transport", "method", "is_error"]
+			)?, registry)?,
+			calls_time: register(HistogramVec::new(
+				HistogramOpts {
+					common_opts: Opts::new(
+						"rpc_calls_time_seconds",
+						"Time it takes to answer an RPC call, by transport and method"
+					),
+					buckets: vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0],
+				},
+				&["transport", "method"]
+			)?, registry)?,
+			errors: register(CounterVec::new(
+				Opts::new(
+					"rpc_calls_errors_total",
+					"Total number of RPC calls answered with an error, by transport, method and error code"
+				),
+				&["transport", "method", "code"]
+			)?, registry)?,
+		})
+	}
+
+	/// Counts the connection of `session` until it is closed.
+	pub(crate) fn on_connection(&self, session: &Session) {
+		self.ws_connections.inc();
+		let connections = self.ws_connections.clone();
+		session.on_drop(move || connections.dec());
+	}
+
+	/// Counts a subscription opened over `transport`.
+	pub(crate) fn on_subscribe(&self, transport: Transport) {
+		self.subscriptions.with_label_values(&[transport.as_str()]).inc();
+	}
+
+	/// Forgets `count` subscriptions opened over `transport`.
+	pub(crate) fn on_unsubscribe(&self, transport: Transport, count: usize) {
+		self.subscriptions.with_label_values(&[transport.as_str()]).sub(count as u64);
+	}
+
+	/// Reports a call to `method` answered with `output` after `started`.
+	fn on_call_answered(&self, transport: Transport, method: &str, started: Instant, output: &Output) {
+		let error = match output {
+			Output::Success(_) => None,
+			Output::Failure(failure) => Some(&failure.error.code),
+		};
+		let transport = transport.as_str();
+
+		self.calls
+			.with_label_values(&[transport, method, if error.is_some() { "true" } else { "false" }])
+			.inc();
+		self.calls_time
+			.with_label_values(&[transport, method])
+			.observe(started.elapsed().as_secs_f64());
+		if let Some(code) = error {
+			self.errors
+				.with_label_values(&[transport, method, &code.code().to_string()])
+				.inc();
+		}
+	}
+}
+
+/// Middleware reporting the calls made over a transport to the [`ServerMetrics`].
+///
+/// The default value doesn't report anything.
+#[derive(Clone, Default)]
+pub struct CallMetrics {
+	metrics: Option<ServerMetrics>,
+	transport: Transport,
+	/// Methods reported under their own name, shared by the clones of the middleware.
+	methods: Arc<RwLock<HashSet<String>>>,
+}
+
+impl CallMetrics {
+	/// Creates a middleware reporting the calls made over `transport` to `metrics`.
+	pub fn new(metrics: Option<ServerMetrics>, transport: Transport) -> Self {
+		CallMetrics { metrics, transport, methods: Default::default() }
+	}
+
+	/// Registers the methods of the handler the middleware is used by. The calls to the other
+	/// methods are reported under the `<unknown>` method.
+	pub fn register_methods(&self, methods: impl IntoIterator<Item = String>) {
+		self.methods.write().extend(methods);
+	}
+}
+
+impl<M: Metadata> Middleware<M> for CallMetrics {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let (metrics, method) = match (&self.metrics, &call) {
+			(Some(metrics), Call::MethodCall(call)) => {
+				let method = if self.methods.read().contains(&call.method) {
+					call.method.clone()
+				} else {
+					UNKNOWN_METHOD.to_owned()
+				};
+				(metrics.clone(), method)
+			},
+			_ => return Either::B(next(call, meta)),
+		};
+
+		let transport = self.transport;
+		let started = Instant::now();
+		Either::A(Box::new(next(call, meta).map(move |output| {
+			if let Some(output) = &output {
+				metrics.on_call_answered(transport, &method, started, output);
+			}
+			output
+		})))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{Error, MetaIoHandler, Value};
+
+	fn call(io: &MetaIoHandler<(), CallMetrics>, method: &str) {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		io.handle_request_sync(&request, ()).unwrap();
+	}
+
+	#[test]
+	fn calls_are_counted_by_method_and_outcome() {
+		let registry = Registry::new();
+		let metrics = ServerMetrics::register(&registry).unwrap();
+		let call_metrics = CallMetrics::new(Some(metrics.clone()), Transport::Http);
+		let mut io = MetaIoHandler::with_middleware(call_metrics.clone());
+		io.add_method("test_ok", |_| Ok(Value::Bool(true)));
+		io.add_method("test_err", |_| Err(Error::invalid_params("test")));
+		io.add_method("test_unregistered", |_| Err(Error::invalid_params("test")));
+		call_metrics.register_methods(vec!["test_ok".into(), "test_err".into()]);
+
+		call(&io, "test_ok");
+		call(&io, "test_ok");
+		call(&io, "test_err");
+		call(&io, "test_unregistered");
+		call(&io, "nonexistent");
+
+		let calls = |method, is_error| metrics.calls.with_label_values(&["http", method, is_error]).get();
+		assert_eq!(calls("test_ok", "false"), 2);
+		assert_eq!(calls("test_err", "true"), 1);
+		assert_eq!(calls("<unknown>", "true"), 2);
+		assert_eq!(calls("test_unregistered", "true"), 0);
+		assert_eq!(calls("nonexistent", "true"), 0);
+		assert_eq!(metrics.errors.with_label_values(&["http", "test_err", "-32602"]).get(), 1);
+		assert_eq!(metrics.calls_time.with_label_values(&["http", "test_ok"]).get_sample_count(), 2);
+	}
+}
@@ -0,0 +1,270 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rate limiting of the RPC calls.
+//!
+//! Calls are counted with token buckets: a bucket holds up to `burst` tokens, is refilled at
+//! `per_second` tokens per second, and every call takes a token out of it. Calls made while the
+//! bucket is empty are answered with a [`RATE_LIMITED_ERROR_CODE`] error without reaching the
+//! handler.
+//!
+//! Safe and unsafe methods have separate limits. Every WebSocket connection gets buckets of its
+//! own, while the requests made over HTTP, which have no notion of connection, share a single
+//! bucket per group of methods. The transports don't expose the address of the remote, so calls
+//! can't be limited per IP address.
+
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Instant};
+use jsonrpc_core::{
+	Call, Error, ErrorCode, FutureOutput, FutureResponse, Middleware, Output,
+	futures::future::{self, Either, Future},
+};
+use parking_lot::Mutex;
+use pubsub::PubSubMetadata;
+
+/// JSON-RPC error code of the calls rejected because of the rate limit.
+pub const RATE_LIMITED_ERROR_CODE: i64 = -32005;
+
+/// Limit of the rate of calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+	/// Number of calls allowed per second on average.
+	pub per_second: u32,
+	/// Number of calls that can be made at once after a period of inactivity.
+	pub burst: u32,
+}
+
+impl RateLimit {
+	/// Allows `per_second` calls per second, with bursts of as many calls.
+	pub fn per_second(per_second: u32) -> Self {
+		RateLimit { per_second, burst: per_second }
+	}
+}
+
+/// Rate limits of the RPC calls. `None` means no limit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitConfig {
+	/// Limit of the calls to the methods considered safe.
+	pub safe: Option<RateLimit>,
+	/// Limit of the calls to the methods considered unsafe.
+	pub unsafe_methods: Option<RateLimit>,
+}
+
+/// Group of methods sharing the same limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MethodGroup {
+	Safe,
+	Unsafe,
+}
+
+struct TokenBucket {
+	tokens: f64,
+	refilled_at: Instant,
+}
+
+impl TokenBucket {
+	fn new(limit: &RateLimit, now: Instant) -> Self {
+		TokenBucket { tokens: f64::from(limit.burst), refilled_at: now }
+	}
+
+	/// Takes a token out of the bucket, returning false if it is empty.
+	fn try_take(&mut self, limit: &RateLimit, now: Instant) -> bool {
+		let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+		self.tokens = (self.tokens + elapsed * f64::from(limit.per_second)).min(f64::from(limit.burst));
+		self.refilled_at = now;
+		if self.tokens >= 1.0 {
+			self.tokens -= 1.0;
+			true
+		} else {
+			false
+		}
+	}
+}
+
+/// Buckets, indexed by connection (`None` for the calls made outside of a session) and group.
+type Buckets = HashMap<(Option<usize>, MethodGroup), TokenBucket>;
+
+/// Middleware rejecting the calls exceeding the configured rate limits.
+///
+/// The default value doesn't limit anything.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+	config: RateLimitConfig,
+	unsafe_methods: Arc<HashSet<String>>,
+	buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+	/// Creates a rate limiter applying `config`, considering the methods in `unsafe_methods`
+	/// unsafe and every other one safe.
+	pub fn new(config: RateLimitConfig, unsafe_methods: &[&str]) -> Self {
+		RateLimiter {
+			config,
+			unsafe_methods: Arc::new(unsafe_methods.iter().map(|m| m.to_string()).collect()),
+			buckets: Default::default(),
+		}
+	}
+
+	/// Returns true if a call to `method` made with `meta` is within the limits, and counts it.
+	fn check<M: PubSubMetadata>(&self, method: &str, meta: &M) -> bool {
+		let (group, limit) = if self.unsafe_methods.contains(method) {
+			(MethodGroup::Unsafe, self.config.unsafe_methods)
+		} else {
+			(MethodGroup::Safe, self.config.safe)
+		};
+		let limit = match limit {
+			Some(limit) => limit,
+			None => return true,
+		};
+
+		let session = meta.session();
+		let connection = session.as_ref().map(|s| &**s as *const pubsub::Session as usize);
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock();
+
+		if let Some(session) = &session {
+			// Forget the buckets of the connection once it is closed.
+			let is_new = !buckets.keys().any(|(c, _)| *c == connection);
+			if is_new {
+				let buckets = self.buckets.clone();
+				session.on_drop(move || buckets.lock().retain(|(c, _), _| *c != connection));
+			}
+		}
+
+		buckets.entry((connection, group))
+			.or_insert_with(|| TokenBucket::new(&limit, now))
+			.try_take(&limit, now)
+	}
+}
+
+fn rate_limited() -> Error {
+	Error {
+		code: ErrorCode::ServerError(RATE_LIMITED_ERROR_CODE),
+		message: "Too many requests".into(),
+		data: None,
+	}
+}
+
+impl<M: PubSubMetadata> Middleware<M> for RateLimiter {
+	type Future = FutureResponse;
+	type CallFuture = FutureOutput;
+
+	fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X> where
+		F: Fn(Call, M) -> X + Send + Sync,
+		X: Future<Item = Option<Output>, Error = ()> + Send + 'static,
+	{
+		let allowed = match &call {
+			Call::MethodCall(call) => self.check(&call.method, &meta),
+			Call::Notification(notification) => self.check(&notification.method, &meta),
+			Call::Invalid { .. } => true,
+		};
+		if allowed {
+			return Either::B(next(call, meta))
+		}
+
+		let output = match call {
+			Call::MethodCall(call) => Some(Output::from(Err(rate_limited()), call.id, call.jsonrpc)),
+			_ => None,
+		};
+		Either::A(Box::new(future::ok(output)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_core::{futures::sync::mpsc, MetaIoHandler, Value};
+	use std::{sync::Arc, time::Duration};
+
+	#[derive(Clone, Default)]
+	struct Meta(Option<Arc<pubsub::Session>>);
+
+	impl jsonrpc_core::Metadata for Meta {}
+
+	impl PubSubMetadata for Meta {
+		fn session(&self) -> Option<Arc<pubsub::Session>> {
+			self.0.clone()
+		}
+	}
+
+	fn session() -> Meta {
+		let (tx, _rx) = mpsc::channel(1);
+		Meta(Some(Arc::new(pubsub::Session::new(tx))))
+	}
+
+	fn io(config: RateLimitConfig) -> MetaIoHandler<Meta, RateLimiter> {
+		let mut io = MetaIoHandler::with_middleware(RateLimiter::new(config, &["unsafe_method"]));
+		io.add_method("safe_method", |_| Ok(Value::Bool(true)));
+		io.add_method("unsafe_method", |_| Ok(Value::Bool(true)));
+		io
+	}
+
+	fn call(io: &MetaIoHandler<Meta, RateLimiter>, method: &str, meta: &Meta) -> String {
+		let request = format!(r#"{{"jsonrpc":"2.0","method":"{}","params":[],"id":1}}"#, method);
+		io.handle_request_sync(&request, meta.clone()).unwrap()
+	}
+
+	const OK: &str = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	const LIMITED: &str = r#"{"jsonrpc":"2.0","error":{"code":-32005,"message":"Too many requests"},"id":1}"#;
+
+	#[test]
+	fn token_bucket_refills_over_time() {
+		let limit = RateLimit { per_second: 2, burst: 2 };
+		let start = Instant::now();
+		let mut bucket = TokenBucket::new(&limit, start);
+
+		assert!(bucket.try_take(&limit, start));
+		assert!(bucket.try_take(&limit, start));
+		assert!(!bucket.try_take(&limit, start));
+		assert!(bucket.try_take(&limit, start + Duration::from_millis(500)));
+		assert!(!bucket.try_take(&limit, start + Duration::from_millis(500)));
+		// the bucket never holds more than `burst` tokens.
+		let later = start + Duration::from_secs(60);
+		assert!(bucket.try_take(&limit, later));
+		assert!(bucket.try_take(&limit, later));
+		assert!(!bucket.try_take(&limit, later));
+	}
+
+	#[test]
+	fn limits_are_per_group_and_connection() {
+		let io = io(RateLimitConfig {
+			safe: None,
+			unsafe_methods: Some(RateLimit { per_second: 1, burst: 1 }),
+		});
+		let first = session();
+		let second = session();
+
+		assert_eq!(call(&io, "unsafe_method", &first), OK);
+		assert_eq!(call(&io, "unsafe_method", &first), LIMITED);
+		// safe methods aren't limited.
+		assert_eq!(call(&io, "safe_method", &first), OK);
+		assert_eq!(call(&io, "safe_method", &first), OK);
+		// other connections have buckets of their own.
+		assert_eq!(call(&io, "unsafe_method", &second), OK);
+	}
+
+	#[test]
+	fn calls_without_session_share_a_bucket() {
+		let io = io(RateLimitConfig {
+			safe: Some(RateLimit { per_second: 1, burst: 1 }),
+			unsafe_methods: None,
+		});
+
+		assert_eq!(call(&io, "safe_method", &Meta::default()), OK);
+		assert_eq!(call(&io, "safe_method", &Meta::default()), LIMITED);
+	}
+}
@@ -39,7 +39,7 @@ use sp_core::{Bytes, traits::BareCryptoStorePtr};
 use sp_api::ProvideRuntimeApi;
 use sp_runtime::generic;
 use sp_transaction_pool::{
-	TransactionPool, InPoolTransaction, TransactionStatus, TransactionSource,
+	TransactionPool, InPoolTransaction, PoolEvent, TransactionStatus, TransactionSource,
 	BlockHash, TxHash, TransactionFor, error::IntoPoolError,
 };
 use sp_session::SessionKeys;
@@ -47,6 +47,7 @@ use sp_session::SessionKeys;
 /// Re-export the API for backward compatibility.
 pub use sc_rpc_api::author::*;
 use self::error::{Error, FutureResult, Result};
+use self::keys::{RotatedKeys, SessionKey};
 
 /// Authoring API
 pub struct Author<P, Client> {
@@ -122,6 +123,40 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 		).map(Into::into).map_err(|e| Error::Client(Box::new(e)))
 	}
 
+	fn rotate_keys_with_proof(&self) -> Result<RotatedKeys> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let best_block_hash = generic::BlockId::Hash(self.client.info().best_hash);
+		let runtime_api = self.client.runtime_api();
+		let keys = runtime_api.generate_session_keys(&best_block_hash, None)
+			.map_err(|e| Error::Client(Box::new(e)))?;
+		let public_keys = runtime_api.decode_session_keys(&best_block_hash, keys.clone())
+			.map_err(|e| Error::Client(Box::new(e)))?
+			.ok_or_else(|| Error::InvalidSessionKeys)?;
+
+		let keystore = self.keystore.read();
+		let session_keys = public_keys.into_iter()
+			.map(|(public, key_type)| {
+				let key = keystore.keys(key_type)
+					.map_err(|_| Error::KeyStoreUnavailable)?
+					.into_iter()
+					.find(|key| key.1 == public)
+					.ok_or_else(|| Error::KeyStoreUnavailable)?;
+				let signature = keystore.sign_with(key_type, &key, &keys)
+					.map_err(|_| Error::KeyStoreUnavailable)?;
+
+				Ok(SessionKey {
+					key_type: String::from_utf8_lossy(&key_type.0).into_owned(),
+					crypto: String::from_utf8_lossy(&(key.0).0).into_owned(),
+					public: public.into(),
+					signature: signature.into(),
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(RotatedKeys { keys: keys.into(), session_keys })
+	}
+
 	fn has_session_keys(&self, session_keys: Bytes) -> Result<bool> {
 		self.deny_unsafe.check_if_safe()?;
 
@@ -238,4 +273,21 @@ impl<P, Client> AuthorApi<TxHash<P>, BlockHash<P>> for Author<P, Client>
 	fn unwatch_extrinsic(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_all_extrinsics(&self,
+		_metadata: Self::Metadata,
+		subscriber: Subscriber<PoolEvent<TxHash<P>, BlockHash<P>>>,
+	) {
+		let events = self.pool.event_stream().map(|event| Ok::<_, ()>(Ok(event)));
+		self.subscriptions.add(subscriber, |sink| {
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(Compat::new(events))
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_all_extrinsics(&self, _metadata: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
 }
@@ -174,6 +174,33 @@ fn should_return_watch_validation_error() {
 	assert!(res.is_err(), "Expected the transaction to be rejected as invalid.");
 }
 
+#[test]
+fn should_subscribe_to_all_extrinsics() {
+	// given
+	let setup = TestSetup::default();
+	let p = setup.author();
+
+	let (subscriber, id_rx, data) = jsonrpc_pubsub::typed::Subscriber::new_test("test");
+	p.subscribe_all_extrinsics(Default::default(), subscriber);
+	assert_eq!(executor::block_on(id_rx.compat()), Ok(Ok(1.into())));
+
+	// when
+	let xt = uxt(AccountKeyring::Alice, 0);
+	AuthorApi::submit_extrinsic(&p, xt.encode().into()).wait().unwrap();
+
+	// then
+	let h = HexDisplay::from(&blake2_256(&xt.encode())).to_string();
+	let (res, data) = executor::block_on(data.into_future().compat()).unwrap();
+	assert_eq!(
+		res,
+		Some(format!(r#"{{"jsonrpc":"2.0","method":"test","params":{{"result":{{"hash":"0x{}","kind":"imported"}},"subscription":1}}}}"#, h))
+	);
+	assert_eq!(
+		executor::block_on(data.into_future().compat()).unwrap().0,
+		Some(format!(r#"{{"jsonrpc":"2.0","method":"test","params":{{"result":{{"hash":"0x{}","kind":"ready"}},"subscription":1}}}}"#, h))
+	);
+}
+
 #[test]
 fn should_return_pending_extrinsics() {
 	let p = TestSetup::default().author();
@@ -244,6 +271,33 @@ fn should_rotate_keys() {
 	assert!(sr25519_public_keys.contains(&CryptoTypePublicPair(sr25519::CRYPTO_ID, session_keys.sr25519.to_raw_vec())));
 }
 
+#[test]
+fn should_rotate_keys_with_proof() {
+	let setup = TestSetup::default();
+	let p = setup.author();
+
+	let rotated = p.rotate_keys_with_proof().expect("Rotates the keys");
+
+	let session_keys = SessionKeys::decode(&mut &rotated.keys[..])
+		.expect("SessionKeys decode successfully");
+	assert_eq!(
+		rotated.session_keys.iter().map(|key| key.key_type.as_str()).collect::<Vec<_>>(),
+		vec!["ed25", "sr25"],
+	);
+
+	let ed25519 = &rotated.session_keys[0];
+	assert_eq!(ed25519.public.to_vec(), session_keys.ed25519.to_raw_vec());
+	let signature = ed25519::Signature::decode(&mut &ed25519.signature[..]).unwrap();
+	let public = ed25519::Public::from_slice(&ed25519.public);
+	assert!(ed25519::Pair::verify(&signature, &rotated.keys[..], &public));
+
+	let sr25519 = &rotated.session_keys[1];
+	assert_eq!(sr25519.public.to_vec(), session_keys.sr25519.to_raw_vec());
+	let signature = sr25519::Signature::decode(&mut &sr25519.signature[..]).unwrap();
+	let public = sr25519::Public::from_slice(&sr25519.public);
+	assert!(sr25519::Pair::verify(&signature, &rotated.keys[..], &public));
+}
+
 #[test]
 fn test_has_session_keys() {
 	let setup = TestSetup::default();
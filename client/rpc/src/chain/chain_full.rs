@@ -20,10 +20,10 @@ use std::sync::Arc;
 use rpc::futures::future::result;
 
 use sc_rpc_api::Subscriptions;
-use sc_client_api::{BlockchainEvents, BlockBackend};
-use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT}};
+use sc_client_api::{BlockchainEvents, BlockBackend, ForkBackend};
+use sp_runtime::{generic::{BlockId, SignedBlock}, traits::{Block as BlockT, NumberFor}};
 
-use super::{ChainBackend, client_err, error::FutureResult};
+use super::{ChainBackend, ExtrinsicLocation, LeafInfo, client_err, error::FutureResult};
 use std::marker::PhantomData;
 use sp_blockchain::HeaderBackend;
 
@@ -50,7 +50,7 @@ impl<Block: BlockT, Client> FullChain<Block, Client> {
 
 impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> where
 	Block: BlockT + 'static,
-	Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+	Client: BlockBackend<Block> + ForkBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
 {
 	fn client(&self) -> &Arc<Client> {
 		&self.client
@@ -75,4 +75,33 @@ impl<Block, Client> ChainBackend<Client, Block> for FullChain<Block, Client> whe
 			.map_err(client_err)
 		))
 	}
+
+	fn extrinsic(&self, hash: Block::Hash) -> FutureResult<Option<ExtrinsicLocation<Block::Hash>>> {
+		Box::new(result(self.client
+			.extrinsic_location(&hash)
+			.map(|location| location.map(|(block_hash, index)| ExtrinsicLocation { block_hash, index }))
+			.map_err(client_err)
+		))
+	}
+
+	fn leaves(&self) -> FutureResult<Vec<LeafInfo<Block::Hash, NumberFor<Block>>>> {
+		Box::new(result(self.client
+			.leaves_info()
+			.map(|leaves| leaves.into_iter().map(|leaf| LeafInfo {
+				hash: leaf.hash,
+				number: leaf.number,
+				ancestor_hash: leaf.ancestor_hash,
+				ancestor_number: leaf.ancestor_number,
+				abandoned: leaf.abandoned,
+			}).collect())
+			.map_err(client_err)
+		))
+	}
+
+	fn prune_forks(&self, below: NumberFor<Block>) -> FutureResult<Vec<Block::Hash>> {
+		Box::new(result(self.client
+			.prune_abandoned_forks(below)
+			.map_err(client_err)
+		))
+	}
 }
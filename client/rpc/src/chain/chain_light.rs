@@ -24,11 +24,11 @@ use sc_rpc_api::Subscriptions;
 use sc_client_api::light::{Fetcher, RemoteBodyRequest, RemoteBlockchain};
 use sp_runtime::{
 	generic::{BlockId, SignedBlock},
-	traits::{Block as BlockT},
+	traits::{Block as BlockT, NumberFor},
 };
 
-use super::{ChainBackend, client_err, error::FutureResult};
-use sp_blockchain::HeaderBackend;
+use super::{ChainBackend, ExtrinsicLocation, LeafInfo, client_err, error::{Error, FutureResult}};
+use sp_blockchain::{Error as ClientError, HeaderBackend};
 use sc_client_api::BlockchainEvents;
 
 /// Blockchain API backend for light nodes. Reads all the data from local
@@ -113,4 +113,16 @@ impl<Block, Client, F> ChainBackend<Client, Block> for LightChain<Block, Client,
 
 		Box::new(block)
 	}
+
+	fn extrinsic(&self, _hash: Block::Hash) -> FutureResult<Option<ExtrinsicLocation<Block::Hash>>> {
+		Box::new(result(Err(Error::Other("The extrinsics are not indexed by light nodes".into()))))
+	}
+
+	fn leaves(&self) -> FutureResult<Vec<LeafInfo<Block::Hash, NumberFor<Block>>>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
+
+	fn prune_forks(&self, _below: NumberFor<Block>) -> FutureResult<Vec<Block::Hash>> {
+		Box::new(result(Err(client_err(ClientError::NotAvailableOnLightClient))))
+	}
 }
@@ -29,10 +29,10 @@ use futures::{future, StreamExt, TryStreamExt};
 use log::warn;
 use rpc::{
 	Result as RpcResult,
-	futures::{stream, Future, Sink, Stream},
+	futures::{stream, Future, Sink, Stream, future::result},
 };
 
-use sc_rpc_api::Subscriptions;
+use sc_rpc_api::{DenyUnsafe, Subscriptions};
 use sc_client_api::{BlockchainEvents, light::{Fetcher, RemoteBlockchain}};
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use sp_rpc::{number::NumberOrHex, list::ListOrValue};
@@ -45,7 +45,7 @@ use self::error::{Result, Error, FutureResult};
 
 pub use sc_rpc_api::chain::*;
 use sp_blockchain::HeaderBackend;
-use sc_client_api::BlockBackend;
+use sc_client_api::{BlockBackend, ForkBackend};
 
 /// Blockchain backend API
 trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
@@ -94,6 +94,15 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 		Ok(self.client().info().finalized_hash)
 	}
 
+	/// Get the block including the extrinsic with the given hash in the canon chain.
+	fn extrinsic(&self, hash: Block::Hash) -> FutureResult<Option<ExtrinsicLocation<Block::Hash>>>;
+
+	/// Get the leaves of the block tree, best first.
+	fn leaves(&self) -> FutureResult<Vec<LeafInfo<Block::Hash, NumberFor<Block>>>>;
+
+	/// Remove the abandoned forks whose leaves are below the block `below`.
+	fn prune_forks(&self, below: NumberFor<Block>) -> FutureResult<Vec<Block::Hash>>;
+
 	/// All new head subscription
 	fn subscribe_all_heads(
 		&self,
@@ -178,13 +187,16 @@ trait ChainBackend<Client, Block: BlockT>: Send + Sync + 'static
 pub fn new_full<Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	deny_unsafe: DenyUnsafe,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
-		Client: BlockBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block> + 'static,
+		Client: BlockBackend<Block> + ForkBackend<Block> + HeaderBackend<Block> + BlockchainEvents<Block>
+			+ 'static,
 {
 	Chain {
 		backend: Box::new(self::chain_full::FullChain::new(client, subscriptions)),
+		deny_unsafe,
 	}
 }
 
@@ -194,6 +206,7 @@ pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	deny_unsafe: DenyUnsafe,
 ) -> Chain<Block, Client>
 	where
 		Block: BlockT + 'static,
@@ -207,12 +220,15 @@ pub fn new_light<Block: BlockT, Client, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 		)),
+		deny_unsafe,
 	}
 }
 
 /// Chain API with subscriptions support.
 pub struct Chain<Block: BlockT, Client> {
 	backend: Box<dyn ChainBackend<Client, Block>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, SignedBlock<Block>> for
@@ -251,6 +267,26 @@ impl<Block, Client> ChainApi<NumberFor<Block>, Block::Hash, Block::Header, Signe
 		self.backend.finalized_head()
 	}
 
+	fn extrinsic(&self, hash: Block::Hash) -> FutureResult<Option<ExtrinsicLocation<Block::Hash>>> {
+		self.backend.extrinsic(hash)
+	}
+
+	fn leaves(&self) -> FutureResult<Vec<LeafInfo<Block::Hash, NumberFor<Block>>>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		self.backend.leaves()
+	}
+
+	fn prune_forks(&self, below: NumberFor<Block>) -> FutureResult<Vec<Block::Hash>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		self.backend.prune_forks(below)
+	}
+
 	fn subscribe_all_heads(&self, metadata: Self::Metadata, subscriber: Subscriber<Block::Header>) {
 		self.backend.subscribe_all_heads(metadata, subscriber)
 	}
@@ -21,8 +21,9 @@ use assert_matches::assert_matches;
 use substrate_test_runtime_client::{
 	prelude::*,
 	sp_consensus::BlockOrigin,
-	runtime::{H256, Block, Header},
+	runtime::{self, H256, Block, Header},
 };
+use sp_runtime::traits::{BlakeTwo256, Hash as HashT};
 use sp_rpc::list::ListOrValue;
 use sc_block_builder::BlockBuilderProvider;
 use futures::{executor, compat::{Future01CompatExt, Stream01CompatExt}};
@@ -31,7 +32,7 @@ use crate::testing::TaskExecutor;
 #[test]
 fn should_return_header() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.header(Some(client.genesis_hash()).into()).wait(),
@@ -63,7 +64,7 @@ fn should_return_header() {
 #[test]
 fn should_return_a_block() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
 	let block_hash = block.hash();
@@ -114,7 +115,7 @@ fn should_return_a_block() {
 #[test]
 fn should_return_block_hash() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.block_hash(None.into()),
@@ -158,7 +159,7 @@ fn should_return_block_hash() {
 #[test]
 fn should_return_finalized_hash() {
 	let mut client = Arc::new(substrate_test_runtime_client::new());
-	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	assert_matches!(
 		api.finalized_head(),
@@ -182,13 +183,73 @@ fn should_return_finalized_hash() {
 	);
 }
 
+#[test]
+fn should_return_extrinsic_location() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = block.hash();
+	let extrinsic_hash = BlakeTwo256::hash_of(&block.extrinsics[0]);
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	assert_matches!(
+		api.extrinsic(extrinsic_hash).wait(),
+		Ok(Some(ExtrinsicLocation { block_hash: ref x, index: 0 })) if x == &block_hash
+	);
+	assert_matches!(api.extrinsic(H256::from_low_u64_be(5)).wait(), Ok(None));
+}
+
+#[test]
+fn should_return_leaves_and_prune_forks() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let best_hash = block.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	// a fork of the genesis block, abandoned once the best block is finalized.
+	let mut builder = client.new_block_at(&BlockId::Number(0), Default::default(), false).unwrap();
+	builder.push_storage_change(vec![1], None).unwrap();
+	let fork = builder.build().unwrap().block;
+	let fork_hash = fork.hash();
+	client.import(BlockOrigin::Own, fork).unwrap();
+	client.finalize_block(BlockId::Hash(best_hash), None).unwrap();
+
+	assert_eq!(api.leaves().wait().unwrap(), vec![
+		LeafInfo { hash: best_hash, number: 1, ancestor_hash: best_hash, ancestor_number: 1, abandoned: false },
+		LeafInfo {
+			hash: fork_hash,
+			number: 1,
+			ancestor_hash: client.genesis_hash(),
+			ancestor_number: 0,
+			abandoned: true,
+		},
+	]);
+
+	assert_eq!(api.prune_forks(2).wait().unwrap(), vec![fork_hash]);
+	assert_eq!(api.leaves().wait().unwrap().len(), 1);
+	assert_matches!(client.header(&BlockId::Hash(fork_hash)), Ok(None));
+
+	let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::Yes);
+	assert_matches!(api.prune_forks(2).wait(), Err(Error::UnsafeRpcCalled(_)));
+}
+
 #[test]
 fn should_notify_about_latest_block() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		api.subscribe_all_heads(Default::default(), subscriber);
 
@@ -215,7 +276,7 @@ fn should_notify_about_best_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		api.subscribe_new_heads(Default::default(), subscriber);
 
@@ -242,7 +303,7 @@ fn should_notify_about_finalized_block() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let api = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		api.subscribe_finalized_heads(Default::default(), subscriber);
 
@@ -24,7 +24,7 @@
 
 mod metadata;
 
-pub use sc_rpc_api::{DenyUnsafe, Subscriptions};
+pub use sc_rpc_api::{DenyUnsafe, Subscriptions, AUTHOR_METHODS, DEBUG_METHODS, UNSAFE_METHODS};
 pub use self::metadata::Metadata;
 pub use rpc::IoHandlerExtension as RpcExtension;
 
@@ -35,3 +35,75 @@ pub mod state;
 pub mod system;
 #[cfg(test)]
 mod testing;
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use super::UNSAFE_METHODS;
+
+	/// Returns the name of the function declared on `line`, if any.
+	fn fn_name(line: &str) -> Option<&str> {
+		let line = line.trim_start();
+		let line = if line.starts_with("pub(crate) ") { &line[11..] } else { line };
+		let line = if line.starts_with("pub ") { &line[4..] } else { line };
+		if !line.starts_with("fn ") {
+			return None
+		}
+		let rest = &line[3..];
+		let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+		Some(&rest[..end])
+	}
+
+	/// Returns the names of the RPC methods declared in `api` whose implementation in
+	/// `implementation` checks whether unsafe calls are denied.
+	fn methods_checking_if_safe(api: &str, implementation: &str) -> Vec<String> {
+		let mut rpc_names = HashMap::new();
+		let mut rpc_name = None;
+		for line in api.lines() {
+			if let Some(start) = line.find("name = \"") {
+				let rest = &line[start + 8..];
+				rpc_name = rest.find('"').map(|end| rest[..end].to_string());
+			}
+			if let Some(name) = fn_name(line) {
+				if let Some(rpc_name) = rpc_name.take() {
+					rpc_names.insert(name.to_string(), rpc_name);
+				}
+			}
+		}
+
+		let mut methods = Vec::new();
+		let mut current = None;
+		for line in implementation.lines() {
+			if let Some(name) = fn_name(line) {
+				current = Some(name);
+			}
+			if line.contains("check_if_safe()") || line.contains("bail_if_unsafe!(") {
+				if let Some(name) = current {
+					let rpc_name = rpc_names.get(name)
+						.unwrap_or_else(|| panic!("`{}` checks if it is safe but isn't an RPC method", name));
+					methods.push(rpc_name.clone());
+				}
+			}
+		}
+		methods
+	}
+
+	#[test]
+	fn methods_checking_if_safe_are_unsafe() {
+		let apis = [
+			(include_str!("../../rpc-api/src/author/mod.rs"), include_str!("author/mod.rs")),
+			(include_str!("../../rpc-api/src/chain/mod.rs"), include_str!("chain/mod.rs")),
+			(include_str!("../../rpc-api/src/offchain/mod.rs"), include_str!("offchain/mod.rs")),
+			(include_str!("../../rpc-api/src/state/mod.rs"), include_str!("state/mod.rs")),
+			(include_str!("../../rpc-api/src/system/mod.rs"), include_str!("system/mod.rs")),
+		];
+		let mut checked = 0;
+		for (api, implementation) in apis.iter() {
+			for method in methods_checking_if_safe(api, implementation) {
+				assert!(UNSAFE_METHODS.contains(&method.as_str()), "{} isn't listed as unsafe", method);
+				checked += 1;
+			}
+		}
+		assert!(checked > 0);
+	}
+}
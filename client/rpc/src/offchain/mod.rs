@@ -23,6 +23,7 @@ mod tests;
 
 /// Re-export the API for backward compatibility.
 pub use sc_rpc_api::offchain::*;
+use sc_client_api::IndexedData;
 use sc_rpc_api::DenyUnsafe;
 use self::error::{Error, Result};
 use sp_core::{
@@ -32,19 +33,29 @@ use sp_core::{
 use parking_lot::RwLock;
 use std::sync::Arc;
 
+/// Returns the prefix under which the offchain workers store the values of `kind`.
+fn storage_prefix(kind: StorageKind) -> &'static [u8] {
+	match kind {
+		StorageKind::PERSISTENT => sp_offchain::STORAGE_PREFIX,
+		StorageKind::LOCAL => sp_offchain::LOCAL_STORAGE_PREFIX,
+	}
+}
+
 /// Offchain API
-#[derive(Debug)]
 pub struct Offchain<T: OffchainStorage> {
 	/// Offchain storage
 	storage: Arc<RwLock<T>>,
+	/// Data indexed by the runtime, if the node indexes it.
+	index: Option<Arc<dyn IndexedData>>,
 	deny_unsafe: DenyUnsafe,
 }
 
 impl<T: OffchainStorage> Offchain<T> {
 	/// Create new instance of Offchain API.
-	pub fn new(storage: T, deny_unsafe: DenyUnsafe) -> Self {
+	pub fn new(storage: T, index: Option<Arc<dyn IndexedData>>, deny_unsafe: DenyUnsafe) -> Self {
 		Offchain {
 			storage: Arc::new(RwLock::new(storage)),
+			index,
 			deny_unsafe,
 		}
 	}
@@ -55,10 +66,7 @@ impl<T: OffchainStorage + 'static> OffchainApi for Offchain<T> {
 	fn set_local_storage(&self, kind: StorageKind, key: Bytes, value: Bytes) -> Result<()> {
 		self.deny_unsafe.check_if_safe()?;
 
-		let prefix = match kind {
-			StorageKind::PERSISTENT => sp_offchain::STORAGE_PREFIX,
-			StorageKind::LOCAL => return Err(Error::UnavailableStorageKind),
-		};
+		let prefix = storage_prefix(kind);
 		self.storage.write().set(prefix, &*key, &*value);
 		Ok(())
 	}
@@ -67,10 +75,13 @@ impl<T: OffchainStorage + 'static> OffchainApi for Offchain<T> {
 	fn get_local_storage(&self, kind: StorageKind, key: Bytes) -> Result<Option<Bytes>> {
 		self.deny_unsafe.check_if_safe()?;
 
-		let prefix = match kind {
-			StorageKind::PERSISTENT => sp_offchain::STORAGE_PREFIX,
-			StorageKind::LOCAL => return Err(Error::UnavailableStorageKind),
-		};
+		let prefix = storage_prefix(kind);
 		Ok(self.storage.read().get(prefix, &*key).map(Into::into))
 	}
+
+	/// Get the value written under given key by the runtime through offchain indexing.
+	fn get_indexed(&self, key: Bytes) -> Result<Option<Bytes>> {
+		let index = self.index.as_ref().ok_or(Error::IndexingDisabled)?;
+		Ok(index.indexed_data(&*key).map(Into::into))
+	}
 }
@@ -16,14 +16,14 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use super::*;
+use super::{*, error::Error};
 use assert_matches::assert_matches;
 use sp_core::{Bytes, offchain::storage::InMemOffchainStorage};
 
 #[test]
 fn local_storage_should_work() {
 	let storage = InMemOffchainStorage::default();
-	let offchain = Offchain::new(storage, DenyUnsafe::No);
+	let offchain = Offchain::new(storage, None, DenyUnsafe::No);
 	let key = Bytes(b"offchain_storage".to_vec());
 	let value = Bytes(b"offchain_value".to_vec());
 
@@ -37,10 +37,28 @@ fn local_storage_should_work() {
 	);
 }
 
+#[test]
+fn local_storage_kinds_are_kept_apart() {
+	let storage = InMemOffchainStorage::default();
+	let offchain = Offchain::new(storage, None, DenyUnsafe::No);
+	let key = Bytes(b"offchain_storage".to_vec());
+	let value = Bytes(b"offchain_value".to_vec());
+
+	assert_matches!(
+		offchain.set_local_storage(StorageKind::LOCAL, key.clone(), value.clone()),
+		Ok(())
+	);
+	assert_matches!(
+		offchain.get_local_storage(StorageKind::LOCAL, key.clone()),
+		Ok(Some(ref v)) if *v == value
+	);
+	assert_matches!(offchain.get_local_storage(StorageKind::PERSISTENT, key), Ok(None));
+}
+
 #[test]
 fn offchain_calls_considered_unsafe() {
 	let storage = InMemOffchainStorage::default();
-	let offchain = Offchain::new(storage, DenyUnsafe::Yes);
+	let offchain = Offchain::new(storage, None, DenyUnsafe::Yes);
 	let key = Bytes(b"offchain_storage".to_vec());
 	let value = Bytes(b"offchain_value".to_vec());
 
@@ -53,3 +71,25 @@ fn offchain_calls_considered_unsafe() {
 		Err(Error::UnsafeRpcCalled(_))
 	);
 }
+
+#[test]
+fn indexed_data_is_read_when_indexing_is_enabled() {
+	struct TestIndex;
+
+	impl IndexedData for TestIndex {
+		fn indexed_data(&self, key: &[u8]) -> Option<Vec<u8>> {
+			if key == b"indexed_key" { Some(b"indexed_value".to_vec()) } else { None }
+		}
+	}
+
+	// indexed data is public, it can be read by any client.
+	let offchain = Offchain::new(InMemOffchainStorage::default(), Some(Arc::new(TestIndex)), DenyUnsafe::Yes);
+	assert_matches!(
+		offchain.get_indexed(Bytes(b"indexed_key".to_vec())),
+		Ok(Some(ref v)) if *v == Bytes(b"indexed_value".to_vec())
+	);
+	assert_matches!(offchain.get_indexed(Bytes(b"other_key".to_vec())), Ok(None));
+
+	let offchain = Offchain::new(InMemOffchainStorage::default(), None, DenyUnsafe::No);
+	assert_matches!(offchain.get_indexed(Bytes(b"indexed_key".to_vec())), Err(Error::IndexingDisabled));
+}
@@ -18,6 +18,7 @@
 
 //! Substrate state API.
 
+mod runtime_metadata;
 mod state_full;
 mod state_light;
 
@@ -26,9 +27,9 @@ mod tests;
 
 use std::sync::Arc;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
-use rpc::{Result as RpcResult, futures::{Future, future::result}};
+use rpc::{Result as RpcResult, futures::{Future, future::{self, result}}};
 
-use sc_rpc_api::{Subscriptions, state::ReadProof};
+use sc_rpc_api::{DenyUnsafe, Subscriptions, state::{BlockTrace, ChildReadProofKeys, ReadProof, StorageChangesPage}};
 use sc_client_api::light::{RemoteBlockchain, Fetcher};
 use sp_core::{Bytes, storage::{StorageKey, PrefixedStorageKey, StorageData, StorageChangeSet}};
 use sp_version::RuntimeVersion;
@@ -40,11 +41,17 @@ use self::error::{Error, FutureResult};
 
 pub use sc_rpc_api::state::*;
 pub use sc_rpc_api::child_state::*;
-use sc_client_api::{ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider};
+pub use self::runtime_metadata::persist_runtime_metadata;
+use sc_client_api::{
+	ExecutorProvider, StorageProvider, BlockchainEvents, Backend, ProofProvider, BlockBackend, AuxStore,
+};
 use sp_blockchain::{HeaderMetadata, HeaderBackend};
 
 const STORAGE_KEYS_PAGED_MAX_COUNT: u32 = 1000;
 
+/// Targets recorded by `state_traceBlock` if none are given: the runtime and the storage accesses.
+const DEFAULT_TRACE_TARGETS: &str = "pallet,frame,state";
+
 /// State backend API.
 pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	where
@@ -109,6 +116,9 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 	/// Returns the runtime metadata as an opaque blob.
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes>;
 
+	/// Returns the stored metadata of the runtime with the given `spec_version`.
+	fn metadata_at_version(&self, spec_version: u32) -> FutureResult<Option<Bytes>>;
+
 	/// Get the runtime version.
 	fn runtime_version(&self, block: Option<Block::Hash>) -> FutureResult<RuntimeVersion>;
 
@@ -123,6 +133,16 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		keys: Vec<StorageKey>,
 	) -> FutureResult<Vec<StorageChangeSet<Block::Hash>>>;
 
+	/// Query historical storage entries (by key) of at most `count` blocks with changes,
+	/// starting from `from`.
+	fn query_storage_entries(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		count: u32,
+	) -> FutureResult<StorageChangesPage<Block::Hash>>;
+
 	/// Query storage entries (by key) starting at block hash given as the second parameter.
 	fn query_storage_at(
 		&self,
@@ -130,13 +150,24 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		at: Option<Block::Hash>
 	) -> FutureResult<Vec<StorageChangeSet<Block::Hash>>>;
 
-	/// Returns proof of storage entries at a specific block's state.
+	/// Returns proof of storage entries of the top trie and of child tries at a specific
+	/// block's state.
 	fn read_proof(
 		&self,
 		block: Option<Block::Hash>,
 		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
 	) -> FutureResult<ReadProof<Block::Hash>>;
 
+	/// Re-executes the given block with the spans and events of `targets` being recorded. Only the
+	/// storage accesses to keys starting with one of `storage_keys` are kept, if given.
+	fn trace_block(
+		&self,
+		block: Block::Hash,
+		targets: String,
+		storage_keys: Option<String>,
+	) -> FutureResult<BlockTrace<Block::Hash>>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(
 		&self,
@@ -165,27 +196,43 @@ pub trait StateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		_meta: Option<crate::metadata::Metadata>,
 		id: SubscriptionId,
 	) -> RpcResult<bool>;
+
+	/// New subscription to the keys starting with one of `prefixes`
+	fn subscribe_storage_prefix(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	);
+
+	/// Unsubscribe from storage prefix subscription
+	fn unsubscribe_storage_prefix(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
 }
 
 /// Create new state API that works on full node.
 pub fn new_full<BE, Block: BlockT, Client>(
 	client: Arc<Client>,
 	subscriptions: Subscriptions,
+	deny_unsafe: DenyUnsafe,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
 		BE: Backend<Block> + 'static,
 		Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 			+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-			+ CallApiAt<Block, Error = sp_blockchain::Error>
-			+ ProvideRuntimeApi<Block> + Send + Sync + 'static,
+			+ BlockBackend<Block> + CallApiAt<Block, Error = sp_blockchain::Error>
+			+ ProvideRuntimeApi<Block> + AuxStore + Send + Sync + 'static,
 		Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	let child_backend = Box::new(
 		self::state_full::FullState::new(client.clone(), subscriptions.clone())
 	);
 	let backend = Box::new(self::state_full::FullState::new(client, subscriptions));
-	(State { backend }, ChildState { backend: child_backend })
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// Create new state API that works on light node.
@@ -194,6 +241,7 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 	subscriptions: Subscriptions,
 	remote_blockchain: Arc<dyn RemoteBlockchain<Block>>,
 	fetcher: Arc<F>,
+	deny_unsafe: DenyUnsafe,
 ) -> (State<Block, Client>, ChildState<Block, Client>)
 	where
 		Block: BlockT + 'static,
@@ -217,12 +265,14 @@ pub fn new_light<BE, Block: BlockT, Client, F: Fetcher<Block>>(
 			remote_blockchain,
 			fetcher,
 	));
-	(State { backend }, ChildState { backend: child_backend })
+	(State { backend, deny_unsafe }, ChildState { backend: child_backend })
 }
 
 /// State API with subscriptions support.
 pub struct State<Block, Client> {
 	backend: Box<dyn StateBackend<Block, Client>>,
+	/// Whether to deny unsafe calls
+	deny_unsafe: DenyUnsafe,
 }
 
 impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
@@ -286,6 +336,10 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.metadata(block)
 	}
 
+	fn metadata_at_version(&self, spec_version: u32) -> FutureResult<Option<Bytes>> {
+		self.backend.metadata_at_version(spec_version)
+	}
+
 	fn query_storage(
 		&self,
 		keys: Vec<StorageKey>,
@@ -295,6 +349,27 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.query_storage(from, to, keys)
 	}
 
+	fn query_storage_entries(
+		&self,
+		keys: Vec<StorageKey>,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		count: u32,
+	) -> FutureResult<StorageChangesPage<Block::Hash>> {
+		if count == 0 {
+			return Box::new(result(Err(Error::ZeroCount)));
+		}
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: count,
+					max: STORAGE_KEYS_PAGED_MAX_COUNT,
+				}
+			)));
+		}
+		self.backend.query_storage_entries(from, to, keys, count)
+	}
+
 	fn query_storage_at(
 		&self,
 		keys: Vec<StorageKey>,
@@ -303,8 +378,27 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.query_storage_at(keys, at)
 	}
 
-	fn read_proof(&self, keys: Vec<StorageKey>, block: Option<Block::Hash>) -> FutureResult<ReadProof<Block::Hash>> {
-		self.backend.read_proof(block, keys)
+	fn read_proof(
+		&self,
+		keys: Vec<StorageKey>,
+		block: Option<Block::Hash>,
+		child_keys: Option<Vec<ChildReadProofKeys>>,
+	) -> FutureResult<ReadProof<Block::Hash>> {
+		self.backend.read_proof(block, keys, child_keys.unwrap_or_default())
+	}
+
+	fn trace_block(
+		&self,
+		block: Block::Hash,
+		targets: Option<String>,
+		storage_keys: Option<String>,
+	) -> FutureResult<BlockTrace<Block::Hash>> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		let targets = targets.unwrap_or_else(|| DEFAULT_TRACE_TARGETS.to_owned());
+		self.backend.trace_block(block, targets, storage_keys)
 	}
 
 	fn subscribe_storage(
@@ -320,6 +414,19 @@ impl<Block, Client> StateApi<Block::Hash> for State<Block, Client>
 		self.backend.unsubscribe_storage(meta, id)
 	}
 
+	fn subscribe_storage_prefix(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	) {
+		self.backend.subscribe_storage_prefix(meta, subscriber, prefixes);
+	}
+
+	fn unsubscribe_storage_prefix(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage_prefix(meta, id)
+	}
+
 	fn runtime_version(&self, at: Option<Block::Hash>) -> FutureResult<RuntimeVersion> {
 		self.backend.runtime_version(at)
 	}
@@ -352,6 +459,16 @@ pub trait ChildStateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		prefix: StorageKey,
 	) -> FutureResult<Vec<StorageKey>>;
 
+	/// Returns the keys with prefix from a child storage with pagination support.
+	fn storage_keys_paged(
+		&self,
+		block: Option<Block::Hash>,
+		storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>>;
+
 	/// Returns a child storage entry at a specific block's state.
 	fn storage(
 		&self,
@@ -360,6 +477,18 @@ pub trait ChildStateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		key: StorageKey,
 	) -> FutureResult<Option<StorageData>>;
 
+	/// Returns child storage entries for multiple keys at a specific block's state.
+	fn storage_entries(
+		&self,
+		block: Option<Block::Hash>,
+		storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+	) -> FutureResult<Vec<Option<StorageData>>> {
+		Box::new(future::join_all(keys.into_iter()
+			.map(|key| self.storage(block.clone(), storage_key.clone(), key))
+			.collect::<Vec<_>>()))
+	}
+
 	/// Returns the hash of a child storage entry at a block's state.
 	fn storage_hash(
 		&self,
@@ -378,6 +507,22 @@ pub trait ChildStateBackend<Block: BlockT, Client>: Send + Sync + 'static
 		Box::new(self.storage(block, storage_key, key)
 			.map(|x| x.map(|x| x.0.len() as u64)))
 	}
+
+	/// New child storage subscription
+	fn subscribe_storage(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		storage_key: PrefixedStorageKey,
+		keys: Option<Vec<StorageKey>>,
+	);
+
+	/// Unsubscribe from child storage subscription
+	fn unsubscribe_storage(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool>;
 }
 
 /// Child state API with subscriptions support.
@@ -410,6 +555,42 @@ impl<Block, Client> ChildStateApi<Block::Hash> for ChildState<Block, Client>
 		self.backend.storage_keys(block, storage_key, key_prefix)
 	}
 
+	fn storage_keys_paged(
+		&self,
+		storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+		block: Option<Block::Hash>,
+	) -> FutureResult<Vec<StorageKey>> {
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: count,
+					max: STORAGE_KEYS_PAGED_MAX_COUNT,
+				}
+			)));
+		}
+		self.backend.storage_keys_paged(block, storage_key, prefix, count, start_key)
+	}
+
+	fn storage_entries(
+		&self,
+		storage_key: PrefixedStorageKey,
+		keys: Vec<StorageKey>,
+		block: Option<Block::Hash>
+	) -> FutureResult<Vec<Option<StorageData>>> {
+		if keys.len() > STORAGE_KEYS_PAGED_MAX_COUNT as usize {
+			return Box::new(result(Err(
+				Error::InvalidCount {
+					value: keys.len() as u32,
+					max: STORAGE_KEYS_PAGED_MAX_COUNT,
+				}
+			)));
+		}
+		self.backend.storage_entries(block, storage_key, keys)
+	}
+
 	fn storage_hash(
 		&self,
 		storage_key: PrefixedStorageKey,
@@ -427,6 +608,24 @@ impl<Block, Client> ChildStateApi<Block::Hash> for ChildState<Block, Client>
 	) -> FutureResult<Option<u64>> {
 		self.backend.storage_size(block, storage_key, key)
 	}
+
+	fn subscribe_storage(
+		&self,
+		meta: Self::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		storage_key: PrefixedStorageKey,
+		keys: Option<Vec<StorageKey>>,
+	) {
+		self.backend.subscribe_storage(meta, subscriber, storage_key, keys);
+	}
+
+	fn unsubscribe_storage(
+		&self,
+		meta: Option<Self::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		self.backend.unsubscribe_storage(meta, id)
+	}
 }
 
 fn client_err(err: sp_blockchain::Error) -> Error {
@@ -0,0 +1,101 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Metadata of the runtimes of the chain.
+//!
+//! The client stores the metadata of the runtimes enacted by the blocks it imports, see
+//! [`sc_client_api::runtime_metadata`]. The metadata of the runtime of the best block is also
+//! stored at startup, as the database may predate it.
+
+use std::sync::Arc;
+use log::warn;
+use sc_client_api::{AuxStore, Backend, StorageProvider, runtime_metadata};
+use sp_api::{CallApiAt, Metadata, ProvideRuntimeApi};
+use sp_blockchain::{HeaderBackend, Result as ClientResult};
+use sp_core::{Bytes, storage::{well_known_keys, StorageKey}};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+fn code_hash<Block, BE, Client>(client: &Client, at: &BlockId<Block>) -> ClientResult<Option<Block::Hash>> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE>,
+{
+	client.storage_hash(at, &StorageKey(well_known_keys::CODE.to_vec()))
+}
+
+/// Returns the stored metadata of the runtime with the given `spec_version`.
+pub(crate) fn load_metadata<A: AuxStore>(store: &A, spec_version: u32) -> ClientResult<Option<Bytes>> {
+	Ok(runtime_metadata::load_metadata(store, spec_version)?.map(Bytes))
+}
+
+/// Returns the stored metadata of the runtime of the block `at`.
+pub(crate) fn load_metadata_at<Block, BE, Client>(
+	client: &Client,
+	at: &BlockId<Block>,
+) -> ClientResult<Option<Bytes>> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + AuxStore,
+{
+	let code_hash = match code_hash(client, at)? {
+		Some(code_hash) => code_hash,
+		None => return Ok(None),
+	};
+	match runtime_metadata::load_spec_version(client, &code_hash)? {
+		Some(spec_version) => load_metadata(client, spec_version),
+		None => Ok(None),
+	}
+}
+
+/// Stores the metadata of the runtime of the block `at`, unless it has already been stored.
+pub(crate) fn store_metadata<Block, BE, Client>(client: &Client, at: &BlockId<Block>) -> ClientResult<()> where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + CallApiAt<Block, Error = sp_blockchain::Error>
+		+ ProvideRuntimeApi<Block> + AuxStore,
+	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
+{
+	let code_hash = match code_hash(client, at)? {
+		Some(code_hash) => code_hash,
+		None => return Ok(()),
+	};
+	if runtime_metadata::load_spec_version(client, &code_hash)?.is_some() {
+		return Ok(())
+	}
+
+	let spec_version = client.runtime_version_at(at)?.spec_version;
+	let metadata: Bytes = client.runtime_api().metadata(at)?.into();
+	runtime_metadata::write_metadata(client, &code_hash, spec_version, &metadata)
+}
+
+/// Stores the metadata of the runtime of the best block, unless it has already been stored.
+///
+/// The client stores the metadata of the runtimes enacted by the blocks it imports, but not of
+/// the runtime enacted before the database was created with it, such as the genesis one.
+pub async fn persist_runtime_metadata<Block, BE, Client>(client: Arc<Client>) where
+	Block: BlockT,
+	BE: Backend<Block>,
+	Client: StorageProvider<Block, BE> + CallApiAt<Block, Error = sp_blockchain::Error>
+		+ ProvideRuntimeApi<Block> + AuxStore + HeaderBackend<Block>,
+	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
+{
+	let hash = client.info().best_hash;
+	if let Err(e) = store_metadata(&*client, &BlockId::Hash(hash)) {
+		warn!("Failed to store the metadata of the runtime of {:?}: {:?}", hash, e);
+	}
+}
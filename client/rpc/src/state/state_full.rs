@@ -16,7 +16,7 @@
 
 //! State API backend for full nodes.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 use std::ops::Range;
 use futures::{future, StreamExt as _, TryStreamExt as _};
@@ -24,7 +24,7 @@ use log::warn;
 use jsonrpc_pubsub::{typed::Subscriber, SubscriptionId};
 use rpc::{Result as RpcResult, futures::{stream, Future, Sink, Stream, future::result}};
 
-use sc_rpc_api::{Subscriptions, state::ReadProof};
+use sc_rpc_api::{Subscriptions, state::{BlockTrace, ChildReadProofKeys, ReadProof, StorageChangesPage, TraceEvent, TraceSpan}};
 use sc_client_api::backend::Backend;
 use sp_blockchain::{Result as ClientResult, Error as ClientError, HeaderMetadata, CachedHeaderMetadata, HeaderBackend};
 use sc_client_api::BlockchainEvents;
@@ -34,14 +34,19 @@ use sp_core::{
 };
 use sp_version::RuntimeVersion;
 use sp_runtime::{
-	generic::BlockId, traits::{Block as BlockT, NumberFor, SaturatedConversion, CheckedSub},
+	generic::BlockId, traits::{Block as BlockT, Header as HeaderT, NumberFor, SaturatedConversion, CheckedSub},
 };
 
-use sp_api::{Metadata, ProvideRuntimeApi, CallApiAt};
+use sp_api::{Core, Metadata, ProvideRuntimeApi, CallApiAt};
 
 use super::{StateBackend, ChildStateBackend, error::{FutureResult, Error, Result}, client_err};
 use std::marker::PhantomData;
-use sc_client_api::{CallExecutor, StorageProvider, ExecutorProvider, ProofProvider};
+use sc_client_api::{
+	AuxStore, CallExecutor, StorageProvider, ExecutorProvider, ProofProvider, BlockBackend, StorageProof,
+};
+
+/// Maximum number of keys proven at once by `state_getReadProof`.
+const READ_PROOF_CHUNK_SIZE: usize = 1000;
 
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
@@ -137,15 +142,58 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 		})
 	}
 
+	/// Returns the indices within `range.hashes` of the blocks of range.unfiltered_range changing
+	/// some of the keys, or `None` if the storage changes of these blocks are not indexed.
+	fn indexed_changed_blocks(
+		&self,
+		range: &QueryStorageRange<Block>,
+		keys: &[StorageKey],
+	) -> Result<Option<HashSet<usize>>> {
+		if range.unfiltered_range.start == range.unfiltered_range.end {
+			return Ok(None)
+		}
+		let first = range.first_number + range.unfiltered_range.start.saturated_into();
+		let last = range.first_number + (range.unfiltered_range.end - 1).saturated_into();
+		let mut changed_blocks = HashSet::new();
+		for key in keys {
+			let key_changes = match self.client.indexed_key_changes(first, last, key).map_err(client_err)? {
+				Some(key_changes) => key_changes,
+				None => return Ok(None),
+			};
+			for (number, hash) in key_changes {
+				let block = (number - range.first_number).saturated_into::<usize>();
+				// the blocks of the other forks are left out.
+				if range.hashes[block] == hash {
+					changed_blocks.insert(block);
+				}
+			}
+		}
+		Ok(Some(changed_blocks))
+	}
+
 	/// Iterates through range.unfiltered_range and check each block for changes of keys' values.
+	///
+	/// The blocks changing none of the keys are skipped if the storage changes are indexed.
+	/// Stops once `changes` holds `max_changes` change sets, returning the index within
+	/// `range.hashes` of the first block that hasn't been checked.
 	fn query_storage_unfiltered(
 		&self,
 		range: &QueryStorageRange<Block>,
 		keys: &[StorageKey],
 		last_values: &mut HashMap<StorageKey, Option<StorageData>>,
 		changes: &mut Vec<StorageChangeSet<Block::Hash>>,
-	) -> Result<()> {
+		max_changes: usize,
+	) -> Result<Option<usize>> {
+		let changed_blocks = self.indexed_changed_blocks(range, keys)?;
 		for block in range.unfiltered_range.start..range.unfiltered_range.end {
+			if changes.len() >= max_changes {
+				return Ok(Some(block))
+			}
+			// the values of the keys are always read at the first block.
+			let is_unchanged = changed_blocks.as_ref().map_or(false, |changed| !changed.contains(&block));
+			if block != range.unfiltered_range.start && is_unchanged {
+				continue;
+			}
 			let block_hash = range.hashes[block].clone();
 			let mut block_changes = StorageChangeSet { block: block_hash.clone(), changes: Vec::new() };
 			let id = BlockId::hash(block_hash);
@@ -166,31 +214,45 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 				changes.push(block_changes);
 			}
 		}
-		Ok(())
+		Ok(None)
 	}
 
 	/// Iterates through all blocks that are changing keys within range.filtered_range and collects these changes.
+	///
+	/// Only the first blocks changing keys are looked at, so that at most `max_changes` change
+	/// sets end up in `changes`. Returns the index within `range.hashes` of the first block that
+	/// hasn't been looked at.
 	fn query_storage_filtered(
 		&self,
 		range: &QueryStorageRange<Block>,
 		keys: &[StorageKey],
 		last_values: &HashMap<StorageKey, Option<StorageData>>,
 		changes: &mut Vec<StorageChangeSet<Block::Hash>>,
-	) -> Result<()> {
+		max_changes: usize,
+	) -> Result<Option<usize>> {
 		let (begin, end) = match range.filtered_range {
 			Some(ref filtered_range) => (
 				range.first_number + filtered_range.start.saturated_into(),
 				BlockId::Hash(range.hashes[filtered_range.end - 1].clone())
 			),
-			None => return Ok(()),
+			None => return Ok(None),
 		};
+		let keys_changes = keys.iter()
+			.map(|key| self.client.key_changes(begin, end, None, key).map_err(client_err))
+			.collect::<Result<Vec<_>>>()?;
+		// the changes trie tells which blocks change the keys without reading their values, so
+		// the blocks past the ones filling the page are left out before reading anything.
+		let next = keys_changes.iter()
+			.flat_map(|key_changes| key_changes.iter().map(|(block, _)| *block))
+			.collect::<BTreeSet<_>>()
+			.into_iter()
+			.nth(max_changes.saturating_sub(changes.len()));
 		let mut changes_map: BTreeMap<NumberFor<Block>, StorageChangeSet<Block::Hash>> = BTreeMap::new();
-		for key in keys {
+		for (key, key_changes) in keys.iter().zip(keys_changes) {
 			let mut last_block = None;
 			let mut last_value = last_values.get(key).cloned().unwrap_or_default();
-			let key_changes = self.client.key_changes(begin, end, None, key).map_err(client_err)?;
 			for (block, _) in key_changes.into_iter().rev() {
-				if last_block == Some(block) {
+				if last_block == Some(block) || next.map_or(false, |next| block >= next) {
 					continue;
 				}
 
@@ -212,7 +274,7 @@ impl<BE, Block: BlockT, Client> FullState<BE, Block, Client>
 			changes.reserve(additional_capacity);
 		}
 		changes.extend(changes_map.into_iter().map(|(_, cs)| cs));
-		Ok(())
+		Ok(next.map(|next| (next - range.first_number).saturated_into()))
 	}
 }
 
@@ -221,8 +283,8 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	BE: Backend<Block> + 'static,
 	Client: ExecutorProvider<Block> + StorageProvider<Block, BE> + ProofProvider<Block> + HeaderBackend<Block>
 		+ HeaderMetadata<Block, Error = sp_blockchain::Error> + BlockchainEvents<Block>
-		+ CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
-		+ Send + Sync + 'static,
+		+ BlockBackend<Block> + CallApiAt<Block, Error = sp_blockchain::Error> + ProvideRuntimeApi<Block>
+		+ AuxStore + Send + Sync + 'static,
 	Client::Api: Metadata<Block, Error = sp_blockchain::Error>,
 {
 	fn call(
@@ -312,9 +374,19 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	fn metadata(&self, block: Option<Block::Hash>) -> FutureResult<Bytes> {
 		Box::new(result(
 			self.block_or_best(block)
-				.and_then(|block|
-					self.client.runtime_api().metadata(&BlockId::Hash(block)).map(Into::into)
-				)
+				.and_then(|block| {
+					let at = BlockId::Hash(block);
+					self.client.runtime_api().metadata(&at).map(Into::into).or_else(|err| {
+						// the runtime of old blocks may not be instantiable any more.
+						super::runtime_metadata::load_metadata_at(&*self.client, &at)?.ok_or(err)
+					})
+				})
+				.map_err(client_err)))
+	}
+
+	fn metadata_at_version(&self, spec_version: u32) -> FutureResult<Option<Bytes>> {
+		Box::new(result(
+			super::runtime_metadata::load_metadata(&*self.client, spec_version)
 				.map_err(client_err)))
 	}
 
@@ -335,13 +407,37 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 			let range = self.split_query_storage_range(from, to)?;
 			let mut changes = Vec::new();
 			let mut last_values = HashMap::new();
-			self.query_storage_unfiltered(&range, &keys, &mut last_values, &mut changes)?;
-			self.query_storage_filtered(&range, &keys, &last_values, &mut changes)?;
+			self.query_storage_unfiltered(&range, &keys, &mut last_values, &mut changes, usize::max_value())?;
+			self.query_storage_filtered(&range, &keys, &last_values, &mut changes, usize::max_value())?;
 			Ok(changes)
 		};
 		Box::new(result(call_fn()))
 	}
 
+	fn query_storage_entries(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+		count: u32,
+	) -> FutureResult<StorageChangesPage<Block::Hash>> {
+		let call_fn = move || {
+			let range = self.split_query_storage_range(from, to)?;
+			let count = count as usize;
+			let mut changes = Vec::new();
+			let mut last_values = HashMap::new();
+			let next = match self.query_storage_unfiltered(&range, &keys, &mut last_values, &mut changes, count)? {
+				Some(next) => Some(next),
+				None => self.query_storage_filtered(&range, &keys, &last_values, &mut changes, count)?,
+			};
+			Ok(StorageChangesPage {
+				changes,
+				next: next.map(|next| range.hashes[next].clone()),
+			})
+		};
+		Box::new(result(call_fn()))
+	}
+
 	fn query_storage_at(
 		&self,
 		keys: Vec<StorageKey>,
@@ -355,22 +451,100 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 		&self,
 		block: Option<Block::Hash>,
 		keys: Vec<StorageKey>,
+		child_keys: Vec<ChildReadProofKeys>,
 	) -> FutureResult<ReadProof<Block::Hash>> {
 		Box::new(result(
 			self.block_or_best(block)
 				.and_then(|block| {
-					self.client
-						.read_proof(
+					let child_keys = child_keys.into_iter()
+						.map(|child| match ChildType::from_prefixed_key(&child.storage_key) {
+							Some((ChildType::ParentKeyId, storage_key)) => Ok((
+								ChildInfo::new_default(storage_key),
+								child.keys.into_iter().map(|key| key.0).collect(),
+							)),
+							None => Err("Invalid child storage key".into()),
+						})
+						.collect::<ClientResult<Vec<_>>>()?;
+					let keys = keys.into_iter().map(|key| key.0).collect();
+
+					// the proofs of the chunks share the nodes close to the roots, which are
+					// only kept once when merging them.
+					let proofs = read_proof_chunks(keys, child_keys, READ_PROOF_CHUNK_SIZE)
+						.into_iter()
+						.map(|(keys, child_keys)| self.client.read_proof_with_child_keys(
 							&BlockId::Hash(block),
-							&mut keys.iter().map(|key| key.0.as_ref()),
-						)
-						.map(|proof| proof.iter_nodes().map(|node| node.into()).collect())
-						.map(|proof| ReadProof { at: block, proof })
+							&mut keys.iter().map(|key| key.as_ref()),
+							&child_keys,
+						))
+						.collect::<ClientResult<Vec<_>>>()?;
+					let proof = StorageProof::merge(proofs).iter_nodes().map(|node| node.into()).collect();
+					Ok(ReadProof { at: block, proof })
 				})
 				.map_err(client_err),
 		))
 	}
 
+	fn trace_block(
+		&self,
+		block: Block::Hash,
+		targets: String,
+		storage_keys: Option<String>,
+	) -> FutureResult<BlockTrace<Block::Hash>> {
+		let trace_fn = || -> ClientResult<BlockTrace<Block::Hash>> {
+			let id = BlockId::Hash(block);
+			let unknown_block = || ClientError::UnknownBlock(format!("{:?}", block));
+			let header = self.client.header(id.clone())?.ok_or_else(unknown_block)?;
+			let extrinsics = self.client.block_body(&id)?.ok_or_else(unknown_block)?;
+			let parent = BlockId::Hash(*header.parent_hash());
+
+			// The changes made by the execution are discarded along with the runtime API instance.
+			let (execution, traces) = sc_tracing::trace_with(&targets, || {
+				self.client.runtime_api().execute_block(&parent, Block::new(header, extrinsics))
+			});
+			execution?;
+
+			let key_prefixes = storage_keys.map(|keys| keys
+				.split(',')
+				.map(|key| key.trim().trim_start_matches("0x").to_lowercase())
+				.collect::<Vec<_>>()
+			);
+			let is_wanted_access = |event: &sc_tracing::TracedEvent| {
+				match key_prefixes {
+					Some(ref prefixes) if event.target == "state" => event.values
+						.iter()
+						.find(|(name, _)| name == "key")
+						.map_or(false, |(_, key)| prefixes.iter().any(|prefix| key.starts_with(prefix))),
+					_ => true,
+				}
+			};
+
+			Ok(BlockTrace {
+				block_hash: block,
+				spans: traces.spans.into_iter()
+					.map(|span| TraceSpan {
+						id: span.id,
+						parent_id: span.parent_id,
+						name: span.name,
+						target: span.target,
+						line: span.line,
+						overall_time: span.overall_time.as_nanos().saturated_into(),
+						values: span.values.into_iter().collect(),
+					})
+					.collect(),
+				events: traces.events.into_iter()
+					.filter(is_wanted_access)
+					.map(|event| TraceEvent {
+						target: event.target,
+						parent_id: event.parent_id,
+						values: event.values.into_iter().collect(),
+					})
+					.collect(),
+				targets,
+			})
+		};
+		Box::new(result(trace_fn().map_err(client_err)))
+	}
+
 	fn subscribe_runtime_version(
 		&self,
 		_meta: crate::metadata::Metadata,
@@ -489,6 +663,47 @@ impl<BE, Block, Client> StateBackend<Block, Client> for FullState<BE, Block, Cli
 	) -> RpcResult<bool> {
 		Ok(self.subscriptions.cancel(id))
 	}
+
+	fn subscribe_storage_prefix(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		prefixes: Vec<StorageKey>,
+	) {
+		let stream = match self.client.storage_changes_prefix_notification_stream(&prefixes) {
+			Ok(stream) => stream,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = stream
+				.map(|(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet {
+					block,
+					changes: changes.iter()
+						.filter_map(|(o_sk, k, v)| if o_sk.is_none() {
+							Some((k.clone(), v.cloned()))
+						} else { None }).collect(),
+				})))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(stream)
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_storage_prefix(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client> where
@@ -522,6 +737,32 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 				.map_err(client_err)))
 	}
 
+	fn storage_keys_paged(
+		&self,
+		block: Option<Block::Hash>,
+		storage_key: PrefixedStorageKey,
+		prefix: Option<StorageKey>,
+		count: u32,
+		start_key: Option<StorageKey>,
+	) -> FutureResult<Vec<StorageKey>> {
+		Box::new(result(
+			self.block_or_best(block)
+				.and_then(|block| {
+					let child_info = match ChildType::from_prefixed_key(&storage_key) {
+						Some((ChildType::ParentKeyId, storage_key)) => ChildInfo::new_default(storage_key),
+						None => return Err("Invalid child storage key".into()),
+					};
+					self.client.child_storage_keys_iter(
+						&BlockId::Hash(block),
+						child_info,
+						prefix.as_ref(),
+						start_key.as_ref(),
+					)
+				})
+				.map(|v| v.take(count as usize).collect())
+				.map_err(client_err)))
+	}
+
 	fn storage(
 		&self,
 		block: Option<Block::Hash>,
@@ -565,6 +806,111 @@ impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block
 				})
 				.map_err(client_err)))
 	}
+
+	fn subscribe_storage(
+		&self,
+		_meta: crate::metadata::Metadata,
+		subscriber: Subscriber<StorageChangeSet<Block::Hash>>,
+		storage_key: PrefixedStorageKey,
+		keys: Option<Vec<StorageKey>>,
+	) {
+		let child_key = match ChildType::from_prefixed_key(&storage_key) {
+			Some((ChildType::ParentKeyId, child_key)) => StorageKey(child_key.to_vec()),
+			None => {
+				let _ = subscriber.reject(client_err("Invalid child storage key".into()).into());
+				return;
+			},
+		};
+		let child_filter = [(child_key.clone(), keys.clone())];
+		let stream = match self.client.storage_changes_notification_stream(
+			Some(&[]),
+			Some(&child_filter[..]),
+		) {
+			Ok(stream) => stream,
+			Err(err) => {
+				let _ = subscriber.reject(client_err(err).into());
+				return;
+			},
+		};
+
+		// initial values
+		let initial = stream::iter_result(keys
+			.map(|keys| {
+				let block = self.client.info().best_hash;
+				let changes = keys
+					.into_iter()
+					.map(|key| ChildStateBackend::storage(self, Some(block.clone()), storage_key.clone(), key.clone())
+						.map(|val| (key.clone(), val))
+						.wait()
+						.unwrap_or_else(|_| (key, None))
+					)
+					.collect();
+				vec![Ok(Ok(StorageChangeSet { block, changes }))]
+			}).unwrap_or_default());
+
+		self.subscriptions.add(subscriber, |sink| {
+			let stream = stream
+				.map(move |(block, changes)| Ok::<_, ()>(Ok(StorageChangeSet {
+					block,
+					changes: changes.iter()
+						.filter_map(|(o_sk, k, v)| if o_sk == Some(&child_key) {
+							Some((k.clone(), v.cloned()))
+						} else { None }).collect(),
+				})))
+				.compat();
+
+			sink
+				.sink_map_err(|e| warn!("Error sending notifications: {:?}", e))
+				.send_all(initial.chain(stream))
+				// we ignore the resulting Stream (if the first stream is over we are unsubscribed)
+				.map(|_| ())
+		});
+	}
+
+	fn unsubscribe_storage(
+		&self,
+		_meta: Option<crate::metadata::Metadata>,
+		id: SubscriptionId,
+	) -> RpcResult<bool> {
+		Ok(self.subscriptions.cancel(id))
+	}
+}
+
+/// Keys of the top trie and of child tries to prove.
+pub(crate) type ReadProofKeys = (Vec<Vec<u8>>, Vec<(ChildInfo, Vec<Vec<u8>>)>);
+
+/// Splits the keys of a read proof into chunks of at most `size` keys, which are proven one
+/// after the other so that proving a large key set doesn't read all of them at once.
+pub(crate) fn read_proof_chunks(
+	keys: Vec<Vec<u8>>,
+	child_keys: Vec<(ChildInfo, Vec<Vec<u8>>)>,
+	size: usize,
+) -> Vec<ReadProofKeys> {
+	let mut chunks = Vec::new();
+	let mut chunk = ReadProofKeys::default();
+	let mut len = 0;
+	let keys = keys.into_iter().map(|key| (None, key))
+		.chain(child_keys.into_iter().flat_map(|(child_info, keys)| {
+			keys.into_iter().map(move |key| (Some(child_info.clone()), key))
+		}));
+	for (child_info, key) in keys {
+		if len == size {
+			chunks.push(std::mem::take(&mut chunk));
+			len = 0;
+		}
+		match child_info {
+			None => chunk.0.push(key),
+			Some(child_info) => match chunk.1.last_mut() {
+				Some((last, keys)) if *last == child_info => keys.push(key),
+				_ => chunk.1.push((child_info, vec![key])),
+			},
+		}
+		len += 1;
+	}
+	if len > 0 {
+		chunks.push(chunk);
+	}
+	chunks
 }
 
 /// Splits passed range into two subranges where:
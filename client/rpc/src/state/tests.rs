@@ -17,7 +17,8 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use super::*;
-use super::state_full::split_range;
+use sc_client_api::runtime_metadata::write_metadata;
+use super::state_full::{read_proof_chunks, split_range};
 use self::error::Error;
 
 use std::sync::Arc;
@@ -25,6 +26,7 @@ use assert_matches::assert_matches;
 use futures01::stream::Stream;
 use sp_core::{storage::ChildInfo, ChangesTrieConfiguration};
 use sp_core::hash::H256;
+use sp_core::hexdisplay::HexDisplay;
 use sc_block_builder::BlockBuilderProvider;
 use sp_io::hashing::blake2_256;
 use substrate_test_runtime_client::{
@@ -55,7 +57,7 @@ fn should_return_storage() {
 		.add_extra_child_storage(&child_info, KEY.to_vec(), CHILD_VALUE.to_vec())
 		.build();
 	let genesis_hash = client.genesis_hash();
-	let (client, child) = new_full(Arc::new(client), Subscriptions::new(Arc::new(TaskExecutor)));
+	let (client, child) = new_full(Arc::new(client), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 	let key = StorageKey(KEY.to_vec());
 
 	assert_eq!(
@@ -90,7 +92,7 @@ fn should_return_child_storage() {
 		.add_child_storage(&child_info, "key", vec![42_u8])
 		.build());
 	let genesis_hash = client.genesis_hash();
-	let (_client, child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)));
+	let (_client, child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 	let child_key = prefixed_storage_key();
 	let key = StorageKey(b"key".to_vec());
 
@@ -121,11 +123,118 @@ fn should_return_child_storage() {
 	);
 }
 
+#[test]
+fn should_return_child_storage_keys_paged_and_entries() {
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+		.add_child_storage(&child_info, "key1", vec![1_u8])
+		.add_child_storage(&child_info, "key2", vec![2_u8])
+		.add_child_storage(&child_info, "key3", vec![3_u8])
+		.build());
+	let (_client, child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+	let child_key = prefixed_storage_key();
+	let key = |k: &[u8]| StorageKey(k.to_vec());
+
+	assert_eq!(
+		child.storage_keys_paged(child_key.clone(), Some(key(b"key")), 2, None, None).wait().unwrap(),
+		vec![key(b"key1"), key(b"key2")],
+	);
+	assert_eq!(
+		child.storage_keys_paged(child_key.clone(), Some(key(b"key")), 2, Some(key(b"key2")), None)
+			.wait().unwrap(),
+		vec![key(b"key3")],
+	);
+	assert_matches!(
+		child.storage_keys_paged(child_key.clone(), None, 1001, None, None).wait(),
+		Err(Error::InvalidCount { value: 1001, max: 1000 })
+	);
+	assert_eq!(
+		child.storage_entries(child_key, vec![key(b"key3"), key(b"missing"), key(b"key1")], None)
+			.wait().unwrap(),
+		vec![Some(StorageData(vec![3])), None, Some(StorageData(vec![1]))],
+	);
+}
+
+#[test]
+fn should_prove_top_and_child_storage_together() {
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let client = Arc::new(TestClientBuilder::new()
+		.add_extra_storage(b":mock".to_vec(), vec![1_u8])
+		.add_extra_child_storage(&child_info, b"key".to_vec(), vec![42_u8])
+		.build());
+	let genesis_hash = client.genesis_hash();
+	let root = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap().state_root;
+	let (api, _child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+	let key = |k: &[u8]| StorageKey(k.to_vec());
+	let child_keys = ChildReadProofKeys { storage_key: prefixed_storage_key(), keys: vec![key(b"key")] };
+
+	let proof = api.read_proof(vec![key(b":mock")], Some(genesis_hash), Some(vec![child_keys]))
+		.wait()
+		.unwrap();
+
+	assert_eq!(proof.at, genesis_hash);
+	let proof = sc_client_api::StorageProof::new(proof.proof.into_iter().map(|node| node.0).collect());
+	assert_eq!(
+		sp_state_machine::read_proof_check::<sp_runtime::traits::BlakeTwo256, _>(
+			root, proof.clone(), &[b":mock"],
+		).unwrap().remove(&b":mock"[..]),
+		Some(Some(vec![1])),
+	);
+	assert_eq!(
+		sp_state_machine::read_child_proof_check::<sp_runtime::traits::BlakeTwo256, _>(
+			root, proof, &child_info, &[b"key"],
+		).unwrap().remove(&b"key"[..]),
+		Some(Some(vec![42])),
+	);
+}
+
+#[test]
+fn read_proof_keys_are_chunked() {
+	let child_info = ChildInfo::new_default(STORAGE_KEY);
+	let keys = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+	let child_keys = vec![(child_info.clone(), vec![b"d".to_vec(), b"e".to_vec()])];
+
+	assert_eq!(read_proof_chunks(keys, child_keys, 2), vec![
+		(vec![b"a".to_vec(), b"b".to_vec()], vec![]),
+		(vec![b"c".to_vec()], vec![(child_info.clone(), vec![b"d".to_vec()])]),
+		(vec![], vec![(child_info, vec![b"e".to_vec()])]),
+	]);
+}
+
+#[test]
+fn should_send_initial_child_storage_values() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let child_info = ChildInfo::new_default(STORAGE_KEY);
+		let client = Arc::new(substrate_test_runtime_client::TestClientBuilder::new()
+			.add_child_storage(&child_info, "key", vec![42_u8])
+			.build());
+		let (_client, child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+
+		child.subscribe_storage(
+			Default::default(),
+			subscriber,
+			prefixed_storage_key(),
+			Some(vec![StorageKey(b"key".to_vec())]),
+		);
+
+		// assert id assigned
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+	}
+
+	// assert initial values sent to transport
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.unwrap().contains("0x2a"));
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
 #[test]
 fn should_call_contract() {
 	let client = Arc::new(substrate_test_runtime_client::new());
 	let genesis_hash = client.genesis_hash();
-	let (client, _child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)));
+	let (client, _child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	assert_matches!(
 		client.call("balanceOf".into(), Bytes(vec![1,2,3]), Some(genesis_hash).into()).wait(),
@@ -133,13 +242,58 @@ fn should_call_contract() {
 	)
 }
 
+#[test]
+fn should_trace_block_storage_accesses() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(runtime::Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = block.header.hash();
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let alice_balance_key = HexDisplay::from(
+		&blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()))
+	).to_string();
+	let trace = api.trace_block(
+		block_hash,
+		Some("state".into()),
+		Some(format!("0x{}", alice_balance_key)),
+	).wait().unwrap();
+
+	assert_eq!(trace.block_hash, block_hash);
+	assert!(!trace.events.is_empty());
+	for event in &trace.events {
+		assert_eq!(event.target, "state");
+		assert_eq!(event.values["key"], alice_balance_key);
+	}
+}
+
+#[test]
+fn should_deny_unsafe_block_tracing() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.genesis_hash();
+	let (api, _child) = new_full(client, Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::Yes);
+
+	assert_matches!(
+		api.trace_block(genesis_hash, None, None).wait(),
+		Err(Error::UnsafeRpcCalled(_))
+	);
+}
+
 #[test]
 fn should_notify_about_storage_changes() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		api.subscribe_storage(Default::default(), subscriber, None.into());
 
@@ -170,7 +324,7 @@ fn should_send_initial_storage_changes_and_notifications() {
 
 	{
 		let mut client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
 
@@ -202,10 +356,45 @@ fn should_send_initial_storage_changes_and_notifications() {
 	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
 }
 
+#[test]
+fn should_notify_about_storage_changes_under_prefix() {
+	let (subscriber, id, transport) = Subscriber::new_test("test");
+
+	{
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+
+		let alice_balance_key = blake2_256(&runtime::system::balance_of_key(AccountKeyring::Alice.into()));
+
+		api.subscribe_storage_prefix(Default::default(), subscriber, vec![
+			StorageKey(alice_balance_key[..16].to_vec()),
+		]);
+
+		// assert id assigned
+		assert_eq!(executor::block_on(id.compat()), Ok(Ok(SubscriptionId::Number(1))));
+
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		}).unwrap();
+		let block = builder.build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+	}
+
+	// assert notification sent to transport, without initial values
+	let (notification, next) = executor::block_on(transport.into_future().compat()).unwrap();
+	assert!(notification.is_some());
+	// no more notifications on this channel
+	assert_eq!(executor::block_on(next.into_future().compat()).unwrap().0, None);
+}
+
 #[test]
 fn should_query_storage() {
 	fn run_tests(mut client: Arc<TestClient>, has_changes_trie_config: bool) {
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		let mut add_block = |nonce| {
 			let mut builder = client.new_block(Default::default()).unwrap();
@@ -292,6 +481,35 @@ fn should_query_storage() {
 
 		assert_eq!(result.wait().unwrap(), expected);
 
+		// Query all changes, two blocks at a time.
+		let page = api.query_storage_entries(keys.clone(), genesis_hash, None, 2).wait().unwrap();
+
+		assert_eq!(page.changes, &expected[..2]);
+		assert_eq!(page.next, Some(block2_hash));
+
+		// The next page starts with the state of all keys.
+		let page = api.query_storage_entries(keys.clone(), block2_hash, None, 2).wait().unwrap();
+
+		assert_eq!(page.changes, vec![StorageChangeSet {
+			block: block2_hash,
+			changes: vec![
+				(StorageKey(vec![1]), None),
+				(StorageKey(vec![2]), Some(StorageData(vec![2]))),
+				(StorageKey(vec![3]), None),
+				(StorageKey(vec![4]), Some(StorageData(vec![4]))),
+				(StorageKey(vec![5]), Some(StorageData(vec![1]))),
+			],
+		}]);
+		assert_eq!(page.next, None);
+
+		// Empty pages would never advance.
+		let result = api.query_storage_entries(keys.clone(), genesis_hash, None, 0);
+
+		assert_eq!(
+			result.wait().map_err(|e| e.to_string()),
+			Err(Error::ZeroCount).map_err(|e| e.to_string())
+		);
+
 		// Inverted range.
 		let result = api.query_storage(
 			keys.clone(),
@@ -422,7 +640,7 @@ fn should_split_ranges() {
 #[test]
 fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
-	let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+	let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 	let result = "{\"specName\":\"test\",\"implName\":\"parity-test\",\"authoringVersion\":1,\
 		\"specVersion\":2,\"implVersion\":2,\"apis\":[[\"0xdf6acb689907609b\",3],\
@@ -439,13 +657,33 @@ fn should_return_runtime_version() {
 	assert_eq!(deserialized, runtime_version);
 }
 
+#[test]
+fn should_return_stored_metadata() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
+	let code_hash = client.storage_hash(
+		&BlockId::Number(0),
+		&StorageKey(sp_core::storage::well_known_keys::CODE.to_vec()),
+	).unwrap().unwrap();
+
+	assert_eq!(api.metadata_at_version(2).wait().unwrap(), None);
+	// the test runtime can't build its metadata.
+	assert!(api.metadata(None).wait().is_err());
+
+	write_metadata(&*client, &code_hash, 2, b"metadata").unwrap();
+
+	assert_eq!(api.metadata_at_version(2).wait().unwrap(), Some(Bytes(b"metadata".to_vec())));
+	assert_eq!(api.metadata_at_version(1).wait().unwrap(), None);
+	assert_eq!(api.metadata(None).wait().unwrap(), Bytes(b"metadata".to_vec()));
+}
+
 #[test]
 fn should_notify_on_runtime_version_initially() {
 	let (subscriber, id, transport) = Subscriber::new_test("test");
 
 	{
 		let client = Arc::new(substrate_test_runtime_client::new());
-		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)));
+		let (api, _child) = new_full(client.clone(), Subscriptions::new(Arc::new(TaskExecutor)), DenyUnsafe::No);
 
 		api.subscribe_runtime_version(Default::default(), subscriber);
 
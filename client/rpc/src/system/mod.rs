@@ -30,7 +30,7 @@ use sp_runtime::traits::{self, Header as HeaderT};
 use self::error::Result;
 
 pub use sc_rpc_api::system::*;
-pub use self::helpers::{SystemInfo, Health, PeerInfo, NodeRole};
+pub use self::helpers::{SystemInfo, Health, PeerInfo, PeerRequestStats, NodeRole, IpFilter, TrafficDump, SyncState, GapSync, PruningStatus};
 pub use self::gen_client::Client as SystemClient;
 
 macro_rules! bail_if_unsafe {
@@ -65,8 +65,26 @@ pub enum Request<B: traits::Block> {
 	NetworkAddReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return any potential parse error.
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
+	/// Must return the filter applied to incoming connections.
+	NetworkIpFilter(oneshot::Sender<IpFilter>),
+	/// Must return any potential parse error.
+	NetworkSetIpFilter(IpFilter, oneshot::Sender<Result<()>>),
+	/// Must return the traffic dump currently running, if any.
+	NetworkTrafficDump(oneshot::Sender<Option<TrafficDump>>),
+	/// Must return any error starting the dump.
+	NetworkSetTrafficDump(Option<TrafficDump>, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
-	NodeRoles(oneshot::Sender<Vec<NodeRole>>)
+	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
+	/// Must return the block hashes pinned at given heights.
+	PinnedBlocks(oneshot::Sender<Vec<(<B::Header as HeaderT>::Number, B::Hash)>>),
+	/// Must pin the hash of the block at the given height.
+	PinBlock(<B::Header as HeaderT>::Number, B::Hash, oneshot::Sender<()>),
+	/// Must unpin the hash of the block at the given height, and return whether there was one.
+	UnpinBlock(<B::Header as HeaderT>::Number, oneshot::Sender<bool>),
+	/// Must return the state of the syncing of the node.
+	SyncState(oneshot::Sender<SyncState<<B::Header as HeaderT>::Number>>),
+	/// Must return the status of the pruning of the states, if any.
+	PruningStatus(oneshot::Sender<Option<PruningStatus>>),
 }
 
 impl<B: traits::Block> System<B> {
@@ -184,9 +202,138 @@ impl<B: traits::Block> SystemApi<B::Hash, <B::Header as HeaderT>::Number> for Sy
 		}.boxed().compat()
 	}
 
+	fn system_ip_filter(&self)
+		-> Compat<BoxFuture<'static, rpc::Result<IpFilter>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkIpFilter(tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
+	fn system_set_ip_filter(&self, filter: IpFilter)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkSetIpFilter(filter, tx));
+		async move {
+			match rx.await {
+				Ok(Ok(())) => Ok(()),
+				Ok(Err(e)) => Err(rpc::Error::from(e)),
+				Err(_) => Err(rpc::Error::internal_error()),
+			}
+		}.boxed().compat()
+	}
+
+	fn system_traffic_dump(&self)
+		-> Compat<BoxFuture<'static, rpc::Result<Option<TrafficDump>>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkTrafficDump(tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
+	fn system_set_traffic_dump(&self, dump: Option<TrafficDump>)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkSetTrafficDump(dump, tx));
+		async move {
+			match rx.await {
+				Ok(Ok(())) => Ok(()),
+				Ok(Err(e)) => Err(rpc::Error::from(e)),
+				Err(_) => Err(rpc::Error::internal_error()),
+			}
+		}.boxed().compat()
+	}
+
+	fn system_set_log_filter(&self, directives: String)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let result = sc_tracing::logging::set_log_filter(&directives)
+			.map_err(|e| rpc::Error::from(error::Error::LogFilterFailed(e)));
+		async move { result }.boxed().compat()
+	}
+
+	fn system_reset_log_filter(&self)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let result = sc_tracing::logging::reload_logger()
+			.map_err(|e| rpc::Error::from(error::Error::LogFilterFailed(e)));
+		async move { result }.boxed().compat()
+	}
+
 	fn system_node_roles(&self) -> Receiver<Vec<NodeRole>> {
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
 		Receiver(Compat::new(rx))
 	}
+
+	fn system_pinned_blocks(&self)
+		-> Compat<BoxFuture<'static, rpc::Result<Vec<(<B::Header as HeaderT>::Number, B::Hash)>>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::PinnedBlocks(tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
+	fn system_pin_block(&self, number: <B::Header as HeaderT>::Number, hash: B::Hash)
+		-> Compat<BoxFuture<'static, std::result::Result<(), rpc::Error>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::PinBlock(number, hash, tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
+	fn system_unpin_block(&self, number: <B::Header as HeaderT>::Number)
+		-> Compat<BoxFuture<'static, rpc::Result<bool>>>
+	{
+		bail_if_unsafe!(self.deny_unsafe);
+
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::UnpinBlock(number, tx));
+
+		async move {
+			rx.await.map_err(|_| rpc::Error::internal_error())
+		}.boxed().compat()
+	}
+
+	fn system_sync_state(&self) -> Receiver<SyncState<<B::Header as HeaderT>::Number>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::SyncState(tx));
+		Receiver(Compat::new(rx))
+	}
+
+	fn system_pruning_status(&self) -> Receiver<Option<PruningStatus>> {
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::PruningStatus(tx));
+		Receiver(Compat::new(rx))
+	}
 }
@@ -75,6 +75,10 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 							protocol_version: 1,
 							best_hash: Default::default(),
 							best_number: 1,
+							finalized_hash: None,
+							finalized_number: Some(1),
+							notification_protocols: vec!["/substrate/block-announces/1".into()],
+							requests: Default::default(),
 						});
 					}
 					let _ = sender.send(peers);
@@ -103,9 +107,62 @@ fn api<T: Into<Option<Status>>>(sync: T) -> System<Block> {
 						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
 					};
 				}
+				Request::NetworkIpFilter(sender) => {
+					let _ = sender.send(IpFilter {
+						allow: None,
+						deny: vec!["198.51.100.0/24".into()],
+					});
+				}
+				Request::NetworkSetIpFilter(filter, sender) => {
+					let invalid = filter.allow.iter().flatten().chain(filter.deny.iter())
+						.find(|range| range.parse::<sc_network::config::IpNetwork>().is_err());
+					let _ = match invalid {
+						Some(range) => sender.send(Err(error::Error::MalformattedIpRange(range.clone()))),
+						None => sender.send(Ok(())),
+					};
+				}
+				Request::NetworkTrafficDump(sender) => {
+					let _ = sender.send(Some(TrafficDump {
+						path: "/tmp/traffic".into(),
+						include_payloads: false,
+					}));
+				}
+				Request::NetworkSetTrafficDump(dump, sender) => {
+					let _ = match dump {
+						Some(dump) if dump.path.is_empty() =>
+							sender.send(Err(error::Error::TrafficDumpFailed("empty path".into()))),
+						_ => sender.send(Ok(())),
+					};
+				}
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				}
+				Request::PinnedBlocks(sender) => {
+					let _ = sender.send(vec![(1, Default::default())]);
+				}
+				Request::PinBlock(_, _, sender) => {
+					let _ = sender.send(());
+				}
+				Request::UnpinBlock(number, sender) => {
+					let _ = sender.send(number == 1);
+				}
+				Request::SyncState(sender) => {
+					let _ = sender.send(SyncState {
+						starting_block: 1,
+						current_block: 2,
+						highest_block: Some(3),
+						gap_sync: None,
+					});
+				}
+				Request::PruningStatus(sender) => {
+					let _ = sender.send(Some(PruningStatus {
+						window: Some(256),
+						oldest_block: 10,
+						last_canonicalized: Some(265),
+						non_canonical: 2,
+						pending_prunes: 0,
+					}));
+				}
 			};
 
 			future::ready(())
@@ -263,6 +320,10 @@ fn system_peers() {
 			protocol_version: 1,
 			best_hash: Default::default(),
 			best_number: 1u64,
+			finalized_hash: None,
+			finalized_number: Some(1u64),
+			notification_protocols: vec!["/substrate/block-announces/1".into()],
+			requests: Default::default(),
 		}]
 	);
 }
@@ -296,6 +357,33 @@ fn system_node_roles() {
 	);
 }
 
+#[test]
+fn system_sync_state() {
+	assert_eq!(
+		wait_receiver(api(None).system_sync_state()),
+		SyncState {
+			starting_block: 1,
+			current_block: 2,
+			highest_block: Some(3),
+			gap_sync: None,
+		}
+	);
+}
+
+#[test]
+fn system_pruning_status() {
+	assert_eq!(
+		wait_receiver(api(None).system_pruning_status()),
+		Some(PruningStatus {
+			window: Some(256),
+			oldest_block: 10,
+			last_canonicalized: Some(265),
+			non_canonical: 2,
+			pending_prunes: 0,
+		})
+	);
+}
+
 #[test]
 fn system_network_add_reserved() {
 	let good_peer_id = "/ip4/198.51.100.19/tcp/30333/p2p/QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
@@ -319,3 +407,71 @@ fn system_network_remove_reserved() {
 	assert_eq!(runtime.block_on(good_fut), Ok(()));
 	assert!(runtime.block_on(bad_fut).is_err());
 }
+
+#[test]
+fn system_network_ip_filter() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	assert_eq!(
+		runtime.block_on(api(None).system_ip_filter()).unwrap(),
+		IpFilter {
+			allow: None,
+			deny: vec!["198.51.100.0/24".into()],
+		},
+	);
+}
+
+#[test]
+fn system_network_set_ip_filter() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	let good_fut = api(None).system_set_ip_filter(IpFilter {
+		allow: Some(vec!["10.0.0.0/8".into()]),
+		deny: vec!["10.1.0.0/16".into()],
+	});
+	let bad_fut = api(None).system_set_ip_filter(IpFilter {
+		allow: None,
+		deny: vec!["10.1.0.1".into()],
+	});
+	assert_eq!(runtime.block_on(good_fut), Ok(()));
+	assert!(runtime.block_on(bad_fut).is_err());
+}
+
+#[test]
+fn system_network_traffic_dump() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	assert_eq!(
+		runtime.block_on(api(None).system_traffic_dump()).unwrap(),
+		Some(TrafficDump {
+			path: "/tmp/traffic".into(),
+			include_payloads: false,
+		}),
+	);
+
+	let good_fut = api(None).system_set_traffic_dump(Some(TrafficDump {
+		path: "/tmp/traffic".into(),
+		include_payloads: true,
+	}));
+	let stop_fut = api(None).system_set_traffic_dump(None);
+	let bad_fut = api(None).system_set_traffic_dump(Some(TrafficDump {
+		path: String::new(),
+		include_payloads: true,
+	}));
+	assert_eq!(runtime.block_on(good_fut), Ok(()));
+	assert_eq!(runtime.block_on(stop_fut), Ok(()));
+	assert!(runtime.block_on(bad_fut).is_err());
+}
+
+#[test]
+fn system_pinned_blocks() {
+	let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+	assert_eq!(
+		runtime.block_on(api(None).system_pinned_blocks()).unwrap(),
+		vec![(1, Default::default())],
+	);
+	assert_eq!(runtime.block_on(api(None).system_pin_block(2, Default::default())), Ok(()));
+	assert_eq!(runtime.block_on(api(None).system_unpin_block(1)), Ok(true));
+	assert_eq!(runtime.block_on(api(None).system_unpin_block(2)), Ok(false));
+}
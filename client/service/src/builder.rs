@@ -18,14 +18,16 @@
 
 use crate::{Service, NetworkStatus, NetworkState, error::Error, DEFAULT_PROTOCOL_ID, MallocSizeOfWasm};
 use crate::{start_rpc_servers, build_network_future, TransactionPoolAdapter, TaskManager, SpawnTaskHandle};
+use crate::task_manager::{restartable_essential_task, RestartPolicy};
 use crate::status_sinks;
-use crate::config::{Configuration, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig};
+use crate::{health, systemd};
+use crate::config::{Configuration, KeystoreConfig, PrometheusConfig, OffchainWorkerConfig, PruningMode};
 use crate::metrics::MetricsService;
 use sc_client_api::{
 	self, BlockchainEvents, backend::RemoteBackend, light::RemoteBlockchain, execution_extensions::ExtensionsFactory,
 	ExecutorProvider, CallExecutor, ForkBlocks, BadBlocks, CloneableSpawn, UsageProvider,
 };
-use crate::client::{Client, ClientConfig};
+use crate::client::{Client, ClientConfig, PinnedBlocks};
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
 use sc_chain_spec::get_extension;
 use sp_consensus::{
@@ -38,7 +40,9 @@ use futures::{
 };
 use sc_keystore::Store as Keystore;
 use log::{info, warn, error};
-use sc_network::config::{Role, FinalityProofProvider, OnDemand, BoxFinalityProofRequestBuilder};
+use sc_network::config::{
+	Role, FinalityProofProvider, OnDemand, BoxFinalityProofRequestBuilder, WarpSyncProvider,
+};
 use sc_network::{NetworkService, NetworkStateInfo};
 use parking_lot::{Mutex, RwLock};
 use sp_runtime::generic::BlockId;
@@ -49,8 +53,8 @@ use sp_api::ProvideRuntimeApi;
 use sc_executor::{NativeExecutor, NativeExecutionDispatch, RuntimeInfo};
 use std::{
 	collections::HashMap,
-	io::{Read, Write, Seek},
-	marker::PhantomData, sync::Arc, pin::Pin
+	io::{Read, Write},
+	marker::PhantomData, sync::Arc, pin::Pin, time::Duration,
 };
 use wasm_timer::SystemTime;
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
@@ -62,6 +66,7 @@ use sp_core::traits::CodeExecutor;
 use sp_runtime::BuildStorage;
 use sc_client_api::execution_extensions::ExecutionExtensions;
 use sp_core::storage::Storage;
+use crate::chain_ops::SnapshotManifest;
 
 pub type BackgroundTask = Pin<Box<dyn Future<Output=()> + Send>>;
 
@@ -100,6 +105,9 @@ pub struct ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
 	remote_backend: Option<Arc<dyn RemoteBlockchain<TBl>>>,
 	marker: PhantomData<(TBl, TRtApi)>,
 	block_announce_validator_builder: Option<Box<dyn FnOnce(Arc<TCl>) -> Box<dyn BlockAnnounceValidator<TBl> + Send> + Send>>,
+	warp_sync_provider: Option<Arc<dyn WarpSyncProvider<TBl>>>,
+	pub(crate) aux_revert: Option<Box<dyn Fn(Arc<TCl>) -> Result<(), Error> + Send>>,
+	rpc_middleware: Option<Arc<dyn sc_rpc_server::RpcCallMiddleware<sc_rpc::Metadata>>>,
 }
 
 /// A utility trait for building an RPC extension given a `DenyUnsafe` instance.
@@ -214,6 +222,23 @@ pub fn new_full_client<TBl, TRtApi, TExecDisp>(
 	new_full_parts(config).map(|parts| parts.0)
 }
 
+/// Returns the settings of the database of the node described by `config`.
+pub fn database_settings(config: &Configuration) -> DatabaseSettings {
+	DatabaseSettings {
+		state_cache_size: config.state_cache_size,
+		state_cache_child_ratio: config.state_cache_child_ratio.map(|v| (v, 100)),
+		trie_cache_size: config.trie_cache_size,
+		pruning: config.pruning.clone(),
+		block_pruning: config.block_pruning,
+		index_extrinsics: config.index_extrinsics,
+		index_storage_changes: config.index_storage_changes,
+		cold_storage: config.cold_storage.clone(),
+		delta_archive: config.delta_archive,
+		canonicalization_delay: config.canonicalization_delay,
+		source: config.database.clone(),
+	}
+}
+
 fn new_full_parts<TBl, TRtApi, TExecDisp>(
 	config: &Configuration,
 ) -> Result<TFullParts<TBl, TRtApi, TExecDisp>,	Error> where
@@ -228,10 +253,7 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 		KeystoreConfig::InMemory => Keystore::new_in_memory(),
 	};
 
-	let task_manager = {
-		let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
-		TaskManager::new(config.task_executor.clone(), registry)?
-	};
+	let task_manager = new_task_manager(config)?;
 
 	let executor = NativeExecutor::<TExecDisp>::new(
 		config.wasm_method,
@@ -249,13 +271,7 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 		.unwrap_or_default();
 
 	let (client, backend) = {
-		let db_config = sc_client_db::DatabaseSettings {
-			state_cache_size: config.state_cache_size,
-			state_cache_child_ratio:
-			config.state_cache_child_ratio.map(|v| (v, 100)),
-			pruning: config.pruning.clone(),
-			source: config.database.clone(),
-		};
+		let db_config = database_settings(config);
 
 		let extensions = sc_client_api::execution_extensions::ExecutionExtensions::new(
 			config.execution_strategies.clone(),
@@ -277,10 +293,49 @@ fn new_full_parts<TBl, TRtApi, TExecDisp>(
 			},
 		)?
 	};
+	pin_blocks(&client.pinned_blocks(), &config.pinned_blocks)?;
+
+	// A secondary instance of the database only sees the blocks imported by the primary instance
+	// until it catches up with it.
+	#[cfg(feature = "db")]
+	{
+		if let crate::config::DatabaseConfig::RocksDbSecondary { .. } = config.database {
+			let backend = backend.clone();
+			task_manager.spawn_handle().spawn("secondary-database-catch-up", async move {
+				loop {
+					futures_timer::Delay::new(SECONDARY_DATABASE_CATCH_UP_INTERVAL).await;
+					if let Err(e) = backend.try_catch_up_with_primary() {
+						warn!("Failed to catch up with the primary database: {}", e);
+					}
+				}
+			});
+		}
+	}
 
 	Ok((client, backend, keystore, task_manager))
 }
 
+/// Interval at which a secondary instance of the database catches up with the primary instance.
+#[cfg(feature = "db")]
+const SECONDARY_DATABASE_CATCH_UP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pins the SCALE-encoded block hashes of the configuration at their heights.
+fn pin_blocks<TBl: BlockT>(
+	pinned_blocks: &PinnedBlocks<TBl>,
+	blocks: &[(u64, Vec<u8>)],
+) -> Result<(), Error> {
+	for (number, encoded) in blocks {
+		let mut input = &encoded[..];
+		let hash = <TBl::Hash as codec::Decode>::decode(&mut input)
+			.ok()
+			.filter(|_| input.is_empty())
+			.ok_or_else(|| Error::Other(format!("Invalid hash of the pinned block #{}", number)))?;
+		info!("📌 Pinning block #{} to {:?}", number, hash);
+		pinned_blocks.pin((*number).saturated_into(), hash);
+	}
+	Ok(())
+}
+
 
 /// Create an instance of db-backed client.
 pub fn new_client<E, Block, RA>(
@@ -308,9 +363,7 @@ pub fn new_client<E, Block, RA>(
 		Block: BlockT,
 		E: CodeExecutor + RuntimeInfo,
 {
-	const CANONICALIZATION_DELAY: u64 = 4096;
-
-	let backend = Arc::new(Backend::new(settings, CANONICALIZATION_DELAY)?);
+	let backend = Arc::new(Backend::new(settings)?);
 	let executor = crate::client::LocalCallExecutor::new(backend.clone(), executor, spawn_handle, config.clone());
 	Ok((
 		crate::client::Client::new(
@@ -363,6 +416,9 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: None,
 			block_announce_validator_builder: None,
+			warp_sync_provider: None,
+			aux_revert: None,
+			rpc_middleware: None,
 			marker: PhantomData,
 		})
 	}
@@ -383,10 +439,7 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		(),
 		TLightBackend<TBl>,
 	>, Error> {
-		let task_manager = {
-			let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
-			TaskManager::new(config.task_executor.clone(), registry)?
-		};
+		let task_manager = new_task_manager(&config)?;
 
 		let keystore = match &config.keystore {
 			KeystoreConfig::Path { path, password } => Keystore::open(
@@ -403,14 +456,7 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 		);
 
 		let db_storage = {
-			let db_settings = sc_client_db::DatabaseSettings {
-				state_cache_size: config.state_cache_size,
-				state_cache_child_ratio:
-					config.state_cache_child_ratio.map(|v| (v, 100)),
-				pruning: config.pruning.clone(),
-				source: config.database.clone(),
-			};
-			sc_client_db::light::LightStorage::new(db_settings)?
+			sc_client_db::light::LightStorage::new(database_settings(&config))?
 		};
 		let light_blockchain = crate::client::light::new_light_blockchain(db_storage);
 		let fetch_checker = Arc::new(
@@ -430,6 +476,7 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			Box::new(task_manager.spawn_handle()),
 			config.prometheus_config.as_ref().map(|config| config.registry.clone()),
 		)?);
+		pin_blocks(&client.pinned_blocks(), &config.pinned_blocks)?;
 
 		Ok(ServiceBuilder {
 			config,
@@ -446,6 +493,9 @@ impl ServiceBuilder<(), (), (), (), (), (), (), (), (), (), ()> {
 			rpc_extensions_builder: Box::new(|_| ()),
 			remote_backend: Some(remote_blockchain),
 			block_announce_validator_builder: None,
+			warp_sync_provider: None,
+			aux_revert: None,
+			rpc_middleware: None,
 			marker: PhantomData,
 		})
 	}
@@ -465,6 +515,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 		&self.backend
 	}
 
+	/// Returns a reference to the chain spec of the configuration of this builder.
+	pub fn chain_spec(&self) -> &dyn sc_chain_spec::ChainSpec {
+		&*self.config.chain_spec
+	}
+
 	/// Returns a reference to the select-chain that was stored in this builder.
 	pub fn select_chain(&self) -> Option<&TSc> {
 		self.select_chain.as_ref()
@@ -480,6 +535,11 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 		self.transaction_pool.clone()
 	}
 
+	/// Returns a handle for spawning tasks that are stopped along with the service.
+	pub fn spawn_handle(&self) -> SpawnTaskHandle {
+		self.task_manager.spawn_handle()
+	}
+
 	/// Returns a reference to the fetcher, only available if builder
 	/// was created with `new_light`.
 	pub fn fetcher(&self) -> Option<TFchr>
@@ -519,6 +579,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -545,7 +608,7 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			self.client.clone(),
 			self.select_chain.clone(),
 			self.transaction_pool.clone(),
-			&self.task_manager.spawn_handle(),
+			&self.task_manager.spawn_handle().with_group("import"),
 			self.config.prometheus_config.as_ref().map(|config| &config.registry),
 		)?;
 
@@ -564,6 +627,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -602,6 +668,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -649,7 +718,7 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			self.fetcher.clone(),
 			self.select_chain.clone(),
 			self.transaction_pool.clone(),
-			&self.task_manager.spawn_handle(),
+			&self.task_manager.spawn_handle().with_group("import"),
 			self.config.prometheus_config.as_ref().map(|config| &config.registry),
 		)?;
 
@@ -668,6 +737,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -714,7 +786,7 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 		)?;
 
 		if let Some(background_task) = background_task{
-			self.task_manager.spawn_handle().spawn("txpool-background", background_task);
+			self.task_manager.spawn_handle().with_group("txpool").spawn("txpool-background", background_task);
 		}
 
 		Ok(ServiceBuilder {
@@ -732,6 +804,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -769,6 +844,9 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: Box::new(rpc_extensions_builder),
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: self.block_announce_validator_builder,
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
@@ -814,9 +892,59 @@ impl<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp, TExPool, TRpc, Backend>
 			rpc_extensions_builder: self.rpc_extensions_builder,
 			remote_backend: self.remote_backend,
 			block_announce_validator_builder: Some(Box::new(block_announce_validator_builder)),
+			warp_sync_provider: self.warp_sync_provider,
+			aux_revert: self.aux_revert,
+			rpc_middleware: self.rpc_middleware,
 			marker: self.marker,
 		})
 	}
+
+	/// Defines which strategy to use for providing and verifying warp sync proofs.
+	pub fn with_warp_sync_provider(
+		self,
+		builder: impl FnOnce(Arc<TCl>, Arc<Backend>) -> Result<Arc<dyn WarpSyncProvider<TBl>>, Error>
+	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
+		TExPool, TRpc, Backend>, Error> {
+		let warp_sync_provider = builder(self.client.clone(), self.backend.clone())?;
+
+		Ok(ServiceBuilder {
+			warp_sync_provider: Some(warp_sync_provider),
+			..self
+		})
+	}
+
+	/// Defines how to revert the auxiliary data of the consensus engines, e.g. their authority
+	/// sets, once blocks have been reverted by the `revert` command.
+	pub fn with_aux_revert(
+		self,
+		revert: impl Fn(Arc<TCl>) -> Result<(), Error> + Send + 'static,
+	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
+		TExPool, TRpc, Backend>, Error> {
+		Ok(ServiceBuilder {
+			aux_revert: Some(Box::new(revert)),
+			..self
+		})
+	}
+
+	/// Checks that the forks up to `depth` blocks deep, which the consensus engines expect to
+	/// reorganize to, can still be imported given the canonicalization delay of the database.
+	pub fn with_expected_reorg_depth(self, depth: u64) -> Result<Self, Error> {
+		check_canonicalization_delay(&self.config.pruning, self.config.canonicalization_delay, depth)?;
+		Ok(self)
+	}
+
+	/// Defines middleware of the node wrapping the calls made to the RPC servers, after the
+	/// standard middlewares.
+	pub fn with_rpc_middleware(
+		self,
+		middleware: impl sc_rpc_server::RpcCallMiddleware<sc_rpc::Metadata>,
+	) -> Result<ServiceBuilder<TBl, TRtApi, TCl, TFchr, TSc, TImpQu, TFprb, TFpp,
+		TExPool, TRpc, Backend>, Error> {
+		Ok(ServiceBuilder {
+			rpc_middleware: Some(Arc::new(middleware)),
+			..self
+		})
+	}
 }
 
 /// Implemented on `ServiceBuilder`. Allows running block commands, such as import/export/validate
@@ -827,26 +955,39 @@ pub trait ServiceBuilderCommand {
 	/// Native execution dispatch required by some commands.
 	type NativeDispatch: NativeExecutionDispatch + 'static;
 	/// Starts the process of importing blocks.
+	///
+	/// Only the blocks numbered from `from` to `to` are imported. The blocks already in the
+	/// chain are skipped unless `force` is set, so that an interrupted import can be resumed.
 	fn import_blocks(
 		self,
-		input: impl Read + Seek + Send + 'static,
+		input: impl Read + Send + 'static,
 		force: bool,
 		binary: bool,
+		from: Option<NumberFor<Self::Block>>,
+		to: Option<NumberFor<Self::Block>>,
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
 
 	/// Performs the blocks export.
+	///
+	/// The [`BlocksIndex`](crate::chain_ops::BlocksIndex) of a binary export is written to
+	/// `index`, if any.
 	fn export_blocks(
 		self,
 		output: impl Write + 'static,
 		from: NumberFor<Self::Block>,
 		to: Option<NumberFor<Self::Block>>,
-		binary: bool
+		binary: bool,
+		index: Option<Box<dyn Write>>,
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>>>>;
 
 	/// Performs a revert of `blocks` blocks.
+	///
+	/// The finalized blocks are only reverted if `revert_finalized` is set, which can leave the
+	/// node in an inconsistent state.
 	fn revert_chain(
 		&self,
-		blocks: NumberFor<Self::Block>
+		blocks: NumberFor<Self::Block>,
+		revert_finalized: bool,
 	) -> Result<(), Error>;
 
 	/// Re-validate known block.
@@ -861,6 +1002,29 @@ pub trait ServiceBuilderCommand {
 		&self,
 		block: Option<BlockId<Self::Block>>,
 	) -> Result<Storage, Error>;
+
+	/// Exports a snapshot of the state at the given `block`, or at the best block if `None`.
+	///
+	/// The state is read in chunks of about `chunk_size` bytes, each passed to `output` along with
+	/// its index, as the SCALE encoding of a list of
+	/// [`SnapshotEntry`](crate::chain_ops::SnapshotEntry). Returns the manifest of the snapshot.
+	fn export_snapshot(
+		&self,
+		block: Option<BlockId<Self::Block>>,
+		chunk_size: usize,
+		output: impl FnMut(usize, Vec<u8>) -> Result<(), Error>,
+	) -> Result<SnapshotManifest<<Self::Block as BlockT>::Hash, <Self::Block as BlockT>::Header>, Error>;
+
+	/// Imports a snapshot of the state of a block into a node without any block but the genesis.
+	///
+	/// The `chunks` are checked against the `manifest`, and the state they hold against the state
+	/// root of the header. The block of the snapshot is imported as finalized, without being
+	/// verified by the consensus engine: the manifest must come from a trusted source.
+	fn import_snapshot(
+		self,
+		manifest: SnapshotManifest<<Self::Block as BlockT>::Hash, <Self::Block as BlockT>::Header>,
+		chunks: impl Iterator<Item = Result<Vec<u8>, Error>>,
+	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>>;
 }
 
 impl<TBl, TRtApi, TBackend, TExec, TSc, TImpQu, TExPool, TRpc>
@@ -933,8 +1097,17 @@ ServiceBuilder<
 			rpc_extensions_builder,
 			remote_backend,
 			block_announce_validator_builder,
+			warp_sync_provider,
+			aux_revert: _,
+			rpc_middleware,
 		} = self;
 
+		if config.network.sync_mode == sc_network::config::SyncMode::Warp && warp_sync_provider.is_none() {
+			return Err(Error::Other(
+				"Warp sync isn't supported by this chain, use `--sync full` or `--sync fast` instead".into()
+			))
+		}
+
 		sp_session::generate_initial_session_keys(
 			client.clone(),
 			&BlockId::Hash(client.chain_info().best_hash),
@@ -989,7 +1162,7 @@ ServiceBuilder<
 		let network_params = sc_network::config::Params {
 			role: config.role.clone(),
 			executor: {
-				let spawn_handle = task_manager.spawn_handle();
+				let spawn_handle = task_manager.spawn_handle().with_group("network");
 				Some(Box::new(move |fut| {
 					spawn_handle.spawn("libp2p-node", fut);
 				}))
@@ -998,11 +1171,18 @@ ServiceBuilder<
 			chain: client.clone(),
 			finality_proof_provider,
 			finality_proof_request_builder,
+			warp_sync_provider,
+			indexed_data: if config.network.ipfs_server {
+				backend.offchain_index()
+			} else {
+				None
+			},
 			on_demand: on_demand.clone(),
 			transaction_pool: transaction_pool_adapter.clone() as _,
 			import_queue,
 			protocol_id,
 			block_announce_validator,
+			transaction_propagation_policy: config.network.transaction_propagation.policy(),
 			metrics_registry: config.prometheus_config.as_ref().map(|config| config.registry.clone())
 		};
 
@@ -1029,7 +1209,8 @@ ServiceBuilder<
 			// block notifications
 			let txpool = Arc::downgrade(&transaction_pool);
 			let offchain = offchain_workers.as_ref().map(Arc::downgrade);
-			let notifications_spawn_handle = task_manager.spawn_handle();
+			let offchain_spawn_handle = task_manager.spawn_handle().with_group("offchain");
+			let txpool_spawn_handle = task_manager.spawn_handle().with_group("txpool");
 			let network_state_info: Arc<dyn NetworkStateInfo + Send + Sync> = network.clone();
 			let is_validator = config.role.is_authority();
 
@@ -1051,7 +1232,7 @@ ServiceBuilder<
 						let offchain = offchain.as_ref().and_then(|o| o.upgrade());
 						match offchain {
 							Some(offchain) if is_new_best => {
-								notifications_spawn_handle.spawn(
+								offchain_spawn_handle.spawn(
 									"offchain-on-block",
 									offchain.on_block_imported(
 										&header,
@@ -1071,7 +1252,7 @@ ServiceBuilder<
 
 					let txpool = txpool.upgrade();
 					if let Some(txpool) = txpool.as_ref() {
-						notifications_spawn_handle.spawn(
+						txpool_spawn_handle.spawn(
 							"txpool-maintain",
 							txpool.maintain(event),
 						);
@@ -1103,14 +1284,23 @@ ServiceBuilder<
 					ready(())
 				});
 
-			spawn_handle.spawn(
+			spawn_handle.with_group("txpool").spawn(
 				"on-transaction-imported",
 				events,
 			);
 		}
 
+		// Full nodes store the metadata of the runtimes they enact, since the runtime of old
+		// blocks may not be instantiable any more.
+		if !matches!(config.role, Role::Light) {
+			spawn_handle.spawn(
+				"runtime-metadata",
+				sc_rpc::state::persist_runtime_metadata(client.clone()),
+			);
+		}
+
 		// Prometheus metrics.
-		let mut metrics_service = if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
+		let mut metrics_service = if let Some(PrometheusConfig { port, registry, serve }) = config.prometheus_config.clone() {
 			// Set static metrics.
 
 
@@ -1126,10 +1316,12 @@ ServiceBuilder<
 				&config.impl_version,
 				role_bits,
 			)?;
-			spawn_handle.spawn(
-				"prometheus-endpoint",
-				prometheus_endpoint::init_prometheus(port, registry).map(drop)
-			);
+			if serve {
+				spawn_handle.with_group("telemetry").spawn(
+					"prometheus-endpoint",
+					prometheus_endpoint::init_prometheus(port, registry).map(drop)
+				);
+			}
 
 			metrics
 		} else {
@@ -1151,7 +1343,7 @@ ServiceBuilder<
 			ready(())
 		});
 
-		spawn_handle.spawn(
+		spawn_handle.with_group("telemetry").spawn(
 			"telemetry-periodic-send",
 			tel_task,
 		);
@@ -1167,14 +1359,85 @@ ServiceBuilder<
 			);
 			ready(())
 		});
-		spawn_handle.spawn(
+		spawn_handle.with_group("telemetry").spawn(
 			"telemetry-periodic-network-state",
 			tel_task_2,
 		);
 
+		// Notify systemd of the readiness of the node, and pet its watchdog from the network
+		// status updates, which stop if the network hangs.
+		if let Some(systemd_config) = config.systemd_notify.clone() {
+			let watchdog = systemd::watchdog_timeout();
+			// systemd advises to pet the watchdog twice per timeout.
+			let interval = watchdog.map_or(Duration::from_secs(5), |timeout| timeout / 2);
+			let threshold = systemd_config.ready_sync_threshold.saturated_into::<NumberFor<TBl>>();
+			let client_ = client.clone();
+			let mut is_ready = false;
+			let (systemd_tx, systemd_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>("mpsc_systemd");
+			network_status_sinks.lock().push(interval, systemd_tx);
+			let systemd_task = systemd_rx.for_each(move |(net_status, _)| {
+				let best_number = client_.chain_info().best_number;
+				if !is_ready && systemd::is_ready(
+					best_number,
+					net_status.best_seen_block,
+					net_status.num_connected_peers,
+					&net_status.sync_state,
+					threshold,
+				) {
+					is_ready = true;
+					match systemd::notify("READY=1") {
+						Ok(true) => info!("📣 Notified systemd of the readiness of the node"),
+						Ok(false) => warn!("Can't notify systemd, `NOTIFY_SOCKET` isn't set"),
+						Err(e) => warn!("Failed to notify systemd of the readiness of the node: {}", e),
+					}
+				}
+				if watchdog.is_some() {
+					if let Err(e) = systemd::notify("WATCHDOG=1") {
+						warn!("Failed to pet the watchdog of systemd: {}", e);
+					}
+				}
+				ready(())
+			});
+			spawn_handle.spawn("systemd-notify", systemd_task);
+		}
+
+		// Serve the health and the readiness of the node, updated from the network status.
+		if let Some(health_config) = config.health.clone() {
+			let address = health_config.address;
+			let status = health::SharedHealthStatus::default();
+			let mut tracker = health::HealthTracker::new(
+				health_config,
+				has_bootnodes,
+			);
+			let client_ = client.clone();
+			let status_ = status.clone();
+			let (health_tx, health_rx) = tracing_unbounded::<(NetworkStatus<_>, NetworkState)>("mpsc_health");
+			network_status_sinks.lock().push(Duration::from_secs(1), health_tx);
+			let health_task = health_rx.for_each(move |(net_status, _)| {
+				let info = client_.chain_info();
+				*status_.write() = Some(tracker.update(
+					info.best_number.saturated_into(),
+					net_status.best_seen_block.map(|number| number.saturated_into()),
+					info.finalized_number.saturated_into(),
+					net_status.num_connected_peers,
+					wasm_timer::Instant::now(),
+				));
+				ready(())
+			});
+			spawn_handle.spawn("health-status", health_task);
+			spawn_handle.spawn("health-endpoint", health::serve(address, status).map(|result| {
+				if let Err(e) = result {
+					warn!("Health endpoint stopped: {}", e);
+				}
+			}));
+		}
+
 		// RPC
 		let (system_rpc_tx, system_rpc_rx) = tracing_unbounded("mpsc_system_rpc");
-		let gen_handler = |deny_unsafe: sc_rpc::DenyUnsafe| {
+		let gen_handler = |
+			deny_unsafe: sc_rpc::DenyUnsafe,
+			middleware: sc_rpc_server::RpcMiddleware<sc_rpc::Metadata>,
+		| {
 			use sc_rpc::{chain, state, author, system, offchain};
 
 			let system_info = sc_rpc::system::SystemInfo {
@@ -1185,7 +1448,7 @@ ServiceBuilder<
 				chain_type: chain_spec.chain_type().clone(),
 			};
 
-			let subscriptions = sc_rpc::Subscriptions::new(Arc::new(task_manager.spawn_handle()));
+			let subscriptions = sc_rpc::Subscriptions::new(Arc::new(task_manager.spawn_handle().with_group("rpc")));
 
 			let (chain, state, child_state) = if let (Some(remote_backend), Some(on_demand)) =
 				(remote_backend.as_ref(), on_demand.as_ref()) {
@@ -1194,20 +1457,26 @@ ServiceBuilder<
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					deny_unsafe,
 				);
 				let (state, child_state) = sc_rpc::state::new_light(
 					client.clone(),
 					subscriptions.clone(),
 					remote_backend.clone(),
-					on_demand.clone()
+					on_demand.clone(),
+					deny_unsafe,
 				);
 				(chain, state, child_state)
 
 			} else {
 				// Full nodes
-				let chain = sc_rpc::chain::new_full(client.clone(), subscriptions.clone());
-				let (state, child_state) = sc_rpc::state::new_full(client.clone(), subscriptions.clone());
+				let chain = sc_rpc::chain::new_full(client.clone(), subscriptions.clone(), deny_unsafe);
+				let (state, child_state) = sc_rpc::state::new_full(
+					client.clone(),
+					subscriptions.clone(),
+					deny_unsafe,
+				);
 				(chain, state, child_state)
 			};
 
@@ -1222,7 +1491,12 @@ ServiceBuilder<
 
 			let maybe_offchain_rpc = offchain_storage.clone()
 			.map(|storage| {
-				let offchain = sc_rpc::offchain::Offchain::new(storage, deny_unsafe);
+				let offchain_index = if config.offchain_worker.indexing_enabled {
+					backend.offchain_index()
+				} else {
+					None
+				};
+				let offchain = sc_rpc::offchain::Offchain::new(storage, offchain_index, deny_unsafe);
 				// FIXME: Use plain Option (don't collect into HashMap) when we upgrade to jsonrpc 14.1
 				// https://github.com/paritytech/jsonrpc/commit/20485387ed06a48f1a70bf4d609a7cde6cf0accf
 				let delegate = offchain::OffchainApi::to_delegate(offchain);
@@ -1237,11 +1511,11 @@ ServiceBuilder<
 				author::AuthorApi::to_delegate(author),
 				system::SystemApi::to_delegate(system),
 				rpc_extensions_builder.build(deny_unsafe),
-			))
+			), middleware)
 		};
-		let rpc = start_rpc_servers(&config, gen_handler)?;
-		// This is used internally, so don't restrict access to unsafe RPC
-		let rpc_handlers = gen_handler(sc_rpc::DenyUnsafe::No);
+		let rpc = start_rpc_servers(&config, rpc_middleware, gen_handler)?;
+		// This is used internally, so don't restrict access to any RPC nor limit the calls
+		let rpc_handlers = gen_handler(sc_rpc::DenyUnsafe::No, Default::default());
 
 		// The network worker is responsible for gathering all network messages and processing
 		// them. This is quite a heavy task, and at the time of the writing of this comment it
@@ -1250,7 +1524,7 @@ ServiceBuilder<
 		// issue, and ideally we would like to fix the network future to take as little time as
 		// possible, but we also take the extra harm-prevention measure to execute the networking
 		// future using `spawn_blocking`.
-		spawn_handle.spawn_blocking(
+		spawn_handle.with_group("network").spawn_blocking(
 			"network-worker",
 			build_network_future(
 				config.role.clone(),
@@ -1258,6 +1532,7 @@ ServiceBuilder<
 				client.clone(),
 				network_status_sinks.clone(),
 				system_rpc_rx,
+				client.pinned_blocks(),
 				has_bootnodes,
 				config.announce_block,
 			),
@@ -1281,31 +1556,46 @@ ServiceBuilder<
 			let startup_time = SystemTime::UNIX_EPOCH.elapsed()
 				.map(|dur| dur.as_millis())
 				.unwrap_or(0);
-			let future = telemetry.clone()
-				.for_each(move |event| {
-					// Safe-guard in case we add more events in the future.
-					let sc_telemetry::TelemetryEvent::Connected = event;
-
-					telemetry!(SUBSTRATE_INFO; "system.connected";
-						"name" => name.clone(),
-						"implementation" => impl_name.clone(),
-						"version" => version.clone(),
-						"config" => "",
-						"chain" => chain_name.clone(),
-						"authority" => is_authority,
-						"startup_time" => startup_time,
-						"network_id" => network_id.clone()
-					);
+			// the worker is built again if it panics, e.g. on a transient failure of an endpoint.
+			let telemetry_ = telemetry.clone();
+			let worker = move || {
+				let name = name.clone();
+				let impl_name = impl_name.clone();
+				let version = version.clone();
+				let chain_name = chain_name.clone();
+				let network_id = network_id.clone();
+				let telemetry_connection_sinks_ = telemetry_connection_sinks_.clone();
+				telemetry_.clone()
+					.for_each(move |event| {
+						// Safe-guard in case we add more events in the future.
+						let sc_telemetry::TelemetryEvent::Connected = event;
+
+						telemetry!(SUBSTRATE_INFO; "system.connected";
+							"name" => name.clone(),
+							"implementation" => impl_name.clone(),
+							"version" => version.clone(),
+							"config" => "",
+							"chain" => chain_name.clone(),
+							"authority" => is_authority,
+							"startup_time" => startup_time,
+							"network_id" => network_id.clone()
+						);
 
-					telemetry_connection_sinks_.lock().retain(|sink| {
-						sink.unbounded_send(()).is_ok()
-					});
-					ready(())
-				});
+						telemetry_connection_sinks_.lock().retain(|sink| {
+							sink.unbounded_send(()).is_ok()
+						});
+						ready(())
+					})
+			};
 
-			spawn_handle.spawn(
+			spawn_handle.with_group("telemetry").spawn(
 				"telemetry-worker",
-				future,
+				restartable_essential_task(
+					"telemetry-worker",
+					RestartPolicy::default(),
+					worker,
+					essential_failed_tx.clone(),
+				),
 			);
 
 			telemetry
@@ -1342,3 +1632,47 @@ ServiceBuilder<
 		})
 	}
 }
+
+/// Creates the task manager of a service, shared with the services of other chains if configured.
+fn new_task_manager(config: &Configuration) -> Result<TaskManager, Error> {
+	let registry = config.prometheus_config.as_ref().map(|cfg| &cfg.registry);
+	let task_manager = TaskManager::new(config.task_executor.clone(), registry)?;
+	Ok(match &config.shared_task_manager {
+		Some(shared) => task_manager.shared_with(shared),
+		None => task_manager,
+	})
+}
+
+/// Checks that the forks up to `depth` blocks deep are still kept by a database pruning the
+/// states of the forks once canonicalized `canonicalization_delay` blocks behind the best block.
+fn check_canonicalization_delay(
+	pruning: &PruningMode,
+	canonicalization_delay: u64,
+	depth: u64,
+) -> Result<(), Error> {
+	match pruning {
+		// canonicalization is a no-op, the states of the forks are never pruned.
+		PruningMode::ArchiveAll => Ok(()),
+		_ if canonicalization_delay < depth => Err(Error::Other(format!(
+			"The canonicalization delay ({}) is lower than the depth of the reorganizations \
+			 expected by the consensus ({}), the deeper forks couldn't be imported",
+			canonicalization_delay,
+			depth,
+		))),
+		_ => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn canonicalization_delay_must_cover_the_expected_reorgs_of_pruned_databases() {
+		assert!(check_canonicalization_delay(&PruningMode::keep_blocks(256), 4096, 512).is_ok());
+		assert!(check_canonicalization_delay(&PruningMode::keep_blocks(256), 512, 512).is_ok());
+		assert!(check_canonicalization_delay(&PruningMode::keep_blocks(256), 511, 512).is_err());
+		assert!(check_canonicalization_delay(&PruningMode::ArchiveCanonical, 0, 512).is_err());
+		assert!(check_canonicalization_delay(&PruningMode::ArchiveAll, 0, 512).is_ok());
+	}
+}
@@ -27,18 +27,21 @@ use futures::{future, prelude::*};
 use sp_runtime::traits::{
 	Block as BlockT, NumberFor, One, Zero, Header, SaturatedConversion, MaybeSerializeDeserialize,
 };
+use sp_runtime::Justification;
 use sp_runtime::generic::{BlockId, SignedBlock};
 use codec::{Decode, Encode, IoReader as CodecIoReader};
 use crate::client::{Client, LocalCallExecutor};
 use sp_consensus::{
-	BlockOrigin,
+	BlockOrigin, ImportedState,
 	import_queue::{IncomingBlock, Link, BlockImportError, BlockImportResult, ImportQueue},
 };
 use sc_executor::{NativeExecutor, NativeExecutionDispatch};
+use sp_core::hashing::blake2_256;
 use sp_core::storage::{StorageKey, well_known_keys, ChildInfo, Storage, StorageChild, StorageMap};
-use sc_client_api::{StorageProvider, BlockBackend, UsageProvider};
+use sc_client_api::{StorageProvider, BlockBackend, UsageProvider, ProofProvider, StateEntry};
+use sp_blockchain::{BlockStatus, HeaderBackend};
 
-use std::{io::{Read, Write, Seek}, pin::Pin, collections::HashMap};
+use std::{io::{Read, Write}, pin::Pin, collections::HashMap, path::{Path, PathBuf}};
 use std::time::{Duration, Instant};
 use futures_timer::Delay;
 use std::task::Poll;
@@ -55,16 +58,169 @@ const DELAY_TIME: u64 = 2_000;
 /// Number of milliseconds that must have passed between two updates.
 const TIME_BETWEEN_UPDATES: u64 = 3_000;
 
+/// Number of blocks between two entries of the index of a binary export.
+const INDEX_INTERVAL: u64 = 1_000;
+
+/// Index of a binary export of blocks.
+///
+/// It gives the offsets of some of the blocks of the export, so that an import can start
+/// reading the export from the middle of it.
+#[derive(Debug, Default, PartialEq, Encode, Decode)]
+pub struct BlocksIndex {
+	/// Number of blocks of the export.
+	pub count: u64,
+	/// Numbers of every `INDEX_INTERVAL`-th block of the export, starting from the first one,
+	/// and offsets of their encoding in bytes since the start of the export.
+	pub entries: Vec<(u64, u64)>,
+}
+
+impl BlocksIndex {
+	/// Returns the path of the index of the export at `path`.
+	pub fn path(export: &Path) -> PathBuf {
+		let mut path = export.as_os_str().to_owned();
+		path.push(".index");
+		path.into()
+	}
+
+	/// Returns the offset of the last indexed block numbered up to `number`, along with the
+	/// number of blocks of the export from this one.
+	pub fn seek(&self, number: u64) -> Option<(u64, u64)> {
+		let first = self.entries.first()?.0;
+		self.entries.iter()
+			.rev()
+			.find(|(block, _)| *block <= number)
+			.map(|(block, offset)| (*offset, self.count.saturating_sub(block - first)))
+	}
+}
+
+/// Version of the format of the state snapshots.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Entry of a chunk of a state snapshot: the storage key of the default child trie it belongs to
+/// (`None` for the top trie), its key and its value.
+pub type SnapshotEntry = (Option<Vec<u8>>, Vec<u8>, Vec<u8>);
+
+/// Manifest of a snapshot of the state of a block.
+///
+/// The state is split in chunks, each being the SCALE encoding of a `Vec<SnapshotEntry>`, in
+/// the order of [`ProofProvider::state_chunk`]. How the chunks are stored is up to the caller.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct SnapshotManifest<Hash, Header> {
+	/// Version of the format of the snapshot.
+	pub version: u32,
+	/// Hash of the block the state belongs to.
+	pub block_hash: Hash,
+	/// Root of the state, as found in the header of the block.
+	pub state_root: Hash,
+	/// Header of the block.
+	pub header: Header,
+	/// Justification of the block, if any.
+	pub justification: Option<Justification>,
+	/// BLAKE2-256 hashes of the encoded chunks, in order.
+	pub chunks: Vec<[u8; 32]>,
+}
+
+/// Returns the `start` of [`ProofProvider::state_chunk`] resuming after `entry`.
+fn snapshot_cursor(entry: &StateEntry) -> Vec<Vec<u8>> {
+	match &entry.child {
+		None => vec![entry.key.clone()],
+		Some(storage_key) => vec![
+			ChildInfo::new_default(storage_key).prefixed_storage_key().into_inner(),
+			entry.key.clone(),
+		],
+	}
+}
+
+/// Checks the chunks of a snapshot against its `manifest`, and returns the block to import along
+/// with its state.
+///
+/// The state itself is checked against the state root of the header when the block is imported.
+fn read_snapshot<B: BlockT>(
+	manifest: SnapshotManifest<B::Hash, B::Header>,
+	chunks: impl Iterator<Item = Result<Vec<u8>, Error>>,
+) -> Result<IncomingBlock<B>, Error> {
+	if manifest.version != SNAPSHOT_VERSION {
+		return Err(Error::Other(format!("Unsupported snapshot version {}", manifest.version)))
+	}
+	let hash = manifest.header.hash();
+	if hash != manifest.block_hash {
+		return Err(Error::Other(format!(
+			"The header of the snapshot hashes to {}, not to the block hash {} of the manifest",
+			hash,
+			manifest.block_hash,
+		)))
+	}
+	if *manifest.header.state_root() != manifest.state_root {
+		return Err(Error::Other(format!(
+			"The state root {} of the manifest differs from the one of the header",
+			manifest.state_root,
+		)))
+	}
+
+	let mut state = ImportedState::<B> { block: hash, top: Vec::new(), children: Vec::new() };
+	let mut count = 0;
+	for (index, chunk) in chunks.enumerate() {
+		let chunk = chunk?;
+		if manifest.chunks.get(index) != Some(&blake2_256(&chunk)) {
+			return Err(Error::Other(format!("The chunk #{} doesn't match the manifest", index)))
+		}
+		let entries = Vec::<SnapshotEntry>::decode(&mut &chunk[..])
+			.map_err(|e| Error::Other(format!("Invalid chunk #{}: {}", index, e)))?;
+		for (child, key, value) in entries {
+			let storage_key = match child {
+				None => {
+					state.top.push((key, value));
+					continue
+				},
+				Some(storage_key) => storage_key,
+			};
+			if state.children.last().map_or(true, |(last, _)| *last != storage_key) {
+				state.children.push((storage_key, Vec::new()));
+			}
+			let (_, child) = state.children.last_mut()
+				.expect("an entry for the child trie has been pushed above; qed");
+			child.push((key, value));
+		}
+		count += 1;
+	}
+	if count != manifest.chunks.len() {
+		return Err(Error::Other(format!(
+			"The snapshot has {} chunks, the manifest lists {}",
+			count,
+			manifest.chunks.len(),
+		)))
+	}
+
+	Ok(IncomingBlock {
+		hash,
+		header: Some(manifest.header),
+		body: None,
+		justification: manifest.justification,
+		origin: None,
+		allow_missing_state: true,
+		import_existing: false,
+		state: Some(state),
+	})
+}
+
 /// Build a chain spec json
 pub fn build_spec(spec: &dyn ChainSpec, raw: bool) -> error::Result<String> {
 	spec.as_json(raw).map_err(Into::into)
 }
 
 
+/// Writes the index of a binary export, if requested.
+fn write_index(index: &mut Option<Box<dyn Write>>, blocks_index: &BlocksIndex) -> Result<(), Error> {
+	if let Some(index) = index {
+		index.write_all(&blocks_index.encode())?;
+	}
+	Ok(())
+}
+
 /// Helper enum that wraps either a binary decoder (from parity-scale-codec), or a JSON decoder (from serde_json).
 /// Implements the Iterator Trait, calling `next()` will decode the next SignedBlock and return it.
 enum BlockIter<R, B> where
-	R: std::io::Read + std::io::Seek,
+	R: std::io::Read,
 {
 	Binary {
 		// Total number of blocks we are expecting to decode.
@@ -83,7 +239,7 @@ enum BlockIter<R, B> where
 }
 
 impl<R, B> BlockIter<R, B> where
-	R: Read + Seek + 'static,
+	R: Read + 'static,
 	B: BlockT + MaybeSerializeDeserialize,
 {
 	fn new(input: R, binary: bool) -> Result<Self, String> {
@@ -116,18 +272,10 @@ impl<R, B> BlockIter<R, B> where
 			=> *read_block_count,
 		}
 	}
-
-	/// Returns the total number of blocks to be imported, if possible.
-	fn num_expected_blocks(&self) -> Option<u64> {
-		match self {
-			BlockIter::Binary { num_expected_blocks, ..} => Some(*num_expected_blocks),
-			BlockIter::Json {..} => None
-		}
-	}
 }
 
 impl<R, B> Iterator for BlockIter<R, B> where
-	R: Read + Seek + 'static,
+	R: Read + 'static,
 	B: BlockT + MaybeSerializeDeserialize,
 {
 	type Item = Result<SignedBlock<B>, String>;
@@ -175,21 +323,18 @@ fn import_block_to_queue<TBl, TImpQu>(
 			origin: None,
 			allow_missing_state: false,
 			import_existing: force,
+			state: None,
 		}
 	]);
 }
 
 /// Returns true if we have imported every block we were supposed to import, else returns false.
 fn importing_is_done(
-	num_expected_blocks: Option<u64>,
 	read_block_count: u64,
+	skipped_blocks: u64,
 	imported_blocks: u64
 ) -> bool {
-	if let Some(num_expected_blocks) = num_expected_blocks {
-		imported_blocks >= num_expected_blocks
-	} else {
-		imported_blocks >= read_block_count
-	}
+	imported_blocks + skipped_blocks >= read_block_count
 }
 
 /// Structure used to log the block importing speed.
@@ -266,7 +411,7 @@ impl<B: BlockT> Speedometer<B> {
 
 /// Different State that the `import_blocks` future could be in.
 enum ImportState<R, B> where 
-	R: Read + Seek + 'static,
+	R: Read + 'static,
 	B: BlockT + MaybeSerializeDeserialize,
 {
 	/// We are reading from the BlockIter structure, adding those blocks to the queue if possible.
@@ -279,7 +424,6 @@ enum ImportState<R, B> where
 	},
 	// We have added all the blocks to the queue but they are still being processed.
 	WaitingForImportQueueToFinish{
-		num_expected_blocks: Option<u64>, 
 		read_block_count: u64,
 		delay: Delay,
 	},
@@ -306,9 +450,11 @@ impl<
 
 	fn import_blocks(
 		mut self,
-		input: impl Read + Seek + Send + 'static,
+		input: impl Read + Send + 'static,
 		force: bool,
 		binary: bool,
+		from: Option<NumberFor<TBl>>,
+		to: Option<NumberFor<TBl>>,
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
 		struct WaitLink {
 			imported_blocks: u64,
@@ -357,6 +503,9 @@ impl<
 
 		let mut state = Some(ImportState::Reading{block_iter});
 		let mut speedometer = Speedometer::<TBl>::new();
+		// Number of blocks read but not added to the queue, because they are out of the range or
+		// already imported.
+		let mut skipped_blocks = 0;
 
 		// Importing blocks is implemented as a future, because we want the operation to be
 		// interruptible.
@@ -371,18 +520,32 @@ impl<
 			match state.take().expect("state should never be None; qed") {
 				ImportState::Reading{mut block_iter} => {
 					match block_iter.next() {
+						// The blocks are exported in ascending order, so the ones following a block
+						// past the range are past it too.
+						Some(Ok(ref block)) if to.map_or(false, |to| *block.block.header().number() > to) => {
+							skipped_blocks += 1;
+							let read_block_count = block_iter.read_block_count();
+							let delay = Delay::new(Duration::from_millis(DELAY_TIME));
+							state = Some(ImportState::WaitingForImportQueueToFinish{read_block_count, delay});
+						},
 						None => {
 							// The iterator is over: we now need to wait for the import queue to finish.
-							let num_expected_blocks = block_iter.num_expected_blocks();
 							let read_block_count = block_iter.read_block_count();
 							let delay = Delay::new(Duration::from_millis(DELAY_TIME));
-							state = Some(ImportState::WaitingForImportQueueToFinish{num_expected_blocks, read_block_count, delay});
+							state = Some(ImportState::WaitingForImportQueueToFinish{read_block_count, delay});
 						},
 						Some(block_result) => {
 							let read_block_count = block_iter.read_block_count();
 							match block_result {
 								Ok(block) => {
-									if read_block_count - link.imported_blocks >= MAX_PENDING_BLOCKS {
+									let header = block.block.header();
+									let skip = from.map_or(false, |from| *header.number() < from) || (
+										!force && client.status(BlockId::Hash(header.hash())).ok() == Some(BlockStatus::InChain)
+									);
+									if skip {
+										skipped_blocks += 1;
+										state = Some(ImportState::Reading{block_iter});
+									} else if read_block_count - skipped_blocks - link.imported_blocks >= MAX_PENDING_BLOCKS {
 										// The queue is full, so do not add this block and simply wait until
 										// the queue has made some progress.
 										let delay = Delay::new(Duration::from_millis(DELAY_TIME));
@@ -403,7 +566,7 @@ impl<
 				},
 				ImportState::WaitingForImportQueueToCatchUp{block_iter, mut delay, block} => {
 					let read_block_count = block_iter.read_block_count();
-					if read_block_count - link.imported_blocks >= MAX_PENDING_BLOCKS {
+					if read_block_count - skipped_blocks - link.imported_blocks >= MAX_PENDING_BLOCKS {
 						// Queue is still full, so wait until there is room to insert our block.
 						match Pin::new(&mut delay).poll(cx) {
 							Poll::Pending => {
@@ -422,14 +585,17 @@ impl<
 						state = Some(ImportState::Reading{block_iter});
 					}
 				},
-				ImportState::WaitingForImportQueueToFinish{num_expected_blocks, read_block_count, mut delay} => {
+				ImportState::WaitingForImportQueueToFinish{read_block_count, mut delay} => {
 					// All the blocks have been added to the queue, which doesn't mean they 
 					// have all been properly imported.
-					if importing_is_done(num_expected_blocks, read_block_count, link.imported_blocks) {
+					if importing_is_done(read_block_count, skipped_blocks, link.imported_blocks) {
 						// Importing is done, we can log the result and return.
+						if skipped_blocks > 0 {
+							info!("Skipped {} blocks out of the range or already imported", skipped_blocks);
+						}
 						info!(
 							"🎉 Imported {} blocks. Best: #{}",
-							read_block_count, client.chain_info().best_number
+							read_block_count - skipped_blocks, client.chain_info().best_number
 						);
 						return Poll::Ready(Ok(()))
 					} else {
@@ -437,7 +603,7 @@ impl<
 						// Wait for the delay, because we know the queue is lagging behind.
 						match Pin::new(&mut delay).poll(cx) {
 							Poll::Pending => {
-								state = Some(ImportState::WaitingForImportQueueToFinish{num_expected_blocks, read_block_count, delay});
+								state = Some(ImportState::WaitingForImportQueueToFinish{read_block_count, delay});
 								return Poll::Pending
 							},
 							Poll::Ready(_) => {
@@ -445,7 +611,7 @@ impl<
 							},
 						}
 
-						state = Some(ImportState::WaitingForImportQueueToFinish{num_expected_blocks, read_block_count, delay});
+						state = Some(ImportState::WaitingForImportQueueToFinish{read_block_count, delay});
 					}
 				}
 			}
@@ -474,7 +640,8 @@ impl<
 		mut output: impl Write + 'static,
 		from: NumberFor<TBl>,
 		to: Option<NumberFor<TBl>>,
-		binary: bool
+		binary: bool,
+		mut index: Option<Box<dyn Write>>,
 	) -> Pin<Box<dyn Future<Output = Result<(), Error>>>> {
 		let mut block = from;
 
@@ -485,6 +652,9 @@ impl<
 		};
 
 		let mut wrote_header = false;
+		let mut blocks_index = BlocksIndex::default();
+		// Offset of the next block in the output, after the number of blocks.
+		let mut offset = 8u64;
 
 		// Exporting blocks is implemented as a future, because we want the operation to be
 		// interruptible.
@@ -512,22 +682,28 @@ impl<
 			}
 
 			match client.block(&BlockId::number(block))? {
-				Some(block) => {
+				Some(signed_block) => {
 					if binary {
-						output.write_all(&block.encode())?;
+						if blocks_index.count % INDEX_INTERVAL == 0 {
+							blocks_index.entries.push((block.saturated_into::<u64>(), offset));
+						}
+						let encoded = signed_block.encode();
+						output.write_all(&encoded)?;
+						offset += encoded.len() as u64;
+						blocks_index.count += 1;
 					} else {
-						serde_json::to_writer(&mut output, &block)
+						serde_json::to_writer(&mut output, &signed_block)
 							.map_err(|e| format!("Error writing JSON: {}", e))?;
 					}
-			},
+				},
 				// Reached end of the chain.
-				None => return Poll::Ready(Ok(())),
+				None => return Poll::Ready(write_index(&mut index, &blocks_index)),
 			}
 			if (block % 10000.into()).is_zero() {
 				info!("#{}", block);
 			}
 			if block == last {
-				return Poll::Ready(Ok(()));
+				return Poll::Ready(write_index(&mut index, &blocks_index));
 			}
 			block += One::one();
 
@@ -541,15 +717,37 @@ impl<
 
 	fn revert_chain(
 		&self,
-		blocks: NumberFor<TBl>
+		blocks: NumberFor<TBl>,
+		revert_finalized: bool,
 	) -> Result<(), Error> {
-		let reverted = self.client.revert(blocks)?;
+		let reverted = if revert_finalized {
+			let finalized = self.client.chain_info().finalized_number;
+			warn!("⚠️  Reverting finalized blocks, from #{}.", finalized);
+			warn!("⚠️  Other nodes keep considering them final: all the operators of the chain must revert them.");
+			self.client.unsafe_revert(blocks)?
+		} else {
+			self.client.revert(blocks)?
+		};
 		let info = self.client.chain_info();
 
-		if reverted.is_zero() {
+		if let Some(aux_revert) = &self.aux_revert {
+			aux_revert(self.client.clone())?;
+		}
+
+		if reverted.is_zero() && revert_finalized {
+			info!("There aren't any blocks whose state is available to revert.");
+		} else if reverted.is_zero() {
 			info!("There aren't any non-finalized blocks to revert.");
+		} else if reverted < blocks && revert_finalized {
+			warn!(
+				"Reverted {} blocks only, the states of the older blocks are pruned. Best: #{} ({}), finalized: #{}",
+				reverted, info.best_number, info.best_hash, info.finalized_number,
+			);
 		} else {
-			info!("Reverted {} blocks. Best: #{} ({})", reverted, info.best_number, info.best_hash);
+			info!(
+				"Reverted {} blocks. Best: #{} ({}), finalized: #{}",
+				reverted, info.best_number, info.best_hash, info.finalized_number,
+			);
 		}
 		Ok(())
 	}
@@ -564,7 +762,7 @@ impl<
 				1u64.encode_to(&mut buf);
 				block.encode_to(&mut buf);
 				let reader = std::io::Cursor::new(buf);
-				self.import_blocks(reader, true, true)
+				self.import_blocks(reader, true, true, None, None)
 			}
 			Ok(None) => Box::pin(future::err("Unknown block".into())),
 			Err(e) => Box::pin(future::err(format!("Error reading block: {:?}", e).into())),
@@ -611,4 +809,170 @@ impl<
 		let top = top_storage.into_iter().map(|(k, v)| (k.0, v.0)).collect();
 		Ok(Storage { top, children_default })
 	}
+
+	fn export_snapshot(
+		&self,
+		block: Option<BlockId<TBl>>,
+		chunk_size: usize,
+		mut output: impl FnMut(usize, Vec<u8>) -> Result<(), Error>,
+	) -> Result<SnapshotManifest<TBl::Hash, TBl::Header>, Error> {
+		let block = block.unwrap_or_else(
+			|| BlockId::Hash(self.client.usage_info().chain.best_hash)
+		);
+		let header = self.client.header(&block)?
+			.ok_or_else(|| Error::Other(format!("Unknown block {:?}", block)))?;
+		let block_hash = header.hash();
+		let block = BlockId::Hash(block_hash);
+		let justification = self.client.justification(&block)?;
+
+		let mut chunks = Vec::new();
+		let mut start = Vec::new();
+		loop {
+			let (entries, complete) = self.client.state_chunk(&block, &start, chunk_size)?;
+			if let Some(last) = entries.last() {
+				start = snapshot_cursor(last);
+			}
+			let chunk = entries.into_iter()
+				.map(|entry| (entry.child, entry.key, entry.value))
+				.collect::<Vec<SnapshotEntry>>()
+				.encode();
+			chunks.push(blake2_256(&chunk));
+			output(chunks.len() - 1, chunk)?;
+			if complete {
+				break
+			}
+		}
+
+		info!("Exported the state of block #{} ({}) in {} chunks", header.number(), block_hash, chunks.len());
+		Ok(SnapshotManifest {
+			version: SNAPSHOT_VERSION,
+			block_hash,
+			state_root: *header.state_root(),
+			header,
+			justification,
+			chunks,
+		})
+	}
+
+	fn import_snapshot(
+		mut self,
+		manifest: SnapshotManifest<TBl::Hash, TBl::Header>,
+		chunks: impl Iterator<Item = Result<Vec<u8>, Error>>,
+	) -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+		struct SnapshotLink {
+			result: Option<Result<(), String>>,
+		}
+
+		impl<B: BlockT> Link<B> for SnapshotLink {
+			fn blocks_processed(
+				&mut self,
+				_imported: usize,
+				_num_expected_blocks: usize,
+				results: Vec<(Result<BlockImportResult<NumberFor<B>>, BlockImportError>, B::Hash)>
+			) {
+				for (result, hash) in results {
+					self.result = Some(result.map(|_| ()).map_err(|e| {
+						format!("Error importing the block {:?} of the snapshot: {:?}", hash, e)
+					}));
+				}
+			}
+		}
+
+		let info = self.client.chain_info();
+		if !info.best_number.is_zero() {
+			return Box::pin(future::err(Error::Other(format!(
+				"A snapshot can only be imported into a fresh node, the best block is #{}",
+				info.best_number,
+			))))
+		}
+		let number = *manifest.header.number();
+		let block = match read_snapshot::<TBl>(manifest, chunks) {
+			Ok(block) => block,
+			Err(e) => return Box::pin(future::err(e)),
+		};
+		let hash = block.hash;
+		self.import_queue.import_blocks(BlockOrigin::File, vec![block]);
+
+		let mut link = SnapshotLink { result: None };
+		let import = future::poll_fn(move |cx| {
+			self.import_queue.poll_actions(cx, &mut link);
+			match link.result.take() {
+				Some(result) => {
+					if result.is_ok() {
+						info!("🎉 Imported the state of block #{} ({})", number, hash);
+					}
+					Poll::Ready(result.map_err(Error::Other))
+				},
+				None => {
+					cx.waker().wake_by_ref();
+					Poll::Pending
+				},
+			}
+		});
+		Box::pin(import)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn blocks_index_seeks_to_the_preceding_indexed_block() {
+		let index = BlocksIndex { count: 2_500, entries: vec![(10, 8), (1_010, 500), (2_010, 900)] };
+		assert_eq!(index.seek(5), None);
+		assert_eq!(index.seek(10), Some((8, 2_500)));
+		assert_eq!(index.seek(1_500), Some((500, 1_500)));
+		assert_eq!(index.seek(10_000), Some((900, 500)));
+		assert_eq!(BlocksIndex::default().seek(10), None);
+		assert_eq!(
+			BlocksIndex::path(Path::new("/tmp/blocks.bin")),
+			PathBuf::from("/tmp/blocks.bin.index"),
+		);
+	}
+
+	#[test]
+	fn snapshot_chunks_are_checked_against_the_manifest() {
+		use substrate_test_runtime_client::runtime::{Block as TestBlock, Header as TestHeader};
+
+		let header = TestHeader::new(
+			10,
+			Default::default(),
+			[1; 32].into(),
+			Default::default(),
+			Default::default(),
+		);
+		fn entry(child: Option<&[u8]>, key: &[u8], value: &[u8]) -> SnapshotEntry {
+			(child.map(|child| child.to_vec()), key.to_vec(), value.to_vec())
+		}
+		let child = Some(&b"child"[..]);
+		let chunks = vec![
+			vec![entry(None, b"a", b"1"), entry(child, b"b", b"2")].encode(),
+			vec![entry(child, b"c", b"3"), entry(None, b"d", b"4")].encode(),
+		];
+		let manifest = SnapshotManifest {
+			version: SNAPSHOT_VERSION,
+			block_hash: header.hash(),
+			state_root: *header.state_root(),
+			header: header.clone(),
+			justification: None,
+			chunks: chunks.iter().map(|chunk| blake2_256(chunk)).collect(),
+		};
+
+		let block = read_snapshot::<TestBlock>(manifest.clone(), chunks.clone().into_iter().map(Ok)).unwrap();
+		let state = block.state.unwrap();
+		assert_eq!(block.hash, header.hash());
+		assert_eq!(state.top, vec![(b"a".to_vec(), b"1".to_vec()), (b"d".to_vec(), b"4".to_vec())]);
+		assert_eq!(
+			state.children,
+			vec![(b"child".to_vec(), vec![(b"b".to_vec(), b"2".to_vec()), (b"c".to_vec(), b"3".to_vec())])],
+		);
+
+		let mut tampered = chunks.clone();
+		tampered[1] = Vec::<SnapshotEntry>::new().encode();
+		assert!(read_snapshot::<TestBlock>(manifest.clone(), tampered.into_iter().map(Ok)).is_err());
+		assert!(read_snapshot::<TestBlock>(manifest.clone(), chunks.clone().into_iter().take(1).map(Ok)).is_err());
+		let wrong_root = SnapshotManifest { state_root: [2; 32].into(), ..manifest };
+		assert!(read_snapshot::<TestBlock>(wrong_root, chunks.into_iter().map(Ok)).is_err());
+	}
 }
@@ -18,8 +18,9 @@
 
 //! Client fixed chain specification rules
 
-use std::collections::{HashMap, HashSet};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
 
+use parking_lot::RwLock;
 use sp_runtime::{
 	traits::{Block as BlockT, NumberFor},
 };
@@ -43,6 +44,51 @@ pub enum LookupResult<B: BlockT> {
 pub struct BlockRules<B: BlockT> {
 	bad: HashSet<B::Hash>,
 	forks: HashMap<NumberFor<B>, B::Hash>,
+	pinned: PinnedBlocks<B>,
+}
+
+/// Block hashes pinned at given heights while the node is running.
+///
+/// Competing blocks at these heights are rejected on import, in addition to the ones of the
+/// fork blocks of the chain spec. This is meant to recover from a contentious fork without
+/// changing the chain spec: blocks already imported are left untouched.
+pub struct PinnedBlocks<B: BlockT>(Arc<RwLock<HashMap<NumberFor<B>, B::Hash>>>);
+
+impl<B: BlockT> Clone for PinnedBlocks<B> {
+	fn clone(&self) -> Self {
+		PinnedBlocks(self.0.clone())
+	}
+}
+
+impl<B: BlockT> Default for PinnedBlocks<B> {
+	fn default() -> Self {
+		PinnedBlocks(Default::default())
+	}
+}
+
+impl<B: BlockT> PinnedBlocks<B> {
+	/// Pins `hash` at the height `number`, replacing the hash previously pinned there, if any.
+	pub fn pin(&self, number: NumberFor<B>, hash: B::Hash) {
+		self.0.write().insert(number, hash);
+	}
+
+	/// Unpins the hash at the height `number`. Returns `false` if there was none.
+	pub fn unpin(&self, number: NumberFor<B>) -> bool {
+		self.0.write().remove(&number).is_some()
+	}
+
+	/// Returns the pinned hashes, ordered by height.
+	pub fn list(&self) -> Vec<(NumberFor<B>, B::Hash)> {
+		let mut pinned = self.0.read().iter()
+			.map(|(number, hash)| (*number, hash.clone()))
+			.collect::<Vec<_>>();
+		pinned.sort_by_key(|(number, _)| *number);
+		pinned
+	}
+
+	fn get(&self, number: &NumberFor<B>) -> Option<B::Hash> {
+		self.0.read().get(number).cloned()
+	}
 }
 
 impl<B: BlockT> BlockRules<B> {
@@ -54,11 +100,23 @@ impl<B: BlockT> BlockRules<B> {
 		Self {
 			bad: bad_blocks.unwrap_or(HashSet::new()),
 			forks: fork_blocks.unwrap_or(vec![]).into_iter().collect(),
+			pinned: Default::default(),
 		}
 	}
 
+	/// The block hashes pinned at runtime, which take precedence over the fork blocks.
+	pub fn pinned(&self) -> &PinnedBlocks<B> {
+		&self.pinned
+	}
+
 	/// Check if there's any rule affecting the given block.
 	pub fn lookup(&self, number: NumberFor<B>, hash: &B::Hash) -> LookupResult<B> {
+		if let Some(pinned_hash) = self.pinned.get(&number) {
+			if &pinned_hash != hash {
+				return LookupResult::Expected(pinned_hash);
+			}
+		}
+
 		if let Some(hash_for_height) = self.forks.get(&number) {
 			if hash_for_height != hash {
 				return LookupResult::Expected(hash_for_height.clone());
@@ -72,3 +130,29 @@ impl<B: BlockT> BlockRules<B> {
 		LookupResult::NotSpecial
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_test_runtime_client::runtime::{Block, Hash};
+
+	#[test]
+	fn pinned_blocks_take_precedence_over_fork_blocks() {
+		let fork = Hash::repeat_byte(1);
+		let pinned = Hash::repeat_byte(2);
+		let rules = BlockRules::<Block>::new(Some(vec![(10, fork)]), None);
+		assert!(matches!(rules.lookup(10, &fork), LookupResult::NotSpecial));
+		assert!(matches!(rules.lookup(11, &pinned), LookupResult::NotSpecial));
+
+		rules.pinned().pin(10, pinned);
+		rules.pinned().pin(11, pinned);
+		assert!(matches!(rules.lookup(10, &fork), LookupResult::Expected(hash) if hash == pinned));
+		assert!(matches!(rules.lookup(10, &pinned), LookupResult::NotSpecial));
+		assert!(matches!(rules.lookup(11, &fork), LookupResult::Expected(hash) if hash == pinned));
+		assert_eq!(rules.pinned().list(), vec![(10, pinned), (11, pinned)]);
+
+		assert!(rules.pinned().unpin(10));
+		assert!(!rules.pinned().unpin(10));
+		assert!(matches!(rules.lookup(10, &fork), LookupResult::NotSpecial));
+	}
+}
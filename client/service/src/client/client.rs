@@ -29,7 +29,10 @@ use codec::{Encode, Decode};
 use hash_db::Prefix;
 use sp_core::{
 	ChangesTrieConfiguration, convert_hash, NativeOrEncoded,
-	storage::{StorageKey, PrefixedStorageKey, StorageData, well_known_keys, ChildInfo},
+	storage::{
+		StorageKey, PrefixedStorageKey, StorageData, well_known_keys, ChildInfo, Storage,
+		StorageChild,
+	},
 };
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
 use sp_runtime::{
@@ -44,11 +47,12 @@ use sp_state_machine::{
 	DBValue, Backend as StateBackend, ChangesTrieAnchorBlockId,
 	prove_read, prove_child_read, ChangesTrieRootsStorage, ChangesTrieStorage,
 	ChangesTrieConfigurationRange, key_changes, key_changes_proof,
+	ProvingBackend, create_proof_check_backend,
 };
 use sc_executor::RuntimeVersion;
 use sp_consensus::{
 	Error as ConsensusError, BlockStatus, BlockImportParams, BlockCheckParams,
-	ImportResult, BlockOrigin, ForkChoiceStrategy, RecordProof,
+	ImportResult, BlockOrigin, ForkChoiceStrategy, RecordProof, ImportedState,
 };
 use sp_blockchain::{
 	self as blockchain,
@@ -67,18 +71,21 @@ use sc_client_api::{
 	backend::{
 		self, BlockImportOperation, PrunableStateChangesTrieStorage,
 		ClientImportOperation, Finalizer, ImportSummary, NewBlockState,
-		changes_tries_state_at_block, StorageProvider,
-		LockImportRun, apply_aux,
+		changes_tries_state_at_block, StorageProvider, StoragePrefixStats,
+		LockImportRun, apply_aux, storage_stats,
 	},
 	client::{
 		ImportNotifications, FinalityNotification, FinalityNotifications, BlockImportNotification,
 		ClientInfo, BlockchainEvents, BlockBackend, ProvideUncles, BadBlocks, ForkBlocks,
-		BlockOf,
+		BlockOf, ForkBackend, LeafInfo,
 	},
 	execution_extensions::ExecutionExtensions,
+	lossy_notifications::{
+		LossyImportNotifications, LossyFinalityNotifications, LossySender, lossy_channel,
+	},
 	notifications::{StorageNotifications, StorageEventStream},
-	KeyIterator, CallExecutor, ExecutorProvider, ProofProvider,
-	cht, UsageProvider
+	KeyIterator, CallExecutor, ExecutionStrategy, ExecutorProvider, ProofProvider, StateEntry,
+	cht, runtime_metadata, UsageProvider
 };
 use sp_utils::mpsc::tracing_unbounded;
 use sp_blockchain::Error;
@@ -86,7 +93,7 @@ use prometheus_endpoint::Registry;
 use super::{
 	genesis,
 	light::{call_executor::prove_execution, fetcher::ChangesProof},
-	block_rules::{BlockRules, LookupResult as BlockLookupResult},
+	block_rules::{BlockRules, LookupResult as BlockLookupResult, PinnedBlocks},
 };
 use futures::channel::mpsc;
 use rand::Rng;
@@ -100,6 +107,7 @@ use {
 };
 
 type NotificationSinks<T> = Mutex<Vec<mpsc::UnboundedSender<T>>>;
+type LossyNotificationSinks<Block, T> = Mutex<Vec<LossySender<Block, T>>>;
 
 /// Substrate Client
 pub struct Client<B, E, Block, RA> where Block: BlockT {
@@ -108,6 +116,8 @@ pub struct Client<B, E, Block, RA> where Block: BlockT {
 	storage_notifications: Mutex<StorageNotifications<Block>>,
 	import_notification_sinks: NotificationSinks<BlockImportNotification<Block>>,
 	finality_notification_sinks: NotificationSinks<FinalityNotification<Block>>,
+	lossy_import_notification_sinks: LossyNotificationSinks<Block, BlockImportNotification<Block>>,
+	lossy_finality_notification_sinks: LossyNotificationSinks<Block, FinalityNotification<Block>>,
 	// holds the block hash currently being imported. TODO: replace this with block queue
 	importing_block: RwLock<Option<Block::Hash>>,
 	block_rules: BlockRules<Block>,
@@ -240,15 +250,21 @@ impl<B, E, Block, RA> LockImportRun<Block, B> for Client<B, E, Block, RA>
 				op: self.backend.begin_operation()?,
 				notify_imported: None,
 				notify_finalized: Vec::new(),
+				runtime_upgrades: Vec::new(),
 			};
 
 			let r = f(&mut op)?;
 
-			let ClientImportOperation { op, notify_imported, notify_finalized } = op;
+			let ClientImportOperation { op, notify_imported, notify_finalized, runtime_upgrades } = op;
 			self.backend.commit_operation(op)?;
 
 			self.notify_finalized(notify_finalized)?;
 			self.notify_imported(notify_imported)?;
+			for hash in runtime_upgrades {
+				if let Err(e) = self.store_runtime_metadata(hash) {
+					warn!("Failed to store the metadata of the runtime of {:?}: {:?}", hash, e);
+				}
+			}
 
 			Ok(r)
 		};
@@ -317,6 +333,8 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			storage_notifications: Mutex::new(StorageNotifications::new(prometheus_registry)),
 			import_notification_sinks: Default::default(),
 			finality_notification_sinks: Default::default(),
+			lossy_import_notification_sinks: Default::default(),
+			lossy_finality_notification_sinks: Default::default(),
 			importing_block: Default::default(),
 			block_rules: BlockRules::new(fork_blocks, bad_blocks),
 			execution_extensions,
@@ -354,6 +372,25 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		self.executor.runtime_version(id)
 	}
 
+	// stores the metadata of the runtime of the committed block `hash`, unless it has already
+	// been stored. The runtimes of old blocks may not be instantiable any more.
+	fn store_runtime_metadata(&self, hash: Block::Hash) -> sp_blockchain::Result<()> {
+		let at = BlockId::Hash(hash);
+		let code_hash = match self.storage_hash(&at, &StorageKey(well_known_keys::CODE.to_vec()))? {
+			Some(code_hash) => code_hash,
+			None => return Ok(()),
+		};
+		if runtime_metadata::load_spec_version(&*self.backend, &code_hash)?.is_some() {
+			return Ok(())
+		}
+
+		let spec_version = self.runtime_version_at(&at)?.spec_version;
+		let metadata = self.executor.call(&at, "Metadata_metadata", &[], ExecutionStrategy::NativeElseWasm, None)?;
+		let metadata = Vec::<u8>::decode(&mut &metadata[..])
+			.map_err(|e| sp_blockchain::Error::Backend(format!("Invalid runtime metadata: {}", e)))?;
+		runtime_metadata::write_metadata(&*self.backend, &code_hash, spec_version, &metadata)
+	}
+
 	/// Get block hash by number.
 	pub fn block_hash(&self,
 		block_number: <<Block as BlockT>::Header as HeaderT>::Number
@@ -621,6 +658,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			fork_choice,
 			intermediates,
 			import_existing,
+			state,
 			..
 		} = import_block;
 
@@ -660,6 +698,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			auxiliary,
 			fork_choice,
 			import_existing,
+			state,
 		);
 
 		if let Ok(ImportResult::Imported(ref aux)) = result {
@@ -696,6 +735,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		aux: Vec<(Vec<u8>, Option<Vec<u8>>)>,
 		fork_choice: ForkChoiceStrategy,
 		import_existing: bool,
+		imported_state: Option<ImportedState<Block>>,
 	) -> sp_blockchain::Result<ImportResult> where
 		Self: ProvideRuntimeApi<Block>,
 		<Self as ProvideRuntimeApi<Block>>::Api: CoreApi<Block, Error = Error> +
@@ -714,8 +754,9 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		let info = self.backend.blockchain().info();
 
 		// the block is lower than our last finalized block so it must revert
-		// finality, refusing import.
-		if *import_headers.post().number() <= info.finalized_number {
+		// finality, refusing import. blocks that are already in chain, and only get their
+		// state or body imported, are the exception.
+		if *import_headers.post().number() <= info.finalized_number && !import_existing && imported_state.is_none() {
 			return Err(sp_blockchain::Error::NotInFinalizedChain);
 		}
 
@@ -727,6 +768,24 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			BlockOrigin::Genesis | BlockOrigin::NetworkInitialSync | BlockOrigin::File => false,
 		};
 
+		let has_imported_state = imported_state.is_some();
+		if let Some(imported_state) = imported_state {
+			let storage = Storage {
+				top: imported_state.top.into_iter().collect(),
+				children_default: imported_state.children.into_iter().map(|(storage_key, data)| {
+					let child_info = ChildInfo::new_default(&storage_key);
+					(storage_key, StorageChild { data: data.into_iter().collect(), child_info })
+				}).collect(),
+			};
+
+			// the state has been downloaded from peers we don't trust, it is only checked
+			// against the header here.
+			let state_root = operation.op.set_imported_state(storage)?;
+			if state_root != *import_headers.post().state_root() {
+				return Err(Error::InvalidStateRoot);
+			}
+		}
+
 		let storage_changes = match storage_changes {
 			Some(storage_changes) => {
 				self.backend.begin_state_operation(&mut operation.op, BlockId::Hash(parent_hash))?;
@@ -774,6 +833,13 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			None => None,
 		};
 
+		let enacts_runtime = has_imported_state || storage_changes.as_ref().map_or(false, |(main_sc, _)|
+			main_sc.iter().any(|(key, value)| key == well_known_keys::CODE && value.is_some())
+		);
+		if enacts_runtime {
+			operation.runtime_upgrades.push(hash);
+		}
+
 		let is_new_best = finalized || match fork_choice {
 			ForkChoiceStrategy::LongestChain => import_headers.post().number() > &info.best_number,
 			ForkChoiceStrategy::Custom(v) => v,
@@ -787,7 +853,8 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			NewBlockState::Normal
 		};
 
-		let retracted = if is_new_best {
+		// the ancestry of a block imported along with its state is unknown.
+		let retracted = if is_new_best && !has_imported_state {
 			let route_from_best = sp_blockchain::tree_route(
 				self.backend.blockchain(),
 				info.best_hash,
@@ -969,12 +1036,14 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		notify_finalized: Vec<Block::Hash>,
 	) -> sp_blockchain::Result<()> {
 		let mut sinks = self.finality_notification_sinks.lock();
+		let mut lossy_sinks = self.lossy_finality_notification_sinks.lock();
 
 		if notify_finalized.is_empty() {
 			// cleanup any closed finality notification sinks
 			// since we won't be running the loop below which
 			// would also remove any closed sinks.
 			sinks.retain(|sink| !sink.is_closed());
+			lossy_sinks.retain(|sink| !sink.is_closed());
 
 			return Ok(());
 		}
@@ -1007,6 +1076,7 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			};
 
 			sinks.retain(|sink| sink.unbounded_send(notification.clone()).is_ok());
+			lossy_sinks.retain(|sink| sink.send(notification.clone()).is_ok());
 		}
 
 		Ok(())
@@ -1028,18 +1098,22 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 				self.import_notification_sinks
 					.lock()
 					.retain(|sink| !sink.is_closed());
+				self.lossy_import_notification_sinks
+					.lock()
+					.retain(|sink| !sink.is_closed());
 
 				return Ok(());
 			}
 		};
 
-		if let Some(storage_changes) = notify_import.storage_changes {
+		let storage_changes = notify_import.storage_changes.map(Arc::new);
+		if let Some(storage_changes) = &storage_changes {
 			// TODO [ToDr] How to handle re-orgs? Should we re-emit all storage changes?
 			self.storage_notifications.lock()
 				.trigger(
 					&notify_import.hash,
-					storage_changes.0.into_iter(),
-					storage_changes.1.into_iter().map(|(sk, v)| (sk, v.into_iter())),
+					storage_changes.0.iter().cloned(),
+					storage_changes.1.iter().map(|(sk, v)| (sk.clone(), v.iter().cloned())),
 				);
 		}
 
@@ -1049,10 +1123,13 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 			header: notify_import.header,
 			is_new_best: notify_import.is_new_best,
 			retracted: notify_import.retracted,
+			storage_changes,
 		};
 
 		self.import_notification_sinks.lock()
 			.retain(|sink| sink.unbounded_send(notification.clone()).is_ok());
+		self.lossy_import_notification_sinks.lock()
+			.retain(|sink| sink.send(notification.clone()).is_ok());
 
 		Ok(())
 	}
@@ -1077,6 +1154,11 @@ impl<B, E, Block, RA> Client<B, E, Block, RA> where
 		Ok(self.backend.revert(n, true)?)
 	}
 
+	/// The block hashes pinned at given heights, whose competing blocks are rejected on import.
+	pub fn pinned_blocks(&self) -> PinnedBlocks<Block> {
+		self.block_rules.pinned().clone()
+	}
+
 	/// Get blockchain info.
 	pub fn chain_info(&self) -> blockchain::Info<Block> {
 		self.backend.blockchain().info()
@@ -1200,6 +1282,31 @@ impl<B, E, Block, RA> ProofProvider<Block> for Client<B, E, Block, RA> where
 				.map_err(Into::into))
 	}
 
+	fn read_proof_with_child_keys(
+		&self,
+		id: &BlockId<Block>,
+		keys: &mut dyn Iterator<Item=&[u8]>,
+		child_keys: &[(ChildInfo, Vec<Vec<u8>>)],
+	) -> sp_blockchain::Result<StorageProof> {
+		let mut state = self.state_at(id)?;
+		let trie_backend = state.as_trie_backend()
+			.ok_or_else(|| sp_blockchain::Error::from_state(Box::new(
+				sp_state_machine::ExecutionError::UnableToGenerateProof
+			)))?;
+		let proving_backend = ProvingBackend::new(trie_backend);
+		for key in keys {
+			proving_backend.storage(key)
+				.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))?;
+		}
+		for (child_info, keys) in child_keys {
+			for key in keys {
+				proving_backend.child_storage(child_info, key)
+					.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))?;
+			}
+		}
+		Ok(proving_backend.extract_proof())
+	}
+
 	fn execution_proof(
 		&self,
 		id: &BlockId<Block>,
@@ -1228,6 +1335,27 @@ impl<B, E, Block, RA> ProofProvider<Block> for Client<B, E, Block, RA> where
 		})
 	}
 
+	fn prove_execution(
+		&self,
+		id: &BlockId<Block>,
+		method: &str,
+		call_data: &[u8]
+	) -> sp_blockchain::Result<(Vec<u8>, StorageProof)> {
+		// the code is read outside of the proving backend, prove it for the verifier to run it.
+		let code_proof = self.read_proof(
+			id,
+			&mut [well_known_keys::CODE, well_known_keys::HEAP_PAGES].iter().map(|v| *v),
+		)?;
+
+		let mut state = self.state_at(id)?;
+		let trie_state = state.as_trie_backend()
+			.ok_or_else(|| sp_blockchain::Error::from_state(Box::new(
+				sp_state_machine::ExecutionError::UnableToGenerateProof
+			)))?;
+		self.executor.prove_at_trie_state(trie_state, &mut Default::default(), method, call_data)
+			.map(|(r, p)| (r, StorageProof::merge(vec![p, code_proof])))
+	}
+
 	fn header_proof(&self, id: &BlockId<Block>) -> sp_blockchain::Result<(Block::Header, StorageProof)> {
 		self.header_proof_with_cht_size(id, cht::size())
 	}
@@ -1251,8 +1379,135 @@ impl<B, E, Block, RA> ProofProvider<Block> for Client<B, E, Block, RA> where
 			cht::size(),
 		)
 	}
+
+	fn state_chunk(
+		&self,
+		id: &BlockId<Block>,
+		start: &[Vec<u8>],
+		size_limit: usize,
+	) -> sp_blockchain::Result<(Vec<StateEntry>, bool)> {
+		let state = self.state_at(id)?;
+		read_state_chunk(&state, start, size_limit, false)
+	}
+
+	fn read_proof_collection(
+		&self,
+		id: &BlockId<Block>,
+		start: &[Vec<u8>],
+		size_limit: usize,
+	) -> sp_blockchain::Result<StorageProof> {
+		let mut state = self.state_at(id)?;
+		let trie_backend = state.as_trie_backend()
+			.ok_or_else(|| sp_blockchain::Error::from_state(Box::new(
+				sp_state_machine::ExecutionError::UnableToGenerateProof
+			)))?;
+		let proving_backend = ProvingBackend::new(trie_backend);
+		read_state_chunk(&proving_backend, start, size_limit, false)?;
+		Ok(proving_backend.extract_proof())
+	}
+
+	fn verify_range_proof(
+		&self,
+		root: Block::Hash,
+		proof: StorageProof,
+		start: &[Vec<u8>],
+	) -> sp_blockchain::Result<(Vec<StateEntry>, bool)> {
+		let proof_backend = create_proof_check_backend::<HashFor<Block>>(root, proof)
+			.map_err(|e| sp_blockchain::Error::from_state(e))?;
+		read_state_chunk(&proof_backend, start, usize::max_value(), true)
+	}
 }
 
+/// Reads a chunk of the state in key order, with child tries listed right after the top trie key
+/// holding their root. See [`ProofProvider::state_chunk`] for the meaning of `start`.
+///
+/// If `partial` is set, the state is expected to be backed by a proof only covering part of it,
+/// and the chunk ends at the first entry that can't be read.
+fn read_state_chunk<H, S>(
+	state: &S,
+	start: &[Vec<u8>],
+	size_limit: usize,
+	partial: bool,
+) -> sp_blockchain::Result<(Vec<StateEntry>, bool)> where
+	H: hash_db::Hasher,
+	H::Out: Ord + codec::Codec,
+	S: StateBackend<H>,
+{
+	let (mut current, mut child_cursor) = match start {
+		[] => (Vec::new(), None),
+		[top] => (top.clone(), None),
+		[top, child] => (top.clone(), Some(child.clone())),
+		_ => return Err(sp_blockchain::Error::Backend(
+			format!("Invalid start of state chunk: {} keys", start.len())
+		)),
+	};
+	let mut entries = Vec::new();
+	let mut size = 0;
+
+	macro_rules! read {
+		($e:expr) => {
+			match $e {
+				Ok(value) => value,
+				Err(_) if partial => return Ok((entries, false)),
+				Err(e) => return Err(sp_blockchain::Error::from_state(Box::new(e))),
+			}
+		}
+	}
+
+	loop {
+		// `current` holds the root of the child trie being read.
+		if let Some(mut cursor) = child_cursor.take() {
+			let prefix = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+			if !current.starts_with(prefix) {
+				return Err(sp_blockchain::Error::Backend(
+					format!("Unsupported child trie type: {:?}", current)
+				))
+			}
+			let storage_key = current[prefix.len()..].to_vec();
+			let child_info = ChildInfo::new_default(&storage_key);
+			while let Some(next) = read!(state.next_child_storage_key(&child_info, &cursor)) {
+				let value = read!(state.child_storage(&child_info, &next)).unwrap_or_default();
+				size += next.len() + value.len();
+				entries.push(StateEntry { child: Some(storage_key.clone()), key: next.clone(), value });
+				if size >= size_limit {
+					return Ok((entries, false))
+				}
+				cursor = next;
+			}
+		}
+
+		match read!(state.next_storage_key(&current)) {
+			Some(next) if well_known_keys::is_child_storage_key(&next) => {
+				// start from the empty key, which `next_child_storage_key` would skip.
+				current = next;
+				child_cursor = Some(Vec::new());
+				let prefix = well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+				if !current.starts_with(prefix) {
+					continue
+				}
+				let child_info = ChildInfo::new_default(&current[prefix.len()..]);
+				if let Some(value) = read!(state.child_storage(&child_info, &[])) {
+					size += value.len();
+					entries.push(StateEntry {
+						child: Some(child_info.storage_key().to_vec()),
+						key: Vec::new(),
+						value,
+					});
+				}
+			},
+			Some(next) => {
+				let value = read!(state.storage(&next)).unwrap_or_default();
+				size += next.len() + value.len();
+				entries.push(StateEntry { child: None, key: next.clone(), value });
+				current = next;
+				if size >= size_limit {
+					return Ok((entries, false))
+				}
+			},
+			None => return Ok((entries, true)),
+		}
+	}
+}
 
 impl<B, E, Block, RA> BlockBuilderProvider<B, Block, Self> for Client<B, E, Block, RA>
 	where
@@ -1336,19 +1591,33 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 		Ok(keys)
 	}
 
+	fn storage_stats(
+		&self,
+		id: &BlockId<Block>,
+		prefix_len: usize,
+	) -> sp_blockchain::Result<Vec<StoragePrefixStats>> {
+		Ok(storage_stats::<Block, _>(&self.state_at(id)?, prefix_len))
+	}
+
+	fn storage_keys_iter(
+		&self,
+		id: &BlockId<Block>,
+		prefix: Option<&StorageKey>,
+		start_key: Option<&StorageKey>
+	) -> sp_blockchain::Result<KeyIterator<B::State, Block>> {
+		let state = self.state_at(id)?;
+		Ok(KeyIterator::new(state, prefix.cloned(), start_key.cloned()))
+	}
 
-	fn storage_keys_iter<'a>(
+	fn child_storage_keys_iter(
 		&self,
 		id: &BlockId<Block>,
-		prefix: Option<&'a StorageKey>,
+		child_info: ChildInfo,
+		prefix: Option<&StorageKey>,
 		start_key: Option<&StorageKey>
-	) -> sp_blockchain::Result<KeyIterator<'a, B::State, Block>> {
+	) -> sp_blockchain::Result<KeyIterator<B::State, Block>> {
 		let state = self.state_at(id)?;
-		let start_key = start_key
-			.or(prefix)
-			.map(|key| key.0.clone())
-			.unwrap_or_else(Vec::new);
-		Ok(KeyIterator::new(state, prefix, start_key))
+		Ok(KeyIterator::new_child(state, child_info, prefix.cloned(), start_key.cloned()))
 	}
 
 
@@ -1483,6 +1752,15 @@ impl<B, E, Block, RA> StorageProvider<Block, B> for Client<B, E, Block, RA> wher
 
 		Ok(result)
 	}
+
+	fn indexed_key_changes(
+		&self,
+		first: NumberFor<Block>,
+		last: NumberFor<Block>,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<Vec<(NumberFor<Block>, Block::Hash)>>> {
+		self.backend.blockchain().storage_changes(first, last, &key.0)
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA> where
@@ -1689,11 +1967,15 @@ impl<B, E, Block, RA> sp_consensus::BlockImport<Block> for &Client<B, E, Block,
 		let span = tracing::span!(tracing::Level::DEBUG, "import_block");
 		let _enter = span.enter();
 
-		if let Some(res) = self.prepare_block_storage_changes(&mut import_block).map_err(|e| {
-			warn!("Block prepare storage changes error:\n{:?}", e);
-			ConsensusError::ClientImport(e.to_string())
-		})? {
-			return Ok(res)
+		// blocks imported along with their state are neither executed nor required to have a
+		// known parent.
+		if import_block.state.is_none() {
+			if let Some(res) = self.prepare_block_storage_changes(&mut import_block).map_err(|e| {
+				warn!("Block prepare storage changes error:\n{:?}", e);
+				ConsensusError::ClientImport(e.to_string())
+			})? {
+				return Ok(res)
+			}
 		}
 
 		self.lock_import_and_run(|operation| {
@@ -1741,7 +2023,10 @@ impl<B, E, Block, RA> sp_consensus::BlockImport<Block> for &Client<B, E, Block,
 		{
 			BlockStatus::InChainWithState | BlockStatus::Queued if !import_existing  => return Ok(ImportResult::AlreadyInChain),
 			BlockStatus::InChainWithState | BlockStatus::Queued => {},
-			BlockStatus::InChainPruned => return Ok(ImportResult::AlreadyInChain),
+			BlockStatus::InChainPruned if !import_existing => return Ok(ImportResult::AlreadyInChain),
+			// blocks imported without their state by fast sync are executed once their parent
+			// state is available.
+			BlockStatus::InChainPruned => {},
 			BlockStatus::Unknown => {},
 			BlockStatus::KnownBad => return Ok(ImportResult::KnownBad),
 		}
@@ -1867,6 +2152,18 @@ where
 		stream
 	}
 
+	fn lossy_import_notification_stream(&self, capacity: usize) -> LossyImportNotifications<Block> {
+		let (sink, stream) = lossy_channel(capacity);
+		self.lossy_import_notification_sinks.lock().push(sink);
+		stream
+	}
+
+	fn lossy_finality_notification_stream(&self, capacity: usize) -> LossyFinalityNotifications<Block> {
+		let (sink, stream) = lossy_channel(capacity);
+		self.lossy_finality_notification_sinks.lock().push(sink);
+		stream
+	}
+
 	/// Get storage changes event stream.
 	fn storage_changes_notification_stream(
 		&self,
@@ -1875,6 +2172,13 @@ where
 	) -> sp_blockchain::Result<StorageEventStream<Block::Hash>> {
 		Ok(self.storage_notifications.lock().listen(filter_keys, child_filter_keys))
 	}
+
+	fn storage_changes_prefix_notification_stream(
+		&self,
+		prefixes: &[StorageKey],
+	) -> sp_blockchain::Result<StorageEventStream<Block::Hash>> {
+		Ok(self.storage_notifications.lock().listen_prefixes(prefixes))
+	}
 }
 
 impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
@@ -1925,6 +2229,43 @@ impl<B, E, Block, RA> BlockBackend<Block> for Client<B, E, Block, RA>
 	fn justification(&self, id: &BlockId<Block>) -> sp_blockchain::Result<Option<Justification>> {
 		self.backend.blockchain().justification(*id)
 	}
+
+	fn extrinsic_location(&self, hash: &Block::Hash) -> sp_blockchain::Result<Option<(Block::Hash, u32)>> {
+		self.backend.blockchain().extrinsic_location(hash)
+	}
+}
+
+impl<B, E, Block, RA> ForkBackend<Block> for Client<B, E, Block, RA>
+	where
+		B: backend::Backend<Block>,
+		E: CallExecutor<Block>,
+		Block: BlockT,
+{
+	fn leaves_info(&self) -> sp_blockchain::Result<Vec<LeafInfo<Block>>> {
+		let blockchain = self.backend.blockchain();
+		let info = blockchain.info();
+		blockchain.leaves()?.into_iter().map(|hash| {
+			let number = blockchain.header_metadata(hash)?.number;
+			let ancestor = sp_blockchain::lowest_common_ancestor(blockchain, hash, info.best_hash)?;
+			let finalized_ancestor = sp_blockchain::lowest_common_ancestor(blockchain, hash, info.finalized_hash)?;
+			Ok(LeafInfo {
+				hash,
+				number,
+				ancestor_hash: ancestor.hash,
+				ancestor_number: ancestor.number,
+				abandoned: finalized_ancestor.hash != info.finalized_hash,
+			})
+		}).collect()
+	}
+
+	fn prune_abandoned_forks(&self, below: NumberFor<Block>) -> sp_blockchain::Result<Vec<Block::Hash>> {
+		let _import_lock = self.backend.get_import_lock().write();
+		let removed = self.backend.prune_abandoned_forks(below)?;
+		if !removed.is_empty() {
+			info!("Pruned {} blocks of abandoned forks below #{}", removed.len(), below);
+		}
+		Ok(removed)
+	}
 }
 
 impl<B, E, Block, RA> backend::AuxStore for Client<B, E, Block, RA>
@@ -235,6 +235,10 @@ impl<S, Block> ClientBackend<Block> for Backend<S, HashFor<Block>>
 		Err(ClientError::NotAvailableOnLightClient)
 	}
 
+	fn prune_abandoned_forks(&self, _below: NumberFor<Block>) -> ClientResult<Vec<Block::Hash>> {
+		Err(ClientError::NotAvailableOnLightClient)
+	}
+
 	fn get_import_lock(&self) -> &RwLock<()> {
 		&self.import_lock
 	}
@@ -334,6 +338,11 @@ impl<S, Block> BlockImportOperation<Block> for ImportOperation<Block, S>
 		Ok(storage_root)
 	}
 
+	fn set_imported_state(&mut self, _storage: Storage) -> ClientResult<Block::Hash> {
+		// light clients never download the state
+		Err(ClientError::NotAvailableOnLightClient)
+	}
+
 	fn insert_aux<I>(&mut self, ops: I) -> ClientResult<()>
 		where I: IntoIterator<Item=(Vec<u8>, Option<Vec<u8>>)>
 	{
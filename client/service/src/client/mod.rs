@@ -51,6 +51,7 @@ mod client;
 mod block_rules;
 
 pub use self::{
+	block_rules::PinnedBlocks,
 	call_executor::LocalCallExecutor,
 	client::{Client, ClientConfig},
 };
@@ -18,18 +18,23 @@
 
 //! Service configuration.
 
-pub use sc_client_db::{Database, PruningMode, DatabaseSettingsSrc as DatabaseConfig};
+pub use sc_client_db::{
+	Database, DatabaseData, PruningMode, BlockPruning, DatabaseSettingsSrc as DatabaseConfig, purge_database,
+	migrate_database, MigrationProgress, ColdStorage, DeltaArchive, DEFAULT_CANONICALIZATION_DELAY,
+};
 pub use sc_network::Multiaddr;
 pub use sc_network::config::{ExtTransport, MultiaddrWithPeerId, NetworkConfiguration, Role, NodeKeyConfig};
 pub use sc_executor::WasmExecutionMethod;
+pub use sc_rpc_server::{RateLimit, RateLimitConfig, RpcMethodFilter, RpcMethodGroup, RpcMethodGroups};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 
-use std::{future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc};
+use std::{future::Future, path::{PathBuf, Path}, pin::Pin, net::SocketAddr, sync::Arc, time::Duration};
 pub use sc_transaction_pool::txpool::Options as TransactionPoolOptions;
 use sc_chain_spec::ChainSpec;
 use sp_core::crypto::Protected;
 pub use sc_telemetry::TelemetryEndpoints;
 use prometheus_endpoint::Registry;
+use crate::task_manager::SharedTaskManager;
 
 /// Service configuration.
 pub struct Configuration {
@@ -41,6 +46,10 @@ pub struct Configuration {
 	pub role: Role,
 	/// How to spawn background tasks. Mandatory, otherwise creating a `Service` will error.
 	pub task_executor: Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>, TaskType) + Send + Sync>,
+	/// Task manager shared with the services of other chains, set by [`SharedResources`].
+	///
+	/// [`SharedResources`]: ../struct.SharedResources.html
+	pub shared_task_manager: Option<SharedTaskManager>,
 	/// Extrinsic pool configuration.
 	pub transaction_pool: TransactionPoolOptions,
 	/// Network configuration.
@@ -53,10 +62,27 @@ pub struct Configuration {
 	pub state_cache_size: usize,
 	/// Size in percent of cache size dedicated to child tries
 	pub state_cache_child_ratio: Option<usize>,
+	/// Size of the cache of trie nodes shared by all the states, in bytes.
+	pub trie_cache_size: usize,
 	/// Pruning settings.
 	pub pruning: PruningMode,
+	/// Pruning of the bodies and justifications of the blocks.
+	pub block_pruning: BlockPruning,
+	/// Whether the extrinsics of the imported blocks are indexed by their hash.
+	pub index_extrinsics: bool,
+	/// Whether the imported blocks are indexed by the keys of the top storage they change.
+	pub index_storage_changes: bool,
+	/// Secondary database the bodies and justifications of the ancient blocks are moved to.
+	pub cold_storage: Option<ColdStorage>,
+	/// Archive of the states of the blocks as deltas against periodic snapshots.
+	pub delta_archive: Option<DeltaArchive>,
+	/// Number of blocks behind the best block from which the states are canonicalized.
+	pub canonicalization_delay: u64,
 	/// Chain configuration.
 	pub chain_spec: Box<dyn ChainSpec>,
+	/// SCALE-encoded block hashes pinned at given heights, in addition to the fork blocks of
+	/// the chain spec. Competing blocks at these heights are rejected on import.
+	pub pinned_blocks: Vec<(u64, Vec<u8>)>,
 	/// Wasm execution method.
 	pub wasm_method: WasmExecutionMethod,
 	/// Execution strategies.
@@ -65,12 +91,30 @@ pub struct Configuration {
 	pub rpc_http: Option<SocketAddr>,
 	/// RPC over Websockets binding address. `None` if disabled.
 	pub rpc_ws: Option<SocketAddr>,
+	/// RPC over IPC binding path. `None` if disabled.
+	pub rpc_ipc: Option<String>,
+	/// gRPC gateway binding address. `None` if disabled. Only used with the `grpc` feature.
+	pub rpc_grpc: Option<SocketAddr>,
 	/// Maximum number of connections for WebSockets RPC server. `None` if default.
 	pub rpc_ws_max_connections: Option<usize>,
+	/// Maximum number of subscriptions per connection for WebSockets RPC server. `None` if default.
+	pub rpc_ws_max_subscriptions_per_connection: Option<usize>,
+	/// Maximum size in bytes of the requests to the HTTP & WS servers. `None` if default.
+	pub rpc_max_request_size: Option<usize>,
+	/// Maximum size in bytes of the responses of the HTTP & WS servers. `None` if default.
+	pub rpc_max_response_size: Option<usize>,
+	/// Maximum number of calls in a batch request to the RPC servers. `None` if default.
+	pub rpc_max_batch_size: Option<usize>,
 	/// CORS settings for HTTP & WS servers. `None` if all origins are allowed.
 	pub rpc_cors: Option<Vec<String>>,
 	/// RPC methods to expose (by default only a safe subset or all of them).
 	pub rpc_methods: RpcMethods,
+	/// RPC methods to expose on specific servers, instead of `rpc_methods`.
+	pub rpc_interface_methods: RpcInterfaceMethods,
+	/// Rate limits of the calls made to the HTTP & WS servers.
+	pub rpc_rate_limit: RateLimitConfig,
+	/// RPC methods that can be called through the HTTP & WS servers, on top of `rpc_methods`.
+	pub rpc_method_filter: RpcMethodFilter,
 	/// Prometheus endpoint configuration. `None` if disabled.
 	pub prometheus_config: Option<PrometheusConfig>,
 	/// Telemetry service URL. `None` if disabled.
@@ -102,6 +146,10 @@ pub struct Configuration {
 	pub max_runtime_instances: usize,
 	/// Announce block automatically after they have been imported
 	pub announce_block: bool,
+	/// Notifications to systemd. `None` if disabled.
+	pub systemd_notify: Option<SystemdNotifyConfig>,
+	/// HTTP endpoint serving the health and the readiness of the node. `None` if disabled.
+	pub health: Option<HealthConfig>,
 }
 
 /// Type for tasks spawned by the executor.
@@ -141,7 +189,7 @@ impl KeystoreConfig {
 pub struct OffchainWorkerConfig {
 	/// If this is allowed.
 	pub enabled: bool,
-	/// allow writes from the runtime to the offchain worker database.
+	/// allow writes from the runtime to the offchain index of the database.
 	pub indexing_enabled: bool,
 }
 
@@ -152,6 +200,12 @@ pub struct PrometheusConfig {
 	pub port: SocketAddr,
 	/// A metrics registry to use. Useful for setting the metric prefix.
 	pub registry: Registry,
+	/// Whether the service serves the metrics of the registry on the port.
+	///
+	/// Disabled when the registry is served along with others, e.g. by [`SharedResources`].
+	///
+	/// [`SharedResources`]: ../struct.SharedResources.html
+	pub serve: bool,
 }
 
 impl PrometheusConfig {
@@ -162,11 +216,40 @@ impl PrometheusConfig {
 		Self {
 			port,
 			registry: Registry::new_custom(Some("substrate".into()), None)
-				.expect("this can only fail if the prefix is empty")
+				.expect("this can only fail if the prefix is empty"),
+			serve: true,
 		}
 	}
 }
 
+/// Configuration of the notifications to systemd.
+///
+/// The readiness of the node is notified once it's synced, and the watchdog is pet as long as the
+/// network runs, if enabled by the unit with `WatchdogSec=`.
+#[derive(Debug, Clone)]
+pub struct SystemdNotifyConfig {
+	/// Maximum number of blocks the node may be behind the best block seen on the network to be
+	/// considered synced.
+	pub ready_sync_threshold: u32,
+}
+
+/// Configuration of the HTTP endpoint serving the health and the readiness of the node.
+///
+/// `/health` responds with `200 OK` as long as the finality of the node progresses, and `/ready`
+/// once it's also synced and connected to peers, `503 Service Unavailable` otherwise. Both report
+/// the sync status, the number of peers and the age of the last finalized block as JSON.
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+	/// Address to listen on, separate from the RPC servers.
+	pub address: SocketAddr,
+	/// Maximum number of blocks the node may be behind the best block seen on the network to be
+	/// considered synced.
+	pub ready_sync_threshold: u32,
+	/// Maximum time since the last finalized block for the node to be considered healthy. `None`
+	/// if the finality isn't checked.
+	pub max_finality_lag: Option<Duration>,
+}
+
 impl Configuration {
 	/// Returns a string displaying the node role.
 	pub fn display_role(&self) -> String {
@@ -175,7 +258,7 @@ impl Configuration {
 }
 
 /// Available RPC methods.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RpcMethods {
 	/// Expose every RPC method only when RPC is listening on `localhost`,
 	/// otherwise serve only safe RPC methods.
@@ -184,6 +267,33 @@ pub enum RpcMethods {
 	Safe,
 	/// Expose every RPC method (even potentially unsafe ones).
 	Unsafe,
+	/// Expose the methods of the given groups.
+	Groups(RpcMethodGroups),
+}
+
+impl RpcMethods {
+	/// Returns the groups of methods exposed by a server listening on `addr`, or only accessible
+	/// locally if `addr` is `None`.
+	pub fn groups(&self, addr: Option<&SocketAddr>) -> RpcMethodGroups {
+		match self {
+			RpcMethods::Auto if addr.map_or(true, |addr| addr.ip().is_loopback()) => RpcMethodGroups::all(),
+			RpcMethods::Auto | RpcMethods::Safe => RpcMethodGroups::safe(),
+			RpcMethods::Unsafe => RpcMethodGroups::all(),
+			RpcMethods::Groups(groups) => *groups,
+		}
+	}
+}
+
+/// RPC methods exposed by each RPC server, overriding [`Configuration::rpc_methods`]. `None` if
+/// the server exposes the default methods.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct RpcInterfaceMethods {
+	/// Methods exposed by the HTTP server.
+	pub http: Option<RpcMethods>,
+	/// Methods exposed by the WebSockets server.
+	pub ws: Option<RpcMethods>,
+	/// Methods exposed by the IPC server.
+	pub ipc: Option<RpcMethods>,
 }
 
 impl Default for RpcMethods {
@@ -0,0 +1,221 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! HTTP endpoint serving the health and the readiness of the node, e.g. to load balancers and the
+//! probes of Kubernetes, without exposing the RPC servers.
+
+use std::{sync::Arc, time::Duration};
+use parking_lot::RwLock;
+use wasm_timer::Instant;
+use crate::config::HealthConfig;
+
+/// Status of the node, as served by the endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HealthStatus {
+	/// Whether the node is behind the best block seen on the network.
+	pub is_syncing: bool,
+	/// Number of connected peers.
+	pub peers: usize,
+	/// Number of the best block.
+	pub best_number: u64,
+	/// Number of the last finalized block.
+	pub finalized_number: u64,
+	/// Time since the last finalized block has been finalized.
+	pub last_finalized_age: Duration,
+	/// Whether the finality of the node progresses.
+	pub is_healthy: bool,
+	/// Whether the node is healthy, synced and connected to peers.
+	pub is_ready: bool,
+}
+
+impl HealthStatus {
+	/// Returns the JSON representation of the status.
+	fn to_json(&self) -> serde_json::Value {
+		serde_json::json!({
+			"isSyncing": self.is_syncing,
+			"peers": self.peers,
+			"bestNumber": self.best_number,
+			"finalizedNumber": self.finalized_number,
+			"lastFinalizedAge": self.last_finalized_age.as_secs(),
+			"isHealthy": self.is_healthy,
+			"isReady": self.is_ready,
+		})
+	}
+}
+
+/// Status of the node shared with the endpoint, `None` until the network reports its first status.
+pub(crate) type SharedHealthStatus = Arc<RwLock<Option<HealthStatus>>>;
+
+/// Computes the status of the node from the updates of the network status.
+pub(crate) struct HealthTracker {
+	config: HealthConfig,
+	should_have_peers: bool,
+	/// Number of the last finalized block, and when it was first seen.
+	finalized: Option<(u64, Instant)>,
+}
+
+impl HealthTracker {
+	/// Creates a tracker of a node which is expected to be connected to peers if
+	/// `should_have_peers`.
+	pub fn new(config: HealthConfig, should_have_peers: bool) -> Self {
+		HealthTracker {
+			config,
+			should_have_peers,
+			finalized: None,
+		}
+	}
+
+	/// Returns the status of the node at `now`.
+	pub fn update(
+		&mut self,
+		best_number: u64,
+		best_seen_number: Option<u64>,
+		finalized_number: u64,
+		peers: usize,
+		now: Instant,
+	) -> HealthStatus {
+		let finalized_at = match self.finalized {
+			Some((number, at)) if number == finalized_number => at,
+			_ => {
+				self.finalized = Some((finalized_number, now));
+				now
+			},
+		};
+		let last_finalized_age = now.duration_since(finalized_at);
+
+		let is_syncing = best_seen_number.map_or(false, |best_seen| {
+			best_number.saturating_add(self.config.ready_sync_threshold.into()) < best_seen
+		});
+		let is_healthy = self.config.max_finality_lag.map_or(true, |max| last_finalized_age <= max);
+		let is_ready = is_healthy && !is_syncing && (peers > 0 || !self.should_have_peers);
+
+		HealthStatus {
+			is_syncing,
+			peers,
+			best_number,
+			finalized_number,
+			last_finalized_age,
+			is_healthy,
+			is_ready,
+		}
+	}
+}
+
+/// Returns the HTTP status code and the body of the response to a request of `path`.
+fn respond(path: &str, status: &Option<HealthStatus>) -> (u16, String) {
+	let is_ok = match (path, status) {
+		("/health", Some(status)) => status.is_healthy,
+		("/ready", Some(status)) => status.is_ready,
+		("/health", None) | ("/ready", None) => false,
+		_ => return (404, "Not found.".into()),
+	};
+	let body = match status {
+		Some(status) => status.to_json().to_string(),
+		None => serde_json::json!({ "isHealthy": false, "isReady": false }).to_string(),
+	};
+	(if is_ok { 200 } else { 503 }, body)
+}
+
+#[cfg(not(target_os = "unknown"))]
+pub(crate) use known_os::serve;
+#[cfg(target_os = "unknown")]
+pub(crate) use unknown_os::serve;
+
+// On WASM `serve` becomes a no-op.
+#[cfg(target_os = "unknown")]
+mod unknown_os {
+	use super::SharedHealthStatus;
+	use std::net::SocketAddr;
+
+	pub async fn serve(_: SocketAddr, _: SharedHealthStatus) -> Result<(), String> {
+		Ok(())
+	}
+}
+
+#[cfg(not(target_os = "unknown"))]
+mod known_os {
+	use super::{respond, SharedHealthStatus};
+	use hyper::{Body, Request, Response, Server, service::{make_service_fn, service_fn}};
+	use log::info;
+	use std::{convert::Infallible, net::SocketAddr};
+
+	/// Serves the status of the node on `address` until the returned future is dropped.
+	pub async fn serve(address: SocketAddr, status: SharedHealthStatus) -> Result<(), String> {
+		let service = make_service_fn(move |_| {
+			let status = status.clone();
+			async move {
+				Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+					let (code, body) = respond(request.uri().path(), &status.read());
+					async move {
+						Response::builder()
+							.status(code)
+							.header("Content-Type", "application/json")
+							.body(Body::from(body))
+					}
+				}))
+			}
+		});
+
+		let server = Server::try_bind(&address)
+			.map_err(|e| format!("Failed to listen on {}: {}", address, e))?
+			.serve(service);
+		info!("🩺 Health endpoint started at {}", address);
+		server.await.map_err(|e| format!("Health endpoint error: {}", e))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn tracker(should_have_peers: bool) -> HealthTracker {
+		HealthTracker::new(HealthConfig {
+			address: ([127, 0, 0, 1], 0).into(),
+			ready_sync_threshold: 10,
+			max_finality_lag: Some(Duration::from_secs(60)),
+		}, should_have_peers)
+	}
+
+	#[test]
+	fn readiness_follows_sync_peers_and_finality() {
+		let start = Instant::now();
+		let mut tracker = tracker(true);
+
+		let status = tracker.update(5, Some(100), 3, 4, start);
+		assert!(status.is_syncing && status.is_healthy && !status.is_ready);
+		assert_eq!(respond("/ready", &Some(status.clone())).0, 503);
+		assert_eq!(respond("/health", &Some(status)).0, 200);
+
+		let status = tracker.update(95, Some(100), 3, 4, start + Duration::from_secs(30));
+		assert!(status.is_ready);
+		assert_eq!(status.last_finalized_age, Duration::from_secs(30));
+		assert!(!tracker.update(95, Some(100), 3, 0, start + Duration::from_secs(30)).is_ready);
+
+		let status = tracker.update(100, Some(100), 3, 4, start + Duration::from_secs(61));
+		assert!(!status.is_healthy && !status.is_ready);
+		assert_eq!(respond("/health", &Some(status)).0, 503);
+
+		let status = tracker.update(101, Some(100), 98, 4, start + Duration::from_secs(62));
+		assert!(status.is_ready);
+		assert_eq!(status.last_finalized_age, Duration::from_secs(0));
+
+		assert!(tracker(false).update(0, None, 0, 0, start).is_ready);
+		assert_eq!(respond("/ready", &None).0, 503);
+		assert_eq!(respond("/metrics", &None).0, 404);
+	}
+}
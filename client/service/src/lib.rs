@@ -29,12 +29,15 @@ pub mod error;
 
 mod metrics;
 mod builder;
+mod health;
 #[cfg(feature = "test-helpers")]
 pub mod client;
 #[cfg(not(feature = "test-helpers"))]
 mod client;
 mod status_sinks;
+mod shared_resources;
 mod task_manager;
+mod systemd;
 
 use std::{io, pin::Pin};
 use std::marker::PhantomData;
@@ -49,11 +52,10 @@ use client::Client;
 use futures::{
 	Future, FutureExt, Stream, StreamExt,
 	compat::*,
-	sink::SinkExt,
 	task::{Spawn, FutureObj, SpawnError},
 };
 use sc_network::{NetworkService, network_state::NetworkState, PeerId};
-use log::{log, warn, debug, error, Level};
+use log::{log, info, warn, debug, Level};
 use codec::{Encode, Decode};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{NumberFor, Block as BlockT};
@@ -62,11 +64,14 @@ use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver,  TracingUnboun
 
 pub use self::error::Error;
 pub use self::builder::{
-	new_full_client, new_client,
+	new_full_client, new_client, database_settings,
 	ServiceBuilder, ServiceBuilderCommand, TFullClient, TLightClient, TFullBackend, TLightBackend,
 	TFullCallExecutor, TLightCallExecutor, RpcExtensionBuilder,
 };
-pub use config::{Configuration, DatabaseConfig, PruningMode, Role, RpcMethods, TaskType};
+pub use config::{
+	BlockPruning, Configuration, DatabaseConfig, PruningMode, Role, RpcInterfaceMethods, RpcMethodGroup, RpcMethodGroups,
+	RpcMethods, TaskType,
+};
 pub use sc_chain_spec::{
 	ChainSpec, GenericChainSpec, Properties, RuntimeGenesis, Extension as ChainSpecExtension,
 	NoExtension, ChainType,
@@ -83,7 +88,9 @@ pub use sc_network::config::{
 	TransactionImportFuture,
 };
 pub use sc_tracing::TracingReceiver;
-pub use task_manager::SpawnTaskHandle;
+pub use client::PinnedBlocks;
+pub use shared_resources::SharedResources;
+pub use task_manager::{SpawnTaskHandle, RestartPolicy, SharedTaskManager, ShutdownHooks, ShutdownStage};
 use task_manager::TaskManager;
 use sp_blockchain::{HeaderBackend, HeaderMetadata};
 use sp_api::{ApiExt, ConstructRuntimeApi, ApiErrorExt};
@@ -171,9 +178,25 @@ pub trait AbstractService: Future<Output = Result<(), Error>> + Send + Unpin + S
 	/// trigger a service exit.
 	fn spawn_essential_task(&self, name: &'static str, task: impl Future<Output = ()> + Send + 'static);
 
+	/// Spawns an essential task in the background, built by the closure passed as parameter.
+	///
+	/// When the task ends or panics, it is built again and restarted after a delay according to
+	/// the `policy`, and a telemetry event is sent. Once the policy allows no more restarts, we
+	/// trigger a service exit.
+	fn spawn_restartable_essential_task<F, Fut>(&self, name: &'static str, policy: RestartPolicy, task: F)
+	where
+		F: FnMut() -> Fut + Send + 'static,
+		Fut: Future<Output = ()> + Send + 'static;
+
 	/// Returns a handle for spawning tasks.
 	fn spawn_task_handle(&self) -> SpawnTaskHandle;
 
+	/// Returns a handle for registering the hooks run when the service shuts down.
+	///
+	/// The hooks run before the tasks of the service are stopped, once the owner of the service
+	/// awaits `ShutdownHooks::run`, e.g. on `SIGTERM` or after an essential task failed.
+	fn shutdown_hooks(&self) -> ShutdownHooks;
+
 	/// Returns the keystore that stores keys.
 	fn keystore(&self) -> sc_keystore::KeyStorePtr;
 
@@ -256,21 +279,34 @@ where
 	}
 
 	fn spawn_essential_task(&self, name: &'static str, task: impl Future<Output = ()> + Send + 'static) {
-		let mut essential_failed = self.essential_failed_tx.clone();
-		let essential_task = std::panic::AssertUnwindSafe(task)
-			.catch_unwind()
-			.map(move |_| {
-				error!("Essential task `{}` failed. Shutting down service.", name);
-				let _ = essential_failed.send(());
-			});
+		let mut task = Some(task);
+		self.spawn_restartable_essential_task(name, RestartPolicy::never(), move || {
+			task.take().expect("the task is never restarted; qed")
+		});
+	}
 
-		let _ = self.spawn_task(name, essential_task);
+	fn spawn_restartable_essential_task<F, Fut>(&self, name: &'static str, policy: RestartPolicy, task: F)
+	where
+		F: FnMut() -> Fut + Send + 'static,
+		Fut: Future<Output = ()> + Send + 'static,
+	{
+		let essential_task = task_manager::restartable_essential_task(
+			name,
+			policy,
+			task,
+			self.essential_failed_tx.clone(),
+		);
+		self.spawn_task(name, essential_task);
 	}
 
 	fn spawn_task_handle(&self) -> SpawnTaskHandle {
 		self.task_manager.spawn_handle()
 	}
 
+	fn shutdown_hooks(&self) -> ShutdownHooks {
+		self.task_manager.shutdown_hooks()
+	}
+
 	fn rpc_query(&self, mem: &RpcSession, request: &str) -> Pin<Box<dyn Future<Output = Option<String>> + Send>> {
 		Box::pin(
 			self.rpc_handlers.handle_request(request, mem.metadata.clone())
@@ -351,7 +387,7 @@ impl<TBl, TCl, TSc, TNetStatus, TNet, TTxPool, TOc> Spawn for
 /// The `status_sink` contain a list of senders to send a periodic network status to.
 fn build_network_future<
 	B: BlockT,
-	C: BlockchainEvents<B>,
+	C: BlockchainEvents<B> + HeaderBackend<B> + UsageProvider<B>,
 	H: sc_network::ExHashT
 > (
 	role: Role,
@@ -359,11 +395,13 @@ fn build_network_future<
 	client: Arc<C>,
 	status_sinks: Arc<Mutex<status_sinks::StatusSinks<(NetworkStatus<B>, NetworkState)>>>,
 	mut rpc_rx: TracingUnboundedReceiver<sc_rpc::system::Request<B>>,
+	pinned_blocks: PinnedBlocks<B>,
 	should_have_peers: bool,
 	announce_imported_blocks: bool,
 ) -> impl Future<Output = ()> {
 	let mut imported_blocks_stream = client.import_notification_stream().fuse();
 	let mut finality_notification_stream = client.finality_notification_stream().fuse();
+	let starting_block = client.info().best_number;
 
 	futures::future::poll_fn(move |cx| {
 		let before_polling = Instant::now();
@@ -406,13 +444,25 @@ fn build_network_future<
 					let _ = sender.send(addresses);
 				},
 				sc_rpc::system::Request::Peers(sender) => {
-					let _ = sender.send(network.peers_debug_info().into_iter().map(|(peer_id, p)|
+					let _ = sender.send(network.peers_details().into_iter().map(|(peer_id, p)|
 						sc_rpc::system::PeerInfo {
 							peer_id: peer_id.to_base58(),
-							roles: format!("{:?}", p.roles),
-							protocol_version: p.protocol_version,
-							best_hash: p.best_hash,
-							best_number: p.best_number,
+							roles: format!("{:?}", p.info.roles),
+							protocol_version: p.info.protocol_version,
+							best_hash: p.info.best_hash,
+							best_number: p.info.best_number,
+							finalized_hash: p.finalized.map(|(_, hash)| hash),
+							finalized_number: p.finalized.map(|(number, _)| number),
+							notification_protocols: p.notifications_protocols.iter()
+								.map(|protocol| String::from_utf8_lossy(protocol).into_owned())
+								.collect(),
+							requests: sc_rpc::system::PeerRequestStats {
+								sent: p.requests.sent,
+								answered: p.requests.answered,
+								timed_out: p.requests.timed_out,
+								average_latency_ms: p.requests.average_latency
+									.map(|latency| latency.as_millis() as u64),
+							},
 						}
 					).collect());
 				}
@@ -437,6 +487,45 @@ fn build_network_future<
 						))),
 					};
 				}
+				sc_rpc::system::Request::NetworkIpFilter(sender) => {
+					let filter = network.service().ip_filter();
+					let _ = sender.send(sc_rpc::system::IpFilter {
+						allow: filter.allow
+							.map(|ranges| ranges.iter().map(|r| r.to_string()).collect()),
+						deny: filter.deny.iter().map(|r| r.to_string()).collect(),
+					});
+				}
+				sc_rpc::system::Request::NetworkSetIpFilter(filter, sender) => {
+					let parse = |ranges: Vec<String>| ranges.into_iter()
+						.map(|r| r.parse::<sc_network::config::IpNetwork>()
+							.map_err(|e| sc_rpc::system::error::Error::MalformattedIpRange(
+								format!("{}: {}", r, e)
+							))
+						)
+						.collect::<Result<Vec<_>, _>>();
+
+					let filter = match (filter.allow.map(parse).transpose(), parse(filter.deny)) {
+						(Ok(allow), Ok(deny)) => Ok(sc_network::config::IpFilter { allow, deny }),
+						(Err(e), _) | (_, Err(e)) => Err(e),
+					};
+					let _ = sender.send(filter.map(|f| network.service().set_ip_filter(f)));
+				}
+				sc_rpc::system::Request::NetworkTrafficDump(sender) => {
+					let dump = network.service().traffic_dump();
+					let _ = sender.send(dump.map(|config| sc_rpc::system::TrafficDump {
+						path: config.path.display().to_string(),
+						include_payloads: config.include_payloads,
+					}));
+				}
+				sc_rpc::system::Request::NetworkSetTrafficDump(dump, sender) => {
+					let config = dump.map(|dump| sc_network::config::TrafficDumpConfig {
+						include_payloads: dump.include_payloads,
+						..sc_network::config::TrafficDumpConfig::new(dump.path.into())
+					});
+					let result = network.service().set_traffic_dump(config)
+						.map_err(|e| sc_rpc::system::error::Error::TrafficDumpFailed(e.to_string()));
+					let _ = sender.send(result);
+				}
 				sc_rpc::system::Request::NodeRoles(sender) => {
 					use sc_rpc::system::NodeRole;
 
@@ -449,6 +538,45 @@ fn build_network_future<
 
 					let _ = sender.send(vec![node_role]);
 				}
+				sc_rpc::system::Request::PinnedBlocks(sender) => {
+					let _ = sender.send(pinned_blocks.list());
+				}
+				sc_rpc::system::Request::PinBlock(number, hash, sender) => {
+					warn!("📌 Pinning block #{} to {:?}, competing blocks will be rejected", number, hash);
+					pinned_blocks.pin(number, hash);
+					let _ = sender.send(());
+				}
+				sc_rpc::system::Request::UnpinBlock(number, sender) => {
+					let unpinned = pinned_blocks.unpin(number);
+					if unpinned {
+						info!("Unpinned block #{}", number);
+					}
+					let _ = sender.send(unpinned);
+				}
+				sc_rpc::system::Request::SyncState(sender) => {
+					let _ = sender.send(sc_rpc::system::SyncState {
+						starting_block,
+						current_block: client.info().best_number,
+						highest_block: network.best_seen_block(),
+						gap_sync: network.gap_sync_progress().map(|gap| sc_rpc::system::GapSync {
+							start: gap.start,
+							current: gap.current,
+							end: gap.end,
+						}),
+					});
+				}
+				sc_rpc::system::Request::PruningStatus(sender) => {
+					let status = client.usage_info().usage
+						.and_then(|usage| usage.pruning)
+						.map(|pruning| sc_rpc::system::PruningStatus {
+							window: pruning.window,
+							oldest_block: pruning.oldest_block,
+							last_canonicalized: pruning.last_canonicalized,
+							non_canonical: pruning.non_canonical,
+							pending_prunes: pruning.pending_prunes,
+						});
+					let _ = sender.send(status);
+				}
 			};
 		}
 
@@ -457,6 +585,7 @@ fn build_network_future<
 			let status = NetworkStatus {
 				sync_state: network.sync_state(),
 				best_seen_block: network.best_seen_block(),
+				gap_sync: network.gap_sync_progress(),
 				num_sync_peers: network.num_sync_peers(),
 				num_connected_peers: network.num_connected_peers(),
 				num_active_peers: network.num_active_peers(),
@@ -494,6 +623,8 @@ pub struct NetworkStatus<B: BlockT> {
 	pub sync_state: sc_network::SyncState,
 	/// Target sync block number.
 	pub best_seen_block: Option<NumberFor<B>>,
+	/// Progress of the download of the bodies of old blocks, if any.
+	pub gap_sync: Option<sc_network::GapSyncProgress<NumberFor<B>>>,
 	/// Number of peers participating in syncing.
 	pub num_sync_peers: u32,
 	/// Total number of connected peers
@@ -507,7 +638,7 @@ pub struct NetworkStatus<B: BlockT> {
 }
 
 #[cfg(not(target_os = "unknown"))]
-// Wrapper for HTTP and WS servers that makes sure they are properly shut down.
+// Wrapper for HTTP, WS and IPC servers that makes sure they are properly shut down.
 mod waiting {
 	pub struct HttpServer(pub Option<sc_rpc_server::HttpServer>);
 	impl Drop for HttpServer {
@@ -528,12 +659,28 @@ mod waiting {
 			}
 		}
 	}
+
+	pub struct IpcServer(pub Option<sc_rpc_server::IpcServer>);
+	impl Drop for IpcServer {
+		fn drop(&mut self) {
+			if let Some(server) = self.0.take() {
+				server.close_handle().close();
+				let _ = server.wait();
+			}
+		}
+	}
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(not(target_os = "unknown"))]
-fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
+fn start_rpc_servers<
+	H: FnMut(
+		sc_rpc::DenyUnsafe,
+		sc_rpc_server::RpcMiddleware<sc_rpc::Metadata>,
+	) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>
+>(
 	config: &Configuration,
+	custom_middleware: Option<Arc<dyn sc_rpc_server::RpcCallMiddleware<sc_rpc::Metadata>>>,
 	mut gen_handler: H
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
 	fn maybe_start_server<T, F>(address: Option<SocketAddr>, mut start: F) -> Result<Option<T>, io::Error>
@@ -554,22 +701,87 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 		})
 	}
 
-	fn deny_unsafe(addr: &SocketAddr, methods: &RpcMethods) -> sc_rpc::DenyUnsafe {
-		let is_exposed_addr = !addr.ip().is_loopback();
-		match (is_exposed_addr, methods) {
-			| (_, RpcMethods::Unsafe)
-			| (false, RpcMethods::Auto) => sc_rpc::DenyUnsafe::No,
-			_ => sc_rpc::DenyUnsafe::Yes
+	#[cfg(feature = "grpc")]
+	fn start_grpc<F>(
+		address: Option<SocketAddr>,
+		mut gen_handler: F,
+	) -> Result<Option<sc_rpc_server::GrpcServer>, io::Error> where
+		F: FnMut(&SocketAddr) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+	{
+		maybe_start_server(address, |address| sc_rpc_server::start_grpc(address, gen_handler(address)))
+	}
+
+	#[cfg(not(feature = "grpc"))]
+	fn start_grpc<F>(address: Option<SocketAddr>, _: F) -> Result<Option<()>, io::Error> where
+		F: FnMut(&SocketAddr) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>,
+	{
+		if address.is_some() {
+			warn!("Not starting the gRPC gateway: the node was built without the `grpc` feature.");
 		}
+		Ok(None)
 	}
 
+	// The groups a server doesn't expose are filtered out by its middleware, so the handlers only
+	// have to deny the unsafe calls if neither of the unsafe groups is exposed.
+	fn deny_unsafe(groups: RpcMethodGroups) -> sc_rpc::DenyUnsafe {
+		if groups.contains(RpcMethodGroup::Admin) || groups.contains(RpcMethodGroup::Debug) {
+			sc_rpc::DenyUnsafe::No
+		} else {
+			sc_rpc::DenyUnsafe::Yes
+		}
+	}
+
+	let metrics = config.prometheus_config.as_ref().and_then(|config|
+		sc_rpc_server::ServerMetrics::register(&config.registry)
+			.map_err(|err| warn!("Failed to register RPC servers metrics: {}", err))
+			.ok()
+	);
+	let resource_limits = sc_rpc_server::ResourceLimits {
+		max_response_size: Some(config.rpc_max_response_size.unwrap_or(sc_rpc_server::MAX_RESPONSE_SIZE)),
+		max_subscriptions_per_connection: Some(config.rpc_ws_max_subscriptions_per_connection
+			.unwrap_or(sc_rpc_server::WS_MAX_SUBSCRIPTIONS_PER_CONNECTION)),
+	};
+
+	// The calls of the batches of every server are executed by the same threads.
+	let batch_executor = sc_rpc_server::BatchExecutor::new(
+		Some(config.rpc_max_batch_size.unwrap_or(sc_rpc_server::MAX_BATCH_SIZE)),
+		sc_rpc_server::BATCH_THREADS,
+	)?;
+
+	let method_groups = Arc::new(sc_rpc_server::MethodGroups::new(&[
+		(RpcMethodGroup::Author, sc_rpc::AUTHOR_METHODS),
+		(RpcMethodGroup::Admin, sc_rpc::UNSAFE_METHODS),
+		(RpcMethodGroup::Debug, sc_rpc::DEBUG_METHODS),
+	]));
+	let interface_methods = &config.rpc_interface_methods;
+	let http_methods = interface_methods.http.unwrap_or(config.rpc_methods);
+	let ws_methods = interface_methods.ws.unwrap_or(config.rpc_methods);
+	let ipc_methods = interface_methods.ipc.unwrap_or(config.rpc_methods);
+
+	// Every server gets limiters of its own.
+	let middleware = |transport, groups| (
+		sc_rpc_server::CallMetrics::new(metrics.clone(), transport),
+		sc_rpc_server::MethodFilter::new(config.rpc_method_filter.clone())
+			.with_groups(groups, method_groups.clone()),
+		sc_rpc_server::RateLimiter::new(config.rpc_rate_limit.clone(), sc_rpc::UNSAFE_METHODS),
+		(
+			sc_rpc_server::ResourceLimiter::new(resource_limits, metrics.clone(), transport),
+			sc_rpc_server::CustomMiddleware::new(custom_middleware.clone()),
+			batch_executor.clone(),
+		),
+	);
+
 	Ok(Box::new((
 		maybe_start_server(
 			config.rpc_http,
 			|address| sc_rpc_server::start_http(
 				address,
+				config.rpc_max_request_size,
 				config.rpc_cors.as_ref(),
-				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
+				{
+					let groups = http_methods.groups(Some(address));
+					gen_handler(deny_unsafe(groups), middleware(sc_rpc_server::Transport::Http, groups))
+				},
 			),
 		)?.map(|s| waiting::HttpServer(Some(s))),
 		maybe_start_server(
@@ -577,17 +789,41 @@ fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<s
 			|address| sc_rpc_server::start_ws(
 				address,
 				config.rpc_ws_max_connections,
+				config.rpc_max_request_size,
 				config.rpc_cors.as_ref(),
-				gen_handler(deny_unsafe(&address, &config.rpc_methods)),
+				{
+					let groups = ws_methods.groups(Some(address));
+					gen_handler(deny_unsafe(groups), middleware(sc_rpc_server::Transport::Ws, groups))
+				},
+				metrics.clone(),
 			),
 		)?.map(|s| waiting::WsServer(Some(s))),
+		config.rpc_ipc.as_ref().map(|path| sc_rpc_server::start_ipc(
+			path,
+			{
+				// The socket is only accessible locally.
+				let groups = ipc_methods.groups(None);
+				gen_handler(deny_unsafe(groups), middleware(sc_rpc_server::Transport::Ipc, groups))
+			},
+		)).transpose()?.map(|s| waiting::IpcServer(Some(s))),
+		start_grpc(config.rpc_grpc, |address| {
+			// The gateway exposes the methods of the HTTP server.
+			let groups = http_methods.groups(Some(address));
+			gen_handler(deny_unsafe(groups), middleware(sc_rpc_server::Transport::Grpc, groups))
+		})?,
 	)))
 }
 
 /// Starts RPC servers that run in their own thread, and returns an opaque object that keeps them alive.
 #[cfg(target_os = "unknown")]
-fn start_rpc_servers<H: FnMut(sc_rpc::DenyUnsafe) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>>(
+fn start_rpc_servers<
+	H: FnMut(
+		sc_rpc::DenyUnsafe,
+		sc_rpc_server::RpcMiddleware<sc_rpc::Metadata>,
+	) -> sc_rpc_server::RpcHandler<sc_rpc::Metadata>
+>(
 	_: &Configuration,
+	_: Option<Arc<dyn sc_rpc_server::RpcCallMiddleware<sc_rpc::Metadata>>>,
 	_: H
 ) -> Result<Box<dyn std::any::Any + Send + Sync>, error::Error> {
 	Ok(Box::new(()))
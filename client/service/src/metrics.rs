@@ -19,7 +19,7 @@
 use std::convert::TryFrom;
 
 use crate::NetworkStatus;
-use prometheus_endpoint::{register, Gauge, U64, F64, Registry, PrometheusError, Opts, GaugeVec};
+use prometheus_endpoint::{register, Counter, CounterVec, Gauge, U64, F64, Registry, PrometheusError, Opts, GaugeVec};
 use sc_telemetry::{telemetry, SUBSTRATE_INFO};
 use sp_runtime::traits::{NumberFor, Block, SaturatedConversion, UniqueSaturatedInto};
 use sp_transaction_pool::PoolStatus;
@@ -58,6 +58,14 @@ struct PrometheusMetrics {
 	database_cache: Gauge<U64>,
 	state_cache: Gauge<U64>,
 	state_db: GaugeVec<U64>,
+	state_db_non_canonical_blocks: Gauge<U64>,
+	trie_cache: Gauge<U64>,
+	trie_cache_reads: GaugeVec<U64>,
+	cache_hit_ratio: GaugeVec<F64>,
+	database_reads: CounterVec<U64>,
+	database_read_bytes: CounterVec<U64>,
+	database_writes: CounterVec<U64>,
+	database_written_bytes: CounterVec<U64>,
 }
 
 impl PrometheusMetrics {
@@ -142,10 +150,45 @@ impl PrometheusMetrics {
 				Opts::new("state_db_cache_bytes", "State DB cache in bytes"),
 				&["subtype"]
 			)?, registry)?,
+			state_db_non_canonical_blocks: register(Gauge::new(
+				"state_db_non_canonical_blocks", "Number of blocks of the non-canonical overlay of the state DB",
+			)?, registry)?,
+			trie_cache: register(Gauge::new(
+				"trie_cache_bytes", "Trie node cache size in bytes",
+			)?, registry)?,
+			trie_cache_reads: register(GaugeVec::new(
+				Opts::new("trie_cache_reads", "Trie nodes read since the start, by whether they were cached"),
+				&["result"]
+			)?, registry)?,
+			cache_hit_ratio: register(GaugeVec::new(
+				Opts::new("cache_hit_ratio", "Ratio of the reads served by the cache"),
+				&["cache"]
+			)?, registry)?,
+			database_reads: register(CounterVec::new(
+				Opts::new("database_reads_total", "Values read from the database since the start, by column"),
+				&["column"]
+			)?, registry)?,
+			database_read_bytes: register(CounterVec::new(
+				Opts::new("database_read_bytes_total", "Bytes read from the database since the start, by column"),
+				&["column"]
+			)?, registry)?,
+			database_writes: register(CounterVec::new(
+				Opts::new("database_writes_total", "Values written to or removed from the database since the start, by column"),
+				&["column"]
+			)?, registry)?,
+			database_written_bytes: register(CounterVec::new(
+				Opts::new("database_written_bytes_total", "Bytes written to the database since the start, by column"),
+				&["column"]
+			)?, registry)?,
 		})
 	}
 }
 
+/// Increases `counter` up to `total`, counted since the start.
+fn increase_to(counter: &Counter<U64>, total: u64) {
+	counter.inc_by(total.saturating_sub(counter.get()));
+}
+
 #[cfg(all(any(unix, windows), not(target_os = "android")))]
 #[derive(Default)]
 struct ConnectionsCount {
@@ -406,6 +449,37 @@ impl MetricsService {
 				metrics.state_db.with_label_values(&["pinned"]).set(
 					info.memory.state_db.pinned.as_bytes() as u64,
 				);
+				if let Some(pruning) = info.pruning.as_ref() {
+					metrics.state_db_non_canonical_blocks.set(pruning.non_canonical);
+				}
+
+				if let Some(trie_cache) = info.trie_cache.as_ref() {
+					metrics.trie_cache.set(trie_cache.size.as_bytes() as u64);
+					metrics.trie_cache_reads.with_label_values(&["hit"]).set(trie_cache.hits);
+					metrics.trie_cache_reads.with_label_values(&["miss"]).set(trie_cache.misses);
+					let reads = trie_cache.hits + trie_cache.misses;
+					if reads > 0 {
+						metrics.cache_hit_ratio.with_label_values(&["trie"])
+							.set(trie_cache.hits as f64 / reads as f64);
+					}
+				}
+				// the state reads are the ones since the previous collection of the usage.
+				if info.io.state_reads > 0 {
+					metrics.cache_hit_ratio.with_label_values(&["state"])
+						.set(info.io.state_reads_cache as f64 / info.io.state_reads as f64);
+				}
+
+				for column in &info.database_columns {
+					let counters = [
+						(&metrics.database_reads, column.reads),
+						(&metrics.database_read_bytes, column.bytes_read),
+						(&metrics.database_writes, column.writes),
+						(&metrics.database_written_bytes, column.bytes_written),
+					];
+					for (counter, total) in counters.iter() {
+						increase_to(&counter.with_label_values(&[column.name]), *total);
+					}
+				}
 			}
 
 			#[cfg(all(any(unix, windows), not(target_os = "android")))]
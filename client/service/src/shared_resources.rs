@@ -0,0 +1,163 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Resources shared by the services of several chains running in the same process.
+
+use std::net::SocketAddr;
+use futures::FutureExt;
+use prometheus_endpoint::{register_registry, Registry};
+use crate::{
+	config::{Configuration, PrometheusConfig},
+	error::Error,
+	task_manager::{ServiceTaskExecutor, SharedTaskManager, TaskManager},
+};
+
+/// Resources shared by the services of several chains running in the same process, e.g. a relay
+/// chain and a parachain.
+///
+/// The services share a task manager, whose executor runs their tasks and whose shutdown hooks
+/// include theirs, and their metrics are served on the same Prometheus endpoint, prefixed with
+/// `substrate` and labelled with the name of their chain. The other settings of the services,
+/// e.g. their database and network, are configured independently.
+///
+/// The tasks of all the services are stopped when the `SharedResources` are dropped.
+pub struct SharedResources {
+	task_manager: TaskManager,
+	registry: Option<Registry>,
+}
+
+impl SharedResources {
+	/// Creates the resources spawning the tasks with `task_executor`, and serving the metrics of the
+	/// services on `prometheus_port`, if any.
+	pub fn new(task_executor: ServiceTaskExecutor, prometheus_port: Option<SocketAddr>) -> Result<Self, Error> {
+		let task_manager = TaskManager::new(task_executor, None)?;
+		let registry = prometheus_port.map(|port| {
+			let registry = Registry::new();
+			let endpoint = prometheus_endpoint::init_prometheus(port, registry.clone()).map(drop);
+			task_manager.spawn("prometheus-endpoint", endpoint);
+			registry
+		});
+
+		Ok(SharedResources {
+			task_manager,
+			registry,
+		})
+	}
+
+	/// Sets up the configuration of the service of the chain named `chain` to use the resources.
+	///
+	/// The names of the chains sharing the resources must be distinct.
+	pub fn configure(&self, config: &mut Configuration, chain: &str) -> Result<(), Error> {
+		config.shared_task_manager = Some(self.task_manager());
+		config.prometheus_config = self.chain_registry(chain)?.map(|registry| PrometheusConfig {
+			// the port is only used to serve the metrics, which is done by the parent registry.
+			port: ([127, 0, 0, 1], 0).into(),
+			registry,
+			serve: false,
+		});
+		Ok(())
+	}
+
+	/// Runs the shutdown hooks of all the services, by stage.
+	pub fn run_shutdown_hooks(&self) -> impl futures::Future<Output = ()> {
+		self.task_manager.shutdown_hooks().run()
+	}
+
+	fn task_manager(&self) -> SharedTaskManager {
+		self.task_manager.share()
+	}
+
+	// returns the registry of the metrics of the chain `chain`, registered in the shared one.
+	fn chain_registry(&self, chain: &str) -> Result<Option<Registry>, Error> {
+		match &self.registry {
+			Some(parent) => {
+				let labels = std::iter::once(("chain".to_string(), chain.to_string())).collect();
+				let registry = Registry::new_custom(Some("substrate".into()), Some(labels))?;
+				register_registry(chain, registry.clone(), parent)?;
+				Ok(Some(registry))
+			},
+			None => Ok(None),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+	use futures::{channel::oneshot, executor::block_on, future};
+	use parking_lot::Mutex;
+	use crate::ShutdownStage;
+
+	#[test]
+	fn services_share_the_resources() {
+		let tasks = Arc::new(Mutex::new(Vec::new()));
+		let tasks_ = tasks.clone();
+		let executor: ServiceTaskExecutor = Arc::new(move |task, _| tasks_.lock().push(task));
+		let resources = SharedResources {
+			task_manager: TaskManager::new(executor.clone(), None).unwrap(),
+			registry: Some(Registry::new()),
+		};
+
+		// the task managers of the services of two chains, created as by the service builder.
+		let hooks = Arc::new(Mutex::new(Vec::new()));
+		let mut task_managers = Vec::new();
+		let mut task_ends = Vec::new();
+		for &chain in &["relay", "para"] {
+			let registry = resources.chain_registry(chain).unwrap().unwrap();
+			let task_manager = TaskManager::new(executor.clone(), Some(&registry)).unwrap()
+				.shared_with(&resources.task_manager());
+			let (sender, receiver) = oneshot::channel::<()>();
+			task_manager.spawn("pending", async move {
+				future::pending::<()>().await;
+				drop(sender);
+			});
+			task_ends.push(receiver);
+			let hooks = hooks.clone();
+			task_manager.shutdown_hooks()
+				.register(chain, ShutdownStage::Flush, move || async move { hooks.lock().push(chain) });
+			task_managers.push(task_manager);
+		}
+
+		// the metrics of both chains are served by the shared registry.
+		let families = resources.registry.as_ref().unwrap().gather();
+		let spawned = families.iter()
+			.find(|family| family.get_name() == "substrate_tasks_spawned_total")
+			.unwrap();
+		let mut chains = spawned.get_metric().iter()
+			.flat_map(|metric| metric.get_label().iter().filter(|label| label.get_name() == "chain"))
+			.map(|label| label.get_value().to_string())
+			.collect::<Vec<_>>();
+		chains.sort();
+		assert_eq!(chains, vec!["para", "relay"]);
+
+		// the shutdown hooks of both chains run along with the shared ones.
+		block_on(resources.run_shutdown_hooks());
+		hooks.lock().sort();
+		assert_eq!(*hooks.lock(), vec!["para", "relay"]);
+
+		// the tasks of both chains stop along with the shared task manager.
+		drop(resources);
+		for task in tasks.lock().drain(..) {
+			block_on(task);
+		}
+		for task_end in task_ends {
+			assert_eq!(block_on(task_end), Err(oneshot::Canceled));
+		}
+	}
+}
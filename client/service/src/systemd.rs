@@ -0,0 +1,105 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Notifications of the state of the node to systemd, following the protocol of `sd_notify`.
+
+use std::{env, io, ops::Add, time::Duration};
+use sc_network::SyncState;
+
+/// Sends `state`, e.g. `READY=1`, to the socket given by systemd in `NOTIFY_SOCKET`.
+///
+/// Returns whether the socket is set. Abstract socket addresses, starting with `@`, aren't
+/// supported.
+#[cfg(unix)]
+pub(crate) fn notify(state: &str) -> io::Result<bool> {
+	use std::os::unix::net::UnixDatagram;
+
+	let path = match env::var_os("NOTIFY_SOCKET") {
+		Some(path) => path,
+		None => return Ok(false),
+	};
+	UnixDatagram::unbound()?.send_to(state.as_bytes(), path)?;
+	Ok(true)
+}
+
+/// Sends `state` to systemd, which only runs on unix.
+#[cfg(not(unix))]
+pub(crate) fn notify(_state: &str) -> io::Result<bool> {
+	Ok(false)
+}
+
+/// Returns the timeout of the watchdog of systemd, if enabled for this process.
+pub(crate) fn watchdog_timeout() -> Option<Duration> {
+	let timeout = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+	if let Ok(pid) = env::var("WATCHDOG_PID") {
+		if pid.parse::<u32>().ok()? != std::process::id() {
+			return None
+		}
+	}
+	Some(Duration::from_micros(timeout))
+}
+
+/// Returns whether the node is synced enough to be reported ready: it is connected to peers,
+/// knows of their best block, is no longer catching up with the chain, and its best block is
+/// within `threshold` blocks of theirs.
+pub(crate) fn is_ready<N: Add<Output = N> + PartialOrd + Copy>(
+	best_number: N,
+	best_seen_block: Option<N>,
+	num_peers: usize,
+	sync_state: &SyncState,
+	threshold: N,
+) -> bool {
+	match best_seen_block {
+		Some(best_seen_block) =>
+			num_peers > 0
+				&& *sync_state == SyncState::Idle
+				&& best_number + threshold >= best_seen_block,
+		None => false,
+	}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+	use std::os::unix::net::UnixDatagram;
+
+	#[test]
+	fn only_synced_nodes_are_ready() {
+		assert!(is_ready(10u32, Some(12), 1, &SyncState::Idle, 2));
+		assert!(!is_ready(10u32, Some(13), 1, &SyncState::Idle, 2));
+		assert!(!is_ready(10u32, None, 1, &SyncState::Idle, 2));
+		assert!(!is_ready(10u32, Some(10), 0, &SyncState::Idle, 2));
+		assert!(!is_ready(10u32, Some(10), 1, &SyncState::Downloading, 2));
+	}
+
+	#[test]
+	fn states_are_sent_to_the_notify_socket() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("notify");
+		let socket = UnixDatagram::bind(&path).unwrap();
+		env::set_var("NOTIFY_SOCKET", &path);
+
+		assert!(notify("READY=1").unwrap());
+		let mut buffer = [0; 16];
+		let len = socket.recv(&mut buffer).unwrap();
+		assert_eq!(&buffer[..len], b"READY=1");
+
+		env::remove_var("NOTIFY_SOCKET");
+		assert!(!notify("READY=1").unwrap());
+	}
+}
@@ -13,9 +13,9 @@
 
 //! Substrate service tasks management module.
 
-use std::{panic, pin::Pin, result::Result, sync::Arc};
+use std::{panic, pin::Pin, result::Result, sync::Arc, time::Duration};
 use exit_future::Signal;
-use log::debug;
+use log::{debug, error, warn};
 use futures::{
 	Future, FutureExt,
 	future::{select, Either, BoxFuture},
@@ -27,7 +27,11 @@ use prometheus_endpoint::{
 	PrometheusError,
 	CounterVec, HistogramOpts, HistogramVec, Opts, Registry, U64
 };
+use futures_timer::Delay;
+use parking_lot::Mutex;
 use sc_client_api::CloneableSpawn;
+use sc_telemetry::{telemetry, SUBSTRATE_INFO};
+use sp_utils::mpsc::TracingUnboundedSender;
 use crate::config::TaskType;
 
 mod prometheus_future;
@@ -35,15 +39,31 @@ mod prometheus_future;
 /// Type alias for service task executor (usually runtime).
 pub type ServiceTaskExecutor = Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>, TaskType) + Send + Sync>;
 
+/// Group of the tasks spawned without a group.
+const DEFAULT_TASK_GROUP: &str = "default";
+
 /// An handle for spawning tasks in the service.
 #[derive(Clone)]
 pub struct SpawnTaskHandle {
 	on_exit: exit_future::Exit,
 	executor: ServiceTaskExecutor,
 	metrics: Option<Metrics>,
+	group: &'static str,
 }
 
 impl SpawnTaskHandle {
+	/// Returns a handle spawning the tasks in the given group, e.g. `network` or `txpool`.
+	///
+	/// The group of the tasks is reported to the Prometheus endpoint along with their name, so
+	/// that the statistics of the tasks of a subsystem can be added up. As for the names of the
+	/// tasks, the set of possible groups must be bounded.
+	pub fn with_group(&self, group: &'static str) -> Self {
+		SpawnTaskHandle {
+			group,
+			..Clone::clone(self)
+		}
+	}
+
 	/// Spawns the given task with the given name.
 	///
 	/// Note that the `name` is a `&'static str`. The reason for this choice is that statistics
@@ -70,21 +90,22 @@ impl SpawnTaskHandle {
 	) {
 		let on_exit = self.on_exit.clone();
 		let metrics = self.metrics.clone();
+		let group = self.group;
 
 		// Note that we increase the started counter here and not within the future. This way,
 		// we could properly visualize on Prometheus situations where the spawning doesn't work.
 		if let Some(metrics) = &self.metrics {
-			metrics.tasks_spawned.with_label_values(&[name]).inc();
+			metrics.tasks_spawned.with_label_values(&[name, group]).inc();
 			// We do a dummy increase in order for the task to show up in metrics.
-			metrics.tasks_ended.with_label_values(&[name, "finished"]).inc_by(0);
+			metrics.tasks_ended.with_label_values(&[name, group, "finished"]).inc_by(0);
 		}
 
 		let future = async move {
 			if let Some(metrics) = metrics {
 				// Add some wrappers around `task`.
 				let task = {
-					let poll_duration = metrics.poll_duration.with_label_values(&[name]);
-					let poll_start = metrics.poll_start.with_label_values(&[name]);
+					let poll_duration = metrics.poll_duration.with_label_values(&[name, group]);
+					let poll_start = metrics.poll_start.with_label_values(&[name, group]);
 					let inner = prometheus_future::with_poll_durations(poll_duration, poll_start, task);
 					// The logic of `AssertUnwindSafe` here is ok considering that we throw
 					// away the `Future` after it has panicked.
@@ -94,15 +115,15 @@ impl SpawnTaskHandle {
 
 				match select(on_exit, task).await {
 					Either::Right((Err(payload), _)) => {
-						metrics.tasks_ended.with_label_values(&[name, "panic"]).inc();
+						metrics.tasks_ended.with_label_values(&[name, group, "panic"]).inc();
 						panic::resume_unwind(payload)
 					}
 					Either::Right((Ok(()), _)) => {
-						metrics.tasks_ended.with_label_values(&[name, "finished"]).inc();
+						metrics.tasks_ended.with_label_values(&[name, group, "finished"]).inc();
 					}
 					Either::Left(((), _)) => {
 						// The `on_exit` has triggered.
-						metrics.tasks_ended.with_label_values(&[name, "interrupted"]).inc();
+						metrics.tasks_ended.with_label_values(&[name, group, "interrupted"]).inc();
 					}
 				}
 
@@ -145,6 +166,164 @@ impl futures01::future::Executor<Boxed01Future01> for SpawnTaskHandle {
 	}
 }
 
+/// Policy for restarting an essential task once it has ended or panicked, before giving up and
+/// shutting down the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+	/// Maximum number of restarts of the task.
+	pub max_restarts: u32,
+	/// Delay before the first restart, doubled at each of the following ones.
+	pub backoff: Duration,
+	/// Maximum delay before a restart.
+	pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+	/// Never restart the task.
+	pub fn never() -> Self {
+		RestartPolicy {
+			max_restarts: 0,
+			backoff: Duration::from_secs(0),
+			max_backoff: Duration::from_secs(0),
+		}
+	}
+
+	/// Returns the delay before the restart following the given number of restarts, or `None`
+	/// if the task shouldn't be restarted any more.
+	pub fn delay(&self, restarts: u32) -> Option<Duration> {
+		if restarts >= self.max_restarts {
+			return None
+		}
+		let delay = self.backoff.checked_mul(1 << restarts.min(31)).unwrap_or(self.max_backoff);
+		Some(delay.min(self.max_backoff))
+	}
+}
+
+impl Default for RestartPolicy {
+	/// Restarts the task up to 5 times, waiting from 1 to 60 seconds, e.g. to get over a transient
+	/// failure of the telemetry or of the authority discovery.
+	fn default() -> Self {
+		RestartPolicy {
+			max_restarts: 5,
+			backoff: Duration::from_secs(1),
+			max_backoff: Duration::from_secs(60),
+		}
+	}
+}
+
+/// Returns the essential task built by `task`, built again and restarted according to the
+/// `policy` once it has ended or panicked.
+///
+/// Once the policy allows no more restarts, the failure of the task is reported to
+/// `essential_failed`, which triggers a service exit.
+pub(crate) fn restartable_essential_task<F, Fut>(
+	name: &'static str,
+	policy: RestartPolicy,
+	mut task: F,
+	essential_failed: TracingUnboundedSender<()>,
+) -> impl Future<Output = ()>
+where
+	F: FnMut() -> Fut + Send + 'static,
+	Fut: Future<Output = ()> + Send + 'static,
+{
+	async move {
+		let mut restarts = 0;
+		loop {
+			let panicked = panic::AssertUnwindSafe(task()).catch_unwind().await.is_err();
+			let delay = match policy.delay(restarts) {
+				Some(delay) => delay,
+				None => break,
+			};
+			restarts += 1;
+			warn!(
+				"Essential task `{}` {}. Restarting it in {:?} ({}/{}).",
+				name,
+				if panicked { "panicked" } else { "ended" },
+				delay,
+				restarts,
+				policy.max_restarts,
+			);
+			telemetry!(SUBSTRATE_INFO; "service.essential_task_restarted";
+				"name" => name,
+				"restarts" => restarts,
+				"panicked" => panicked
+			);
+			Delay::new(delay).await;
+		}
+
+		error!("Essential task `{}` failed. Shutting down service.", name);
+		let _ = essential_failed.unbounded_send(());
+	}
+}
+
+/// Maximum duration of a shutdown hook, after which the shutdown goes on without it.
+const SHUTDOWN_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Stage of the shutdown of the service at which a shutdown hook runs.
+///
+/// The stages run in the order of the variants, and the hooks of a stage in the order they were
+/// registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShutdownStage {
+	/// Flushing of the databases and other persisted state, while the service fully runs.
+	Flush,
+	/// Stopping of the services built on top of the node, e.g. indexers or custom RPC servers.
+	Services,
+	/// Stopping of the networking, last so that the other hooks can still reach the peers.
+	Network,
+}
+
+type ShutdownHook = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+/// An handle for registering the hooks run when the service shuts down, before its tasks are
+/// stopped.
+#[derive(Clone, Default)]
+pub struct ShutdownHooks {
+	hooks: Arc<Mutex<Vec<(ShutdownStage, &'static str, ShutdownHook)>>>,
+}
+
+impl ShutdownHooks {
+	/// Registers the hook with the given name, run at the given stage of the shutdown.
+	pub fn register<F, Fut>(&self, name: &'static str, stage: ShutdownStage, hook: F)
+	where
+		F: FnOnce() -> Fut + Send + 'static,
+		Fut: Future<Output = ()> + Send + 'static,
+	{
+		self.hooks.lock().push((stage, name, Box::new(move || hook().boxed())));
+	}
+
+	/// Runs the hooks registered so far, stage by stage, and forgets them.
+	///
+	/// A hook still running after 30 seconds is abandoned, so that it can't block the shutdown.
+	pub fn run(&self) -> impl Future<Output = ()> {
+		let mut hooks = std::mem::take(&mut *self.hooks.lock());
+		// The sort is stable, which keeps the order of registration within a stage.
+		hooks.sort_by_key(|(stage, _, _)| *stage);
+
+		async move {
+			for (stage, name, hook) in hooks {
+				debug!(target: "service", "Running shutdown hook `{}` ({:?})", name, stage);
+				if let Either::Right(_) = select(hook(), Delay::new(SHUTDOWN_HOOK_TIMEOUT)).await {
+					warn!(target: "service", "Shutdown hook `{}` timed out", name);
+				}
+			}
+		}
+	}
+}
+
+/// Task manager shared by the services of several chains, e.g. through [`SharedResources`].
+///
+/// The tasks of the services sharing it are spawned on its executor and stopped along with its
+/// own, and their shutdown hooks are run along with its own.
+///
+/// [`SharedResources`]: ../struct.SharedResources.html
+#[derive(Clone)]
+pub struct SharedTaskManager {
+	on_exit: exit_future::Exit,
+	executor: ServiceTaskExecutor,
+	shutdown_hooks: ShutdownHooks,
+}
+
 /// Helper struct to manage background/async tasks in Service.
 pub struct TaskManager {
 	/// A future that resolves when the service has exited, this is useful to
@@ -156,6 +335,8 @@ pub struct TaskManager {
 	executor: ServiceTaskExecutor,
 	/// Prometheus metric where to report the polling times.
 	metrics: Option<Metrics>,
+	/// Hooks to run before the tasks are stopped.
+	shutdown_hooks: ShutdownHooks,
 }
 
 impl TaskManager {
@@ -174,9 +355,31 @@ impl TaskManager {
 			signal: Some(signal),
 			executor,
 			metrics,
+			shutdown_hooks: Default::default(),
 		})
 	}
 
+	/// Makes the tasks of this task manager stop along with the ones of `shared`, and its
+	/// shutdown hooks run along with the ones of `shared`.
+	pub(super) fn shared_with(mut self, shared: &SharedTaskManager) -> Self {
+		let executor = shared.executor.clone();
+		let shared_exit = shared.on_exit.clone();
+		self.executor = Arc::new(move |task, task_type| {
+			executor(select(shared_exit.clone(), task).map(drop).boxed(), task_type)
+		});
+		self.shutdown_hooks = shared.shutdown_hooks.clone();
+		self
+	}
+
+	/// Returns a handle sharing this task manager with the services of other chains.
+	pub(super) fn share(&self) -> SharedTaskManager {
+		SharedTaskManager {
+			on_exit: self.on_exit.clone(),
+			executor: self.executor.clone(),
+			shutdown_hooks: self.shutdown_hooks.clone(),
+		}
+	}
+
 	/// Spawn background/async task, which will be aware on exit signal.
 	///
 	/// See also the documentation of [`SpawnTaskHandler::spawn`].
@@ -184,11 +387,14 @@ impl TaskManager {
 		self.spawn_handle().spawn(name, task)
 	}
 
+	/// Get a handle for spawning tasks in the default group. See also
+	/// [`SpawnTaskHandle::with_group`].
 	pub(super) fn spawn_handle(&self) -> SpawnTaskHandle {
 		SpawnTaskHandle {
 			on_exit: self.on_exit.clone(),
 			executor: self.executor.clone(),
 			metrics: self.metrics.clone(),
+			group: DEFAULT_TASK_GROUP,
 		}
 	}
 
@@ -196,6 +402,11 @@ impl TaskManager {
 	pub(super) fn on_exit(&self) -> exit_future::Exit {
 		self.on_exit.clone()
 	}
+
+	/// Get the handle for registering shutdown hooks.
+	pub(super) fn shutdown_hooks(&self) -> ShutdownHooks {
+		self.shutdown_hooks.clone()
+	}
 }
 
 impl Drop for TaskManager {
@@ -228,29 +439,123 @@ impl Metrics {
 					buckets: exponential_buckets(0.001, 4.0, 9)
 						.expect("function parameters are constant and always valid; qed"),
 				},
-				&["task_name"]
+				&["task_name", "task_group"]
 			)?, registry)?,
 			poll_start: register(CounterVec::new(
 				Opts::new(
 					"tasks_polling_started_total",
 					"Total number of times we started invoking Future::poll"
 				),
-				&["task_name"]
+				&["task_name", "task_group"]
 			)?, registry)?,
 			tasks_spawned: register(CounterVec::new(
 				Opts::new(
 					"tasks_spawned_total",
 					"Total number of tasks that have been spawned on the Service"
 				),
-				&["task_name"]
+				&["task_name", "task_group"]
 			)?, registry)?,
 			tasks_ended: register(CounterVec::new(
 				Opts::new(
 					"tasks_ended_total",
 					"Total number of tasks for which Future::poll has returned Ready(()) or panicked"
 				),
-				&["task_name", "reason"]
+				&["task_name", "task_group", "reason"]
 			)?, registry)?,
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn restart_delays_back_off() {
+		let policy = RestartPolicy {
+			max_restarts: 5,
+			backoff: Duration::from_secs(1),
+			max_backoff: Duration::from_secs(6),
+		};
+		let delays = (0..6).map(|restarts| policy.delay(restarts)).collect::<Vec<_>>();
+		assert_eq!(delays, vec![
+			Some(Duration::from_secs(1)),
+			Some(Duration::from_secs(2)),
+			Some(Duration::from_secs(4)),
+			Some(Duration::from_secs(6)),
+			Some(Duration::from_secs(6)),
+			None,
+		]);
+		assert_eq!(RestartPolicy::never().delay(0), None);
+	}
+
+	#[test]
+	fn essential_tasks_restart_after_a_panic() {
+		let policy = RestartPolicy {
+			max_restarts: 2,
+			backoff: Duration::from_secs(0),
+			max_backoff: Duration::from_secs(0),
+		};
+		let runs = Arc::new(Mutex::new(0));
+		let runs_ = runs.clone();
+		let (essential_failed_tx, mut essential_failed_rx) = sp_utils::mpsc::tracing_unbounded("test");
+		let task = restartable_essential_task("test", policy, move || {
+			let runs = runs_.clone();
+			async move {
+				*runs.lock() += 1;
+				if *runs.lock() == 1 {
+					panic!("the first run fails");
+				}
+			}
+		}, essential_failed_tx);
+
+		futures::executor::block_on(task);
+		assert_eq!(*runs.lock(), 3);
+		assert_eq!(essential_failed_rx.try_next().unwrap(), Some(()));
+	}
+
+	#[test]
+	fn task_metrics_are_labelled_with_the_group() {
+		let tasks = Arc::new(Mutex::new(Vec::new()));
+		let tasks_ = tasks.clone();
+		let executor: ServiceTaskExecutor = Arc::new(move |task, _| tasks_.lock().push(task));
+		let registry = Registry::new();
+		let task_manager = TaskManager::new(executor, Some(&registry)).unwrap();
+
+		task_manager.spawn_handle().with_group("txpool").spawn("txpool-maintain", async {});
+		task_manager.spawn("unnamed", async {});
+		for task in tasks.lock().drain(..) {
+			futures::executor::block_on(task);
+		}
+
+		let families = registry.gather();
+		let spawned = families.iter().find(|family| family.get_name() == "tasks_spawned_total").unwrap();
+		let mut labels = spawned.get_metric().iter()
+			.map(|metric| metric.get_label().iter().map(|label| label.get_value()).collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+		labels.sort();
+		assert_eq!(labels, vec![vec!["default", "unnamed"], vec!["txpool", "txpool-maintain"]]);
+	}
+
+	#[test]
+	fn shutdown_hooks_run_by_stage() {
+		let hooks = ShutdownHooks::default();
+		let order = Arc::new(Mutex::new(Vec::new()));
+		for &(name, stage) in &[
+			("network", ShutdownStage::Network),
+			("indexer", ShutdownStage::Services),
+			("database", ShutdownStage::Flush),
+			("rpc", ShutdownStage::Services),
+		] {
+			let order = order.clone();
+			hooks.register(name, stage, move || async move { order.lock().push(name) });
+		}
+
+		futures::executor::block_on(hooks.run());
+		assert_eq!(*order.lock(), vec!["database", "indexer", "rpc", "network"]);
+
+		// the hooks only run once.
+		futures::executor::block_on(hooks.run());
+		assert_eq!(order.lock().len(), 4);
+	}
+}
@@ -398,6 +398,45 @@ fn execution_proof_is_generated_and_checked() {
 	execute_with_proof_failure(&remote_client, 2, "Core_version");
 }
 
+#[test]
+fn runtime_call_proof_is_checked_with_the_state_root() {
+	let mut remote_client = substrate_test_runtime_client::new();
+	remote_client.import(
+		BlockOrigin::Own,
+		remote_client.new_block(Default::default()).unwrap().build().unwrap().block,
+	).unwrap();
+	let remote_header = remote_client.header(&BlockId::Number(1)).unwrap().unwrap();
+	let alice: runtime::AccountId = AccountKeyring::Alice.into();
+
+	for (method, call_data) in vec![("Core_version", vec![]), ("TestAPI_balance_of", alice.encode())] {
+		let (remote_result, proof) = remote_client.prove_execution(
+			&BlockId::Number(1),
+			method,
+			&call_data,
+		).unwrap();
+
+		let local_result = sp_state_machine::execution_proof_check_with_proof_code::<BlakeTwo256, u64, _>(
+			remote_header.state_root,
+			proof.clone(),
+			&local_executor(),
+			tasks_executor(),
+			method,
+			&call_data,
+		).unwrap();
+		assert_eq!(remote_result, local_result);
+
+		// the proof doesn't hold against another state.
+		assert!(sp_state_machine::execution_proof_check_with_proof_code::<BlakeTwo256, u64, _>(
+			Default::default(),
+			proof,
+			&local_executor(),
+			tasks_executor(),
+			method,
+			&call_data,
+		).is_err());
+	}
+}
+
 #[test]
 fn code_is_executed_at_genesis_only() {
 	let backend = Arc::new(InMemBackend::<Block>::new());
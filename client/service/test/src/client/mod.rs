@@ -29,7 +29,7 @@ use substrate_test_runtime_client::{
 	BlockBuilderExt, DefaultTestClientBuilderExt, TestClientBuilderExt, ClientExt,
 };
 use sc_client_api::{
-	StorageProvider, BlockBackend, in_mem, BlockchainEvents,
+	StorageProvider, BlockBackend, in_mem, BlockchainEvents, ProofProvider,
 };
 use sc_client_db::{Backend, DatabaseSettings, DatabaseSettingsSrc, PruningMode};
 use sc_block_builder::BlockBuilderProvider;
@@ -410,6 +410,57 @@ fn block_builder_works_with_transactions() {
 	);
 }
 
+#[test]
+fn import_notifications_carry_the_storage_changes() {
+	use futures::StreamExt;
+
+	let mut client = substrate_test_runtime_client::new();
+	let mut notifications = client.import_notification_stream();
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_transfer(Transfer {
+		from: AccountKeyring::Alice.into(),
+		to: AccountKeyring::Ferdie.into(),
+		amount: 42,
+		nonce: 0,
+	}).unwrap();
+	let block = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, block).unwrap();
+
+	let notification = futures::executor::block_on(notifications.next()).unwrap();
+	let storage_changes = notification.storage_changes.expect("the block has been executed; qed");
+	let state = client.state_at(&BlockId::Number(1)).unwrap();
+	assert!(!storage_changes.0.is_empty());
+	for (key, value) in &storage_changes.0 {
+		assert_eq!(&state.storage(key).unwrap(), value);
+	}
+}
+
+#[test]
+fn lossy_finality_notifications_skip_the_blocks_of_lagging_subscribers() {
+	use futures::StreamExt;
+	use sc_client_api::LossyNotification;
+
+	let mut client = substrate_test_runtime_client::new();
+	let mut notifications = client.lossy_finality_notification_stream(1);
+
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import_as_final(BlockOrigin::Own, block).unwrap();
+	}
+
+	match futures::executor::block_on(notifications.next()) {
+		Some(LossyNotification::Skipped { from, to, count }) => assert_eq!((from, to, count), (1, 2, 2)),
+		other => panic!("Expected skipped blocks, got {:?}", other),
+	}
+	match futures::executor::block_on(notifications.next()) {
+		Some(LossyNotification::Notification(notification)) => {
+			assert_eq!(notification.hash, client.chain_info().finalized_hash);
+		},
+		other => panic!("Expected a notification, got {:?}", other),
+	}
+}
+
 #[test]
 fn block_builder_does_not_include_invalid() {
 	let mut client = substrate_test_runtime_client::new();
@@ -1275,13 +1326,19 @@ fn doesnt_import_blocks_that_revert_finality() {
 		DatabaseSettings {
 			state_cache_size: 1 << 20,
 			state_cache_child_ratio: None,
+			trie_cache_size: 0,
 			pruning: PruningMode::ArchiveAll,
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: u64::max_value(),
 			source: DatabaseSettingsSrc::RocksDb {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
 		},
-		u64::max_value(),
 	).unwrap());
 
 	let mut client = TestClientBuilder::with_backend(backend).build();
@@ -1476,13 +1533,19 @@ fn returns_status_for_pruned_blocks() {
 		DatabaseSettings {
 			state_cache_size: 1 << 20,
 			state_cache_child_ratio: None,
+			trie_cache_size: 0,
 			pruning: PruningMode::keep_blocks(1),
+			block_pruning: Default::default(),
+			index_extrinsics: false,
+			index_storage_changes: false,
+			cold_storage: None,
+			delta_archive: None,
+			canonicalization_delay: u64::max_value(),
 			source: DatabaseSettingsSrc::RocksDb {
 				path: tmp.path().into(),
 				cache_size: 1024,
 			},
 		},
-		u64::max_value(),
 	).unwrap());
 
 	let mut client = TestClientBuilder::with_backend(backend).build();
@@ -1691,6 +1754,35 @@ fn storage_keys_iter_prefix_and_start_key_works() {
 		.map(|x| x.0)
 		.collect();
 	assert_eq!(res, Vec::<Vec<u8>>::new());
+
+	// a key equal to the prefix is included.
+	let prefix = StorageKey(hex!("3a636f6465").to_vec());
+	let res: Vec<_> = client.storage_keys_iter(&BlockId::Number(0), Some(&prefix), None)
+		.unwrap()
+		.map(|x| x.0)
+		.collect();
+	assert_eq!(res, [hex!("3a636f6465").to_vec()]);
+}
+
+#[test]
+fn storage_keys_iter_resumes_after_the_last_key_of_a_page() {
+	let client = substrate_test_runtime_client::new();
+	let all: Vec<_> = client.storage_keys_iter(&BlockId::Number(0), None, None).unwrap().collect();
+
+	let mut pages = Vec::new();
+	let mut start_key = None;
+	loop {
+		// the iterator doesn't borrow the client, nor the prefix and the start key.
+		let iter = client.storage_keys_iter(&BlockId::Number(0), None, start_key.as_ref()).unwrap();
+		let page = std::thread::spawn(move || iter.take(2).collect::<Vec<_>>()).join().unwrap();
+		match page.last() {
+			Some(last) => start_key = Some(last.clone()),
+			None => break,
+		}
+		pages.extend(page);
+	}
+	assert!(all.len() > 2);
+	assert_eq!(pages, all);
 }
 
 #[test]
@@ -1802,3 +1894,35 @@ fn cleans_up_closed_notification_sinks_on_block_import() {
 	assert_eq!(client.finality_notification_sinks().lock().len(), 0);
 }
 
+#[test]
+fn state_chunks_can_be_verified_from_proofs() {
+	let client = substrate_test_runtime_client::new();
+	let at = BlockId::Number(0);
+	let state_root = *client.header(&at).unwrap().unwrap().state_root();
+
+	let (expected, complete) = client.state_chunk(&at, &[], usize::max_value()).unwrap();
+	assert!(complete);
+
+	let mut entries = Vec::new();
+	let mut start = Vec::new();
+	loop {
+		let proof = client.read_proof_collection(&at, &start, 256).unwrap();
+		let (chunk, complete) = client.verify_range_proof(state_root, proof, &start).unwrap();
+		assert!(!chunk.is_empty() || complete);
+		if let Some(last) = chunk.last() {
+			start = match &last.child {
+				Some(storage_key) => vec![
+					sp_core::storage::ChildInfo::new_default(storage_key).prefixed_storage_key().into_inner(),
+					last.key.clone(),
+				],
+				None => vec![last.key.clone()],
+			};
+		}
+		entries.extend(chunk);
+		if complete {
+			break
+		}
+	}
+
+	assert_eq!(entries, expected);
+}
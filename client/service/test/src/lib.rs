@@ -177,6 +177,7 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		impl_version: "0.1",
 		role,
 		task_executor,
+		shared_task_manager: None,
 		transaction_pool: Default::default(),
 		network: network_config,
 		keystore: KeystoreConfig::Path {
@@ -189,15 +190,32 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		},
 		state_cache_size: 16777216,
 		state_cache_child_ratio: None,
+		trie_cache_size: 16777216,
 		pruning: Default::default(),
+		block_pruning: Default::default(),
+		index_extrinsics: Default::default(),
+		index_storage_changes: Default::default(),
+		cold_storage: None,
+		delta_archive: None,
+		canonicalization_delay: sc_service::config::DEFAULT_CANONICALIZATION_DELAY,
 		chain_spec: Box::new((*spec).clone()),
+		pinned_blocks: Default::default(),
 		wasm_method: sc_service::config::WasmExecutionMethod::Interpreted,
 		execution_strategies: Default::default(),
 		rpc_http: None,
 		rpc_ws: None,
+		rpc_ipc: Default::default(),
+		rpc_grpc: None,
 		rpc_ws_max_connections: None,
+		rpc_ws_max_subscriptions_per_connection: Default::default(),
+		rpc_max_request_size: Default::default(),
+		rpc_max_response_size: Default::default(),
+		rpc_max_batch_size: Default::default(),
+		rpc_rate_limit: Default::default(),
+		rpc_method_filter: Default::default(),
 		rpc_cors: None,
 		rpc_methods: Default::default(),
+		rpc_interface_methods: Default::default(),
 		prometheus_config: None,
 		telemetry_endpoints: None,
 		telemetry_external_transport: None,
@@ -210,6 +228,8 @@ fn node_config<G: RuntimeGenesis + 'static, E: ChainSpecExtension + Clone + 'sta
 		tracing_receiver: Default::default(),
 		max_runtime_instances: 8,
 		announce_block: true,
+		systemd_notify: None,
+		health: None,
 	}
 }
 
@@ -263,7 +283,7 @@ impl<G, E, F, L, U> TestNet<G, E, F, L, U> where
 			let node_config = node_config(
 				self.nodes,
 				&self.chain_spec,
-				Role::Authority { sentry_nodes: Vec::new() },
+				Role::Authority { sentry_nodes: Vec::new(), private: false },
 				task_executor,
 				Some(key),
 				self.base_port,
@@ -42,15 +42,19 @@ use codec::Codec;
 use std::collections::{HashMap, hash_map::Entry};
 use noncanonical::NonCanonicalOverlay;
 use pruning::RefWindow;
-use log::trace;
+use log::{info, trace};
 use parity_util_mem::{MallocSizeOf, malloc_size};
-use sc_client_api::{StateDbMemoryInfo, MemorySize};
+use sc_client_api::{StateDbMemoryInfo, StateDbPruningInfo, MemorySize};
 
 const PRUNING_MODE: &[u8] = b"mode";
 const PRUNING_MODE_ARCHIVE: &[u8] = b"archive";
 const PRUNING_MODE_ARCHIVE_CANON: &[u8] = b"archive_canonical";
 const PRUNING_MODE_CONSTRAINED: &[u8] = b"constrained";
 
+/// Maximum number of blocks pruned on each canonicalization, so that the blocks past a window
+/// reduced since the database was created are pruned progressively.
+const MAX_BLOCKS_PRUNED_AT_ONCE: usize = 32;
+
 /// Database value type.
 pub type DBValue = Vec<u8>;
 
@@ -221,6 +225,28 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => None,
 		};
 
+		if let (Some(pruning), PruningMode::Constrained(constraints)) = (&pruning, &mode) {
+			let max_blocks = constraints.max_blocks.unwrap_or(0) as u64;
+			let window_size = pruning.window_size();
+			if window_size > max_blocks {
+				info!(target: "state-db",
+					"The states of {} canonical blocks are kept, more than the pruning window of {} blocks. \
+					The {} oldest ones will be pruned progressively.",
+					window_size,
+					max_blocks,
+					window_size - max_blocks,
+				);
+			} else if window_size < max_blocks && pruning.pending() > 0 {
+				info!(target: "state-db",
+					"The states of {} canonical blocks are kept, less than the pruning window of {} blocks. \
+					The states before #{} are already pruned, the window fills up as blocks are finalized.",
+					window_size,
+					max_blocks,
+					pruning.pending(),
+				);
+			}
+		}
+
 		Ok(StateDbSync {
 			mode,
 			non_canonical,
@@ -297,6 +323,28 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 		Ok(commit)
 	}
 
+	fn import_canonical_block<E: fmt::Debug>(
+		&mut self,
+		hash: &BlockHash,
+		number: u64,
+		mut changeset: ChangeSet<Key>,
+	) -> Result<CommitSet<Key>, Error<E>> {
+		changeset.deleted.clear();
+		let mut commit = CommitSet {
+			data: changeset,
+			meta: ChangeSet::default(),
+		};
+		if self.mode == PruningMode::ArchiveAll {
+			return Ok(commit)
+		}
+		self.non_canonical.reset_canonical(hash, number, &mut commit)?;
+		if let Some(ref mut pruning) = self.pruning {
+			pruning.reset(number, &mut commit);
+			pruning.note_canonical(hash, &mut commit);
+		}
+		Ok(commit)
+	}
+
 	fn best_canonical(&self) -> Option<u64> {
 		return self.non_canonical.last_canonicalized_block_number()
 	}
@@ -321,7 +369,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 
 	fn prune(&mut self, commit: &mut CommitSet<Key>) {
 		if let (&mut Some(ref mut pruning), &PruningMode::Constrained(ref constraints)) = (&mut self.pruning, &self.mode) {
-			loop {
+			for _ in 0..MAX_BLOCKS_PRUNED_AT_ONCE {
 				if pruning.window_size() <= constraints.max_blocks.unwrap_or(0) as u64 {
 					break;
 				}
@@ -425,6 +473,22 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDbSync<Block
 		self.non_canonical.revert_pending();
 	}
 
+	fn pruning_info(&self) -> StateDbPruningInfo {
+		let window = match &self.mode {
+			PruningMode::Constrained(constraints) => Some(constraints.max_blocks.unwrap_or(0) as u64),
+			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => None,
+		};
+		let (oldest_block, window_size) = self.pruning.as_ref()
+			.map_or((0, 0), |pruning| (pruning.pending(), pruning.window_size()));
+		StateDbPruningInfo {
+			window,
+			oldest_block,
+			last_canonicalized: self.best_canonical(),
+			non_canonical: self.non_canonical.block_count() as u64,
+			pending_prunes: window.map_or(0, |window| window_size.saturating_sub(window)),
+		}
+	}
+
 	fn memory_info(&self) -> StateDbMemoryInfo {
 		StateDbMemoryInfo {
 			non_canonical: MemorySize::from_bytes(malloc_size(&self.non_canonical)),
@@ -452,6 +516,20 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 		})
 	}
 
+	/// Reloads the non-canonical blocks and the pruning window from the database, after it has
+	/// been written by another instance. The pinned blocks that are still known stay pinned.
+	pub fn reload<D: MetaDb>(&self, ref_counting: bool, db: &D) -> Result<(), Error<D::Error>> {
+		let mut state_db = self.db.write();
+		let pinned = std::mem::take(&mut state_db.pinned);
+		*state_db = StateDbSync::new(state_db.mode.clone(), ref_counting, db)?;
+		for (hash, refs) in pinned {
+			if state_db.pin(&hash).is_ok() {
+				state_db.pinned.insert(hash, refs);
+			}
+		}
+		Ok(())
+	}
+
 	/// Add a new non-canonical block.
 	pub fn insert_block<E: fmt::Debug>(
 		&self,
@@ -471,6 +549,17 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 		self.db.write().canonicalize_block(hash)
 	}
 
+	/// Add the complete state of a block, which becomes the last canonical block regardless of
+	/// its ancestry. Only possible when there are no non-canonical blocks.
+	pub fn import_canonical_block<E: fmt::Debug>(
+		&self,
+		hash: &BlockHash,
+		number: u64,
+		changeset: ChangeSet<Key>,
+	) -> Result<CommitSet<Key>, Error<E>> {
+		self.db.write().import_canonical_block(hash, number, changeset)
+	}
+
 	/// Prevents pruning of specified block and its descendants.
 	pub fn pin(&self, hash: &BlockHash) -> Result<(), PinError> {
 		self.db.write().pin(hash)
@@ -518,6 +607,11 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 		self.db.write().revert_pending();
 	}
 
+	/// Returns the current pruning status of this instance.
+	pub fn pruning_info(&self) -> StateDbPruningInfo {
+		self.db.read().pruning_info()
+	}
+
 	/// Returns the current memory statistics of this instance.
 	pub fn memory_info(&self) -> StateDbMemoryInfo {
 		self.db.read().memory_info()
@@ -528,7 +622,7 @@ impl<BlockHash: Hash + MallocSizeOf, Key: Hash + MallocSizeOf> StateDb<BlockHash
 mod tests {
 	use std::io;
 	use sp_core::H256;
-	use crate::{StateDb, PruningMode, Constraints};
+	use crate::{StateDb, PruningMode, Constraints, StateDbPruningInfo};
 	use crate::test::{make_db, make_changeset, TestDb};
 
 	fn make_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256>) {
@@ -645,6 +739,65 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn imports_canonical_block_with_unknown_parent() {
+		let mut db = make_db(&[91]);
+		let sdb = StateDb::new(PruningMode::Constrained(Constraints {
+			max_blocks: Some(1),
+			max_mem: None,
+		}), false, &db).unwrap();
+		db.commit(
+			&sdb.insert_block::<io::Error>(
+				&H256::from_low_u64_be(1),
+				1,
+				&H256::from_low_u64_be(0),
+				make_changeset(&[1], &[91]),
+			)
+			.unwrap(),
+		);
+		sdb.apply_pending();
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(1)).unwrap());
+		sdb.apply_pending();
+
+		db.commit(
+			&sdb.import_canonical_block::<io::Error>(
+				&H256::from_low_u64_be(10),
+				10,
+				make_changeset(&[10, 11], &[1]),
+			)
+			.unwrap(),
+		);
+		sdb.apply_pending();
+		assert_eq!(sdb.best_canonical(), Some(10));
+		assert!(!sdb.is_pruned(&H256::from_low_u64_be(10), 10));
+
+		// The imported block is a valid parent for the following blocks.
+		db.commit(
+			&sdb.insert_block::<io::Error>(
+				&H256::from_low_u64_be(12),
+				11,
+				&H256::from_low_u64_be(10),
+				make_changeset(&[12], &[11]),
+			)
+			.unwrap(),
+		);
+		sdb.apply_pending();
+		db.commit(&sdb.canonicalize_block::<io::Error>(&H256::from_low_u64_be(12)).unwrap());
+		sdb.apply_pending();
+		assert!(sdb.is_pruned(&H256::from_low_u64_be(10), 10));
+		assert!(db.data_eq(&make_db(&[1, 10, 11, 12])));
+	}
+
+	#[test]
+	fn refuses_to_import_canonical_block_over_non_canonical_ones() {
+		let (_, sdb) = make_test_db(PruningMode::ArchiveCanonical);
+		assert!(sdb.import_canonical_block::<io::Error>(
+			&H256::from_low_u64_be(10),
+			10,
+			make_changeset(&[10], &[]),
+		).is_err());
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
@@ -663,4 +816,108 @@ mod tests {
 		let state_db: Result<StateDb<H256, H256>, _> = StateDb::new(new_mode, false, &db);
 		assert!(state_db.is_err());
 	}
+
+	#[test]
+	fn reloads_the_blocks_inserted_by_another_instance() {
+		let mut db = make_db(&[]);
+		let mode = PruningMode::Constrained(Constraints { max_blocks: Some(2), max_mem: None });
+		let primary = StateDb::new(mode.clone(), false, &db).unwrap();
+		db.commit(
+			&primary.insert_block::<io::Error>(
+				&H256::from_low_u64_be(1),
+				1,
+				&H256::from_low_u64_be(0),
+				make_changeset(&[1], &[]),
+			)
+			.unwrap(),
+		);
+		primary.apply_pending();
+		db.commit(&primary.canonicalize_block::<io::Error>(&H256::from_low_u64_be(1)).unwrap());
+		primary.apply_pending();
+
+		let secondary: StateDb<H256, H256> = StateDb::new(mode, false, &db).unwrap();
+		db.commit(
+			&primary.insert_block::<io::Error>(
+				&H256::from_low_u64_be(2),
+				2,
+				&H256::from_low_u64_be(1),
+				make_changeset(&[2], &[]),
+			)
+			.unwrap(),
+		);
+		primary.apply_pending();
+		// the non-canonical blocks are only known from the journal written by the primary instance.
+		assert!(secondary.is_pruned(&H256::from_low_u64_be(2), 2));
+
+		secondary.reload(false, &db).unwrap();
+		assert!(!secondary.is_pruned(&H256::from_low_u64_be(2), 2));
+	}
+
+	fn import_canonical_chain(db: &mut TestDb, state_db: &StateDb<H256, H256>, blocks: std::ops::Range<u64>) {
+		for number in blocks {
+			db.commit(
+				&state_db
+					.insert_block::<io::Error>(
+						&H256::from_low_u64_be(number + 1),
+						number,
+						&H256::from_low_u64_be(number),
+						make_changeset(&[number + 1], &[]),
+					)
+					.unwrap(),
+			);
+			state_db.apply_pending();
+			db.commit(&state_db.canonicalize_block::<io::Error>(&H256::from_low_u64_be(number + 1)).unwrap());
+			state_db.apply_pending();
+		}
+	}
+
+	#[test]
+	fn reduced_window_is_pruned_progressively() {
+		let mut db = make_db(&[]);
+		let state_db = StateDb::new(PruningMode::keep_blocks(100), false, &db).unwrap();
+		import_canonical_chain(&mut db, &state_db, 0..40);
+		assert_eq!(state_db.pruning_info(), StateDbPruningInfo {
+			window: Some(100),
+			oldest_block: 0,
+			last_canonicalized: Some(39),
+			non_canonical: 0,
+			pending_prunes: 0,
+		});
+
+		// restarting with a smaller window doesn't prune everything at once.
+		let state_db = StateDb::new(PruningMode::keep_blocks(4), false, &db).unwrap();
+		assert_eq!(state_db.pruning_info().pending_prunes, 36);
+		assert!(!state_db.is_pruned(&H256::from_low_u64_be(21), 20));
+		import_canonical_chain(&mut db, &state_db, 40..41);
+		assert_eq!(state_db.pruning_info().oldest_block, 32);
+		assert_eq!(state_db.pruning_info().pending_prunes, 5);
+		import_canonical_chain(&mut db, &state_db, 41..42);
+		assert_eq!(state_db.pruning_info().oldest_block, 38);
+		assert_eq!(state_db.pruning_info().pending_prunes, 0);
+		assert!(state_db.is_pruned(&H256::from_low_u64_be(21), 20));
+
+		db.commit(
+			&state_db
+				.insert_block::<io::Error>(
+					&H256::from_low_u64_be(43),
+					42,
+					&H256::from_low_u64_be(42),
+					make_changeset(&[43], &[]),
+				)
+				.unwrap(),
+		);
+		state_db.apply_pending();
+		assert_eq!(state_db.pruning_info(), StateDbPruningInfo {
+			window: Some(4),
+			oldest_block: 38,
+			last_canonicalized: Some(41),
+			non_canonical: 1,
+			pending_prunes: 0,
+		});
+
+		// restarting with a larger window doesn't bring the pruned states back.
+		let state_db: StateDb<H256, H256> = StateDb::new(PruningMode::keep_blocks(8), false, &db).unwrap();
+		assert_eq!(state_db.pruning_info().oldest_block, 38);
+		assert_eq!(state_db.pruning_info().window, Some(8));
+	}
 }
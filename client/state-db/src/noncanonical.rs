@@ -297,6 +297,11 @@ impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 		}
 	}
 
+	/// Returns the number of non-canonical blocks, excluding the pending canonicalizations.
+	pub fn block_count(&self) -> usize {
+		self.levels.iter().skip(self.pending_canonicalizations.len()).map(|level| level.len()).sum()
+	}
+
 	pub fn last_canonicalized_hash(&self) -> Option<BlockHash> {
 		self.last_canonicalized.as_ref().map(|&(ref h, _)| h.clone())
 	}
@@ -309,6 +314,24 @@ impl<BlockHash: Hash, Key: Hash> NonCanonicalOverlay<BlockHash, Key> {
 			.unwrap_or_default()
 	}
 
+	/// Make the given block the last canonicalized one, regardless of its ancestry. Used when
+	/// the state of a block is imported as a whole. Fails if there are any non-canonical blocks.
+	pub fn reset_canonical<E: fmt::Debug>(
+		&mut self,
+		hash: &BlockHash,
+		number: u64,
+		commit: &mut CommitSet<Key>,
+	) -> Result<(), Error<E>> {
+		if !self.levels.is_empty() || !self.pending_canonicalizations.is_empty() {
+			return Err(Error::InvalidBlockNumber);
+		}
+		trace!(target: "state-db", "Resetting last canonicalized block to #{} ({:?})", number, hash);
+		let last_canonicalized = (hash.clone(), number);
+		commit.meta.inserted.push((to_meta_key(LAST_CANONICAL, &()), last_canonicalized.encode()));
+		self.last_canonicalized = Some(last_canonicalized);
+		Ok(())
+	}
+
 	/// Select a top-level root and canonicalized it. Discards all sibling subtrees and the root.
 	/// Returns a set of changes that need to be added to the DB.
 	pub fn canonicalize<E: fmt::Debug>(
@@ -163,6 +163,29 @@ impl<BlockHash: Hash, Key: Hash> RefWindow<BlockHash, Key> {
 		}
 	}
 
+	/// Drop all the blocks of the window and make it start at the block with the given number.
+	/// The nodes these blocks were going to delete are scheduled for deletion in `commit`, unless
+	/// `commit` inserts them again.
+	pub fn reset(&mut self, number: u64, commit: &mut CommitSet<Key>) {
+		trace!(target: "state-db", "Resetting pruning window to #{}", number);
+		let mut deleted = Vec::new();
+		{
+			let inserted: HashSet<&Key> = commit.data.inserted.iter().map(|(k, _)| k).collect();
+			for row in self.death_rows.drain(..) {
+				deleted.extend(row.deleted.into_iter().filter(|k| !inserted.contains(k)));
+				commit.meta.deleted.push(row.journal_key);
+			}
+		}
+		commit.data.deleted.extend(deleted);
+		if number > 0 {
+			commit.meta.inserted.push((to_meta_key(LAST_PRUNED, &()), (number - 1).encode()));
+		}
+		self.death_index.clear();
+		self.pending_number = number;
+		self.pending_canonicalizations = 0;
+		self.pending_prunings = 0;
+	}
+
 	/// Add a change set to the window. Creates a journal record and pushes it to `commit`
 	pub fn note_canonical(&mut self, hash: &BlockHash, commit: &mut CommitSet<Key>) {
 		trace!(target: "state-db", "Adding to pruning window: {:?} ({} inserted, {} deleted)", hash, commit.data.inserted.len(), commit.data.deleted.len());
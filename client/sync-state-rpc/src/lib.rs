@@ -0,0 +1,130 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A RPC handler to create sync states for light clients.
+//!
+//! The sync state is made of the header of the last finalized block, the BABE epoch changes and
+//! the GRANDPA authority set at that block. It is embedded in a chain spec, from which light
+//! clients can start syncing instead of starting from genesis.
+
+use codec::Encode;
+use jsonrpc_derive::rpc;
+use sc_chain_spec::LightSyncState;
+use sc_rpc_api::DenyUnsafe;
+use sp_blockchain::HeaderBackend;
+use sp_core::storage::StorageData;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, NumberFor}};
+use std::sync::Arc;
+
+type SharedAuthoritySet<TBl> =
+	sc_finality_grandpa::SharedAuthoritySet<<TBl as BlockT>::Hash, NumberFor<TBl>>;
+type SharedEpochChanges<TBl> = sc_consensus_epochs::SharedEpochChanges<TBl, sc_consensus_babe::Epoch>;
+
+/// Error code of the sync states that can't be created.
+const SYNC_STATE_ERROR_CODE: i64 = 1;
+
+#[derive(derive_more::Display, derive_more::From)]
+enum Error<Block: BlockT> {
+	#[display(fmt = "Failed to access the blockchain: {}", _0)]
+	Blockchain(sp_blockchain::Error),
+
+	#[display(fmt = "Failed to generate the chain spec: {}", _0)]
+	ChainSpec(String),
+
+	#[display(fmt = "Failed to parse the generated chain spec: {}", _0)]
+	JsonRpc(serde_json::Error),
+
+	#[display(fmt = "Failed to load the header of the block {:?}", _0)]
+	#[from(ignore)]
+	LoadingBlockHeaderFailed(BlockId<Block>),
+}
+
+impl<Block: BlockT> From<Error<Block>> for jsonrpc_core::Error {
+	fn from(error: Error<Block>) -> Self {
+		jsonrpc_core::Error {
+			message: error.to_string(),
+			code: jsonrpc_core::ErrorCode::ServerError(SYNC_STATE_ERROR_CODE),
+			data: None,
+		}
+	}
+}
+
+/// An api for sync state RPC calls.
+#[rpc]
+pub trait SyncStateRpcApi {
+	/// Returns the json-serialized chainspec running the node, with a sync state.
+	#[rpc(name = "sync_state_genSyncSpec", returns = "jsonrpc_core::Value")]
+	fn system_gen_sync_spec(&self, raw: bool) -> jsonrpc_core::Result<jsonrpc_core::Value>;
+}
+
+/// The handler for sync state RPC calls.
+pub struct SyncStateRpcHandler<TBl: BlockT, TCl> {
+	chain_spec: Box<dyn sc_chain_spec::ChainSpec>,
+	client: Arc<TCl>,
+	shared_authority_set: SharedAuthoritySet<TBl>,
+	shared_epoch_changes: SharedEpochChanges<TBl>,
+	deny_unsafe: DenyUnsafe,
+}
+
+impl<TBl, TCl> SyncStateRpcHandler<TBl, TCl>
+	where
+		TBl: BlockT,
+		TCl: HeaderBackend<TBl> + 'static,
+{
+	/// Create a new handler.
+	pub fn new(
+		chain_spec: Box<dyn sc_chain_spec::ChainSpec>,
+		client: Arc<TCl>,
+		shared_authority_set: SharedAuthoritySet<TBl>,
+		shared_epoch_changes: SharedEpochChanges<TBl>,
+		deny_unsafe: DenyUnsafe,
+	) -> Self {
+		Self {
+			chain_spec, client, shared_authority_set, shared_epoch_changes, deny_unsafe,
+		}
+	}
+
+	fn build_sync_state(&self) -> Result<LightSyncState, Error<TBl>> {
+		let finalized_hash = self.client.info().finalized_hash;
+		let finalized_header = self.client.header(BlockId::Hash(finalized_hash))?
+			.ok_or_else(|| Error::LoadingBlockHeaderFailed(BlockId::Hash(finalized_hash)))?;
+
+		Ok(LightSyncState {
+			finalized_block_header: StorageData(finalized_header.encode()),
+			babe_epoch_changes: StorageData(self.shared_epoch_changes.lock().encode()),
+			grandpa_authority_set: StorageData(self.shared_authority_set.clone_inner().encode()),
+		})
+	}
+}
+
+impl<TBl, TCl> SyncStateRpcApi for SyncStateRpcHandler<TBl, TCl>
+	where
+		TBl: BlockT,
+		TCl: HeaderBackend<TBl> + 'static,
+{
+	fn system_gen_sync_spec(&self, raw: bool) -> jsonrpc_core::Result<jsonrpc_core::Value> {
+		self.deny_unsafe.check_if_safe()?;
+
+		let mut chain_spec = self.chain_spec.cloned_box();
+		let sync_state = self.build_sync_state()?;
+		chain_spec.set_light_sync_state(sync_state);
+
+		let json_string = chain_spec.as_json(raw).map_err(Error::<TBl>::ChainSpec)?;
+		serde_json::from_str(&json_string).map_err(|e| Error::<TBl>::JsonRpc(e).into())
+	}
+}
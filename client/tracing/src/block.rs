@@ -0,0 +1,243 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Recording of the spans and events emitted while executing some code, for example while
+//! re-executing a block.
+//!
+//! Contrary to the [`ProfilingSubscriber`](crate::ProfilingSubscriber), which is installed
+//! globally and reports spans as they are closed, [`trace_with`] only records what is emitted on
+//! the current thread while running the given closure, and returns everything at once.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use tracing_core::{
+	dispatcher::{self, Dispatch},
+	event::Event,
+	Level,
+	metadata::Metadata,
+	span::{Attributes, Id, Record},
+	subscriber::Subscriber,
+};
+
+use crate::{Visitor, parse_target};
+
+/// Span recorded by [`trace_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedSpan {
+	/// Identifier of the span, unique among the spans of the same trace.
+	pub id: u64,
+	/// Identifier of the span this one was created in, if any.
+	pub parent_id: Option<u64>,
+	/// Name of the span.
+	pub name: String,
+	/// Target of the span.
+	pub target: String,
+	/// Line of the source file the span was created at.
+	pub line: u32,
+	/// Total time spent inside of the span.
+	pub overall_time: Duration,
+	/// Fields of the span, formatted with their `Debug` implementation.
+	pub values: Vec<(String, String)>,
+}
+
+/// Event recorded by [`trace_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedEvent {
+	/// Target of the event.
+	pub target: String,
+	/// Identifier of the span the event was emitted in, if any.
+	pub parent_id: Option<u64>,
+	/// Fields of the event, formatted with their `Debug` implementation.
+	pub values: Vec<(String, String)>,
+}
+
+/// Everything recorded by [`trace_with`], in the order the spans were created and the events
+/// emitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Traces {
+	/// Spans created.
+	pub spans: Vec<TracedSpan>,
+	/// Events emitted.
+	pub events: Vec<TracedEvent>,
+}
+
+/// Runs `f`, recording the spans and events emitted on the current thread in the meantime whose
+/// target matches one of `targets`.
+///
+/// `targets` is a comma separated list of targets, either with a level: "pallet=trace" or
+/// without: "pallet".
+pub fn trace_with<R>(targets: &str, f: impl FnOnce() -> R) -> (R, Traces) {
+	let state = Arc::new(Mutex::new(State::default()));
+	let subscriber = RecordingSubscriber {
+		targets: targets.split(',').map(parse_target).collect(),
+		next_id: AtomicU64::new(1),
+		state: state.clone(),
+	};
+
+	let result = dispatcher::with_default(&Dispatch::new(subscriber), f);
+
+	let mut state = state.lock();
+	let mut spans = state.spans.drain().map(|(_, span)| span.span).collect::<Vec<_>>();
+	spans.sort_by_key(|span| span.id);
+	let traces = Traces { spans, events: std::mem::take(&mut state.events) };
+	(result, traces)
+}
+
+struct RecordedSpan {
+	span: TracedSpan,
+	/// Time at which the span was last entered.
+	entered_at: Instant,
+}
+
+#[derive(Default)]
+struct State {
+	spans: HashMap<u64, RecordedSpan>,
+	/// Spans entered and not exited yet, the latest last.
+	entered: Vec<u64>,
+	events: Vec<TracedEvent>,
+}
+
+/// Subscriber recording everything into a shared [`State`].
+struct RecordingSubscriber {
+	targets: Vec<(String, Level)>,
+	next_id: AtomicU64,
+	state: Arc<Mutex<State>>,
+}
+
+impl RecordingSubscriber {
+	fn parent_id(
+		&self,
+		state: &State,
+		explicit: Option<&Id>,
+		is_contextual: bool,
+	) -> Option<u64> {
+		if let Some(parent) = explicit {
+			Some(parent.into_u64())
+		} else if is_contextual {
+			state.entered.last().cloned()
+		} else {
+			None
+		}
+	}
+}
+
+impl Subscriber for RecordingSubscriber {
+	fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+		self.targets.iter().any(|(target, level)| {
+			metadata.target().starts_with(target.as_str()) && metadata.level() <= level
+		})
+	}
+
+	fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let mut values = Visitor(Vec::new());
+		attrs.record(&mut values);
+
+		let mut state = self.state.lock();
+		let parent_id = self.parent_id(&state, attrs.parent(), attrs.is_contextual());
+		let span = TracedSpan {
+			id,
+			parent_id,
+			name: attrs.metadata().name().to_owned(),
+			target: attrs.metadata().target().to_owned(),
+			line: attrs.metadata().line().unwrap_or(0),
+			overall_time: Duration::from_nanos(0),
+			values: values.0,
+		};
+		state.spans.insert(id, RecordedSpan { span, entered_at: Instant::now() });
+		Id::from_u64(id)
+	}
+
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		let mut visitor = Visitor(Vec::new());
+		values.record(&mut visitor);
+		if let Some(recorded) = self.state.lock().spans.get_mut(&span.into_u64()) {
+			recorded.span.values.extend(visitor.0);
+		}
+	}
+
+	fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+	fn event(&self, event: &Event<'_>) {
+		let mut values = Visitor(Vec::new());
+		event.record(&mut values);
+
+		let mut state = self.state.lock();
+		let parent_id = self.parent_id(&state, event.parent(), event.is_contextual());
+		state.events.push(TracedEvent {
+			target: event.metadata().target().to_owned(),
+			parent_id,
+			values: values.0,
+		});
+	}
+
+	fn enter(&self, span: &Id) {
+		let mut state = self.state.lock();
+		state.entered.push(span.into_u64());
+		if let Some(recorded) = state.spans.get_mut(&span.into_u64()) {
+			recorded.entered_at = Instant::now();
+		}
+	}
+
+	fn exit(&self, span: &Id) {
+		let mut state = self.state.lock();
+		if let Some(pos) = state.entered.iter().rposition(|id| *id == span.into_u64()) {
+			state.entered.remove(pos);
+		}
+		if let Some(recorded) = state.spans.get_mut(&span.into_u64()) {
+			recorded.span.overall_time += recorded.entered_at.elapsed();
+		}
+	}
+
+	// Spans are kept until the end of the trace, so there is nothing to do when they are closed.
+	fn try_close(&self, _span: Id) -> bool {
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::trace_with;
+
+	#[test]
+	fn records_matching_spans_and_events() {
+		let (result, traces) = trace_with("test,state=debug", || {
+			let span = tracing::span!(target: "test", tracing::Level::INFO, "outer", n = 1);
+			let _guard = span.enter();
+			tracing::event!(target: "state", tracing::Level::DEBUG, key = "00");
+			// below the level of the target.
+			tracing::event!(target: "state", tracing::Level::TRACE, key = "01");
+			// not one of the targets.
+			tracing::event!(target: "other", tracing::Level::ERROR, key = "02");
+			42
+		});
+
+		assert_eq!(result, 42);
+		assert_eq!(traces.spans.len(), 1);
+		assert_eq!(traces.spans[0].name, "outer");
+		assert_eq!(traces.spans[0].parent_id, None);
+		assert_eq!(traces.spans[0].values, vec![("n".to_owned(), "1".to_owned())]);
+		assert_eq!(traces.events.len(), 1);
+		assert_eq!(traces.events[0].target, "state");
+		assert_eq!(traces.events[0].parent_id, Some(traces.spans[0].id));
+	}
+}
@@ -24,6 +24,11 @@
 //!
 //! Currently we provide `Log` (default), `Telemetry` variants for `Receiver`
 
+mod block;
+pub mod logging;
+
+pub use block::{trace_with, Traces, TracedEvent, TracedSpan};
+
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -0,0 +1,262 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Logger of the node, writing to the console and optionally to a rotated file, whose filter
+//! can be changed while the node is running.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use lazy_static::lazy_static;
+use log::{Log, Metadata, Record};
+use parking_lot::{Mutex, RwLock};
+
+lazy_static! {
+	/// The logger registered by `init_logger`, if any.
+	static ref LOGGER: RwLock<Option<&'static Logger>> = RwLock::new(None);
+}
+
+/// Rotation of the log file.
+#[derive(Debug, Clone, Default)]
+pub struct LogRotation {
+	/// Rotate the file once it would exceed this size, in bytes.
+	pub max_size: Option<u64>,
+	/// Rotate the file once it has been written to for this long.
+	pub max_age: Option<Duration>,
+	/// Number of rotated files kept, as `<path>.1`, the most recent, to `<path>.<keep>`.
+	pub keep: usize,
+}
+
+/// File the logs are written to, besides the console.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+	/// Path of the file, created if missing and appended to otherwise.
+	pub path: PathBuf,
+	/// Rotation of the file.
+	pub rotation: LogRotation,
+}
+
+/// Error initializing the logger.
+#[derive(Debug)]
+pub enum InitError {
+	/// There is already a global logger registered.
+	AlreadyRegistered,
+	/// The log file couldn't be opened.
+	LogFile(io::Error),
+}
+
+impl fmt::Display for InitError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InitError::AlreadyRegistered => write!(f, "There is already a global logger registered"),
+			InitError::LogFile(err) => write!(f, "Failed to open the log file: {}", err),
+		}
+	}
+}
+
+impl std::error::Error for InitError {}
+
+/// Registers the global logger.
+///
+/// `directives` are the filter directives, with the syntax of `RUST_LOG`. The records matching
+/// them are passed to `console`, and written to `file`, if any, as formatted by `format`.
+pub fn init_logger<C, F>(directives: String, console: C, format: F, file: Option<LogFile>) -> Result<(), InitError>
+where
+	C: Fn(&Record) + Send + Sync + 'static,
+	F: Fn(&Record) -> String + Send + Sync + 'static,
+{
+	let file = match file {
+		Some(LogFile { path, rotation }) => Some(
+			RotatingFile::open(path, rotation).map_err(InitError::LogFile)?
+		),
+		None => None,
+	};
+	let filter = build_filter(&directives);
+	let max_level = filter.filter();
+	let logger: &'static Logger = Box::leak(Box::new(Logger {
+		directives,
+		filter: RwLock::new(filter),
+		console: Box::new(console),
+		format: Box::new(format),
+		file: file.map(Mutex::new),
+	}));
+
+	log::set_logger(logger).map_err(|_| InitError::AlreadyRegistered)?;
+	log::set_max_level(max_level);
+	*LOGGER.write() = Some(logger);
+	Ok(())
+}
+
+/// Adds `directives`, with the syntax of `RUST_LOG`, to the ones the logger was initialized
+/// with, replacing the ones of a previous call.
+pub fn set_log_filter(directives: &str) -> Result<(), String> {
+	let logger = LOGGER.read().ok_or("The logger of the node isn't registered")?;
+	logger.set_filter(&format!("{},{}", logger.directives, directives));
+	Ok(())
+}
+
+/// Restores the filter directives the logger was initialized with, and reopens the log file,
+/// e.g. once it has been moved by an external tool.
+pub fn reload_logger() -> Result<(), String> {
+	let logger = LOGGER.read().ok_or("The logger of the node isn't registered")?;
+	logger.set_filter(&logger.directives);
+	if let Some(file) = &logger.file {
+		file.lock().reopen().map_err(|e| format!("Failed to reopen the log file: {}", e))?;
+	}
+	Ok(())
+}
+
+fn build_filter(directives: &str) -> Filter {
+	FilterBuilder::new().parse(directives).build()
+}
+
+/// Logger whose filter can be replaced.
+struct Logger {
+	/// Filter directives the logger was initialized with.
+	directives: String,
+	filter: RwLock<Filter>,
+	console: Box<dyn Fn(&Record) + Send + Sync>,
+	format: Box<dyn Fn(&Record) -> String + Send + Sync>,
+	file: Option<Mutex<RotatingFile>>,
+}
+
+impl Logger {
+	fn set_filter(&self, directives: &str) {
+		let filter = build_filter(directives);
+		log::set_max_level(filter.filter());
+		*self.filter.write() = filter;
+	}
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		self.filter.read().enabled(metadata)
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.filter.read().matches(record) {
+			return
+		}
+		(self.console)(record);
+		if let Some(file) = &self.file {
+			if let Err(e) = file.lock().write_line(&(self.format)(record)) {
+				let _ = writeln!(io::stderr(), "Failed to write to the log file: {}", e);
+			}
+		}
+	}
+
+	fn flush(&self) {
+		if let Some(file) = &self.file {
+			let _ = file.lock().file.flush();
+		}
+	}
+}
+
+/// File rotated according to a `LogRotation`.
+struct RotatingFile {
+	path: PathBuf,
+	rotation: LogRotation,
+	file: File,
+	/// Size of the file, in bytes.
+	size: u64,
+	/// When the file was opened.
+	opened_at: Instant,
+}
+
+impl RotatingFile {
+	fn open(path: PathBuf, rotation: LogRotation) -> io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(&path)?;
+		let size = file.metadata()?.len();
+		Ok(RotatingFile { path, rotation, file, size, opened_at: Instant::now() })
+	}
+
+	fn reopen(&mut self) -> io::Result<()> {
+		*self = RotatingFile::open(self.path.clone(), self.rotation.clone())?;
+		Ok(())
+	}
+
+	/// Moves `<path>.<n>` to `<path>.<n + 1>`, dropping the oldest file, and the current file
+	/// to `<path>.1`, then starts a new file.
+	fn rotate(&mut self) -> io::Result<()> {
+		self.file.flush()?;
+		if self.rotation.keep == 0 {
+			fs::remove_file(&self.path)?;
+		} else {
+			for index in (1..self.rotation.keep).rev() {
+				let from = rotated_path(&self.path, index);
+				if from.exists() {
+					fs::rename(&from, rotated_path(&self.path, index + 1))?;
+				}
+			}
+			fs::rename(&self.path, rotated_path(&self.path, 1))?;
+		}
+		self.reopen()
+	}
+
+	fn write_line(&mut self, line: &str) -> io::Result<()> {
+		let len = line.len() as u64 + 1;
+		let expired = self.rotation.max_age.map_or(false, |age| self.opened_at.elapsed() >= age);
+		let full = self.rotation.max_size.map_or(false, |max| self.size > 0 && self.size + len > max);
+		if expired || full {
+			self.rotate()?;
+		}
+		writeln!(self.file, "{}", line)?;
+		self.size += len;
+		Ok(())
+	}
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+	let mut path = path.as_os_str().to_owned();
+	path.push(format!(".{}", index));
+	path.into()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn files_are_rotated_by_size() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("node.log");
+		let rotation = LogRotation { max_size: Some(10), max_age: None, keep: 2 };
+		let mut file = RotatingFile::open(path.clone(), rotation).unwrap();
+
+		for line in &["first", "second", "third", "fourth"] {
+			file.write_line(line).unwrap();
+		}
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+		assert_eq!(fs::read_to_string(rotated_path(&path, 1)).unwrap(), "third\n");
+		assert_eq!(fs::read_to_string(rotated_path(&path, 2)).unwrap(), "second\n");
+		assert!(!rotated_path(&path, 3).exists());
+	}
+
+	#[test]
+	fn filters_are_built_from_directives() {
+		let filter = build_filter("info,sync=trace,sync=debug");
+		assert_eq!(filter.filter(), log::LevelFilter::Debug);
+		let metadata = Metadata::builder().target("sync").level(log::Level::Trace).build();
+		assert!(!filter.enabled(&metadata));
+	}
+}
@@ -25,21 +25,28 @@ use serde::Serialize;
 use crate::{watcher, ChainApi, BlockHash};
 use log::{debug, trace, warn};
 use sp_runtime::traits;
+use sp_transaction_pool::{DroppedReason, InvalidReason, PoolEvent, PoolEventKind, PoolEventStream};
+use futures::channel::mpsc;
 
 /// Extrinsic pool default listener.
 pub struct Listener<H: hash::Hash + Eq + Debug, C: ChainApi> {
 	watchers: HashMap<H, watcher::Sender<H, BlockHash<C>>>,
 	finality_watchers: LinkedHashMap<BlockHash<C>, Vec<H>>,
+	event_sinks: Vec<mpsc::Sender<PoolEvent<H, BlockHash<C>>>>,
 }
 
 /// Maximum number of blocks awaiting finality at any time.
 const MAX_FINALITY_WATCHERS: usize = 512;
 
+/// Maximum number of events buffered by a stream of the events of all the transactions.
+pub(crate) const EVENT_STREAM_BUFFER: usize = 4096;
+
 impl<H: hash::Hash + Eq + Debug, C: ChainApi> Default for Listener<H, C> {
 	fn default() -> Self {
 		Listener {
 			watchers: Default::default(),
 			finality_watchers: Default::default(),
+			event_sinks: Default::default(),
 		}
 	}
 }
@@ -58,6 +65,34 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		}
 	}
 
+	fn notify(&mut self, hash: &H, kind: PoolEventKind<H, BlockHash<C>>) {
+		if self.event_sinks.is_empty() {
+			return
+		}
+
+		let event = PoolEvent { hash: hash.clone(), kind };
+		self.event_sinks = std::mem::take(&mut self.event_sinks)
+			.into_iter()
+			.filter_map(|mut sink| match sink.try_send(event.clone()) {
+				Ok(()) => Some(sink),
+				Err(err) if err.is_full() => {
+					debug!(target: "txpool", "[{:?}] Event stream is full, dropping the event", hash);
+					Some(sink)
+				},
+				Err(_) => None,
+			})
+			.collect();
+	}
+
+	/// Creates a new stream of the events of all the transactions.
+	///
+	/// The events are dropped while the stream holds more than `EVENT_STREAM_BUFFER` of them.
+	pub fn create_event_stream(&mut self) -> PoolEventStream<H, BlockHash<C>> {
+		let (sink, stream) = mpsc::channel(EVENT_STREAM_BUFFER);
+		self.event_sinks.push(sink);
+		stream
+	}
+
 	/// Creates a new watcher for given verified extrinsic.
 	///
 	/// The watcher can be used to subscribe to life-cycle events of that extrinsic.
@@ -72,12 +107,19 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		self.fire(hash, |watcher| watcher.broadcast(peers));
 	}
 
+	/// New transaction was imported to the pool.
+	pub fn imported(&mut self, tx: &H) {
+		self.notify(tx, PoolEventKind::Imported);
+	}
+
 	/// New transaction was added to the ready pool or promoted from the future pool.
 	pub fn ready(&mut self, tx: &H, old: Option<&H>) {
 		trace!(target: "txpool", "[{:?}] Ready (replaced with {:?})", tx, old);
 		self.fire(tx, |watcher| watcher.ready());
+		self.notify(tx, PoolEventKind::Ready);
 		if let Some(old) = old {
 			self.fire(old, |watcher| watcher.usurped(tx.clone()));
+			self.notify(old, PoolEventKind::Usurped(tx.clone()));
 		}
 	}
 
@@ -85,37 +127,46 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 	pub fn future(&mut self, tx: &H) {
 		trace!(target: "txpool", "[{:?}] Future", tx);
 		self.fire(tx, |watcher| watcher.future());
+		self.notify(tx, PoolEventKind::Future);
+	}
+
+	/// Transaction was replaced in the pool by another one.
+	pub fn usurped(&mut self, tx: &H, by: &H) {
+		trace!(target: "txpool", "[{:?}] Usurped by {:?}", tx, by);
+		self.fire(tx, |watcher| watcher.usurped(by.clone()));
+		self.notify(tx, PoolEventKind::Usurped(by.clone()));
 	}
 
-	/// Transaction was dropped from the pool because of the limit.
-	pub fn dropped(&mut self, tx: &H, by: Option<&H>) {
-		trace!(target: "txpool", "[{:?}] Dropped (replaced with {:?})", tx, by);
-		self.fire(tx, |watcher| match by {
-			Some(t) => watcher.usurped(t.clone()),
-			None => watcher.dropped(),
-		})
+	/// Transaction was dropped from the pool.
+	pub fn dropped(&mut self, tx: &H, reason: DroppedReason) {
+		trace!(target: "txpool", "[{:?}] Dropped ({:?})", tx, reason);
+		self.fire(tx, |watcher| watcher.dropped());
+		self.notify(tx, PoolEventKind::Dropped(reason));
 	}
 
 	/// Transaction was removed as invalid.
-	pub fn invalid(&mut self, tx: &H, warn: bool) {
+	pub fn invalid(&mut self, tx: &H, reason: InvalidReason, warn: bool) {
 		if warn {
 			warn!(target: "txpool", "[{:?}] Extrinsic invalid", tx);
 		} else {
 			debug!(target: "txpool", "[{:?}] Extrinsic invalid", tx);
 		}
 		self.fire(tx, |watcher| watcher.invalid());
+		self.notify(tx, PoolEventKind::Invalid(reason));
 	}
 
 	/// Transaction was pruned from the pool.
 	pub fn pruned(&mut self, block_hash: BlockHash<C>, tx: &H) {
 		debug!(target: "txpool", "[{:?}] Pruned at {:?}", tx, block_hash);
 		self.fire(tx, |s| s.in_block(block_hash));
+		self.notify(tx, PoolEventKind::InBlock(block_hash));
 		self.finality_watchers.entry(block_hash).or_insert(vec![]).push(tx.clone());
 
 		while self.finality_watchers.len() > MAX_FINALITY_WATCHERS {
 			if let Some((hash, txs)) = self.finality_watchers.pop_front() {
 				for tx in txs {
 					self.fire(&tx, |s| s.finality_timeout(hash.clone()));
+					self.notify(&tx, PoolEventKind::FinalityTimeout(hash.clone()));
 				}
 			}
 		}
@@ -125,7 +176,8 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 	pub fn retracted(&mut self, block_hash: BlockHash<C>) {
 		if let Some(hashes) = self.finality_watchers.remove(&block_hash) {
 			for hash in hashes {
-				self.fire(&hash, |s| s.retracted(block_hash))
+				self.fire(&hash, |s| s.retracted(block_hash));
+				self.notify(&hash, PoolEventKind::Retracted(block_hash));
 			}
 		}
 	}
@@ -135,7 +187,8 @@ impl<H: hash::Hash + traits::Member + Serialize, C: ChainApi> Listener<H, C> {
 		if let Some(hashes) = self.finality_watchers.remove(&block_hash) {
 			for hash in hashes {
 				log::debug!(target: "txpool", "[{:?}] Sent finalization event (block {:?})", hash, block_hash);
-				self.fire(&hash, |s| s.finalized(block_hash))
+				self.fire(&hash, |s| s.finalized(block_hash));
+				self.notify(&hash, PoolEventKind::Finalized(block_hash));
 			}
 		}
 	}
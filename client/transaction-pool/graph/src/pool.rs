@@ -443,7 +443,8 @@ mod tests {
 	use parking_lot::Mutex;
 	use futures::executor::block_on;
 	use super::*;
-	use sp_transaction_pool::TransactionStatus;
+	use sp_transaction_pool::{InvalidReason, PoolEvent, PoolEventKind, TransactionStatus};
+	use crate::listener::{Listener, EVENT_STREAM_BUFFER};
 	use sp_runtime::transaction_validity::{ValidTransaction, InvalidTransaction, TransactionSource};
 	use codec::Encode;
 	use substrate_test_runtime::{Block, Extrinsic, Transfer, H256, AccountId};
@@ -874,6 +875,74 @@ mod tests {
 			assert_eq!(stream.next(), None);
 		}
 
+		#[test]
+		fn should_stream_events_of_all_transactions() {
+			// given
+			let pool = pool();
+			let events = pool.validated_pool().event_stream();
+			let hash = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 0,
+			}))).unwrap();
+
+			// when
+			block_on(pool.prune_tags(&BlockId::Number(2), vec![vec![0u8]], vec![])).unwrap();
+			let block_hash = H256::from_low_u64_be(2).into();
+			block_on(pool.validated_pool().on_block_finalized(block_hash)).unwrap();
+
+			// then
+			let event = |kind| PoolEvent { hash, kind };
+			let mut stream = futures::executor::block_on_stream(events);
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Imported)));
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Ready)));
+			assert_eq!(stream.next(), Some(event(PoolEventKind::InBlock(block_hash))));
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Finalized(block_hash))));
+		}
+
+		#[test]
+		fn should_stream_the_reason_of_invalid_transactions() {
+			// given
+			let pool = pool();
+			let events = pool.validated_pool().event_stream();
+			let hash = block_on(pool.submit_one(&BlockId::Number(0), SOURCE, uxt(Transfer {
+				from: AccountId::from_h256(H256::from_low_u64_be(1)),
+				to: AccountId::from_h256(H256::from_low_u64_be(2)),
+				amount: 5,
+				nonce: 0,
+			}))).unwrap();
+
+			// when
+			pool.validated_pool().remove_invalid(&[hash]);
+
+			// then
+			let event = |kind| PoolEvent { hash, kind };
+			let mut stream = futures::executor::block_on_stream(events);
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Imported)));
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Ready)));
+			assert_eq!(stream.next(), Some(event(PoolEventKind::Invalid(InvalidReason::Reported))));
+		}
+
+		#[test]
+		fn should_drop_events_of_full_streams() {
+			// given
+			let mut listener = Listener::<u64, TestApi>::default();
+			let events = listener.create_event_stream();
+
+			// when
+			for hash in 0..EVENT_STREAM_BUFFER as u64 + 10 {
+				listener.future(&hash);
+			}
+			drop(listener);
+
+			// then
+			let events = futures::executor::block_on_stream(events).collect::<Vec<_>>();
+			// the channel holds one more event per sender than its buffer.
+			assert_eq!(events.len(), EVENT_STREAM_BUFFER + 1);
+			assert_eq!(events[0], PoolEvent { hash: 0, kind: PoolEventKind::Future });
+		}
+
 		#[test]
 		fn should_trigger_broadcasted() {
 			// given
@@ -34,7 +34,7 @@ use sp_runtime::{
 	traits::{self, SaturatedConversion},
 	transaction_validity::{TransactionTag as Tag, ValidTransaction, TransactionSource},
 };
-use sp_transaction_pool::{error, PoolStatus};
+use sp_transaction_pool::{error, DroppedReason, InvalidReason, PoolEventStream, PoolStatus};
 use wasm_timer::Instant;
 use sp_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
 
@@ -169,6 +169,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				}
 
 				let mut listener = self.listener.write();
+				listener.imported(imported.hash());
 				fire_events(&mut *listener, &imported);
 				Ok(imported.hash().clone())
 			},
@@ -177,7 +178,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				Err(err.into())
 			},
 			ValidatedTransaction::Unknown(hash, err) => {
-				self.listener.write().invalid(&hash, false);
+				self.listener.write().invalid(&hash, InvalidReason::UnknownValidity, false);
 				Err(err.into())
 			},
 		}
@@ -215,7 +216,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 			// run notifications
 			let mut listener = self.listener.write();
 			for h in &removed {
-				listener.dropped(h, None);
+				listener.dropped(h, DroppedReason::LimitsExceeded);
 			}
 
 			removed
@@ -252,7 +253,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 	/// Transactions that are missing from the pool are not submitted.
 	pub fn resubmit(&self, mut updated_transactions: HashMap<ExHash<B>, ValidatedTransactionFor<B>>) {
 		#[derive(Debug, Clone, Copy, PartialEq)]
-		enum Status { Future, Ready, Failed, Dropped };
+		enum Status { Future, Ready, Failed(InvalidReason), Dropped(DroppedReason) };
 
 		let (mut initial_statuses, final_statuses) = {
 			let mut pool = self.pool.write();
@@ -312,10 +313,13 @@ impl<B: ChainApi> ValidatedPool<B> {
 										final_statuses.insert(hash, Status::Ready);
 									}
 									for hash in failed {
-										final_statuses.insert(hash, Status::Failed);
+										final_statuses.insert(hash, Status::Failed(InvalidReason::PromotionFailed));
 									}
 									for tx in removed {
-										final_statuses.insert(tx.hash.clone(), Status::Dropped);
+										final_statuses.insert(
+											tx.hash.clone(),
+											Status::Dropped(DroppedReason::Replaced),
+										);
 									}
 								},
 								base::Imported::Future { .. } => {
@@ -332,11 +336,14 @@ impl<B: ChainApi> ValidatedPool<B> {
 									hash,
 									err,
 								);
-								final_statuses.insert(hash, Status::Failed);
+								final_statuses.insert(hash, Status::Failed(InvalidReason::Revalidation));
 							},
 						},
-						ValidatedTransaction::Invalid(_, _) | ValidatedTransaction::Unknown(_, _) => {
-							final_statuses.insert(hash, Status::Failed);
+						ValidatedTransaction::Invalid(_, _) => {
+							final_statuses.insert(hash, Status::Failed(InvalidReason::Revalidation));
+						},
+						ValidatedTransaction::Unknown(_, _) => {
+							final_statuses.insert(hash, Status::Failed(InvalidReason::UnknownValidity));
 						},
 					}
 				}
@@ -345,7 +352,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 				// queue, updating final statuses as required
 				if reject_future_transactions {
 					for future_tx in pool.clear_future() {
-						final_statuses.insert(future_tx.hash.clone(), Status::Dropped);
+						final_statuses.insert(
+							future_tx.hash.clone(),
+							Status::Dropped(DroppedReason::FutureRejected),
+						);
 					}
 				}
 
@@ -361,8 +371,8 @@ impl<B: ChainApi> ValidatedPool<B> {
 				match final_status {
 					Status::Future => listener.future(&hash),
 					Status::Ready => listener.ready(&hash, None),
-					Status::Dropped => listener.dropped(&hash, None),
-					Status::Failed => listener.invalid(&hash, initial_status.is_some()),
+					Status::Dropped(reason) => listener.dropped(&hash, reason),
+					Status::Failed(reason) => listener.invalid(&hash, reason, initial_status.is_some()),
 				}
 			}
 		}
@@ -397,7 +407,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 				fire_events(&mut *listener, promoted);
 			}
 			for f in &status.failed {
-				listener.dropped(f, None);
+				listener.dropped(f, DroppedReason::ReimportFailed);
 			}
 		}
 
@@ -484,8 +494,8 @@ impl<B: ChainApi> ValidatedPool<B> {
 			hashes
 		};
 		// removing old transactions
-		self.remove_invalid(&to_remove);
-		self.remove_invalid(&futures_to_remove);
+		self.remove_subtrees(&to_remove, InvalidReason::Stale);
+		self.remove_subtrees(&futures_to_remove, InvalidReason::Stale);
 		// clear banned transactions timeouts
 		self.rotator.clear_timeouts(&now);
 
@@ -513,6 +523,11 @@ impl<B: ChainApi> ValidatedPool<B> {
 		stream
 	}
 
+	/// Return an event stream of the life-cycle events of all the transactions of the pool.
+	pub fn event_stream(&self) -> PoolEventStream<ExHash<B>, BlockHash<B>> {
+		self.listener.write().create_event_stream()
+	}
+
 	/// Invoked when extrinsics are broadcasted.
 	pub fn on_broadcasted(&self, propagated: HashMap<ExHash<B>, Vec<String>>) {
 		let mut listener = self.listener.write();
@@ -528,6 +543,10 @@ impl<B: ChainApi> ValidatedPool<B> {
 	/// Note this is not the case for the dependent transactions - those may
 	/// still be valid so we want to be able to re-import them.
 	pub fn remove_invalid(&self, hashes: &[ExHash<B>]) -> Vec<TransactionFor<B>> {
+		self.remove_subtrees(hashes, InvalidReason::Reported)
+	}
+
+	fn remove_subtrees(&self, hashes: &[ExHash<B>], reason: InvalidReason) -> Vec<TransactionFor<B>> {
 		// early exit in case there is no invalid transactions.
 		if hashes.is_empty() {
 			return vec![];
@@ -544,7 +563,7 @@ impl<B: ChainApi> ValidatedPool<B> {
 
 		let mut listener = self.listener.write();
 		for tx in &invalid {
-			listener.invalid(&tx.hash, true);
+			listener.invalid(&tx.hash, reason, true);
 		}
 
 		invalid
@@ -584,10 +603,10 @@ fn fire_events<H, B, Ex>(
 		base::Imported::Ready { ref promoted, ref failed, ref removed, ref hash } => {
 			listener.ready(hash, None);
 			for f in failed {
-				listener.invalid(f, true);
+				listener.invalid(f, InvalidReason::PromotionFailed, true);
 			}
 			for r in removed {
-				listener.dropped(&r.hash, Some(hash));
+				listener.usurped(&r.hash, hash);
 			}
 			for p in promoted {
 				listener.ready(p, None);
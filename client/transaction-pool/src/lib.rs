@@ -43,7 +43,7 @@ use sp_runtime::{
 	traits::{Block as BlockT, NumberFor, AtLeast32Bit, Extrinsic, Zero},
 };
 use sp_transaction_pool::{
-	TransactionPool, PoolStatus, ImportNotificationStream, TxHash, TransactionFor,
+	TransactionPool, PoolStatus, ImportNotificationStream, PoolEventStreamFor, TxHash, TransactionFor,
 	TransactionStatusStreamFor, MaintainedTransactionPool, PoolFuture, ChainEvent,
 	TransactionSource,
 };
@@ -311,6 +311,10 @@ impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
 		self.pool.validated_pool().import_notification_stream()
 	}
 
+	fn event_stream(&self) -> PoolEventStreamFor<Self> {
+		self.pool.validated_pool().event_stream()
+	}
+
 	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
 		self.pool.hash_of(xt)
 	}
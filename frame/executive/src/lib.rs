@@ -233,10 +233,7 @@ where
 		digest: &Digest<System::Hash>,
 	) {
 		if Self::runtime_upgraded() {
-			// System is not part of `AllModules`, so we need to call this manually.
-			let mut weight = <frame_system::Module::<System> as OnRuntimeUpgrade>::on_runtime_upgrade();
-			weight = weight.saturating_add(COnRuntimeUpgrade::on_runtime_upgrade());
-			weight = weight.saturating_add(<AllModules as OnRuntimeUpgrade>::on_runtime_upgrade());
+			let weight = Self::execute_on_runtime_upgrade();
 			<frame_system::Module<System>>::register_extra_weight_unchecked(weight, DispatchClass::Mandatory);
 		}
 		<frame_system::Module<System>>::initialize(
@@ -254,6 +251,23 @@ where
 		frame_system::Module::<System>::note_finished_initialize();
 	}
 
+	/// Execute all `OnRuntimeUpgrade` of this runtime, and return the aggregate weight.
+	fn execute_on_runtime_upgrade() -> frame_support::weights::Weight {
+		// System is not part of `AllModules`, so we need to call this manually.
+		let mut weight = <frame_system::Module::<System> as OnRuntimeUpgrade>::on_runtime_upgrade();
+		weight = weight.saturating_add(COnRuntimeUpgrade::on_runtime_upgrade());
+		weight.saturating_add(<AllModules as OnRuntimeUpgrade>::on_runtime_upgrade())
+	}
+
+	/// Execute all `OnRuntimeUpgrade` of this runtime, regardless of whether the runtime was
+	/// upgraded, and return the aggregate weight.
+	///
+	/// Only meant for testing the runtime upgrades against a real state, see `frame-try-runtime`.
+	#[cfg(feature = "try-runtime")]
+	pub fn try_runtime_upgrade() -> frame_support::weights::Weight {
+		Self::execute_on_runtime_upgrade()
+	}
+
 	/// Returns if the runtime was upgraded since the last time this function was called.
 	fn runtime_upgraded() -> bool {
 		let last = frame_system::LastRuntimeUpgrade::get();
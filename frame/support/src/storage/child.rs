@@ -24,6 +24,7 @@
 use crate::sp_std::prelude::*;
 use codec::{Codec, Encode, Decode};
 pub use sp_core::storage::{ChildInfo, ChildType};
+pub use sp_io::ChildStorageKillProgress;
 
 /// Return the value of the item in storage under `key`, or `None` if there is no explicit entry.
 pub fn get<T: Decode + Sized>(
@@ -158,6 +159,24 @@ pub fn kill_storage(
 	}
 }
 
+/// Remove at most `limit` key/values of `storage_key`, starting from the key `from`.
+///
+/// The `next_key` of the returned progress is the `from` of the next batch, the removal is
+/// complete once it is `None`.
+pub fn kill_storage_limited(
+	child_info: &ChildInfo,
+	from: &[u8],
+	limit: u32,
+) -> ChildStorageKillProgress {
+	match child_info.child_type() {
+		ChildType::ParentKeyId => sp_io::default_child_storage::storage_kill_limited(
+			child_info.storage_key(),
+			from,
+			limit,
+		),
+	}
+}
+
 /// Ensure `key` has no explicit entry in storage.
 pub fn kill(
 	child_info: &ChildInfo,
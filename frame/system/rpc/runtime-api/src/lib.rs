@@ -23,6 +23,23 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+use sp_std::prelude::*;
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+
+/// Parts of an extrinsic, as seen by the runtime.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct ExtrinsicParts {
+	/// Name of the pallet of the call.
+	pub pallet: Vec<u8>,
+	/// Name of the call.
+	pub call: Vec<u8>,
+	/// SCALE encoded arguments of the call.
+	pub args: Vec<u8>,
+	/// SCALE encoded address of the signer, if the extrinsic is signed.
+	pub signer: Option<Vec<u8>>,
+}
+
 sp_api::decl_runtime_apis! {
 	/// The API to query account nonce (aka transaction index).
 	pub trait AccountNonceApi<AccountId, Index> where
@@ -32,4 +49,12 @@ sp_api::decl_runtime_apis! {
 		/// Get current account nonce of given `AccountId`.
 		fn account_nonce(account: AccountId) -> Index;
 	}
+
+	/// The API to split extrinsics into their parts.
+	pub trait ExtrinsicDecodeApi<Extrinsic> where
+		Extrinsic: codec::Codec,
+	{
+		/// Get the names of the call of `extrinsic`, its arguments and its signer.
+		fn decode_extrinsic(extrinsic: Extrinsic) -> ExtrinsicParts;
+	}
 }
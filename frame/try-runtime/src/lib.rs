@@ -0,0 +1,37 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API for testing the runtime upgrades of a FRAME runtime against a real state, without
+//! producing a block.
+//!
+//! The API is meant to be implemented only by the runtimes built for testing, see the
+//! `try-runtime` subcommand of the node.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use frame_support::weights::Weight;
+
+sp_api::decl_runtime_apis! {
+	/// Runtime api for testing the runtime upgrades of a FRAME runtime.
+	pub trait TryRuntime {
+		/// Executes the `on_runtime_upgrade` hooks of the runtime, as the first block after an
+		/// upgrade would.
+		///
+		/// Returns the weight consumed by the hooks, and the maximum weight of a block.
+		fn on_runtime_upgrade() -> (Weight, Weight);
+	}
+}
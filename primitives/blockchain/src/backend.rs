@@ -99,6 +99,24 @@ pub trait Backend<Block: BlockT>: HeaderBackend<Block> + HeaderMetadata<Block, E
 	/// Return hashes of all blocks that are children of the block with `parent_hash`.
 	fn children(&self, parent_hash: Block::Hash) -> Result<Vec<Block::Hash>>;
 
+	/// Returns the hash of the canonical block including the extrinsic with the given hash, and the
+	/// index of the extrinsic in its body. Returns `None` if the extrinsic is not indexed.
+	fn extrinsic_location(&self, _hash: &Block::Hash) -> Result<Option<(Block::Hash, u32)>> {
+		Ok(None)
+	}
+
+	/// Returns the numbers and hashes of the blocks numbered from `first` to `last` that changed
+	/// the entry `key` of the top storage, including the blocks of every fork, ordered by number.
+	/// Returns `None` if the changes of some of these blocks are not indexed.
+	fn storage_changes(
+		&self,
+		_first: NumberFor<Block>,
+		_last: NumberFor<Block>,
+		_key: &[u8],
+	) -> Result<Option<Vec<(NumberFor<Block>, Block::Hash)>>> {
+		Ok(None)
+	}
+
 	/// Get the most recent block hash of the best (longest) chains
 	/// that contain block with the given `target_hash`.
 	///
@@ -113,6 +113,19 @@ pub struct BlockCheckParams<Block: BlockT> {
 	pub import_existing: bool,
 }
 
+/// State of a block downloaded as a whole from the network, rather than obtained by executing the
+/// block on top of the state of its parent.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ImportedState<B: BlockT> {
+	/// Hash of the block the state belongs to.
+	pub block: B::Hash,
+	/// Key-value pairs of the top trie, excluding the entries holding the roots of child tries.
+	pub top: Vec<(Vec<u8>, Vec<u8>)>,
+	/// Key-value pairs of each default child trie, along with the storage key of the child trie
+	/// (without the `:child_storage:default:` prefix).
+	pub children: Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>,
+}
+
 /// Data required to import a Block.
 #[non_exhaustive]
 pub struct BlockImportParams<Block: BlockT, Transaction> {
@@ -166,6 +179,9 @@ pub struct BlockImportParams<Block: BlockT, Transaction> {
 	pub import_existing: bool,
 	/// Cached full header hash (with post-digests applied).
 	pub post_hash: Option<Block::Hash>,
+	/// Complete state of the block, imported instead of executing the block. The parent of such a
+	/// block doesn't need to be known, and the block is imported as finalized.
+	pub state: Option<ImportedState<Block>>,
 }
 
 impl<Block: BlockT, Transaction> BlockImportParams<Block, Transaction> {
@@ -187,6 +203,7 @@ impl<Block: BlockT, Transaction> BlockImportParams<Block, Transaction> {
 			allow_missing_state: false,
 			import_existing: false,
 			post_hash: None,
+			state: None,
 		}
 	}
 
@@ -227,6 +244,7 @@ impl<Block: BlockT, Transaction> BlockImportParams<Block, Transaction> {
 			fork_choice: self.fork_choice,
 			import_existing: self.import_existing,
 			post_hash: self.post_hash,
+			state: self.state,
 		}
 	}
 
@@ -17,7 +17,7 @@
 //! Block announcement validation.
 
 use crate::BlockStatus;
-use sp_runtime::{generic::BlockId, traits::Block};
+use sp_runtime::{generic::BlockId, traits::{Block, NumberFor}};
 use std::{error::Error, sync::Arc};
 
 /// A type which provides access to chain information.
@@ -41,10 +41,34 @@ pub enum Validation {
 	Failure,
 }
 
+/// Position of a peer on its chain, as reported by the peer when opening the block announces
+/// substream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerChainInfo<B: Block> {
+	/// Genesis block hash.
+	pub genesis_hash: B::Hash,
+	/// Best block number.
+	pub best_number: NumberFor<B>,
+	/// Best block hash.
+	pub best_hash: B::Hash,
+	/// Finalized block number.
+	pub finalized_number: NumberFor<B>,
+	/// Finalized block hash.
+	pub finalized_hash: B::Hash,
+}
+
 /// Type which checks incoming block announcements.
 pub trait BlockAnnounceValidator<B: Block> {
 	/// Validate the announced header and its associated data.
 	fn validate(&mut self, header: &B::Header, data: &[u8]) -> Result<Validation, Box<dyn Error + Send>>;
+
+	/// Validate the chain position a peer reported when opening the block announces substream.
+	///
+	/// Peers failing the validation are disconnected. The genesis hash has already been checked
+	/// against ours at this point.
+	fn validate_handshake(&mut self, _info: &PeerChainInfo<B>) -> Result<Validation, Box<dyn Error + Send>> {
+		Ok(Validation::Success)
+	}
 }
 
 /// Default implementation of `BlockAnnounceValidator`.
@@ -24,14 +24,16 @@
 //!
 //! The `ImportQueue` trait allows such verification strategies to be
 //! instantiated. The `BasicQueue` and `BasicVerifier` traits allow serial
-//! queues to be instantiated simply.
+//! queues to be instantiated simply, and the `PreVerifier` trait lets them
+//! check the blocks in parallel ahead of their import.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
+use futures::executor::ThreadPool;
 use sp_runtime::{Justification, traits::{Block as BlockT, Header as _, NumberFor}};
 use crate::error::Error as ConsensusError;
 use crate::block_import::{
 	BlockImport, BlockOrigin, BlockImportParams, ImportedAux, JustificationImport, ImportResult,
-	BlockCheckParams, FinalityProofImport,
+	BlockCheckParams, FinalityProofImport, ImportedState, ForkChoiceStrategy,
 };
 
 pub use basic_queue::BasicQueue;
@@ -72,6 +74,11 @@ pub struct IncomingBlock<B: BlockT> {
 	pub allow_missing_state: bool,
 	/// Re-validate existing block.
 	pub import_existing: bool,
+	/// Complete state of the block, downloaded instead of executing the block.
+	///
+	/// The finality of such a block must have been proven by whoever produced this struct, as it
+	/// bypasses the verifier.
+	pub state: Option<ImportedState<B>>,
 }
 
 /// Type of keys in the blockchain cache that consensus module could use for its needs.
@@ -91,6 +98,45 @@ pub trait Verifier<B: BlockT>: Send + Sync {
 	) -> Result<(BlockImportParams<B, ()>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String>;
 }
 
+/// Checks of a block that don't depend on the import of its ancestors, such as the check of the
+/// seal of its header.
+///
+/// As opposed to the `Verifier`, which runs sequentially, the pre-verification of many blocks
+/// can run in parallel before they are imported. A block failing it is rejected without being
+/// passed to the `Verifier`.
+pub trait PreVerifier<B: BlockT>: Send + Sync {
+	/// Check the given block, returning the reason of its rejection if it is invalid.
+	fn pre_verify(
+		&self,
+		origin: BlockOrigin,
+		header: &B::Header,
+		body: Option<&[B::Extrinsic]>,
+	) -> Result<(), String>;
+}
+
+/// Verification of the blocks in parallel by a pool of workers, ahead of their sequential
+/// import.
+///
+/// Only the checks that don't depend on the import of the ancestors of a block, such as the
+/// check of a PoW seal, can run ahead of it. The Aura and BABE headers are checked against the
+/// authorities or the epoch at their parent, hence their import queues don't use it.
+pub struct ParallelVerification<B: BlockT> {
+	pub(crate) pre_verifier: Arc<dyn PreVerifier<B>>,
+	pub(crate) pool: ThreadPool,
+}
+
+impl<B: BlockT> ParallelVerification<B> {
+	/// Start a pool of `workers` workers running the checks of `pre_verifier`.
+	pub fn new(pre_verifier: Arc<dyn PreVerifier<B>>, workers: usize) -> Result<Self, ConsensusError> {
+		let pool = ThreadPool::builder()
+			.pool_size(workers.max(1))
+			.name_prefix("block-pre-verification-")
+			.create()
+			.map_err(|err| ConsensusError::Other(Box::new(err)))?;
+		Ok(ParallelVerification { pre_verifier, pool })
+	}
+}
+
 /// Blocks import queue API.
 ///
 /// The `import_*` methods can be called in order to send elements for the import queue to verify.
@@ -232,6 +278,21 @@ pub fn import_single_block<B: BlockT, V: Verifier<B>, Transaction>(
 			}
 		}
 	};
+	if let Some(state) = block.state {
+		// The ancestry of a block imported along with its state is usually unknown, which makes
+		// both the preliminary checks and the consensus verification impossible. Its finality has
+		// been proven before downloading the state, or it has already been imported without its
+		// state and finalized.
+		let mut import_block = BlockImportParams::new(block_origin, header);
+		import_block.justification = justification;
+		import_block.body = block.body;
+		import_block.finalized = true;
+		import_block.fork_choice = Some(ForkChoiceStrategy::Custom(true));
+		import_block.import_existing = block.import_existing;
+		import_block.state = Some(state);
+		return import_error(import_handle.import_block(import_block, HashMap::new()))
+	}
+
 	match import_error(import_handle.check_block(BlockCheckParams {
 		hash,
 		number,
@@ -258,6 +319,7 @@ pub fn import_single_block<B: BlockT, V: Verifier<B>, Transaction>(
 		cache.extend(keys.into_iter());
 	}
 	import_block.allow_missing_state = block.allow_missing_state;
+	import_block.import_existing = block.import_existing;
 
 	import_error(import_handle.import_block(import_block.convert_transaction(), cache))
 }
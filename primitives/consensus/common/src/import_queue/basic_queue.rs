@@ -15,8 +15,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{mem, pin::Pin, time::Duration, marker::PhantomData};
-use futures::{prelude::*, task::Context, task::Poll};
+use std::{mem, pin::Pin, time::Duration, marker::PhantomData, panic::{self, AssertUnwindSafe}, sync::Arc};
+use futures::{prelude::*, channel::oneshot, future::Either, task::Context, task::Poll};
 use futures_timer::Delay;
 use sp_runtime::{Justification, traits::{Block as BlockT, Header as HeaderT, NumberFor}};
 use sp_utils::mpsc::{TracingUnboundedSender, tracing_unbounded};
@@ -26,7 +26,7 @@ use crate::block_import::BlockOrigin;
 use crate::metrics::Metrics;
 use crate::import_queue::{
 	BlockImportResult, BlockImportError, Verifier, BoxBlockImport, BoxFinalityProofImport,
-	BoxJustificationImport, ImportQueue, Link, Origin,
+	BoxJustificationImport, ImportQueue, Link, Origin, PreVerifier, ParallelVerification,
 	IncomingBlock, import_single_block,
 	buffered_link::{self, BufferedLinkSender, BufferedLinkReceiver}
 };
@@ -61,6 +61,30 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
 		spawner: &impl sp_core::traits::SpawnBlocking,
 		prometheus_registry: Option<&Registry>,
+	) -> Self {
+		Self::with_parallel_verification(
+			verifier,
+			None,
+			block_import,
+			justification_import,
+			finality_proof_import,
+			spawner,
+			prometheus_registry,
+		)
+	}
+
+	/// Instantiate a new basic queue, with given verifier, whose blocks are first pre-verified in
+	/// parallel if `parallel_verification` is given.
+	///
+	/// The blocks are still verified by `verifier` and imported one at a time, in order.
+	pub fn with_parallel_verification<V: 'static + Verifier<B>>(
+		verifier: V,
+		parallel_verification: Option<ParallelVerification<B>>,
+		block_import: BoxBlockImport<B, Transaction>,
+		justification_import: Option<BoxJustificationImport<B>>,
+		finality_proof_import: Option<BoxFinalityProofImport<B>>,
+		spawner: &impl sp_core::traits::SpawnBlocking,
+		prometheus_registry: Option<&Registry>,
 	) -> Self {
 		let (result_sender, result_port) = buffered_link::buffered_link();
 		let metrics = prometheus_registry.and_then(|r|
@@ -71,6 +95,7 @@ impl<B: BlockT, Transaction: Send + 'static> BasicQueue<B, Transaction> {
 		let (future, worker_sender) = BlockImportWorker::new(
 			result_sender,
 			verifier,
+			parallel_verification,
 			block_import,
 			justification_import,
 			finality_proof_import,
@@ -137,8 +162,54 @@ enum ToWorkerMsg<B: BlockT> {
 	ImportFinalityProof(Origin, B::Hash, NumberFor<B>, Vec<u8>),
 }
 
+impl<B: BlockT> ParallelVerification<B> {
+	/// Returns a `Future` that pre-verifies the blocks in parallel and yields them back in the
+	/// same order, along with the index and the reason of the rejection of the first invalid
+	/// block, if any.
+	///
+	/// The blocks without header, or imported along with their state, bypass the pre-verification
+	/// as they do the verification.
+	fn pre_verify(
+		&self,
+		origin: BlockOrigin,
+		blocks: Vec<IncomingBlock<B>>,
+	) -> impl Future<Output = (Vec<IncomingBlock<B>>, Option<(usize, String)>)> {
+		trace!(target: "sync", "Pre-verifying {} blocks in parallel", blocks.len());
+
+		let results = blocks.into_iter().map(|block| {
+			let (sender, receiver) = oneshot::channel();
+			let pre_verifier = self.pre_verifier.clone();
+			self.pool.spawn_ok(async move {
+				let result = match (&block.header, &block.state) {
+					(Some(header), None) => panic::catch_unwind(AssertUnwindSafe(||
+						pre_verifier.pre_verify(origin, header, block.body.as_ref().map(|body| &body[..]))
+					)).unwrap_or_else(|_| Err("Pre-verification panicked".into())),
+					_ => Ok(()),
+				};
+				let _ = sender.send((block, result));
+			});
+			receiver
+		}).collect::<Vec<_>>();
+
+		future::join_all(results).map(|results| {
+			let mut blocks = Vec::with_capacity(results.len());
+			let mut rejection = None;
+			for (index, result) in results.into_iter().enumerate() {
+				let (block, result) = result
+					.expect("The workers are kept alive by the pool and always send a result; qed");
+				if let (Err(reason), None) = (result, &rejection) {
+					rejection = Some((index, reason));
+				}
+				blocks.push(block);
+			}
+			(blocks, rejection)
+		})
+	}
+}
+
 struct BlockImportWorker<B: BlockT, Transaction> {
 	result_sender: BufferedLinkSender<B>,
+	pre_verification: Option<ParallelVerification<B>>,
 	justification_import: Option<BoxJustificationImport<B>>,
 	finality_proof_import: Option<BoxFinalityProofImport<B>>,
 	delay_between_blocks: Duration,
@@ -155,6 +226,7 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 	fn new<V: 'static + Verifier<B>>(
 		result_sender: BufferedLinkSender<B>,
 		verifier: V,
+		parallel_verification: Option<ParallelVerification<B>>,
 		block_import: BoxBlockImport<B, Transaction>,
 		justification_import: Option<BoxJustificationImport<B>>,
 		finality_proof_import: Option<BoxFinalityProofImport<B>>,
@@ -164,6 +236,7 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 
 		let mut worker = BlockImportWorker {
 			result_sender,
+			pre_verification: parallel_verification,
 			justification_import,
 			finality_proof_import,
 			delay_between_blocks: Duration::new(0, 0),
@@ -259,8 +332,45 @@ impl<B: BlockT, Transaction: Send> BlockImportWorker<B, Transaction> {
 	) -> impl Future<Output = (BoxBlockImport<B, Transaction>, V)> {
 		let mut result_sender = self.result_sender.clone();
 		let metrics = self.metrics.clone();
+		let delay_between_blocks = self.delay_between_blocks;
 
-		import_many_blocks(block_import, origin, blocks, verifier, self.delay_between_blocks)
+		let pre_verification = match &self.pre_verification {
+			Some(pool) => Either::Left(pool.pre_verify(origin, blocks)),
+			None => Either::Right(future::ready((blocks, None))),
+		};
+
+		pre_verification
+			.then(move |(mut blocks, rejection)| {
+				// The blocks following the first one rejected by the pre-verification would be
+				// cancelled, only the ones before it are imported.
+				let rejected = match &rejection {
+					Some((index, _)) => blocks.split_off(*index),
+					None => Vec::new(),
+				};
+				import_many_blocks(block_import, origin, blocks, verifier, delay_between_blocks)
+					.map(move |(imported, count, mut results, block_import, verifier)| {
+						let mut cancelled = results.iter().any(|(result, _)| result.is_err());
+						let count = count + rejected.len();
+						let reason = rejection.map(|(_, reason)| reason);
+						for block in rejected {
+							let result = match (&reason, cancelled) {
+								(Some(reason), false) => {
+									trace!(
+										target: "sync",
+										"Pre-verifying {:?} failed: {}",
+										block.hash,
+										reason,
+									);
+									Err(BlockImportError::VerificationFailed(block.origin, reason.clone()))
+								},
+								_ => Err(BlockImportError::Cancelled),
+							};
+							cancelled = true;
+							results.push((result, block.hash));
+						}
+						(imported, count, results, block_import, verifier)
+					})
+			})
 			.then(move |(imported, count, results, block_import, verifier)| {
 				if let Some(metrics) = metrics {
 					let amounts = results.iter().fold([0u64; 8], |mut acc, result| {
@@ -449,3 +559,160 @@ fn import_many_blocks<B: BlockT, V: Verifier<B>, Transaction>(
 		Poll::Pending
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use sp_test_primitives::{Block, Extrinsic, Header};
+	use crate::block_import::{BlockCheckParams, BlockImport, BlockImportParams, ImportResult};
+	use crate::import_queue::{BlockImportResult, CacheKeyId};
+
+	struct RejectOddNumbers;
+
+	impl PreVerifier<Block> for RejectOddNumbers {
+		fn pre_verify(&self, _: BlockOrigin, header: &Header, _: Option<&[Extrinsic]>) -> Result<(), String> {
+			if header.number % 2 == 1 {
+				Err(format!("odd block {}", header.number))
+			} else {
+				Ok(())
+			}
+		}
+	}
+
+	/// Verifier rejecting the block with the given number.
+	struct RejectNumber(u64);
+
+	impl Verifier<Block> for RejectNumber {
+		fn verify(
+			&mut self,
+			origin: BlockOrigin,
+			header: Header,
+			_: Option<Justification>,
+			_: Option<Vec<Extrinsic>>,
+		) -> Result<(BlockImportParams<Block, ()>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
+			if header.number == self.0 {
+				Err(format!("block {}", header.number))
+			} else {
+				Ok((BlockImportParams::new(origin, header), None))
+			}
+		}
+	}
+
+	struct ImportAll;
+
+	impl BlockImport<Block> for ImportAll {
+		type Error = crate::Error;
+		type Transaction = ();
+
+		fn check_block(&mut self, _: BlockCheckParams<Block>) -> Result<ImportResult, Self::Error> {
+			Ok(ImportResult::imported(false))
+		}
+
+		fn import_block(
+			&mut self,
+			_: BlockImportParams<Block, ()>,
+			_: HashMap<CacheKeyId, Vec<u8>>,
+		) -> Result<ImportResult, Self::Error> {
+			Ok(ImportResult::imported(true))
+		}
+	}
+
+	#[derive(Default)]
+	struct TestLink {
+		results: Vec<(Result<BlockImportResult<u64>, BlockImportError>, <Block as BlockT>::Hash)>,
+	}
+
+	impl Link<Block> for TestLink {
+		fn blocks_processed(
+			&mut self,
+			_: usize,
+			_: usize,
+			results: Vec<(Result<BlockImportResult<u64>, BlockImportError>, <Block as BlockT>::Hash)>,
+		) {
+			self.results.extend(results);
+		}
+	}
+
+	fn block(number: u64) -> IncomingBlock<Block> {
+		let header = Header::new(number, Default::default(), Default::default(), Default::default(), Default::default());
+		IncomingBlock {
+			hash: header.hash(),
+			header: Some(header),
+			body: None,
+			justification: None,
+			origin: None,
+			allow_missing_state: false,
+			import_existing: false,
+			state: None,
+		}
+	}
+
+	// imports the blocks with the given numbers, pre-verified by `RejectOddNumbers` and verified
+	// by `verifier`, and returns the results sent to the link.
+	fn import_blocks(
+		verifier: RejectNumber,
+		numbers: Vec<u64>,
+	) -> Vec<(Result<BlockImportResult<u64>, BlockImportError>, <Block as BlockT>::Hash)> {
+		let (result_sender, mut result_port) = buffered_link::buffered_link();
+		let mut worker = BlockImportWorker::<Block, ()> {
+			result_sender,
+			pre_verification: Some(ParallelVerification::new(Arc::new(RejectOddNumbers), 4).unwrap()),
+			justification_import: None,
+			finality_proof_import: None,
+			delay_between_blocks: Duration::new(0, 0),
+			metrics: None,
+			_phantom: PhantomData,
+		};
+		let blocks = numbers.into_iter().map(block).collect();
+		futures::executor::block_on(
+			worker.import_a_batch_of_blocks(Box::new(ImportAll), verifier, BlockOrigin::NetworkInitialSync, blocks),
+		);
+
+		let mut link = TestLink::default();
+		futures::executor::block_on(future::poll_fn(|cx| {
+			result_port.poll_actions(cx, &mut link);
+			Poll::Ready(())
+		}));
+		link.results
+	}
+
+	#[test]
+	fn pre_verification_keeps_the_order_and_reports_the_first_rejection() {
+		let pool = ParallelVerification::new(Arc::new(RejectOddNumbers), 4).unwrap();
+		let blocks: Vec<_> = vec![2, 4, 6, 7, 8, 9].into_iter().map(block).collect();
+		let hashes: Vec<_> = blocks.iter().map(|block| block.hash).collect();
+
+		let (blocks, rejection) = futures::executor::block_on(pool.pre_verify(BlockOrigin::NetworkInitialSync, blocks));
+		assert_eq!(blocks.iter().map(|block| block.hash).collect::<Vec<_>>(), hashes);
+		assert_eq!(rejection, Some((3, "odd block 7".into())));
+
+		let (_, rejection) = futures::executor::block_on(
+			pool.pre_verify(BlockOrigin::NetworkInitialSync, vec![block(2), block(4)]),
+		);
+		assert_eq!(rejection, None);
+	}
+
+	#[test]
+	fn blocks_following_a_pre_verification_failure_are_cancelled() {
+		let results = import_blocks(RejectNumber(0), vec![2, 4, 7, 8]);
+		assert_eq!(results.iter().map(|(_, hash)| *hash).collect::<Vec<_>>(), vec![
+			block(2).hash, block(4).hash, block(7).hash, block(8).hash,
+		]);
+		assert!(matches!(results[0].0, Ok(BlockImportResult::ImportedUnknown(2, _, _))));
+		assert!(matches!(results[1].0, Ok(BlockImportResult::ImportedUnknown(4, _, _))));
+		assert!(matches!(&results[2].0, Err(BlockImportError::VerificationFailed(None, reason)) if reason == "odd block 7"));
+		assert!(matches!(results[3].0, Err(BlockImportError::Cancelled)));
+	}
+
+	#[test]
+	fn pre_verification_failures_following_a_verification_failure_are_cancelled() {
+		let results = import_blocks(RejectNumber(4), vec![2, 4, 6, 7, 8]);
+		assert_eq!(results.len(), 5);
+		assert!(matches!(results[0].0, Ok(BlockImportResult::ImportedUnknown(2, _, _))));
+		assert!(matches!(&results[1].0, Err(BlockImportError::VerificationFailed(None, reason)) if reason == "block 4"));
+		assert!(matches!(results[2].0, Err(BlockImportError::Cancelled)));
+		assert!(matches!(results[3].0, Err(BlockImportError::Cancelled)));
+		assert!(matches!(results[4].0, Err(BlockImportError::Cancelled)));
+	}
+}
@@ -32,6 +32,11 @@ pub mod testing;
 /// Local storage prefix used by the Offchain Worker API to
 pub const STORAGE_PREFIX : &'static [u8] = b"storage";
 
+/// Prefix of the `LOCAL` storage of the Offchain Worker API.
+///
+/// NOTE the values aren't reverted along with the blocks that set them yet.
+pub const LOCAL_STORAGE_PREFIX : &'static [u8] = b"local_storage";
+
 /// Offchain workers local storage.
 pub trait OffchainStorage: Clone + Send + Sync {
 	/// Persist a value in storage under given key and prefix.
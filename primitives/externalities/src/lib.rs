@@ -137,6 +137,18 @@ pub trait Externalities: ExtensionStore {
 	/// Clear an entire child storage.
 	fn kill_child_storage(&mut self, child_info: &ChildInfo);
 
+	/// Clear at most `limit` entries of a child storage, starting from the key `from`.
+	///
+	/// Returns the number of cleared entries, and the key of the first entry left after them,
+	/// from which the clearing can be resumed, or `None` if the child storage has no entry left
+	/// after `from`.
+	fn kill_child_storage_limited(
+		&mut self,
+		child_info: &ChildInfo,
+		from: &[u8],
+		limit: u32,
+	) -> (u32, Option<Vec<u8>>);
+
 	/// Clear storage entries which keys are start with the given prefix.
 	fn clear_prefix(&mut self, prefix: &[u8]);
 
@@ -42,7 +42,7 @@ use sp_core::{
 };
 
 use sp_core::{
-	crypto::KeyTypeId, ed25519, sr25519, ecdsa, H256, LogLevel,
+	crypto::KeyTypeId, ed25519, sr25519, ecdsa, H256, LogLevel, RuntimeDebug,
 	offchain::{
 		Timestamp, HttpRequestId, HttpRequestStatus, HttpError, StorageKind, OpaqueNetworkState,
 	},
@@ -51,7 +51,7 @@ use sp_core::{
 #[cfg(feature = "std")]
 use sp_trie::{TrieConfiguration, trie_types::Layout};
 
-use sp_runtime_interface::{runtime_interface, Pointer};
+use sp_runtime_interface::{runtime_interface, Pointer, pass_by::PassByCodec};
 
 use codec::{Encode, Decode};
 
@@ -75,6 +75,16 @@ pub enum EcdsaVerifyError {
 	BadSignature,
 }
 
+/// Progress of the removal of a child storage in batches.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug, Encode, Decode, PassByCodec)]
+pub struct ChildStorageKillProgress {
+	/// Number of entries removed by the batch.
+	pub removed: u32,
+	/// Key of the first entry left after the removed ones, from which the removal can be
+	/// resumed, or `None` if the child storage has been entirely removed.
+	pub next_key: Option<Vec<u8>>,
+}
+
 /// Interface for accessing the storage from within the runtime.
 #[runtime_interface]
 pub trait Storage {
@@ -237,6 +247,23 @@ pub trait DefaultChildStorage {
 		self.kill_child_storage(&child_info);
 	}
 
+	/// Clear a child storage in batches.
+	///
+	/// At most `limit` entries of the child storage for `storage_key` are removed, starting
+	/// from the key `from`. The removal of a large child storage can be spread over several
+	/// blocks by giving the `next_key` of the returned progress as `from` of the next call,
+	/// until it is `None`.
+	fn storage_kill_limited(
+		&mut self,
+		storage_key: &[u8],
+		from: &[u8],
+		limit: u32,
+	) -> ChildStorageKillProgress {
+		let child_info = ChildInfo::new_default(storage_key);
+		let (removed, next_key) = self.kill_child_storage_limited(&child_info, from, limit);
+		ChildStorageKillProgress { removed, next_key }
+	}
+
 	/// Check a child storage key.
 	///
 	/// Check whether the given `key` exists in default child defined at `storage_key`.
@@ -1190,6 +1217,26 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn storage_kill_limited_works() {
+		let mut t = BasicExternalities::default();
+		let storage_key = b"contract";
+
+		t.execute_with(|| {
+			for key in &[b"a", b"b", b"c"] {
+				default_child_storage::set(storage_key, *key, b"value");
+			}
+
+			let progress = default_child_storage::storage_kill_limited(storage_key, &[], 2);
+			assert_eq!(progress, ChildStorageKillProgress { removed: 2, next_key: Some(b"c".to_vec()) });
+			assert!(default_child_storage::get(storage_key, b"b").is_none());
+
+			let progress = default_child_storage::storage_kill_limited(storage_key, b"c", 2);
+			assert_eq!(progress, ChildStorageKillProgress { removed: 1, next_key: None });
+			assert!(default_child_storage::get(storage_key, b"c").is_none());
+		});
+	}
+
 	#[test]
 	fn dynamic_extensions_work() {
 		let mut ext = BasicExternalities::with_tasks_executor();
@@ -220,6 +220,30 @@ impl Externalities for BasicExternalities {
 		self.inner.children_default.remove(child_info.storage_key());
 	}
 
+	fn kill_child_storage_limited(
+		&mut self,
+		child_info: &ChildInfo,
+		from: &[u8],
+		limit: u32,
+	) -> (u32, Option<StorageKey>) {
+		let child = match self.inner.children_default.get_mut(child_info.storage_key()) {
+			Some(child) => child,
+			None => return (0, None),
+		};
+		let mut keys = child.data.range::<[u8], _>((Bound::Included(from), Bound::Unbounded))
+			.map(|(k, _)| k.clone());
+		let to_remove = keys.by_ref().take(limit as usize).collect::<Vec<_>>();
+		let next = keys.next();
+
+		for key in &to_remove {
+			child.data.remove(key);
+		}
+		if child.data.is_empty() {
+			self.inner.children_default.remove(child_info.storage_key());
+		}
+		(to_remove.len() as u32, next)
+	}
+
 	fn clear_prefix(&mut self, prefix: &[u8]) {
 		if is_child_storage_key(prefix) {
 			warn!(
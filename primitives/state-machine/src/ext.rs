@@ -38,6 +38,28 @@ use log::{warn, trace};
 
 const EXT_NOT_ALLOWED_TO_FAIL: &str = "Externalities not allowed to fail within runtime";
 
+/// Reports an access to the storage as a `tracing` event, so that the accesses made while
+/// executing a block can be recorded by a tracing subscriber.
+macro_rules! trace_storage_access {
+	($method:expr, $key:expr, $result:expr) => {
+		tracing::trace!(
+			target: "state",
+			method = $method,
+			key = %HexDisplay::from(&$key),
+			result = ?$result,
+		)
+	};
+	($method:expr, $child_info:expr, $key:expr, $result:expr) => {
+		tracing::trace!(
+			target: "state",
+			method = $method,
+			child = %HexDisplay::from(&$child_info.storage_key()),
+			key = %HexDisplay::from(&$key),
+			result = ?$result,
+		)
+	};
+}
+
 /// Errors that can occur when interacting with the externalities.
 #[derive(Debug, Copy, Clone)]
 pub enum Error<B, E> {
@@ -180,6 +202,7 @@ where
 			HexDisplay::from(&key),
 			result.as_ref().map(HexDisplay::from)
 		);
+		trace_storage_access!("Get", key, result.as_ref().map(HexDisplay::from));
 		result
 	}
 
@@ -195,6 +218,7 @@ where
 			HexDisplay::from(&key),
 			result,
 		);
+		trace_storage_access!("Hash", key, result);
 		result.map(|r| r.encode())
 	}
 
@@ -218,6 +242,7 @@ where
 			HexDisplay::from(&key),
 			result.as_ref().map(HexDisplay::from)
 		);
+		trace_storage_access!("GetChild", child_info, key, result.as_ref().map(HexDisplay::from));
 
 		result
 	}
@@ -258,6 +283,7 @@ where
 			HexDisplay::from(&key),
 			result,
 		);
+		trace_storage_access!("Exists", key, result);
 
 		result
 	}
@@ -333,6 +359,7 @@ where
 			HexDisplay::from(&key),
 			value.as_ref().map(HexDisplay::from)
 		);
+		trace_storage_access!("Put", key, value.as_ref().map(HexDisplay::from));
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 		if is_child_storage_key(&key) {
 			warn!(target: "trie", "Refuse to directly set child storage key");
@@ -355,6 +382,7 @@ where
 			HexDisplay::from(&key),
 			value.as_ref().map(HexDisplay::from)
 		);
+		trace_storage_access!("PutChild", child_info, key, value.as_ref().map(HexDisplay::from));
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 
 		self.mark_dirty();
@@ -378,11 +406,45 @@ where
 		});
 	}
 
+	fn kill_child_storage_limited(
+		&mut self,
+		child_info: &ChildInfo,
+		from: &[u8],
+		limit: u32,
+	) -> (u32, Option<StorageKey>) {
+		trace!(target: "state", "{:04x}: KillChildLimited({}) from {} limit {}",
+			self.id,
+			HexDisplay::from(&child_info.storage_key()),
+			HexDisplay::from(&from),
+			limit,
+		);
+		let _guard = sp_panic_handler::AbortGuard::force_abort();
+
+		let mut next = if self.exists_child_storage(child_info, from) {
+			Some(from.to_vec())
+		} else {
+			self.next_child_storage_key(child_info, from)
+		};
+		let mut removed = 0;
+		while removed < limit {
+			let key = match next {
+				Some(key) => key,
+				None => break,
+			};
+			self.mark_dirty();
+			self.overlay.set_child_storage(child_info, key.clone(), None);
+			removed += 1;
+			next = self.next_child_storage_key(child_info, &key);
+		}
+		(removed, next)
+	}
+
 	fn clear_prefix(&mut self, prefix: &[u8]) {
 		trace!(target: "state", "{:04x}: ClearPrefix {}",
 			self.id,
 			HexDisplay::from(&prefix),
 		);
+		trace_storage_access!("ClearPrefix", prefix, Option::<()>::None);
 		let _guard = sp_panic_handler::AbortGuard::force_abort();
 		if is_child_storage_key(prefix) {
 			warn!(target: "trie", "Refuse to directly clear prefix that is part of child storage key");
@@ -881,6 +943,42 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn kill_child_storage_limited_works() {
+		let child_info = ChildInfo::new_default(b"Child1");
+		let child_info = &child_info;
+		let mut cache = StorageTransactionCache::default();
+		let mut overlay = OverlayedChanges::default();
+		overlay.set_child_storage(child_info, vec![20], None);
+		overlay.set_child_storage(child_info, vec![30], Some(vec![31]));
+		let mut offchain_overlay = prepare_offchain_overlay_with_changes();
+		let backend = Storage {
+			top: map![],
+			children_default: map![
+				child_info.storage_key().to_vec() => StorageChild {
+					data: map![
+						vec![10] => vec![10],
+						vec![20] => vec![20],
+						vec![40] => vec![40]
+					],
+					child_info: child_info.to_owned(),
+				}
+			],
+		}.into();
+
+		let mut ext = TestExt::new(&mut overlay, &mut offchain_overlay, &mut cache, &backend, None, None);
+
+		// the deleted entry is skipped, the one of the overlay is removed.
+		assert_eq!(ext.kill_child_storage_limited(child_info, &[], 2), (2, Some(vec![40])));
+		assert_eq!(ext.child_storage(child_info, &[10]), None);
+		assert_eq!(ext.child_storage(child_info, &[30]), None);
+		assert_eq!(ext.child_storage(child_info, &[40]), Some(vec![40]));
+
+		assert_eq!(ext.kill_child_storage_limited(child_info, &[40], 0), (0, Some(vec![40])));
+		assert_eq!(ext.kill_child_storage_limited(child_info, &[40], 2), (1, None));
+		assert_eq!(ext.next_child_storage_key(child_info, &[]), None);
+	}
+
 	#[test]
 	fn storage_append_works() {
 		let mut data = Vec::new();
@@ -558,6 +558,40 @@ where
 	)
 }
 
+/// Check the proof of the execution of `method` on the state with the given `root`, reading the
+/// runtime code from the proof.
+///
+/// Unlike `execution_proof_check`, the verifier doesn't need to know the runtime of the state, but
+/// the proof must also include the `:code` and `:heap_pages` entries of the state, as returned by
+/// `prove_read`.
+pub fn execution_proof_check_with_proof_code<H, N, Exec>(
+	root: H::Out,
+	proof: StorageProof,
+	exec: &Exec,
+	spawn_handle: Box<dyn CloneableSpawn>,
+	method: &str,
+	call_data: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>>
+where
+	H: Hasher,
+	Exec: CodeExecutor + Clone + 'static,
+	H::Out: Ord + 'static + codec::Codec,
+	N: crate::changes_trie::BlockNumber,
+{
+	let trie_backend = create_proof_check_backend::<H>(root, proof)?;
+	let runtime_code = backend::BackendRuntimeCode::new(&trie_backend).runtime_code()
+		.map_err(|e| Box::new(e) as Box<dyn Error>)?;
+	execution_proof_check_on_trie_backend::<_, N, _>(
+		&trie_backend,
+		&mut Default::default(),
+		exec,
+		spawn_handle,
+		method,
+		call_data,
+		&runtime_code,
+	)
+}
+
 /// Check execution proof on proving backend, generated by `prove_execution` call.
 pub fn execution_proof_check_on_trie_backend<H, N, Exec>(
 	trie_backend: &TrieBackend<MemoryDB<H>, H>,
@@ -963,6 +997,60 @@ mod tests {
 		assert_eq!(remote_result, local_result);
 	}
 
+	#[test]
+	fn prove_execution_with_proof_code_and_proof_check_works() {
+		let executor = DummyCodeExecutor {
+			change_changes_trie_config: false,
+			native_available: true,
+			native_succeeds: true,
+			fallback_succeeds: true,
+		};
+
+		// the code is big enough not to be inlined in the nodes read by the execution.
+		let initial: BTreeMap<_, _> = map![
+			sp_core::storage::well_known_keys::CODE.to_vec() => vec![1; 64],
+			b"value1".to_vec() => vec![40],
+			b"value2".to_vec() => vec![26]
+		];
+		let remote_backend = InMemoryBackend::<BlakeTwo256>::from(initial);
+		let remote_root = remote_backend.storage_root(std::iter::empty()).0;
+		let runtime_code = backend::BackendRuntimeCode::new(&remote_backend).runtime_code().unwrap();
+		let (remote_result, execution_proof) = prove_execution::<_, _, u64, _>(
+			remote_backend.clone(),
+			&mut Default::default(),
+			&executor,
+			sp_core::tasks::executor(),
+			"test",
+			&[],
+			&runtime_code,
+		).unwrap();
+		let code_proof = prove_read(
+			remote_backend,
+			&[sp_core::storage::well_known_keys::CODE, sp_core::storage::well_known_keys::HEAP_PAGES],
+		).unwrap();
+
+		// the code isn't read through the proving backend, so it must be proven separately.
+		assert!(execution_proof_check_with_proof_code::<BlakeTwo256, u64, _>(
+			remote_root,
+			execution_proof.clone(),
+			&executor,
+			sp_core::tasks::executor(),
+			"test",
+			&[],
+		).is_err());
+
+		let local_result = execution_proof_check_with_proof_code::<BlakeTwo256, u64, _>(
+			remote_root,
+			StorageProof::merge(vec![execution_proof, code_proof]),
+			&executor,
+			sp_core::tasks::executor(),
+			"test",
+			&[],
+		).unwrap();
+		assert_eq!(remote_result, vec![66]);
+		assert_eq!(remote_result, local_result);
+	}
+
 	#[test]
 	fn clear_prefix_in_ext_works() {
 		let initial: BTreeMap<_, _> = map![
@@ -133,6 +133,15 @@ impl<'a, H: Hasher, B: 'a + Backend<H>> Externalities for ReadOnlyExternalities<
 		unimplemented!("kill_child_storage is not supported in ReadOnlyExternalities")
 	}
 
+	fn kill_child_storage_limited(
+		&mut self,
+		_child_info: &ChildInfo,
+		_from: &[u8],
+		_limit: u32,
+	) -> (u32, Option<StorageKey>) {
+		unimplemented!("kill_child_storage_limited is not supported in ReadOnlyExternalities")
+	}
+
 	fn clear_prefix(&mut self, _prefix: &[u8]) {
 		unimplemented!("clear_prefix is not supported in ReadOnlyExternalities")
 	}
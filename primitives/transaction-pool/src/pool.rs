@@ -127,6 +127,82 @@ pub enum TransactionStatus<Hash, BlockHash> {
 	Invalid,
 }
 
+/// Life-cycle event of one of the transactions of the pool.
+///
+/// Unlike [`TransactionStatus`], which is only emitted for the transactions that are watched,
+/// these events are emitted for every transaction of the pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolEvent<Hash, BlockHash> {
+	/// Hash of the transaction.
+	pub hash: Hash,
+	/// What happened to the transaction.
+	pub kind: PoolEventKind<Hash, BlockHash>,
+}
+
+/// Kind of a [`PoolEvent`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolEventKind<Hash, BlockHash> {
+	/// Transaction has been imported to the pool.
+	Imported,
+	/// Transaction is part of the future queue.
+	Future,
+	/// Transaction is part of the ready queue.
+	Ready,
+	/// Transaction has been included in block with given hash.
+	InBlock(BlockHash),
+	/// The block this transaction was included in has been retracted.
+	Retracted(BlockHash),
+	/// The block this transaction was included in has not been finalized within the maximum
+	/// number of blocks awaiting finality.
+	FinalityTimeout(BlockHash),
+	/// Transaction has been finalized in the block with given hash.
+	Finalized(BlockHash),
+	/// Transaction has been replaced in the pool, by the transaction with given hash that
+	/// provides the same tags.
+	Usurped(Hash),
+	/// Transaction has been dropped from the pool.
+	Dropped(DroppedReason),
+	/// Transaction has been removed from the pool as invalid.
+	Invalid(InvalidReason),
+}
+
+/// Reason for a transaction to be dropped from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DroppedReason {
+	/// The pool exceeded its limits.
+	LimitsExceeded,
+	/// The transaction was in the future queue of a pool that rejects future transactions.
+	FutureRejected,
+	/// The transaction was replaced while being resubmitted to the pool.
+	Replaced,
+	/// The transaction couldn't be imported back after the pruning of a block.
+	ReimportFailed,
+}
+
+/// Reason for a transaction to be removed from the pool as invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InvalidReason {
+	/// The validity of the transaction couldn't be determined.
+	UnknownValidity,
+	/// The transaction was found invalid when it was revalidated.
+	Revalidation,
+	/// The transaction, or one of the transactions it depends on, was reported invalid.
+	Reported,
+	/// The transaction stayed in the pool for longer than its longevity.
+	Stale,
+	/// The transaction couldn't be promoted to the ready queue.
+	PromotionFailed,
+}
+
+/// The stream of the events of all the transactions of the pool.
+///
+/// The stream is bounded: the events are dropped while it is full.
+pub type PoolEventStream<Hash, BlockHash> = futures::channel::mpsc::Receiver<PoolEvent<Hash, BlockHash>>;
+
 /// The stream of transaction events.
 pub type TransactionStatusStream<Hash, BlockHash> = dyn Stream<Item=TransactionStatus<Hash, BlockHash>> + Send + Unpin;
 
@@ -141,6 +217,8 @@ pub type BlockHash<P> = <<P as TransactionPool>::Block as BlockT>::Hash;
 pub type TransactionFor<P> = <<P as TransactionPool>::Block as BlockT>::Extrinsic;
 /// Type of transactions event stream for a pool.
 pub type TransactionStatusStreamFor<P> = TransactionStatusStream<TxHash<P>, BlockHash<P>>;
+/// Type of the event stream of all the transactions of a pool.
+pub type PoolEventStreamFor<P> = PoolEventStream<TxHash<P>, BlockHash<P>>;
 
 /// Typical future type used in transaction pool api.
 pub type PoolFuture<T, E> = std::pin::Pin<Box<dyn Future<Output=Result<T, E>> + Send>>;
@@ -234,6 +312,9 @@ pub trait TransactionPool: Send + Sync {
 	/// Return an event stream of transactions imported to the pool.
 	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>>;
 
+	/// Return an event stream of the life-cycle events of all the transactions of the pool.
+	fn event_stream(&self) -> PoolEventStreamFor<Self>;
+
 	// *** networking
 	/// Notify the pool about transactions broadcast.
 	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>);
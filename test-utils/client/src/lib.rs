@@ -96,6 +96,13 @@ impl<Block: BlockT, Executor, G: GenesisInit> TestClientBuilder<Block, Executor,
 		let backend = Arc::new(Backend::new_test(keep_blocks, 0));
 		Self::with_backend(backend)
 	}
+
+	/// Create new `TestClientBuilder` with a backend keeping at most `max_memory` bytes of its
+	/// database in memory, the rest being moved to a temporary file.
+	pub fn with_memory_cap(max_memory: usize) -> Self {
+		let backend = Arc::new(Backend::new_test_with_memory_cap(std::u32::MAX, std::u64::MAX, max_memory));
+		Self::with_backend(backend)
+	}
 }
 
 impl<Block: BlockT, Executor, Backend, G: GenesisInit> TestClientBuilder<Block, Executor, Backend, G> {
@@ -200,6 +200,26 @@ impl Extrinsic {
 			_ => panic!("cannot convert to transfer ref"),
 		}
 	}
+
+	/// Split into the parts returned by `ExtrinsicDecodeApi`.
+	fn parts(self) -> frame_system_rpc_runtime_api::ExtrinsicParts {
+		let (call, args, signer) = match self {
+			Extrinsic::AuthoritiesChange(authorities) =>
+				(&b"authorities_change"[..], authorities.encode(), None),
+			Extrinsic::Transfer { transfer, .. } =>
+				(&b"transfer"[..], transfer.encode(), Some(transfer.from.encode())),
+			Extrinsic::IncludeData(data) => (&b"include_data"[..], data.encode(), None),
+			Extrinsic::StorageChange(key, value) => (&b"storage_change"[..], (key, value).encode(), None),
+			Extrinsic::ChangesTrieConfigUpdate(config) =>
+				(&b"changes_trie_config_update"[..], config.encode(), None),
+		};
+		frame_system_rpc_runtime_api::ExtrinsicParts {
+			pallet: b"System".to_vec(),
+			call: call.to_vec(),
+			args,
+			signer,
+		}
+	}
 }
 
 /// The signature type used by accounts/transactions.
@@ -715,6 +735,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::ExtrinsicDecodeApi<Block, Extrinsic> for Runtime {
+				fn decode_extrinsic(extrinsic: Extrinsic) -> frame_system_rpc_runtime_api::ExtrinsicParts {
+					extrinsic.parts()
+				}
+			}
 		}
 	} else {
 		impl_runtime_apis! {
@@ -913,6 +939,12 @@ cfg_if! {
 					0
 				}
 			}
+
+			impl frame_system_rpc_runtime_api::ExtrinsicDecodeApi<Block, Extrinsic> for Runtime {
+				fn decode_extrinsic(extrinsic: Extrinsic) -> frame_system_rpc_runtime_api::ExtrinsicParts {
+					extrinsic.parts()
+				}
+			}
 		}
 	}
 }
@@ -64,7 +64,9 @@ where
 		network,
 		telemetry_endpoints: chain_spec.telemetry_endpoints().clone(),
 		chain_spec: Box::new(chain_spec),
+		pinned_blocks: Default::default(),
 		task_executor: Arc::new(move |fut, _| wasm_bindgen_futures::spawn_local(fut)),
+		shared_task_manager: None,
 		telemetry_external_transport: Some(transport),
 		role: Role::Light,
 		database: {
@@ -84,19 +86,37 @@ where
 		offchain_worker: Default::default(),
 		prometheus_config: Default::default(),
 		pruning: Default::default(),
+		block_pruning: Default::default(),
+		index_extrinsics: Default::default(),
+		index_storage_changes: Default::default(),
+		cold_storage: None,
+		delta_archive: None,
+		canonicalization_delay: sc_service::config::DEFAULT_CANONICALIZATION_DELAY,
 		rpc_cors: Default::default(),
 		rpc_http: Default::default(),
 		rpc_ws: Default::default(),
+		rpc_ipc: Default::default(),
+		rpc_grpc: Default::default(),
 		rpc_ws_max_connections: Default::default(),
+		rpc_ws_max_subscriptions_per_connection: Default::default(),
+		rpc_max_request_size: Default::default(),
+		rpc_max_response_size: Default::default(),
+		rpc_max_batch_size: Default::default(),
+		rpc_rate_limit: Default::default(),
+		rpc_method_filter: Default::default(),
 		rpc_methods: Default::default(),
+		rpc_interface_methods: Default::default(),
 		state_cache_child_ratio: Default::default(),
 		state_cache_size: Default::default(),
+		trie_cache_size: Default::default(),
 		tracing_receiver: Default::default(),
 		tracing_targets: Default::default(),
 		transaction_pool: Default::default(),
 		wasm_method: Default::default(),
 		max_runtime_instances: 8,
 		announce_block: true,
+		systemd_notify: None,
+		health: None,
 	};
 
 	Ok(config)
@@ -44,6 +44,8 @@ impl BenchmarkCmd {
 		<BB as BlockT>::Hash: std::str::FromStr,
 		ExecDispatch: NativeExecutionDispatch + 'static,
 	{
+		let pallet = self.pallet.as_ref().ok_or("No pallet to benchmark")?;
+		let extrinsic = self.extrinsic.as_ref().ok_or("No extrinsic to benchmark")?;
 		let spec = config.chain_spec;
 		let wasm_method = self.wasm_method.into();
 		let strategy = self.execution.unwrap_or(ExecutionStrategy::Native);
@@ -72,8 +74,8 @@ impl BenchmarkCmd {
 			&executor,
 			"Benchmark_dispatch_benchmark",
 			&(
-				&self.pallet,
-				&self.extrinsic,
+				pallet,
+				extrinsic,
 				self.lowest_range_values.clone(),
 				self.highest_range_values.clone(),
 				self.steps.clone(),
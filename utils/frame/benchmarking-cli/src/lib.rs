@@ -16,20 +16,32 @@
 // limitations under the License.
 
 mod command;
+mod machine;
+mod report;
+mod storage;
 
 use sc_cli::{ExecutionStrategy, WasmExecutionMethod};
 use std::fmt::Debug;
 
-/// The `benchmark` command used to benchmark FRAME Pallets.
+pub use machine::MachineCmd;
+pub use storage::StorageCmd;
+
+/// The `benchmark` command used to benchmark FRAME Pallets, or the hardware of the node with
+/// one of its subcommands.
 #[derive(Debug, structopt::StructOpt, Clone)]
+#[structopt(setting = structopt::clap::AppSettings::SubcommandsNegateReqs)]
 pub struct BenchmarkCmd {
+	/// Benchmark the hardware instead of pallets.
+	#[structopt(subcommand)]
+	pub hardware: Option<HardwareBenchmarkCmd>,
+
 	/// Select a FRAME Pallet to benchmark, or `*` for all (in which case `extrinsic` must be `*`).
-	#[structopt(short, long)]
-	pub pallet: String,
+	#[structopt(short, long, required = true)]
+	pub pallet: Option<String>,
 
 	/// Select an extrinsic inside the pallet to benchmark, or `*` for all.
-	#[structopt(short, long)]
-	pub extrinsic: String,
+	#[structopt(short, long, required = true)]
+	pub extrinsic: Option<String>,
 
 	/// Select how many samples we should take across the variable components.
 	#[structopt(short, long, use_delimiter = true)]
@@ -86,3 +98,13 @@ pub struct BenchmarkCmd {
 	#[structopt(long = "db-cache", value_name = "MiB", default_value = "128")]
 	pub database_cache_size: u32,
 }
+
+/// The benchmarks of the hardware of the node.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub enum HardwareBenchmarkCmd {
+	/// Benchmark the CPU, the memory, the disk and the execution of the runtime by the machine.
+	Machine(MachineCmd),
+
+	/// Benchmark the reads and writes of the database.
+	Storage(StorageCmd),
+}
@@ -0,0 +1,193 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmark of the machine running the node.
+
+use crate::report::{Measurement, Report};
+use rand::RngCore;
+use sc_cli::{CliConfiguration, Result, SharedParams, WasmExecutionMethod};
+use sc_executor::NativeExecutor;
+use sc_service::{Configuration, NativeExecutionDispatch};
+use sp_core::{hashing::blake2_256, storage::well_known_keys, traits::{CallInWasm, MissingHostFunctions}};
+use sp_state_machine::BasicExternalities;
+use std::{fs, io::Write, path::PathBuf, time::Instant};
+
+/// Size of the buffers hashed and copied.
+const BUFFER_SIZE: usize = 64 * 1024 * 1024;
+
+/// Size of the blocks written to the disk.
+const DISK_BLOCK_SIZE: usize = 4 * 1024;
+
+/// Throughput of BLAKE2-256 of the reference hardware, in MiB/s.
+const REFERENCE_CPU: f64 = 800.0;
+
+/// Throughput of memory copies of the reference hardware, in MiB/s.
+const REFERENCE_MEMORY: f64 = 10_000.0;
+
+/// Throughput of sequential disk writes of the reference hardware, in MiB/s.
+const REFERENCE_DISK_THROUGHPUT: f64 = 500.0;
+
+/// Latency of synced 4 KiB disk writes of the reference hardware, in µs.
+const REFERENCE_DISK_LATENCY: f64 = 1_000.0;
+
+/// The `benchmark machine` command used to benchmark the CPU, the memory, the disk and the
+/// execution of the runtime by the machine running the node.
+///
+/// The results are compared against the ones of the reference hardware of the validators.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub struct MachineCmd {
+	/// Directory on the disk to benchmark.
+	///
+	/// Default is the directory of the database.
+	#[structopt(long = "disk-path", value_name = "PATH", parse(from_os_str))]
+	pub disk_path: Option<PathBuf>,
+
+	/// Select how many repetitions of the disk and runtime benchmarks should run.
+	#[structopt(short, long, default_value = "1000")]
+	pub repeat: u32,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::enabled_variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl MachineCmd {
+	/// Runs the command and prints the report.
+	pub fn run<ExecDispatch>(&self, config: Configuration) -> Result<()>
+	where
+		ExecDispatch: NativeExecutionDispatch + 'static,
+	{
+		let repeat = self.repeat.max(1) as usize;
+		let disk_path = match (&self.disk_path, config.database.path()) {
+			(Some(path), _) => path.clone(),
+			(None, Some(path)) => path.to_owned(),
+			(None, None) => return Err("No disk to benchmark, pass `--disk-path`".into()),
+		};
+
+		let mut buffer = vec![0u8; BUFFER_SIZE];
+		rand::thread_rng().fill_bytes(&mut buffer);
+
+		let start = Instant::now();
+		buffer.chunks(1024 * 1024).for_each(|chunk| { blake2_256(chunk); });
+		let cpu = Measurement::throughput("CPU (BLAKE2-256)", BUFFER_SIZE, start.elapsed(), Some(REFERENCE_CPU));
+
+		let mut copy = vec![0u8; BUFFER_SIZE];
+		let start = Instant::now();
+		copy.copy_from_slice(&buffer);
+		let memory = Measurement::throughput("Memory (copy)", BUFFER_SIZE, start.elapsed(), Some(REFERENCE_MEMORY));
+
+		let (disk_throughput, disk_latency) = benchmark_disk(disk_path, &buffer, repeat)?;
+		let runtime = benchmark_runtime::<ExecDispatch>(&config, self.wasm_method, repeat)?;
+
+		print!("{}", Report(vec![cpu, memory, disk_throughput, disk_latency, runtime]));
+		Ok(())
+	}
+}
+
+/// Measures the throughput of sequential writes of `buffer`, and the latency of synced writes of
+/// blocks, to a file of the directory `path`.
+fn benchmark_disk(path: PathBuf, buffer: &[u8], repeat: usize) -> Result<(Measurement, Measurement)> {
+	fs::create_dir_all(&path)?;
+	let file_path = path.join("benchmark.tmp");
+	let mut file = fs::File::create(&file_path)?;
+
+	let result = (|| -> Result<_> {
+		let start = Instant::now();
+		file.write_all(buffer)?;
+		file.sync_all()?;
+		let throughput = Measurement::throughput(
+			"Disk (sequential writes)",
+			buffer.len(),
+			start.elapsed(),
+			Some(REFERENCE_DISK_THROUGHPUT),
+		);
+
+		let start = Instant::now();
+		for block in buffer.chunks(DISK_BLOCK_SIZE).take(repeat) {
+			file.write_all(block)?;
+			file.sync_data()?;
+		}
+		let latency = Measurement::latency(
+			"Disk (synced 4 KiB writes)",
+			repeat.min(buffer.len() / DISK_BLOCK_SIZE),
+			start.elapsed(),
+			Some(REFERENCE_DISK_LATENCY),
+		);
+
+		Ok((throughput, latency))
+	})();
+
+	let _ = fs::remove_file(&file_path);
+	result
+}
+
+/// Measures the latency of calls to the runtime of the chain spec.
+fn benchmark_runtime<ExecDispatch>(
+	config: &Configuration,
+	wasm_method: WasmExecutionMethod,
+	repeat: usize,
+) -> Result<Measurement>
+where
+	ExecDispatch: NativeExecutionDispatch + 'static,
+{
+	let storage = config.chain_spec.build_storage()?;
+	let code = storage.top.get(well_known_keys::CODE)
+		.cloned()
+		.ok_or("The chain spec has no runtime code")?;
+	let code_hash = blake2_256(&code).to_vec();
+	let executor = NativeExecutor::<ExecDispatch>::new(wasm_method.into(), None, 1);
+	let mut ext = BasicExternalities::new(storage);
+	let call = |ext: &mut BasicExternalities| executor.call_in_wasm(
+		&code,
+		Some(code_hash.clone()),
+		"Core_version",
+		&[],
+		ext,
+		MissingHostFunctions::Allow,
+	).map_err(|e| format!("Error executing the runtime: {}", e));
+
+	// the first call compiles the runtime.
+	call(&mut ext)?;
+	let start = Instant::now();
+	for _ in 0..repeat {
+		call(&mut ext)?;
+	}
+	Ok(Measurement::latency("Runtime (`Core_version` calls)", repeat, start.elapsed(), None))
+}
+
+impl CliConfiguration for MachineCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn chain_id(&self, _is_dev: bool) -> Result<String> {
+		Ok(match self.shared_params.chain {
+			Some(ref chain) => chain.clone(),
+			None => "dev".into(),
+		})
+	}
+}
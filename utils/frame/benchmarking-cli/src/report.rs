@@ -0,0 +1,115 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Report of the hardware benchmarks.
+
+use std::{fmt, time::Duration};
+
+/// Result of a hardware benchmark, along with the result of the reference hardware.
+pub(crate) struct Measurement {
+	/// What was measured.
+	pub name: &'static str,
+	/// The result.
+	pub value: f64,
+	/// Unit of the result.
+	pub unit: &'static str,
+	/// Result of the reference hardware, if any.
+	pub reference: Option<f64>,
+	/// Whether a higher result is better, e.g. for throughputs, or worse, e.g. for latencies.
+	pub higher_is_better: bool,
+}
+
+impl Measurement {
+	/// Creates a throughput in MiB/s, out of `bytes` processed in `elapsed`.
+	pub fn throughput(name: &'static str, bytes: usize, elapsed: Duration, reference: Option<f64>) -> Self {
+		let mib = bytes as f64 / (1024.0 * 1024.0);
+		Measurement {
+			name,
+			value: mib / elapsed.as_secs_f64(),
+			unit: "MiB/s",
+			reference,
+			higher_is_better: true,
+		}
+	}
+
+	/// Creates a latency in µs, out of `count` operations done in `elapsed`.
+	pub fn latency(name: &'static str, count: usize, elapsed: Duration, reference: Option<f64>) -> Self {
+		Measurement {
+			name,
+			value: elapsed.as_secs_f64() * 1_000_000.0 / count.max(1) as f64,
+			unit: "µs",
+			reference,
+			higher_is_better: false,
+		}
+	}
+
+	/// Returns the result relative to the one of the reference hardware, in percents.
+	pub fn score(&self) -> Option<f64> {
+		self.reference.filter(|reference| *reference > 0.0 && self.value > 0.0).map(|reference| {
+			if self.higher_is_better {
+				self.value / reference * 100.0
+			} else {
+				reference / self.value * 100.0
+			}
+		})
+	}
+}
+
+/// Results of hardware benchmarks, displayed as a table.
+pub(crate) struct Report(pub Vec<Measurement>);
+
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		writeln!(f, "{:<40} | {:>18} | {:>18} | {:>7}", "Benchmark", "Result", "Reference", "Score")?;
+		writeln!(f, "{:-<40}-|-{:->18}-|-{:->18}-|-{:->7}", "", "", "", "")?;
+		for measurement in &self.0 {
+			let reference = measurement.reference
+				.map(|reference| format!("{:.2} {}", reference, measurement.unit))
+				.unwrap_or_else(|| "-".into());
+			let score = measurement.score()
+				.map(|score| format!("{:.0}%", score))
+				.unwrap_or_else(|| "-".into());
+			writeln!(
+				f,
+				"{:<40} | {:>18} | {:>18} | {:>7}",
+				measurement.name,
+				format!("{:.2} {}", measurement.value, measurement.unit),
+				reference,
+				score,
+			)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scores_are_relative_to_the_reference() {
+		let throughput = Measurement::throughput("copy", 2 * 1024 * 1024, Duration::from_secs(1), Some(4.0));
+		assert_eq!(throughput.value, 2.0);
+		assert_eq!(throughput.score(), Some(50.0));
+
+		let latency = Measurement::latency("write", 4, Duration::from_millis(2), Some(1000.0));
+		assert_eq!(latency.value, 500.0);
+		assert_eq!(latency.score(), Some(200.0));
+
+		assert_eq!(Measurement::latency("call", 1, Duration::from_millis(1), None).score(), None);
+	}
+}
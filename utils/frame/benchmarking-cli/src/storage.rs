@@ -0,0 +1,146 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmark of the database of the node.
+
+use crate::report::{Measurement, Report};
+use rand::{seq::SliceRandom, RngCore};
+use sc_cli::{CliConfiguration, DatabaseParams, PruningParams, Result, SharedParams};
+use sc_client_api::backend::Backend as _;
+use sc_client_db::{Backend, DatabaseSettings};
+use sc_service::Configuration;
+use sp_blockchain::HeaderBackend;
+use sp_core::offchain::OffchainStorage;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, Zero}};
+use sp_state_machine::Backend as _;
+use std::time::Instant;
+
+/// Prefix of the offchain storage entries written by the benchmark.
+const BENCHMARK_PREFIX: &[u8] = b"benchmark_storage";
+
+/// Latency of state reads of the reference hardware, in µs.
+const REFERENCE_READ_LATENCY: f64 = 50.0;
+
+/// Latency of committed writes of the reference hardware, in µs.
+const REFERENCE_WRITE_LATENCY: f64 = 1_000.0;
+
+/// The `benchmark storage` command used to benchmark the reads and writes of the database of the
+/// node.
+///
+/// The state of the best block is read, and entries are written to and then removed from the
+/// local storage of the offchain workers. The results are compared against the ones of the
+/// reference hardware of the validators.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub struct StorageCmd {
+	/// Select how many keys of the state should be read.
+	#[structopt(long, default_value = "10000")]
+	pub reads: usize,
+
+	/// Select how many values should be written.
+	#[structopt(long, default_value = "1000")]
+	pub writes: usize,
+
+	/// Size of the written values, in bytes.
+	#[structopt(long = "value-size", value_name = "BYTES", default_value = "32")]
+	pub value_size: usize,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl StorageCmd {
+	/// Runs the command and prints the report.
+	pub fn run<Block: BlockT>(&self, config: Configuration) -> Result<()> {
+		// the state caches are disabled to measure the reads of the database.
+		let settings = DatabaseSettings {
+			state_cache_size: 0,
+			state_cache_child_ratio: None,
+			trie_cache_size: 0,
+			..sc_service::database_settings(&config)
+		};
+		let backend = Backend::<Block>::new(settings)?;
+		let info = backend.blockchain().info();
+		if info.best_number.is_zero() {
+			return Err("The database has no block to benchmark, sync the chain first".into())
+		}
+		let state = backend.state_at(BlockId::Hash(info.best_hash))?;
+
+		let start = Instant::now();
+		let mut keys = Vec::with_capacity(self.reads);
+		let mut key = Vec::new();
+		while keys.len() < self.reads {
+			match state.next_storage_key(&key).map_err(|e| format!("Failed to iterate the state: {:?}", e))? {
+				Some(next) => {
+					keys.push(next.clone());
+					key = next;
+				},
+				None => break,
+			}
+		}
+		let iteration = Measurement::latency(
+			"State (key iteration)",
+			keys.len(),
+			start.elapsed(),
+			Some(REFERENCE_READ_LATENCY),
+		);
+
+		keys.shuffle(&mut rand::thread_rng());
+		let start = Instant::now();
+		for key in &keys {
+			state.storage(key).map_err(|e| format!("Failed to read the state: {:?}", e))?;
+		}
+		let reads = Measurement::latency("State (random reads)", keys.len(), start.elapsed(), Some(REFERENCE_READ_LATENCY));
+
+		let mut storage = backend.offchain_storage().ok_or("The database has no offchain storage")?;
+		let mut value = vec![0u8; self.value_size];
+		let start = Instant::now();
+		for index in 0..self.writes {
+			rand::thread_rng().fill_bytes(&mut value);
+			storage.set(BENCHMARK_PREFIX, &(index as u64).to_le_bytes(), &value);
+		}
+		let writes = Measurement::latency("Database (committed writes)", self.writes, start.elapsed(), Some(REFERENCE_WRITE_LATENCY));
+		for index in 0..self.writes {
+			storage.remove(BENCHMARK_PREFIX, &(index as u64).to_le_bytes());
+		}
+
+		print!("{}", Report(vec![iteration, reads, writes]));
+		Ok(())
+	}
+}
+
+impl CliConfiguration for StorageCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
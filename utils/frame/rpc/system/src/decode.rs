@@ -0,0 +1,322 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoding of the extrinsics of blocks.
+//!
+//! The runtime a block was executed with splits its extrinsics into the names of their pallet
+//! and call, their encoded arguments and their signer. The metadata of that runtime then gives
+//! the names and types of the arguments, whose values are decoded when their type is a
+//! primitive one.
+
+use std::{collections::HashMap, sync::Arc};
+
+use codec::{Codec, Compact, Decode, Encode};
+use frame_metadata::{DecodeDifferent, RuntimeMetadata, RuntimeMetadataPrefixed};
+use frame_system_rpc_runtime_api::ExtrinsicParts;
+use jsonrpc_core::{Error, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sc_client_api::BlockBackend;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_api::{Metadata, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_core::{Bytes, hexdisplay::HexDisplay};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{self, Header as _, Zero},
+};
+
+pub use frame_system_rpc_runtime_api::ExtrinsicDecodeApi;
+pub use self::gen_client::Client as BlockDecodeClient;
+
+/// Argument of a call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallArgument {
+	/// Name of the argument.
+	pub name: String,
+	/// Type of the argument, as written in the metadata.
+	#[serde(rename = "type")]
+	pub ty: String,
+	/// Value of the argument, if its type is a primitive one. Integers that might not fit in 64
+	/// bits are written as strings.
+	pub value: Option<Value>,
+}
+
+/// Extrinsic decoded by the runtime of its block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedExtrinsic {
+	/// Name of the pallet of the call.
+	pub pallet: String,
+	/// Name of the call.
+	pub call: String,
+	/// Arguments of the call, empty if the metadata doesn't describe them.
+	pub args: Vec<CallArgument>,
+	/// SCALE encoded arguments of the call.
+	pub encoded_args: Bytes,
+	/// SCALE encoded address of the signer, if the extrinsic is signed.
+	pub signer: Option<Bytes>,
+}
+
+/// Block along with its decoded extrinsics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedBlock<Header> {
+	/// Header of the block.
+	pub header: Header,
+	/// Extrinsics of the block, in order.
+	pub extrinsics: Vec<DecodedExtrinsic>,
+}
+
+/// Block decoding RPC methods.
+#[rpc]
+pub trait BlockDecodeApi<BlockHash, Header> {
+	/// Returns the block with the given hash, or the best block, with its extrinsics decoded
+	/// using the runtime the block was executed with.
+	#[rpc(name = "chain_getBlockDecoded")]
+	fn block_decoded(&self, hash: Option<BlockHash>) -> Result<Option<DecodedBlock<Header>>>;
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(super::RUNTIME_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+/// Names and types of the arguments of the calls, indexed by the names of their pallet and
+/// their name.
+type CallArguments = HashMap<(String, String), Vec<(String, String)>>;
+
+/// Returns a metadata item that has been decoded.
+fn decoded<B, O: Default>(item: DecodeDifferent<B, O>) -> O {
+	match item {
+		DecodeDifferent::Decoded(item) => item,
+		// only the metadata built by the runtime holds encodable items.
+		DecodeDifferent::Encode(_) => Default::default(),
+	}
+}
+
+fn call_arguments(metadata: RuntimeMetadata) -> CallArguments {
+	let modules = match metadata {
+		RuntimeMetadata::V11(metadata) => decoded(metadata.modules),
+		_ => return Default::default(),
+	};
+
+	let mut arguments = HashMap::new();
+	for module in modules {
+		let pallet = decoded(module.name);
+		for call in module.calls.map(decoded).unwrap_or_default() {
+			let args = decoded(call.arguments).into_iter()
+				.map(|arg| (decoded(arg.name), decoded(arg.ty)))
+				.collect();
+			arguments.insert((pallet.clone(), decoded(call.name)), args);
+		}
+	}
+	arguments
+}
+
+fn integer(value: u128) -> Value {
+	if value <= u128::from(u64::max_value()) {
+		Value::from(value as u64)
+	} else {
+		Value::from(value.to_string())
+	}
+}
+
+/// Decodes a value of type `ty` from `input`, if it is a primitive type.
+fn decode_value(ty: &str, input: &mut &[u8]) -> Option<Value> {
+	let value = match ty {
+		"bool" => Value::from(bool::decode(input).ok()?),
+		"u8" => Value::from(u8::decode(input).ok()?),
+		"u16" => Value::from(u16::decode(input).ok()?),
+		"u32" => Value::from(u32::decode(input).ok()?),
+		"u64" => Value::from(u64::decode(input).ok()?),
+		"u128" => integer(u128::decode(input).ok()?),
+		"Vec<u8>" | "Bytes" => Value::from(format!("0x{}", HexDisplay::from(&Vec::<u8>::decode(input).ok()?))),
+		ty if ty.starts_with("Compact<") => integer(Compact::<u128>::decode(input).ok()?.0),
+		_ => return None,
+	};
+	Some(value)
+}
+
+fn decode_parts(parts: ExtrinsicParts, calls: &CallArguments) -> DecodedExtrinsic {
+	let pallet = String::from_utf8_lossy(&parts.pallet).into_owned();
+	let call = String::from_utf8_lossy(&parts.call).into_owned();
+
+	let mut args = Vec::new();
+	let mut input = &parts.args[..];
+	let mut decoding = true;
+	for (name, ty) in calls.get(&(pallet.clone(), call.clone())).into_iter().flatten() {
+		// the arguments following one that can't be decoded can't be found.
+		let value = if decoding { decode_value(ty, &mut input) } else { None };
+		decoding = value.is_some();
+		args.push(CallArgument { name: name.clone(), ty: ty.clone(), value });
+	}
+
+	DecodedExtrinsic {
+		pallet,
+		call,
+		args,
+		encoded_args: parts.args.into(),
+		signer: parts.signer.map(Into::into),
+	}
+}
+
+/// An implementation of the block decoding RPC methods on full client.
+pub struct BlockDecoder<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> BlockDecoder<C, B> {
+	/// Create new `BlockDecoder` given client.
+	///
+	/// `B` is the pair of the block type and of the type the runtime decodes extrinsics into.
+	pub fn new(client: Arc<C>) -> Self {
+		BlockDecoder {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+impl<C, Block, Extrinsic> BlockDecodeApi<<Block as traits::Block>::Hash, <Block as traits::Block>::Header>
+	for BlockDecoder<C, (Block, Extrinsic)>
+where
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + BlockBackend<Block>,
+	C: Send + Sync + 'static,
+	C::Api: ExtrinsicDecodeApi<Block, Extrinsic>,
+	C::Api: Metadata<Block>,
+	Block: traits::Block,
+	Extrinsic: Codec + Send + Sync + 'static,
+{
+	fn block_decoded(
+		&self,
+		hash: Option<<Block as traits::Block>::Hash>,
+	) -> Result<Option<DecodedBlock<<Block as traits::Block>::Header>>> {
+		let hash = hash.unwrap_or_else(|| self.client.info().best_hash);
+		let block = match self.client.block(&BlockId::Hash(hash))
+			.map_err(|e| runtime_error("Unable to fetch the block.", e))?
+		{
+			Some(block) => block.block,
+			None => return Ok(None),
+		};
+		let (header, extrinsics) = traits::Block::deconstruct(block);
+
+		// blocks are executed with the runtime of their parent.
+		let at = if header.number().is_zero() {
+			BlockId::Hash(hash)
+		} else {
+			BlockId::Hash(*header.parent_hash())
+		};
+		let api = self.client.runtime_api();
+		// the metadata only describes the arguments, so extrinsics are decoded without it.
+		let calls = api.metadata(&at).ok()
+			.and_then(|metadata| RuntimeMetadataPrefixed::decode(&mut &metadata[..]).ok())
+			.map(|metadata| call_arguments(metadata.1))
+			.unwrap_or_default();
+
+		let extrinsics = extrinsics.into_iter()
+			.map(|extrinsic| {
+				let extrinsic = Extrinsic::decode(&mut &*extrinsic.encode())
+					.map_err(|e| runtime_error("Unable to decode extrinsic.", e))?;
+				let parts = api.decode_extrinsic(&at, extrinsic)
+					.map_err(|e| runtime_error("Unable to decode extrinsic.", e))?;
+				Ok(decode_parts(parts, &calls))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(Some(DecodedBlock { header, extrinsics }))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sc_block_builder::BlockBuilderProvider;
+	use substrate_test_runtime_client::{
+		prelude::*,
+		runtime::{Block, Extrinsic, Transfer},
+		sp_consensus::BlockOrigin,
+	};
+
+	#[test]
+	fn should_decode_the_extrinsics_of_a_block() {
+		// given
+		let mut client = Arc::new(substrate_test_runtime_client::new());
+		let transfer = Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 5,
+			nonce: 0,
+		};
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_transfer(transfer.clone()).unwrap();
+		let block = builder.build().unwrap().block;
+		client.import(BlockOrigin::Own, block).unwrap();
+
+		let decoder = BlockDecoder::<_, (Block, Extrinsic)>::new(client);
+
+		// when
+		let block = decoder.block_decoded(None).unwrap().unwrap();
+
+		// then
+		assert_eq!(block.header.number, 1);
+		assert_eq!(block.extrinsics, vec![DecodedExtrinsic {
+			pallet: "System".into(),
+			call: "transfer".into(),
+			// the test runtime doesn't provide metadata.
+			args: Vec::new(),
+			encoded_args: transfer.encode().into(),
+			signer: Some(transfer.from.encode().into()),
+		}]);
+	}
+
+	#[test]
+	fn should_decode_primitive_arguments() {
+		let mut calls = CallArguments::new();
+		calls.insert(("Balances".into(), "transfer".into()), vec![
+			("dest".into(), "<T::Lookup as StaticLookup>::Source".into()),
+			("value".into(), "Compact<T::Balance>".into()),
+		]);
+		calls.insert(("System".into(), "remark".into()), vec![
+			("_remark".into(), "Vec<u8>".into()),
+			("flag".into(), "bool".into()),
+		]);
+		let parts = |pallet: &[u8], call: &[u8], args: Vec<u8>| ExtrinsicParts {
+			pallet: pallet.to_vec(),
+			call: call.to_vec(),
+			args,
+			signer: None,
+		};
+
+		let remark = decode_parts(parts(b"System", b"remark", (vec![1u8, 2], true).encode()), &calls);
+		assert_eq!(
+			remark.args.iter().map(|arg| arg.value.clone()).collect::<Vec<_>>(),
+			vec![Some(Value::from("0x0102")), Some(Value::from(true))],
+		);
+
+		// the value following an argument of unknown type can't be found.
+		let transfer = decode_parts(parts(b"Balances", b"transfer", vec![0; 40]), &calls);
+		assert_eq!(transfer.args.len(), 2);
+		assert!(transfer.args.iter().all(|arg| arg.value.is_none()));
+	}
+}
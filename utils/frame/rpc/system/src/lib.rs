@@ -20,7 +20,10 @@
 use std::sync::Arc;
 
 use codec::{self, Codec, Decode, Encode};
-use sc_client_api::light::{future_header, RemoteBlockchain, Fetcher, RemoteCallRequest};
+use sc_client_api::{
+	backend::{Backend, StateBackendFor},
+	light::{future_header, RemoteBlockchain, Fetcher, RemoteCallRequest},
+};
 use jsonrpc_core::{
 	Error, ErrorCode,
 	futures::future::{result, Future},
@@ -35,18 +38,39 @@ use sp_runtime::{
 	generic::BlockId,
 	traits,
 };
-use sp_core::hexdisplay::HexDisplay;
+use sp_api::{ApiExt, ExecutionContext};
+use sp_block_builder::BlockBuilder;
+use sp_core::{Bytes, hexdisplay::HexDisplay, twox_128};
 use sp_transaction_pool::{TransactionPool, InPoolTransaction};
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
 
 pub use frame_system_rpc_runtime_api::AccountNonceApi;
 pub use self::gen_client::Client as SystemClient;
+pub use self::decode::{
+	BlockDecodeApi, BlockDecodeClient, BlockDecoder, CallArgument, DecodedBlock, DecodedExtrinsic,
+	ExtrinsicDecodeApi,
+};
+
+mod decode;
 
 /// Future that resolves to account nonce.
 pub type FutureResult<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
 
+/// Outcome of the dry run of an extrinsic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+	/// SCALE encoded `ApplyExtrinsicResult` returned by the runtime.
+	pub result: Bytes,
+	/// SCALE encoded value of the `System::Events` storage item after the extrinsic has been
+	/// applied, or `None` if the extrinsic didn't change it.
+	pub events: Option<Bytes>,
+}
+
 /// System RPC methods.
 #[rpc]
-pub trait SystemApi<AccountId, Index> {
+pub trait SystemApi<BlockHash, AccountId, Index> {
 	/// Returns the next valid index (aka nonce) for given account.
 	///
 	/// This method takes into consideration all pending transactions
@@ -54,34 +78,64 @@ pub trait SystemApi<AccountId, Index> {
 	/// it fallbacks to query the index from the runtime (aka. state nonce).
 	#[rpc(name = "system_accountNextIndex", alias("account_nextIndex"))]
 	fn nonce(&self, account: AccountId) -> FutureResult<Index>;
+
+	/// Applies the SCALE encoded extrinsic on top of the state of the given block, or of the
+	/// best block, without importing anything.
+	///
+	/// Returns the dispatch outcome and the events the extrinsic emitted.
+	#[rpc(name = "system_dryRun", alias("system_dryRunAt"))]
+	fn dry_run(&self, extrinsic: Bytes, at: Option<BlockHash>) -> FutureResult<DryRunResult>;
 }
 
 const RUNTIME_ERROR: i64 = 1;
 
+/// Storage key of the events deposited by `frame_system`.
+fn events_key() -> Vec<u8> {
+	let mut key = twox_128(b"System").to_vec();
+	key.extend_from_slice(&twox_128(b"Events"));
+	key
+}
+
+fn dry_run_error(message: &str, e: impl std::fmt::Debug) -> Error {
+	Error {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: message.into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
 /// An implementation of System-specific RPC methods on full client.
-pub struct FullSystem<P: TransactionPool, C, B> {
+pub struct FullSystem<BE, P: TransactionPool, C, B> {
+	backend: Arc<BE>,
 	client: Arc<C>,
 	pool: Arc<P>,
+	deny_unsafe: DenyUnsafe,
 	_marker: std::marker::PhantomData<B>,
 }
 
-impl<P: TransactionPool, C, B> FullSystem<P, C, B> {
-	/// Create new `FullSystem` given client and transaction pool.
-	pub fn new(client: Arc<C>, pool: Arc<P>) -> Self {
+impl<BE, P: TransactionPool, C, B> FullSystem<BE, P, C, B> {
+	/// Create new `FullSystem` given backend, client and transaction pool.
+	pub fn new(backend: Arc<BE>, client: Arc<C>, pool: Arc<P>, deny_unsafe: DenyUnsafe) -> Self {
 		FullSystem {
+			backend,
 			client,
 			pool,
+			deny_unsafe,
 			_marker: Default::default(),
 		}
 	}
 }
 
-impl<P, C, Block, AccountId, Index> SystemApi<AccountId, Index> for FullSystem<P, C, Block>
+impl<BE, P, C, Block, AccountId, Index> SystemApi<<Block as traits::Block>::Hash, AccountId, Index>
+	for FullSystem<BE, P, C, Block>
 where
+	BE: Backend<Block> + Send + Sync + 'static,
 	C: sp_api::ProvideRuntimeApi<Block>,
 	C: HeaderBackend<Block>,
 	C: Send + Sync + 'static,
 	C::Api: AccountNonceApi<Block, AccountId, Index>,
+	C::Api: BlockBuilder<Block>,
+	C::Api: ApiExt<Block, StateBackend = StateBackendFor<BE, Block>>,
 	P: TransactionPool + 'static,
 	Block: traits::Block,
 	AccountId: Clone + std::fmt::Display + Codec,
@@ -104,6 +158,58 @@ where
 
 		Box::new(result(get_nonce()))
 	}
+
+	fn dry_run(
+		&self,
+		extrinsic: Bytes,
+		at: Option<<Block as traits::Block>::Hash>,
+	) -> FutureResult<DryRunResult> {
+		if let Err(err) = self.deny_unsafe.check_if_safe() {
+			return Box::new(result(Err(err.into())));
+		}
+
+		let dry_run = || {
+			let parent_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+			let parent_number = self.client.number(parent_hash)
+				.map_err(|e| dry_run_error("Unable to dry run extrinsic.", e))?
+				.ok_or_else(|| dry_run_error("Unable to dry run extrinsic.", ClientError::UnknownBlock(
+					format!("{}", parent_hash),
+				)))?;
+			let extrinsic: <Block as traits::Block>::Extrinsic = Decode::decode(&mut &*extrinsic)
+				.map_err(|e| dry_run_error("Unable to decode extrinsic.", e))?;
+
+			let parent = BlockId::hash(parent_hash);
+			let header = <<Block as traits::Block>::Header as traits::Header>::new(
+				parent_number + traits::One::one(),
+				Default::default(),
+				Default::default(),
+				parent_hash,
+				Default::default(),
+			);
+			let api = self.client.runtime_api();
+			api.initialize_block_with_context(&parent, ExecutionContext::OffchainCall(None), &header)
+				.map_err(|e| dry_run_error("Unable to initialize block.", e))?;
+			let outcome = api.apply_extrinsic_with_context(
+				&parent,
+				ExecutionContext::OffchainCall(None),
+				extrinsic,
+			).map_err(|e| dry_run_error("Unable to dry run extrinsic.", e))?;
+
+			let state = self.backend.state_at(parent)
+				.map_err(|e| dry_run_error("Unable to dry run extrinsic.", e))?;
+			let changes = api.into_storage_changes(&state, None, parent_hash)
+				.map_err(|e| dry_run_error("Unable to collect the changes of the extrinsic.", e))?;
+			let events_key = events_key();
+			let events = changes.main_storage_changes.into_iter()
+				.find(|(key, _)| *key == events_key)
+				.and_then(|(_, value)| value)
+				.map(Bytes);
+
+			Ok(DryRunResult { result: outcome.encode().into(), events })
+		};
+
+		Box::new(result(dry_run()))
+	}
 }
 
 /// An implementation of System-specific RPC methods on light client.
@@ -131,7 +237,8 @@ impl<P: TransactionPool, C, F, Block> LightSystem<P, C, F, Block> {
 	}
 }
 
-impl<P, C, F, Block, AccountId, Index> SystemApi<AccountId, Index> for LightSystem<P, C, F, Block>
+impl<P, C, F, Block, AccountId, Index> SystemApi<<Block as traits::Block>::Hash, AccountId, Index>
+	for LightSystem<P, C, F, Block>
 where
 	P: TransactionPool + 'static,
 	C: HeaderBackend<Block>,
@@ -176,6 +283,18 @@ where
 
 		Box::new(future_nonce)
 	}
+
+	fn dry_run(
+		&self,
+		_extrinsic: Bytes,
+		_at: Option<<Block as traits::Block>::Hash>,
+	) -> FutureResult<DryRunResult> {
+		Box::new(result(Err(Error {
+			code: ErrorCode::MethodNotFound,
+			message: "Unable to dry run extrinsic on a light client.".into(),
+			data: None,
+		})))
+	}
 }
 
 /// Adjust account nonce from state, so that tx with the nonce will be
@@ -225,14 +344,18 @@ mod tests {
 	use substrate_test_runtime_client::{
 		runtime::Transfer,
 		AccountKeyring,
+		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
 	};
 	use sc_transaction_pool::{BasicPool, FullChainApi};
+	use sp_runtime::ApplyExtrinsicResult;
 
 	#[test]
 	fn should_return_next_nonce_for_some_account() {
 		// given
 		let _ = env_logger::try_init();
-		let client = Arc::new(substrate_test_runtime_client::new());
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
 		let pool = Arc::new(
 			BasicPool::new(
 				Default::default(),
@@ -257,7 +380,7 @@ mod tests {
 		let ext1 = new_transaction(1);
 		block_on(pool.submit_one(&BlockId::number(0), source, ext1)).unwrap();
 
-		let accounts = FullSystem::new(client, pool);
+		let accounts = FullSystem::new(backend, client, pool, DenyUnsafe::No);
 
 		// when
 		let nonce = accounts.nonce(AccountKeyring::Alice.into());
@@ -265,4 +388,55 @@ mod tests {
 		// then
 		assert_eq!(nonce.wait().unwrap(), 2);
 	}
+
+	#[test]
+	fn dry_run_should_apply_the_extrinsic_without_importing_it() {
+		// given
+		let _ = env_logger::try_init();
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
+		let pool = Arc::new(
+			BasicPool::new(
+				Default::default(),
+				Arc::new(FullChainApi::new(client.clone())),
+				None,
+			).0
+		);
+
+		let accounts = FullSystem::new(backend, client.clone(), pool, DenyUnsafe::No);
+
+		let tx = Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Bob.into(),
+			amount: 5,
+			nonce: 0,
+		}.into_signed_tx();
+
+		// when
+		let res = accounts.dry_run(tx.encode().into(), None).wait().unwrap();
+
+		// then
+		let apply_res: ApplyExtrinsicResult = Decode::decode(&mut &*res.result).unwrap();
+		assert_eq!(apply_res, Ok(Ok(())));
+		assert_eq!(client.info().best_number, 0);
+	}
+
+	#[test]
+	fn dry_run_should_be_denied_if_unsafe() {
+		let builder = TestClientBuilder::new();
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
+		let pool = Arc::new(
+			BasicPool::new(
+				Default::default(),
+				Arc::new(FullChainApi::new(client.clone())),
+				None,
+			).0
+		);
+
+		let accounts = FullSystem::new(backend, client, pool, DenyUnsafe::Yes);
+
+		assert!(accounts.dry_run(vec![].into(), None).wait().is_err());
+	}
 }
@@ -0,0 +1,226 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Check of the version of a Wasm runtime against the one of a chain.
+
+use codec::Decode;
+use futures::compat::Future01CompatExt;
+use jsonrpc_core_client::transports::http;
+use sc_cli::{CliConfiguration, Result, SharedParams, WasmExecutionMethod};
+use sc_executor::NativeExecutor;
+use sc_rpc_api::state::StateClient;
+use sc_service::{Configuration, NativeExecutionDispatch};
+use sp_core::{hashing::blake2_256, storage::well_known_keys, traits::{CallInWasm, MissingHostFunctions}};
+use sp_runtime::traits::Block as BlockT;
+use sp_state_machine::BasicExternalities;
+use sp_version::RuntimeVersion;
+use std::{fmt, fs, path::PathBuf};
+
+/// The `check-runtime` command used to check that a Wasm runtime can be upgraded to.
+///
+/// The version of the runtime is compared to the one of the runtime of the chain spec, or of the
+/// best block of a live node, following the rules of `frame_system::Module::set_code`.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub struct CheckRuntimeCmd {
+	/// Path to the Wasm runtime to check.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub wasm: PathBuf,
+
+	/// URL of the HTTP RPC server of a node whose runtime should be compared to.
+	///
+	/// By default the runtime is compared to the one of the chain spec.
+	#[structopt(long, value_name = "URL")]
+	pub url: Option<String>,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::enabled_variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl CheckRuntimeCmd {
+	/// Runs the command and prints the versions, failing if the runtime can't be upgraded to.
+	pub fn run<Block, ExecDispatch>(&self, config: Configuration) -> Result<()>
+	where
+		Block: BlockT,
+		Block::Hash: serde::Serialize + serde::de::DeserializeOwned,
+		ExecDispatch: NativeExecutionDispatch + 'static,
+	{
+		let executor = NativeExecutor::<ExecDispatch>::new(self.wasm_method.into(), None, 1);
+		let new = runtime_version(&executor, &fs::read(&self.wasm)?)?;
+		let current = match &self.url {
+			Some(url) => download_runtime_version::<Block::Hash>(url)?,
+			None => {
+				let storage = config.chain_spec.build_storage()?;
+				let code = storage.top.get(well_known_keys::CODE).ok_or("The chain spec has no runtime code")?;
+				runtime_version(&executor, code)?
+			},
+		};
+
+		println!("Current runtime: {}", current);
+		println!("New runtime:     {}", new);
+		let issues = check_compatibility(&current, &new);
+		for issue in &issues {
+			println!("{}", issue);
+		}
+
+		if issues.iter().any(|issue| issue.is_error()) {
+			Err("The runtime can't be upgraded to".into())
+		} else {
+			println!("The runtime can be upgraded to");
+			Ok(())
+		}
+	}
+}
+
+/// Returns the version of the runtime `code`.
+fn runtime_version<Exec: CallInWasm>(executor: &Exec, code: &[u8]) -> Result<RuntimeVersion> {
+	let mut ext = BasicExternalities::default();
+	let version = executor.call_in_wasm(
+		code,
+		Some(blake2_256(code).to_vec()),
+		"Core_version",
+		&[],
+		&mut ext,
+		MissingHostFunctions::Allow,
+	).map_err(|e| format!("Error executing the runtime: {}", e))?;
+	RuntimeVersion::decode(&mut &version[..])
+		.map_err(|e| format!("Failed to decode the runtime version: {:?}", e).into())
+}
+
+/// Downloads the version of the runtime of the best block from the node serving the HTTP RPC at
+/// `url`.
+fn download_runtime_version<Hash>(url: &str) -> Result<RuntimeVersion>
+where
+	Hash: serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+	let version = futures::executor::block_on(async {
+		let client: StateClient<Hash> = http::connect(url).compat().await
+			.map_err(|e| format!("Failed to connect to {}: {:?}", url, e))?;
+		client.runtime_version(None).compat().await
+			.map_err(|e| format!("Failed to download the runtime version: {:?}", e))
+	})?;
+	Ok(version)
+}
+
+/// Difference between two runtime versions that matters for an upgrade.
+#[derive(Debug, PartialEq)]
+enum Issue {
+	/// The upgrade would be rejected.
+	Error(String),
+	/// The upgrade would be accepted, but has consequences.
+	Warning(String),
+}
+
+impl Issue {
+	fn is_error(&self) -> bool {
+		match self {
+			Issue::Error(_) => true,
+			Issue::Warning(_) => false,
+		}
+	}
+}
+
+impl fmt::Display for Issue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Issue::Error(issue) => write!(f, "Error: {}", issue),
+			Issue::Warning(issue) => write!(f, "Warning: {}", issue),
+		}
+	}
+}
+
+/// Returns the issues of the upgrade from the runtime version `current` to `new`.
+fn check_compatibility(current: &RuntimeVersion, new: &RuntimeVersion) -> Vec<Issue> {
+	let mut issues = Vec::new();
+	if new.spec_name != current.spec_name {
+		issues.push(Issue::Error(format!(
+			"the spec name changes from `{}` to `{}`",
+			current.spec_name,
+			new.spec_name,
+		)));
+	}
+	if new.spec_version <= current.spec_version {
+		issues.push(Issue::Error(format!(
+			"the spec version doesn't increase from {} (new: {})",
+			current.spec_version,
+			new.spec_version,
+		)));
+	}
+	if new.transaction_version < current.transaction_version {
+		issues.push(Issue::Error(format!(
+			"the transaction version decreases from {} to {}",
+			current.transaction_version,
+			new.transaction_version,
+		)));
+	} else if new.transaction_version > current.transaction_version {
+		issues.push(Issue::Warning(format!(
+			"the transaction version increases from {} to {}, the transactions signed for the current runtime will be invalid",
+			current.transaction_version,
+			new.transaction_version,
+		)));
+	}
+	if new.authoring_version != current.authoring_version {
+		issues.push(Issue::Warning(format!(
+			"the authoring version changes from {} to {}, the block authors must upgrade their nodes",
+			current.authoring_version,
+			new.authoring_version,
+		)));
+	}
+	issues
+}
+
+impl CliConfiguration for CheckRuntimeCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn version(spec_name: &'static str, spec_version: u32, transaction_version: u32) -> RuntimeVersion {
+		RuntimeVersion {
+			spec_name: spec_name.into(),
+			spec_version,
+			transaction_version,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn upgrades_follow_the_rules_of_set_code() {
+		let current = version("node", 10, 1);
+		assert_eq!(check_compatibility(&current, &version("node", 11, 1)), vec![]);
+		assert!(check_compatibility(&current, &version("node", 11, 2)).iter().all(|issue| !issue.is_error()));
+
+		for new in &[version("other", 11, 1), version("node", 10, 1), version("node", 11, 0)] {
+			let issues = check_compatibility(&current, new);
+			assert!(issues.iter().any(Issue::is_error), "{} should be rejected", new);
+		}
+	}
+}
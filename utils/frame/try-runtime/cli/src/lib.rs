@@ -0,0 +1,438 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Testing of the runtime upgrades of FRAME runtimes against a real state.
+//!
+//! The `try-runtime` command executes the `on_runtime_upgrade` hooks of a Wasm runtime against the
+//! state of a block, read from the local database or downloaded from a node, and reports the
+//! weight they used and the storage changes they made. Nothing is written and no block is
+//! produced.
+//!
+//! The runtime has to implement the `TryRuntime` runtime API of `frame-try-runtime`.
+//!
+//! The `check-runtime` command compares the version of a Wasm runtime to the one of the chain, to
+//! catch the upgrades that would be rejected before they are proposed.
+
+mod check_runtime;
+
+pub use check_runtime::CheckRuntimeCmd;
+
+use codec::Decode;
+use futures::compat::Future01CompatExt;
+use jsonrpc_core_client::transports::http;
+use sc_cli::{
+	BlockNumberOrHash, CliConfiguration, DatabaseParams, PruningParams, Result, SharedParams,
+	WasmExecutionMethod,
+};
+use sc_client_api::backend::Backend as _;
+use sc_client_db::Backend;
+use sc_executor::NativeExecutor;
+use sc_rpc_api::{child_state::ChildStateClient, state::StateClient};
+use sc_service::{Configuration, NativeExecutionDispatch};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	hashing::blake2_256, hexdisplay::HexDisplay, tasks, traits::WrappedRuntimeCode,
+	storage::{well_known_keys, ChildInfo, Storage, StorageChild, StorageKey},
+};
+use sp_externalities::Extensions;
+use sp_runtime::{generic::BlockId, traits::{Block as BlockT, HashFor, NumberFor}};
+use sp_state_machine::{
+	backend::BackendRuntimeCode, Backend as StateBackend, ExecutionStrategy, InMemoryBackend,
+	OverlayedChanges, StateMachine,
+};
+use std::{collections::{BTreeMap, HashMap}, fmt, fs, path::PathBuf, str::FromStr};
+
+/// The `try-runtime` command used to test the runtime upgrades against a real state.
+#[derive(Debug, structopt::StructOpt, Clone)]
+pub struct TryRuntimeCmd {
+	/// Path to the Wasm runtime whose upgrade should be tested.
+	///
+	/// Default is the runtime of the state.
+	#[structopt(long, value_name = "PATH", parse(from_os_str))]
+	pub wasm: Option<PathBuf>,
+
+	/// Block hash or number of the state to test the upgrade against.
+	///
+	/// Only block hashes are supported with `--url`. Default is the best block.
+	#[structopt(long, value_name = "HASH or NUMBER")]
+	pub at: Option<BlockNumberOrHash>,
+
+	/// URL of the HTTP RPC server of a node to download the state from.
+	///
+	/// By default the state is read from the local database.
+	#[structopt(long, value_name = "URL")]
+	pub url: Option<String>,
+
+	/// Print the new values of the changed storage entries, not only their keys.
+	#[structopt(long)]
+	pub show_values: bool,
+
+	/// Method for executing Wasm runtime code.
+	#[structopt(
+		long = "wasm-execution",
+		value_name = "METHOD",
+		possible_values = &WasmExecutionMethod::enabled_variants(),
+		case_insensitive = true,
+		default_value = "Interpreted"
+	)]
+	pub wasm_method: WasmExecutionMethod,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+impl TryRuntimeCmd {
+	/// Runs the command and prints the report.
+	pub fn run<Block, ExecDispatch>(&self, config: Configuration) -> Result<()>
+	where
+		Block: BlockT,
+		Block::Hash: FromStr + serde::Serialize + serde::de::DeserializeOwned,
+		<Block::Hash as FromStr>::Err: fmt::Debug,
+		<NumberFor<Block> as FromStr>::Err: fmt::Debug,
+		ExecDispatch: NativeExecutionDispatch + 'static,
+	{
+		let at = self.at.as_ref().map(|at| at.parse::<Block>()).transpose()?;
+		let wasm = self.wasm.as_ref().map(fs::read).transpose()?;
+		let executor = NativeExecutor::<ExecDispatch>::new(self.wasm_method.into(), None, 1);
+
+		let report = match &self.url {
+			Some(url) => {
+				let at = match at {
+					Some(BlockId::Hash(hash)) => Some(hash),
+					Some(BlockId::Number(_)) => return Err("Only block hashes are supported with `--url`".into()),
+					None => None,
+				};
+				let state = InMemoryBackend::<HashFor<Block>>::from(download_state(url, at)?);
+				try_runtime_upgrade(&state, &executor, wasm.as_deref())?
+			},
+			None => {
+				let backend = Backend::<Block>::new(sc_service::database_settings(&config))?;
+				let at = at.unwrap_or_else(|| BlockId::Hash(backend.blockchain().info().best_hash));
+				let state = backend.state_at(at)?;
+				try_runtime_upgrade(&state, &executor, wasm.as_deref())?
+			},
+		};
+
+		print!("{}", report.display(self.show_values));
+		Ok(())
+	}
+}
+
+/// Number of keys downloaded at once, which is the maximum allowed by the nodes.
+const DOWNLOAD_PAGE_SIZE: u32 = 1000;
+
+/// Downloads the state of the block `at`, or of the best block, along with its child tries, from
+/// the node serving the HTTP RPC at `url`.
+///
+/// The keys are downloaded by pages, each followed by their values.
+fn download_state<Hash>(url: &str, at: Option<Hash>) -> Result<Storage>
+where
+	Hash: Clone + serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+	futures::executor::block_on(async {
+		let client: StateClient<Hash> = http::connect(url).compat().await
+			.map_err(|e| format!("Failed to connect to {}: {:?}", url, e))?;
+		let child_client: ChildStateClient<Hash> = http::connect(url).compat().await
+			.map_err(|e| format!("Failed to connect to {}: {:?}", url, e))?;
+
+		// all the pages must be downloaded at the same block, while the best one may change.
+		let at = match at {
+			Some(at) => at,
+			None => client.query_storage_at(vec![StorageKey(well_known_keys::CODE.to_vec())], None)
+				.compat().await
+				.map_err(download_error)?
+				.pop()
+				.ok_or_else(|| "The node didn't return the best block".to_string())?
+				.block,
+		};
+
+		let mut top = download_top_trie(&client, &at).await?;
+		let child_infos = top.keys()
+			.filter(|key| key.starts_with(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX))
+			.map(|key| ChildInfo::new_default(&key[well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX.len()..]))
+			.collect::<Vec<_>>();
+		let mut children_default = HashMap::new();
+		for child_info in child_infos {
+			// the roots of the child tries are computed again from their content.
+			top.remove(&child_info.prefixed_storage_key().into_inner());
+			let data = download_child_trie(&child_client, &child_info, &at).await?;
+			children_default.insert(child_info.storage_key().to_vec(), StorageChild { data, child_info });
+		}
+
+		Ok(Storage { top, children_default })
+	})
+}
+
+/// Downloads the top trie of the state of the block `at`.
+async fn download_top_trie<Hash>(client: &StateClient<Hash>, at: &Hash) -> Result<BTreeMap<Vec<u8>, Vec<u8>>>
+where
+	Hash: Clone + serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+	let mut pairs = BTreeMap::new();
+	let mut start_key = None;
+	loop {
+		let keys = client.storage_keys_paged(None, DOWNLOAD_PAGE_SIZE, start_key, Some(at.clone()))
+			.compat().await
+			.map_err(download_error)?;
+		let is_last_page = keys.len() < DOWNLOAD_PAGE_SIZE as usize;
+		start_key = keys.last().cloned();
+		if keys.is_empty() {
+			break
+		}
+		let change_sets = client.query_storage_at(keys, Some(at.clone())).compat().await
+			.map_err(download_error)?;
+		for (key, value) in change_sets.into_iter().flat_map(|change_set| change_set.changes) {
+			if let Some(value) = value {
+				pairs.insert(key.0, value.0);
+			}
+		}
+		if is_last_page {
+			break
+		}
+	}
+	Ok(pairs)
+}
+
+/// Downloads the child trie `child_info` of the state of the block `at`.
+async fn download_child_trie<Hash>(
+	client: &ChildStateClient<Hash>,
+	child_info: &ChildInfo,
+	at: &Hash,
+) -> Result<BTreeMap<Vec<u8>, Vec<u8>>>
+where
+	Hash: Clone + serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static,
+{
+	let storage_key = child_info.prefixed_storage_key();
+	let mut pairs = BTreeMap::new();
+	let mut start_key = None;
+	loop {
+		let keys = client.storage_keys_paged(
+			storage_key.clone(),
+			None,
+			DOWNLOAD_PAGE_SIZE,
+			start_key,
+			Some(at.clone()),
+		)
+			.compat().await
+			.map_err(download_error)?;
+		let is_last_page = keys.len() < DOWNLOAD_PAGE_SIZE as usize;
+		start_key = keys.last().cloned();
+		if keys.is_empty() {
+			break
+		}
+		let values = client.storage_entries(storage_key.clone(), keys.clone(), Some(at.clone()))
+			.compat().await
+			.map_err(download_error)?;
+		for (key, value) in keys.into_iter().zip(values) {
+			if let Some(value) = value {
+				pairs.insert(key.0, value.0);
+			}
+		}
+		if is_last_page {
+			break
+		}
+	}
+	Ok(pairs)
+}
+
+fn download_error(e: jsonrpc_core_client::RpcError) -> sc_cli::Error {
+	format!("Failed to download the state: {:?}", e).into()
+}
+
+/// Executes the `on_runtime_upgrade` hooks of the runtime `wasm`, or of the runtime of the state,
+/// against `state`.
+fn try_runtime_upgrade<H, B, Exec>(state: &B, executor: &Exec, wasm: Option<&[u8]>) -> Result<UpgradeReport>
+where
+	H: sp_core::Hasher,
+	H::Out: Ord + 'static + codec::Codec,
+	B: StateBackend<H>,
+	Exec: sp_core::traits::CodeExecutor + Clone + 'static,
+{
+	let backend_code = BackendRuntimeCode::new(state);
+	let mut runtime_code = backend_code.runtime_code()?;
+	let wasm = wasm.map(|wasm| WrappedRuntimeCode(wasm.into()));
+	if let Some(wasm) = &wasm {
+		runtime_code.hash = blake2_256(&wasm.0).to_vec();
+		runtime_code.code_fetcher = wasm;
+	}
+
+	let mut changes = OverlayedChanges::default();
+	let mut offchain_changes = Default::default();
+	let result = StateMachine::<_, _, u32, _>::new(
+		state,
+		None,
+		&mut changes,
+		&mut offchain_changes,
+		executor,
+		"TryRuntime_on_runtime_upgrade",
+		&[],
+		Extensions::default(),
+		&runtime_code,
+		tasks::executor(),
+	)
+	// the native runtime is the one of the node, not the one being tested.
+	.execute(ExecutionStrategy::AlwaysWasm)
+	.map_err(|e| format!("Error executing the runtime upgrade: {:?}", e))?;
+	let (weight, max_weight) = <(u64, u64)>::decode(&mut &result[..])
+		.map_err(|e| format!("Failed to decode the result of the runtime upgrade: {:?}", e))?;
+
+	let read = |child_info: Option<&ChildInfo>, key: &[u8]| match child_info {
+		Some(child_info) => state.child_storage(child_info, key),
+		None => state.storage(key),
+	}.map_err(|e| format!("Failed to read the state: {:?}", e));
+
+	let mut diffs = Vec::new();
+	let child_infos = changes.child_infos().into_iter().cloned().collect::<Vec<_>>();
+	for child_info in std::iter::once(None).chain(child_infos.iter().map(Some)) {
+		for (key, value) in changes.changes(child_info) {
+			let diff = StorageDiff::new(read(child_info, key)?, value.value().cloned());
+			if let Some(diff) = diff {
+				diffs.push((child_info.map(|info| info.storage_key().to_vec()), key.clone(), diff));
+			}
+		}
+	}
+
+	Ok(UpgradeReport { weight, max_weight, diffs })
+}
+
+/// Change of a storage entry.
+#[derive(Debug, PartialEq)]
+enum StorageDiff {
+	Added(Vec<u8>),
+	Changed(Vec<u8>),
+	Removed,
+}
+
+impl StorageDiff {
+	/// Returns the change from the value `old` to `new`, `None` if the value is unchanged.
+	fn new(old: Option<Vec<u8>>, new: Option<Vec<u8>>) -> Option<Self> {
+		match (old, new) {
+			(None, Some(new)) => Some(StorageDiff::Added(new)),
+			(Some(old), Some(new)) if old != new => Some(StorageDiff::Changed(new)),
+			(Some(_), None) => Some(StorageDiff::Removed),
+			_ => None,
+		}
+	}
+}
+
+/// Results of the runtime upgrade.
+struct UpgradeReport {
+	/// Weight used by the `on_runtime_upgrade` hooks.
+	weight: u64,
+	/// Maximum weight of a block.
+	max_weight: u64,
+	/// The changes of the storage, each along with the storage key of its child trie, if any.
+	diffs: Vec<(Option<Vec<u8>>, Vec<u8>, StorageDiff)>,
+}
+
+impl UpgradeReport {
+	fn display(&self, show_values: bool) -> impl fmt::Display + '_ {
+		struct Display<'a>(&'a UpgradeReport, bool);
+
+		impl<'a> fmt::Display for Display<'a> {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				let Display(report, show_values) = self;
+				let ratio = report.weight as f64 / report.max_weight.max(1) as f64 * 100.0;
+				writeln!(
+					f,
+					"Weight used: {} ({:.2}% of the maximum block weight {})",
+					report.weight,
+					ratio,
+					report.max_weight,
+				)?;
+				writeln!(f, "Storage changes: {}", report.diffs.len())?;
+				for (child, key, diff) in &report.diffs {
+					let (sign, value) = match diff {
+						StorageDiff::Added(value) => ('+', Some(value)),
+						StorageDiff::Changed(value) => ('~', Some(value)),
+						StorageDiff::Removed => ('-', None),
+					};
+					write!(f, "{} ", sign)?;
+					if let Some(child) = child {
+						write!(f, "0x{} / ", HexDisplay::from(child))?;
+					}
+					write!(f, "0x{}", HexDisplay::from(key))?;
+					match value {
+						Some(value) if *show_values => writeln!(f, " = 0x{}", HexDisplay::from(value))?,
+						_ => writeln!(f)?,
+					}
+				}
+				Ok(())
+			}
+		}
+
+		Display(self, show_values)
+	}
+}
+
+impl CliConfiguration for TryRuntimeCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn storage_diffs_ignore_unchanged_values() {
+		assert_eq!(StorageDiff::new(None, Some(vec![1])), Some(StorageDiff::Added(vec![1])));
+		assert_eq!(StorageDiff::new(Some(vec![1]), Some(vec![2])), Some(StorageDiff::Changed(vec![2])));
+		assert_eq!(StorageDiff::new(Some(vec![1]), None), Some(StorageDiff::Removed));
+		assert_eq!(StorageDiff::new(Some(vec![1]), Some(vec![1])), None);
+		assert_eq!(StorageDiff::new(None, None), None);
+	}
+
+	#[test]
+	fn report_lists_the_changes() {
+		let report = UpgradeReport {
+			weight: 500,
+			max_weight: 1000,
+			diffs: vec![
+				(None, vec![1], StorageDiff::Added(vec![2])),
+				(Some(vec![3]), vec![4], StorageDiff::Removed),
+			],
+		};
+
+		assert_eq!(
+			report.display(true).to_string(),
+			"Weight used: 500 (50.00% of the maximum block weight 1000)\n\
+			Storage changes: 2\n\
+			+ 0x01 = 0x02\n\
+			- 0x03 / 0x04\n",
+		);
+		assert!(report.display(false).to_string().contains("+ 0x01\n"));
+	}
+}
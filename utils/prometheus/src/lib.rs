@@ -26,8 +26,8 @@ pub use prometheus::{
 		AtomicF64 as F64, AtomicI64 as I64, AtomicU64 as U64,
 	}
 };
-use prometheus::{Encoder, TextEncoder, core::Collector};
-use std::net::SocketAddr;
+use prometheus::{Encoder, TextEncoder, core::{Collector, Desc}, proto::MetricFamily};
+use std::{collections::HashMap, net::SocketAddr};
 
 #[cfg(not(target_os = "unknown"))]
 mod networking;
@@ -42,6 +42,40 @@ pub fn register<T: Clone + Collector + 'static>(metric: T, registry: &Registry)
 	Ok(metric)
 }
 
+/// Collector gathering the metrics of a registry into another one.
+struct RegistryCollector {
+	registry: Registry,
+	desc: Desc,
+}
+
+impl Collector for RegistryCollector {
+	fn desc(&self) -> Vec<&Desc> {
+		vec![&self.desc]
+	}
+
+	fn collect(&self) -> Vec<MetricFamily> {
+		self.registry.gather()
+	}
+}
+
+/// Registers the registry `child` in `parent`, so that the metrics of `child` are gathered along
+/// with the ones of `parent`, e.g. to serve the metrics of several chains on the same endpoint.
+///
+/// `name` tells apart the registries registered in `parent`. The metrics of the registries must be
+/// told apart by their prefix or labels, the ones with the same name are gathered together.
+pub fn register_registry(name: &str, child: Registry, parent: &Registry) -> Result<(), PrometheusError> {
+	let name = name.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.collect::<String>();
+	let desc = Desc::new(
+		format!("registry_{}", name),
+		format!("Metrics of the registry {}", name),
+		Vec::new(),
+		HashMap::new(),
+	)?;
+	parent.register(Box::new(RegistryCollector { registry: child, desc }))
+}
+
 // On WASM `init_prometheus` becomes a no-op.
 #[cfg(target_os = "unknown")]
 mod unknown_os {
@@ -145,3 +179,34 @@ mod known_os {
 		result
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chain_registry(chain: &str) -> Registry {
+		let labels = std::iter::once(("chain".to_string(), chain.to_string())).collect();
+		Registry::new_custom(Some("substrate".into()), Some(labels)).unwrap()
+	}
+
+	#[test]
+	fn registries_are_gathered_with_their_labels() {
+		let parent = Registry::new();
+		for (chain, value) in &[("relay", 1), ("para", 2)] {
+			let registry = chain_registry(chain);
+			let counter = register(Counter::<U64>::new("blocks", "Blocks").unwrap(), &registry).unwrap();
+			counter.inc_by(*value);
+			register_registry(chain, registry, &parent).unwrap();
+		}
+		assert!(register_registry("relay", chain_registry("relay"), &parent).is_err());
+
+		let families = parent.gather();
+		assert_eq!(families.len(), 1);
+		assert_eq!(families[0].get_name(), "substrate_blocks");
+		let mut values = families[0].get_metric().iter()
+			.map(|metric| (metric.get_label()[0].get_value().to_string(), metric.get_counter().get_value()))
+			.collect::<Vec<_>>();
+		values.sort_by(|a, b| a.0.cmp(&b.0));
+		assert_eq!(values, vec![("para".to_string(), 2.0), ("relay".to_string(), 1.0)]);
+	}
+}